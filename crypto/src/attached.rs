@@ -0,0 +1,29 @@
+//! Framing for attached signatures: `u32_be(signature_len) || signature ||
+//! message`.
+
+use crate::error::{CryptoError, CryptoResult};
+
+/// Prepend the length-prefixed `signature` to `message`.
+pub(crate) fn attach(signature: &[u8], message: &[u8]) -> Vec<u8> {
+    let mut blob = Vec::with_capacity(4 + signature.len() + message.len());
+    blob.extend_from_slice(&(signature.len() as u32).to_be_bytes());
+    blob.extend_from_slice(signature);
+    blob.extend_from_slice(message);
+    blob
+}
+
+/// Split a blob written by [`attach`] into its signature and message.
+pub(crate) fn detach(blob: &[u8]) -> CryptoResult<(&[u8], &[u8])> {
+    let truncated = || CryptoError::Verification("attached signature truncated".into());
+    let (len, rest) = blob.split_first_chunk::<4>().ok_or_else(truncated)?;
+    let len = u32::from_be_bytes(*len) as usize;
+    if rest.len() < len {
+        return Err(truncated());
+    }
+    Ok(rest.split_at(len))
+}
+
+/// Error for an attached signature that does not verify.
+pub(crate) fn mismatch() -> CryptoError {
+    CryptoError::Verification("attached signature does not match message".into())
+}
@@ -0,0 +1,46 @@
+//! Run a file of known-answer test vectors and print a pass/fail table.
+//!
+//! Usage: `run_kat <vectors.json>...`. Exits with status 1 if any vector
+//! fails.
+
+use quantun_crypto::test_vectors::run_all_vectors;
+use std::path::Path;
+use std::process::ExitCode;
+
+fn main() -> ExitCode {
+    let paths: Vec<String> = std::env::args().skip(1).collect();
+    if paths.is_empty() {
+        eprintln!("usage: run_kat <vectors.json>...");
+        return ExitCode::from(2);
+    }
+
+    let results: Vec<(String, Result<(), String>)> = paths
+        .iter()
+        .flat_map(|path| run_all_vectors(Path::new(path)))
+        .collect();
+    let width = results
+        .iter()
+        .map(|(name, _)| name.len())
+        .chain(["VECTOR".len()])
+        .max()
+        .unwrap_or_default();
+
+    println!("{:<width$}  RESULT", "VECTOR");
+    let mut failed = 0;
+    for (name, result) in &results {
+        match result {
+            Ok(()) => println!("{name:<width$}  pass"),
+            Err(e) => {
+                failed += 1;
+                println!("{name:<width$}  FAIL: {e}");
+            }
+        }
+    }
+    println!("\n{} passed, {failed} failed", results.len() - failed);
+
+    if failed == 0 {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    }
+}
@@ -0,0 +1,140 @@
+//! Hash commitments for binding protocol values before they are revealed.
+//!
+//! `commit(value, blinding) = SHA3-256("qsgw-commit-v1" || blinding || value)`.
+//! A party sends the [`Commitment`] first and the opening (value and
+//! blinding) later; the 32-byte random blinding keeps low-entropy values
+//! hidden until then.
+
+use crate::error::{CryptoError, CryptoResult};
+use serde::{Deserialize, Serialize};
+use sha3::{Digest, Sha3_256};
+use std::fmt;
+use subtle::ConstantTimeEq;
+use zeroize::Zeroize;
+
+const DOMAIN: &[u8] = b"qsgw-commit-v1";
+
+/// Commit to `value` under `blinding`.
+pub fn commit(value: &[u8], blinding: &[u8; 32]) -> [u8; 32] {
+    let mut hasher = Sha3_256::new();
+    hasher.update(DOMAIN);
+    hasher.update(blinding);
+    hasher.update(value);
+    hasher.finalize().into()
+}
+
+/// Whether `value` and `blinding` open `commitment`, compared in constant
+/// time.
+pub fn verify_commitment(commitment: &[u8; 32], value: &[u8], blinding: &[u8; 32]) -> bool {
+    commit(value, blinding).ct_eq(commitment).into()
+}
+
+/// The public half of a commitment, safe to send before the opening.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Commitment(pub [u8; 32]);
+
+impl Commitment {
+    /// Whether `value` and `blinding` open this commitment.
+    pub fn verify(&self, value: &[u8], blinding: &[u8; 32]) -> bool {
+        verify_commitment(&self.0, value, blinding)
+    }
+}
+
+/// A commitment together with its opening.
+///
+/// Only [`CommitmentPair::commitment`] is serializable; the value and
+/// blinding are zeroized on drop and must be revealed deliberately.
+pub struct CommitmentPair {
+    commitment: Commitment,
+    value: Vec<u8>,
+    blinding: [u8; 32],
+}
+
+impl CommitmentPair {
+    /// Commit to `value` under a fresh random blinding.
+    pub fn new(value: Vec<u8>) -> CryptoResult<Self> {
+        let mut blinding = [0u8; 32];
+        getrandom::fill(&mut blinding).map_err(|e| CryptoError::Rng(e.to_string()))?;
+        Ok(Self {
+            commitment: Commitment(commit(&value, &blinding)),
+            value,
+            blinding,
+        })
+    }
+
+    /// The commitment to send ahead of the opening.
+    pub fn commitment(&self) -> Commitment {
+        self.commitment
+    }
+
+    /// The committed value.
+    pub fn value(&self) -> &[u8] {
+        &self.value
+    }
+
+    /// The blinding to reveal alongside the value.
+    pub fn blinding(&self) -> &[u8; 32] {
+        &self.blinding
+    }
+}
+
+impl fmt::Debug for CommitmentPair {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CommitmentPair")
+            .field("commitment", &self.commitment)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Drop for CommitmentPair {
+    fn drop(&mut self) {
+        self.value.zeroize();
+        self.blinding.zeroize();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn commitment_opens_with_its_value_and_blinding() {
+        let pair = CommitmentPair::new(b"handshake nonce".to_vec()).unwrap();
+        let commitment = pair.commitment();
+        assert!(commitment.verify(pair.value(), pair.blinding()));
+        assert!(verify_commitment(
+            &commitment.0,
+            b"handshake nonce",
+            pair.blinding()
+        ));
+    }
+
+    #[test]
+    fn wrong_value_or_blinding_is_rejected() {
+        let pair = CommitmentPair::new(b"value".to_vec()).unwrap();
+        let commitment = pair.commitment();
+        assert!(!commitment.verify(b"other", pair.blinding()));
+
+        let mut blinding = *pair.blinding();
+        blinding[0] ^= 1;
+        assert!(!commitment.verify(b"value", &blinding));
+    }
+
+    #[test]
+    fn blinding_changes_the_commitment() {
+        assert_ne!(commit(b"v", &[1; 32]), commit(b"v", &[2; 32]));
+        let a = CommitmentPair::new(b"v".to_vec()).unwrap();
+        let b = CommitmentPair::new(b"v".to_vec()).unwrap();
+        assert_ne!(a.commitment(), b.commitment());
+    }
+
+    #[test]
+    fn only_the_commitment_serializes() {
+        let pair = CommitmentPair::new(b"secret".to_vec()).unwrap();
+        let json = serde_json::to_string(&pair.commitment()).unwrap();
+        let back: Commitment = serde_json::from_str(&json).unwrap();
+        assert_eq!(back, pair.commitment());
+        assert!(!format!("{pair:?}").contains("blinding"));
+    }
+}
@@ -0,0 +1,165 @@
+//! Domain-separated key derivation from a long-lived master secret.
+//!
+//! Sub-key seeds are derived as
+//! `HKDF-SHA384(master, salt = epoch.to_le_bytes(), info)` with
+//! `info = "qsgw-derive-" || algorithm || "/" || domain`, and fed to the
+//! deterministic `from_seed` constructors, so the same `(domain, epoch)`
+//! always yields the same key pair. The algorithm label keeps the ML-KEM
+//! and ML-DSA seeds for one domain independent.
+//!
+//! [`derive_session_key`] turns a KEM shared secret into a symmetric session
+//! key the same way, with the exchange's context as HKDF info.
+
+use crate::error::{CryptoError, CryptoResult};
+use crate::mldsa::MlDsaKeyPair;
+use crate::mlkem::MlKemKeyPair;
+use crate::secure::SecureBytes;
+use hkdf::Hkdf;
+use quantun_types::{MlDsaVariant, MlKemVariant};
+use sha2::Sha384;
+use zeroize::Zeroize;
+
+/// Minimum length of master secret material, in bytes.
+pub const MIN_MASTER_SECRET_LEN: usize = 32;
+
+/// HKDF info labels for each derived algorithm; none contains `/`.
+const MLKEM_LABEL: &str = "ml-kem-768";
+const MLDSA_LABEL: &str = "ml-dsa-65";
+
+/// Length of keys produced by [`derive_session_key`], in bytes.
+pub const SESSION_KEY_LEN: usize = 32;
+
+/// Derive a session key from a KEM shared secret as
+/// `HKDF-SHA384(shared_secret, info = "qsgw-session-" || context)`.
+///
+/// Both ends must pass the same `context`, e.g. the KEM ciphertext, which
+/// binds the key to a single exchange.
+pub fn derive_session_key(shared_secret: &[u8], context: &[u8]) -> SecureBytes {
+    let mut info = b"qsgw-session-".to_vec();
+    info.extend_from_slice(context);
+    let mut key = vec![0u8; SESSION_KEY_LEN];
+    Hkdf::<Sha384>::new(None, shared_secret)
+        .expand(&info, &mut key)
+        .expect("32 bytes is a valid HKDF-SHA384 output length");
+    SecureBytes::new(key)
+}
+
+/// Master secret from which per-purpose, per-epoch key pairs are derived.
+#[derive(Debug)]
+pub struct MasterSecret {
+    material: SecureBytes,
+}
+
+impl MasterSecret {
+    /// Wrap master secret material, which must be at least 32 bytes.
+    pub fn new(material: SecureBytes) -> CryptoResult<Self> {
+        if material.len() < MIN_MASTER_SECRET_LEN {
+            return Err(CryptoError::InvalidKeyMaterial(format!(
+                "master secret must be at least {MIN_MASTER_SECRET_LEN} bytes, got {}",
+                material.len()
+            )));
+        }
+        Ok(Self { material })
+    }
+
+    /// Derive the ML-KEM-768 key pair for `domain` at `epoch`.
+    pub fn derive_mlkem_keypair(&self, domain: &str, epoch: u64) -> CryptoResult<MlKemKeyPair> {
+        let mut seed = [0u8; 64];
+        self.expand(MLKEM_LABEL, domain, epoch, &mut seed)?;
+        let kp = MlKemKeyPair::from_seed(MlKemVariant::MlKem768, &seed);
+        seed.zeroize();
+        kp
+    }
+
+    /// Derive the ML-DSA-65 key pair for `domain` at `epoch`.
+    pub fn derive_mldsa_keypair(&self, domain: &str, epoch: u64) -> CryptoResult<MlDsaKeyPair> {
+        let mut seed = [0u8; 32];
+        self.expand(MLDSA_LABEL, domain, epoch, &mut seed)?;
+        let kp = MlDsaKeyPair::from_seed(MlDsaVariant::MlDsa65, &seed);
+        seed.zeroize();
+        Ok(kp)
+    }
+
+    fn expand(
+        &self,
+        algorithm: &str,
+        domain: &str,
+        epoch: u64,
+        out: &mut [u8],
+    ) -> CryptoResult<()> {
+        let hk = Hkdf::<Sha384>::new(Some(&epoch.to_le_bytes()), self.material.as_bytes());
+        let info = format!("qsgw-derive-{algorithm}/{domain}");
+        hk.expand(info.as_bytes(), out).map_err(|e| CryptoError::KeyGeneration {
+            algorithm: "HKDF-SHA384".into(),
+            reason: e.to_string(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn master() -> MasterSecret {
+        MasterSecret::new(SecureBytes::new(vec![0x42; 32])).unwrap()
+    }
+
+    #[test]
+    fn short_material_is_rejected() {
+        assert!(MasterSecret::new(SecureBytes::new(vec![0; 31])).is_err());
+    }
+
+    #[test]
+    fn derivation_is_deterministic() {
+        let m = master();
+        let a = m.derive_mlkem_keypair("kem", 1).unwrap();
+        let b = m.derive_mlkem_keypair("kem", 1).unwrap();
+        assert_eq!(a.public_key, b.public_key);
+    }
+
+    #[test]
+    fn epochs_produce_different_keys() {
+        let m = master();
+        let e1 = m.derive_mlkem_keypair("kem", 1).unwrap();
+        let e2 = m.derive_mlkem_keypair("kem", 2).unwrap();
+        assert_ne!(e1.public_key, e2.public_key);
+    }
+
+    #[test]
+    fn domains_produce_different_keys() {
+        let m = master();
+        let kem = m.derive_mlkem_keypair("kem", 1).unwrap();
+        let sign = m.derive_mlkem_keypair("sign", 1).unwrap();
+        assert_ne!(kem.public_key, sign.public_key);
+
+        let kem_sig = m.derive_mldsa_keypair("kem", 1).unwrap();
+        let sign_sig = m.derive_mldsa_keypair("sign", 1).unwrap();
+        assert_ne!(kem_sig.public_key, sign_sig.public_key);
+    }
+
+    #[test]
+    fn algorithms_get_independent_seeds() {
+        let m = master();
+        let mut kem_seed = [0u8; 64];
+        let mut dsa_seed = [0u8; 32];
+        m.expand(MLKEM_LABEL, "shared", 1, &mut kem_seed).unwrap();
+        m.expand(MLDSA_LABEL, "shared", 1, &mut dsa_seed).unwrap();
+        assert_ne!(kem_seed[..32], dsa_seed);
+    }
+
+    #[test]
+    fn session_key_is_bound_to_context() {
+        let a = derive_session_key(b"shared", b"ct-1");
+        assert_eq!(a.len(), SESSION_KEY_LEN);
+        assert_eq!(a.as_bytes(), derive_session_key(b"shared", b"ct-1").as_bytes());
+        assert_ne!(a.as_bytes(), derive_session_key(b"shared", b"ct-2").as_bytes());
+        assert_ne!(a.as_bytes(), derive_session_key(b"other", b"ct-1").as_bytes());
+    }
+
+    #[test]
+    fn derived_mldsa_keypair_signs() {
+        let kp = master().derive_mldsa_keypair("sign", 1).unwrap();
+        let sig = kp.sign(b"derived").unwrap();
+        assert!(kp.verify(b"derived", &sig).unwrap());
+    }
+}
@@ -0,0 +1,378 @@
+//! Encrypted message envelopes: hybrid KEM + AES-256-GCM.
+//!
+//! Sealing encapsulates against the recipient's hybrid public key, expands
+//! the shared secret with HKDF-SHA256 into a one-time AES-256-GCM key and
+//! nonce, and encrypts the plaintext under the caller's AAD. The envelope
+//! carries a SHA-256 of the AAD so a receiver notices a missing or swapped
+//! AAD before attempting decryption.
+
+use crate::error::{CryptoError, CryptoResult};
+use crate::hybrid::{HybridEncapsulated, HybridKemKeyPair};
+use crate::nonce::NONCE_LEN;
+use crate::rng::PqcRng;
+use crate::secure::SecureBytes;
+use aes_gcm::aead::consts::U12;
+use aes_gcm::aead::{AeadInPlace, KeyInit};
+use aes_gcm::{Aes256Gcm, Nonce, Tag};
+use hkdf::Hkdf;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use signature::rand_core::TryRng;
+use std::fmt;
+use zeroize::Zeroize;
+
+/// Wire format version written by [`SealedEnvelope::to_bytes`].
+const WIRE_VERSION: u8 = 1;
+/// X25519 ephemeral public key length.
+const CLASSICAL_LEN: usize = 32;
+const TAG_LEN: usize = 16;
+const AAD_HASH_LEN: usize = 32;
+/// HKDF labels for the envelope and reply ciphers.
+const ENVELOPE_LABEL: &[u8] = b"quantun-envelope-v1";
+const REPLY_LABEL: &[u8] = b"quantun-envelope-reply-v1";
+
+/// A sealed message and everything the recipient needs to open it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SealedEnvelope {
+    pub kem_output: HybridEncapsulated,
+    pub ciphertext: Vec<u8>,
+    pub tag: [u8; TAG_LEN],
+    pub aad_hash: [u8; AAD_HASH_LEN],
+}
+
+/// Seal and open [`SealedEnvelope`]s.
+pub struct Envelope;
+
+impl Envelope {
+    /// Encrypt `plaintext` to the holder of `recipient_public`'s secret key.
+    ///
+    /// Only the public components of `recipient_public` are used.
+    pub fn seal(
+        recipient_public: &HybridKemKeyPair,
+        plaintext: &[u8],
+        aad: &[u8],
+    ) -> CryptoResult<SealedEnvelope> {
+        Self::seal_with_reply_key(recipient_public, plaintext, aad).map(|(sealed, _)| sealed)
+    }
+
+    /// [`Envelope::seal`], also returning the key the recipient will use
+    /// to seal its reply.
+    pub fn seal_with_reply_key(
+        recipient_public: &HybridKemKeyPair,
+        plaintext: &[u8],
+        aad: &[u8],
+    ) -> CryptoResult<(SealedEnvelope, ReplyKey)> {
+        let mut kem_output = recipient_public.encapsulate()?;
+        let shared_secret = SecureBytes::new(std::mem::take(&mut kem_output.shared_secret));
+        let (cipher, nonce) = derive_cipher(shared_secret.as_bytes(), ENVELOPE_LABEL);
+
+        let mut ciphertext = plaintext.to_vec();
+        let tag = cipher
+            .encrypt_in_place_detached(&nonce, aad, &mut ciphertext)
+            .map_err(|_| CryptoError::Encapsulation("envelope encryption failed".into()))?;
+
+        let sealed = SealedEnvelope {
+            kem_output,
+            ciphertext,
+            tag: tag.into(),
+            aad_hash: Sha256::digest(aad).into(),
+        };
+        let reply_key = ReplyKey::from_shared_secret(shared_secret.as_bytes());
+        Ok((sealed, reply_key))
+    }
+
+    /// Decrypt `envelope` with the recipient's key pair.
+    ///
+    /// Fails if `aad` differs from the one used to seal, or if the envelope
+    /// was sealed to a different key or modified in transit.
+    pub fn open(
+        recipient_kp: &HybridKemKeyPair,
+        envelope: &SealedEnvelope,
+        aad: &[u8],
+    ) -> CryptoResult<Vec<u8>> {
+        check_aad(envelope, aad)?;
+        let shared_secret = SecureBytes::new(recipient_kp.decapsulate(
+            &envelope.kem_output.classical_public,
+            &envelope.kem_output.pqc_ciphertext,
+        )?);
+        Self::open_with_shared_secret(shared_secret.as_bytes(), envelope, aad)
+    }
+
+    /// Decrypt `envelope` given the shared secret decapsulated from its
+    /// [`SealedEnvelope::kem_ciphertext`], e.g. by a
+    /// [`KeyStore`](crate::KeyStore) that holds the recipient key.
+    pub fn open_with_shared_secret(
+        shared_secret: &[u8],
+        envelope: &SealedEnvelope,
+        aad: &[u8],
+    ) -> CryptoResult<Vec<u8>> {
+        check_aad(envelope, aad)?;
+        let (cipher, nonce) = derive_cipher(shared_secret, ENVELOPE_LABEL);
+        let mut plaintext = envelope.ciphertext.clone();
+        cipher
+            .decrypt_in_place_detached(&nonce, aad, &mut plaintext, Tag::from_slice(&envelope.tag))
+            .map_err(|_| CryptoError::Decryption("envelope authentication failed".into()))?;
+        Ok(plaintext)
+    }
+}
+
+/// Key for replies to a [`SealedEnvelope`], derived from the envelope's
+/// shared secret so only its sender and recipient hold it.
+///
+/// Each reply is sealed under a fresh random nonce, sent with it, so
+/// sealing more than one reply never reuses a nonce.
+pub struct ReplyKey(SecureBytes);
+
+impl ReplyKey {
+    /// The reply key for the envelope whose KEM produced `shared_secret`.
+    pub fn from_shared_secret(shared_secret: &[u8]) -> Self {
+        Self(SecureBytes::new(shared_secret.to_vec()))
+    }
+
+    /// Encrypt `plaintext` as `nonce || ciphertext || tag`.
+    pub fn seal(&self, plaintext: &[u8]) -> CryptoResult<Vec<u8>> {
+        let (cipher, _) = derive_cipher(self.0.as_bytes(), REPLY_LABEL);
+        let mut nonce = [0u8; NONCE_LEN];
+        let Ok(()) = PqcRng.try_fill_bytes(&mut nonce);
+        let mut ciphertext = plaintext.to_vec();
+        let tag = cipher
+            .encrypt_in_place_detached(Nonce::from_slice(&nonce), b"", &mut ciphertext)
+            .map_err(|_| CryptoError::Encapsulation("reply encryption failed".into()))?;
+        let mut sealed = Vec::with_capacity(NONCE_LEN + ciphertext.len() + TAG_LEN);
+        sealed.extend_from_slice(&nonce);
+        sealed.extend_from_slice(&ciphertext);
+        sealed.extend_from_slice(&tag);
+        Ok(sealed)
+    }
+
+    /// Decrypt a reply produced by [`ReplyKey::seal`].
+    pub fn open(&self, sealed: &[u8]) -> CryptoResult<Vec<u8>> {
+        if sealed.len() < NONCE_LEN + TAG_LEN {
+            return Err(CryptoError::Decryption("reply truncated".into()));
+        }
+        let (nonce, rest) = sealed.split_at(NONCE_LEN);
+        let (ciphertext, tag) = rest.split_at(rest.len() - TAG_LEN);
+        let (cipher, _) = derive_cipher(self.0.as_bytes(), REPLY_LABEL);
+        let mut plaintext = ciphertext.to_vec();
+        cipher
+            .decrypt_in_place_detached(
+                Nonce::from_slice(nonce),
+                b"",
+                &mut plaintext,
+                Tag::from_slice(tag),
+            )
+            .map_err(|_| CryptoError::Decryption("reply authentication failed".into()))?;
+        Ok(plaintext)
+    }
+}
+
+impl fmt::Debug for ReplyKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("ReplyKey([REDACTED])")
+    }
+}
+
+fn check_aad(envelope: &SealedEnvelope, aad: &[u8]) -> CryptoResult<()> {
+    let aad_hash: [u8; AAD_HASH_LEN] = Sha256::digest(aad).into();
+    if aad_hash != envelope.aad_hash {
+        return Err(CryptoError::Decryption(
+            "associated data does not match envelope".into(),
+        ));
+    }
+    Ok(())
+}
+
+impl SealedEnvelope {
+    /// Encode as `version || x25519_public || u32_be(pqc_len) || pqc_ciphertext
+    /// || aad_hash || tag || ciphertext`.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let kem = &self.kem_output;
+        let mut out = Vec::with_capacity(
+            1 + CLASSICAL_LEN
+                + 4
+                + kem.pqc_ciphertext.len()
+                + AAD_HASH_LEN
+                + TAG_LEN
+                + self.ciphertext.len(),
+        );
+        out.push(WIRE_VERSION);
+        out.extend_from_slice(&kem.classical_public);
+        out.extend_from_slice(&(kem.pqc_ciphertext.len() as u32).to_be_bytes());
+        out.extend_from_slice(&kem.pqc_ciphertext);
+        out.extend_from_slice(&self.aad_hash);
+        out.extend_from_slice(&self.tag);
+        out.extend_from_slice(&self.ciphertext);
+        out
+    }
+
+    /// The KEM ciphertext in [`KeyStore::decapsulate`](crate::KeyStore::decapsulate)'s
+    /// hybrid format: the X25519 ephemeral public key, then the ML-KEM
+    /// ciphertext.
+    pub fn kem_ciphertext(&self) -> Vec<u8> {
+        [
+            self.kem_output.classical_public.as_slice(),
+            &self.kem_output.pqc_ciphertext,
+        ]
+        .concat()
+    }
+
+    /// Decode the format written by [`SealedEnvelope::to_bytes`].
+    pub fn from_bytes(bytes: &[u8]) -> CryptoResult<Self> {
+        let mut reader = Reader(bytes);
+        let version = reader.take(1)?[0];
+        if version != WIRE_VERSION {
+            return Err(CryptoError::Serialization(format!(
+                "unsupported envelope version {version}"
+            )));
+        }
+        let classical_public = reader.take(CLASSICAL_LEN)?.to_vec();
+        let pqc_len = u32::from_be_bytes(reader.take(4)?.try_into().expect("4 bytes")) as usize;
+        let pqc_ciphertext = reader.take(pqc_len)?.to_vec();
+        let aad_hash = reader.take(AAD_HASH_LEN)?.try_into().expect("32 bytes");
+        let tag = reader.take(TAG_LEN)?.try_into().expect("16 bytes");
+
+        Ok(Self {
+            kem_output: HybridEncapsulated {
+                classical_public,
+                pqc_ciphertext,
+                shared_secret: Vec::new(),
+            },
+            ciphertext: reader.0.to_vec(),
+            tag,
+            aad_hash,
+        })
+    }
+}
+
+struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, n: usize) -> CryptoResult<&'a [u8]> {
+        if self.0.len() < n {
+            return Err(CryptoError::Serialization("envelope truncated".into()));
+        }
+        let (head, rest) = self.0.split_at(n);
+        self.0 = rest;
+        Ok(head)
+    }
+}
+
+/// Expand a KEM shared secret into a one-time AES-256-GCM key and nonce.
+///
+/// Every envelope has a fresh shared secret, so the derived nonce is never
+/// reused under the same key and label. Replies may be sealed more than
+/// once and use a random nonce instead.
+fn derive_cipher(shared_secret: &[u8], label: &[u8]) -> (Aes256Gcm, Nonce<U12>) {
+    let mut okm = [0u8; 44];
+    Hkdf::<Sha256>::new(None, shared_secret)
+        .expand(label, &mut okm)
+        .expect("44 bytes is a valid HKDF-SHA256 output length");
+    let cipher = Aes256Gcm::new_from_slice(&okm[..32]).expect("32-byte key");
+    let nonce = *Nonce::from_slice(&okm[32..]);
+    okm.zeroize();
+    (cipher, nonce)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn recipient() -> (HybridKemKeyPair, HybridKemKeyPair) {
+        let kp = HybridKemKeyPair::generate().unwrap();
+        let mut public = kp.clone();
+        public.classical_secret = None;
+        public.pqc_keypair.secret_key.clear();
+        (kp, public)
+    }
+
+    #[test]
+    fn round_trip() {
+        let (kp, public) = recipient();
+        let sealed = Envelope::seal(&public, b"attack at dawn", b"header").unwrap();
+        assert!(sealed.kem_output.shared_secret.is_empty());
+        assert_ne!(sealed.ciphertext, b"attack at dawn");
+        assert_eq!(
+            Envelope::open(&kp, &sealed, b"header").unwrap(),
+            b"attack at dawn"
+        );
+    }
+
+    #[test]
+    fn wire_format_round_trip() {
+        let (kp, public) = recipient();
+        let sealed = Envelope::seal(&public, b"payload", b"").unwrap();
+        let decoded = SealedEnvelope::from_bytes(&sealed.to_bytes()).unwrap();
+        assert_eq!(Envelope::open(&kp, &decoded, b"").unwrap(), b"payload");
+
+        let bytes = sealed.to_bytes();
+        assert!(SealedEnvelope::from_bytes(&bytes[..40]).is_err());
+        let mut wrong_version = bytes;
+        wrong_version[0] = 2;
+        assert!(SealedEnvelope::from_bytes(&wrong_version).is_err());
+    }
+
+    #[test]
+    fn wrong_recipient_fails() {
+        let (_, public) = recipient();
+        let (other, _) = recipient();
+        let sealed = Envelope::seal(&public, b"secret", b"").unwrap();
+        let err = Envelope::open(&other, &sealed, b"").unwrap_err();
+        assert!(matches!(err, CryptoError::Decryption(_)));
+    }
+
+    #[test]
+    fn tampered_ciphertext_fails() {
+        let (kp, public) = recipient();
+        let mut sealed = Envelope::seal(&public, b"secret", b"").unwrap();
+        sealed.ciphertext[0] ^= 1;
+        assert!(Envelope::open(&kp, &sealed, b"").is_err());
+    }
+
+    #[test]
+    fn mismatched_aad_fails() {
+        let (kp, public) = recipient();
+        let sealed = Envelope::seal(&public, b"secret", b"route=/a").unwrap();
+        assert!(Envelope::open(&kp, &sealed, b"route=/b").is_err());
+        assert!(Envelope::open(&kp, &sealed, b"").is_err());
+    }
+
+    #[test]
+    fn open_via_keystore_and_reply() {
+        use crate::KeyStore;
+        use quantun_types::{Algorithm, HybridVariant, KeyUsage};
+
+        let keystore = KeyStore::new();
+        let handle = keystore
+            .create(
+                Algorithm::Hybrid(HybridVariant::X25519MlKem768),
+                [KeyUsage::KeyAgreement],
+            )
+            .unwrap();
+        let public =
+            HybridKemKeyPair::from_public_key(&keystore.get_public(&handle).unwrap()).unwrap();
+        assert!(HybridKemKeyPair::from_public_key(&[0u8; 32]).is_err());
+        let (sealed, client_reply) =
+            Envelope::seal_with_reply_key(&public, b"request", b"POST /a").unwrap();
+
+        let shared_secret = keystore
+            .decapsulate(&handle, &sealed.kem_ciphertext())
+            .unwrap();
+        assert_eq!(
+            Envelope::open_with_shared_secret(&shared_secret, &sealed, b"POST /a").unwrap(),
+            b"request"
+        );
+        assert!(Envelope::open_with_shared_secret(&shared_secret, &sealed, b"POST /b").is_err());
+
+        let server_reply = ReplyKey::from_shared_secret(&shared_secret);
+        let reply = server_reply.seal(b"response").unwrap();
+        assert_eq!(client_reply.open(&reply).unwrap(), b"response");
+        // Each reply gets its own nonce.
+        let again = server_reply.seal(b"response").unwrap();
+        assert_ne!(again[..NONCE_LEN], reply[..NONCE_LEN]);
+        assert_eq!(client_reply.open(&again).unwrap(), b"response");
+        let mut tampered = reply;
+        tampered[0] ^= 1;
+        assert!(client_reply.open(&tampered).is_err());
+        assert!(client_reply.open(&[0u8; NONCE_LEN + TAG_LEN - 1]).is_err());
+    }
+}
@@ -1,4 +1,4 @@
-use quantun_types::ErrorCode;
+use quantun_types::{ErrorCode, InvalidLength};
 use thiserror::Error;
 
 /// Errors produced by cryptographic operations.
@@ -13,6 +13,9 @@ pub enum CryptoError {
     #[error("decapsulation failed: {0}")]
     Decapsulation(String),
 
+    #[error("decryption failed: {0}")]
+    Decryption(String),
+
     #[error("signing failed: {0}")]
     Signing(String),
 
@@ -30,6 +33,30 @@ pub enum CryptoError {
 
     #[error("rng error: {0}")]
     Rng(String),
+
+    #[error("key not found: {0}")]
+    KeyNotFound(String),
+
+    #[error("key {key_id} cannot be used: {code}")]
+    KeyUnusable { key_id: String, code: ErrorCode },
+
+    /// A remote signing backend could not be reached or timed out. Safe to
+    /// retry.
+    #[error("signer unavailable: {0}")]
+    SignerUnavailable(String),
+
+    /// Every nonce for an AES-GCM key has been used; the key must be
+    /// replaced.
+    #[error("nonce space exhausted")]
+    NonceExhausted,
+
+    #[error("key wrap failed: {0}")]
+    KeyWrap(String),
+
+    /// The wrapped key is malformed, was wrapped under another key, or was
+    /// altered.
+    #[error("key unwrap failed: {0}")]
+    KeyUnwrap(String),
 }
 
 impl CryptoError {
@@ -39,14 +66,27 @@ impl CryptoError {
             CryptoError::KeyGeneration { .. } => ErrorCode::KeyGenerationFailed,
             CryptoError::Encapsulation(_) => ErrorCode::EncapsulationFailed,
             CryptoError::Decapsulation(_) => ErrorCode::DecapsulationFailed,
+            CryptoError::Decryption(_) => ErrorCode::VerificationFailed,
             CryptoError::Signing(_) => ErrorCode::SigningFailed,
             CryptoError::Verification(_) => ErrorCode::VerificationFailed,
             CryptoError::InvalidKeyMaterial(_) => ErrorCode::InvalidKeyMaterial,
             CryptoError::UnsupportedAlgorithm(_) => ErrorCode::UnsupportedAlgorithm,
             CryptoError::Serialization(_) => ErrorCode::Internal,
             CryptoError::Rng(_) => ErrorCode::Internal,
+            CryptoError::KeyNotFound(_) => ErrorCode::NotFound,
+            CryptoError::KeyUnusable { code, .. } => *code,
+            CryptoError::SignerUnavailable(_) => ErrorCode::ServiceUnavailable,
+            CryptoError::NonceExhausted => ErrorCode::KeyExhausted,
+            CryptoError::KeyWrap(_) => ErrorCode::Internal,
+            CryptoError::KeyUnwrap(_) => ErrorCode::VerificationFailed,
         }
     }
 }
 
+impl From<InvalidLength> for CryptoError {
+    fn from(e: InvalidLength) -> Self {
+        CryptoError::InvalidKeyMaterial(e.to_string())
+    }
+}
+
 pub type CryptoResult<T> = Result<T, CryptoError>;
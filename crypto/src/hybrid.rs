@@ -1,11 +1,20 @@
 use crate::error::{CryptoError, CryptoResult};
+#[cfg(feature = "mldsa")]
+use crate::mldsa::{MlDsaKeyPair, MlDsaSignature, MlDsaVerifier};
 use crate::mlkem::MlKemKeyPair;
+use hkdf::Hkdf;
 use quantun_types::{HybridVariant, MlKemVariant};
 use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
-use x25519_dalek::{PublicKey, StaticSecret};
+use x25519_dalek::{PublicKey, SharedSecret, StaticSecret};
 use zeroize::Zeroize;
 
+/// Magic header ("QG") that starts the binary encodings in this module.
+pub const WIRE_MAGIC: [u8; 2] = [0x51, 0x47];
+
+/// Version of the binary encodings, following [`WIRE_MAGIC`].
+pub const WIRE_VERSION: u8 = 1;
+
 /// Hybrid KEM key pair combining X25519 with ML-KEM-768.
 ///
 /// Provides security against both classical and quantum adversaries by
@@ -50,50 +59,136 @@ impl Drop for HybridEncapsulated {
     }
 }
 
+/// A hybrid encapsulation signed by the sender's ML-DSA identity key.
+///
+/// `encapsulated.shared_secret` is bound to the sender's public key
+/// fingerprint, so it only matches on the recipient side if both agree on
+/// who sent it.
+#[cfg(feature = "mldsa")]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuthenticatedHybridEncapsulated {
+    pub encapsulated: HybridEncapsulated,
+    /// ML-DSA signature over the recipient's fingerprint and both
+    /// ciphertext components.
+    pub signature: Vec<u8>,
+}
+
 impl HybridKemKeyPair {
     /// Generate a new X25519 + ML-KEM-768 hybrid key pair.
     pub fn generate() -> CryptoResult<Self> {
-        // Generate X25519 key pair using OS CSPRNG (getrandom)
-        let mut key_bytes = [0u8; 32];
-        getrandom::fill(&mut key_bytes)
-            .expect("OS entropy source unavailable — cannot proceed safely");
-        let classical_secret = StaticSecret::from(key_bytes);
-        let classical_public = PublicKey::from(&classical_secret);
-
         // Generate ML-KEM-768 key pair using real FIPS 203 (uses OS RNG internally)
         let pqc_keypair = MlKemKeyPair::generate(MlKemVariant::MlKem768)?;
+        Ok(Self::with_classical_secret(&mut random_x25519_secret(), pqc_keypair))
+    }
+
+    /// Rebuild a key pair from its 32-byte X25519 secret and 64-byte
+    /// ML-KEM-768 seed.
+    pub fn from_secret_parts(classical_secret: &[u8], pqc_seed: &[u8; 64]) -> CryptoResult<Self> {
+        let mut key_bytes: [u8; 32] = classical_secret.try_into().map_err(|_| {
+            CryptoError::InvalidKeyMaterial("X25519 secret must be 32 bytes".into())
+        })?;
+        let pqc_keypair = MlKemKeyPair::from_seed(MlKemVariant::MlKem768, pqc_seed)?;
+        Ok(Self::with_classical_secret(&mut key_bytes, pqc_keypair))
+    }
 
+    /// Complete a key pair from an X25519 secret, zeroizing `key_bytes`.
+    fn with_classical_secret(key_bytes: &mut [u8; 32], pqc_keypair: MlKemKeyPair) -> Self {
+        let classical_public = PublicKey::from(&StaticSecret::from(*key_bytes));
         let result = Self {
             variant: HybridVariant::X25519MlKem768,
             classical_public: classical_public.as_bytes().to_vec(),
             classical_secret: Some(key_bytes.to_vec()),
             pqc_keypair,
         };
-
-        // Zeroize the stack copy of key_bytes
         key_bytes.zeroize();
+        result
+    }
 
-        Ok(result)
+    /// A public-only key pair from the encoding returned by
+    /// [`KeyPair::public_key`](crate::KeyPair::public_key): the 32-byte
+    /// X25519 public key followed by the ML-KEM-768 encapsulation key.
+    ///
+    /// It can be encapsulated to but cannot decapsulate.
+    pub fn from_public_key(public_key: &[u8]) -> CryptoResult<Self> {
+        let expected = 32 + MlKemVariant::MlKem768.key_sizes().0;
+        if public_key.len() != expected {
+            return Err(CryptoError::InvalidKeyMaterial(format!(
+                "hybrid public key must be {expected} bytes, got {}",
+                public_key.len()
+            )));
+        }
+        let (classical, pqc) = public_key.split_at(32);
+        Ok(Self {
+            variant: HybridVariant::X25519MlKem768,
+            classical_public: classical.to_vec(),
+            classical_secret: None,
+            pqc_keypair: MlKemKeyPair {
+                variant: MlKemVariant::MlKem768,
+                public_key: pqc.to_vec(),
+                secret_key: Vec::new(),
+            },
+        })
+    }
+
+    /// The public components as `magic || version || len:2 || X25519 key
+    /// || len:2 || ML-KEM-768 key`, lengths big-endian. See [`WIRE_MAGIC`].
+    pub fn public_to_bytes(&self) -> Vec<u8> {
+        encode_wire(&[&self.classical_public, &self.pqc_keypair.public_key])
+    }
+
+    /// A public-only key pair from [`HybridKemKeyPair::public_to_bytes`].
+    pub fn public_from_bytes(bytes: &[u8]) -> CryptoResult<Self> {
+        let [classical, pqc] = decode_wire(bytes, "hybrid public key")?;
+        check_len("X25519 public key", classical, 32)?;
+        check_len(
+            "ML-KEM-768 public key",
+            pqc,
+            MlKemVariant::MlKem768.key_sizes().0,
+        )?;
+        Self::from_public_key(&[classical, pqc].concat())
+    }
+
+    /// Generate a hybrid key pair, running the ML-KEM-768 keygen on a
+    /// blocking thread while the X25519 key pair is generated.
+    ///
+    /// Useful when provisioning keys in bulk from async code; the result is
+    /// equivalent to [`HybridKemKeyPair::generate`].
+    pub async fn generate_parallel() -> CryptoResult<Self> {
+        let pqc_task =
+            tokio::task::spawn_blocking(|| MlKemKeyPair::generate(MlKemVariant::MlKem768));
+
+        let mut key_bytes = random_x25519_secret();
+
+        let pqc_keypair = pqc_task.await.map_err(|e| CryptoError::KeyGeneration {
+            algorithm: HybridVariant::X25519MlKem768.to_string(),
+            reason: format!("ML-KEM keygen task failed: {e}"),
+        })??;
+
+        Ok(Self::with_classical_secret(&mut key_bytes, pqc_keypair))
+    }
+
+    /// Rotate away from `old_kp` for forward secrecy.
+    ///
+    /// Generates a fresh key pair and encapsulates against `old_kp`'s public
+    /// components. The returned encapsulation carries the ciphertext to send
+    /// to the holder of `old_kp`, who recovers the same transition secret with
+    /// [`HybridKemKeyPair::decapsulate`]. Both sides then feed it to
+    /// [`derive_session_key`] and must switch to the new keys atomically:
+    /// traffic protected under the old session key must not be accepted once
+    /// either side has swapped.
+    pub fn rekey(old_kp: &HybridKemKeyPair) -> CryptoResult<(HybridKemKeyPair, HybridEncapsulated)> {
+        let new_kp = Self::generate()?;
+        let transition = old_kp.encapsulate()?;
+        Ok((new_kp, transition))
     }
 
     /// Encapsulate against this key pair's public components.
     pub fn encapsulate(&self) -> CryptoResult<HybridEncapsulated> {
         // X25519 ephemeral key exchange using OS CSPRNG
-        let mut ephemeral_bytes = [0u8; 32];
-        getrandom::fill(&mut ephemeral_bytes)
-            .expect("OS entropy source unavailable — cannot proceed safely");
-        let ephemeral_secret = StaticSecret::from(ephemeral_bytes);
+        let ephemeral_secret = StaticSecret::from(random_x25519_secret());
         let ephemeral_public = PublicKey::from(&ephemeral_secret);
-
-        // Zeroize ephemeral bytes on stack
-        ephemeral_bytes.zeroize();
-
-        let recipient_public = PublicKey::from(
-            <[u8; 32]>::try_from(self.classical_public.as_slice()).map_err(|_| {
-                CryptoError::InvalidKeyMaterial("X25519 public key must be 32 bytes".into())
-            })?,
-        );
-        let classical_shared = ephemeral_secret.diffie_hellman(&recipient_public);
+        let classical_shared =
+            x25519_agree(ephemeral_secret, &self.classical_public, "X25519 public key")?;
 
         // Real ML-KEM-768 encapsulation (FIPS 203)
         let mut pqc_enc = self.pqc_keypair.encapsulate()?;
@@ -129,18 +224,12 @@ impl HybridKemKeyPair {
             CryptoError::InvalidKeyMaterial("X25519 secret must be 32 bytes".into())
         })?;
 
-        let classical_secret = StaticSecret::from(secret_array);
-
-        let ephemeral_public = PublicKey::from(
-            <[u8; 32]>::try_from(ephemeral_public_bytes).map_err(|_| {
-                CryptoError::InvalidKeyMaterial(
-                    "ephemeral public key must be 32 bytes".into(),
-                )
-            })?,
-        );
-
         // X25519 shared secret
-        let classical_shared = classical_secret.diffie_hellman(&ephemeral_public);
+        let classical_shared = x25519_agree(
+            StaticSecret::from(secret_array),
+            ephemeral_public_bytes,
+            "ephemeral public key",
+        )?;
 
         // Real ML-KEM-768 decapsulation (FIPS 203)
         let pqc_shared = self.pqc_keypair.decapsulate(pqc_ciphertext)?;
@@ -153,6 +242,246 @@ impl HybridKemKeyPair {
     }
 }
 
+impl HybridEncapsulated {
+    /// The ciphertext as `magic || version || len:2 || X25519 ephemeral
+    /// key || len:2 || ML-KEM-768 ciphertext`. The shared secret is not
+    /// included.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        encode_wire(&[&self.classical_public, &self.pqc_ciphertext])
+    }
+
+    /// A ciphertext from [`HybridEncapsulated::to_bytes`], with an empty
+    /// shared secret.
+    pub fn from_bytes(bytes: &[u8]) -> CryptoResult<Self> {
+        let [classical, pqc] = decode_wire(bytes, "hybrid ciphertext")?;
+        check_len("X25519 ephemeral key", classical, 32)?;
+        check_len(
+            "ML-KEM-768 ciphertext",
+            pqc,
+            MlKemVariant::MlKem768.ciphertext_size(),
+        )?;
+        Ok(Self {
+            classical_public: classical.to_vec(),
+            pqc_ciphertext: pqc.to_vec(),
+            shared_secret: Vec::new(),
+        })
+    }
+}
+
+#[cfg(feature = "mldsa")]
+impl AuthenticatedHybridEncapsulated {
+    /// As [`HybridEncapsulated::to_bytes`], followed by `len:2 ||
+    /// signature`.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        encode_wire(&[
+            &self.encapsulated.classical_public,
+            &self.encapsulated.pqc_ciphertext,
+            &self.signature,
+        ])
+    }
+
+    /// A signed ciphertext from [`AuthenticatedHybridEncapsulated::to_bytes`].
+    pub fn from_bytes(bytes: &[u8]) -> CryptoResult<Self> {
+        let [classical, pqc, signature] =
+            decode_wire(bytes, "authenticated hybrid ciphertext")?;
+        check_len("X25519 ephemeral key", classical, 32)?;
+        check_len(
+            "ML-KEM-768 ciphertext",
+            pqc,
+            MlKemVariant::MlKem768.ciphertext_size(),
+        )?;
+        if signature.is_empty() {
+            return Err(CryptoError::Serialization("signature is empty".into()));
+        }
+        Ok(Self {
+            encapsulated: HybridEncapsulated {
+                classical_public: classical.to_vec(),
+                pqc_ciphertext: pqc.to_vec(),
+                shared_secret: Vec::new(),
+            },
+            signature: signature.to_vec(),
+        })
+    }
+}
+
+#[cfg(feature = "mldsa")]
+impl HybridKemKeyPair {
+    /// Encapsulate to `recipient` and sign the ciphertext with
+    /// `sender_identity`, binding the shared secret to the sender.
+    ///
+    /// An active attacker who substitutes their own ciphertext cannot
+    /// produce a signature the recipient accepts for the expected sender.
+    pub fn encapsulate_authenticated(
+        recipient: &HybridKemKeyPair,
+        sender_identity: &MlDsaKeyPair,
+    ) -> CryptoResult<AuthenticatedHybridEncapsulated> {
+        let mut encapsulated = recipient.encapsulate()?;
+        let transcript = auth_transcript(recipient, &encapsulated);
+        let signature = sender_identity.sign(&transcript)?.signature;
+
+        let bound = bind_to_sender(&encapsulated.shared_secret, &sender_identity.public_key);
+        encapsulated.shared_secret.zeroize();
+        encapsulated.shared_secret = bound;
+
+        Ok(AuthenticatedHybridEncapsulated {
+            encapsulated,
+            signature,
+        })
+    }
+
+    /// Verify that `sender_public` signed `authenticated`, then decapsulate
+    /// and return the sender-bound shared secret.
+    ///
+    /// Fails with [`CryptoError::Verification`] before touching the secret
+    /// key if the signature does not verify.
+    pub fn decapsulate_authenticated(
+        &self,
+        sender_public: &MlDsaVerifier,
+        authenticated: &AuthenticatedHybridEncapsulated,
+    ) -> CryptoResult<Vec<u8>> {
+        let encapsulated = &authenticated.encapsulated;
+        let signature = MlDsaSignature {
+            signature: authenticated.signature.clone(),
+            variant: sender_public.variant,
+        };
+        if !sender_public.verify(&auth_transcript(self, encapsulated), &signature)? {
+            return Err(CryptoError::Verification(
+                "encapsulation not signed by the expected sender".into(),
+            ));
+        }
+
+        let mut shared_secret =
+            self.decapsulate(&encapsulated.classical_public, &encapsulated.pqc_ciphertext)?;
+        let bound = bind_to_sender(&shared_secret, &sender_public.public_key);
+        shared_secret.zeroize();
+        Ok(bound)
+    }
+}
+
+/// Bytes the sender signs: a domain separator, the recipient's public key
+/// fingerprint, and both ciphertext components (length-prefixed).
+#[cfg(feature = "mldsa")]
+fn auth_transcript(recipient: &HybridKemKeyPair, encapsulated: &HybridEncapsulated) -> Vec<u8> {
+    let mut transcript = b"quantun-auth-kem-v1".to_vec();
+    transcript.extend_from_slice(&fingerprint(&[
+        &recipient.classical_public,
+        &recipient.pqc_keypair.public_key,
+    ]));
+    for part in [&encapsulated.classical_public, &encapsulated.pqc_ciphertext] {
+        transcript.extend_from_slice(&(part.len() as u32).to_be_bytes());
+        transcript.extend_from_slice(part);
+    }
+    transcript
+}
+
+/// `HKDF-SHA256(shared_secret, info = "auth-kem" || SHA-256(sender_pk))`.
+#[cfg(feature = "mldsa")]
+fn bind_to_sender(shared_secret: &[u8], sender_public_key: &[u8]) -> Vec<u8> {
+    let mut info = b"auth-kem".to_vec();
+    info.extend_from_slice(&fingerprint(&[sender_public_key]));
+
+    let mut key = vec![0u8; 32];
+    Hkdf::<Sha256>::new(None, shared_secret)
+        .expand(&info, &mut key)
+        .expect("32 bytes is a valid HKDF-SHA256 output length");
+    key
+}
+
+#[cfg(feature = "mldsa")]
+fn fingerprint(parts: &[&[u8]]) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    for part in parts {
+        hasher.update(part);
+    }
+    hasher.finalize().into()
+}
+
+/// Derive the 32-byte session key that follows a [`HybridKemKeyPair::rekey`].
+///
+/// Binds the transition secret to the new key pair's public components, so
+/// both parties must agree on which key pair they rotated to.
+pub fn derive_session_key(transition_secret: &[u8], new_kp: &HybridKemKeyPair) -> Vec<u8> {
+    let mut info = b"quantun-hybrid-rekey-v1".to_vec();
+    info.extend_from_slice(&new_kp.classical_public);
+    info.extend_from_slice(&new_kp.pqc_keypair.public_key);
+
+    let mut key = vec![0u8; 32];
+    Hkdf::<Sha256>::new(None, transition_secret)
+        .expand(&info, &mut key)
+        .expect("32 bytes is a valid HKDF-SHA256 output length");
+    key
+}
+
+/// `WIRE_MAGIC || WIRE_VERSION` followed by each field with a 2-byte
+/// big-endian length.
+fn encode_wire(fields: &[&[u8]]) -> Vec<u8> {
+    let mut out = WIRE_MAGIC.to_vec();
+    out.push(WIRE_VERSION);
+    for field in fields {
+        let len = u16::try_from(field.len()).expect("hybrid components are under 64 KiB");
+        out.extend_from_slice(&len.to_be_bytes());
+        out.extend_from_slice(field);
+    }
+    out
+}
+
+/// The `N` fields of an [`encode_wire`] encoding of a `what`.
+fn decode_wire<'a, const N: usize>(bytes: &'a [u8], what: &str) -> CryptoResult<[&'a [u8]; N]> {
+    let malformed = |reason: &str| CryptoError::Serialization(format!("{what}: {reason}"));
+    let rest = bytes
+        .strip_prefix(&WIRE_MAGIC)
+        .ok_or_else(|| malformed("bad magic header"))?;
+    let (&version, mut rest) = rest.split_first().ok_or_else(|| malformed("truncated"))?;
+    if version != WIRE_VERSION {
+        return Err(malformed(&format!("unsupported version {version}")));
+    }
+    let mut fields = [&[][..]; N];
+    for field in &mut fields {
+        let (len, tail) = rest
+            .split_first_chunk::<2>()
+            .ok_or_else(|| malformed("truncated"))?;
+        let len = u16::from_be_bytes(*len) as usize;
+        if tail.len() < len {
+            return Err(malformed("truncated"));
+        }
+        (*field, rest) = tail.split_at(len);
+    }
+    if !rest.is_empty() {
+        return Err(malformed("trailing bytes"));
+    }
+    Ok(fields)
+}
+
+fn check_len(what: &str, field: &[u8], expected: usize) -> CryptoResult<()> {
+    if field.len() != expected {
+        return Err(CryptoError::Serialization(format!(
+            "{what} must be {expected} bytes, got {}",
+            field.len()
+        )));
+    }
+    Ok(())
+}
+
+/// A fresh X25519 secret from the OS CSPRNG.
+fn random_x25519_secret() -> [u8; 32] {
+    let mut key_bytes = [0u8; 32];
+    getrandom::fill(&mut key_bytes)
+        .expect("OS entropy source unavailable — cannot proceed safely");
+    key_bytes
+}
+
+/// X25519 agreement between `secret` and the 32-byte `peer_public` key,
+/// which is named `what` in the error if it has the wrong length.
+fn x25519_agree(
+    secret: StaticSecret,
+    peer_public: &[u8],
+    what: &str,
+) -> CryptoResult<SharedSecret> {
+    let peer_public = <[u8; 32]>::try_from(peer_public)
+        .map_err(|_| CryptoError::InvalidKeyMaterial(format!("{what} must be 32 bytes")))?;
+    Ok(secret.diffie_hellman(&PublicKey::from(peer_public)))
+}
+
 /// KDF: combine classical and PQC shared secrets.
 ///
 /// Uses SHA-256 with a domain separator to derive the final shared secret.
@@ -190,6 +519,18 @@ mod tests {
         assert_eq!(shared.len(), 32); // SHA-256 output
     }
 
+    #[tokio::test]
+    async fn hybrid_generate_parallel_round_trip() {
+        let kp = HybridKemKeyPair::generate_parallel().await.unwrap();
+        assert_eq!(kp.classical_public.len(), 32);
+        assert_eq!(kp.variant, HybridVariant::X25519MlKem768);
+        let enc = kp.encapsulate().unwrap();
+        let shared = kp
+            .decapsulate(&enc.classical_public, &enc.pqc_ciphertext)
+            .unwrap();
+        assert_eq!(enc.shared_secret, shared);
+    }
+
     #[test]
     fn different_encapsulations_produce_different_secrets() {
         let kp = HybridKemKeyPair::generate().unwrap();
@@ -199,6 +540,30 @@ mod tests {
         assert_ne!(enc1.shared_secret, enc2.shared_secret);
     }
 
+    #[test]
+    fn two_party_rekey_agrees_on_session_key() {
+        // Side B owns the long-lived key pair; side A initiates the rotation.
+        let b_old = HybridKemKeyPair::generate().unwrap();
+        let mut b_old_public = b_old.clone();
+        b_old_public.classical_secret = None;
+        b_old_public.pqc_keypair.secret_key.clear();
+
+        let (new_kp, transition) = HybridKemKeyPair::rekey(&b_old_public).unwrap();
+        let a_key = derive_session_key(&transition.shared_secret, &new_kp);
+
+        let b_transition = b_old
+            .decapsulate(&transition.classical_public, &transition.pqc_ciphertext)
+            .unwrap();
+        let b_key = derive_session_key(&b_transition, &new_kp);
+
+        assert_eq!(a_key, b_key);
+        assert_eq!(a_key.len(), 32);
+        assert_ne!(new_kp.classical_public, b_old.classical_public);
+
+        let other = HybridKemKeyPair::generate().unwrap();
+        assert_ne!(derive_session_key(&b_transition, &other), b_key);
+    }
+
     #[test]
     fn missing_secret_key_errors() {
         let mut kp = HybridKemKeyPair::generate().unwrap();
@@ -208,4 +573,155 @@ mod tests {
             .decapsulate(&enc.classical_public, &enc.pqc_ciphertext)
             .is_err());
     }
+
+    #[test]
+    fn wire_format_round_trips() {
+        let kp = HybridKemKeyPair::generate().unwrap();
+        let bytes = kp.public_to_bytes();
+        assert_eq!(bytes[..3], [0x51, 0x47, WIRE_VERSION]);
+        assert_eq!(bytes.len(), 3 + 2 + 32 + 2 + 1184);
+        let public = HybridKemKeyPair::public_from_bytes(&bytes).unwrap();
+        assert_eq!(public.classical_public, kp.classical_public);
+        assert_eq!(public.pqc_keypair.public_key, kp.pqc_keypair.public_key);
+        assert!(public.classical_secret.is_none());
+
+        let enc = public.encapsulate().unwrap();
+        let decoded = HybridEncapsulated::from_bytes(&enc.to_bytes()).unwrap();
+        assert_eq!(decoded.classical_public, enc.classical_public);
+        assert_eq!(decoded.pqc_ciphertext, enc.pqc_ciphertext);
+        assert!(decoded.shared_secret.is_empty());
+        let shared = kp
+            .decapsulate(&decoded.classical_public, &decoded.pqc_ciphertext)
+            .unwrap();
+        assert_eq!(shared, enc.shared_secret);
+    }
+
+    #[test]
+    fn wire_format_rejects_malformed_input() {
+        let kp = HybridKemKeyPair::generate().unwrap();
+        let bytes = kp.public_to_bytes();
+        let enc = kp.encapsulate().unwrap().to_bytes();
+
+        let mut wrong_magic = bytes.clone();
+        wrong_magic[0] = 0x00;
+        let mut wrong_version = bytes.clone();
+        wrong_version[2] = WIRE_VERSION + 1;
+        let mut trailing = bytes.clone();
+        trailing.push(0);
+        for bad in [
+            wrong_magic,
+            wrong_version,
+            trailing,
+            bytes[..bytes.len() - 1].to_vec(),
+            bytes[..2].to_vec(),
+            // A ciphertext is not a public key.
+            enc.clone(),
+        ] {
+            assert!(matches!(
+                HybridKemKeyPair::public_from_bytes(&bad),
+                Err(CryptoError::Serialization(_))
+            ));
+        }
+
+        let mut wrong_magic = enc.clone();
+        wrong_magic[1] = b'X';
+        assert!(matches!(
+            HybridEncapsulated::from_bytes(&wrong_magic),
+            Err(CryptoError::Serialization(_))
+        ));
+        assert!(matches!(
+            HybridEncapsulated::from_bytes(&bytes),
+            Err(CryptoError::Serialization(_))
+        ));
+    }
+
+    #[cfg(feature = "mldsa")]
+    mod authenticated {
+        use super::*;
+        use quantun_types::MlDsaVariant;
+
+        fn identity() -> MlDsaKeyPair {
+            MlDsaKeyPair::generate(MlDsaVariant::MlDsa44).unwrap()
+        }
+
+        #[test]
+        fn round_trip_binds_sender() {
+            let recipient = HybridKemKeyPair::generate().unwrap();
+            let sender = identity();
+            let auth = HybridKemKeyPair::encapsulate_authenticated(&recipient, &sender).unwrap();
+
+            let secret = recipient
+                .decapsulate_authenticated(&sender.to_verifier(), &auth)
+                .unwrap();
+            assert_eq!(secret, auth.encapsulated.shared_secret);
+
+            // The unauthenticated secret differs: it is not bound to the sender.
+            let plain = recipient
+                .decapsulate(
+                    &auth.encapsulated.classical_public,
+                    &auth.encapsulated.pqc_ciphertext,
+                )
+                .unwrap();
+            assert_ne!(plain, secret);
+        }
+
+        #[test]
+        fn substituted_ciphertext_is_rejected() {
+            let recipient = HybridKemKeyPair::generate().unwrap();
+            let sender = identity();
+            let mitm = identity();
+
+            // The attacker encapsulates and signs with their own key.
+            let forged = HybridKemKeyPair::encapsulate_authenticated(&recipient, &mitm).unwrap();
+            let err = recipient
+                .decapsulate_authenticated(&sender.to_verifier(), &forged)
+                .unwrap_err();
+            assert!(matches!(err, CryptoError::Verification(_)));
+
+            // Or swaps in their own ciphertext under the real signature.
+            let mut spliced =
+                HybridKemKeyPair::encapsulate_authenticated(&recipient, &sender).unwrap();
+            let substitute = recipient.encapsulate().unwrap();
+            spliced.encapsulated.pqc_ciphertext = substitute.pqc_ciphertext.clone();
+            assert!(recipient
+                .decapsulate_authenticated(&sender.to_verifier(), &spliced)
+                .is_err());
+        }
+
+        #[test]
+        fn signature_is_bound_to_recipient() {
+            let recipient = HybridKemKeyPair::generate().unwrap();
+            let other = HybridKemKeyPair::generate().unwrap();
+            let sender = identity();
+            let auth = HybridKemKeyPair::encapsulate_authenticated(&recipient, &sender).unwrap();
+            assert!(other
+                .decapsulate_authenticated(&sender.to_verifier(), &auth)
+                .is_err());
+        }
+
+        #[test]
+        fn wire_format_round_trips() {
+            let recipient = HybridKemKeyPair::generate().unwrap();
+            let sender = identity();
+            let auth = HybridKemKeyPair::encapsulate_authenticated(&recipient, &sender).unwrap();
+
+            let decoded = AuthenticatedHybridEncapsulated::from_bytes(&auth.to_bytes()).unwrap();
+            assert_eq!(decoded.signature, auth.signature);
+            let secret = recipient
+                .decapsulate_authenticated(&sender.to_verifier(), &decoded)
+                .unwrap();
+            assert_eq!(secret, auth.encapsulated.shared_secret);
+
+            let mut wrong_magic = auth.to_bytes();
+            wrong_magic[0] = b'X';
+            assert!(matches!(
+                AuthenticatedHybridEncapsulated::from_bytes(&wrong_magic),
+                Err(CryptoError::Serialization(_))
+            ));
+            // The unsigned encoding lacks the signature field.
+            assert!(
+                AuthenticatedHybridEncapsulated::from_bytes(&auth.encapsulated.to_bytes()).is_err()
+            );
+        }
+    }
 }
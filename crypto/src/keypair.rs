@@ -0,0 +1,191 @@
+use crate::error::{CryptoError, CryptoResult};
+use crate::secure::SecureBytes;
+use quantun_types::Algorithm;
+
+#[cfg(feature = "hybrid")]
+use crate::hybrid::HybridKemKeyPair;
+#[cfg(feature = "mldsa")]
+use crate::mldsa::MlDsaKeyPair;
+#[cfg(feature = "mlkem")]
+use crate::mlkem::MlKemKeyPair;
+#[cfg(feature = "hybrid")]
+use quantun_types::MlKemVariant;
+#[cfg(feature = "slhdsa")]
+use crate::slhdsa::SlhDsaKeyPair;
+
+/// A key pair of any algorithm family compiled into this build.
+#[derive(Debug, Clone)]
+pub enum KeyPair {
+    #[cfg(feature = "mlkem")]
+    MlKem(MlKemKeyPair),
+    #[cfg(feature = "mldsa")]
+    MlDsa(MlDsaKeyPair),
+    #[cfg(feature = "slhdsa")]
+    SlhDsa(SlhDsaKeyPair),
+    #[cfg(feature = "hybrid")]
+    HybridKem(HybridKemKeyPair),
+}
+
+impl KeyPair {
+    /// Generate a key pair for `algorithm`.
+    ///
+    /// Returns `UnsupportedAlgorithm` when the algorithm's family is
+    /// disabled by Cargo features, or has no implementation (the
+    /// Ed25519 + ML-DSA-65 hybrid signature).
+    pub fn generate(algorithm: Algorithm) -> CryptoResult<Self> {
+        match algorithm {
+            #[cfg(feature = "mlkem")]
+            Algorithm::MlKem(v) => MlKemKeyPair::generate(v).map(KeyPair::MlKem),
+            #[cfg(feature = "mldsa")]
+            Algorithm::MlDsa(v) => MlDsaKeyPair::generate(v).map(KeyPair::MlDsa),
+            #[cfg(feature = "slhdsa")]
+            Algorithm::SlhDsa(v) => SlhDsaKeyPair::generate(v).map(KeyPair::SlhDsa),
+            #[cfg(feature = "hybrid")]
+            Algorithm::Hybrid(quantun_types::HybridVariant::X25519MlKem768) => {
+                HybridKemKeyPair::generate().map(KeyPair::HybridKem)
+            }
+            other => Err(CryptoError::UnsupportedAlgorithm(format!(
+                "{other} is not available in this build"
+            ))),
+        }
+    }
+
+    /// Public key bytes. Hybrid KEM keys are the X25519 public key
+    /// followed by the ML-KEM encapsulation key.
+    pub fn public_key(&self) -> Vec<u8> {
+        match *self {
+            #[cfg(feature = "mlkem")]
+            KeyPair::MlKem(ref kp) => kp.public_key.clone(),
+            #[cfg(feature = "mldsa")]
+            KeyPair::MlDsa(ref kp) => kp.public_key.clone(),
+            #[cfg(feature = "slhdsa")]
+            KeyPair::SlhDsa(ref kp) => kp.public_key.clone(),
+            #[cfg(feature = "hybrid")]
+            KeyPair::HybridKem(ref kp) => {
+                [kp.classical_public.as_slice(), &kp.pqc_keypair.public_key].concat()
+            }
+        }
+    }
+
+    /// Rebuild a key pair from the encoding produced by
+    /// [`KeyPair::secret_material`]: the ML-KEM or ML-DSA seed, the SLH-DSA
+    /// signing key, or the X25519 secret followed by the ML-KEM seed.
+    #[cfg_attr(
+        not(any(feature = "mlkem", feature = "mldsa", feature = "slhdsa")),
+        allow(unused_variables)
+    )]
+    pub fn from_secret_material(algorithm: Algorithm, material: &[u8]) -> CryptoResult<Self> {
+        match algorithm {
+            #[cfg(feature = "mlkem")]
+            Algorithm::MlKem(v) => {
+                v.validate_seed(material)?;
+                let seed = material.try_into().expect("length validated");
+                MlKemKeyPair::from_seed(v, seed).map(KeyPair::MlKem)
+            }
+            #[cfg(feature = "mldsa")]
+            Algorithm::MlDsa(v) => {
+                v.validate_seed(material)?;
+                let seed = material.try_into().expect("length validated");
+                Ok(KeyPair::MlDsa(MlDsaKeyPair::from_seed(v, seed)))
+            }
+            #[cfg(feature = "slhdsa")]
+            Algorithm::SlhDsa(v) => SlhDsaKeyPair::from_secret_key(v, material).map(KeyPair::SlhDsa),
+            #[cfg(feature = "hybrid")]
+            Algorithm::Hybrid(quantun_types::HybridVariant::X25519MlKem768) => {
+                let (classical, seed) = material.split_at(material.len().min(32));
+                MlKemVariant::MlKem768.validate_seed(seed)?;
+                let seed = seed.try_into().expect("length validated");
+                HybridKemKeyPair::from_secret_parts(classical, seed).map(KeyPair::HybridKem)
+            }
+            other => Err(CryptoError::UnsupportedAlgorithm(format!(
+                "{other} is not available in this build"
+            ))),
+        }
+    }
+
+    /// Secret material in the encoding read by [`KeyPair::from_secret_material`].
+    pub fn secret_material(&self) -> CryptoResult<SecureBytes> {
+        match *self {
+            #[cfg(feature = "mlkem")]
+            KeyPair::MlKem(ref kp) => Ok(SecureBytes::from_slice(&kp.secret_key)),
+            #[cfg(feature = "mldsa")]
+            KeyPair::MlDsa(ref kp) => Ok(SecureBytes::from_slice(&kp.secret_key)),
+            #[cfg(feature = "slhdsa")]
+            KeyPair::SlhDsa(ref kp) => Ok(SecureBytes::from_slice(&kp.secret_key)),
+            #[cfg(feature = "hybrid")]
+            KeyPair::HybridKem(ref kp) => {
+                let classical = kp.classical_secret.as_ref().ok_or_else(|| {
+                    CryptoError::InvalidKeyMaterial("secret key not available".into())
+                })?;
+                Ok(SecureBytes::new(
+                    [classical.as_slice(), &kp.pqc_keypair.secret_key].concat(),
+                ))
+            }
+        }
+    }
+
+    /// The algorithm this key pair implements.
+    pub fn algorithm(&self) -> Algorithm {
+        match *self {
+            #[cfg(feature = "mlkem")]
+            KeyPair::MlKem(ref kp) => Algorithm::MlKem(kp.variant),
+            #[cfg(feature = "mldsa")]
+            KeyPair::MlDsa(ref kp) => Algorithm::MlDsa(kp.variant),
+            #[cfg(feature = "slhdsa")]
+            KeyPair::SlhDsa(ref kp) => Algorithm::SlhDsa(kp.variant),
+            #[cfg(feature = "hybrid")]
+            KeyPair::HybridKem(ref kp) => Algorithm::Hybrid(kp.variant),
+        }
+    }
+}
+
+#[cfg(all(
+    test,
+    feature = "mlkem",
+    feature = "mldsa",
+    feature = "slhdsa",
+    feature = "hybrid"
+))]
+mod tests {
+    use super::*;
+    use quantun_types::{HybridVariant, MlDsaVariant, MlKemVariant, SlhDsaVariant};
+
+    #[test]
+    fn default_features_enable_all_families() {
+        for algorithm in [
+            Algorithm::MlKem(MlKemVariant::MlKem768),
+            Algorithm::MlDsa(MlDsaVariant::MlDsa65),
+            Algorithm::SlhDsa(SlhDsaVariant::Sha2_128f),
+            Algorithm::Hybrid(HybridVariant::X25519MlKem768),
+        ] {
+            let kp = KeyPair::generate(algorithm).unwrap();
+            assert_eq!(kp.algorithm(), algorithm);
+        }
+    }
+
+    #[test]
+    fn secret_material_round_trip() {
+        for algorithm in [
+            Algorithm::MlKem(MlKemVariant::MlKem512),
+            Algorithm::MlDsa(MlDsaVariant::MlDsa44),
+            Algorithm::SlhDsa(SlhDsaVariant::Sha2_128f),
+            Algorithm::Hybrid(HybridVariant::X25519MlKem768),
+        ] {
+            let kp = KeyPair::generate(algorithm).unwrap();
+            let material = kp.secret_material().unwrap();
+            let restored = KeyPair::from_secret_material(algorithm, material.as_bytes()).unwrap();
+            assert_eq!(restored.public_key(), kp.public_key(), "{algorithm}");
+        }
+        assert!(KeyPair::from_secret_material(
+            Algorithm::MlDsa(MlDsaVariant::MlDsa65),
+            &[0u8; 31]
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn unimplemented_hybrid_signature_is_unsupported() {
+        let err = KeyPair::generate(Algorithm::Hybrid(HybridVariant::Ed25519MlDsa65)).unwrap_err();
+        assert!(matches!(err, CryptoError::UnsupportedAlgorithm(_)));
+    }
+}
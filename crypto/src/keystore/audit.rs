@@ -0,0 +1,179 @@
+//! Audit trail of private-key operations.
+//!
+//! Every sign, decapsulate, and export through a [`KeyStore`](super::KeyStore)
+//! produces a [`KeyUsageEvent`]. Events go to an optional [`AuditSink`] and
+//! to a bounded in-memory [`UsageLog`] queried by
+//! [`KeyStore::recent_usage`](super::KeyStore::recent_usage).
+//!
+//! Events never carry key material or message contents, only the input's
+//! length and SHA-256 digest.
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::VecDeque;
+use std::fmt;
+use std::sync::Mutex;
+
+/// Events kept by [`UsageLog`] when no capacity is given.
+pub const DEFAULT_USAGE_LOG_CAPACITY: usize = 1024;
+
+/// A private-key operation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum KeyOperation {
+    Sign,
+    Decapsulate,
+    Export,
+}
+
+impl fmt::Display for KeyOperation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            KeyOperation::Sign => "sign",
+            KeyOperation::Decapsulate => "decapsulate",
+            KeyOperation::Export => "export",
+        })
+    }
+}
+
+/// Who asked for a key operation. Empty for internal callers.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Caller {
+    pub request_id: Option<String>,
+    pub api_key_id: Option<String>,
+    #[serde(default)]
+    pub tenant_id: Option<String>,
+}
+
+/// One recorded key operation.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KeyUsageEvent {
+    pub key_id: String,
+    pub operation: KeyOperation,
+    pub caller: Caller,
+    /// Seconds since the Unix epoch.
+    pub timestamp: u64,
+    pub success: bool,
+    /// Error message on failure.
+    pub error: Option<String>,
+    /// Length of the message or ciphertext operated on.
+    pub input_len: usize,
+    /// Hex SHA-256 of the input; absent for exports, whose only input is the
+    /// wrapping key.
+    pub input_sha256: Option<String>,
+}
+
+impl KeyUsageEvent {
+    pub(super) fn new(
+        key_id: &str,
+        operation: KeyOperation,
+        caller: &Caller,
+        timestamp: u64,
+        input: Option<&[u8]>,
+    ) -> Self {
+        Self {
+            key_id: key_id.to_string(),
+            operation,
+            caller: caller.clone(),
+            timestamp,
+            success: true,
+            error: None,
+            input_len: input.map_or(0, <[u8]>::len),
+            input_sha256: input.map(|i| {
+                Sha256::digest(i)
+                    .iter()
+                    .map(|b| format!("{b:02x}"))
+                    .collect()
+            }),
+        }
+    }
+}
+
+/// Destination for [`KeyUsageEvent`]s, e.g. the gateway's audit log.
+pub trait AuditSink: Send + Sync {
+    fn record(&self, event: &KeyUsageEvent);
+}
+
+/// Ring buffer holding the most recent [`KeyUsageEvent`]s.
+#[derive(Debug)]
+pub struct UsageLog {
+    events: Mutex<VecDeque<KeyUsageEvent>>,
+    capacity: usize,
+}
+
+impl UsageLog {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            events: Mutex::new(VecDeque::with_capacity(capacity)),
+            capacity,
+        }
+    }
+
+    pub(super) fn push(&self, event: KeyUsageEvent) {
+        if self.capacity == 0 {
+            return;
+        }
+        let mut events = self.events.lock().unwrap();
+        if events.len() == self.capacity {
+            events.pop_front();
+        }
+        events.push_back(event);
+    }
+
+    /// Up to `n` most recent events for `key_id`, oldest first.
+    pub fn recent(&self, key_id: &str, n: usize) -> Vec<KeyUsageEvent> {
+        let events = self.events.lock().unwrap();
+        let mut recent: Vec<_> = events
+            .iter()
+            .rev()
+            .filter(|e| e.key_id == key_id)
+            .take(n)
+            .cloned()
+            .collect();
+        recent.reverse();
+        recent
+    }
+}
+
+impl Default for UsageLog {
+    fn default() -> Self {
+        Self::new(DEFAULT_USAGE_LOG_CAPACITY)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(key_id: &str, timestamp: u64) -> KeyUsageEvent {
+        KeyUsageEvent::new(
+            key_id,
+            KeyOperation::Sign,
+            &Caller::default(),
+            timestamp,
+            Some(b"m"),
+        )
+    }
+
+    #[test]
+    fn ring_buffer_drops_oldest() {
+        let log = UsageLog::new(3);
+        for t in 0..5 {
+            log.push(event(if t % 2 == 0 { "a" } else { "b" }, t));
+        }
+        let a: Vec<_> = log.recent("a", 10).iter().map(|e| e.timestamp).collect();
+        assert_eq!(a, [2, 4]);
+        let b: Vec<_> = log.recent("b", 1).iter().map(|e| e.timestamp).collect();
+        assert_eq!(b, [3]);
+    }
+
+    #[test]
+    fn event_records_digest_not_input() {
+        let e = event("a", 0);
+        assert_eq!(e.input_len, 1);
+        assert_eq!(
+            e.input_sha256.as_deref(),
+            Some("62c66a7a5dd70c3146618063c344e531e6d4b59e379808443ce962b3abd63c5a")
+        );
+    }
+}
@@ -0,0 +1,314 @@
+//! On-disk layout for a persistent [`KeyStore`](super::KeyStore).
+//!
+//! Each key is two files in the store directory:
+//!
+//! - `<key_id>.key`: the key's secret material sealed with AES-256-GCM under
+//!   the store's key-encryption key, bound to the key ID.
+//! - `<key_id>.json`: the [`KeyMetadata`] in clear JSON.
+//! - `<kind>/<id>.json`: a record stored with
+//!   [`KeyStore::put_record`](super::KeyStore::put_record), in clear.
+//!
+//! Files are written to a temporary name and renamed into place, and are
+//! created with mode `0600` on Unix. The metadata file is written last, so
+//! a `.key` file without metadata is an interrupted write and is ignored.
+
+use super::{seal, unseal};
+use crate::error::{CryptoError, CryptoResult};
+use crate::keypair::KeyPair;
+use crate::secure::SecureBytes;
+use quantun_types::KeyMetadata;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// A store entry that could not be loaded.
+#[derive(Debug, Clone)]
+pub struct SkippedKey {
+    pub path: PathBuf,
+    pub reason: String,
+}
+
+/// Directory holding sealed keys and their metadata.
+pub(super) struct KeyDir {
+    dir: PathBuf,
+    kek: SecureBytes,
+}
+
+impl KeyDir {
+    pub(super) fn open(dir: &Path, kek: SecureBytes) -> CryptoResult<Self> {
+        if kek.len() != 32 {
+            return Err(CryptoError::InvalidKeyMaterial(format!(
+                "key-encryption key must be 32 bytes, got {}",
+                kek.len()
+            )));
+        }
+        fs::create_dir_all(dir).map_err(|e| io_error(dir, e))?;
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(dir, fs::Permissions::from_mode(0o700))
+                .map_err(|e| io_error(dir, e))?;
+        }
+        Ok(Self {
+            dir: dir.to_path_buf(),
+            kek,
+        })
+    }
+
+    /// Persist a new key: sealed secret first, then metadata.
+    pub(super) fn store(&self, metadata: &KeyMetadata, keypair: &KeyPair) -> CryptoResult<()> {
+        let secret = keypair.secret_material()?;
+        let sealed = seal(self.kek(), &metadata.key_id, secret.as_bytes())?;
+        self.write_atomic(&self.key_path(&metadata.key_id), &sealed)?;
+        self.store_metadata(metadata)
+    }
+
+    /// Overwrite a key's metadata.
+    pub(super) fn store_metadata(&self, metadata: &KeyMetadata) -> CryptoResult<()> {
+        let json = serde_json::to_vec_pretty(metadata)
+            .map_err(|e| CryptoError::Serialization(e.to_string()))?;
+        self.write_atomic(&self.metadata_path(&metadata.key_id), &json)
+    }
+
+    /// Load every complete entry, skipping those that fail to parse, unseal,
+    /// or rebuild.
+    pub(super) fn load(&self) -> (Vec<(KeyMetadata, KeyPair)>, Vec<SkippedKey>) {
+        let mut loaded = Vec::new();
+        let mut skipped = Vec::new();
+
+        let entries = match fs::read_dir(&self.dir) {
+            Ok(entries) => entries,
+            Err(e) => {
+                skipped.push(SkippedKey {
+                    path: self.dir.clone(),
+                    reason: e.to_string(),
+                });
+                return (loaded, skipped);
+            }
+        };
+
+        for path in entries.filter_map(|e| e.ok()).map(|e| e.path()) {
+            if path.extension().and_then(|e| e.to_str()) != Some("json") {
+                continue;
+            }
+            match self.load_entry(&path) {
+                Ok(entry) => loaded.push(entry),
+                Err(e) => {
+                    tracing::warn!(path = %path.display(), error = %e, "skipping unreadable key");
+                    skipped.push(SkippedKey {
+                        path,
+                        reason: e.to_string(),
+                    });
+                }
+            }
+        }
+
+        (loaded, skipped)
+    }
+
+    /// Persist a record as `<kind>/<id>.json`.
+    pub(super) fn store_record(&self, kind: &str, id: &str, record: &[u8]) -> CryptoResult<()> {
+        let dir = self.dir.join(kind);
+        fs::create_dir_all(&dir).map_err(|e| io_error(&dir, e))?;
+        self.write_atomic(&dir.join(format!("{id}.json")), record)
+    }
+
+    /// Every record of `kind`, ordered by ID.
+    pub(super) fn load_records(&self, kind: &str) -> CryptoResult<Vec<Vec<u8>>> {
+        let dir = self.dir.join(kind);
+        let entries = match fs::read_dir(&dir) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(io_error(&dir, e)),
+        };
+        let mut paths: Vec<_> = entries
+            .filter_map(|e| e.ok())
+            .map(|e| e.path())
+            .filter(|p| p.extension().and_then(|e| e.to_str()) == Some("json"))
+            .collect();
+        paths.sort();
+        paths
+            .iter()
+            .map(|path| fs::read(path).map_err(|e| io_error(path, e)))
+            .collect()
+    }
+
+    fn load_entry(&self, metadata_path: &Path) -> CryptoResult<(KeyMetadata, KeyPair)> {
+        let json = fs::read(metadata_path).map_err(|e| io_error(metadata_path, e))?;
+        let metadata: KeyMetadata = serde_json::from_slice(&json)
+            .map_err(|e| CryptoError::Serialization(format!("invalid metadata: {e}")))?;
+
+        let stem = metadata_path.file_stem().and_then(|s| s.to_str());
+        if stem != Some(metadata.key_id.as_str()) {
+            return Err(CryptoError::Serialization(format!(
+                "metadata key ID {} does not match file name",
+                metadata.key_id
+            )));
+        }
+
+        let key_path = self.key_path(&metadata.key_id);
+        let sealed = fs::read(&key_path).map_err(|e| io_error(&key_path, e))?;
+        let secret = unseal(self.kek(), &metadata.key_id, &sealed)?;
+        let keypair = KeyPair::from_secret_material(metadata.algorithm, secret.as_bytes())?;
+        Ok((metadata, keypair))
+    }
+
+    fn write_atomic(&self, path: &Path, contents: &[u8]) -> CryptoResult<()> {
+        let mut tmp = path.as_os_str().to_owned();
+        tmp.push(".tmp");
+        let tmp = PathBuf::from(tmp);
+        let _ = fs::remove_file(&tmp);
+
+        let mut options = OpenOptions::new();
+        options.write(true).create_new(true);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::OpenOptionsExt;
+            options.mode(0o600);
+        }
+
+        let mut file = options.open(&tmp).map_err(|e| io_error(&tmp, e))?;
+        file.write_all(contents)
+            .and_then(|()| file.sync_all())
+            .map_err(|e| io_error(&tmp, e))?;
+        fs::rename(&tmp, path).map_err(|e| io_error(path, e))
+    }
+
+    fn kek(&self) -> &[u8; 32] {
+        self.kek.as_bytes().try_into().expect("length checked in open")
+    }
+
+    fn key_path(&self, key_id: &str) -> PathBuf {
+        self.dir.join(format!("{key_id}.key"))
+    }
+
+    fn metadata_path(&self, key_id: &str) -> PathBuf {
+        self.dir.join(format!("{key_id}.json"))
+    }
+}
+
+fn io_error(path: &Path, e: std::io::Error) -> CryptoError {
+    CryptoError::Serialization(format!("{}: {e}", path.display()))
+}
+
+#[cfg(all(test, feature = "mlkem", feature = "mldsa"))]
+mod tests {
+    use super::super::KeyStore;
+    use crate::mldsa::{MlDsaSignature, MlDsaVerifier};
+    use crate::secure::SecureBytes;
+    use crate::testing::TempDir;
+    use quantun_types::{Algorithm, KeyState, KeyUsage, MlDsaVariant, MlKemVariant};
+
+    fn kek() -> SecureBytes {
+        SecureBytes::new(vec![0x5a; 32])
+    }
+
+    #[test]
+    fn keys_survive_restart() {
+        let dir = TempDir::new("keystore");
+        let (store, skipped) = KeyStore::open(dir.path(), kek()).unwrap();
+        assert!(skipped.is_empty());
+        let sign = store
+            .create(Algorithm::MlDsa(MlDsaVariant::MlDsa65), [KeyUsage::Sign])
+            .unwrap();
+        let kem = store
+            .create(Algorithm::MlKem(MlKemVariant::MlKem768), [KeyUsage::KeyAgreement])
+            .unwrap();
+        store.revoke(&kem).unwrap();
+        let public_key = store.get_public(&sign).unwrap();
+        drop(store);
+
+        let (store, skipped) = KeyStore::open(dir.path(), kek()).unwrap();
+        assert!(skipped.is_empty());
+        assert_eq!(store.list().len(), 2);
+        assert_eq!(store.handle(sign.key_id()), Some(sign.clone()));
+        assert_eq!(store.metadata(&kem).unwrap().state, KeyState::Deactivated);
+
+        let signature = store.sign(&sign, b"after restart").unwrap();
+        let verifier = MlDsaVerifier {
+            variant: MlDsaVariant::MlDsa65,
+            public_key,
+        };
+        let sig = MlDsaSignature {
+            signature,
+            variant: MlDsaVariant::MlDsa65,
+        };
+        assert!(verifier.verify(b"after restart", &sig).unwrap());
+    }
+
+    #[test]
+    fn corrupt_entries_are_skipped() {
+        let dir = TempDir::new("keystore");
+        let (store, _) = KeyStore::open(dir.path(), kek()).unwrap();
+        let good = store
+            .create(Algorithm::MlDsa(MlDsaVariant::MlDsa44), [KeyUsage::Sign])
+            .unwrap();
+        let bad = store
+            .create(Algorithm::MlDsa(MlDsaVariant::MlDsa44), [KeyUsage::Sign])
+            .unwrap();
+        drop(store);
+
+        let bad_key = dir.join(format!("{}.key", bad.key_id()));
+        let mut sealed = std::fs::read(&bad_key).unwrap();
+        *sealed.last_mut().unwrap() ^= 0xff;
+        std::fs::write(&bad_key, sealed).unwrap();
+
+        let (store, skipped) = KeyStore::open(dir.path(), kek()).unwrap();
+        assert_eq!(skipped.len(), 1);
+        assert!(skipped[0].path.ends_with(format!("{}.json", bad.key_id())));
+        assert!(store.handle(good.key_id()).is_some());
+        assert!(store.handle(bad.key_id()).is_none());
+    }
+
+    #[test]
+    fn records_survive_restart() {
+        let dir = TempDir::new("keystore");
+        let (store, _) = KeyStore::open(dir.path(), kek()).unwrap();
+        store.put_record("devices", "b", b"{\"v\":2}").unwrap();
+        store.put_record("devices", "a", b"{\"v\":1}").unwrap();
+        store.put_record("devices", "b", b"{\"v\":3}").unwrap();
+        assert!(store.put_record("devices", "../escape", b"{}").is_err());
+        drop(store);
+
+        let (store, skipped) = KeyStore::open(dir.path(), kek()).unwrap();
+        assert!(skipped.is_empty());
+        assert!(store.list().is_empty());
+        assert_eq!(
+            store.records("devices").unwrap(),
+            [b"{\"v\":1}".to_vec(), b"{\"v\":3}".to_vec()]
+        );
+        assert!(store.records("other").unwrap().is_empty());
+    }
+
+    #[test]
+    fn wrong_kek_skips_everything() {
+        let dir = TempDir::new("keystore");
+        let (store, _) = KeyStore::open(dir.path(), kek()).unwrap();
+        store
+            .create(Algorithm::MlDsa(MlDsaVariant::MlDsa44), [KeyUsage::Sign])
+            .unwrap();
+        drop(store);
+
+        let (store, skipped) = KeyStore::open(dir.path(), SecureBytes::new(vec![1; 32])).unwrap();
+        assert_eq!(skipped.len(), 1);
+        assert!(store.list().is_empty());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn files_are_owner_only() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = TempDir::new("keystore");
+        let (store, _) = KeyStore::open(dir.path(), kek()).unwrap();
+        let handle = store
+            .create(Algorithm::MlDsa(MlDsaVariant::MlDsa44), [KeyUsage::Sign])
+            .unwrap();
+
+        for ext in ["key", "json"] {
+            let path = dir.join(format!("{}.{ext}", handle.key_id()));
+            let mode = std::fs::metadata(path).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+    }
+}
@@ -0,0 +1,117 @@
+//! Content-addressed key storage.
+//!
+//! A [`FingerprintKeyStore`] addresses each key by its fingerprint, so an
+//! ID can never be reused for a different key and inserting the same key
+//! twice stores it once.
+
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::sync::RwLock;
+
+use crate::keypair::KeyPair;
+#[cfg(feature = "mldsa")]
+use crate::mldsa::MlDsaKeyPair;
+#[cfg(feature = "mlkem")]
+use crate::mlkem::MlKemKeyPair;
+#[cfg(feature = "slhdsa")]
+use crate::slhdsa::SlhDsaKeyPair;
+
+/// Hex SHA-256 of the algorithm name (e.g. `ML-KEM-768`) followed by the
+/// public key.
+pub fn key_fingerprint(keypair: &KeyPair) -> String {
+    let digest = Sha256::new()
+        .chain_update(keypair.algorithm().to_string())
+        .chain_update(keypair.public_key())
+        .finalize();
+    digest.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+/// Thread-safe in-memory key store keyed by [`key_fingerprint`].
+#[derive(Default)]
+pub struct FingerprintKeyStore {
+    keys: RwLock<HashMap<String, KeyPair>>,
+}
+
+impl FingerprintKeyStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Store `keypair` and return its fingerprint. Inserting a key that is
+    /// already stored leaves the store unchanged.
+    pub fn insert(&self, keypair: KeyPair) -> String {
+        let fingerprint = key_fingerprint(&keypair);
+        self.keys
+            .write()
+            .unwrap()
+            .entry(fingerprint.clone())
+            .or_insert(keypair);
+        fingerprint
+    }
+
+    #[cfg(feature = "mlkem")]
+    pub fn insert_mlkem(&self, kp: MlKemKeyPair) -> String {
+        self.insert(KeyPair::MlKem(kp))
+    }
+
+    #[cfg(feature = "mldsa")]
+    pub fn insert_mldsa(&self, kp: MlDsaKeyPair) -> String {
+        self.insert(KeyPair::MlDsa(kp))
+    }
+
+    #[cfg(feature = "slhdsa")]
+    pub fn insert_slhdsa(&self, kp: SlhDsaKeyPair) -> String {
+        self.insert(KeyPair::SlhDsa(kp))
+    }
+
+    pub fn contains(&self, fingerprint: &str) -> bool {
+        self.keys.read().unwrap().contains_key(fingerprint)
+    }
+
+    /// Public key bytes of the key with `fingerprint`.
+    pub fn get_public(&self, fingerprint: &str) -> Option<Vec<u8>> {
+        self.keys
+            .read()
+            .unwrap()
+            .get(fingerprint)
+            .map(KeyPair::public_key)
+    }
+
+    pub fn len(&self) -> usize {
+        self.keys.read().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+#[cfg(all(test, feature = "mlkem", feature = "mldsa"))]
+mod tests {
+    use super::*;
+    use quantun_types::{MlDsaVariant, MlKemVariant};
+
+    #[test]
+    fn same_key_is_stored_once() {
+        let store = FingerprintKeyStore::new();
+        let kp = MlKemKeyPair::generate(MlKemVariant::MlKem768).unwrap();
+        let first = store.insert_mlkem(kp.clone());
+        let second = store.insert_mlkem(kp.clone());
+        assert_eq!(first, second);
+        assert_eq!(first.len(), 64);
+        assert_eq!(store.len(), 1);
+        assert!(store.contains(&first));
+        assert_eq!(store.get_public(&first), Some(kp.public_key.clone()));
+    }
+
+    #[test]
+    fn different_keys_get_different_fingerprints() {
+        let store = FingerprintKeyStore::new();
+        let a = store.insert_mldsa(MlDsaKeyPair::generate(MlDsaVariant::MlDsa44).unwrap());
+        let b = store.insert_mldsa(MlDsaKeyPair::generate(MlDsaVariant::MlDsa44).unwrap());
+        assert_ne!(a, b);
+        assert_eq!(store.len(), 2);
+        assert!(!store.contains("00"));
+        assert!(store.get_public("00").is_none());
+    }
+}
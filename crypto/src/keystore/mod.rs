@@ -0,0 +1,709 @@
+//! In-memory store for the gateway's live keys.
+//!
+//! Keys are addressed by opaque [`KeyHandle`]s. Every private-key operation
+//! is checked against the key's [`KeyMetadata`] (state, expiry, and permitted
+//! usages), so a KEM key cannot sign and a revoked key cannot be used at all.
+//! Secret material never leaves the store except through
+//! [`KeyStore::export_encrypted`].
+//!
+//! [`KeyStore::open`] backs the store with a directory so keys survive
+//! restarts; see [`file`] for the on-disk layout. Private-key operations are
+//! recorded in an audit trail; see [`audit`].
+//!
+//! The store also keeps small named records, such as registered devices, in
+//! the same backend: see [`KeyStore::put_record`].
+//!
+//! [`FingerprintKeyStore`] is a simpler, content-addressed alternative where
+//! each key's ID is its fingerprint.
+
+pub mod audit;
+mod file;
+mod fingerprint;
+
+use crate::error::{CryptoError, CryptoResult};
+use crate::keypair::KeyPair;
+use crate::secure::SecureBytes;
+use audit::{AuditSink, Caller, KeyOperation, KeyUsageEvent, UsageLog};
+use file::KeyDir;
+use aes_gcm::aead::{Aead, KeyInit, Payload};
+use aes_gcm::{Aes256Gcm, Nonce};
+use quantun_types::{Algorithm, ErrorCode, KeyMetadata, KeyState, KeyUsage};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::path::Path;
+use std::sync::{Arc, RwLock};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+pub use file::SkippedKey;
+pub use fingerprint::{key_fingerprint, FingerprintKeyStore};
+
+/// Length of the random nonce prefixed to sealed key material.
+const NONCE_LEN: usize = 12;
+
+/// Opaque reference to a key held in a [`KeyStore`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct KeyHandle(String);
+
+impl KeyHandle {
+    /// The key identifier, as recorded in [`KeyMetadata::key_id`].
+    pub fn key_id(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Display for KeyHandle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+struct StoredKey {
+    metadata: KeyMetadata,
+    keypair: KeyPair,
+}
+
+/// Thread-safe key store, in memory or backed by a directory.
+///
+/// Operations take a shared lock, so concurrent signs and decapsulations
+/// on the same key do not serialize; only create, import, and revoke take
+/// the exclusive lock.
+#[derive(Default)]
+pub struct KeyStore {
+    keys: RwLock<HashMap<String, StoredKey>>,
+    persistence: Option<KeyDir>,
+    usage_log: UsageLog,
+    audit_sink: Option<Arc<dyn AuditSink>>,
+    records: RwLock<HashMap<String, BTreeMap<String, Vec<u8>>>>,
+}
+
+impl KeyStore {
+    /// Create an empty in-memory store.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Open a store persisted in `dir`, creating the directory if needed.
+    ///
+    /// Secret material is sealed with AES-256-GCM under the 32-byte `kek`.
+    /// Entries that cannot be read or unsealed are skipped and returned
+    /// rather than failing the whole load.
+    pub fn open(
+        dir: impl AsRef<Path>,
+        kek: SecureBytes,
+    ) -> CryptoResult<(Self, Vec<SkippedKey>)> {
+        let key_dir = KeyDir::open(dir.as_ref(), kek)?;
+        let (loaded, skipped) = key_dir.load();
+
+        let keys = loaded
+            .into_iter()
+            .map(|(metadata, keypair)| (metadata.key_id.clone(), StoredKey { metadata, keypair }))
+            .collect();
+
+        tracing::info!(
+            dir = %dir.as_ref().display(),
+            skipped = skipped.len(),
+            "keystore loaded"
+        );
+
+        Ok((
+            Self {
+                keys: RwLock::new(keys),
+                persistence: Some(key_dir),
+                ..Self::default()
+            },
+            skipped,
+        ))
+    }
+
+    /// Forward every [`KeyUsageEvent`] to `sink` in addition to the
+    /// in-memory usage log.
+    pub fn with_audit_sink(mut self, sink: Arc<dyn AuditSink>) -> Self {
+        self.audit_sink = Some(sink);
+        self
+    }
+
+    /// Keep the last `capacity` events in the in-memory usage log.
+    pub fn with_usage_log_capacity(mut self, capacity: usize) -> Self {
+        self.usage_log = UsageLog::new(capacity);
+        self
+    }
+
+    /// Up to `n` most recent usage events for `handle`, oldest first.
+    pub fn recent_usage(&self, handle: &KeyHandle, n: usize) -> Vec<KeyUsageEvent> {
+        self.usage_log.recent(handle.key_id(), n)
+    }
+
+    /// Look up the handle for a stored key ID.
+    pub fn handle(&self, key_id: &str) -> Option<KeyHandle> {
+        self.keys
+            .read()
+            .unwrap()
+            .contains_key(key_id)
+            .then(|| KeyHandle(key_id.to_string()))
+    }
+
+    /// Generate and store an active key for `algorithm`.
+    pub fn create(
+        &self,
+        algorithm: Algorithm,
+        usages: impl IntoIterator<Item = KeyUsage>,
+    ) -> CryptoResult<KeyHandle> {
+        self.import(KeyPair::generate(algorithm)?, usages)
+    }
+
+    /// Generate and store a key for `algorithm` in the `PendingActivation`
+    /// state. Its public key is available immediately; private-key
+    /// operations fail until [`KeyStore::activate`].
+    pub fn create_pending(
+        &self,
+        algorithm: Algorithm,
+        usages: impl IntoIterator<Item = KeyUsage>,
+    ) -> CryptoResult<KeyHandle> {
+        self.insert(KeyPair::generate(algorithm)?, usages, KeyState::PendingActivation)
+    }
+
+    /// Store an existing key pair as an active key.
+    pub fn import(
+        &self,
+        keypair: KeyPair,
+        usages: impl IntoIterator<Item = KeyUsage>,
+    ) -> CryptoResult<KeyHandle> {
+        self.insert(keypair, usages, KeyState::Active)
+    }
+
+    fn insert(
+        &self,
+        keypair: KeyPair,
+        usages: impl IntoIterator<Item = KeyUsage>,
+        state: KeyState,
+    ) -> CryptoResult<KeyHandle> {
+        let key_id = new_key_id()?;
+        let now = unix_now();
+        let mut metadata = KeyMetadata::new(key_id.clone(), keypair.algorithm(), usages, now);
+        if state != metadata.state {
+            metadata
+                .transition(state, now)
+                .map_err(|code| unusable(&key_id, code))?;
+        }
+
+        if let Some(key_dir) = &self.persistence {
+            key_dir.store(&metadata, &keypair)?;
+        }
+
+        tracing::info!(
+            key_id = %key_id,
+            algorithm = %metadata.algorithm,
+            state = %state,
+            "key added to store"
+        );
+
+        self.keys
+            .write()
+            .unwrap()
+            .insert(key_id.clone(), StoredKey { metadata, keypair });
+        Ok(KeyHandle(key_id))
+    }
+
+    /// Public key bytes for `handle`. Available in every key state.
+    pub fn get_public(&self, handle: &KeyHandle) -> CryptoResult<Vec<u8>> {
+        self.with_key(handle, |key| Ok(key.keypair.public_key()))
+    }
+
+    /// Metadata for `handle`.
+    pub fn metadata(&self, handle: &KeyHandle) -> CryptoResult<KeyMetadata> {
+        self.with_key(handle, |key| Ok(key.metadata.clone()))
+    }
+
+    /// Sign `message`. Requires the `Sign` usage.
+    pub fn sign(&self, handle: &KeyHandle, message: &[u8]) -> CryptoResult<Vec<u8>> {
+        self.sign_as(&Caller::default(), handle, message)
+    }
+
+    /// [`KeyStore::sign`] on behalf of `caller`, for the audit trail.
+    #[cfg_attr(not(any(feature = "mldsa", feature = "slhdsa")), allow(unused_variables))]
+    pub fn sign_as(
+        &self,
+        caller: &Caller,
+        handle: &KeyHandle,
+        message: &[u8],
+    ) -> CryptoResult<Vec<u8>> {
+        let event = KeyUsageEvent::new(
+            handle.key_id(),
+            KeyOperation::Sign,
+            caller,
+            unix_now(),
+            Some(message),
+        );
+        let result = self.with_key(handle, |key| {
+            check_usage(key, KeyUsage::Sign)?;
+            #[allow(unreachable_patterns)]
+            match key.keypair {
+                #[cfg(feature = "mldsa")]
+                KeyPair::MlDsa(ref kp) => kp.sign(message).map(|sig| sig.signature),
+                #[cfg(feature = "slhdsa")]
+                KeyPair::SlhDsa(ref kp) => kp.sign(message).map(|sig| sig.signature),
+                _ => Err(CryptoError::UnsupportedAlgorithm(format!(
+                    "{} cannot sign",
+                    key.metadata.algorithm
+                ))),
+            }
+        });
+        self.audited(event, result)
+    }
+
+    /// Recover a shared secret from `ciphertext`. Requires the
+    /// `KeyAgreement` usage.
+    ///
+    /// Hybrid ciphertexts are the 32-byte X25519 ephemeral public key
+    /// followed by the ML-KEM ciphertext.
+    pub fn decapsulate(&self, handle: &KeyHandle, ciphertext: &[u8]) -> CryptoResult<Vec<u8>> {
+        self.decapsulate_as(&Caller::default(), handle, ciphertext)
+    }
+
+    /// [`KeyStore::decapsulate`] on behalf of `caller`, for the audit trail.
+    #[cfg_attr(not(feature = "mlkem"), allow(unused_variables))]
+    pub fn decapsulate_as(
+        &self,
+        caller: &Caller,
+        handle: &KeyHandle,
+        ciphertext: &[u8],
+    ) -> CryptoResult<Vec<u8>> {
+        let event = KeyUsageEvent::new(
+            handle.key_id(),
+            KeyOperation::Decapsulate,
+            caller,
+            unix_now(),
+            Some(ciphertext),
+        );
+        let result = self.with_key(handle, |key| {
+            check_usage(key, KeyUsage::KeyAgreement)?;
+            #[allow(unreachable_patterns)]
+            match key.keypair {
+                #[cfg(feature = "mlkem")]
+                KeyPair::MlKem(ref kp) => kp.decapsulate(ciphertext),
+                #[cfg(feature = "hybrid")]
+                KeyPair::HybridKem(ref kp) => {
+                    if ciphertext.len() < 32 {
+                        return Err(CryptoError::Decapsulation(format!(
+                            "hybrid ciphertext too short ({} bytes)",
+                            ciphertext.len()
+                        )));
+                    }
+                    let (classical, pqc) = ciphertext.split_at(32);
+                    kp.decapsulate(classical, pqc)
+                }
+                _ => Err(CryptoError::UnsupportedAlgorithm(format!(
+                    "{} cannot decapsulate",
+                    key.metadata.algorithm
+                ))),
+            }
+        });
+        self.audited(event, result)
+    }
+
+    /// Metadata for every stored key.
+    pub fn list(&self) -> Vec<KeyMetadata> {
+        self.keys
+            .read()
+            .unwrap()
+            .values()
+            .map(|key| key.metadata.clone())
+            .collect()
+    }
+
+    /// Activate a key created with [`KeyStore::create_pending`].
+    pub fn activate(&self, handle: &KeyHandle) -> CryptoResult<()> {
+        self.transition(handle, KeyState::Active)?;
+        tracing::info!(key_id = %handle, "key activated");
+        Ok(())
+    }
+
+    /// Deactivate `handle`. Later private-key operations fail with
+    /// `KEY_REVOKED`; the public key stays available for verification.
+    pub fn revoke(&self, handle: &KeyHandle) -> CryptoResult<()> {
+        self.transition(handle, KeyState::Deactivated)?;
+        tracing::info!(key_id = %handle, "key revoked");
+        Ok(())
+    }
+
+    /// Mark `handle` compromised. Like [`KeyStore::revoke`], but recorded
+    /// as a compromise.
+    pub fn mark_compromised(&self, handle: &KeyHandle) -> CryptoResult<()> {
+        self.transition(handle, KeyState::Compromised)?;
+        tracing::warn!(key_id = %handle, "key marked compromised");
+        Ok(())
+    }
+
+    fn transition(&self, handle: &KeyHandle, next: KeyState) -> CryptoResult<()> {
+        let mut keys = self.keys.write().unwrap();
+        let key = keys
+            .get_mut(handle.key_id())
+            .ok_or_else(|| CryptoError::KeyNotFound(handle.to_string()))?;
+        let mut metadata = key.metadata.clone();
+        metadata
+            .transition(next, unix_now())
+            .map_err(|code| unusable(handle.key_id(), code))?;
+        if let Some(key_dir) = &self.persistence {
+            key_dir.store_metadata(&metadata)?;
+        }
+        key.metadata = metadata;
+        Ok(())
+    }
+
+    /// Export the secret material of an active key, sealed with AES-256-GCM
+    /// under `kek` and bound to the key ID.
+    ///
+    /// Output is a 12-byte nonce followed by the ciphertext. Hybrid keys
+    /// export the X25519 secret followed by the ML-KEM seed.
+    pub fn export_encrypted(&self, handle: &KeyHandle, kek: &[u8; 32]) -> CryptoResult<Vec<u8>> {
+        self.export_encrypted_as(&Caller::default(), handle, kek)
+    }
+
+    /// [`KeyStore::export_encrypted`] on behalf of `caller`, for the audit
+    /// trail.
+    pub fn export_encrypted_as(
+        &self,
+        caller: &Caller,
+        handle: &KeyHandle,
+        kek: &[u8; 32],
+    ) -> CryptoResult<Vec<u8>> {
+        let event = KeyUsageEvent::new(
+            handle.key_id(),
+            KeyOperation::Export,
+            caller,
+            unix_now(),
+            None,
+        );
+        let result = self.with_key(handle, |key| {
+            if key.metadata.state != KeyState::Active {
+                return Err(unusable(handle.key_id(), ErrorCode::KeyRevoked));
+            }
+            let secret = key.keypair.secret_material()?;
+            let sealed = seal(kek, handle.key_id(), secret.as_bytes())?;
+
+            tracing::info!(key_id = %handle, "key exported");
+            Ok(sealed)
+        });
+        self.audited(event, result)
+    }
+
+    /// Record `event` with the outcome of `result`, then return `result`.
+    fn audited<T>(&self, mut event: KeyUsageEvent, result: CryptoResult<T>) -> CryptoResult<T> {
+        if let Err(e) = &result {
+            event.success = false;
+            event.error = Some(e.to_string());
+        }
+        if let Some(sink) = &self.audit_sink {
+            sink.record(&event);
+        }
+        self.usage_log.push(event);
+        result
+    }
+
+    /// Store `record` as `id` among the records of `kind`, replacing any
+    /// previous one, in memory or in the store directory.
+    ///
+    /// Records are kept in clear, so they must not hold secret material.
+    /// `kind` and `id` are limited to ASCII letters, digits, `-`, `_` and
+    /// `.`, and may not start with `.`.
+    pub fn put_record(&self, kind: &str, id: &str, record: &[u8]) -> CryptoResult<()> {
+        check_record_name(kind)?;
+        check_record_name(id)?;
+        if let Some(key_dir) = &self.persistence {
+            key_dir.store_record(kind, id, record)?;
+        }
+        self.records
+            .write()
+            .unwrap()
+            .entry(kind.to_string())
+            .or_default()
+            .insert(id.to_string(), record.to_vec());
+        Ok(())
+    }
+
+    /// Every record of `kind`, ordered by ID. A persistent store reads them
+    /// from its directory, so records written before a restart are
+    /// included.
+    pub fn records(&self, kind: &str) -> CryptoResult<Vec<Vec<u8>>> {
+        check_record_name(kind)?;
+        if let Some(key_dir) = &self.persistence {
+            return key_dir.load_records(kind);
+        }
+        Ok(self
+            .records
+            .read()
+            .unwrap()
+            .get(kind)
+            .map(|records| records.values().cloned().collect())
+            .unwrap_or_default())
+    }
+
+    fn with_key<T>(
+        &self,
+        handle: &KeyHandle,
+        f: impl FnOnce(&StoredKey) -> CryptoResult<T>,
+    ) -> CryptoResult<T> {
+        let keys = self.keys.read().unwrap();
+        let key = keys
+            .get(handle.key_id())
+            .ok_or_else(|| CryptoError::KeyNotFound(handle.to_string()))?;
+        f(key)
+    }
+}
+
+/// Encrypt `plaintext` under `kek`, bound to `key_id`, as nonce || ciphertext.
+fn seal(kek: &[u8; 32], key_id: &str, plaintext: &[u8]) -> CryptoResult<Vec<u8>> {
+    let mut nonce = [0u8; NONCE_LEN];
+    getrandom::fill(&mut nonce).map_err(|e| CryptoError::Rng(e.to_string()))?;
+
+    let sealed = Aes256Gcm::new(kek.into())
+        .encrypt(
+            Nonce::from_slice(&nonce),
+            Payload {
+                msg: plaintext,
+                aad: key_id.as_bytes(),
+            },
+        )
+        .map_err(|_| CryptoError::Serialization("key sealing failed".into()))?;
+    Ok([nonce.as_slice(), &sealed].concat())
+}
+
+/// Reverse [`seal`].
+fn unseal(kek: &[u8; 32], key_id: &str, sealed: &[u8]) -> CryptoResult<SecureBytes> {
+    if sealed.len() < NONCE_LEN {
+        return Err(CryptoError::Serialization("sealed key truncated".into()));
+    }
+    let (nonce, ciphertext) = sealed.split_at(NONCE_LEN);
+    Aes256Gcm::new(kek.into())
+        .decrypt(
+            Nonce::from_slice(nonce),
+            Payload {
+                msg: ciphertext,
+                aad: key_id.as_bytes(),
+            },
+        )
+        .map(SecureBytes::new)
+        .map_err(|_| CryptoError::Serialization("sealed key failed authentication".into()))
+}
+
+fn check_usage(key: &StoredKey, usage: KeyUsage) -> CryptoResult<()> {
+    key.metadata
+        .can_perform(usage)
+        .map_err(|code| unusable(&key.metadata.key_id, code))
+}
+
+fn check_record_name(name: &str) -> CryptoResult<()> {
+    let valid = !name.is_empty()
+        && !name.starts_with('.')
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'));
+    if valid {
+        Ok(())
+    } else {
+        Err(CryptoError::Serialization(format!(
+            "invalid record name {name:?}"
+        )))
+    }
+}
+
+fn unusable(key_id: &str, code: ErrorCode) -> CryptoError {
+    CryptoError::KeyUnusable {
+        key_id: key_id.to_string(),
+        code,
+    }
+}
+
+fn new_key_id() -> CryptoResult<String> {
+    let mut bytes = [0u8; 16];
+    getrandom::fill(&mut bytes).map_err(|e| CryptoError::Rng(e.to_string()))?;
+    Ok(bytes.iter().map(|b| format!("{b:02x}")).collect())
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+#[cfg(all(test, feature = "mlkem", feature = "mldsa"))]
+mod tests {
+    use super::*;
+    use crate::mldsa::{MlDsaSignature, MlDsaVerifier};
+    use crate::mlkem::MlKemKeyPair;
+    use quantun_types::{MlDsaVariant, MlKemVariant};
+    use std::sync::Arc;
+
+    const MLDSA65: Algorithm = Algorithm::MlDsa(MlDsaVariant::MlDsa65);
+    const MLKEM768: Algorithm = Algorithm::MlKem(MlKemVariant::MlKem768);
+
+    #[test]
+    fn concurrent_signs_on_one_handle() {
+        let store = Arc::new(KeyStore::new());
+        let handle = store.create(MLDSA65, [KeyUsage::Sign]).unwrap();
+        let verifier = MlDsaVerifier {
+            variant: MlDsaVariant::MlDsa65,
+            public_key: store.get_public(&handle).unwrap(),
+        };
+
+        let threads: Vec<_> = (0..4)
+            .map(|i| {
+                let store = store.clone();
+                let handle = handle.clone();
+                std::thread::spawn(move || {
+                    let msg = format!("message {i}");
+                    (msg.clone(), store.sign(&handle, msg.as_bytes()).unwrap())
+                })
+            })
+            .collect();
+
+        for t in threads {
+            let (msg, signature) = t.join().unwrap();
+            let sig = MlDsaSignature {
+                signature,
+                variant: MlDsaVariant::MlDsa65,
+            };
+            assert!(verifier.verify(msg.as_bytes(), &sig).unwrap());
+        }
+    }
+
+    #[test]
+    fn kem_key_cannot_sign() {
+        let store = KeyStore::new();
+        let handle = store.create(MLKEM768, [KeyUsage::KeyAgreement]).unwrap();
+        let err = store.sign(&handle, b"msg").unwrap_err();
+        assert_eq!(err.error_code(), ErrorCode::PermissionDenied);
+    }
+
+    #[test]
+    fn signing_key_cannot_decapsulate() {
+        let store = KeyStore::new();
+        let handle = store.create(MLDSA65, [KeyUsage::Sign]).unwrap();
+        let err = store.decapsulate(&handle, &[0u8; 1088]).unwrap_err();
+        assert_eq!(err.error_code(), ErrorCode::PermissionDenied);
+    }
+
+    #[test]
+    fn decapsulate_imported_kem_key() {
+        let store = KeyStore::new();
+        let kp = MlKemKeyPair::generate(MlKemVariant::MlKem768).unwrap();
+        let enc = kp.encapsulate().unwrap();
+        let handle = store
+            .import(KeyPair::MlKem(kp), [KeyUsage::KeyAgreement])
+            .unwrap();
+        assert_eq!(
+            store.decapsulate(&handle, &enc.ciphertext).unwrap(),
+            enc.shared_secret
+        );
+    }
+
+    #[test]
+    fn revoked_key_operations_fail() {
+        let store = KeyStore::new();
+        let handle = store.create(MLDSA65, [KeyUsage::Sign]).unwrap();
+        store.revoke(&handle).unwrap();
+
+        let err = store.sign(&handle, b"msg").unwrap_err();
+        assert_eq!(err.error_code(), ErrorCode::KeyRevoked);
+        assert_eq!(err.error_code().as_str(), "KEY_REVOKED");
+        let err = store.export_encrypted(&handle, &[7u8; 32]).unwrap_err();
+        assert_eq!(err.error_code(), ErrorCode::KeyRevoked);
+
+        assert!(store.get_public(&handle).is_ok());
+        assert_eq!(store.metadata(&handle).unwrap().state, KeyState::Deactivated);
+    }
+
+    #[derive(Default)]
+    struct RecordingSink(std::sync::Mutex<Vec<KeyUsageEvent>>);
+
+    impl AuditSink for RecordingSink {
+        fn record(&self, event: &KeyUsageEvent) {
+            self.0.lock().unwrap().push(event.clone());
+        }
+    }
+
+    #[test]
+    fn key_usage_is_audited() {
+        let sink = Arc::new(RecordingSink::default());
+        let store = KeyStore::new().with_audit_sink(sink.clone());
+        let signing = store.create(MLDSA65, [KeyUsage::Sign]).unwrap();
+        let kem = store.create(MLKEM768, [KeyUsage::KeyAgreement]).unwrap();
+        let caller = Caller {
+            request_id: Some("req-1".into()),
+            api_key_id: Some("k1".into()),
+            tenant_id: None,
+        };
+
+        store.sign_as(&caller, &signing, b"secret message").unwrap();
+        store.sign(&kem, b"msg").unwrap_err();
+        store.export_encrypted(&signing, &[7u8; 32]).unwrap();
+
+        let events = store.recent_usage(&signing, 10);
+        assert_eq!(events.len(), 2);
+        let sign = &events[0];
+        assert_eq!(sign.operation, KeyOperation::Sign);
+        assert_eq!(sign.caller, caller);
+        assert!(sign.success);
+        assert_eq!(sign.input_len, 14);
+        assert_eq!(sign.input_sha256.as_ref().unwrap().len(), 64);
+        let export = &events[1];
+        assert_eq!(export.operation, KeyOperation::Export);
+        assert!(export.input_sha256.is_none());
+
+        let failed = store.recent_usage(&kem, 10);
+        assert_eq!(failed.len(), 1);
+        assert!(!failed[0].success);
+        assert!(failed[0]
+            .error
+            .as_ref()
+            .unwrap()
+            .contains(ErrorCode::PermissionDenied.as_str()));
+
+        // The sink sees the same events; none carry the message.
+        let recorded = sink.0.lock().unwrap();
+        assert_eq!(recorded.len(), 3);
+        assert!(!format!("{recorded:?}").contains("secret message"));
+        assert_eq!(store.recent_usage(&signing, 1), [events[1].clone()]);
+    }
+
+    #[test]
+    fn pending_key_activates() {
+        let store = KeyStore::new();
+        let handle = store.create_pending(MLDSA65, [KeyUsage::Sign]).unwrap();
+        assert!(store.get_public(&handle).is_ok());
+        let err = store.sign(&handle, b"msg").unwrap_err();
+        assert_eq!(err.error_code(), ErrorCode::PermissionDenied);
+
+        store.activate(&handle).unwrap();
+        assert!(store.sign(&handle, b"msg").is_ok());
+
+        store.mark_compromised(&handle).unwrap();
+        assert_eq!(store.metadata(&handle).unwrap().state, KeyState::Compromised);
+        assert!(store.activate(&handle).is_err());
+    }
+
+    #[test]
+    fn unknown_handle_is_not_found() {
+        let store = KeyStore::new();
+        let err = store.get_public(&KeyHandle("missing".into())).unwrap_err();
+        assert_eq!(err.error_code(), ErrorCode::NotFound);
+    }
+
+    #[test]
+    fn list_and_export() {
+        let store = KeyStore::new();
+        let sign = store.create(MLDSA65, [KeyUsage::Sign]).unwrap();
+        store.create(MLKEM768, [KeyUsage::KeyAgreement]).unwrap();
+
+        let listed = store.list();
+        assert_eq!(listed.len(), 2);
+        assert!(listed.iter().all(|m| m.state == KeyState::Active));
+
+        let kek = [7u8; 32];
+        let exported = store.export_encrypted(&sign, &kek).unwrap();
+        let seed = unseal(&kek, sign.key_id(), &exported).unwrap();
+        assert!(unseal(&[8u8; 32], sign.key_id(), &exported).is_err());
+        assert_eq!(seed.len(), MlDsaVariant::MlDsa65.seed_size());
+    }
+}
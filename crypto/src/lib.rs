@@ -1,8 +1,65 @@
+//! Post-quantum cryptographic primitives for QSGW.
+//!
+//! Each algorithm family sits behind a Cargo feature, all enabled by
+//! default: `mlkem`, `mldsa`, `slhdsa`, and `hybrid` (which implies
+//! `mlkem`). Constrained builds can opt out of the families they don't
+//! use, e.g. an ML-KEM/ML-DSA-only build without SLH-DSA:
+//!
+//! ```toml
+//! quantun-crypto = { path = "../crypto", default-features = false, features = ["mlkem", "mldsa"] }
+//! ```
+//!
+//! [`keypair::KeyPair::generate`] dispatches on [`quantun_types::Algorithm`]
+//! and returns [`CryptoError::UnsupportedAlgorithm`] for disabled families.
+
+#[cfg(any(feature = "mldsa", feature = "slhdsa"))]
+mod attached;
+pub mod commitment;
+#[cfg(all(feature = "mlkem", feature = "mldsa"))]
+pub mod derive;
+#[cfg(feature = "hybrid")]
+pub mod envelope;
 pub mod error;
+#[cfg(feature = "hybrid")]
 pub mod hybrid;
+pub mod keypair;
+pub mod keystore;
+#[cfg(feature = "mldsa")]
 pub mod mldsa;
+#[cfg(feature = "mlkem")]
 pub mod mlkem;
+pub mod nonce;
 mod rng;
+pub mod secure;
+pub mod self_test;
+pub mod signer;
+#[cfg(feature = "slhdsa")]
 pub mod slhdsa;
+pub mod symmetric;
+#[cfg(any(test, feature = "testing"))]
+pub mod testing;
+#[cfg(any(feature = "mlkem", feature = "mldsa", feature = "slhdsa"))]
+pub mod test_vectors;
+#[cfg(feature = "mldsa")]
+pub mod zkp;
 
 pub use error::{CryptoError, CryptoResult};
+pub use keypair::KeyPair;
+pub use keystore::{KeyHandle, KeyStore};
+pub use secure::SecureBytes;
+pub use self_test::{power_on_self_test, SelfTestReport};
+pub use signer::RemoteSigner;
+
+/// The algorithm families compiled into this build, by feature name:
+/// `mlkem`, `mldsa`, `slhdsa` and `hybrid`.
+pub fn enabled_features() -> Vec<&'static str> {
+    [
+        ("mlkem", cfg!(feature = "mlkem")),
+        ("mldsa", cfg!(feature = "mldsa")),
+        ("slhdsa", cfg!(feature = "slhdsa")),
+        ("hybrid", cfg!(feature = "hybrid")),
+    ]
+    .into_iter()
+    .filter_map(|(name, enabled)| enabled.then_some(name))
+    .collect()
+}
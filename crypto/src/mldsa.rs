@@ -1,8 +1,10 @@
+use crate::attached;
 use crate::error::{CryptoError, CryptoResult};
-use ml_dsa::KeyGen;
+use crate::secure::SecureBytes;
 use quantun_types::MlDsaVariant;
 use serde::{Deserialize, Serialize};
-use signature::{Signer, Verifier};
+use signature::{Keypair, Signer, Verifier};
+use subtle::{Choice, ConstantTimeEq};
 use zeroize::Zeroize;
 
 /// ML-DSA key pair (FIPS 204).
@@ -34,6 +36,25 @@ impl Drop for MlDsaKeyPair {
     }
 }
 
+/// An ML-DSA signing key without its public half.
+///
+/// Intended for HSM / secure-enclave deployments where the public key is
+/// distributed separately. Only the 32-byte seed is held, in a zeroizing
+/// buffer; the type deliberately does not implement `Serialize`.
+#[derive(Debug, Clone)]
+pub struct MlDsaSigningKey {
+    variant: MlDsaVariant,
+    secret_key: SecureBytes,
+}
+
+/// The public half of an ML-DSA key pair, used for verification only.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MlDsaVerifier {
+    pub variant: MlDsaVariant,
+    /// Serialized verifying (public) key bytes.
+    pub public_key: Vec<u8>,
+}
+
 /// An ML-DSA signature.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MlDsaSignature {
@@ -48,20 +69,14 @@ impl MlDsaKeyPair {
         let mut seed = [0u8; 32];
         getrandom::fill(&mut seed).expect("OS entropy source unavailable — cannot proceed safely");
 
-        match variant {
-            MlDsaVariant::MlDsa44 => {
-                let kp = ml_dsa::MlDsa44::from_seed(&seed.into());
-                Ok(make_keypair::<ml_dsa::MlDsa44>(variant, &kp))
-            }
-            MlDsaVariant::MlDsa65 => {
-                let kp = ml_dsa::MlDsa65::from_seed(&seed.into());
-                Ok(make_keypair::<ml_dsa::MlDsa65>(variant, &kp))
-            }
-            MlDsaVariant::MlDsa87 => {
-                let kp = ml_dsa::MlDsa87::from_seed(&seed.into());
-                Ok(make_keypair::<ml_dsa::MlDsa87>(variant, &kp))
-            }
-        }
+        let kp = keypair_from_seed(variant, &seed);
+        seed.zeroize();
+        Ok(kp)
+    }
+
+    /// Deterministically derive a key pair from a 32-byte seed.
+    pub fn from_seed(variant: MlDsaVariant, seed: &[u8; 32]) -> Self {
+        keypair_from_seed(variant, seed)
     }
 
     /// Generate with a caller-supplied RNG. Delegates to OS RNG for PQC safety.
@@ -83,25 +98,165 @@ impl MlDsaKeyPair {
 
     /// Verify a signature against a message.
     pub fn verify(&self, message: &[u8], sig: &MlDsaSignature) -> CryptoResult<bool> {
-        if sig.variant != self.variant {
-            return Err(CryptoError::Verification(format!(
-                "variant mismatch: key is {}, signature is {}",
-                self.variant, sig.variant
-            )));
+        verify_variant(self.variant, &self.public_key, message, sig)
+    }
+
+    /// Verify a signature without telling a signature of another variant
+    /// apart from an invalid one: both give `false`. The variants are
+    /// compared in constant time and the signature is checked either way.
+    pub fn verify_ct(&self, message: &[u8], sig: &MlDsaSignature) -> bool {
+        let same_variant = (self.variant as u8).ct_eq(&(sig.variant as u8));
+        let valid =
+            verify_bytes(self.variant, &self.public_key, message, &sig.signature).unwrap_or(false);
+        (same_variant & Choice::from(u8::from(valid))).into()
+    }
+
+    /// Sign `message` and return `u32_be(signature_len) || signature ||
+    /// message`.
+    pub fn sign_attached(&self, message: &[u8]) -> CryptoResult<Vec<u8>> {
+        let sig = self.sign(message)?;
+        Ok(attached::attach(&sig.signature, message))
+    }
+
+    /// Verify a blob from [`MlDsaKeyPair::sign_attached`] and return its
+    /// message.
+    pub fn verify_attached(&self, blob: &[u8]) -> CryptoResult<Vec<u8>> {
+        verify_attached_variant(self.variant, &self.public_key, blob)
+    }
+
+    /// Split off the secret half as a signing-only key.
+    pub fn to_signing_key(&self) -> MlDsaSigningKey {
+        MlDsaSigningKey {
+            variant: self.variant,
+            secret_key: SecureBytes::from_slice(&self.secret_key),
+        }
+    }
+
+    /// Split off the public half as a verification-only key.
+    pub fn to_verifier(&self) -> MlDsaVerifier {
+        MlDsaVerifier {
+            variant: self.variant,
+            public_key: self.public_key.clone(),
         }
+    }
+}
+
+impl MlDsaSigningKey {
+    pub fn variant(&self) -> MlDsaVariant {
+        self.variant
+    }
 
+    /// Sign a message.
+    pub fn sign(&self, message: &[u8]) -> CryptoResult<MlDsaSignature> {
+        let sk = self.secret_key.as_bytes();
         match self.variant {
-            MlDsaVariant::MlDsa44 => verify_impl::<ml_dsa::MlDsa44>(&self.public_key, message, &sig.signature),
-            MlDsaVariant::MlDsa65 => verify_impl::<ml_dsa::MlDsa65>(&self.public_key, message, &sig.signature),
-            MlDsaVariant::MlDsa87 => verify_impl::<ml_dsa::MlDsa87>(&self.public_key, message, &sig.signature),
+            MlDsaVariant::MlDsa44 => sign_impl::<ml_dsa::MlDsa44>(sk, message, self.variant),
+            MlDsaVariant::MlDsa65 => sign_impl::<ml_dsa::MlDsa65>(sk, message, self.variant),
+            MlDsaVariant::MlDsa87 => sign_impl::<ml_dsa::MlDsa87>(sk, message, self.variant),
+        }
+    }
+
+    /// Re-derive the encoded public key from the seed.
+    pub fn public_key_bytes(&self) -> CryptoResult<Vec<u8>> {
+        let mut seed: [u8; 32] = self.secret_key.as_bytes().try_into().map_err(|_| {
+            CryptoError::InvalidKeyMaterial(format!(
+                "invalid ML-DSA seed ({} bytes, expected 32)",
+                self.secret_key.len()
+            ))
+        })?;
+        let kp = keypair_from_seed(self.variant, &seed);
+        seed.zeroize();
+        Ok(kp.public_key.clone())
+    }
+}
+
+impl MlDsaVerifier {
+    /// Verify a signature against a message.
+    pub fn verify(&self, message: &[u8], sig: &MlDsaSignature) -> CryptoResult<bool> {
+        verify_variant(self.variant, &self.public_key, message, sig)
+    }
+
+    /// Verify a blob from [`MlDsaKeyPair::sign_attached`] and return its
+    /// message.
+    pub fn verify_attached(&self, blob: &[u8]) -> CryptoResult<Vec<u8>> {
+        verify_attached_variant(self.variant, &self.public_key, blob)
+    }
+}
+
+/// Deterministically derive a key pair from a 32-byte seed.
+fn keypair_from_seed(variant: MlDsaVariant, seed: &[u8; 32]) -> MlDsaKeyPair {
+    match variant {
+        MlDsaVariant::MlDsa44 => {
+            let kp = ml_dsa::SigningKey::<ml_dsa::MlDsa44>::from_seed(&(*seed).into());
+            make_keypair::<ml_dsa::MlDsa44>(variant, &kp)
+        }
+        MlDsaVariant::MlDsa65 => {
+            let kp = ml_dsa::SigningKey::<ml_dsa::MlDsa65>::from_seed(&(*seed).into());
+            make_keypair::<ml_dsa::MlDsa65>(variant, &kp)
         }
+        MlDsaVariant::MlDsa87 => {
+            let kp = ml_dsa::SigningKey::<ml_dsa::MlDsa87>::from_seed(&(*seed).into());
+            make_keypair::<ml_dsa::MlDsa87>(variant, &kp)
+        }
+    }
+}
+
+/// Check the signature variant against the key and dispatch verification.
+fn verify_variant(
+    variant: MlDsaVariant,
+    public_key: &[u8],
+    message: &[u8],
+    sig: &MlDsaSignature,
+) -> CryptoResult<bool> {
+    if sig.variant != variant {
+        return Err(CryptoError::Verification(format!(
+            "variant mismatch: key is {}, signature is {}",
+            variant, sig.variant
+        )));
+    }
+    verify_bytes(variant, public_key, message, &sig.signature)
+}
+
+/// Verify `signature` bytes as a `variant` signature.
+fn verify_bytes(
+    variant: MlDsaVariant,
+    public_key: &[u8],
+    message: &[u8],
+    signature: &[u8],
+) -> CryptoResult<bool> {
+    variant
+        .validate_public_key(public_key)
+        .and_then(|()| variant.validate_signature(signature))
+        .map_err(|e| CryptoError::Verification(e.to_string()))?;
+
+    match variant {
+        MlDsaVariant::MlDsa44 => verify_impl::<ml_dsa::MlDsa44>(public_key, message, signature),
+        MlDsaVariant::MlDsa65 => verify_impl::<ml_dsa::MlDsa65>(public_key, message, signature),
+        MlDsaVariant::MlDsa87 => verify_impl::<ml_dsa::MlDsa87>(public_key, message, signature),
+    }
+}
+
+/// Verify an attached signature blob and return its message.
+fn verify_attached_variant(
+    variant: MlDsaVariant,
+    public_key: &[u8],
+    blob: &[u8],
+) -> CryptoResult<Vec<u8>> {
+    let (signature, message) = attached::detach(blob)?;
+    let sig = MlDsaSignature {
+        signature: signature.to_vec(),
+        variant,
+    };
+    if !verify_variant(variant, public_key, message, &sig)? {
+        return Err(attached::mismatch());
     }
+    Ok(message.to_vec())
 }
 
 /// Helper to build MlDsaKeyPair from a typed KeyPair.
 fn make_keypair<P: ml_dsa::MlDsaParams>(
     variant: MlDsaVariant,
-    kp: &ml_dsa::KeyPair<P>,
+    kp: &ml_dsa::SigningKey<P>,
 ) -> MlDsaKeyPair {
     let public_key = kp.verifying_key().encode().to_vec();
     // Store the seed (32 bytes) for deterministic re-derivation
@@ -130,6 +285,9 @@ fn sign_impl<P: ml_dsa::MlDsaParams>(
 where
     ml_dsa::SigningKey<P>: Signer<ml_dsa::Signature<P>>,
 {
+    variant
+        .validate_seed(seed_bytes)
+        .map_err(|e| CryptoError::Signing(e.to_string()))?;
     let mut seed: [u8; 32] = seed_bytes.try_into().map_err(|_| {
         CryptoError::Signing(format!(
             "invalid ML-DSA seed ({} bytes, expected 32)",
@@ -182,6 +340,26 @@ where
 mod tests {
     use super::*;
 
+    #[test]
+    fn attached_signature_round_trip() {
+        let kp = MlDsaKeyPair::generate(MlDsaVariant::MlDsa44).unwrap();
+        let blob = kp.sign_attached(b"attached message").unwrap();
+        assert_eq!(kp.verify_attached(&blob).unwrap(), b"attached message");
+        assert_eq!(
+            kp.to_verifier().verify_attached(&blob).unwrap(),
+            b"attached message"
+        );
+
+        let mut tampered = blob.clone();
+        *tampered.last_mut().unwrap() ^= 1;
+        assert!(matches!(
+            kp.verify_attached(&tampered),
+            Err(CryptoError::Verification(_))
+        ));
+        assert!(kp.verify_attached(&blob[..3]).is_err());
+        assert!(kp.verify_attached(&blob[..100]).is_err());
+    }
+
     #[test]
     fn keygen_produces_keys() {
         for variant in [
@@ -230,6 +408,23 @@ mod tests {
         assert_eq!(sig.signature.len(), MlDsaVariant::MlDsa87.signature_size());
     }
 
+    #[test]
+    fn signing_key_signature_verifies_against_verifier() {
+        let kp = MlDsaKeyPair::generate(MlDsaVariant::MlDsa65).unwrap();
+        let signer = kp.to_signing_key();
+        let verifier = kp.to_verifier();
+        let sig = signer.sign(b"hsm-backed message").unwrap();
+        assert!(verifier.verify(b"hsm-backed message", &sig).unwrap());
+        assert!(!verifier.verify(b"other message", &sig).unwrap());
+    }
+
+    #[test]
+    fn signing_key_rederives_public_key() {
+        let kp = MlDsaKeyPair::generate(MlDsaVariant::MlDsa44).unwrap();
+        let signer = kp.to_signing_key();
+        assert_eq!(signer.public_key_bytes().unwrap(), kp.public_key);
+    }
+
     #[test]
     fn variant_mismatch_errors() {
         let kp44 = MlDsaKeyPair::generate(MlDsaVariant::MlDsa44).unwrap();
@@ -239,4 +434,36 @@ mod tests {
         };
         assert!(kp44.verify(b"test", &sig65).is_err());
     }
+
+    #[test]
+    fn verify_ct_rejects_wrong_variant_and_tampered_signature() {
+        let kp = MlDsaKeyPair::generate(MlDsaVariant::MlDsa44).unwrap();
+        let sig = kp.sign(b"test").unwrap();
+        assert!(kp.verify_ct(b"test", &sig));
+
+        let relabelled = MlDsaSignature {
+            signature: sig.signature.clone(),
+            variant: MlDsaVariant::MlDsa65,
+        };
+        assert!(!kp.verify_ct(b"test", &relabelled));
+
+        let mut tampered = sig.clone();
+        tampered.signature[0] ^= 1;
+        assert!(!kp.verify_ct(b"test", &tampered));
+        assert!(!kp.verify_ct(b"other", &sig));
+    }
+
+    #[test]
+    fn truncated_signature_reports_expected_size() {
+        let kp = MlDsaKeyPair::generate(MlDsaVariant::MlDsa44).unwrap();
+        let sig = MlDsaSignature {
+            signature: vec![0u8; 100],
+            variant: MlDsaVariant::MlDsa44,
+        };
+        let err = kp.verify(b"test", &sig).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "verification failed: invalid ML-DSA-44 signature: expected 2420 bytes, got 100"
+        );
+    }
 }
@@ -2,8 +2,16 @@ use crate::error::{CryptoError, CryptoResult};
 use ml_kem::{Decapsulate, Encapsulate, Kem, KeyExport, KeyInit, TryKeyInit};
 use quantun_types::MlKemVariant;
 use serde::{Deserialize, Serialize};
+use spki::der::asn1::BitStringRef;
+use spki::der::Encode;
+use spki::{AlgorithmIdentifierRef, ObjectIdentifier, SubjectPublicKeyInfoRef};
 use zeroize::Zeroize;
 
+/// `id-alg-ml-kem-512`, `-768` and `-1024` from the NIST algorithm registry.
+const OID_ML_KEM_512: ObjectIdentifier = ObjectIdentifier::new_unwrap("2.16.840.1.101.3.4.4.1");
+const OID_ML_KEM_768: ObjectIdentifier = ObjectIdentifier::new_unwrap("2.16.840.1.101.3.4.4.2");
+const OID_ML_KEM_1024: ObjectIdentifier = ObjectIdentifier::new_unwrap("2.16.840.1.101.3.4.4.3");
+
 /// ML-KEM key pair (FIPS 203).
 ///
 /// Uses the `ml-kem` crate (RustCrypto) for a standards-compliant
@@ -64,6 +72,34 @@ impl MlKemKeyPair {
         }
     }
 
+    /// Deterministically derive a key pair from a 64-byte seed (`d || z`).
+    pub fn from_seed(variant: MlKemVariant, seed: &[u8; 64]) -> CryptoResult<Self> {
+        let invalid_seed = |_| CryptoError::KeyGeneration {
+            algorithm: variant.to_string(),
+            reason: "invalid 64-byte seed".into(),
+        };
+        match variant {
+            MlKemVariant::MlKem512 => {
+                let dk = ml_kem::DecapsulationKey::<ml_kem::MlKem512>::new_from_slice(seed)
+                    .map_err(invalid_seed)?;
+                let ek = dk.encapsulation_key();
+                Ok(make_keypair(variant, ek.to_bytes().to_vec(), dk.to_bytes().to_vec()))
+            }
+            MlKemVariant::MlKem768 => {
+                let dk = ml_kem::DecapsulationKey::<ml_kem::MlKem768>::new_from_slice(seed)
+                    .map_err(invalid_seed)?;
+                let ek = dk.encapsulation_key();
+                Ok(make_keypair(variant, ek.to_bytes().to_vec(), dk.to_bytes().to_vec()))
+            }
+            MlKemVariant::MlKem1024 => {
+                let dk = ml_kem::DecapsulationKey::<ml_kem::MlKem1024>::new_from_slice(seed)
+                    .map_err(invalid_seed)?;
+                let ek = dk.encapsulation_key();
+                Ok(make_keypair(variant, ek.to_bytes().to_vec(), dk.to_bytes().to_vec()))
+            }
+        }
+    }
+
     /// Generate a key pair using a caller-supplied RNG.
     /// Delegates to OS RNG for cryptographic safety with PQC crates.
     pub fn generate_with_rng<R: rand::RngCore>(
@@ -73,62 +109,49 @@ impl MlKemKeyPair {
         Self::generate(variant)
     }
 
+    /// The public half of the key pair.
+    pub fn encapsulation_key(&self) -> MlKemEncapsulationKey {
+        MlKemEncapsulationKey {
+            variant: self.variant,
+            bytes: self.public_key.clone(),
+        }
+    }
+
+    /// The public key as DER SubjectPublicKeyInfo; see
+    /// [`MlKemEncapsulationKey::to_spki_der`].
+    pub fn export_public_key_spki(&self) -> CryptoResult<Vec<u8>> {
+        self.encapsulation_key().to_spki_der()
+    }
+
     /// Encapsulate: produce a ciphertext and shared secret from a public key.
     pub fn encapsulate(&self) -> CryptoResult<MlKemEncapsulated> {
-        match self.variant {
-            MlKemVariant::MlKem512 => {
-                let ek = ml_kem::EncapsulationKey::<ml_kem::MlKem512>::new_from_slice(
-                    &self.public_key,
-                )
-                .map_err(|_| {
-                    CryptoError::Encapsulation(format!(
-                        "invalid ML-KEM-512 encapsulation key ({} bytes)",
-                        self.public_key.len()
-                    ))
-                })?;
-                let (ct, ss) = ek.encapsulate_with_rng(&mut crate::rng::PqcRng);
-                Ok(MlKemEncapsulated {
-                    ciphertext: ct.to_vec(),
-                    shared_secret: ss.to_vec(),
-                })
-            }
-            MlKemVariant::MlKem768 => {
-                let ek = ml_kem::EncapsulationKey::<ml_kem::MlKem768>::new_from_slice(
-                    &self.public_key,
-                )
-                .map_err(|_| {
-                    CryptoError::Encapsulation(format!(
-                        "invalid ML-KEM-768 encapsulation key ({} bytes)",
-                        self.public_key.len()
-                    ))
-                })?;
-                let (ct, ss) = ek.encapsulate_with_rng(&mut crate::rng::PqcRng);
-                Ok(MlKemEncapsulated {
-                    ciphertext: ct.to_vec(),
-                    shared_secret: ss.to_vec(),
-                })
-            }
-            MlKemVariant::MlKem1024 => {
-                let ek = ml_kem::EncapsulationKey::<ml_kem::MlKem1024>::new_from_slice(
-                    &self.public_key,
-                )
-                .map_err(|_| {
-                    CryptoError::Encapsulation(format!(
-                        "invalid ML-KEM-1024 encapsulation key ({} bytes)",
-                        self.public_key.len()
-                    ))
-                })?;
-                let (ct, ss) = ek.encapsulate_with_rng(&mut crate::rng::PqcRng);
-                Ok(MlKemEncapsulated {
-                    ciphertext: ct.to_vec(),
-                    shared_secret: ss.to_vec(),
-                })
-            }
-        }
+        encapsulate_to(self.variant, &self.public_key)
     }
 
     /// Decapsulate: recover the shared secret from a ciphertext using the secret key.
+    ///
+    /// Fails if the key or ciphertext has the wrong size. A ciphertext of
+    /// the right size always decapsulates, to the implicit-rejection secret
+    /// if it was not produced for this key; see
+    /// [`decapsulate_implicit`](Self::decapsulate_implicit).
     pub fn decapsulate(&self, ciphertext: &[u8]) -> CryptoResult<Vec<u8>> {
+        self.decapsulate_implicit(ciphertext)
+    }
+
+    /// Decapsulate with FIPS 203 implicit rejection.
+    ///
+    /// A well-sized ciphertext that fails the re-encryption check is not an
+    /// error: the result is the pseudo-random secret `J(z || c)`, which the
+    /// sender cannot know, so the failure surfaces later as a key mismatch
+    /// (e.g. an AEAD tag failure) rather than as a distinguishable error.
+    /// Only a wrong-sized key or ciphertext is reported, as
+    /// [`CryptoError::Decapsulation`].
+    pub fn decapsulate_implicit(&self, ciphertext: &[u8]) -> CryptoResult<Vec<u8>> {
+        self.variant
+            .validate_secret_key(&self.secret_key)
+            .and_then(|()| self.variant.validate_ciphertext(ciphertext))
+            .map_err(|e| CryptoError::Decapsulation(e.to_string()))?;
+
         match self.variant {
             MlKemVariant::MlKem512 => {
                 let dk = ml_kem::DecapsulationKey::<ml_kem::MlKem512>::new_from_slice(
@@ -188,6 +211,231 @@ impl MlKemKeyPair {
     }
 }
 
+/// An ML-KEM encapsulation (public) key and its parameter set.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MlKemEncapsulationKey {
+    pub variant: MlKemVariant,
+    pub bytes: Vec<u8>,
+}
+
+impl MlKemEncapsulationKey {
+    /// The key `bytes` for `variant`, checked against `variant.key_sizes()`.
+    pub fn new(variant: MlKemVariant, bytes: Vec<u8>) -> CryptoResult<Self> {
+        variant
+            .validate_public_key(&bytes)
+            .map_err(|e| CryptoError::InvalidKeyMaterial(e.to_string()))?;
+        Ok(Self { variant, bytes })
+    }
+
+    /// DER SubjectPublicKeyInfo with the variant's OID, no parameters, and
+    /// the raw key as the BIT STRING.
+    pub fn to_spki_der(&self) -> CryptoResult<Vec<u8>> {
+        let spki = SubjectPublicKeyInfoRef {
+            algorithm: AlgorithmIdentifierRef {
+                oid: oid(self.variant),
+                parameters: None,
+            },
+            subject_public_key: BitStringRef::from_bytes(&self.bytes)
+                .map_err(|e| CryptoError::Serialization(e.to_string()))?,
+        };
+        spki.to_der()
+            .map_err(|e| CryptoError::Serialization(e.to_string()))
+    }
+
+    /// Parse a key written by [`MlKemEncapsulationKey::to_spki_der`]. The
+    /// variant is taken from the OID, and the key length must match it.
+    pub fn from_spki_der(der: &[u8]) -> CryptoResult<Self> {
+        let spki = SubjectPublicKeyInfoRef::try_from(der)
+            .map_err(|e| CryptoError::Serialization(e.to_string()))?;
+        let oid_of = spki.algorithm.oid;
+        let variant = [
+            MlKemVariant::MlKem512,
+            MlKemVariant::MlKem768,
+            MlKemVariant::MlKem1024,
+        ]
+        .into_iter()
+        .find(|&v| oid(v) == oid_of)
+        .ok_or_else(|| CryptoError::InvalidKeyMaterial(format!("{oid_of} is not an ML-KEM OID")))?;
+        if spki.algorithm.parameters.is_some() {
+            return Err(CryptoError::InvalidKeyMaterial(format!(
+                "{variant} key has algorithm parameters"
+            )));
+        }
+        let bytes = spki.subject_public_key.as_bytes().ok_or_else(|| {
+            CryptoError::Serialization("public key BIT STRING has unused bits".into())
+        })?;
+        Self::new(variant, bytes.to_vec())
+    }
+
+    /// [`encapsulate_to`] this key.
+    pub fn encapsulate(&self) -> CryptoResult<MlKemEncapsulated> {
+        encapsulate_to(self.variant, &self.bytes)
+    }
+}
+
+fn oid(variant: MlKemVariant) -> ObjectIdentifier {
+    match variant {
+        MlKemVariant::MlKem512 => OID_ML_KEM_512,
+        MlKemVariant::MlKem768 => OID_ML_KEM_768,
+        MlKemVariant::MlKem1024 => OID_ML_KEM_1024,
+    }
+}
+
+/// Encapsulate to a recipient's raw encapsulation (public) key, as the
+/// sending side of a KEM that holds only the recipient's public key.
+///
+/// The key length is checked against `variant.key_sizes()`.
+pub fn encapsulate_to(variant: MlKemVariant, public_key: &[u8]) -> CryptoResult<MlKemEncapsulated> {
+    variant
+        .validate_public_key(public_key)
+        .map_err(|e| CryptoError::Encapsulation(e.to_string()))?;
+
+    match variant {
+        MlKemVariant::MlKem512 => {
+            let ek = ml_kem::EncapsulationKey::<ml_kem::MlKem512>::new_from_slice(public_key)
+                .map_err(|_| {
+                    CryptoError::Encapsulation(format!(
+                        "invalid ML-KEM-512 encapsulation key ({} bytes)",
+                        public_key.len()
+                    ))
+                })?;
+            let (ct, ss) = ek.encapsulate_with_rng(&mut crate::rng::PqcRng);
+            Ok(MlKemEncapsulated {
+                ciphertext: ct.to_vec(),
+                shared_secret: ss.to_vec(),
+            })
+        }
+        MlKemVariant::MlKem768 => {
+            let ek = ml_kem::EncapsulationKey::<ml_kem::MlKem768>::new_from_slice(public_key)
+                .map_err(|_| {
+                    CryptoError::Encapsulation(format!(
+                        "invalid ML-KEM-768 encapsulation key ({} bytes)",
+                        public_key.len()
+                    ))
+                })?;
+            let (ct, ss) = ek.encapsulate_with_rng(&mut crate::rng::PqcRng);
+            Ok(MlKemEncapsulated {
+                ciphertext: ct.to_vec(),
+                shared_secret: ss.to_vec(),
+            })
+        }
+        MlKemVariant::MlKem1024 => {
+            let ek = ml_kem::EncapsulationKey::<ml_kem::MlKem1024>::new_from_slice(public_key)
+                .map_err(|_| {
+                    CryptoError::Encapsulation(format!(
+                        "invalid ML-KEM-1024 encapsulation key ({} bytes)",
+                        public_key.len()
+                    ))
+                })?;
+            let (ct, ss) = ek.encapsulate_with_rng(&mut crate::rng::PqcRng);
+            Ok(MlKemEncapsulated {
+                ciphertext: ct.to_vec(),
+                shared_secret: ss.to_vec(),
+            })
+        }
+    }
+}
+
+/// Encapsulate to `public_key` with the randomness `m` instead of the OS
+/// RNG, for known-answer tests. Reusing `m` outside of tests breaks the
+/// KEM.
+pub(crate) fn encapsulate_deterministic(
+    variant: MlKemVariant,
+    public_key: &[u8],
+    m: &[u8; 32],
+) -> CryptoResult<MlKemEncapsulated> {
+    variant
+        .validate_public_key(public_key)
+        .map_err(|e| CryptoError::Encapsulation(e.to_string()))?;
+    let invalid_key = |_| {
+        CryptoError::Encapsulation(format!(
+            "invalid {variant} encapsulation key ({} bytes)",
+            public_key.len()
+        ))
+    };
+    let m = (*m).into();
+    let (ct, ss) = match variant {
+        MlKemVariant::MlKem512 => {
+            let ek = ml_kem::EncapsulationKey::<ml_kem::MlKem512>::new_from_slice(public_key)
+                .map_err(invalid_key)?;
+            let (ct, ss) = ek.encapsulate_deterministic(&m);
+            (ct.to_vec(), ss.to_vec())
+        }
+        MlKemVariant::MlKem768 => {
+            let ek = ml_kem::EncapsulationKey::<ml_kem::MlKem768>::new_from_slice(public_key)
+                .map_err(invalid_key)?;
+            let (ct, ss) = ek.encapsulate_deterministic(&m);
+            (ct.to_vec(), ss.to_vec())
+        }
+        MlKemVariant::MlKem1024 => {
+            let ek = ml_kem::EncapsulationKey::<ml_kem::MlKem1024>::new_from_slice(public_key)
+                .map_err(invalid_key)?;
+            let (ct, ss) = ek.encapsulate_deterministic(&m);
+            (ct.to_vec(), ss.to_vec())
+        }
+    };
+    Ok(MlKemEncapsulated {
+        ciphertext: ct,
+        shared_secret: ss,
+    })
+}
+
+/// Decapsulate `ciphertext` with a FIPS 203 expanded decapsulation key
+/// (`dk_pke || ek || H(ek) || z`), the form known-answer vectors publish
+/// decapsulation keys in. Keys are otherwise always stored as seeds.
+#[allow(deprecated)]
+pub(crate) fn decapsulate_expanded(
+    variant: MlKemVariant,
+    expanded_key: &[u8],
+    ciphertext: &[u8],
+) -> CryptoResult<Vec<u8>> {
+    variant
+        .validate_ciphertext(ciphertext)
+        .map_err(|e| CryptoError::Decapsulation(e.to_string()))?;
+    let invalid_key = || {
+        CryptoError::Decapsulation(format!(
+            "invalid {variant} expanded decapsulation key ({} bytes)",
+            expanded_key.len()
+        ))
+    };
+    let invalid_ct = |_| {
+        CryptoError::Decapsulation(format!(
+            "{variant} decapsulation failed (ct {} bytes)",
+            ciphertext.len()
+        ))
+    };
+    let ss = match variant {
+        MlKemVariant::MlKem512 => {
+            let dk = ml_kem::ExpandedDecapsulationKey::<ml_kem::MlKem512>::try_from(expanded_key)
+                .map_err(|_| invalid_key())?;
+            let dk = ml_kem::DecapsulationKey::<ml_kem::MlKem512>::from_expanded(&dk)
+                .map_err(|_| invalid_key())?;
+            dk.decapsulate_slice(ciphertext)
+                .map_err(invalid_ct)?
+                .to_vec()
+        }
+        MlKemVariant::MlKem768 => {
+            let dk = ml_kem::ExpandedDecapsulationKey::<ml_kem::MlKem768>::try_from(expanded_key)
+                .map_err(|_| invalid_key())?;
+            let dk = ml_kem::DecapsulationKey::<ml_kem::MlKem768>::from_expanded(&dk)
+                .map_err(|_| invalid_key())?;
+            dk.decapsulate_slice(ciphertext)
+                .map_err(invalid_ct)?
+                .to_vec()
+        }
+        MlKemVariant::MlKem1024 => {
+            let dk = ml_kem::ExpandedDecapsulationKey::<ml_kem::MlKem1024>::try_from(expanded_key)
+                .map_err(|_| invalid_key())?;
+            let dk = ml_kem::DecapsulationKey::<ml_kem::MlKem1024>::from_expanded(&dk)
+                .map_err(|_| invalid_key())?;
+            dk.decapsulate_slice(ciphertext)
+                .map_err(invalid_ct)?
+                .to_vec()
+        }
+    };
+    Ok(ss)
+}
+
 /// Helper to log and construct a key pair from raw bytes.
 fn make_keypair(variant: MlKemVariant, public_key: Vec<u8>, secret_key: Vec<u8>) -> MlKemKeyPair {
     tracing::debug!(
@@ -220,6 +468,66 @@ mod tests {
         }
     }
 
+    #[test]
+    fn spki_round_trip_with_oid_prefix() {
+        for (variant, prefix) in [
+            (
+                MlKemVariant::MlKem512,
+                "30820332300b060960864801650304040103820321",
+            ),
+            (
+                MlKemVariant::MlKem768,
+                "308204b2300b0609608648016503040402038204a1",
+            ),
+            (
+                MlKemVariant::MlKem1024,
+                "30820632300b060960864801650304040303820621",
+            ),
+        ] {
+            let kp = MlKemKeyPair::generate(variant).unwrap();
+            let der = kp.export_public_key_spki().unwrap();
+            let hex: String = der.iter().map(|b| format!("{b:02x}")).collect();
+            assert!(
+                hex.starts_with(&format!("{prefix}00")),
+                "{variant}: {}",
+                &hex[..48]
+            );
+            assert_eq!(der.len(), 22 + kp.public_key.len());
+
+            let key = MlKemEncapsulationKey::from_spki_der(&der).unwrap();
+            assert_eq!(key, kp.encapsulation_key());
+            let enc = key.encapsulate().unwrap();
+            assert_eq!(kp.decapsulate(&enc.ciphertext).unwrap(), enc.shared_secret);
+        }
+    }
+
+    #[test]
+    fn spki_rejects_malformed_der_and_foreign_oids() {
+        let kp = MlKemKeyPair::generate(MlKemVariant::MlKem768).unwrap();
+        let der = kp.export_public_key_spki().unwrap();
+        assert!(matches!(
+            MlKemEncapsulationKey::from_spki_der(&der[..der.len() - 1]),
+            Err(CryptoError::Serialization(_))
+        ));
+
+        // id-alg-ml-dsa-65 in place of id-alg-ml-kem-768.
+        let mut foreign = der.clone();
+        foreign[15] = 0x03;
+        foreign[16] = 0x12;
+        assert!(matches!(
+            MlKemEncapsulationKey::from_spki_der(&foreign),
+            Err(CryptoError::InvalidKeyMaterial(_))
+        ));
+
+        // ML-KEM-512's OID on a 768 key.
+        let mut mismatched = der;
+        mismatched[16] = 0x01;
+        assert!(matches!(
+            MlKemEncapsulationKey::from_spki_der(&mismatched),
+            Err(CryptoError::InvalidKeyMaterial(_))
+        ));
+    }
+
     #[test]
     fn encapsulate_decapsulate_round_trip_512() {
         let kp = MlKemKeyPair::generate(MlKemVariant::MlKem512).unwrap();
@@ -244,6 +552,17 @@ mod tests {
         assert_eq!(enc.shared_secret, shared);
     }
 
+    #[test]
+    fn from_seed_is_deterministic() {
+        let seed = [7u8; 64];
+        let kp1 = MlKemKeyPair::from_seed(MlKemVariant::MlKem768, &seed).unwrap();
+        let kp2 = MlKemKeyPair::from_seed(MlKemVariant::MlKem768, &seed).unwrap();
+        assert_eq!(kp1.public_key, kp2.public_key);
+
+        let enc = kp1.encapsulate().unwrap();
+        assert_eq!(kp2.decapsulate(&enc.ciphertext).unwrap(), enc.shared_secret);
+    }
+
     #[test]
     fn decapsulate_wrong_ciphertext_fails() {
         let kp = MlKemKeyPair::generate(MlKemVariant::MlKem512).unwrap();
@@ -251,6 +570,57 @@ mod tests {
         assert!(result.is_err());
     }
 
+    #[test]
+    fn tampered_ciphertext_yields_implicit_rejection_secret() {
+        let kp = MlKemKeyPair::generate(MlKemVariant::MlKem768).unwrap();
+        let enc = kp.encapsulate().unwrap();
+        let mut tampered = enc.ciphertext.clone();
+        tampered[0] ^= 1;
+
+        let rejected = kp.decapsulate_implicit(&tampered).unwrap();
+        assert_eq!(rejected.len(), 32);
+        assert_ne!(rejected, enc.shared_secret);
+        // Deterministic in the key and ciphertext, as FIPS 203 requires.
+        assert_eq!(kp.decapsulate_implicit(&tampered).unwrap(), rejected);
+        assert_eq!(kp.decapsulate(&tampered).unwrap(), rejected);
+
+        assert_eq!(
+            kp.decapsulate_implicit(&enc.ciphertext).unwrap(),
+            enc.shared_secret
+        );
+        assert!(kp.decapsulate_implicit(&tampered[1..]).is_err());
+    }
+
+    #[test]
+    fn size_errors_report_expected_and_actual() {
+        let kp = MlKemKeyPair::generate(MlKemVariant::MlKem512).unwrap();
+        let err = kp.decapsulate(&[0u8; 10]).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "decapsulation failed: invalid ML-KEM-512 ciphertext: expected 768 bytes, got 10"
+        );
+
+        let mismatched = MlKemKeyPair {
+            variant: MlKemVariant::MlKem768,
+            public_key: kp.public_key.clone(),
+            secret_key: Vec::new(),
+        };
+        let err = mismatched.encapsulate().unwrap_err();
+        assert!(err.to_string().contains("expected 1184 bytes, got 800"), "{err}");
+    }
+
+    #[test]
+    fn encapsulate_to_external_public_key() {
+        let recipient = MlKemKeyPair::generate(MlKemVariant::MlKem768).unwrap();
+        let public_key = recipient.public_key.clone();
+
+        let enc = encapsulate_to(MlKemVariant::MlKem768, &public_key).unwrap();
+        assert_eq!(recipient.decapsulate(&enc.ciphertext).unwrap(), enc.shared_secret);
+
+        let err = encapsulate_to(MlKemVariant::MlKem1024, &public_key).unwrap_err();
+        assert!(err.to_string().contains("expected 1568 bytes, got 1184"), "{err}");
+    }
+
     #[test]
     fn different_keypairs_produce_different_shared_secrets() {
         let kp1 = MlKemKeyPair::generate(MlKemVariant::MlKem768).unwrap();
@@ -0,0 +1,95 @@
+//! Unique nonces for AES-GCM.
+//!
+//! Reusing a nonce under one AES-GCM key reveals the XOR of the plaintexts
+//! and lets an attacker forge tags. [`NonceManager`] hands out each 96-bit
+//! nonce at most once: a fixed 4-byte prefix followed by a 64-bit counter
+//! that never wraps. Use one manager per key.
+
+use signature::rand_core::TryRng;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use crate::error::{CryptoError, CryptoResult};
+use crate::rng::PqcRng;
+
+/// Length of an AES-GCM nonce, in bytes.
+pub const NONCE_LEN: usize = 12;
+
+/// Issues `base_nonce || counter` nonces, counting up from zero.
+#[derive(Debug)]
+pub struct NonceManager {
+    next_nonce: AtomicU64,
+    base_nonce: [u8; 4],
+}
+
+impl NonceManager {
+    /// A manager with a fixed prefix, e.g. one per sender sharing a key.
+    pub fn new(base_nonce: [u8; 4]) -> Self {
+        Self {
+            next_nonce: AtomicU64::new(0),
+            base_nonce,
+        }
+    }
+
+    /// A manager with a random prefix from the OS RNG.
+    pub fn new_random() -> CryptoResult<Self> {
+        let mut base_nonce = [0u8; 4];
+        let Ok(()) = PqcRng.try_fill_bytes(&mut base_nonce);
+        Ok(Self::new(base_nonce))
+    }
+
+    /// The next unused nonce.
+    ///
+    /// Fails with [`CryptoError::NonceExhausted`] once the counter reaches
+    /// `u64::MAX`, rather than wrapping to a nonce already issued.
+    pub fn next(&self) -> CryptoResult<[u8; NONCE_LEN]> {
+        let counter = self
+            .next_nonce
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |n| n.checked_add(1))
+            .map_err(|_| CryptoError::NonceExhausted)?;
+        let mut nonce = [0u8; NONCE_LEN];
+        nonce[..4].copy_from_slice(&self.base_nonce);
+        nonce[4..].copy_from_slice(&counter.to_be_bytes());
+        Ok(nonce)
+    }
+
+    /// How many nonces remain before the key must be replaced.
+    pub fn check_remaining(&self) -> u64 {
+        u64::MAX - self.next_nonce.load(Ordering::Relaxed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    #[test]
+    fn consecutive_nonces_are_distinct() {
+        let nonces = NonceManager::new([0xde, 0xad, 0xbe, 0xef]);
+        let issued: HashSet<_> = (0..10).map(|_| nonces.next().unwrap()).collect();
+        assert_eq!(issued.len(), 10);
+        assert!(issued.iter().all(|n| n[..4] == [0xde, 0xad, 0xbe, 0xef]));
+        assert!(issued.contains(&[0xde, 0xad, 0xbe, 0xef, 0, 0, 0, 0, 0, 0, 0, 9]));
+        assert_eq!(nonces.check_remaining(), u64::MAX - 10);
+    }
+
+    #[test]
+    fn counter_never_wraps() {
+        let nonces = NonceManager {
+            next_nonce: AtomicU64::new(u64::MAX - 1),
+            base_nonce: [0; 4],
+        };
+        assert_eq!(nonces.check_remaining(), 1);
+        assert_eq!(nonces.next().unwrap()[4..], (u64::MAX - 1).to_be_bytes());
+        assert_eq!(nonces.check_remaining(), 0);
+        assert!(matches!(nonces.next(), Err(CryptoError::NonceExhausted)));
+        assert!(matches!(nonces.next(), Err(CryptoError::NonceExhausted)));
+    }
+
+    #[test]
+    fn random_prefixes_differ() {
+        let a = NonceManager::new_random().unwrap();
+        let b = NonceManager::new_random().unwrap();
+        assert_ne!(a.next().unwrap(), b.next().unwrap());
+    }
+}
@@ -0,0 +1,84 @@
+use std::fmt;
+use subtle::{Choice, ConstantTimeEq};
+use zeroize::{Zeroize, ZeroizeOnDrop};
+
+/// Heap-allocated secret bytes that are zeroized when dropped.
+///
+/// Deliberately implements neither `Serialize` nor a revealing `Debug`, so
+/// wrapped key material cannot leak through JSON or logs.
+#[derive(Clone, Zeroize, ZeroizeOnDrop)]
+pub struct SecureBytes(Vec<u8>);
+
+impl SecureBytes {
+    /// Take ownership of `bytes`.
+    pub fn new(bytes: Vec<u8>) -> Self {
+        Self(bytes)
+    }
+
+    /// Copy `bytes` into a new secure buffer.
+    pub fn from_slice(bytes: &[u8]) -> Self {
+        Self(bytes.to_vec())
+    }
+
+    /// Borrow the secret bytes.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+impl From<Vec<u8>> for SecureBytes {
+    fn from(bytes: Vec<u8>) -> Self {
+        Self::new(bytes)
+    }
+}
+
+/// Compares contents in constant time; only the lengths may leak.
+impl ConstantTimeEq for SecureBytes {
+    fn ct_eq(&self, other: &Self) -> Choice {
+        self.0.as_slice().ct_eq(other.0.as_slice())
+    }
+}
+
+impl PartialEq for SecureBytes {
+    fn eq(&self, other: &Self) -> bool {
+        self.ct_eq(other).into()
+    }
+}
+
+impl Eq for SecureBytes {}
+
+impl fmt::Debug for SecureBytes {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "SecureBytes([REDACTED; {}])", self.0.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn debug_does_not_reveal_contents() {
+        let secret = SecureBytes::from_slice(&[0xAB; 4]);
+        let debug = format!("{secret:?}");
+        assert_eq!(debug, "SecureBytes([REDACTED; 4])");
+        assert!(!debug.contains("171"));
+    }
+
+    #[test]
+    fn equality_compares_contents_and_length() {
+        let secret = SecureBytes::from_slice(&[1, 2, 3]);
+        assert_eq!(secret, SecureBytes::from_slice(&[1, 2, 3]));
+        assert_ne!(secret, SecureBytes::from_slice(&[1, 2, 4]));
+        assert_ne!(secret, SecureBytes::from_slice(&[1, 2]));
+        assert!(bool::from(secret.ct_eq(&SecureBytes::new(vec![1, 2, 3]))));
+    }
+}
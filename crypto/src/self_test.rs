@@ -0,0 +1,233 @@
+//! Power-on self tests.
+//!
+//! [`power_on_self_test`] checks that the entropy source produces
+//! plausible output and runs one round trip per algorithm family compiled
+//! into this build, from fixed seeds where the family allows it. A failing
+//! check, including one that panics, is recorded rather than propagated.
+
+use serde::{Deserialize, Serialize};
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+use crate::error::{CryptoError, CryptoResult};
+
+/// Where the RNG check draws its bytes from.
+pub trait EntropySource: Send + Sync {
+    fn fill(&self, dest: &mut [u8]) -> Result<(), String>;
+}
+
+/// The operating system CSPRNG, as used by every primitive in this crate.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct OsEntropy;
+
+impl EntropySource for OsEntropy {
+    fn fill(&self, dest: &mut [u8]) -> Result<(), String> {
+        getrandom::fill(dest).map_err(|e| e.to_string())
+    }
+}
+
+/// The outcome of one check.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SelfTestCheck {
+    /// `RNG`, or the algorithm exercised, e.g. `ML-KEM-768`.
+    pub name: String,
+    pub passed: bool,
+    pub duration_us: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SelfTestReport {
+    /// Unix seconds at which the run started.
+    pub run_at: u64,
+    pub checks: Vec<SelfTestCheck>,
+}
+
+impl SelfTestReport {
+    pub fn passed(&self) -> bool {
+        self.checks.iter().all(|c| c.passed)
+    }
+
+    pub fn failures(&self) -> impl Iterator<Item = &SelfTestCheck> {
+        self.checks.iter().filter(|c| !c.passed)
+    }
+}
+
+/// Run every self test against [`OsEntropy`].
+pub fn power_on_self_test() -> SelfTestReport {
+    power_on_self_test_with(&OsEntropy)
+}
+
+/// Run every self test, drawing the RNG check's bytes from `entropy`.
+pub fn power_on_self_test_with(entropy: &dyn EntropySource) -> SelfTestReport {
+    let run_at = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+    #[cfg_attr(
+        not(any(feature = "mlkem", feature = "mldsa", feature = "slhdsa")),
+        allow(unused_mut)
+    )]
+    let mut checks = vec![run_check("RNG", || rng_check(entropy))];
+    #[cfg(feature = "mlkem")]
+    checks.push(run_check("ML-KEM-768", mlkem_check));
+    #[cfg(feature = "mldsa")]
+    checks.push(run_check("ML-DSA-65", mldsa_check));
+    #[cfg(feature = "slhdsa")]
+    checks.push(run_check("SLH-DSA-SHA2-128f", slhdsa_check));
+    #[cfg(feature = "hybrid")]
+    checks.push(run_check("X25519-ML-KEM-768", hybrid_check));
+    SelfTestReport { run_at, checks }
+}
+
+fn run_check(name: &str, check: impl FnOnce() -> CryptoResult<()>) -> SelfTestCheck {
+    let start = Instant::now();
+    let result = match catch_unwind(AssertUnwindSafe(check)) {
+        Ok(result) => result.map_err(|e| e.to_string()),
+        Err(panic) => Err(panic
+            .downcast_ref::<&str>()
+            .map(|s| s.to_string())
+            .or_else(|| panic.downcast_ref::<String>().cloned())
+            .map_or_else(|| "panicked".into(), |m| format!("panicked: {m}"))),
+    };
+    SelfTestCheck {
+        name: name.to_string(),
+        passed: result.is_ok(),
+        duration_us: start.elapsed().as_micros() as u64,
+        error: result.err(),
+    }
+}
+
+#[cfg(any(feature = "mlkem", feature = "mldsa", feature = "slhdsa"))]
+fn failed(check: &str) -> CryptoError {
+    CryptoError::Verification(format!("self test: {check}"))
+}
+
+/// Two 32-byte draws must succeed, differ from each other, and not be a
+/// single repeated byte.
+fn rng_check(entropy: &dyn EntropySource) -> CryptoResult<()> {
+    let mut first = [0u8; 32];
+    let mut second = [0u8; 32];
+    entropy.fill(&mut first).map_err(CryptoError::Rng)?;
+    entropy.fill(&mut second).map_err(CryptoError::Rng)?;
+    for block in [&first, &second] {
+        if block.iter().all(|&b| b == block[0]) {
+            return Err(CryptoError::Rng(
+                "entropy source returned a constant block".into(),
+            ));
+        }
+    }
+    if first == second {
+        return Err(CryptoError::Rng(
+            "entropy source repeated its output".into(),
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(feature = "mlkem")]
+fn mlkem_check() -> CryptoResult<()> {
+    use crate::mlkem::{encapsulate_to, MlKemKeyPair};
+    use quantun_types::MlKemVariant;
+
+    let variant = MlKemVariant::MlKem768;
+    let kp = MlKemKeyPair::from_seed(variant, &[0x4b; 64])?;
+    if MlKemKeyPair::from_seed(variant, &[0x4b; 64])?.public_key != kp.public_key {
+        return Err(failed("ML-KEM key derivation is not deterministic"));
+    }
+    let encapsulated = encapsulate_to(variant, &kp.public_key)?;
+    if kp.decapsulate(&encapsulated.ciphertext)? != encapsulated.shared_secret {
+        return Err(failed("ML-KEM shared secrets differ"));
+    }
+    let mut tampered = encapsulated.ciphertext.clone();
+    tampered[0] ^= 1;
+    if kp.decapsulate(&tampered)? == encapsulated.shared_secret {
+        return Err(failed("ML-KEM accepted a modified ciphertext"));
+    }
+    Ok(())
+}
+
+#[cfg(feature = "mldsa")]
+fn mldsa_check() -> CryptoResult<()> {
+    use crate::mldsa::MlDsaKeyPair;
+    use quantun_types::MlDsaVariant;
+
+    let kp = MlDsaKeyPair::from_seed(MlDsaVariant::MlDsa65, &[0x44; 32]);
+    let sig = kp.sign(b"qsgw self test")?;
+    if !kp.verify(b"qsgw self test", &sig)? {
+        return Err(failed("ML-DSA rejected a valid signature"));
+    }
+    if kp.verify(b"qsgw self test!", &sig)? {
+        return Err(failed("ML-DSA accepted a signature for another message"));
+    }
+    Ok(())
+}
+
+#[cfg(feature = "slhdsa")]
+fn slhdsa_check() -> CryptoResult<()> {
+    use crate::slhdsa::SlhDsaKeyPair;
+    use quantun_types::SlhDsaVariant;
+
+    let kp = SlhDsaKeyPair::generate(SlhDsaVariant::Sha2_128f)?;
+    let sig = kp.sign(b"qsgw self test")?;
+    if !kp.verify(b"qsgw self test", &sig)? {
+        return Err(failed("SLH-DSA rejected a valid signature"));
+    }
+    if kp.verify(b"qsgw self test!", &sig)? {
+        return Err(failed("SLH-DSA accepted a signature for another message"));
+    }
+    Ok(())
+}
+
+#[cfg(feature = "hybrid")]
+fn hybrid_check() -> CryptoResult<()> {
+    use crate::hybrid::HybridKemKeyPair;
+
+    let kp = HybridKemKeyPair::generate()?;
+    let encapsulated = kp.encapsulate()?;
+    let shared = kp.decapsulate(&encapsulated.classical_public, &encapsulated.pqc_ciphertext)?;
+    if shared != encapsulated.shared_secret {
+        return Err(failed("hybrid KEM shared secrets differ"));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Returns the same bytes on every call.
+    struct StuckEntropy;
+
+    impl EntropySource for StuckEntropy {
+        fn fill(&self, dest: &mut [u8]) -> Result<(), String> {
+            for (i, b) in dest.iter_mut().enumerate() {
+                *b = i as u8;
+            }
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn os_entropy_passes_every_check() {
+        let report = power_on_self_test();
+        assert!(report.passed(), "{report:?}");
+        assert_eq!(report.checks[0].name, "RNG");
+        #[cfg(all(feature = "mlkem", feature = "mldsa"))]
+        assert!(report.checks.iter().any(|c| c.name == "ML-DSA-65"));
+    }
+
+    #[test]
+    fn failing_entropy_fails_the_rng_check() {
+        let report = power_on_self_test_with(&StuckEntropy);
+        assert!(!report.passed());
+        let failures: Vec<_> = report.failures().collect();
+        assert_eq!(failures.len(), 1);
+        assert_eq!(failures[0].name, "RNG");
+        assert_eq!(
+            failures[0].error.as_deref(),
+            Some("rng error: entropy source repeated its output")
+        );
+    }
+}
@@ -0,0 +1,258 @@
+//! Signing backends behind a common async interface.
+//!
+//! [`RemoteSigner`] lets callers sign without knowing where the key lives:
+//! in process ([`LocalSigner`], [`KeyStoreSigner`]) or in an HSM or signing
+//! service. Backends report transport failures as
+//! [`CryptoError::SignerUnavailable`], which callers may retry; any other
+//! error is final.
+
+use crate::error::{CryptoError, CryptoResult};
+use crate::keystore::{KeyHandle, KeyStore};
+use async_trait::async_trait;
+use quantun_types::{Algorithm, KeyUsage};
+use std::sync::Arc;
+
+#[cfg(feature = "mldsa")]
+use crate::mldsa::MlDsaKeyPair;
+#[cfg(all(feature = "mldsa", any(test, feature = "testing")))]
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+/// A signing key that may live outside gateway memory.
+#[async_trait]
+pub trait RemoteSigner: Send + Sync {
+    /// Signature algorithm of the key.
+    fn algorithm(&self) -> Algorithm;
+
+    /// Encoded public key, fetched once when the signer is constructed.
+    fn public_key(&self) -> &[u8];
+
+    /// Sign `message`, returning the encoded signature.
+    async fn sign(&self, message: &[u8]) -> CryptoResult<Vec<u8>>;
+
+    /// Check that the backend is reachable and the key is usable.
+    async fn health_check(&self) -> CryptoResult<()>;
+}
+
+/// Signs with an in-memory ML-DSA key pair.
+#[cfg(feature = "mldsa")]
+#[derive(Debug)]
+pub struct LocalSigner {
+    keypair: MlDsaKeyPair,
+}
+
+#[cfg(feature = "mldsa")]
+impl LocalSigner {
+    pub fn new(keypair: MlDsaKeyPair) -> Self {
+        Self { keypair }
+    }
+}
+
+#[cfg(feature = "mldsa")]
+#[async_trait]
+impl RemoteSigner for LocalSigner {
+    fn algorithm(&self) -> Algorithm {
+        Algorithm::MlDsa(self.keypair.variant)
+    }
+
+    fn public_key(&self) -> &[u8] {
+        &self.keypair.public_key
+    }
+
+    async fn sign(&self, message: &[u8]) -> CryptoResult<Vec<u8>> {
+        self.keypair.sign(message).map(|sig| sig.signature)
+    }
+
+    async fn health_check(&self) -> CryptoResult<()> {
+        Ok(())
+    }
+}
+
+/// Signs with a key held in a [`KeyStore`], subject to its usage and
+/// lifecycle checks.
+pub struct KeyStoreSigner {
+    store: Arc<KeyStore>,
+    handle: KeyHandle,
+    algorithm: Algorithm,
+    public_key: Vec<u8>,
+}
+
+impl KeyStoreSigner {
+    pub fn new(store: Arc<KeyStore>, handle: KeyHandle) -> CryptoResult<Self> {
+        let algorithm = store.metadata(&handle)?.algorithm;
+        let public_key = store.get_public(&handle)?;
+        Ok(Self {
+            store,
+            handle,
+            algorithm,
+            public_key,
+        })
+    }
+}
+
+#[async_trait]
+impl RemoteSigner for KeyStoreSigner {
+    fn algorithm(&self) -> Algorithm {
+        self.algorithm
+    }
+
+    fn public_key(&self) -> &[u8] {
+        &self.public_key
+    }
+
+    async fn sign(&self, message: &[u8]) -> CryptoResult<Vec<u8>> {
+        self.store.sign(&self.handle, message)
+    }
+
+    async fn health_check(&self) -> CryptoResult<()> {
+        self.store
+            .metadata(&self.handle)?
+            .can_perform(KeyUsage::Sign)
+            .map_err(|code| CryptoError::KeyUnusable {
+                key_id: self.handle.to_string(),
+                code,
+            })
+    }
+}
+
+/// In-process signer with scriptable failures, for exercising retry and
+/// timeout handling in tests.
+#[cfg(all(feature = "mldsa", any(test, feature = "testing")))]
+#[derive(Debug)]
+pub struct MockSigner {
+    inner: LocalSigner,
+    fail_next: AtomicUsize,
+    hang_next: AtomicUsize,
+    healthy: AtomicBool,
+    sign_calls: AtomicUsize,
+}
+
+#[cfg(all(feature = "mldsa", any(test, feature = "testing")))]
+impl MockSigner {
+    pub fn new(keypair: MlDsaKeyPair) -> Self {
+        Self {
+            inner: LocalSigner::new(keypair),
+            fail_next: AtomicUsize::new(0),
+            hang_next: AtomicUsize::new(0),
+            healthy: AtomicBool::new(true),
+            sign_calls: AtomicUsize::new(0),
+        }
+    }
+
+    /// Fail the next `n` sign calls with [`CryptoError::SignerUnavailable`].
+    pub fn fail_next(&self, n: usize) {
+        self.fail_next.store(n, Ordering::SeqCst);
+    }
+
+    /// Never complete the next `n` sign calls.
+    pub fn hang_next(&self, n: usize) {
+        self.hang_next.store(n, Ordering::SeqCst);
+    }
+
+    pub fn set_healthy(&self, healthy: bool) {
+        self.healthy.store(healthy, Ordering::SeqCst);
+    }
+
+    /// Number of sign calls received, including failed ones.
+    pub fn sign_calls(&self) -> usize {
+        self.sign_calls.load(Ordering::SeqCst)
+    }
+}
+
+#[cfg(all(feature = "mldsa", any(test, feature = "testing")))]
+fn take_one(counter: &AtomicUsize) -> bool {
+    counter
+        .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| n.checked_sub(1))
+        .is_ok()
+}
+
+#[cfg(all(feature = "mldsa", any(test, feature = "testing")))]
+#[async_trait]
+impl RemoteSigner for MockSigner {
+    fn algorithm(&self) -> Algorithm {
+        self.inner.algorithm()
+    }
+
+    fn public_key(&self) -> &[u8] {
+        self.inner.public_key()
+    }
+
+    async fn sign(&self, message: &[u8]) -> CryptoResult<Vec<u8>> {
+        self.sign_calls.fetch_add(1, Ordering::SeqCst);
+        if take_one(&self.hang_next) {
+            std::future::pending::<()>().await;
+        }
+        if take_one(&self.fail_next) {
+            return Err(CryptoError::SignerUnavailable("mock failure".into()));
+        }
+        self.inner.sign(message).await
+    }
+
+    async fn health_check(&self) -> CryptoResult<()> {
+        if self.healthy.load(Ordering::SeqCst) {
+            Ok(())
+        } else {
+            Err(CryptoError::SignerUnavailable("mock unhealthy".into()))
+        }
+    }
+}
+
+#[cfg(all(test, feature = "mldsa", feature = "hybrid"))]
+mod tests {
+    use super::*;
+    use crate::mldsa::{MlDsaSignature, MlDsaVerifier};
+    use quantun_types::MlDsaVariant;
+
+    fn verify(signer: &dyn RemoteSigner, message: &[u8], signature: Vec<u8>) -> bool {
+        let variant = match signer.algorithm() {
+            Algorithm::MlDsa(v) => v,
+            other => panic!("unexpected {other}"),
+        };
+        let verifier = MlDsaVerifier {
+            variant,
+            public_key: signer.public_key().to_vec(),
+        };
+        verifier
+            .verify(message, &MlDsaSignature { signature, variant })
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn local_and_keystore_signers_are_interchangeable() {
+        let store = Arc::new(KeyStore::new());
+        let handle = store
+            .create(Algorithm::MlDsa(MlDsaVariant::MlDsa44), [KeyUsage::Sign])
+            .unwrap();
+        let signers: Vec<Box<dyn RemoteSigner>> = vec![
+            Box::new(LocalSigner::new(
+                MlDsaKeyPair::generate(MlDsaVariant::MlDsa44).unwrap(),
+            )),
+            Box::new(KeyStoreSigner::new(store.clone(), handle.clone()).unwrap()),
+        ];
+        for signer in &signers {
+            signer.health_check().await.unwrap();
+            let signature = signer.sign(b"msg").await.unwrap();
+            assert!(verify(signer.as_ref(), b"msg", signature));
+        }
+
+        store.revoke(&handle).unwrap();
+        assert!(matches!(
+            signers[1].health_check().await,
+            Err(CryptoError::KeyUnusable { .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn mock_fails_on_request() {
+        let mock = MockSigner::new(MlDsaKeyPair::generate(MlDsaVariant::MlDsa44).unwrap());
+        mock.fail_next(1);
+        assert!(matches!(
+            mock.sign(b"m").await,
+            Err(CryptoError::SignerUnavailable(_))
+        ));
+        assert!(mock.sign(b"m").await.is_ok());
+        assert_eq!(mock.sign_calls(), 2);
+
+        mock.set_healthy(false);
+        assert!(mock.health_check().await.is_err());
+    }
+}
@@ -1,7 +1,10 @@
+use crate::attached;
 use crate::error::{CryptoError, CryptoResult};
+use async_trait::async_trait;
 use quantun_types::SlhDsaVariant;
 use serde::{Deserialize, Serialize};
 use signature::Verifier;
+use subtle::{Choice, ConstantTimeEq};
 use zeroize::Zeroize;
 
 /// SLH-DSA key pair (FIPS 205).
@@ -49,9 +52,59 @@ impl SlhDsaKeyPair {
             SlhDsaVariant::Sha2_192f => generate_typed::<slh_dsa::Sha2_192f>(variant),
             SlhDsaVariant::Sha2_256s => generate_typed::<slh_dsa::Sha2_256s>(variant),
             SlhDsaVariant::Sha2_256f => generate_typed::<slh_dsa::Sha2_256f>(variant),
+            SlhDsaVariant::Shake_128s => generate_typed::<slh_dsa::Shake128s>(variant),
+            SlhDsaVariant::Shake_128f => generate_typed::<slh_dsa::Shake128f>(variant),
+            SlhDsaVariant::Shake_192s => generate_typed::<slh_dsa::Shake192s>(variant),
+            SlhDsaVariant::Shake_192f => generate_typed::<slh_dsa::Shake192f>(variant),
+            SlhDsaVariant::Shake_256s => generate_typed::<slh_dsa::Shake256s>(variant),
+            SlhDsaVariant::Shake_256f => generate_typed::<slh_dsa::Shake256f>(variant),
         }
     }
 
+    /// Rebuild a key pair from serialized signing key bytes.
+    pub fn from_secret_key(variant: SlhDsaVariant, secret_key: &[u8]) -> CryptoResult<Self> {
+        variant.validate_secret_key(secret_key)?;
+
+        match variant {
+            SlhDsaVariant::Sha2_128s => from_secret_typed::<slh_dsa::Sha2_128s>(variant, secret_key),
+            SlhDsaVariant::Sha2_128f => from_secret_typed::<slh_dsa::Sha2_128f>(variant, secret_key),
+            SlhDsaVariant::Sha2_192s => from_secret_typed::<slh_dsa::Sha2_192s>(variant, secret_key),
+            SlhDsaVariant::Sha2_192f => from_secret_typed::<slh_dsa::Sha2_192f>(variant, secret_key),
+            SlhDsaVariant::Sha2_256s => from_secret_typed::<slh_dsa::Sha2_256s>(variant, secret_key),
+            SlhDsaVariant::Sha2_256f => from_secret_typed::<slh_dsa::Sha2_256f>(variant, secret_key),
+            SlhDsaVariant::Shake_128s => from_secret_typed::<slh_dsa::Shake128s>(variant, secret_key),
+            SlhDsaVariant::Shake_128f => from_secret_typed::<slh_dsa::Shake128f>(variant, secret_key),
+            SlhDsaVariant::Shake_192s => from_secret_typed::<slh_dsa::Shake192s>(variant, secret_key),
+            SlhDsaVariant::Shake_192f => from_secret_typed::<slh_dsa::Shake192f>(variant, secret_key),
+            SlhDsaVariant::Shake_256s => from_secret_typed::<slh_dsa::Shake256s>(variant, secret_key),
+            SlhDsaVariant::Shake_256f => from_secret_typed::<slh_dsa::Shake256f>(variant, secret_key),
+        }
+    }
+
+    /// A key pair holding only `public_key`, for keys whose secret half
+    /// lives in an HSM. It verifies, but [`SlhDsaKeyPair::sign`] fails;
+    /// sign through an [`HsmSlhDsaSigner`] instead.
+    pub fn public_only(variant: SlhDsaVariant, public_key: Vec<u8>) -> CryptoResult<Self> {
+        if public_key.len() != variant.key_sizes().0 {
+            return Err(CryptoError::InvalidKeyMaterial(format!(
+                "{variant} public key must be {} bytes, got {}",
+                variant.key_sizes().0,
+                public_key.len()
+            )));
+        }
+        Ok(Self {
+            variant,
+            public_key,
+            secret_key: Vec::new(),
+        })
+    }
+
+    /// Whether the secret key is held locally, so [`SlhDsaKeyPair::sign`]
+    /// can be used.
+    pub fn can_sign(&self) -> bool {
+        !self.secret_key.is_empty()
+    }
+
     /// Generate with a caller-supplied RNG. Delegates to OS RNG for PQC safety.
     pub fn generate_with_rng<R: rand::RngCore>(
         variant: SlhDsaVariant,
@@ -62,6 +115,15 @@ impl SlhDsaKeyPair {
 
     /// Sign a message using OS RNG for randomized signing.
     pub fn sign(&self, message: &[u8]) -> CryptoResult<SlhDsaSignature> {
+        if !self.can_sign() {
+            return Err(CryptoError::Signing(
+                "secret key not available—use an HSM signer".into(),
+            ));
+        }
+        self.variant
+            .validate_secret_key(&self.secret_key)
+            .map_err(|e| CryptoError::Signing(e.to_string()))?;
+
         match self.variant {
             SlhDsaVariant::Sha2_128s => sign_typed::<slh_dsa::Sha2_128s>(&self.secret_key, message, self.variant),
             SlhDsaVariant::Sha2_128f => sign_typed::<slh_dsa::Sha2_128f>(&self.secret_key, message, self.variant),
@@ -69,6 +131,12 @@ impl SlhDsaKeyPair {
             SlhDsaVariant::Sha2_192f => sign_typed::<slh_dsa::Sha2_192f>(&self.secret_key, message, self.variant),
             SlhDsaVariant::Sha2_256s => sign_typed::<slh_dsa::Sha2_256s>(&self.secret_key, message, self.variant),
             SlhDsaVariant::Sha2_256f => sign_typed::<slh_dsa::Sha2_256f>(&self.secret_key, message, self.variant),
+            SlhDsaVariant::Shake_128s => sign_typed::<slh_dsa::Shake128s>(&self.secret_key, message, self.variant),
+            SlhDsaVariant::Shake_128f => sign_typed::<slh_dsa::Shake128f>(&self.secret_key, message, self.variant),
+            SlhDsaVariant::Shake_192s => sign_typed::<slh_dsa::Shake192s>(&self.secret_key, message, self.variant),
+            SlhDsaVariant::Shake_192f => sign_typed::<slh_dsa::Shake192f>(&self.secret_key, message, self.variant),
+            SlhDsaVariant::Shake_256s => sign_typed::<slh_dsa::Shake256s>(&self.secret_key, message, self.variant),
+            SlhDsaVariant::Shake_256f => sign_typed::<slh_dsa::Shake256f>(&self.secret_key, message, self.variant),
         }
     }
 
@@ -80,18 +148,71 @@ impl SlhDsaKeyPair {
                 self.variant, sig.variant
             )));
         }
+        self.verify_bytes(message, &sig.signature)
+    }
+
+    /// Verify a signature without telling a signature of another variant
+    /// apart from an invalid one: both give `false`. The variants are
+    /// compared in constant time and the signature is checked either way.
+    pub fn verify_ct(&self, message: &[u8], sig: &SlhDsaSignature) -> bool {
+        let same_variant = (self.variant as u8).ct_eq(&(sig.variant as u8));
+        let valid = self.verify_bytes(message, &sig.signature).unwrap_or(false);
+        (same_variant & Choice::from(u8::from(valid))).into()
+    }
+
+    /// Verify `signature` bytes as a signature of this key's variant.
+    fn verify_bytes(&self, message: &[u8], signature: &[u8]) -> CryptoResult<bool> {
+        self.variant
+            .validate_public_key(&self.public_key)
+            .and_then(|()| self.variant.validate_signature(signature))
+            .map_err(|e| CryptoError::Verification(e.to_string()))?;
 
         match self.variant {
-            SlhDsaVariant::Sha2_128s => verify_typed::<slh_dsa::Sha2_128s>(&self.public_key, message, &sig.signature),
-            SlhDsaVariant::Sha2_128f => verify_typed::<slh_dsa::Sha2_128f>(&self.public_key, message, &sig.signature),
-            SlhDsaVariant::Sha2_192s => verify_typed::<slh_dsa::Sha2_192s>(&self.public_key, message, &sig.signature),
-            SlhDsaVariant::Sha2_192f => verify_typed::<slh_dsa::Sha2_192f>(&self.public_key, message, &sig.signature),
-            SlhDsaVariant::Sha2_256s => verify_typed::<slh_dsa::Sha2_256s>(&self.public_key, message, &sig.signature),
-            SlhDsaVariant::Sha2_256f => verify_typed::<slh_dsa::Sha2_256f>(&self.public_key, message, &sig.signature),
+            SlhDsaVariant::Sha2_128s => verify_typed::<slh_dsa::Sha2_128s>(&self.public_key, message, signature),
+            SlhDsaVariant::Sha2_128f => verify_typed::<slh_dsa::Sha2_128f>(&self.public_key, message, signature),
+            SlhDsaVariant::Sha2_192s => verify_typed::<slh_dsa::Sha2_192s>(&self.public_key, message, signature),
+            SlhDsaVariant::Sha2_192f => verify_typed::<slh_dsa::Sha2_192f>(&self.public_key, message, signature),
+            SlhDsaVariant::Sha2_256s => verify_typed::<slh_dsa::Sha2_256s>(&self.public_key, message, signature),
+            SlhDsaVariant::Sha2_256f => verify_typed::<slh_dsa::Sha2_256f>(&self.public_key, message, signature),
+            SlhDsaVariant::Shake_128s => verify_typed::<slh_dsa::Shake128s>(&self.public_key, message, signature),
+            SlhDsaVariant::Shake_128f => verify_typed::<slh_dsa::Shake128f>(&self.public_key, message, signature),
+            SlhDsaVariant::Shake_192s => verify_typed::<slh_dsa::Shake192s>(&self.public_key, message, signature),
+            SlhDsaVariant::Shake_192f => verify_typed::<slh_dsa::Shake192f>(&self.public_key, message, signature),
+            SlhDsaVariant::Shake_256s => verify_typed::<slh_dsa::Shake256s>(&self.public_key, message, signature),
+            SlhDsaVariant::Shake_256f => verify_typed::<slh_dsa::Shake256f>(&self.public_key, message, signature),
+        }
+    }
+
+    /// Sign `message` and return `u32_be(signature_len) || signature ||
+    /// message`.
+    pub fn sign_attached(&self, message: &[u8]) -> CryptoResult<Vec<u8>> {
+        let sig = self.sign(message)?;
+        Ok(attached::attach(&sig.signature, message))
+    }
+
+    /// Verify a blob from [`SlhDsaKeyPair::sign_attached`] and return its
+    /// message.
+    pub fn verify_attached(&self, blob: &[u8]) -> CryptoResult<Vec<u8>> {
+        let (signature, message) = attached::detach(blob)?;
+        let sig = SlhDsaSignature {
+            signature: signature.to_vec(),
+            variant: self.variant,
+        };
+        if !self.verify(message, &sig)? {
+            return Err(attached::mismatch());
         }
+        Ok(message.to_vec())
     }
 }
 
+/// Signs with SLH-DSA keys held in an HSM, for use alongside
+/// [`SlhDsaKeyPair::public_only`]. Implement against the HSM vendor's SDK.
+#[async_trait]
+pub trait HsmSlhDsaSigner: Send + Sync {
+    /// Sign `message` with the HSM's `variant` key.
+    async fn sign(&self, variant: SlhDsaVariant, message: &[u8]) -> CryptoResult<SlhDsaSignature>;
+}
+
 /// Generate a key pair for a concrete SLH-DSA parameter set.
 fn generate_typed<P>(variant: SlhDsaVariant) -> CryptoResult<SlhDsaKeyPair>
 where
@@ -117,6 +238,26 @@ where
     })
 }
 
+/// Rebuild a key pair for a concrete SLH-DSA parameter set.
+fn from_secret_typed<P>(variant: SlhDsaVariant, sk_bytes: &[u8]) -> CryptoResult<SlhDsaKeyPair>
+where
+    P: slh_dsa::ParameterSet,
+{
+    let sk = slh_dsa::SigningKey::<P>::try_from(sk_bytes).map_err(|_| {
+        CryptoError::InvalidKeyMaterial(format!(
+            "invalid SLH-DSA signing key ({} bytes)",
+            sk_bytes.len()
+        ))
+    })?;
+    let vk: &slh_dsa::VerifyingKey<P> = sk.as_ref();
+
+    Ok(SlhDsaKeyPair {
+        variant,
+        public_key: vk.to_vec(),
+        secret_key: sk.to_vec(),
+    })
+}
+
 /// Sign a message with a serialized signing key.
 fn sign_typed<P>(
     sk_bytes: &[u8],
@@ -179,6 +320,21 @@ where
 mod tests {
     use super::*;
 
+    #[test]
+    fn attached_signature_round_trip() {
+        let kp = SlhDsaKeyPair::generate(SlhDsaVariant::Sha2_128f).unwrap();
+        let blob = kp.sign_attached(b"attached message").unwrap();
+        assert_eq!(kp.verify_attached(&blob).unwrap(), b"attached message");
+
+        let mut tampered = blob.clone();
+        *tampered.last_mut().unwrap() ^= 1;
+        assert!(matches!(
+            kp.verify_attached(&tampered),
+            Err(CryptoError::Verification(_))
+        ));
+        assert!(kp.verify_attached(&[0, 0, 1]).is_err());
+    }
+
     #[test]
     fn keygen_correct_sizes() {
         let kp = SlhDsaKeyPair::generate(SlhDsaVariant::Sha2_128s).unwrap();
@@ -215,10 +371,36 @@ mod tests {
         assert_eq!(sig.signature.len(), SlhDsaVariant::Sha2_128s.signature_size());
     }
 
+    #[test]
+    fn from_secret_key_round_trip() {
+        let kp = SlhDsaKeyPair::generate(SlhDsaVariant::Sha2_128f).unwrap();
+        let restored = SlhDsaKeyPair::from_secret_key(kp.variant, &kp.secret_key).unwrap();
+        assert_eq!(restored.public_key, kp.public_key);
+        let sig = restored.sign(b"restored").unwrap();
+        assert!(kp.verify(b"restored", &sig).unwrap());
+    }
+
     #[test]
     fn is_small_variant() {
         assert!(SlhDsaVariant::Sha2_128s.is_small());
         assert!(!SlhDsaVariant::Sha2_128f.is_small());
+        assert!(SlhDsaVariant::Shake_128s.is_small());
+        assert!(!SlhDsaVariant::Shake_128f.is_small());
+    }
+
+    #[test]
+    fn shake_sign_verify_round_trip() {
+        for variant in [SlhDsaVariant::Shake_128s, SlhDsaVariant::Shake_256f] {
+            let kp = SlhDsaKeyPair::generate(variant).unwrap();
+            let (pk_len, sk_len) = variant.key_sizes();
+            assert_eq!(kp.public_key.len(), pk_len);
+            assert_eq!(kp.secret_key.len(), sk_len);
+
+            let sig = kp.sign(b"shake message").unwrap();
+            assert_eq!(sig.signature.len(), variant.signature_size());
+            assert!(kp.verify(b"shake message", &sig).unwrap());
+            assert!(!kp.verify(b"other message", &sig).unwrap());
+        }
     }
 
     #[test]
@@ -230,4 +412,53 @@ mod tests {
         };
         assert!(kp.verify(b"test", &wrong_sig).is_err());
     }
+
+    #[test]
+    fn verify_ct_rejects_wrong_variant_and_tampered_signature() {
+        let kp = SlhDsaKeyPair::generate(SlhDsaVariant::Sha2_128f).unwrap();
+        let sig = kp.sign(b"test").unwrap();
+        assert!(kp.verify_ct(b"test", &sig));
+
+        // SHAKE-128f signatures have the same size, so only the variant
+        // tells this one apart.
+        let relabelled = SlhDsaSignature {
+            signature: sig.signature.clone(),
+            variant: SlhDsaVariant::Shake_128f,
+        };
+        assert!(!kp.verify_ct(b"test", &relabelled));
+
+        let mut tampered = sig.clone();
+        tampered.signature[0] ^= 1;
+        assert!(!kp.verify_ct(b"test", &tampered));
+    }
+
+    #[test]
+    fn public_only_verifies_but_cannot_sign() {
+        let kp = SlhDsaKeyPair::generate(SlhDsaVariant::Sha2_128f).unwrap();
+        let sig = kp.sign(b"from the hsm").unwrap();
+
+        let public = SlhDsaKeyPair::public_only(kp.variant, kp.public_key.clone()).unwrap();
+        assert!(!public.can_sign());
+        assert!(public.verify(b"from the hsm", &sig).unwrap());
+        assert!(!public.verify(b"tampered", &sig).unwrap());
+        match public.sign(b"local") {
+            Err(CryptoError::Signing(msg)) => {
+                assert_eq!(msg, "secret key not available—use an HSM signer")
+            }
+            other => panic!("expected signing error, got {other:?}"),
+        }
+
+        assert!(matches!(
+            SlhDsaKeyPair::public_only(SlhDsaVariant::Sha2_256f, kp.public_key.clone()),
+            Err(CryptoError::InvalidKeyMaterial(_))
+        ));
+    }
+
+    #[test]
+    fn truncated_signing_key_reports_expected_size() {
+        let mut kp = SlhDsaKeyPair::generate(SlhDsaVariant::Sha2_128f).unwrap();
+        kp.secret_key.truncate(10);
+        let err = kp.sign(b"test").unwrap_err();
+        assert!(err.to_string().contains("expected 64 bytes, got 10"), "{err}");
+    }
 }
@@ -0,0 +1,250 @@
+//! Symmetric keys for AEAD ciphers.
+//!
+//! [`AeadKey::from_shared_secret`] turns a KEM shared secret into a
+//! 256-bit key for a named purpose; [`AeadKey::generate`] draws one from the
+//! OS RNG for symmetric-only use. [`AeadKeyPair`] wraps one key under
+//! another with AES-256 key wrap (RFC 3394) for storage or transport.
+//! [`AeadSession`] encrypts with a key, drawing nonces from a
+//! [`NonceManager`].
+
+use aes_gcm::aead::{Aead, KeyInit, Payload};
+use aes_gcm::{Aes256Gcm, Nonce};
+use aes_kw::KekAes256;
+use hkdf::Hkdf;
+use sha2::Sha256;
+use signature::rand_core::TryRng;
+use std::fmt;
+use zeroize::{Zeroize, ZeroizeOnDrop};
+
+use crate::error::{CryptoError, CryptoResult};
+use crate::nonce::{NonceManager, NONCE_LEN};
+use crate::rng::PqcRng;
+use crate::secure::SecureBytes;
+
+/// Length of an [`AeadKey`], in bytes.
+pub const AEAD_KEY_LEN: usize = 32;
+
+/// Length of an [`AeadKey`] wrapped by [`AeadKeyPair::wrap`], in bytes.
+pub const WRAPPED_KEY_LEN: usize = AEAD_KEY_LEN + 8;
+
+/// A 256-bit AEAD key, zeroized on drop.
+///
+/// Neither `Debug` nor `Display` reveal the key.
+#[derive(Clone, Zeroize, ZeroizeOnDrop)]
+pub struct AeadKey(SecureBytes);
+
+impl AeadKey {
+    /// Derive a key for `purpose` from a KEM shared secret as
+    /// `HKDF-SHA256(ss, info = "qsgw-aead-" || purpose)`.
+    ///
+    /// Different purposes yield independent keys from the same secret.
+    pub fn from_shared_secret(ss: &SecureBytes, purpose: &str) -> CryptoResult<Self> {
+        if ss.is_empty() {
+            return Err(CryptoError::InvalidKeyMaterial(
+                "shared secret is empty".into(),
+            ));
+        }
+        let mut info = b"qsgw-aead-".to_vec();
+        info.extend_from_slice(purpose.as_bytes());
+        let mut key = vec![0u8; AEAD_KEY_LEN];
+        Hkdf::<Sha256>::new(None, ss.as_bytes())
+            .expand(&info, &mut key)
+            .expect("32 bytes is a valid HKDF-SHA256 output length");
+        Ok(Self(SecureBytes::new(key)))
+    }
+
+    /// A random key from the OS RNG.
+    pub fn generate() -> CryptoResult<Self> {
+        let mut key = vec![0u8; AEAD_KEY_LEN];
+        let Ok(()) = PqcRng.try_fill_bytes(&mut key);
+        Ok(Self(SecureBytes::new(key)))
+    }
+
+    /// Borrow the raw key bytes.
+    pub fn as_bytes(&self) -> &[u8] {
+        self.0.as_bytes()
+    }
+
+    fn kek(&self) -> KekAes256 {
+        let key: [u8; AEAD_KEY_LEN] = self
+            .as_bytes()
+            .try_into()
+            .expect("AeadKey is always 32 bytes");
+        KekAes256::from(key)
+    }
+}
+
+impl fmt::Display for AeadKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("AeadKey(256-bit, [REDACTED])")
+    }
+}
+
+impl fmt::Debug for AeadKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+}
+
+/// AES-256 key wrap (RFC 3394) of one [`AeadKey`] under another.
+pub struct AeadKeyPair;
+
+impl AeadKeyPair {
+    /// Wrap `plaintext_key` under `wrapping_key`, returning
+    /// [`WRAPPED_KEY_LEN`] bytes.
+    pub fn wrap(plaintext_key: &AeadKey, wrapping_key: &AeadKey) -> CryptoResult<Vec<u8>> {
+        wrapping_key
+            .kek()
+            .wrap_vec(plaintext_key.as_bytes())
+            .map_err(|e| CryptoError::KeyWrap(e.to_string()))
+    }
+
+    /// Recover a key wrapped by [`AeadKeyPair::wrap`].
+    ///
+    /// Fails with [`CryptoError::KeyUnwrap`] if `wrapped` was not produced
+    /// under `wrapping_key` or has been altered.
+    pub fn unwrap(wrapped: &[u8], wrapping_key: &AeadKey) -> CryptoResult<AeadKey> {
+        if wrapped.len() != WRAPPED_KEY_LEN {
+            return Err(CryptoError::KeyUnwrap(format!(
+                "wrapped key must be {WRAPPED_KEY_LEN} bytes, got {}",
+                wrapped.len()
+            )));
+        }
+        wrapping_key
+            .kek()
+            .unwrap_vec(wrapped)
+            .map(|key| AeadKey(SecureBytes::new(key)))
+            .map_err(|_| CryptoError::KeyUnwrap("integrity check failed".into()))
+    }
+}
+
+/// AES-256-GCM under one key, with every nonce issued by a
+/// [`NonceManager`] so none is reused.
+///
+/// Sealed messages are `nonce || ciphertext || tag`.
+pub struct AeadSession {
+    cipher: Aes256Gcm,
+    nonces: NonceManager,
+}
+
+impl AeadSession {
+    /// A session over `key` with a random nonce prefix.
+    pub fn new(key: &AeadKey) -> CryptoResult<Self> {
+        Ok(Self::with_nonces(key, NonceManager::new_random()?))
+    }
+
+    /// A session over `key` drawing nonces from `nonces`, which must not
+    /// have issued nonces for this key before.
+    pub fn with_nonces(key: &AeadKey, nonces: NonceManager) -> Self {
+        Self {
+            cipher: Aes256Gcm::new_from_slice(key.as_bytes()).expect("AeadKey is always 32 bytes"),
+            nonces,
+        }
+    }
+
+    /// Encrypt `plaintext`, authenticating `aad` alongside it.
+    pub fn seal(&self, plaintext: &[u8], aad: &[u8]) -> CryptoResult<Vec<u8>> {
+        let nonce = self.nonces.next()?;
+        let ciphertext = self
+            .cipher
+            .encrypt(
+                Nonce::from_slice(&nonce),
+                Payload {
+                    msg: plaintext,
+                    aad,
+                },
+            )
+            .map_err(|_| CryptoError::Encapsulation("AES-GCM encryption failed".into()))?;
+        Ok([nonce.as_slice(), &ciphertext].concat())
+    }
+
+    /// Decrypt a message from [`AeadSession::seal`] under the same key.
+    pub fn open(&self, sealed: &[u8], aad: &[u8]) -> CryptoResult<Vec<u8>> {
+        if sealed.len() < NONCE_LEN + 16 {
+            return Err(CryptoError::Decryption(format!(
+                "sealed message too short ({} bytes)",
+                sealed.len()
+            )));
+        }
+        let (nonce, ciphertext) = sealed.split_at(NONCE_LEN);
+        self.cipher
+            .decrypt(
+                Nonce::from_slice(nonce),
+                Payload {
+                    msg: ciphertext,
+                    aad,
+                },
+            )
+            .map_err(|_| CryptoError::Decryption("AES-GCM tag mismatch".into()))
+    }
+
+    /// Messages left to seal before the key must be replaced.
+    pub fn remaining(&self) -> u64 {
+        self.nonces.check_remaining()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn generated_keys_differ() {
+        let a = AeadKey::generate().unwrap();
+        let b = AeadKey::generate().unwrap();
+        assert_eq!(a.as_bytes().len(), AEAD_KEY_LEN);
+        assert_ne!(a.as_bytes(), b.as_bytes());
+    }
+
+    #[test]
+    fn wrap_round_trip() {
+        let key = AeadKey::generate().unwrap();
+        let kek = AeadKey::generate().unwrap();
+
+        let wrapped = AeadKeyPair::wrap(&key, &kek).unwrap();
+        assert_eq!(wrapped.len(), WRAPPED_KEY_LEN);
+        let unwrapped = AeadKeyPair::unwrap(&wrapped, &kek).unwrap();
+        assert_eq!(unwrapped.as_bytes(), key.as_bytes());
+
+        let other = AeadKey::generate().unwrap();
+        assert!(matches!(
+            AeadKeyPair::unwrap(&wrapped, &other),
+            Err(CryptoError::KeyUnwrap(_))
+        ));
+        assert!(matches!(
+            AeadKeyPair::unwrap(&wrapped[..32], &kek),
+            Err(CryptoError::KeyUnwrap(_))
+        ));
+    }
+
+    #[test]
+    fn derivation_is_bound_to_purpose() {
+        let ss = SecureBytes::from_slice(&[7u8; 32]);
+        let a = AeadKey::from_shared_secret(&ss, "tunnel").unwrap();
+        let b = AeadKey::from_shared_secret(&ss, "tunnel").unwrap();
+        let c = AeadKey::from_shared_secret(&ss, "storage").unwrap();
+        assert_eq!(a.as_bytes(), b.as_bytes());
+        assert_ne!(a.as_bytes(), c.as_bytes());
+
+        assert!(AeadKey::from_shared_secret(&SecureBytes::new(Vec::new()), "x").is_err());
+        assert_eq!(a.to_string(), "AeadKey(256-bit, [REDACTED])");
+        assert_eq!(format!("{a:?}"), "AeadKey(256-bit, [REDACTED])");
+    }
+
+    #[test]
+    fn session_round_trip_uses_fresh_nonces() {
+        let key = AeadKey::generate().unwrap();
+        let session = AeadSession::with_nonces(&key, NonceManager::new([1, 2, 3, 4]));
+
+        let a = session.seal(b"hello", b"header").unwrap();
+        let b = session.seal(b"hello", b"header").unwrap();
+        assert_ne!(a[..NONCE_LEN], b[..NONCE_LEN]);
+        assert_ne!(a, b);
+        assert_eq!(session.remaining(), u64::MAX - 2);
+
+        let other = AeadSession::new(&key).unwrap();
+        assert_eq!(other.open(&a, b"header").unwrap(), b"hello");
+        assert!(other.open(&a, b"other header").is_err());
+        assert!(other.open(&a[..NONCE_LEN], b"header").is_err());
+    }
+}
@@ -0,0 +1,432 @@
+//! Known-answer test vectors, for checking this build against published
+//! vectors offline.
+//!
+//! Vectors are read from a JSON array, byte strings in hex, each entry
+//! tagged with its `family`:
+//!
+//! ```json
+//! [
+//!   {
+//!     "family": "ml_kem",
+//!     "id": "keyGen tcId 1",
+//!     "variant": "MlKem512",
+//!     "seed": "000102…3f",
+//!     "expected_pk": "3995815e…"
+//!   }
+//! ]
+//! ```
+//!
+//! An ML-KEM `seed` is `d || z`, 64 bytes, for a key generation vector, or
+//! `d || z || m`, 96 bytes, for one that also encapsulates with the
+//! randomness `m` and checks `expected_ct` and `expected_ss`. An
+//! `ml_kem_encap_decap` vector takes its fields from the NIST ACVP
+//! `encapDecap` groups: `"function": "encapsulation"` with `ek`, `m`, `c`
+//! and `k`, or `"function": "decapsulation"` with an expanded `dk`, `c`
+//! and `k`. ML-DSA and SLH-DSA vectors check key derivation and that
+//! `expected_sig` verifies over `message`. Families disabled in this build
+//! are rejected on load.
+//!
+//! The `run_kat` binary runs a file of vectors and prints a pass/fail
+//! table.
+
+use serde::Deserialize;
+use std::path::Path;
+
+use crate::error::{CryptoError, CryptoResult};
+
+/// A test vector that can be checked against this build.
+pub trait RunVector {
+    /// A label for reports, such as `ML-KEM-512 keyGen tcId 1`.
+    fn name(&self) -> String;
+
+    /// `Ok` if this build reproduces the vector, or why not.
+    fn run(&self) -> Result<(), String>;
+}
+
+/// Compare `actual` with `expected`, naming `what` on a mismatch.
+fn expect(what: &str, expected: &[u8], actual: &[u8]) -> Result<(), String> {
+    if expected == actual {
+        Ok(())
+    } else {
+        Err(format!("{what} mismatch"))
+    }
+}
+
+/// `variant`, followed by the vector's `id` if it has one.
+fn label(variant: impl std::fmt::Display, id: &Option<String>) -> String {
+    match id {
+        Some(id) => format!("{variant} {id}"),
+        None => variant.to_string(),
+    }
+}
+
+/// An ML-KEM (FIPS 203) key generation or encapsulation vector.
+#[cfg(feature = "mlkem")]
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct MlKemTestVector {
+    #[serde(default)]
+    pub id: Option<String>,
+    pub variant: quantun_types::MlKemVariant,
+    /// `d || z`, then the encapsulation randomness `m` if the vector
+    /// encapsulates.
+    #[serde(with = "hex")]
+    pub seed: Vec<u8>,
+    #[serde(with = "hex")]
+    pub expected_pk: Vec<u8>,
+    #[serde(default, with = "hex")]
+    pub expected_ct: Vec<u8>,
+    #[serde(default, with = "hex")]
+    pub expected_ss: Vec<u8>,
+}
+
+/// Derive the key pair from `v.seed` and check its public key, then, for
+/// a 96-byte seed, the ciphertext and shared secret of encapsulating to it
+/// and decapsulating the result.
+#[cfg(feature = "mlkem")]
+pub fn run_mlkem_vector(v: &MlKemTestVector) -> Result<(), String> {
+    use crate::mlkem::{encapsulate_deterministic, MlKemKeyPair};
+
+    let (seed, m) = match v.seed.len() {
+        64 => (&v.seed[..], None),
+        96 => (&v.seed[..64], Some(&v.seed[64..])),
+        n => return Err(format!("seed must be 64 or 96 bytes, got {n}")),
+    };
+    let seed: &[u8; 64] = seed.try_into().expect("checked length");
+    let kp = MlKemKeyPair::from_seed(v.variant, seed).map_err(|e| e.to_string())?;
+    expect("public key", &v.expected_pk, &kp.public_key)?;
+
+    let Some(m) = m else {
+        if v.expected_ct.is_empty() && v.expected_ss.is_empty() {
+            return Ok(());
+        }
+        return Err("encapsulation vectors need a 96-byte seed".into());
+    };
+    let m: &[u8; 32] = m.try_into().expect("checked length");
+    let encapsulated =
+        encapsulate_deterministic(v.variant, &kp.public_key, m).map_err(|e| e.to_string())?;
+    expect("ciphertext", &v.expected_ct, &encapsulated.ciphertext)?;
+    expect("shared secret", &v.expected_ss, &encapsulated.shared_secret)?;
+    let decapsulated = kp
+        .decapsulate(&encapsulated.ciphertext)
+        .map_err(|e| e.to_string())?;
+    expect("decapsulated shared secret", &v.expected_ss, &decapsulated)
+}
+
+#[cfg(feature = "mlkem")]
+impl RunVector for MlKemTestVector {
+    fn name(&self) -> String {
+        label(self.variant, &self.id)
+    }
+
+    fn run(&self) -> Result<(), String> {
+        run_mlkem_vector(self)
+    }
+}
+
+/// An ML-KEM encapsulation or decapsulation vector, in the shape of the
+/// NIST ACVP `encapDecap` test groups: the key, message and ciphertext are
+/// given directly rather than derived from a key generation seed.
+#[cfg(feature = "mlkem")]
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct MlKemEncapDecapTestVector {
+    #[serde(default)]
+    pub id: Option<String>,
+    pub variant: quantun_types::MlKemVariant,
+    #[serde(flatten)]
+    pub case: MlKemEncapDecapCase,
+}
+
+/// What an [`MlKemEncapDecapTestVector`] checks, tagged by `function`.
+#[cfg(feature = "mlkem")]
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(tag = "function", rename_all = "snake_case")]
+pub enum MlKemEncapDecapCase {
+    /// Encapsulating to `ek` with the randomness `m` gives `c` and `k`.
+    Encapsulation {
+        #[serde(with = "hex")]
+        ek: Vec<u8>,
+        #[serde(with = "hex")]
+        m: Vec<u8>,
+        #[serde(with = "hex")]
+        c: Vec<u8>,
+        #[serde(with = "hex")]
+        k: Vec<u8>,
+    },
+    /// Decapsulating `c` with the expanded key `dk` gives `k`, which is the
+    /// implicit-rejection secret if `c` was not produced for `dk`.
+    Decapsulation {
+        #[serde(with = "hex")]
+        dk: Vec<u8>,
+        #[serde(with = "hex")]
+        c: Vec<u8>,
+        #[serde(with = "hex")]
+        k: Vec<u8>,
+    },
+}
+
+/// Encapsulate or decapsulate as `v.case` says and check the result.
+#[cfg(feature = "mlkem")]
+pub fn run_mlkem_encap_decap_vector(v: &MlKemEncapDecapTestVector) -> Result<(), String> {
+    use crate::mlkem::{decapsulate_expanded, encapsulate_deterministic};
+
+    match &v.case {
+        MlKemEncapDecapCase::Encapsulation { ek, m, c, k } => {
+            let m: &[u8; 32] = m
+                .as_slice()
+                .try_into()
+                .map_err(|_| format!("m must be 32 bytes, got {}", m.len()))?;
+            let encapsulated =
+                encapsulate_deterministic(v.variant, ek, m).map_err(|e| e.to_string())?;
+            expect("ciphertext", c, &encapsulated.ciphertext)?;
+            expect("shared secret", k, &encapsulated.shared_secret)
+        }
+        MlKemEncapDecapCase::Decapsulation { dk, c, k } => {
+            let decapsulated = decapsulate_expanded(v.variant, dk, c).map_err(|e| e.to_string())?;
+            expect("shared secret", k, &decapsulated)
+        }
+    }
+}
+
+#[cfg(feature = "mlkem")]
+impl RunVector for MlKemEncapDecapTestVector {
+    fn name(&self) -> String {
+        label(self.variant, &self.id)
+    }
+
+    fn run(&self) -> Result<(), String> {
+        run_mlkem_encap_decap_vector(self)
+    }
+}
+
+/// An ML-DSA (FIPS 204) key generation and signature verification vector.
+#[cfg(feature = "mldsa")]
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct MlDsaTestVector {
+    #[serde(default)]
+    pub id: Option<String>,
+    pub variant: quantun_types::MlDsaVariant,
+    /// The 32-byte key generation seed `ξ`.
+    #[serde(with = "hex")]
+    pub seed: Vec<u8>,
+    #[serde(with = "hex")]
+    pub message: Vec<u8>,
+    #[serde(with = "hex")]
+    pub expected_pk: Vec<u8>,
+    #[serde(with = "hex")]
+    pub expected_sig: Vec<u8>,
+}
+
+/// Derive the key pair from `v.seed`, check its public key and that
+/// `v.expected_sig` verifies over `v.message`.
+#[cfg(feature = "mldsa")]
+pub fn run_mldsa_vector(v: &MlDsaTestVector) -> Result<(), String> {
+    use crate::mldsa::{MlDsaKeyPair, MlDsaSignature};
+
+    let seed: &[u8; 32] = v
+        .seed
+        .as_slice()
+        .try_into()
+        .map_err(|_| format!("seed must be 32 bytes, got {}", v.seed.len()))?;
+    let kp = MlDsaKeyPair::from_seed(v.variant, seed);
+    expect("public key", &v.expected_pk, &kp.public_key)?;
+    let sig = MlDsaSignature {
+        signature: v.expected_sig.clone(),
+        variant: v.variant,
+    };
+    match kp.verify(&v.message, &sig) {
+        Ok(true) => Ok(()),
+        Ok(false) => Err("signature does not verify".into()),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+#[cfg(feature = "mldsa")]
+impl RunVector for MlDsaTestVector {
+    fn name(&self) -> String {
+        label(self.variant, &self.id)
+    }
+
+    fn run(&self) -> Result<(), String> {
+        run_mldsa_vector(self)
+    }
+}
+
+/// An SLH-DSA (FIPS 205) key and signature verification vector.
+#[cfg(feature = "slhdsa")]
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct SlhDsaTestVector {
+    #[serde(default)]
+    pub id: Option<String>,
+    pub variant: quantun_types::SlhDsaVariant,
+    /// The encoded secret key, which embeds the public key.
+    #[serde(with = "hex")]
+    pub secret_key: Vec<u8>,
+    #[serde(with = "hex")]
+    pub message: Vec<u8>,
+    #[serde(with = "hex")]
+    pub expected_pk: Vec<u8>,
+    #[serde(with = "hex")]
+    pub expected_sig: Vec<u8>,
+}
+
+/// Load the key pair from `v.secret_key`, check its public key and that
+/// `v.expected_sig` verifies over `v.message`.
+#[cfg(feature = "slhdsa")]
+pub fn run_slhdsa_vector(v: &SlhDsaTestVector) -> Result<(), String> {
+    use crate::slhdsa::{SlhDsaKeyPair, SlhDsaSignature};
+
+    let kp = SlhDsaKeyPair::from_secret_key(v.variant, &v.secret_key).map_err(|e| e.to_string())?;
+    expect("public key", &v.expected_pk, &kp.public_key)?;
+    let sig = SlhDsaSignature {
+        signature: v.expected_sig.clone(),
+        variant: v.variant,
+    };
+    match kp.verify(&v.message, &sig) {
+        Ok(true) => Ok(()),
+        Ok(false) => Err("signature does not verify".into()),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+#[cfg(feature = "slhdsa")]
+impl RunVector for SlhDsaTestVector {
+    fn name(&self) -> String {
+        label(self.variant, &self.id)
+    }
+
+    fn run(&self) -> Result<(), String> {
+        run_slhdsa_vector(self)
+    }
+}
+
+/// One entry of a vector file.
+#[derive(Deserialize)]
+#[serde(tag = "family", rename_all = "snake_case")]
+enum VectorEntry {
+    #[cfg(feature = "mlkem")]
+    MlKem(MlKemTestVector),
+    #[cfg(feature = "mlkem")]
+    MlKemEncapDecap(MlKemEncapDecapTestVector),
+    #[cfg(feature = "mldsa")]
+    MlDsa(MlDsaTestVector),
+    #[cfg(feature = "slhdsa")]
+    SlhDsa(SlhDsaTestVector),
+}
+
+impl VectorEntry {
+    fn into_runner(self) -> Box<dyn RunVector> {
+        match self {
+            #[cfg(feature = "mlkem")]
+            VectorEntry::MlKem(v) => Box::new(v),
+            #[cfg(feature = "mlkem")]
+            VectorEntry::MlKemEncapDecap(v) => Box::new(v),
+            #[cfg(feature = "mldsa")]
+            VectorEntry::MlDsa(v) => Box::new(v),
+            #[cfg(feature = "slhdsa")]
+            VectorEntry::SlhDsa(v) => Box::new(v),
+        }
+    }
+}
+
+/// Parse a JSON array of vectors.
+pub fn load_vectors_from_json(json: &str) -> CryptoResult<Vec<Box<dyn RunVector>>> {
+    let entries: Vec<VectorEntry> = serde_json::from_str(json)
+        .map_err(|e| CryptoError::Serialization(format!("invalid test vectors: {e}")))?;
+    Ok(entries.into_iter().map(VectorEntry::into_runner).collect())
+}
+
+/// Run every vector in the file at `json_path`, returning each vector's
+/// name and result in file order. A file that cannot be read or parsed
+/// gives a single failed entry named after the path.
+pub fn run_all_vectors(json_path: &Path) -> Vec<(String, Result<(), String>)> {
+    let vectors = std::fs::read_to_string(json_path)
+        .map_err(|e| e.to_string())
+        .and_then(|json| load_vectors_from_json(&json).map_err(|e| e.to_string()));
+    match vectors {
+        Ok(vectors) => vectors.iter().map(|v| (v.name(), v.run())).collect(),
+        Err(e) => vec![(json_path.display().to_string(), Err(e))],
+    }
+}
+
+/// Hex encoding of byte strings in vector files.
+mod hex {
+    use serde::{de, Deserialize, Deserializer};
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+        let text = String::deserialize(deserializer)?;
+        if !text.len().is_multiple_of(2) {
+            return Err(de::Error::custom("odd number of hex digits"));
+        }
+        (0..text.len())
+            .step_by(2)
+            .map(|i| {
+                text.get(i..i + 2)
+                    .and_then(|byte| u8::from_str_radix(byte, 16).ok())
+                    .ok_or_else(|| de::Error::custom(format!("invalid hex at offset {i}")))
+            })
+            .collect()
+    }
+}
+
+#[cfg(all(test, feature = "mlkem"))]
+mod tests {
+    use super::*;
+
+    /// ML-KEM-512 vectors: the key generation example from the IETF LAMPS
+    /// ML-KEM certificates draft, whose keys match the NIST ACVP keyGen
+    /// derivation, and the encapsulation, decapsulation and implicit
+    /// rejection known answers of AWS-LC's FIPS 203 power-on self-test.
+    const MLKEM_512: &str = include_str!("../testdata/kat/ml-kem-512.json");
+
+    #[test]
+    fn mlkem_512_vectors_pass() {
+        let vectors = load_vectors_from_json(MLKEM_512).unwrap();
+        assert_eq!(vectors.len(), 4);
+        for v in &vectors {
+            assert_eq!(v.run(), Ok(()), "{}", v.name());
+        }
+    }
+
+    #[test]
+    fn tampered_vectors_fail() {
+        let mut vectors: Vec<serde_json::Value> = serde_json::from_str(MLKEM_512).unwrap();
+        vectors
+            .iter_mut()
+            .for_each(|v| _ = v.as_object_mut().unwrap().remove("family"));
+        let mut keygen: MlKemTestVector = serde_json::from_value(vectors.remove(0)).unwrap();
+        let vectors: Vec<MlKemEncapDecapTestVector> =
+            serde_json::from_value(vectors.into()).unwrap();
+
+        keygen.expected_pk[0] ^= 1;
+        assert_eq!(run_mlkem_vector(&keygen), Err("public key mismatch".into()));
+        keygen.seed.truncate(63);
+        assert!(run_mlkem_vector(&keygen).is_err());
+
+        for v in &vectors {
+            let mut tampered = v.clone();
+            match &mut tampered.case {
+                MlKemEncapDecapCase::Encapsulation { c, .. } => c[0] ^= 1,
+                MlKemEncapDecapCase::Decapsulation { k, .. } => k[0] ^= 1,
+            }
+            let result = run_mlkem_encap_decap_vector(&tampered);
+            assert!(result.is_err(), "{}", v.name());
+        }
+        let mut encaps = vectors[0].clone();
+        let MlKemEncapDecapCase::Encapsulation { k, .. } = &mut encaps.case else {
+            panic!("first encapDecap vector should encapsulate");
+        };
+        k[0] ^= 1;
+        assert_eq!(
+            run_mlkem_encap_decap_vector(&encaps),
+            Err("shared secret mismatch".into())
+        );
+    }
+
+    #[test]
+    fn malformed_files_are_reported() {
+        assert!(load_vectors_from_json(r#"[{"family": "ml_kem", "seed": "0g"}]"#).is_err());
+        let results = run_all_vectors(Path::new("does-not-exist.json"));
+        assert_eq!(results.len(), 1);
+        assert!(results[0].1.is_err());
+    }
+}
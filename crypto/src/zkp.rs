@@ -0,0 +1,110 @@
+//! Proof of ownership of an ML-DSA key, shaped as a Sigma protocol.
+//!
+//! The verifier sends a [`ZkpChallenge`]: a fresh 32-byte nonce and its own
+//! identifier. The prover answers with a [`ZkpProof`], an ML-DSA signature
+//! over
+//!
+//! ```text
+//! "zkp-ownership-v1" || nonce || verifier_id || public_key
+//! ```
+//!
+//! which the verifier checks against the public key it was given. Binding
+//! the nonce stops a proof being replayed to the same verifier, and binding
+//! the verifier's identifier stops one verifier relaying it to another.
+//!
+//! This proves key ownership with soundness bounded by ML-DSA's
+//! unforgeability: producing a proof without the secret key means forging
+//! a signature on a message never signed before. It is a stub rather than
+//! a zero-knowledge proof. The secret key is not revealed, but the proof is
+//! a signature, which the verifier can show to third parties as evidence
+//! of the exchange.
+
+use crate::error::CryptoResult;
+use crate::mldsa::{MlDsaKeyPair, MlDsaSignature, MlDsaVerifier};
+use serde::{Deserialize, Serialize};
+
+const DOMAIN: &[u8] = b"zkp-ownership-v1";
+
+/// A verifier's challenge. The nonce must be fresh for every proof asked
+/// for.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ZkpChallenge {
+    pub nonce: [u8; 32],
+    /// Identifies the verifier issuing the challenge.
+    pub verifier_id: Vec<u8>,
+}
+
+impl ZkpChallenge {
+    pub fn new(nonce: [u8; 32], verifier_id: &[u8]) -> Self {
+        Self {
+            nonce,
+            verifier_id: verifier_id.to_vec(),
+        }
+    }
+
+    /// The message signed to answer this challenge with `public_key`.
+    fn transcript(&self, public_key: &[u8]) -> Vec<u8> {
+        [DOMAIN, &self.nonce, &self.verifier_id, public_key].concat()
+    }
+}
+
+/// A prover's answer to a [`ZkpChallenge`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ZkpProof {
+    pub signature: MlDsaSignature,
+}
+
+impl ZkpProof {
+    /// Prove ownership of `kp` in answer to `challenge`.
+    pub fn create(kp: &MlDsaKeyPair, challenge: &ZkpChallenge) -> CryptoResult<Self> {
+        let signature = kp.sign(&challenge.transcript(&kp.public_key))?;
+        Ok(Self { signature })
+    }
+
+    /// Whether this proof shows ownership of `public_key` in answer to
+    /// `challenge`. Errors if `public_key` is malformed for the proof's
+    /// variant.
+    pub fn verify(&self, public_key: &[u8], challenge: &ZkpChallenge) -> CryptoResult<bool> {
+        let verifier = MlDsaVerifier {
+            variant: self.signature.variant,
+            public_key: public_key.to_vec(),
+        };
+        verifier.verify(&challenge.transcript(public_key), &self.signature)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use quantun_types::MlDsaVariant;
+
+    fn challenge(nonce: u8) -> ZkpChallenge {
+        ZkpChallenge::new([nonce; 32], b"verifier-a")
+    }
+
+    #[test]
+    fn valid_proof_verifies() {
+        let kp = MlDsaKeyPair::generate(MlDsaVariant::MlDsa44).unwrap();
+        let proof = ZkpProof::create(&kp, &challenge(1)).unwrap();
+        assert!(proof.verify(&kp.public_key, &challenge(1)).unwrap());
+    }
+
+    #[test]
+    fn replayed_proof_fails_for_another_challenge() {
+        let kp = MlDsaKeyPair::generate(MlDsaVariant::MlDsa44).unwrap();
+        let proof = ZkpProof::create(&kp, &challenge(1)).unwrap();
+        assert!(!proof.verify(&kp.public_key, &challenge(2)).unwrap());
+
+        let other_verifier = ZkpChallenge::new([1; 32], b"verifier-b");
+        assert!(!proof.verify(&kp.public_key, &other_verifier).unwrap());
+    }
+
+    #[test]
+    fn proof_fails_for_another_public_key() {
+        let kp = MlDsaKeyPair::generate(MlDsaVariant::MlDsa44).unwrap();
+        let other = MlDsaKeyPair::generate(MlDsaVariant::MlDsa44).unwrap();
+        let proof = ZkpProof::create(&kp, &challenge(1)).unwrap();
+        assert!(!proof.verify(&other.public_key, &challenge(1)).unwrap());
+        assert!(proof.verify(&other.public_key[1..], &challenge(1)).is_err());
+    }
+}
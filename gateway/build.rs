@@ -0,0 +1,45 @@
+//! Bakes build information into the crate for `/gateway/version`.
+//!
+//! `QSGW_GIT_SHA` and `SOURCE_DATE_EPOCH` override the detected commit and
+//! build time, e.g. for builds outside a git checkout or reproducible
+//! builds.
+
+use std::process::Command;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+fn main() {
+    println!("cargo:rerun-if-env-changed=QSGW_GIT_SHA");
+    println!("cargo:rerun-if-env-changed=SOURCE_DATE_EPOCH");
+    for path in ["../.git/HEAD", "../.git/refs"] {
+        if std::path::Path::new(path).exists() {
+            println!("cargo:rerun-if-changed={path}");
+        }
+    }
+
+    let sha = std::env::var("QSGW_GIT_SHA").ok().or_else(git_sha);
+    println!(
+        "cargo:rustc-env=QSGW_GIT_SHA={}",
+        sha.as_deref().unwrap_or("unknown")
+    );
+
+    let timestamp = std::env::var("SOURCE_DATE_EPOCH")
+        .ok()
+        .and_then(|s| s.parse::<u64>().ok())
+        .unwrap_or_else(|| {
+            SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs()
+        });
+    println!("cargo:rustc-env=QSGW_BUILD_TIMESTAMP={timestamp}");
+}
+
+fn git_sha() -> Option<String> {
+    let output = Command::new("git")
+        .args(["rev-parse", "HEAD"])
+        .output()
+        .ok()
+        .filter(|o| o.status.success())?;
+    let sha = String::from_utf8(output.stdout).ok()?;
+    Some(sha.trim().to_string()).filter(|s| !s.is_empty())
+}
@@ -0,0 +1,183 @@
+//! Key usage auditing for the gateway.
+//!
+//! [`TracingAuditSink`] routes keystore [`KeyUsageEvent`]s into the
+//! structured log under the `qsgw::audit` target, and [`router`] serves the
+//! keystore's in-memory usage log to administrators.
+
+use axum::extract::{Path, Query, State};
+use axum::response::{IntoResponse, Response};
+use axum::routing::get;
+use axum::{Json, Router};
+use http::{Request, StatusCode};
+use quantun_crypto::keystore::audit::{AuditSink, Caller, KeyUsageEvent};
+use quantun_crypto::KeyStore;
+use serde::Deserialize;
+use std::sync::Arc;
+
+use crate::auth::AuthenticatedKey;
+use crate::tenant::TenantId;
+
+/// Events returned by the usage endpoint when `n` is not given.
+const DEFAULT_USAGE_LIMIT: usize = 50;
+
+/// Writes each key usage event as a structured `info` record.
+#[derive(Debug, Default)]
+pub struct TracingAuditSink;
+
+impl AuditSink for TracingAuditSink {
+    fn record(&self, event: &KeyUsageEvent) {
+        tracing::info!(
+            target: "qsgw::audit",
+            key_id = %event.key_id,
+            operation = %event.operation,
+            request_id = event.caller.request_id.as_deref(),
+            api_key_id = event.caller.api_key_id.as_deref(),
+            tenant_id = event.caller.tenant_id.as_deref(),
+            timestamp = event.timestamp,
+            success = event.success,
+            error = event.error.as_deref(),
+            input_len = event.input_len,
+            input_sha256 = event.input_sha256.as_deref(),
+            "key used"
+        );
+    }
+}
+
+/// Caller identity for keystore operations made while serving `req`: its
+/// `x-request-id` header, the API key that authenticated it, and its
+/// [`TenantId`].
+///
+/// The key is recorded by [`AuthenticatedKey::log_id`], so keys whose ID
+/// is their credential appear only as a fingerprint.
+pub fn caller_from_request<B>(req: &Request<B>) -> Caller {
+    Caller {
+        request_id: req
+            .headers()
+            .get("x-request-id")
+            .and_then(|v| v.to_str().ok())
+            .map(str::to_string),
+        api_key_id: req
+            .extensions()
+            .get::<AuthenticatedKey>()
+            .map(AuthenticatedKey::log_id),
+        tenant_id: req.extensions().get::<TenantId>().map(|t| t.0.clone()),
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct UsageQuery {
+    n: Option<usize>,
+}
+
+/// Admin routes over `keystore`:
+///
+/// - `GET /admin/keys/{key_id}/usage?n=N`: the key's `N` most recent usage
+///   events, oldest first.
+///
+/// The events identify callers, so [`build_router`](crate::build_router)
+/// mounts these as `AdminOnly` when
+/// [`GatewayConfig::keystore`](crate::GatewayConfig::keystore) is set.
+pub fn router(keystore: Arc<KeyStore>) -> Router {
+    Router::new()
+        .route("/admin/keys/{key_id}/usage", get(key_usage))
+        .with_state(keystore)
+}
+
+async fn key_usage(
+    State(keystore): State<Arc<KeyStore>>,
+    Path(key_id): Path<String>,
+    Query(query): Query<UsageQuery>,
+) -> Response {
+    match keystore.handle(&key_id) {
+        Some(handle) => {
+            let n = query.n.unwrap_or(DEFAULT_USAGE_LIMIT);
+            Json(keystore.recent_usage(&handle, n)).into_response()
+        }
+        None => (StatusCode::NOT_FOUND, "unknown key").into_response(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::Body;
+    use quantun_crypto::keystore::audit::KeyOperation;
+    use quantun_types::{Algorithm, KeyUsage, MlDsaVariant};
+    use tower::ServiceExt;
+
+    #[test]
+    fn caller_includes_request_id_and_api_key() {
+        let mut req = Request::builder()
+            .header("x-request-id", "req-9")
+            .body(())
+            .unwrap();
+        req.extensions_mut().insert(AuthenticatedKey {
+            id: "k1".into(),
+            id_is_credential: false,
+        });
+        req.extensions_mut().insert(TenantId("acme".into()));
+        let caller = caller_from_request(&req);
+        assert_eq!(caller.request_id.as_deref(), Some("req-9"));
+        assert_eq!(caller.api_key_id.as_deref(), Some("k1"));
+        assert_eq!(caller.tenant_id.as_deref(), Some("acme"));
+    }
+
+    #[test]
+    fn caller_fingerprints_keys_whose_id_is_the_credential() {
+        let mut req = Request::builder().body(()).unwrap();
+        req.extensions_mut().insert(AuthenticatedKey {
+            id: "legacy-credential".into(),
+            id_is_credential: true,
+        });
+        let api_key_id = caller_from_request(&req).api_key_id.unwrap();
+        assert!(api_key_id.starts_with("sha256:"), "{api_key_id}");
+        assert_eq!(api_key_id.len(), "sha256:".len() + 16);
+        assert!(!api_key_id.contains("legacy-credential"));
+    }
+
+    #[tokio::test]
+    async fn usage_endpoint_returns_recent_events() {
+        let store = Arc::new(KeyStore::new().with_audit_sink(Arc::new(TracingAuditSink)));
+        let handle = store
+            .create(Algorithm::MlDsa(MlDsaVariant::MlDsa44), [KeyUsage::Sign])
+            .unwrap();
+        let caller = Caller {
+            request_id: Some("req-1".into()),
+            api_key_id: None,
+            tenant_id: None,
+        };
+        store.sign_as(&caller, &handle, b"one").unwrap();
+        store.sign(&handle, b"two").unwrap();
+        store.revoke(&handle).unwrap();
+        store.sign(&handle, b"three").unwrap_err();
+
+        let app = router(store.clone());
+        let uri = format!("/admin/keys/{}/usage?n=2", handle.key_id());
+        let response = app
+            .clone()
+            .oneshot(Request::builder().uri(uri).body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = http_body_util::BodyExt::collect(response.into_body())
+            .await
+            .unwrap()
+            .to_bytes();
+        let events: Vec<KeyUsageEvent> = serde_json::from_slice(&body).unwrap();
+        assert_eq!(events.len(), 2);
+        assert!(events[0].success);
+        assert!(!events[1].success);
+        assert!(events.iter().all(|e| e.operation == KeyOperation::Sign));
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .uri("/admin/keys/missing/usage")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+}
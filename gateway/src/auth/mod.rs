@@ -1,16 +1,203 @@
+pub mod path_matcher;
+pub mod token;
+
 use axum::{
     body::Body,
+    extract::State,
     middleware::Next,
     response::{IntoResponse, Response},
 };
 use http::{Request, StatusCode};
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::fmt;
+use std::net::IpAddr;
+use std::sync::Arc;
+use subtle::ConstantTimeEq;
+use thiserror::Error;
+use tracing::warn;
+
+pub use path_matcher::PathMatcher;
+pub use token::{TokenClaims, TokenIssuer};
+
+use crate::middleware::TrueClientIp;
+use crate::request_signature::ClientSigningKey;
+use crate::tls::ClientIdentity;
+
+/// Maximum number of configured bypass path prefixes.
+pub const MAX_BYPASS_PATHS: usize = 256;
+/// Maximum number of scopes on a single API key.
+pub const MAX_SCOPES_PER_KEY: usize = 64;
+/// Minimum length of an API key secret loaded from the environment.
+pub const MIN_API_KEY_LEN: usize = 32;
+/// Length below which a loaded API key secret is logged as weak.
+pub const RECOMMENDED_API_KEY_LEN: usize = 64;
+/// Random bytes in a secret from [`ApiKey::generate`].
+pub const GENERATED_KEY_BYTES: usize = 32;
+/// Scope required on routes with the
+/// [`AdminOnly`](crate::proxy::MiddlewareProfile::AdminOnly) profile.
+pub const ADMIN_SCOPE: &str = "admin";
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum AuthConfigError {
+    #[error("{count} bypass paths configured, maximum is {max}")]
+    TooManyBypassPaths { count: usize, max: usize },
+    #[error("API key {key_id} has {count} scopes, maximum is {max}")]
+    TooManyScopes {
+        key_id: String,
+        count: usize,
+        max: usize,
+    },
+    #[error("environment variable {0} is not set")]
+    EnvVarMissing(String),
+    #[error("environment variable {var_name} holds a {len}-character API key, minimum is {min}")]
+    EnvVarInvalid {
+        var_name: String,
+        len: usize,
+        min: usize,
+    },
+}
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ApiKey {
     pub id: String,
     pub name: String,
     pub scopes: Vec<String>,
+    /// Key the client signs requests with, for routes that require
+    /// [signed requests](crate::request_signature).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signing_key: Option<ClientSigningKey>,
+    /// Free-form attributes, e.g. the key's tenant for
+    /// [`TenantSource::ApiKeyMetadata`](crate::tenant::TenantSource::ApiKeyMetadata).
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub metadata: HashMap<String, String>,
+    /// Hash of the value clients send as `x-api-key`. Without one, clients
+    /// send the `id`. Never read from or written to config files.
+    #[serde(skip)]
+    pub secret: Option<ApiKeySecret>,
+}
+
+impl ApiKey {
+    /// A key whose secret is the value of the environment variable
+    /// `var_name`, so it never appears in source or config.
+    ///
+    /// The value must be at least [`MIN_API_KEY_LEN`] characters; values
+    /// shorter than [`RECOMMENDED_API_KEY_LEN`] are accepted with a warning.
+    pub fn from_env_var(
+        var_name: &str,
+        id: &str,
+        name: &str,
+        scopes: Vec<String>,
+    ) -> Result<ApiKey, AuthConfigError> {
+        let invalid = |len| AuthConfigError::EnvVarInvalid {
+            var_name: var_name.to_string(),
+            len,
+            min: MIN_API_KEY_LEN,
+        };
+        let secret = match std::env::var(var_name) {
+            Ok(secret) => secret,
+            Err(std::env::VarError::NotPresent) => {
+                return Err(AuthConfigError::EnvVarMissing(var_name.to_string()))
+            }
+            Err(std::env::VarError::NotUnicode(value)) => return Err(invalid(value.len())),
+        };
+        let len = secret.chars().count();
+        if len < MIN_API_KEY_LEN {
+            return Err(invalid(len));
+        }
+        if len < RECOMMENDED_API_KEY_LEN {
+            warn!(
+                var_name,
+                key_id = id,
+                len,
+                recommended = RECOMMENDED_API_KEY_LEN,
+                "API key from environment is shorter than recommended"
+            );
+        }
+        Ok(ApiKey {
+            id: id.to_string(),
+            name: name.to_string(),
+            scopes,
+            signing_key: None,
+            metadata: HashMap::new(),
+            secret: Some(ApiKeySecret::new(&secret)),
+        })
+    }
+
+    /// A new key with a random [`GENERATED_KEY_BYTES`]-byte secret, encoded
+    /// as lowercase base32.
+    ///
+    /// Returns the key, which keeps only the secret's hash, and the secret
+    /// itself. The secret cannot be recovered later, so hand it to the
+    /// client now.
+    pub fn generate(name: &str, scopes: Vec<String>) -> (ApiKey, String) {
+        let mut id = [0u8; 8];
+        let mut secret = [0u8; GENERATED_KEY_BYTES];
+        getrandom::fill(&mut id).expect("OS random number generator failed");
+        getrandom::fill(&mut secret).expect("OS random number generator failed");
+        let secret = base32(&secret);
+        let id: String = id.iter().map(|b| format!("{b:02x}")).collect();
+        let key = ApiKey {
+            id: format!("key-{id}"),
+            name: name.to_string(),
+            scopes,
+            signing_key: None,
+            metadata: HashMap::new(),
+            secret: Some(ApiKeySecret::new(&secret)),
+        };
+        (key, secret)
+    }
+
+    /// Whether `presented`, an `x-api-key` value, identifies this key.
+    pub fn verify(&self, presented: &str) -> bool {
+        match &self.secret {
+            Some(secret) => bool::from(secret.0.ct_eq(&ApiKeySecret::new(presented).0)),
+            None => self.id == presented,
+        }
+    }
+}
+
+/// Unpadded, lowercase RFC 4648 base32.
+fn base32(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 32] = b"abcdefghijklmnopqrstuvwxyz234567";
+    let mut out = String::with_capacity(bytes.len().div_ceil(5) * 8);
+    let (mut buffer, mut bits) = (0u16, 0);
+    for &byte in bytes {
+        buffer = (buffer << 8) | u16::from(byte);
+        bits += 8;
+        while bits >= 5 {
+            bits -= 5;
+            out.push(ALPHABET[usize::from((buffer >> bits) & 0x1f)] as char);
+        }
+    }
+    if bits > 0 {
+        out.push(ALPHABET[usize::from((buffer << (5 - bits)) & 0x1f)] as char);
+    }
+    out
+}
+
+/// The SHA-256 hash of an [`ApiKey`]'s secret. Neither `Debug` nor
+/// `Display` reveal it.
+#[derive(Clone)]
+pub struct ApiKeySecret([u8; 32]);
+
+impl ApiKeySecret {
+    fn new(secret: &str) -> Self {
+        Self(Sha256::digest(secret.as_bytes()).into())
+    }
+}
+
+impl fmt::Display for ApiKeySecret {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("[REDACTED]")
+    }
+}
+
+impl fmt::Debug for ApiKeySecret {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -18,6 +205,9 @@ pub struct AuthConfig {
     pub require_auth: bool,
     pub api_keys: Vec<ApiKey>,
     pub bypass_paths: Vec<String>,
+    /// Scopes granted to mutual-TLS clients, keyed by the common name of
+    /// their [`ClientIdentity`]. A mapped client needs no API key.
+    pub cert_identity_map: HashMap<String, Vec<String>>,
 }
 
 impl Default for AuthConfig {
@@ -25,41 +215,212 @@ impl Default for AuthConfig {
         Self {
             require_auth: false,
             api_keys: Vec::new(),
-            bypass_paths: vec!["/health".into(), "/gateway/stats".into()],
+            bypass_paths: vec![
+                "/health".into(),
+                "/gateway/stats".into(),
+                "/gateway/version".into(),
+                "/.well-known/".into(),
+            ],
+            cert_identity_map: HashMap::new(),
+        }
+    }
+}
+
+impl AuthConfig {
+    /// Check configured sizes against [`MAX_BYPASS_PATHS`] and
+    /// [`MAX_SCOPES_PER_KEY`].
+    pub fn validate(&self) -> Result<(), AuthConfigError> {
+        if self.bypass_paths.len() > MAX_BYPASS_PATHS {
+            return Err(AuthConfigError::TooManyBypassPaths {
+                count: self.bypass_paths.len(),
+                max: MAX_BYPASS_PATHS,
+            });
+        }
+        if let Some(key) = self
+            .api_keys
+            .iter()
+            .find(|k| k.scopes.len() > MAX_SCOPES_PER_KEY)
+        {
+            return Err(AuthConfigError::TooManyScopes {
+                key_id: key.id.clone(),
+                count: key.scopes.len(),
+                max: MAX_SCOPES_PER_KEY,
+            });
+        }
+        Ok(())
+    }
+
+    /// Add an [`ApiKey::from_env_var`] key.
+    pub fn add_key_from_env(
+        &mut self,
+        var_name: &str,
+        id: &str,
+        name: &str,
+        scopes: Vec<String>,
+    ) -> Result<(), AuthConfigError> {
+        self.api_keys
+            .push(ApiKey::from_env_var(var_name, id, name, scopes)?);
+        Ok(())
+    }
+}
+
+/// Validated auth configuration with its bypass matcher built once, shared
+/// with [`auth_middleware`] as router state.
+#[derive(Debug, Clone)]
+pub struct AuthPolicy {
+    config: AuthConfig,
+    bypass: PathMatcher,
+}
+
+impl AuthPolicy {
+    pub fn new(config: AuthConfig) -> Result<Self, AuthConfigError> {
+        config.validate()?;
+        let bypass = PathMatcher::new(&config.bypass_paths);
+        Ok(Self { config, bypass })
+    }
+
+    pub fn config(&self) -> &AuthConfig {
+        &self.config
+    }
+
+    /// Whether `path` skips authentication.
+    pub fn is_bypassed(&self, path: &str) -> bool {
+        self.bypass.matches(path)
+    }
+}
+
+/// The API key that authenticated a request, added to the request's
+/// extensions by [`auth_middleware`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AuthenticatedKey {
+    /// The key's [`ApiKey::id`].
+    pub id: String,
+    /// Whether the key has no `secret`, so that `id` is the credential
+    /// clients send and must not be logged.
+    pub id_is_credential: bool,
+}
+
+impl AuthenticatedKey {
+    pub fn new(key: &ApiKey) -> Self {
+        Self {
+            id: key.id.clone(),
+            id_is_credential: key.secret.is_none(),
+        }
+    }
+
+    /// Identifies the key in logs: its ID, or `sha256:` and the first 8
+    /// bytes of the ID's SHA-256 in hex when the ID is the credential.
+    pub fn log_id(&self) -> String {
+        if !self.id_is_credential {
+            return self.id.clone();
         }
+        let digest = Sha256::digest(self.id.as_bytes());
+        let hex: String = digest[..8].iter().map(|b| format!("{b:02x}")).collect();
+        format!("sha256:{hex}")
     }
 }
 
+/// Identity of a request authenticated by its client certificate, added to
+/// the request's extensions by [`auth_middleware`] for a [`ClientIdentity`]
+/// in [`GatewayConfig::mtls`](crate::GatewayConfig::mtls) mode.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CertIdentity {
+    pub subject_cn: String,
+    pub scopes: Vec<String>,
+}
+
 pub async fn auth_middleware(
-    req: Request<Body>,
+    State(policy): State<Arc<AuthPolicy>>,
+    mut req: Request<Body>,
     next: Next,
 ) -> Response {
-    let config = AuthConfig::default();
+    let config = policy.config();
 
     if !config.require_auth {
         return next.run(req).await;
     }
 
-    let path = req.uri().path().to_string();
-    if config.bypass_paths.iter().any(|p| path.starts_with(p)) {
+    if policy.is_bypassed(req.uri().path()) {
         return next.run(req).await;
     }
 
+    let client_ip = req.extensions().get::<TrueClientIp>().map(|ip| ip.0);
+
+    if let Some(cert) = req.extensions().get::<ClientIdentity>() {
+        if let Some(scopes) = config.cert_identity_map.get(&cert.common_name) {
+            let identity = CertIdentity {
+                subject_cn: cert.common_name.clone(),
+                scopes: scopes.clone(),
+            };
+            req.extensions_mut().insert(identity);
+            return next.run(req).await;
+        }
+        if !req.headers().contains_key("x-api-key") {
+            return reject(StatusCode::FORBIDDEN, "unknown client certificate", client_ip);
+        }
+    }
+
     let api_key = req
         .headers()
         .get("x-api-key")
         .and_then(|v| v.to_str().ok());
 
     match api_key {
-        Some(key) => {
-            if config.api_keys.iter().any(|k| k.id == key) {
+        Some(key) => match config.api_keys.iter().find(|k| k.verify(key)) {
+            Some(key) => {
+                let key = AuthenticatedKey::new(key);
+                req.extensions_mut().insert(key);
                 next.run(req).await
-            } else {
-                (StatusCode::FORBIDDEN, "invalid API key").into_response()
             }
-        }
-        None => (StatusCode::UNAUTHORIZED, "API key required").into_response(),
+            None => reject(StatusCode::FORBIDDEN, "invalid API key", client_ip),
+        },
+        None => reject(StatusCode::UNAUTHORIZED, "API key required", client_ip),
+    }
+}
+
+/// Reject requests not authenticated by [`auth_middleware`] as a caller
+/// holding [`ADMIN_SCOPE`]. Layer inside [`auth_middleware`]. Passes
+/// everything when authentication is not required.
+pub async fn admin_only_middleware(
+    State(policy): State<Arc<AuthPolicy>>,
+    req: Request<Body>,
+    next: Next,
+) -> Response {
+    if !policy.config().require_auth {
+        return next.run(req).await;
+    }
+    let scopes = match (
+        req.extensions().get::<CertIdentity>(),
+        req.extensions().get::<AuthenticatedKey>(),
+    ) {
+        (Some(identity), _) => Some(&identity.scopes),
+        (None, Some(AuthenticatedKey { id, .. })) => policy
+            .config()
+            .api_keys
+            .iter()
+            .find(|k| &k.id == id)
+            .map(|k| &k.scopes),
+        (None, None) => None,
+    };
+    if scopes.is_some_and(|scopes| scopes.iter().any(|s| s == ADMIN_SCOPE)) {
+        return next.run(req).await;
     }
+    let client_ip = req.extensions().get::<TrueClientIp>().map(|ip| ip.0);
+    reject(StatusCode::FORBIDDEN, "admin scope required", client_ip)
+}
+
+/// Reject every request. Layered on
+/// [`AdminOnly`](crate::proxy::MiddlewareProfile::AdminOnly) routes when
+/// there is no [`AuthPolicy`], so that no caller can hold [`ADMIN_SCOPE`].
+pub async fn no_admin_middleware(req: Request<Body>, _next: Next) -> Response {
+    let client_ip = req.extensions().get::<TrueClientIp>().map(|ip| ip.0);
+    reject(StatusCode::FORBIDDEN, "admin scope required", client_ip)
+}
+
+/// Log a rejected request against its [`TrueClientIp`] and respond.
+fn reject(status: StatusCode, reason: &'static str, client_ip: Option<IpAddr>) -> Response {
+    warn!(?client_ip, reason, "request rejected");
+    (status, reason).into_response()
 }
 
 #[cfg(test)]
@@ -72,4 +433,205 @@ mod tests {
         assert!(!config.require_auth);
         assert!(config.bypass_paths.contains(&"/health".to_string()));
     }
+
+    #[test]
+    fn test_bypass_path_limit() {
+        let config = AuthConfig {
+            bypass_paths: (0..=MAX_BYPASS_PATHS).map(|i| format!("/p{i}")).collect(),
+            ..AuthConfig::default()
+        };
+        assert_eq!(
+            AuthPolicy::new(config).unwrap_err(),
+            AuthConfigError::TooManyBypassPaths {
+                count: MAX_BYPASS_PATHS + 1,
+                max: MAX_BYPASS_PATHS,
+            }
+        );
+    }
+
+    #[test]
+    fn test_scope_limit() {
+        let config = AuthConfig {
+            api_keys: vec![ApiKey {
+                id: "k1".into(),
+                name: "noisy".into(),
+                scopes: vec!["read".into(); MAX_SCOPES_PER_KEY + 1],
+                signing_key: None,
+                metadata: HashMap::new(),
+                secret: None,
+            }],
+            ..AuthConfig::default()
+        };
+        assert!(matches!(
+            config.validate(),
+            Err(AuthConfigError::TooManyScopes { ref key_id, .. }) if key_id == "k1"
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_cert_identity_auth() {
+        use axum::{middleware::from_fn_with_state, routing::get, Extension, Router};
+        use tower::ServiceExt;
+
+        let config = AuthConfig {
+            require_auth: true,
+            cert_identity_map: HashMap::from([(
+                "billing-svc".into(),
+                vec!["invoices:read".into()],
+            )]),
+            ..AuthConfig::default()
+        };
+        let policy = Arc::new(AuthPolicy::new(config).unwrap());
+        let app = Router::new()
+            .route(
+                "/api",
+                get(|Extension(identity): Extension<CertIdentity>| async move {
+                    identity.scopes.join(",")
+                }),
+            )
+            .layer(from_fn_with_state(policy, auth_middleware));
+
+        let request = |cn: &str| {
+            let mut req = Request::get("/api").body(Body::empty()).unwrap();
+            req.extensions_mut().insert(ClientIdentity {
+                common_name: cn.into(),
+                fingerprint: String::new(),
+                algorithm: None,
+            });
+            req
+        };
+
+        let response = app.clone().oneshot(request("billing-svc")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = http_body_util::BodyExt::collect(response.into_body())
+            .await
+            .unwrap()
+            .to_bytes();
+        assert_eq!(body, "invoices:read");
+
+        let response = app.oneshot(request("intruder")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    }
+
+    #[test]
+    fn test_policy_bypass() {
+        let policy = AuthPolicy::new(AuthConfig::default()).unwrap();
+        assert!(policy.is_bypassed("/health"));
+        assert!(policy.is_bypassed("/.well-known/qsgw-configuration"));
+        assert!(!policy.is_bypassed("/api/items"));
+    }
+
+    #[test]
+    fn test_api_key_from_env_var() {
+        let secret = "k".repeat(RECOMMENDED_API_KEY_LEN);
+        std::env::set_var("QSGW_TEST_API_KEY_OK", &secret);
+        let mut config = AuthConfig::default();
+        config
+            .add_key_from_env(
+                "QSGW_TEST_API_KEY_OK",
+                "billing",
+                "billing service",
+                vec!["invoices:read".into()],
+            )
+            .unwrap();
+
+        let key = &config.api_keys[0];
+        assert_eq!(key.id, "billing");
+        assert_eq!(key.scopes, ["invoices:read"]);
+        assert!(key.verify(&secret));
+        assert!(!key.verify("billing"));
+        assert!(!format!("{key:?}").contains(&secret));
+        assert!(!serde_json::to_string(key).unwrap().contains(&secret));
+
+        // Short but acceptable keys only warn.
+        std::env::set_var("QSGW_TEST_API_KEY_SHORT", "s".repeat(MIN_API_KEY_LEN));
+        assert!(ApiKey::from_env_var("QSGW_TEST_API_KEY_SHORT", "s", "short", vec![]).is_ok());
+    }
+
+    #[test]
+    fn test_generated_api_keys() {
+        let (key, secret) = ApiKey::generate("onboarding", vec!["read".into()]);
+        let (other, other_secret) = ApiKey::generate("onboarding", vec![]);
+
+        assert_eq!(secret.len(), 52);
+        assert!(secret
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || ('2'..='7').contains(&c)));
+        assert!(key.verify(&secret));
+        assert!(!key.verify(&other_secret));
+        assert!(!key.verify(&key.id));
+        assert_ne!(secret, other_secret);
+        assert_ne!(key.id, other.id);
+        assert_eq!(key.name, "onboarding");
+        assert!(!format!("{key:?}").contains(&secret));
+
+        assert_eq!(base32(b"foobar"), "mzxw6ytboi");
+    }
+
+    #[test]
+    fn test_api_key_from_env_var_errors() {
+        std::env::remove_var("QSGW_TEST_API_KEY_MISSING");
+        let mut config = AuthConfig::default();
+        assert_eq!(
+            config.add_key_from_env("QSGW_TEST_API_KEY_MISSING", "k", "missing", vec![]),
+            Err(AuthConfigError::EnvVarMissing(
+                "QSGW_TEST_API_KEY_MISSING".into()
+            ))
+        );
+        assert!(config.api_keys.is_empty());
+
+        for value in ["", "too-short"] {
+            std::env::set_var("QSGW_TEST_API_KEY_INVALID", value);
+            assert_eq!(
+                ApiKey::from_env_var("QSGW_TEST_API_KEY_INVALID", "k", "invalid", vec![])
+                    .unwrap_err(),
+                AuthConfigError::EnvVarInvalid {
+                    var_name: "QSGW_TEST_API_KEY_INVALID".into(),
+                    len: value.len(),
+                    min: MIN_API_KEY_LEN,
+                }
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_env_api_key_auth() {
+        use axum::{middleware::from_fn_with_state, routing::get, Extension, Router};
+        use tower::ServiceExt;
+
+        let secret = "e".repeat(RECOMMENDED_API_KEY_LEN);
+        std::env::set_var("QSGW_TEST_API_KEY_AUTH", &secret);
+        let mut config = AuthConfig {
+            require_auth: true,
+            ..AuthConfig::default()
+        };
+        config
+            .add_key_from_env("QSGW_TEST_API_KEY_AUTH", "ops", "operations", vec![])
+            .unwrap();
+        let policy = Arc::new(AuthPolicy::new(config).unwrap());
+        let app = Router::new()
+            .route(
+                "/api",
+                get(|Extension(key): Extension<AuthenticatedKey>| async move { key.id }),
+            )
+            .layer(from_fn_with_state(policy, auth_middleware));
+
+        let request = |key: &str| {
+            Request::get("/api")
+                .header("x-api-key", key)
+                .body(Body::empty())
+                .unwrap()
+        };
+        let response = app.clone().oneshot(request(&secret)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = http_body_util::BodyExt::collect(response.into_body())
+            .await
+            .unwrap()
+            .to_bytes();
+        assert_eq!(body, "ops");
+
+        // The ID alone no longer authenticates.
+        let response = app.oneshot(request("ops")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    }
 }
@@ -0,0 +1,136 @@
+use std::collections::BTreeMap;
+
+/// Set of path prefixes, stored as a byte trie so matching a request path
+/// costs O(path length) regardless of how many prefixes are configured.
+#[derive(Debug, Clone)]
+pub struct PathMatcher {
+    nodes: Vec<Node>,
+    len: usize,
+}
+
+#[derive(Debug, Clone, Default)]
+struct Node {
+    children: BTreeMap<u8, usize>,
+    terminal: bool,
+}
+
+impl PathMatcher {
+    pub fn new<I, S>(prefixes: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let mut matcher = Self {
+            nodes: vec![Node::default()],
+            len: 0,
+        };
+        for prefix in prefixes {
+            matcher.insert(prefix.as_ref());
+        }
+        matcher
+    }
+
+    fn insert(&mut self, prefix: &str) {
+        let mut node = 0;
+        for &b in prefix.as_bytes() {
+            node = match self.nodes[node].children.get(&b) {
+                Some(&next) => next,
+                None => {
+                    self.nodes.push(Node::default());
+                    let next = self.nodes.len() - 1;
+                    self.nodes[node].children.insert(b, next);
+                    next
+                }
+            };
+        }
+        if !self.nodes[node].terminal {
+            self.nodes[node].terminal = true;
+            self.len += 1;
+        }
+    }
+
+    /// Whether `path` starts with any configured prefix.
+    pub fn matches(&self, path: &str) -> bool {
+        self.walk(path).0
+    }
+
+    /// Number of distinct prefixes.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Walk the trie along `path`, returning whether a prefix matched and
+    /// how many nodes were visited.
+    fn walk(&self, path: &str) -> (bool, usize) {
+        let mut node = 0;
+        let mut visited = 1;
+        if self.nodes[node].terminal {
+            return (true, visited);
+        }
+        for b in path.bytes() {
+            match self.nodes[node].children.get(&b) {
+                Some(&next) => node = next,
+                None => return (false, visited),
+            }
+            visited += 1;
+            if self.nodes[node].terminal {
+                return (true, visited);
+            }
+        }
+        (false, visited)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn large_set() -> Vec<String> {
+        (0..5_000).map(|i| format!("/public/{i:05}/")).collect()
+    }
+
+    #[test]
+    fn agrees_with_linear_scan_on_large_set() {
+        let prefixes = large_set();
+        let matcher = PathMatcher::new(&prefixes);
+        assert_eq!(matcher.len(), prefixes.len());
+
+        for path in [
+            "/public/00000/",
+            "/public/04999/index.html",
+            "/public/05000/",
+            "/public/0123",
+            "/public/",
+            "/private/00001/",
+            "",
+        ] {
+            let linear = prefixes.iter().any(|p| path.starts_with(p.as_str()));
+            assert_eq!(matcher.matches(path), linear, "{path}");
+        }
+    }
+
+    #[test]
+    fn matching_does_not_scan_every_entry() {
+        let matcher = PathMatcher::new(large_set());
+        let path = "/public/02500/some/deep/resource";
+        let (matched, visited) = matcher.walk(path);
+        assert!(matched);
+        assert!(visited <= path.len() + 1);
+        assert!(visited < matcher.len() / 100);
+
+        let (matched, visited) = matcher.walk("/nope");
+        assert!(!matched);
+        assert!(visited <= 2);
+    }
+
+    #[test]
+    fn empty_prefix_matches_everything() {
+        let matcher = PathMatcher::new([""]);
+        assert!(matcher.matches("/anything"));
+        assert!(!PathMatcher::new(Vec::<String>::new()).matches("/anything"));
+    }
+}
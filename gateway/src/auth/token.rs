@@ -0,0 +1,168 @@
+//! Gateway-issued access tokens.
+//!
+//! A token is `base64url(claims) "." base64url(signature)`, where `claims`
+//! is the JSON [`TokenClaims`] and the signature covers the encoded claims.
+//! Signing goes through a [`RemoteSigner`], so the same issuer works with
+//! an in-process key or an HSM-backed one.
+
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use quantun_crypto::{CryptoError, CryptoResult, RemoteSigner};
+use quantun_types::Algorithm;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TokenClaims {
+    pub iss: String,
+    pub sub: String,
+    pub scopes: Vec<String>,
+    /// Issue time, seconds since the Unix epoch.
+    pub iat: u64,
+    /// Expiry, seconds since the Unix epoch.
+    pub exp: u64,
+}
+
+impl TokenClaims {
+    /// Split `token` into its claims, the signed portion, and the signature.
+    ///
+    /// Does not check the signature.
+    pub fn decode(token: &str) -> Option<(Self, &str, Vec<u8>)> {
+        let (payload, signature) = token.split_once('.')?;
+        let claims = serde_json::from_slice(&URL_SAFE_NO_PAD.decode(payload).ok()?).ok()?;
+        let signature = URL_SAFE_NO_PAD.decode(signature).ok()?;
+        Some((claims, payload, signature))
+    }
+}
+
+/// Issues signed [`TokenClaims`].
+pub struct TokenIssuer {
+    signer: Arc<dyn RemoteSigner>,
+    issuer: String,
+    ttl: Duration,
+}
+
+impl TokenIssuer {
+    pub fn new(signer: Arc<dyn RemoteSigner>, issuer: impl Into<String>, ttl: Duration) -> Self {
+        Self {
+            signer,
+            issuer: issuer.into(),
+            ttl,
+        }
+    }
+
+    pub fn algorithm(&self) -> Algorithm {
+        self.signer.algorithm()
+    }
+
+    /// The `iss` claim of issued tokens.
+    pub fn issuer(&self) -> &str {
+        &self.issuer
+    }
+
+    /// Public key verifiers use to check issued tokens.
+    pub fn public_key(&self) -> &[u8] {
+        self.signer.public_key()
+    }
+
+    /// Issue a token for `subject` carrying `scopes`.
+    pub async fn issue(&self, subject: &str, scopes: &[String]) -> CryptoResult<String> {
+        let iat = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+        let claims = TokenClaims {
+            iss: self.issuer.clone(),
+            sub: subject.to_string(),
+            scopes: scopes.to_vec(),
+            iat,
+            exp: iat + self.ttl.as_secs(),
+        };
+        let json =
+            serde_json::to_vec(&claims).map_err(|e| CryptoError::Serialization(e.to_string()))?;
+        let payload = URL_SAFE_NO_PAD.encode(json);
+        let signature = self.signer.sign(payload.as_bytes()).await?;
+        Ok(format!("{payload}.{}", URL_SAFE_NO_PAD.encode(signature)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metrics::GatewayMetrics;
+    use crate::signer::{HttpSigner, ManagedSigner, SignerPolicy};
+    use quantun_crypto::mldsa::{MlDsaKeyPair, MlDsaSignature, MlDsaVerifier};
+    use quantun_crypto::signer::LocalSigner;
+    use quantun_types::MlDsaVariant;
+
+    fn assert_valid(issuer: &TokenIssuer, token: &str) {
+        let (claims, payload, signature) = TokenClaims::decode(token).unwrap();
+        assert_eq!(claims.iss, "qsgw");
+        assert_eq!(claims.sub, "alice");
+        assert_eq!(claims.exp - claims.iat, 300);
+
+        let verifier = MlDsaVerifier {
+            variant: MlDsaVariant::MlDsa44,
+            public_key: issuer.public_key().to_vec(),
+        };
+        let sig = MlDsaSignature {
+            signature,
+            variant: MlDsaVariant::MlDsa44,
+        };
+        assert!(verifier.verify(payload.as_bytes(), &sig).unwrap());
+    }
+
+    #[tokio::test]
+    async fn issues_with_local_signer() {
+        let signer = LocalSigner::new(MlDsaKeyPair::generate(MlDsaVariant::MlDsa44).unwrap());
+        let issuer = TokenIssuer::new(Arc::new(signer), "qsgw", Duration::from_secs(300));
+        let token = issuer.issue("alice", &["read".into()]).await.unwrap();
+        assert_valid(&issuer, &token);
+    }
+
+    /// Minimal signing service holding `keypair`.
+    async fn signing_service(keypair: MlDsaKeyPair) -> std::net::SocketAddr {
+        use axum::body::Bytes;
+        use axum::routing::{get, post};
+
+        let keypair = Arc::new(keypair);
+        let public_key = keypair.public_key.clone();
+        let app = axum::Router::new()
+            .route("/public-key", get(move || async move { public_key }))
+            .route(
+                "/sign",
+                post(move |body: Bytes| async move { keypair.sign(&body).unwrap().signature }),
+            )
+            .route("/health", get(|| async {}));
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await });
+        addr
+    }
+
+    #[tokio::test]
+    async fn issues_with_remote_signer() {
+        let keypair = MlDsaKeyPair::generate(MlDsaVariant::MlDsa44).unwrap();
+        let addr = signing_service(keypair.clone()).await;
+        let http = HttpSigner::connect(
+            &format!("http://{addr}"),
+            Algorithm::MlDsa(MlDsaVariant::MlDsa44),
+        )
+        .await
+        .unwrap();
+        let metrics = Arc::new(GatewayMetrics::default());
+        let signer = ManagedSigner::new(Arc::new(http), SignerPolicy::default(), metrics.clone());
+        let issuer = TokenIssuer::new(Arc::new(signer), "qsgw", Duration::from_secs(300));
+
+        assert_eq!(issuer.public_key(), keypair.public_key.as_slice());
+        let token = issuer.issue("alice", &["read".into()]).await.unwrap();
+        assert_valid(&issuer, &token);
+        assert_eq!(
+            metrics
+                .remote_signs
+                .load(std::sync::atomic::Ordering::Relaxed),
+            1
+        );
+    }
+}
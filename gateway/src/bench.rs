@@ -0,0 +1,375 @@
+//! On-box throughput measurements run by `qsgw bench`.
+//!
+//! Both modes go through the library code paths the gateway itself uses:
+//! [`KeyPair`] for the crypto operations and the full router for proxied
+//! requests. Each worker runs unmeasured for the warmup period first.
+
+use axum::body::Body;
+use axum::Router;
+use http::Request;
+use http_body_util::BodyExt;
+use quantun_crypto::{CryptoError, CryptoResult, KeyPair};
+use quantun_types::Algorithm;
+use serde::Serialize;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use thiserror::Error;
+use tokio::net::TcpListener;
+use tower::ServiceExt;
+
+use crate::metrics::GatewayMetrics;
+use crate::proxy::{MiddlewareProfile, Route, Upstream};
+use crate::server::CIPHER_SUITE_HEADER;
+use crate::GatewayConfig;
+
+/// Path prefix the proxy benchmark routes to its loopback upstream.
+const PROXY_PREFIX: &str = "/bench";
+
+#[derive(Debug, Error)]
+pub enum BenchError {
+    #[error(transparent)]
+    Crypto(#[from] CryptoError),
+    #[error("loopback upstream: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("proxied request failed with status {0}")]
+    Request(http::StatusCode),
+}
+
+/// How long, and on how many workers, to run each measurement.
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct BenchSettings {
+    #[serde(rename = "duration_secs", serialize_with = "as_secs")]
+    pub duration: Duration,
+    #[serde(rename = "warmup_secs", serialize_with = "as_secs")]
+    pub warmup: Duration,
+    pub parallelism: usize,
+}
+
+impl Default for BenchSettings {
+    fn default() -> Self {
+        Self {
+            duration: Duration::from_secs(5),
+            warmup: Duration::from_secs(1),
+            parallelism: 1,
+        }
+    }
+}
+
+fn as_secs<S: serde::Serializer>(d: &Duration, s: S) -> Result<S::Ok, S::Error> {
+    s.serialize_f64(d.as_secs_f64())
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Operation {
+    Keygen,
+    Encapsulate,
+    Decapsulate,
+    Sign,
+    Verify,
+}
+
+impl std::fmt::Display for Operation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Operation::Keygen => "keygen",
+            Operation::Encapsulate => "encapsulate",
+            Operation::Decapsulate => "decapsulate",
+            Operation::Sign => "sign",
+            Operation::Verify => "verify",
+        })
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CryptoSample {
+    pub algorithm: String,
+    pub operation: Operation,
+    /// Operations completed across all workers after warmup.
+    pub ops: u64,
+    pub ops_per_sec: f64,
+}
+
+/// Latency percentiles in microseconds.
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct Latency {
+    pub p50_us: u64,
+    pub p90_us: u64,
+    pub p99_us: u64,
+    pub max_us: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ProxySample {
+    /// Whether [`pqc_enforcement_middleware`](crate::middleware::pqc_enforcement_middleware)
+    /// was in the router.
+    pub pqc_middleware: bool,
+    pub requests: u64,
+    pub requests_per_sec: f64,
+    pub latency: Latency,
+}
+
+/// Measure every operation `algorithm` supports: keygen, then either
+/// encapsulate and decapsulate or sign and verify.
+pub fn crypto(algorithm: Algorithm, settings: BenchSettings) -> CryptoResult<Vec<CryptoSample>> {
+    let keypair = KeyPair::generate(algorithm)?;
+    let sample = |operation, op: &(dyn Fn() -> CryptoResult<()> + Sync)| -> CryptoResult<_> {
+        let (ops, ops_per_sec) = run_threads(settings, op)?;
+        Ok(CryptoSample {
+            algorithm: algorithm.to_string(),
+            operation,
+            ops,
+            ops_per_sec,
+        })
+    };
+    let mut samples = vec![sample(Operation::Keygen, &|| {
+        KeyPair::generate(algorithm).map(drop)
+    })?];
+    let message = b"qsgw bench message";
+    match keypair {
+        KeyPair::MlKem(kp) => {
+            let encapsulated = kp.encapsulate()?;
+            samples.push(sample(Operation::Encapsulate, &|| {
+                kp.encapsulate().map(drop)
+            })?);
+            samples.push(sample(Operation::Decapsulate, &|| {
+                kp.decapsulate(&encapsulated.ciphertext).map(drop)
+            })?);
+        }
+        KeyPair::HybridKem(kp) => {
+            let encapsulated = kp.encapsulate()?;
+            samples.push(sample(Operation::Encapsulate, &|| {
+                kp.encapsulate().map(drop)
+            })?);
+            samples.push(sample(Operation::Decapsulate, &|| {
+                kp.decapsulate(&encapsulated.classical_public, &encapsulated.pqc_ciphertext)
+                    .map(drop)
+            })?);
+        }
+        KeyPair::MlDsa(kp) => {
+            let signature = kp.sign(message)?;
+            samples.push(sample(Operation::Sign, &|| kp.sign(message).map(drop))?);
+            samples.push(sample(Operation::Verify, &|| {
+                kp.verify(message, &signature).map(drop)
+            })?);
+        }
+        KeyPair::SlhDsa(kp) => {
+            let signature = kp.sign(message)?;
+            samples.push(sample(Operation::Sign, &|| kp.sign(message).map(drop))?);
+            samples.push(sample(Operation::Verify, &|| {
+                kp.verify(message, &signature).map(drop)
+            })?);
+        }
+    }
+    Ok(samples)
+}
+
+/// Run `op` on `settings.parallelism` threads, returning the operations
+/// completed after warmup and their combined rate.
+fn run_threads(
+    settings: BenchSettings,
+    op: &(dyn Fn() -> CryptoResult<()> + Sync),
+) -> CryptoResult<(u64, f64)> {
+    let start = Instant::now();
+    let measure_from = start + settings.warmup;
+    let until = measure_from + settings.duration;
+    std::thread::scope(|scope| {
+        let workers: Vec<_> = (0..settings.parallelism.max(1))
+            .map(|_| {
+                scope.spawn(move || -> CryptoResult<_> {
+                    while Instant::now() < measure_from {
+                        op()?;
+                    }
+                    let measuring = Instant::now();
+                    let mut ops = 0u64;
+                    // Always finish one operation, however slow.
+                    while ops == 0 || Instant::now() < until {
+                        op()?;
+                        ops += 1;
+                    }
+                    Ok((ops, ops as f64 / measuring.elapsed().as_secs_f64()))
+                })
+            })
+            .collect();
+        workers
+            .into_iter()
+            .try_fold((0, 0.0), |(ops, rate), worker| -> CryptoResult<_> {
+                let (n, r) = worker.join().expect("bench worker panicked")?;
+                Ok((ops + n, rate + r))
+            })
+    })
+}
+
+/// Measure `GET` requests proxied through the router to a loopback
+/// upstream, once with and once without the PQC enforcement middleware.
+/// Requests carry a PQC cipher suite header, as a TLS terminator would set.
+pub async fn proxy(settings: BenchSettings) -> Result<Vec<ProxySample>, BenchError> {
+    let listener = TcpListener::bind("127.0.0.1:0").await?;
+    let addr = listener.local_addr()?;
+    let upstream = tokio::spawn(async move {
+        let app = Router::new().fallback(|| async { "ok" });
+        let _ = axum::serve(listener, app).await;
+    });
+
+    let config = GatewayConfig {
+        routes: vec![Route {
+            path_prefix: PROXY_PREFIX.into(),
+            upstream: Upstream {
+                name: "bench".into(),
+                host: addr.ip().to_string(),
+                port: addr.port(),
+                is_healthy: true,
+                tls_verify: false,
+                tls: false,
+                weight: 1,
+            },
+            strip_prefix: true,
+            path_rewrite: None,
+            priority: 0,
+            coalesce: None,
+            cache: None,
+            tunnel: None,
+            unseal: None,
+            transform: None,
+            sse_filter: None,
+            allowed_methods: Vec::new(),
+            denied_methods: Vec::new(),
+            content_type: None,
+            middleware_profile: MiddlewareProfile::NoRateLimit,
+        }],
+        ..GatewayConfig::default()
+    };
+    let mut samples = Vec::new();
+    for pqc_middleware in [true, false] {
+        let router = crate::router(&config, Arc::new(GatewayMetrics::default()), pqc_middleware);
+        samples.push(proxy_sample(router, settings, pqc_middleware).await?);
+    }
+    upstream.abort();
+    Ok(samples)
+}
+
+async fn proxy_sample(
+    router: Router,
+    settings: BenchSettings,
+    pqc_middleware: bool,
+) -> Result<ProxySample, BenchError> {
+    let start = Instant::now();
+    let measure_from = start + settings.warmup;
+    let until = measure_from + settings.duration;
+    let workers: Vec<_> = (0..settings.parallelism.max(1))
+        .map(|_| {
+            let router = router.clone();
+            tokio::spawn(async move {
+                let mut latencies = Vec::new();
+                let mut measuring = None;
+                loop {
+                    let sent = Instant::now();
+                    if sent >= until && !latencies.is_empty() {
+                        break;
+                    }
+                    let request = Request::get(format!("{PROXY_PREFIX}/ping"))
+                        .header(
+                            CIPHER_SUITE_HEADER,
+                            "TLS_AES_256_GCM_SHA384 X25519-ML-KEM-768",
+                        )
+                        .body(Body::empty())
+                        .expect("valid request");
+                    let response = router.clone().oneshot(request).await.expect("infallible");
+                    let status = response.status();
+                    let _ = response.into_body().collect().await;
+                    if !status.is_success() {
+                        return Err(BenchError::Request(status));
+                    }
+                    if sent >= measure_from {
+                        measuring.get_or_insert(sent);
+                        latencies.push(sent.elapsed());
+                    }
+                }
+                let elapsed = measuring.map_or(Duration::ZERO, |m| m.elapsed());
+                Ok((latencies, elapsed))
+            })
+        })
+        .collect();
+
+    let mut latencies = Vec::new();
+    let mut requests_per_sec = 0.0;
+    for worker in workers {
+        let (worker_latencies, elapsed) = worker.await.expect("bench worker panicked")?;
+        requests_per_sec += worker_latencies.len() as f64 / elapsed.as_secs_f64();
+        latencies.extend(worker_latencies);
+    }
+    latencies.sort_unstable();
+    Ok(ProxySample {
+        pqc_middleware,
+        requests: latencies.len() as u64,
+        requests_per_sec,
+        latency: Latency {
+            p50_us: percentile(&latencies, 50),
+            p90_us: percentile(&latencies, 90),
+            p99_us: percentile(&latencies, 99),
+            max_us: percentile(&latencies, 100),
+        },
+    })
+}
+
+/// The `p`th percentile of sorted `latencies`, in microseconds.
+fn percentile(latencies: &[Duration], p: usize) -> u64 {
+    let Some(last) = latencies.len().checked_sub(1) else {
+        return 0;
+    };
+    latencies[last * p / 100].as_micros() as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use quantun_types::{MlDsaVariant, MlKemVariant};
+
+    fn quick() -> BenchSettings {
+        BenchSettings {
+            duration: Duration::from_millis(50),
+            warmup: Duration::from_millis(10),
+            parallelism: 2,
+        }
+    }
+
+    #[test]
+    fn crypto_measures_each_operation() {
+        let kem = crypto(Algorithm::MlKem(MlKemVariant::MlKem512), quick()).unwrap();
+        let ops: Vec<_> = kem.iter().map(|s| s.operation).collect();
+        assert_eq!(
+            ops,
+            [
+                Operation::Keygen,
+                Operation::Encapsulate,
+                Operation::Decapsulate
+            ]
+        );
+        let dsa = crypto(Algorithm::MlDsa(MlDsaVariant::MlDsa44), quick()).unwrap();
+        assert_eq!(dsa[2].operation, Operation::Verify);
+        assert!(kem
+            .iter()
+            .chain(&dsa)
+            .all(|s| s.ops > 0 && s.ops_per_sec > 0.0));
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn proxy_measures_with_and_without_pqc_middleware() {
+        let samples = proxy(quick()).await.unwrap();
+        assert_eq!(samples.len(), 2);
+        assert!(samples[0].pqc_middleware && !samples[1].pqc_middleware);
+        for sample in samples {
+            assert!(sample.requests > 0);
+            assert!(sample.latency.p50_us <= sample.latency.p99_us);
+            assert!(sample.latency.p99_us <= sample.latency.max_us);
+        }
+    }
+
+    #[test]
+    fn percentile_of_sorted_latencies() {
+        let latencies: Vec<_> = (1..=10).map(Duration::from_micros).collect();
+        assert_eq!(percentile(&latencies, 50), 5);
+        assert_eq!(percentile(&latencies, 100), 10);
+        assert_eq!(percentile(&[], 99), 0);
+    }
+}
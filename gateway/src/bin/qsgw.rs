@@ -0,0 +1,501 @@
+//! The `qsgw` command: run the gateway, check its configuration, list its
+//! cryptographic inventory, and generate keys.
+
+use clap::{Parser, Subcommand, ValueEnum};
+use quantun_crypto::{CryptoError, KeyPair};
+use quantun_qsgw_gateway::bench::{self, BenchSettings};
+use quantun_qsgw_gateway::config_file::{AuthFiles, ConfigFile, TunnelFiles};
+use quantun_qsgw_gateway::inventory::CryptoInventory;
+use quantun_qsgw_gateway::keyfile::{self, KeyEncryption, KeyFileError, PBKDF2_ITERATIONS};
+use quantun_qsgw_gateway::metrics::GatewayMetrics;
+use quantun_qsgw_gateway::tunnel::{TunnelAcceptor, TunnelSettings};
+use quantun_qsgw_gateway::{
+    build_router_with_metrics, listener, server, telemetry, tls, GatewayConfig,
+};
+use quantun_types::Algorithm;
+use std::fs::OpenOptions;
+use std::future::Future;
+use std::io::Write;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{info, warn};
+
+/// The command ran into an I/O or runtime failure.
+const EXIT_FAILURE: u8 = 1;
+/// The arguments were invalid; clap also exits with this code.
+const EXIT_USAGE: u8 = 2;
+/// The configuration file, or a file it names, is invalid.
+const EXIT_INVALID_CONFIG: u8 = 3;
+
+#[derive(Parser)]
+#[command(
+    name = "qsgw",
+    version,
+    about = "Quantum-safe API gateway",
+    after_help = "Exit codes: 0 success, 1 runtime failure, 2 usage error, 3 invalid configuration."
+)]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Run the gateway until interrupted.
+    Serve {
+        #[arg(long, default_value = "qsgw.yaml")]
+        config: PathBuf,
+        /// Listen on this address instead of the configured one.
+        #[arg(long)]
+        listen: Option<SocketAddr>,
+    },
+    /// Validate a configuration file and print it with defaults filled in.
+    CheckConfig {
+        #[arg(long, default_value = "qsgw.yaml")]
+        config: PathBuf,
+    },
+    /// List the certificates and algorithms a configuration uses, classified
+    /// by quantum vulnerability.
+    Inventory {
+        #[arg(long, default_value = "qsgw.yaml")]
+        config: PathBuf,
+        /// Print JSON instead of a table.
+        #[arg(long)]
+        json: bool,
+    },
+    /// Generate a key pair as PEM files: the private key at `--out` and the
+    /// public key beside it with a `.pub.pem` extension.
+    Keygen {
+        /// Algorithm name, e.g. ML-DSA-65, ML-KEM-768 or SLH-DSA-SHA2-128s.
+        #[arg(long)]
+        algorithm: Algorithm,
+        #[arg(long)]
+        out: PathBuf,
+        /// Encrypt the private key under the passphrase in this
+        /// environment variable.
+        #[arg(long, value_name = "VAR")]
+        passphrase_env: Option<String>,
+        #[arg(long, default_value_t = PBKDF2_ITERATIONS)]
+        kdf_iterations: u32,
+        /// Overwrite existing files.
+        #[arg(long)]
+        force: bool,
+    },
+    /// Measure crypto operations or proxied requests on this machine.
+    Bench {
+        mode: BenchMode,
+        /// Algorithms for the crypto mode; repeat or separate with commas.
+        #[arg(
+            long = "algorithm",
+            value_delimiter = ',',
+            default_values = ["ML-KEM-768", "ML-DSA-65"]
+        )]
+        algorithms: Vec<Algorithm>,
+        /// Seconds to measure each operation for.
+        #[arg(long, default_value = "5", value_parser = parse_secs)]
+        duration: Duration,
+        /// Seconds to run each operation unmeasured first.
+        #[arg(long, default_value = "1", value_parser = parse_secs)]
+        warmup: Duration,
+        /// Concurrent workers: threads for crypto, request loops for proxy.
+        #[arg(long, default_value_t = 1, value_parser = clap::value_parser!(u16).range(1..))]
+        parallelism: u16,
+        /// Print JSON instead of a table.
+        #[arg(long)]
+        json: bool,
+    },
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum BenchMode {
+    /// Keygen, encapsulate/decapsulate and sign/verify throughput.
+    Crypto,
+    /// Requests per second and latency through the router to a loopback
+    /// upstream, with and without the PQC middleware.
+    Proxy,
+}
+
+/// A failed command: its message and exit code.
+struct Failure(String, u8);
+
+fn main() -> ExitCode {
+    let result = match Cli::parse().command {
+        Command::Serve { config, listen } => serve(&config, listen),
+        Command::CheckConfig { config } => check_config(&config),
+        Command::Inventory { config, json } => inventory(&config, json),
+        Command::Keygen {
+            algorithm,
+            out,
+            passphrase_env,
+            kdf_iterations,
+            force,
+        } => keygen(algorithm, &out, passphrase_env, kdf_iterations, force),
+        Command::Bench {
+            mode,
+            algorithms,
+            duration,
+            warmup,
+            parallelism,
+            json,
+        } => {
+            let settings = BenchSettings {
+                duration,
+                warmup,
+                parallelism: parallelism.into(),
+            };
+            match mode {
+                BenchMode::Crypto => bench_crypto(&algorithms, settings, json),
+                BenchMode::Proxy => bench_proxy(settings, json),
+            }
+        }
+    };
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(Failure(message, code)) => {
+            eprintln!("error: {message}");
+            ExitCode::from(code)
+        }
+    }
+}
+
+/// The config file at `path`, the gateway config it describes, and the
+/// rustls server config if it configures TLS.
+struct Loaded {
+    file: ConfigFile,
+    config: GatewayConfig,
+    tls: Option<rustls::ServerConfig>,
+}
+
+/// Load `path` with its TLS, client CA and tunnel key files and its API
+/// key secrets.
+fn load(path: &Path) -> Result<Loaded, Failure> {
+    let invalid = |e: &dyn std::fmt::Display| Failure(e.to_string(), EXIT_INVALID_CONFIG);
+    let file = ConfigFile::load(path).map_err(|e| invalid(&e))?;
+    let mut config = file
+        .to_gateway_config()
+        .map_err(|e| invalid(&format_args!("{}: {e}", path.display())))?;
+    config.tunnel = file
+        .tunnel
+        .as_ref()
+        .map(TunnelFiles::load)
+        .transpose()
+        .map_err(|e| invalid(&format_args!("{}: tunnel: {e}", path.display())))?;
+    config.auth = file
+        .auth
+        .as_ref()
+        .map(AuthFiles::load)
+        .transpose()
+        .map_err(|e| invalid(&format_args!("{}: auth: {e}", path.display())))?
+        .map(Arc::new);
+    let tls = file
+        .tls
+        .as_ref()
+        .map(|files| {
+            tls::server_config(
+                file.tls_policy,
+                &files.cert_path,
+                &files.key_path,
+                config.mtls.as_deref(),
+            )
+        })
+        .transpose()
+        .map_err(|e| invalid(&format_args!("{}: tls: {e}", path.display())))?;
+    Ok(Loaded { file, config, tls })
+}
+
+fn check_config(path: &Path) -> Result<(), Failure> {
+    let Loaded { file, .. } = load(path)?;
+    print!("{}", file.to_yaml());
+    Ok(())
+}
+
+fn inventory(path: &Path, json: bool) -> Result<(), Failure> {
+    let Loaded { file, config, .. } = load(path)?;
+    let mut inventory = CryptoInventory::new().with_upstreams(Arc::new(config.proxy_service()));
+    if let Some(files) = &file.tls {
+        inventory = inventory.with_tls(quantun_tls::config::TlsConfig {
+            cert_path: files.cert_path.clone(),
+            key_path: files.key_path.clone(),
+            preferred_algorithms: config.preferred_algorithms.clone(),
+            ..quantun_tls::config::TlsConfig::default()
+        });
+    }
+    if let Some(mtls) = &config.mtls {
+        inventory = inventory.with_mtls(mtls.config().clone());
+    }
+    let report = inventory.report();
+    if json {
+        let json = serde_json::to_string_pretty(&report)
+            .map_err(|e| Failure(e.to_string(), EXIT_FAILURE))?;
+        println!("{json}");
+    } else {
+        print!("{}", report.to_table());
+    }
+    Ok(())
+}
+
+fn serve(path: &Path, listen: Option<SocketAddr>) -> Result<(), Failure> {
+    let Loaded {
+        mut config, tls, ..
+    } = load(path)?;
+    if let Some(listen) = listen {
+        config.listen_addr = listen;
+    }
+    telemetry::init_tracing(&config.tracing)
+        .map_err(|e| Failure(e.to_string(), EXIT_INVALID_CONFIG))?;
+    if let Err(e) = config.self_test.run_at_startup() {
+        telemetry::shutdown_tracing();
+        return Err(Failure(e.to_string(), EXIT_FAILURE));
+    }
+
+    let runtime = tokio::runtime::Runtime::new()
+        .map_err(|e| Failure(format!("starting runtime: {e}"), EXIT_FAILURE))?;
+    let result = runtime.block_on(async {
+        let listeners = listener::bind(config.listen_addr, &config.listener)
+            .map_err(|e| Failure(format!("binding {}: {e}", config.listen_addr), EXIT_FAILURE))?;
+        let addr = listeners[0]
+            .local_addr()
+            .map_err(|e| Failure(e.to_string(), EXIT_FAILURE))?;
+        let shutdown = shutdown_signal(config.listener.reuse_port)
+            .map_err(|e| Failure(format!("installing signal handlers: {e}"), EXIT_FAILURE))?;
+        let metrics = Arc::new(GatewayMetrics::with_sink(config.metrics_sink.clone()));
+        let router = build_router_with_metrics(&config, metrics.clone());
+        // Removed when serving ends, unless a successor has taken it over.
+        let _pid_file = match &config.listener.pid_file {
+            Some(pid_file) => Some(record_pid(pid_file, config.listener.reuse_port).map_err(
+                |e| Failure(format!("writing {}: {e}", pid_file.display()), EXIT_FAILURE),
+            )?),
+            None => None,
+        };
+        let tunnels = match config.tunnel.as_ref().filter(|t| t.listen_addr.is_some()) {
+            Some(tunnel) => Some(serve_tunnels(tunnel, router.clone()).await?),
+            None => None,
+        };
+        if config.auth.is_none() {
+            warn!("no auth section: routes are unauthenticated and admin routes refused");
+        }
+        info!(%addr, policy = ?config.tls_policy, tls = tls.is_some(), "qsgw listening");
+        let termination = match tls {
+            Some(server_config) => server::Termination::Tls(server::TlsTermination {
+                config: Arc::new(server_config),
+                handshake_timeout: Duration::from_secs(config.handshake_timeout_secs),
+            }),
+            None if config.trusted_terminator => server::Termination::TrustedProxy,
+            None => server::Termination::Plain,
+        };
+        server::serve_acceptors(listeners, router, termination, metrics, shutdown).await;
+        if let Some(tunnels) = tunnels {
+            tunnels.abort();
+        }
+        Ok(())
+    });
+    telemetry::shutdown_tracing();
+    result
+}
+
+/// Accept tunnels from peer gateways on `tunnel.listen_addr`, serving
+/// their requests with `router`.
+async fn serve_tunnels(
+    tunnel: &TunnelSettings,
+    router: axum::Router,
+) -> Result<tokio::task::JoinHandle<()>, Failure> {
+    let addr = tunnel.listen_addr.expect("tunnel listen address checked");
+    let listener = tokio::net::TcpListener::bind(addr)
+        .await
+        .map_err(|e| Failure(format!("binding tunnel listener {addr}: {e}"), EXIT_FAILURE))?;
+    info!(%addr, name = %tunnel.config.name, "accepting tunnels");
+    let acceptor = Arc::new(TunnelAcceptor::new(tunnel.config.clone(), router));
+    Ok(tokio::spawn(async move {
+        if let Err(e) = acceptor.serve(listener).await {
+            warn!(error = %e, "tunnel listener failed");
+        }
+    }))
+}
+
+/// Record this process in `pid_file`, first asking the process recorded
+/// there to hand off if the address is shared.
+#[cfg(unix)]
+fn record_pid(pid_file: &Path, reuse_port: bool) -> std::io::Result<listener::PidFile> {
+    if reuse_port {
+        listener::take_over(pid_file).map(|(pid_file, _)| pid_file)
+    } else {
+        listener::write_pid(pid_file)
+    }
+}
+
+#[cfg(not(unix))]
+fn record_pid(pid_file: &Path, _reuse_port: bool) -> std::io::Result<listener::PidFile> {
+    listener::write_pid(pid_file)
+}
+
+/// A future completing on Ctrl-C or SIGTERM, or with `reuse_port` on
+/// SIGUSR2 from a process taking over the address. Otherwise SIGUSR2 is
+/// ignored. The handlers are installed before this returns, so no signal
+/// sent after it is missed.
+#[cfg(unix)]
+fn shutdown_signal(reuse_port: bool) -> std::io::Result<impl Future<Output = ()>> {
+    use tokio::signal::unix::{signal, SignalKind};
+    let mut interrupt = signal(SignalKind::interrupt())?;
+    let mut terminate = signal(SignalKind::terminate())?;
+    let mut handoff = signal(SignalKind::user_defined2())?;
+    Ok(async move {
+        loop {
+            tokio::select! {
+                _ = interrupt.recv() => return,
+                _ = terminate.recv() => return,
+                _ = handoff.recv() => {
+                    if reuse_port {
+                        info!("received SIGUSR2, handing off to the new process");
+                        return;
+                    }
+                    warn!("ignoring SIGUSR2: listener.reuse_port is off");
+                }
+            }
+        }
+    })
+}
+
+#[cfg(not(unix))]
+fn shutdown_signal(_reuse_port: bool) -> std::io::Result<impl Future<Output = ()>> {
+    Ok(async {
+        let _ = tokio::signal::ctrl_c().await;
+    })
+}
+
+fn keygen(
+    algorithm: Algorithm,
+    out: &Path,
+    passphrase_env: Option<String>,
+    kdf_iterations: u32,
+    force: bool,
+) -> Result<(), Failure> {
+    let usage = |message: String| Failure(message, EXIT_USAGE);
+    let passphrase = passphrase_env
+        .map(|var| {
+            std::env::var(&var).map_err(|_| usage(format!("environment variable {var} is not set")))
+        })
+        .transpose()?;
+    let keypair = KeyPair::generate(algorithm).map_err(|e| match e {
+        CryptoError::UnsupportedAlgorithm(_) => usage(e.to_string()),
+        e => Failure(e.to_string(), EXIT_FAILURE),
+    })?;
+    let encryption = passphrase
+        .as_deref()
+        .map(|p| KeyEncryption::new(p.as_bytes()).with_iterations(kdf_iterations));
+    let files = keyfile::encode(&keypair, encryption).map_err(|e| match e {
+        KeyFileError::Unsupported(_) => usage(e.to_string()),
+        e => Failure(e.to_string(), EXIT_FAILURE),
+    })?;
+
+    let public_out = out.with_extension("pub.pem");
+    write_file(out, files.private_pem.as_bytes(), force, true)?;
+    write_file(&public_out, files.public_pem.as_bytes(), force, false)?;
+    println!(
+        "{algorithm} key pair written to {} and {}",
+        out.display(),
+        public_out.display()
+    );
+    Ok(())
+}
+
+/// Write `contents` to `path`, readable only by the owner if `private`.
+fn write_file(path: &Path, contents: &[u8], force: bool, private: bool) -> Result<(), Failure> {
+    let mut options = OpenOptions::new();
+    options.write(true);
+    if force {
+        options.create(true).truncate(true);
+    } else {
+        options.create_new(true);
+    }
+    #[cfg(unix)]
+    if private {
+        std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    }
+    #[cfg(not(unix))]
+    let _ = private;
+    options
+        .open(path)
+        .and_then(|mut file| file.write_all(contents))
+        .map_err(|e| Failure(format!("writing {}: {e}", path.display()), EXIT_FAILURE))
+}
+
+fn parse_secs(value: &str) -> Result<Duration, String> {
+    value
+        .parse()
+        .ok()
+        .and_then(|secs| Duration::try_from_secs_f64(secs).ok())
+        .ok_or_else(|| format!("{value:?} is not a number of seconds"))
+}
+
+fn bench_crypto(
+    algorithms: &[Algorithm],
+    settings: BenchSettings,
+    json: bool,
+) -> Result<(), Failure> {
+    let mut samples = Vec::new();
+    for &algorithm in algorithms {
+        samples.extend(bench::crypto(algorithm, settings).map_err(|e| match e {
+            CryptoError::UnsupportedAlgorithm(_) => Failure(e.to_string(), EXIT_USAGE),
+            e => Failure(e.to_string(), EXIT_FAILURE),
+        })?);
+    }
+    if json {
+        print_json("crypto", settings, &samples);
+    } else {
+        println!(
+            "{:<22} {:<12} {:>10} {:>14}",
+            "algorithm", "operation", "ops", "ops/sec"
+        );
+        for s in &samples {
+            println!(
+                "{:<22} {:<12} {:>10} {:>14.1}",
+                s.algorithm,
+                s.operation.to_string(),
+                s.ops,
+                s.ops_per_sec
+            );
+        }
+    }
+    Ok(())
+}
+
+fn bench_proxy(settings: BenchSettings, json: bool) -> Result<(), Failure> {
+    let runtime = tokio::runtime::Runtime::new()
+        .map_err(|e| Failure(format!("starting runtime: {e}"), EXIT_FAILURE))?;
+    let samples = runtime
+        .block_on(bench::proxy(settings))
+        .map_err(|e| Failure(e.to_string(), EXIT_FAILURE))?;
+    if json {
+        print_json("proxy", settings, &samples);
+    } else {
+        println!(
+            "{:<15} {:>10} {:>12} {:>9} {:>9} {:>9} {:>9}",
+            "pqc middleware", "requests", "req/sec", "p50 us", "p90 us", "p99 us", "max us"
+        );
+        for s in &samples {
+            println!(
+                "{:<15} {:>10} {:>12.1} {:>9} {:>9} {:>9} {:>9}",
+                if s.pqc_middleware { "on" } else { "off" },
+                s.requests,
+                s.requests_per_sec,
+                s.latency.p50_us,
+                s.latency.p90_us,
+                s.latency.p99_us,
+                s.latency.max_us
+            );
+        }
+    }
+    Ok(())
+}
+
+fn print_json(mode: &str, settings: BenchSettings, results: &impl serde::Serialize) {
+    let report = serde_json::json!({
+        "mode": mode,
+        "settings": settings,
+        "results": results,
+    });
+    println!("{report:#}");
+}
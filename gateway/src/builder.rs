@@ -0,0 +1,789 @@
+//! Fluent construction of a [`GatewayConfig`] and its routes, for
+//! embedding the gateway as a library.
+//!
+//! ```
+//! use quantun_qsgw_gateway::builder::RouteBuilder;
+//! use quantun_qsgw_gateway::{GatewayConfig, TlsPolicy};
+//!
+//! let config = GatewayConfig::builder()
+//!     .listen(([127, 0, 0, 1], 8443).into())
+//!     .tls_policy(TlsPolicy::PqcOnly)
+//!     .route(
+//!         RouteBuilder::new("/api")
+//!             .upstream("api", "10.0.0.5", 8080)
+//!             .strip_prefix(true),
+//!     )
+//!     .build()
+//!     .unwrap();
+//! assert_eq!(config.routes.len(), 1);
+//! ```
+
+use quantun_crypto::KeyStore;
+use quantun_types::Algorithm;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use thiserror::Error;
+
+use crate::auth::AuthPolicy;
+use crate::devices::DeviceRegistry;
+use crate::inventory::CryptoInventory;
+use crate::jwks::JwksService;
+use crate::kem::KemService;
+use crate::listener::ListenerConfig;
+use crate::maintenance::MaintenanceConfig;
+use crate::metrics::MetricsSink;
+use crate::proxy::cache::CacheConfig;
+use crate::proxy::coalesce::CoalesceConfig;
+use crate::proxy::egress::{EgressConfig, EgressProtocol};
+use crate::proxy::rewrite::PathRewrite;
+use crate::proxy::sealed::{UnsealConfig, Unsealer};
+use crate::proxy::slow_start::SlowStartConfig;
+use crate::proxy::transform::TransformConfig;
+use crate::proxy::upstream_tls::UpstreamTlsConfig;
+use crate::proxy::{
+    normalize_routes, validate_routes, ForwardedProto, MiddlewareProfile, ProxyError, Route,
+    Upstream,
+};
+use crate::rate_limit::{RateLimit, RateLimitError, RateLimiter};
+use crate::rotation::RotationScheduler;
+use crate::scanner::UpstreamScanner;
+use crate::self_test::SelfTestConfig;
+use crate::telemetry::TracingConfig;
+use crate::tenant::TenantPolicy;
+use crate::tls::{ClientCertVerifier, MtlsConfig, TlsError};
+use crate::tunnel::TunnelSettings;
+use crate::{GatewayConfig, TlsPolicy};
+
+/// Why a builder could not produce a valid configuration.
+#[derive(Debug, Error)]
+pub enum ConfigError {
+    #[error("max_connections must be at least 1")]
+    NoConnections,
+    #[error("listener acceptors must be at least 1")]
+    NoAcceptors,
+    #[error("no preferred algorithms specified")]
+    NoPreferredAlgorithms,
+    #[error("upstream timeout must be at least one second")]
+    ZeroUpstreamTimeout,
+    #[error("upstream TLS session cache must hold at least one session")]
+    ZeroSessionCache,
+    #[error("slow start min_fraction must be above 0 and at most 1, got {0}")]
+    InvalidSlowStart(f64),
+    #[error("handshake timeout must be at least one second")]
+    ZeroHandshakeTimeout,
+    #[error("invalid denied method {0:?}")]
+    InvalidMethod(String),
+    #[error("load shed threshold {threshold} exceeds max_connections {max}")]
+    LoadShedAboveMax { threshold: usize, max: usize },
+    #[error("egress proxy {0} uses SOCKS5, which takes no credentials")]
+    SocksCredentials(String),
+    #[error("route {0:?} has no upstream")]
+    MissingUpstream(String),
+    #[error("tunnel peer {0:?} has an address but no trusted key")]
+    UntrustedTunnelPeer(String),
+    #[error("route {route:?} tunnels to {peer:?}, which has no tunnel address")]
+    UnknownTunnelPeer { route: String, peer: String },
+    #[error("route {0:?} unseals request bodies but no unsealer is configured")]
+    NoUnsealer(String),
+    #[error("mtls: {0}")]
+    Mtls(#[from] TlsError),
+    #[error(transparent)]
+    RateLimit(#[from] RateLimitError),
+    #[error(transparent)]
+    Route(#[from] ProxyError),
+}
+
+/// Builds a [`Route`]. Upstreams are healthy and verify TLS unless set
+/// otherwise.
+#[derive(Debug, Clone)]
+pub struct RouteBuilder {
+    path_prefix: String,
+    upstream: Option<Upstream>,
+    strip_prefix: bool,
+    path_rewrite: Option<PathRewrite>,
+    priority: i32,
+    coalesce: Option<CoalesceConfig>,
+    cache: Option<CacheConfig>,
+    tunnel: Option<String>,
+    unseal: Option<UnsealConfig>,
+    transform: Option<TransformConfig>,
+    sse_filter: Option<String>,
+    allowed_methods: Vec<String>,
+    denied_methods: Vec<String>,
+    content_type: Option<String>,
+    middleware_profile: MiddlewareProfile,
+}
+
+impl RouteBuilder {
+    pub fn new(path_prefix: impl Into<String>) -> Self {
+        Self {
+            path_prefix: path_prefix.into(),
+            upstream: None,
+            strip_prefix: false,
+            path_rewrite: None,
+            priority: 0,
+            coalesce: None,
+            cache: None,
+            tunnel: None,
+            unseal: None,
+            transform: None,
+            sse_filter: None,
+            allowed_methods: Vec::new(),
+            denied_methods: Vec::new(),
+            content_type: None,
+            middleware_profile: MiddlewareProfile::Default,
+        }
+    }
+
+    pub fn upstream(mut self, name: impl Into<String>, host: impl Into<String>, port: u16) -> Self {
+        self.upstream = Some(Upstream {
+            name: name.into(),
+            host: host.into(),
+            port,
+            is_healthy: true,
+            tls_verify: true,
+            tls: false,
+            weight: 1,
+        });
+        self
+    }
+
+    /// Whether the upstream's certificate is verified. Call after
+    /// [`RouteBuilder::upstream`].
+    pub fn tls_verify(mut self, verify: bool) -> Self {
+        if let Some(upstream) = &mut self.upstream {
+            upstream.tls_verify = verify;
+        }
+        self
+    }
+
+    /// Whether the upstream is reached over HTTPS. Call after
+    /// [`RouteBuilder::upstream`].
+    pub fn tls(mut self, tls: bool) -> Self {
+        if let Some(upstream) = &mut self.upstream {
+            upstream.tls = tls;
+        }
+        self
+    }
+
+    pub fn strip_prefix(mut self, strip: bool) -> Self {
+        self.strip_prefix = strip;
+        self
+    }
+
+    /// Rewrite the upstream path; see [`Route::path_rewrite`].
+    pub fn path_rewrite(mut self, rewrite: PathRewrite) -> Self {
+        self.path_rewrite = Some(rewrite);
+        self
+    }
+
+    pub fn priority(mut self, priority: i32) -> Self {
+        self.priority = priority;
+        self
+    }
+
+    pub fn coalesce(mut self, coalesce: CoalesceConfig) -> Self {
+        self.coalesce = Some(coalesce);
+        self
+    }
+
+    pub fn cache(mut self, cache: CacheConfig) -> Self {
+        self.cache = Some(cache);
+        self
+    }
+
+    /// Forward through the tunnel to `peer`; see [`Route::tunnel`].
+    pub fn tunnel(mut self, peer: impl Into<String>) -> Self {
+        self.tunnel = Some(peer.into());
+        self
+    }
+
+    pub fn unseal(mut self, unseal: UnsealConfig) -> Self {
+        self.unseal = Some(unseal);
+        self
+    }
+
+    /// Transform bodies; see [`Route::transform`].
+    pub fn transform(mut self, transform: TransformConfig) -> Self {
+        self.transform = Some(transform);
+        self
+    }
+
+    /// Filter event streams; see [`Route::sse_filter`].
+    pub fn sse_filter(mut self, name: impl Into<String>) -> Self {
+        self.sse_filter = Some(name.into());
+        self
+    }
+
+    /// Only forward these methods; see [`Route::allowed_methods`].
+    pub fn allowed_methods<I, S>(mut self, methods: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.allowed_methods = methods.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Never forward these methods; see [`Route::denied_methods`].
+    pub fn denied_methods<I, S>(mut self, methods: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.denied_methods = methods.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Only match this `Content-Type`; see [`Route::content_type`].
+    pub fn content_type(mut self, content_type: impl Into<String>) -> Self {
+        self.content_type = Some(content_type.into());
+        self
+    }
+
+    pub fn middleware_profile(mut self, profile: MiddlewareProfile) -> Self {
+        self.middleware_profile = profile;
+        self
+    }
+
+    /// The route, checked with [`validate_routes`] and its prefix
+    /// normalized with [`normalize_routes`].
+    pub fn build(self) -> Result<Route, ConfigError> {
+        let upstream = self
+            .upstream
+            .ok_or_else(|| ConfigError::MissingUpstream(self.path_prefix.clone()))?;
+        let mut route = Route {
+            path_prefix: self.path_prefix,
+            upstream,
+            strip_prefix: self.strip_prefix,
+            path_rewrite: self.path_rewrite,
+            priority: self.priority,
+            coalesce: self.coalesce,
+            cache: self.cache,
+            tunnel: self.tunnel,
+            unseal: self.unseal,
+            transform: self.transform,
+            sse_filter: self.sse_filter,
+            allowed_methods: self.allowed_methods,
+            denied_methods: self.denied_methods,
+            content_type: self.content_type,
+            middleware_profile: self.middleware_profile,
+        };
+        validate_routes(std::slice::from_ref(&route))?;
+        normalize_routes(std::slice::from_mut(&mut route));
+        Ok(route)
+    }
+}
+
+/// Builds a [`GatewayConfig`], starting from its defaults.
+pub struct GatewayConfigBuilder {
+    config: GatewayConfig,
+    mtls: Option<MtlsConfig>,
+    rate_limit: Option<RateLimit>,
+    routes: Vec<RouteBuilder>,
+}
+
+impl GatewayConfigBuilder {
+    pub fn new() -> Self {
+        Self {
+            config: GatewayConfig::default(),
+            mtls: None,
+            rate_limit: None,
+            routes: Vec::new(),
+        }
+    }
+
+    pub fn listen(mut self, addr: SocketAddr) -> Self {
+        self.config.listen_addr = addr;
+        self
+    }
+
+    pub fn listener(mut self, listener: ListenerConfig) -> Self {
+        self.config.listener = listener;
+        self
+    }
+
+    pub fn tls_policy(mut self, policy: TlsPolicy) -> Self {
+        self.config.tls_policy = policy;
+        self
+    }
+
+    pub fn preferred_algorithms(mut self, algorithms: Vec<Algorithm>) -> Self {
+        self.config.preferred_algorithms = algorithms;
+        self
+    }
+
+    pub fn max_connections(mut self, n: usize) -> Self {
+        self.config.max_connections = n;
+        self
+    }
+
+    pub fn upstream_timeout_secs(mut self, secs: u64) -> Self {
+        self.config.upstream_timeout_secs = secs;
+        self
+    }
+
+    pub fn upstream_tls(mut self, upstream_tls: UpstreamTlsConfig) -> Self {
+        self.config.upstream_tls = upstream_tls;
+        self
+    }
+
+    pub fn slow_start(mut self, slow_start: SlowStartConfig) -> Self {
+        self.config.slow_start = Some(slow_start);
+        self
+    }
+
+    pub fn handshake_timeout_secs(mut self, secs: u64) -> Self {
+        self.config.handshake_timeout_secs = secs;
+        self
+    }
+
+    pub fn load_shed_threshold(mut self, threshold: usize) -> Self {
+        self.config.load_shed_threshold = Some(threshold);
+        self
+    }
+
+    pub fn server_timing(mut self, enabled: bool) -> Self {
+        self.config.server_timing = enabled;
+        self
+    }
+
+    pub fn catch_panics(mut self, enabled: bool) -> Self {
+        self.config.catch_panics = enabled;
+        self
+    }
+
+    pub fn maintenance(mut self, maintenance: MaintenanceConfig) -> Self {
+        self.config.maintenance = maintenance;
+        self
+    }
+
+    pub fn self_test(mut self, self_test: SelfTestConfig) -> Self {
+        self.config.self_test = self_test;
+        self
+    }
+
+    pub fn denied_methods(mut self, methods: Vec<String>) -> Self {
+        self.config.denied_methods = methods;
+        self
+    }
+
+    pub fn forwarded_proto(mut self, proto: ForwardedProto) -> Self {
+        self.config.forwarded_proto = proto;
+        self
+    }
+
+    pub fn egress(mut self, egress: EgressConfig) -> Self {
+        self.config.egress = egress;
+        self
+    }
+
+    /// Require client certificates. The CAs are loaded by
+    /// [`GatewayConfigBuilder::build`].
+    pub fn mtls(mut self, mtls: MtlsConfig) -> Self {
+        self.mtls = Some(mtls);
+        self
+    }
+
+    pub fn trusted_terminator(mut self, trusted: bool) -> Self {
+        self.config.trusted_terminator = trusted;
+        self
+    }
+
+    pub fn trusted_proxies(mut self, cidrs: Vec<ipnet::IpNet>) -> Self {
+        self.config.trusted_proxies = cidrs;
+        self
+    }
+
+    pub fn early_data(mut self, policy: quantun_tls::config::EarlyDataPolicy) -> Self {
+        self.config.early_data = policy;
+        self
+    }
+
+    pub fn tracing(mut self, tracing: TracingConfig) -> Self {
+        self.config.tracing = tracing;
+        self
+    }
+
+    pub fn tunnel(mut self, tunnel: TunnelSettings) -> Self {
+        self.config.tunnel = Some(tunnel);
+        self
+    }
+
+    pub fn unsealer(mut self, unsealer: Arc<Unsealer>) -> Self {
+        self.config.unsealer = Some(unsealer);
+        self
+    }
+
+    pub fn auth(mut self, auth: Arc<AuthPolicy>) -> Self {
+        self.config.auth = Some(auth);
+        self
+    }
+
+    pub fn rate_limit(mut self, limit: RateLimit) -> Self {
+        self.rate_limit = Some(limit);
+        self
+    }
+
+    pub fn tenant(mut self, tenant: Arc<TenantPolicy>) -> Self {
+        self.config.tenant = Some(tenant);
+        self
+    }
+
+    pub fn rotation(mut self, scheduler: Arc<RotationScheduler>) -> Self {
+        self.config.rotation = Some(scheduler);
+        self
+    }
+
+    pub fn keystore(mut self, keystore: Arc<KeyStore>) -> Self {
+        self.config.keystore = Some(keystore);
+        self
+    }
+
+    pub fn devices(mut self, registry: Arc<DeviceRegistry>) -> Self {
+        self.config.devices = Some(registry);
+        self
+    }
+
+    pub fn inventory(mut self, inventory: Arc<CryptoInventory>) -> Self {
+        self.config.inventory = Some(inventory);
+        self
+    }
+
+    pub fn jwks(mut self, service: Arc<JwksService>) -> Self {
+        self.config.jwks = Some(service);
+        self
+    }
+
+    pub fn kem(mut self, service: Arc<KemService>) -> Self {
+        self.config.kem = Some(service);
+        self
+    }
+
+    pub fn scanner(mut self, scanner: Arc<UpstreamScanner>) -> Self {
+        self.config.scanner = Some(scanner);
+        self
+    }
+
+    pub fn metrics_sink(mut self, sink: Arc<dyn MetricsSink>) -> Self {
+        self.config.metrics_sink = sink;
+        self
+    }
+
+    /// Add a route; routes are built and validated together on
+    /// [`GatewayConfigBuilder::build`].
+    pub fn route(mut self, route: RouteBuilder) -> Self {
+        self.routes.push(route);
+        self
+    }
+
+    pub fn build(self) -> Result<GatewayConfig, ConfigError> {
+        let mut config = self.config;
+        if config.max_connections == 0 {
+            return Err(ConfigError::NoConnections);
+        }
+        if config.listener.acceptors == 0 {
+            return Err(ConfigError::NoAcceptors);
+        }
+        if config.preferred_algorithms.is_empty() {
+            return Err(ConfigError::NoPreferredAlgorithms);
+        }
+        if config.upstream_timeout_secs == 0 {
+            return Err(ConfigError::ZeroUpstreamTimeout);
+        }
+        if config.upstream_tls.session_resumption && config.upstream_tls.session_cache_size == 0 {
+            return Err(ConfigError::ZeroSessionCache);
+        }
+        if let Some(slow_start) = &config.slow_start {
+            if !(slow_start.min_fraction > 0.0 && slow_start.min_fraction <= 1.0) {
+                return Err(ConfigError::InvalidSlowStart(slow_start.min_fraction));
+            }
+        }
+        if config.handshake_timeout_secs == 0 {
+            return Err(ConfigError::ZeroHandshakeTimeout);
+        }
+        if let Some(method) = config
+            .denied_methods
+            .iter()
+            .find(|m| http::Method::from_bytes(m.as_bytes()).is_err())
+        {
+            return Err(ConfigError::InvalidMethod(method.clone()));
+        }
+        if let Some(threshold) = config.load_shed_threshold {
+            if threshold > config.max_connections {
+                return Err(ConfigError::LoadShedAboveMax {
+                    threshold,
+                    max: config.max_connections,
+                });
+            }
+        }
+        let socks_credentials = config
+            .egress
+            .proxies()
+            .find(|p| p.protocol == EgressProtocol::Socks5 && p.credentials.is_some());
+        if let Some(proxy) = socks_credentials {
+            return Err(ConfigError::SocksCredentials(proxy.to_string()));
+        }
+        config.routes = self
+            .routes
+            .into_iter()
+            .map(RouteBuilder::build)
+            .collect::<Result<_, _>>()?;
+        validate_routes(&config.routes)?;
+        if let Some(tunnel) = &config.tunnel {
+            if let Some(peer) = tunnel
+                .peer_addrs
+                .keys()
+                .find(|peer| !tunnel.config.peers.contains_key(*peer))
+            {
+                return Err(ConfigError::UntrustedTunnelPeer(peer.clone()));
+            }
+        }
+        validate_tunnel_routes(&config.routes, |peer| {
+            config
+                .tunnel
+                .as_ref()
+                .is_some_and(|tunnel| tunnel.peer_addrs.contains_key(peer))
+        })?;
+        validate_unseal_routes(&config.routes, config.unsealer.is_some())?;
+        if let Some(mtls) = self.mtls {
+            config.mtls = Some(Arc::new(ClientCertVerifier::load(mtls)?));
+        }
+        if let Some(limit) = self.rate_limit {
+            config.rate_limit = Some(Arc::new(RateLimiter::new(limit)?));
+        }
+        Ok(config)
+    }
+}
+
+/// Check that routes with `unseal` set have an unsealer to open bodies
+/// with, so sealed requests do not fail with a `500`.
+pub(crate) fn validate_unseal_routes(
+    routes: &[Route],
+    has_unsealer: bool,
+) -> Result<(), ConfigError> {
+    match routes.iter().find(|route| route.unseal.is_some()) {
+        Some(route) if !has_unsealer => Err(ConfigError::NoUnsealer(route.path_prefix.clone())),
+        _ => Ok(()),
+    }
+}
+
+/// Check that every route with a `tunnel` names a peer that `dialed`
+/// accepts, so it does not fail on every request.
+pub(crate) fn validate_tunnel_routes(
+    routes: &[Route],
+    dialed: impl Fn(&str) -> bool,
+) -> Result<(), ConfigError> {
+    for route in routes {
+        if let Some(peer) = route.tunnel.as_ref().filter(|peer| !dialed(peer)) {
+            return Err(ConfigError::UnknownTunnelPeer {
+                route: route.path_prefix.clone(),
+                peer: peer.clone(),
+            });
+        }
+    }
+    Ok(())
+}
+
+impl Default for GatewayConfigBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::proxy::egress::{EgressProxy, ProxyCredentials};
+
+    #[test]
+    fn test_builder_config_and_route_resolution() {
+        let config = GatewayConfig::builder()
+            .listen(([127, 0, 0, 1], 9443).into())
+            .tls_policy(TlsPolicy::Hybrid)
+            .max_connections(500)
+            .upstream_timeout_secs(10)
+            .load_shed_threshold(400)
+            .forwarded_proto(ForwardedProto::Auto)
+            .route(
+                RouteBuilder::new("/api")
+                    .upstream("api", "10.0.0.5", 8080)
+                    .strip_prefix(true),
+            )
+            .route(
+                RouteBuilder::new("/api/admin")
+                    .upstream("admin", "10.0.0.6", 8443)
+                    .tls_verify(false)
+                    .tls(true)
+                    .priority(10),
+            )
+            .build()
+            .unwrap();
+
+        assert_eq!(config.listen_addr, SocketAddr::from(([127, 0, 0, 1], 9443)));
+        assert_eq!(config.tls_policy, TlsPolicy::Hybrid);
+        assert_eq!(config.max_connections, 500);
+        assert_eq!(config.upstream_timeout_secs, 10);
+        assert_eq!(config.load_shed_threshold, Some(400));
+        assert!(config.catch_panics);
+        assert_eq!(config.routes.len(), 2);
+        assert!(config.routes[0].strip_prefix);
+        assert!(config.routes[0].upstream.tls_verify);
+        assert!(!config.routes[1].upstream.tls_verify);
+        assert!(!config.routes[0].upstream.tls);
+        assert!(config.routes[1].upstream.tls);
+
+        let proxy = config.proxy_service();
+        let admin = proxy.find_route("/api/admin/users").unwrap();
+        assert_eq!(admin.upstream.name, "admin");
+        assert_eq!(admin.priority, 10);
+        let api = proxy.find_route("/api/orders").unwrap();
+        assert_eq!(api.upstream.host, "10.0.0.5");
+        assert!(proxy.find_route("/other").is_none());
+    }
+
+    #[test]
+    fn test_builder_validation() {
+        let build = |builder: GatewayConfigBuilder| builder.build().err().unwrap().to_string();
+
+        assert_eq!(
+            build(GatewayConfig::builder().max_connections(0)),
+            "max_connections must be at least 1"
+        );
+        assert_eq!(
+            build(GatewayConfig::builder().listener(ListenerConfig {
+                acceptors: 0,
+                ..ListenerConfig::default()
+            })),
+            "listener acceptors must be at least 1"
+        );
+        assert_eq!(
+            build(GatewayConfig::builder().preferred_algorithms(Vec::new())),
+            "no preferred algorithms specified"
+        );
+        assert_eq!(
+            build(GatewayConfig::builder().upstream_tls(UpstreamTlsConfig {
+                session_cache_size: 0,
+                ..UpstreamTlsConfig::default()
+            })),
+            "upstream TLS session cache must hold at least one session"
+        );
+        assert_eq!(
+            build(GatewayConfig::builder().slow_start(SlowStartConfig {
+                min_fraction: 0.0,
+                ..SlowStartConfig::default()
+            })),
+            "slow start min_fraction must be above 0 and at most 1, got 0"
+        );
+        assert_eq!(
+            build(GatewayConfig::builder().handshake_timeout_secs(0)),
+            "handshake timeout must be at least one second"
+        );
+        assert_eq!(
+            build(GatewayConfig::builder().denied_methods(vec!["NOT A METHOD".into()])),
+            "invalid denied method \"NOT A METHOD\""
+        );
+        let fixtures = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("testdata/mtls");
+        assert!(build(GatewayConfig::builder().mtls(MtlsConfig {
+            ca_path: fixtures.join("client-ed25519.pem"),
+            require_pqc_client_cert: false,
+            allowed_cn_patterns: Vec::new(),
+        }))
+        .ends_with("is not a CA certificate"));
+        assert_eq!(
+            build(
+                GatewayConfig::builder().route(
+                    RouteBuilder::new("/api")
+                        .upstream("api", "10.0.0.5", 8080)
+                        .allowed_methods(["GET", "BAD/METHOD"])
+                )
+            ),
+            "invalid route configuration: route \"/api\" lists an invalid method \"BAD/METHOD\""
+        );
+        assert_eq!(
+            build(
+                GatewayConfig::builder()
+                    .max_connections(10)
+                    .load_shed_threshold(20)
+            ),
+            "load shed threshold 20 exceeds max_connections 10"
+        );
+        assert_eq!(
+            build(GatewayConfig::builder().egress(EgressConfig {
+                default: Some(EgressProxy {
+                    protocol: EgressProtocol::Socks5,
+                    host: "proxy.corp".into(),
+                    port: 1080,
+                    credentials: Some(ProxyCredentials {
+                        username: "gw".into(),
+                        password: "s3cret".into(),
+                    }),
+                }),
+                ..EgressConfig::default()
+            })),
+            "egress proxy proxy.corp:1080 uses SOCKS5, which takes no credentials"
+        );
+        assert_eq!(
+            build(GatewayConfig::builder().route(RouteBuilder::new("/api"))),
+            "route \"/api\" has no upstream"
+        );
+        assert_eq!(
+            build(
+                GatewayConfig::builder().route(
+                    RouteBuilder::new("/api")
+                        .upstream("api", "10.0.0.5", 8080)
+                        .unseal(UnsealConfig::default())
+                )
+            ),
+            "route \"/api\" unseals request bodies but no unsealer is configured"
+        );
+        assert!(matches!(
+            GatewayConfig::builder()
+                .route(RouteBuilder::new("").upstream("api", "10.0.0.5", 8080))
+                .build(),
+            Err(ConfigError::Route(ProxyError::InvalidConfig(_)))
+        ));
+        assert!(matches!(
+            GatewayConfig::builder()
+                .route(RouteBuilder::new("/api").upstream("a", "10.0.0.5", 8080))
+                .route(RouteBuilder::new("/api").upstream("b", "10.0.0.6", 8080))
+                .build(),
+            Err(ConfigError::Route(ProxyError::InvalidConfig(_)))
+        ));
+    }
+
+    #[test]
+    fn test_tunnel_routes_need_a_dialed_peer() {
+        use crate::tunnel::TunnelConfig;
+        use quantun_crypto::mldsa::MlDsaKeyPair;
+        use quantun_types::MlDsaVariant;
+
+        let build = |builder: GatewayConfigBuilder| builder.build().err().unwrap().to_string();
+        let key = MlDsaKeyPair::generate(MlDsaVariant::MlDsa44).unwrap();
+        let config = TunnelConfig::new("dc1", key.clone()).with_peer("dc2", key.to_verifier());
+        let addr: SocketAddr = ([10, 0, 0, 2], 7443).into();
+        let route = || {
+            RouteBuilder::new("/remote")
+                .upstream("remote", "remote", 80)
+                .tunnel("dc2")
+        };
+
+        assert_eq!(
+            build(GatewayConfig::builder().route(route())),
+            "route \"/remote\" tunnels to \"dc2\", which has no tunnel address"
+        );
+        assert_eq!(
+            build(
+                GatewayConfig::builder()
+                    .tunnel(TunnelSettings::new(config.clone()).with_peer_addr("dc3", addr))
+            ),
+            "tunnel peer \"dc3\" has an address but no trusted key"
+        );
+        let config = GatewayConfig::builder()
+            .tunnel(TunnelSettings::new(config).with_peer_addr("dc2", addr))
+            .route(route())
+            .build()
+            .unwrap();
+        assert_eq!(config.tunnel.unwrap().connectors().count(), 1);
+    }
+}
@@ -0,0 +1,576 @@
+//! The YAML configuration file read by `qsgw serve` and `qsgw check-config`.
+//!
+//! ```yaml
+//! listen_addr: 0.0.0.0:8443
+//! tls_policy: pqc_preferred
+//! tls:
+//!   cert_path: /etc/qsgw/cert.pem
+//!   key_path: /etc/qsgw/key.pem
+//! routes:
+//!   - path_prefix: /api
+//!     upstream: { name: api, host: 10.0.0.5, port: 8080, is_healthy: true, tls_verify: true }
+//!     strip_prefix: true
+//!     priority: 0
+//! ```
+//!
+//! Omitted settings take their [`GatewayConfig`] defaults. Without `tls`
+//! the gateway serves plain HTTP, trusting the headers of an external TLS
+//! terminator only if `trusted_terminator` is set. Without `auth` every
+//! route is unauthenticated and admin routes are refused. With
+//! `metrics_sink: prometheus` request and connection metrics are served
+//! at `/metrics`; by default they are dropped. Key files named
+//! under `tunnel` and API key secrets named under `auth` are read by
+//! [`TunnelFiles::load`] and [`AuthFiles::load`], not by
+//! [`ConfigFile::load`].
+
+use quantun_crypto::mldsa::MlDsaVerifier;
+use quantun_crypto::KeyPair;
+use quantun_types::Algorithm;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+use crate::auth::{ApiKey, AuthConfig, AuthConfigError, AuthPolicy};
+use crate::builder::{validate_tunnel_routes, validate_unseal_routes, ConfigError};
+use crate::keyfile::{self, KeyFileError};
+use crate::listener::ListenerConfig;
+use crate::metrics::MetricsSinkKind;
+use crate::proxy::egress::EgressConfig;
+use crate::proxy::slow_start::SlowStartConfig;
+use crate::proxy::upstream_tls::UpstreamTlsConfig;
+use crate::proxy::{normalize_routes, validate_routes, ForwardedProto, Route};
+use crate::rate_limit::RateLimit;
+use crate::request_signature::ClientSigningKey;
+use crate::self_test::{SelfTestConfig, SelfTestFailure};
+use crate::telemetry::TracingConfig;
+use crate::tls::MtlsConfig;
+use crate::tunnel::{TunnelConfig, TunnelSettings};
+use crate::{GatewayConfig, TlsPolicy};
+
+#[derive(Debug, Error)]
+pub enum ConfigFileError {
+    #[error("{}: {source}", path.display())]
+    Io {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    #[error("{}:{line}:{column}: {message}", path.display())]
+    Parse {
+        path: PathBuf,
+        line: usize,
+        column: usize,
+        message: String,
+    },
+    #[error("{}: {source}", path.display())]
+    Invalid { path: PathBuf, source: ConfigError },
+    #[error("{}: {source}", path.display())]
+    Key { path: PathBuf, source: KeyFileError },
+    #[error(transparent)]
+    Auth(#[from] AuthConfigError),
+}
+
+/// Certificate chain and private key for in-process TLS termination.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TlsFiles {
+    pub cert_path: PathBuf,
+    pub key_path: PathBuf,
+}
+
+/// Gateway-to-gateway tunnel identity and peers, loaded into
+/// [`GatewayConfig::tunnel`] with [`TunnelFiles::load`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TunnelFiles {
+    /// Name presented to peers.
+    pub name: String,
+    /// ML-DSA private key file written by `qsgw keygen`.
+    pub key_path: PathBuf,
+    /// Environment variable holding the passphrase of an encrypted key.
+    #[serde(default)]
+    pub passphrase_env: Option<String>,
+    /// Accept tunnels from peers on this address.
+    #[serde(default)]
+    pub listen_addr: Option<SocketAddr>,
+    #[serde(default)]
+    pub peers: Vec<TunnelPeerFiles>,
+}
+
+/// A gateway trusted on the other end of a tunnel.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TunnelPeerFiles {
+    pub name: String,
+    /// The peer's ML-DSA public key file (`.pub.pem` from `qsgw keygen`).
+    pub public_key_path: PathBuf,
+    /// Where to dial the peer for routes that tunnel to it. Peers that only
+    /// connect in need none.
+    #[serde(default)]
+    pub addr: Option<SocketAddr>,
+}
+
+impl TunnelFiles {
+    /// Read the identity and peer keys.
+    pub fn load(&self) -> Result<TunnelSettings, ConfigFileError> {
+        let passphrase = match &self.passphrase_env {
+            Some(var) => Some(std::env::var(var).map_err(|_| ConfigFileError::Key {
+                path: self.key_path.clone(),
+                source: KeyFileError::Malformed(format!("environment variable {var} is not set")),
+            })?),
+            None => None,
+        };
+        let identity = read_key(&self.key_path, |pem| {
+            match keyfile::decode(pem, passphrase.as_deref().map(str::as_bytes))? {
+                KeyPair::MlDsa(identity) => Ok(identity),
+                other => Err(not_mldsa(other.algorithm())),
+            }
+        })?;
+        let mut settings = TunnelSettings::new(TunnelConfig::new(&self.name, identity));
+        settings.listen_addr = self.listen_addr;
+        for peer in &self.peers {
+            let verifier = read_key(&peer.public_key_path, |pem| {
+                match keyfile::decode_public(pem)? {
+                    (Algorithm::MlDsa(variant), public_key) => Ok(MlDsaVerifier {
+                        variant,
+                        public_key,
+                    }),
+                    (other, _) => Err(not_mldsa(other)),
+                }
+            })?;
+            settings.config = settings.config.with_peer(&peer.name, verifier);
+            if let Some(addr) = peer.addr {
+                settings = settings.with_peer_addr(&peer.name, addr);
+            }
+        }
+        Ok(settings)
+    }
+}
+
+/// API keys and client certificate scopes, loaded into
+/// [`GatewayConfig::auth`] with [`AuthFiles::load`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AuthFiles {
+    pub require_auth: bool,
+    pub bypass_paths: Vec<String>,
+    pub api_keys: Vec<ApiKeyFiles>,
+    /// Scopes granted to mutual-TLS clients, by certificate common name.
+    pub cert_identity_map: HashMap<String, Vec<String>>,
+}
+
+impl Default for AuthFiles {
+    fn default() -> Self {
+        let defaults = AuthConfig::default();
+        Self {
+            require_auth: true,
+            bypass_paths: defaults.bypass_paths,
+            api_keys: Vec::new(),
+            cert_identity_map: defaults.cert_identity_map,
+        }
+    }
+}
+
+/// An API key whose secret is read from the environment.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ApiKeyFiles {
+    pub id: String,
+    pub name: String,
+    #[serde(default)]
+    pub scopes: Vec<String>,
+    /// Environment variable holding the value clients send as `x-api-key`;
+    /// see [`ApiKey::from_env_var`].
+    pub secret_env: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signing_key: Option<ClientSigningKey>,
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub metadata: HashMap<String, String>,
+}
+
+impl AuthFiles {
+    /// Read the API key secrets and build the policy.
+    pub fn load(&self) -> Result<AuthPolicy, ConfigFileError> {
+        let api_keys = self
+            .api_keys
+            .iter()
+            .map(|key| {
+                Ok(ApiKey {
+                    signing_key: key.signing_key.clone(),
+                    metadata: key.metadata.clone(),
+                    ..ApiKey::from_env_var(&key.secret_env, &key.id, &key.name, key.scopes.clone())?
+                })
+            })
+            .collect::<Result<_, AuthConfigError>>()?;
+        Ok(AuthPolicy::new(AuthConfig {
+            require_auth: self.require_auth,
+            api_keys,
+            bypass_paths: self.bypass_paths.clone(),
+            cert_identity_map: self.cert_identity_map.clone(),
+        })?)
+    }
+}
+
+fn read_key<T>(
+    path: &Path,
+    decode: impl FnOnce(&str) -> Result<T, KeyFileError>,
+) -> Result<T, ConfigFileError> {
+    let pem = std::fs::read_to_string(path).map_err(|source| ConfigFileError::Io {
+        path: path.to_path_buf(),
+        source,
+    })?;
+    decode(&pem).map_err(|source| ConfigFileError::Key {
+        path: path.to_path_buf(),
+        source,
+    })
+}
+
+fn not_mldsa(algorithm: Algorithm) -> KeyFileError {
+    KeyFileError::Malformed(format!("tunnel keys must be ML-DSA, not {algorithm}"))
+}
+
+/// A gateway configuration file.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ConfigFile {
+    pub listen_addr: SocketAddr,
+    pub listener: ListenerConfig,
+    pub tls_policy: TlsPolicy,
+    pub preferred_algorithms: Vec<Algorithm>,
+    pub max_connections: usize,
+    pub upstream_timeout_secs: u64,
+    pub upstream_tls: UpstreamTlsConfig,
+    pub slow_start: Option<SlowStartConfig>,
+    pub handshake_timeout_secs: u64,
+    pub load_shed_threshold: Option<usize>,
+    pub server_timing: bool,
+    pub catch_panics: bool,
+    pub denied_methods: Vec<String>,
+    pub forwarded_proto: ForwardedProto,
+    pub egress: EgressConfig,
+    pub early_data: quantun_tls::config::EarlyDataPolicy,
+    pub mtls: Option<MtlsConfig>,
+    /// Whether the terminator in front of a gateway without `tls` is trusted
+    /// to set the `x-tls-*` headers, such as the client certificate.
+    pub trusted_terminator: bool,
+    pub trusted_proxies: Vec<ipnet::IpNet>,
+    /// Requests each client may make on routes whose profile rate limits.
+    pub rate_limit: Option<RateLimit>,
+    /// Whether `qsgw serve` refuses to start when a crypto self test fails.
+    pub self_test_on_failure: SelfTestFailure,
+    pub tls: Option<TlsFiles>,
+    pub tunnel: Option<TunnelFiles>,
+    pub auth: Option<AuthFiles>,
+    pub tracing: TracingConfig,
+    pub metrics_sink: MetricsSinkKind,
+    pub routes: Vec<Route>,
+}
+
+impl Default for ConfigFile {
+    fn default() -> Self {
+        let defaults = GatewayConfig::default();
+        Self {
+            listen_addr: defaults.listen_addr,
+            listener: defaults.listener,
+            tls_policy: defaults.tls_policy,
+            preferred_algorithms: defaults.preferred_algorithms,
+            max_connections: defaults.max_connections,
+            upstream_timeout_secs: defaults.upstream_timeout_secs,
+            upstream_tls: defaults.upstream_tls,
+            slow_start: defaults.slow_start,
+            handshake_timeout_secs: defaults.handshake_timeout_secs,
+            load_shed_threshold: defaults.load_shed_threshold,
+            server_timing: defaults.server_timing,
+            catch_panics: defaults.catch_panics,
+            denied_methods: defaults.denied_methods,
+            forwarded_proto: defaults.forwarded_proto,
+            egress: defaults.egress,
+            early_data: defaults.early_data,
+            mtls: None,
+            trusted_terminator: defaults.trusted_terminator,
+            trusted_proxies: defaults.trusted_proxies,
+            rate_limit: None,
+            self_test_on_failure: defaults.self_test.on_failure,
+            tls: None,
+            tunnel: None,
+            auth: None,
+            tracing: defaults.tracing,
+            metrics_sink: MetricsSinkKind::default(),
+            routes: defaults.routes,
+        }
+    }
+}
+
+impl ConfigFile {
+    /// Read and validate the file at `path`.
+    pub fn load(path: &Path) -> Result<Self, ConfigFileError> {
+        let text = std::fs::read_to_string(path).map_err(|source| ConfigFileError::Io {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        let file = Self::parse(&text).map_err(|e| {
+            let location = e.location();
+            let message = e.to_string();
+            ConfigFileError::Parse {
+                path: path.to_path_buf(),
+                line: location.as_ref().map_or(0, |l| l.line()),
+                column: location.as_ref().map_or(0, |l| l.column()),
+                // serde_yaml appends the location, which the error already
+                // reports.
+                message: match message.rsplit_once(" at line ") {
+                    Some((message, _)) => message.to_string(),
+                    None => message,
+                },
+            }
+        })?;
+        file.to_gateway_config()
+            .map_err(|source| ConfigFileError::Invalid {
+                path: path.to_path_buf(),
+                source,
+            })?;
+        Ok(file)
+    }
+
+    pub fn parse(yaml: &str) -> Result<Self, serde_yaml::Error> {
+        serde_yaml::from_str(yaml)
+    }
+
+    /// The gateway configuration, checked as [`GatewayConfig::builder`]
+    /// checks it.
+    pub fn to_gateway_config(&self) -> Result<GatewayConfig, ConfigError> {
+        let mut builder = GatewayConfig::builder()
+            .listen(self.listen_addr)
+            .listener(self.listener.clone())
+            .tls_policy(self.tls_policy)
+            .preferred_algorithms(self.preferred_algorithms.clone())
+            .max_connections(self.max_connections)
+            .upstream_timeout_secs(self.upstream_timeout_secs)
+            .upstream_tls(self.upstream_tls.clone())
+            .handshake_timeout_secs(self.handshake_timeout_secs)
+            .server_timing(self.server_timing)
+            .catch_panics(self.catch_panics)
+            .denied_methods(self.denied_methods.clone())
+            .forwarded_proto(self.forwarded_proto)
+            .egress(self.egress.clone())
+            .early_data(self.early_data)
+            .trusted_terminator(self.trusted_terminator)
+            .trusted_proxies(self.trusted_proxies.clone())
+            .self_test(SelfTestConfig {
+                on_failure: self.self_test_on_failure,
+                ..SelfTestConfig::default()
+            })
+            .tracing(self.tracing.clone())
+            .metrics_sink(self.metrics_sink.build());
+        if let Some(slow_start) = &self.slow_start {
+            builder = builder.slow_start(slow_start.clone());
+        }
+        if let Some(threshold) = self.load_shed_threshold {
+            builder = builder.load_shed_threshold(threshold);
+        }
+        if let Some(mtls) = &self.mtls {
+            builder = builder.mtls(mtls.clone());
+        }
+        if let Some(limit) = self.rate_limit {
+            builder = builder.rate_limit(limit);
+        }
+        let mut config = builder.build()?;
+        validate_routes(&self.routes)?;
+        validate_tunnel_routes(&self.routes, |name| {
+            self.tunnel
+                .iter()
+                .flat_map(|tunnel| &tunnel.peers)
+                .any(|peer| peer.name == name && peer.addr.is_some())
+        })?;
+        // Unsealing needs a keystore key, which only embedders can supply.
+        validate_unseal_routes(&self.routes, false)?;
+        config.routes = self.routes.clone();
+        normalize_routes(&mut config.routes);
+        Ok(config)
+    }
+
+    /// The file with every default filled in, as YAML.
+    pub fn to_yaml(&self) -> String {
+        serde_yaml::to_string(self).expect("config file serializes to YAML")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    /// Write `yaml` to a fresh file named `qsgw-<name>.yaml`.
+    fn write(name: &str, yaml: &str) -> PathBuf {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_nanos();
+        let path = std::env::temp_dir().join(format!("qsgw-{name}-{nanos}.yaml"));
+        std::fs::write(&path, yaml).unwrap();
+        path
+    }
+
+    #[test]
+    fn test_load_fills_defaults_and_round_trips() {
+        let path = write(
+            "defaults",
+            "listen_addr: 127.0.0.1:9443\n\
+             tls_policy: hybrid\n\
+             egress:\n\
+             \x20 default: { protocol: http_connect, host: proxy.corp, port: 3128 }\n\
+             \x20 upstreams: { api: null }\n\
+             routes:\n\
+             \x20 - path_prefix: /api\n\
+             \x20   upstream: { name: api, host: 10.0.0.5, port: 8080, is_healthy: true, tls_verify: true }\n\
+             \x20   strip_prefix: true\n\
+             \x20   priority: 0\n",
+        );
+        let file = ConfigFile::load(&path).unwrap();
+        std::fs::remove_file(path).unwrap();
+        assert_eq!(file.tls_policy, TlsPolicy::Hybrid);
+        assert_eq!(file.max_connections, 10_000);
+
+        let config = file.to_gateway_config().unwrap();
+        assert_eq!(config.listen_addr, SocketAddr::from(([127, 0, 0, 1], 9443)));
+        assert_eq!(config.routes.len(), 1);
+        assert_eq!(config.egress.proxy_for("api"), None);
+        assert_eq!(
+            config.egress.proxy_for("other").unwrap().to_string(),
+            "proxy.corp:3128"
+        );
+        assert_eq!(
+            ConfigFile::parse(&file.to_yaml()).unwrap().to_yaml(),
+            file.to_yaml()
+        );
+    }
+
+    #[test]
+    fn test_load_errors_carry_context() {
+        let path = write("typo", "listen_addr: 127.0.0.1:9443\nmax_conections: 5\n");
+        let err = ConfigFile::load(&path).unwrap_err();
+        std::fs::remove_file(&path).unwrap();
+        assert!(
+            matches!(err, ConfigFileError::Parse { line: 2, .. }),
+            "{err}"
+        );
+        assert!(err.to_string().starts_with(&format!(
+            "{}:2:1: unknown field `max_conections`",
+            path.display()
+        )));
+
+        let path = write("invalid", "max_connections: 0\n");
+        let err = ConfigFile::load(&path).unwrap_err();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(
+            err.to_string(),
+            format!("{}: max_connections must be at least 1", path.display())
+        );
+    }
+
+    #[test]
+    fn test_rate_limit_section() {
+        let parse = |yaml| ConfigFile::parse(yaml).unwrap().to_gateway_config();
+        let config = parse("rate_limit: { requests_per_second: 5, burst: 10 }\n").unwrap();
+        let limit = RateLimit {
+            requests_per_second: 5.0,
+            burst: 10,
+        };
+        assert_eq!(config.rate_limit.unwrap().limit(), limit);
+
+        let err = parse("rate_limit: { requests_per_second: 5, burst: 0 }\n").err();
+        assert!(matches!(err, Some(ConfigError::RateLimit(_))));
+    }
+
+    #[test]
+    fn test_tunnel_section_loads_keys_and_checks_routes() {
+        use quantun_crypto::testing::TempDir;
+        use quantun_types::MlDsaVariant;
+
+        let dir = TempDir::new("tunnel");
+        let algorithm = Algorithm::MlDsa(MlDsaVariant::MlDsa44);
+        let own = keyfile::encode(&KeyPair::generate(algorithm).unwrap(), None).unwrap();
+        let peer = keyfile::encode(&KeyPair::generate(algorithm).unwrap(), None).unwrap();
+        let key_path = dir.write("dc1.pem", own.private_pem.as_bytes());
+        let peer_path = dir.write("dc2.pub.pem", &peer.public_pem);
+        let yaml = |addr: &str| {
+            format!(
+                "tunnel:\n\
+                 \x20 name: dc1\n\
+                 \x20 key_path: {}\n\
+                 \x20 listen_addr: 127.0.0.1:7443\n\
+                 \x20 peers:\n\
+                 \x20   - {{ name: dc2, public_key_path: {}{addr} }}\n\
+                 routes:\n\
+                 \x20 - path_prefix: /remote\n\
+                 \x20   upstream: {{ name: remote, host: remote, port: 80, is_healthy: true, tls_verify: true }}\n\
+                 \x20   strip_prefix: false\n\
+                 \x20   priority: 0\n\
+                 \x20   tunnel: dc2\n",
+                key_path.display(),
+                peer_path.display(),
+            )
+        };
+
+        let path = dir.write("qsgw.yaml", yaml(", addr: 10.0.0.2:7443"));
+        let file = ConfigFile::load(&path).unwrap();
+        let settings = file.tunnel.as_ref().unwrap().load().unwrap();
+        assert_eq!(settings.config.name, "dc1");
+        assert!(settings.config.peers.contains_key("dc2"));
+        assert_eq!(
+            settings.listen_addr,
+            Some(SocketAddr::from(([127, 0, 0, 1], 7443)))
+        );
+        assert_eq!(settings.connectors().count(), 1);
+
+        // A route can only tunnel to a peer the gateway knows how to dial.
+        let path = dir.write("qsgw.yaml", yaml(""));
+        let err = ConfigFile::load(&path).unwrap_err();
+        assert!(
+            err.to_string()
+                .ends_with("route \"/remote\" tunnels to \"dc2\", which has no tunnel address"),
+            "{err}"
+        );
+
+        let wrong = TunnelFiles {
+            key_path: peer_path,
+            ..file.tunnel.unwrap()
+        };
+        assert!(matches!(wrong.load(), Err(ConfigFileError::Key { .. })));
+    }
+
+    #[test]
+    fn test_auth_section_reads_key_secrets() {
+        let file = ConfigFile::parse(
+            "auth:\n\
+             \x20 api_keys:\n\
+             \x20   - { id: ops, name: operators, scopes: [admin], secret_env: QSGW_TEST_OPS_KEY }\n\
+             \x20 cert_identity_map: { billing.clients.internal: [invoices:read] }\n",
+        )
+        .unwrap();
+        let auth = file.auth.unwrap();
+        assert!(auth.require_auth);
+        assert!(auth.bypass_paths.contains(&"/health".to_string()));
+
+        assert_eq!(
+            auth.load().unwrap_err().to_string(),
+            "environment variable QSGW_TEST_OPS_KEY is not set"
+        );
+        let secret = "s".repeat(crate::auth::RECOMMENDED_API_KEY_LEN);
+        std::env::set_var("QSGW_TEST_OPS_KEY", &secret);
+        let policy = auth.load().unwrap();
+        let key = &policy.config().api_keys[0];
+        assert_eq!(key.id, "ops");
+        assert_eq!(key.scopes, ["admin"]);
+        assert!(key.verify(&secret));
+        assert!(!key.verify("ops"));
+        assert_eq!(
+            policy.config().cert_identity_map["billing.clients.internal"],
+            ["invoices:read"]
+        );
+
+        // Secrets never come from the file itself.
+        let inline = "auth: { api_keys: [{ id: ops, name: ops, secret_env: X, secret: x }] }";
+        assert!(ConfigFile::parse(inline).is_err());
+    }
+}
@@ -0,0 +1,1155 @@
+//! Device registration and provisioning.
+//!
+//! A device is registered with `POST /devices/register`, sending its ID, an
+//! opaque hardware attestation blob, and its ML-DSA or hybrid public key.
+//! The gateway records the device as pending, signs a
+//! [`ProvisioningDocument`] binding the ID to the key's fingerprint with
+//! its own ML-DSA key, and marks the device provisioned. `GET /devices/{id}`
+//! and `POST /devices/{id}/revoke` complete the lifecycle.
+//!
+//! Records are kept in the keystore's record store, so a directory-backed
+//! [`KeyStore`] keeps them across restarts.
+//!
+//! A provisioned device proves it still holds its key with a challenge:
+//! `POST /devices/{id}/challenge` returns a single-use nonce, and
+//! `POST /devices/{id}/attest` exchanges the ML-DSA signature over
+//! [`attestation_message`] for a short-lived session token carrying the
+//! [`DEVICE_SCOPE`]. Repeated failures lock the device for a while.
+//!
+//! [`require_provisioned`] guards device-only routes. The device is named
+//! by the [`DEVICE_ID_HEADER`], which identifies but does not authenticate
+//! it, so mount device routes inside the gateway's authentication layer.
+//! The attestation is not yet verified, so whoever can register can
+//! provision any key: registration and revocation are served separately by
+//! [`admin_router`].
+
+use axum::body::Body;
+use axum::extract::{Path, State};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use http::{header, Request, StatusCode};
+use quantun_crypto::mldsa::{MlDsaSignature, MlDsaVerifier};
+use quantun_crypto::{CryptoError, KeyStore, RemoteSigner};
+use quantun_types::{Algorithm, ErrorCode};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use thiserror::Error;
+use tracing::{info, warn};
+
+use crate::auth::TokenIssuer;
+use crate::jwks::fingerprint;
+use crate::webhook::{GatewayEvent, WebhookNotifier};
+
+/// Header naming the device a request comes from.
+pub const DEVICE_ID_HEADER: &str = "x-device-id";
+/// How long a provisioning document is valid when no validity is configured.
+pub const DEFAULT_PROVISIONING_VALIDITY: Duration = Duration::from_secs(365 * 86_400);
+/// Longest accepted device ID.
+pub const MAX_DEVICE_ID_LEN: usize = 128;
+/// Scope of the session tokens issued to attested devices.
+pub const DEVICE_SCOPE: &str = "device";
+/// How long a challenge nonce can be answered when no TTL is configured.
+pub const DEFAULT_CHALLENGE_TTL: Duration = Duration::from_secs(60);
+/// Lifetime of device session tokens from the default issuer.
+pub const DEFAULT_SESSION_TTL: Duration = Duration::from_secs(15 * 60);
+/// Consecutive failed attestations that lock a device by default.
+pub const DEFAULT_LOCKOUT_THRESHOLD: u32 = 5;
+/// How long a device stays locked by default.
+pub const DEFAULT_LOCKOUT_DURATION: Duration = Duration::from_secs(5 * 60);
+/// Length of a challenge nonce, in bytes.
+pub const NONCE_LEN: usize = 32;
+
+/// Keystore record kind holding [`DeviceRecord`]s.
+const RECORD_KIND: &str = "devices";
+
+/// Where a device is in its lifecycle.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DeviceState {
+    /// Registered, but no provisioning document has been issued yet.
+    Pending,
+    Provisioned,
+    Revoked,
+}
+
+/// JSON body of `POST /devices/register`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RegisterRequest {
+    pub device_id: String,
+    /// Standard base64 encoding of the hardware attestation. Stored but not
+    /// yet verified.
+    pub attestation: String,
+    /// An ML-DSA or hybrid algorithm.
+    pub algorithm: Algorithm,
+    /// Standard base64 encoding of the device's public key.
+    pub public_key: String,
+}
+
+/// A registered device, as returned by `GET /devices/{id}`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DeviceRecord {
+    pub device_id: String,
+    pub state: DeviceState,
+    pub algorithm: Algorithm,
+    /// Standard base64 encoding of the public key.
+    pub public_key: String,
+    /// Hex SHA-256 of the public key.
+    pub fingerprint: String,
+    /// Standard base64 encoding of the attestation sent at registration.
+    pub attestation: String,
+    pub registered_at: u64,
+    pub provisioned_at: Option<u64>,
+    /// End of the issued provisioning document's validity.
+    pub valid_until: Option<u64>,
+    pub revoked_at: Option<u64>,
+}
+
+impl DeviceRecord {
+    /// Whether the device is provisioned and its document is still valid
+    /// at `now`.
+    pub fn is_provisioned_at(&self, now: u64) -> bool {
+        self.state == DeviceState::Provisioned && self.valid_until.is_some_and(|t| now < t)
+    }
+}
+
+/// What the gateway vouches for when provisioning a device.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProvisioningDocument {
+    pub device_id: String,
+    pub algorithm: Algorithm,
+    /// Hex SHA-256 of the device's registered public key.
+    pub public_key_fingerprint: String,
+    /// Hex SHA-256 of the gateway's signing key.
+    pub issuer_fingerprint: String,
+    pub not_before: u64,
+    pub not_after: u64,
+}
+
+impl ProvisioningDocument {
+    /// The bytes the gateway signs: the document's compact JSON.
+    pub fn signing_bytes(&self) -> Vec<u8> {
+        serde_json::to_vec(self).expect("document serializes to JSON")
+    }
+}
+
+/// Response of `POST /devices/register`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SignedProvisioningDocument {
+    pub document: ProvisioningDocument,
+    pub signature_algorithm: Algorithm,
+    /// Standard base64 encoding of the signature over
+    /// [`ProvisioningDocument::signing_bytes`].
+    pub signature: String,
+}
+
+/// Response of `POST /devices/{id}/challenge`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Challenge {
+    /// Standard base64 encoding of the nonce.
+    pub nonce: String,
+    /// Seconds left to answer the challenge.
+    pub expires_in: u64,
+}
+
+/// JSON body of `POST /devices/{id}/attest`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AttestRequest {
+    /// The nonce from the device's outstanding [`Challenge`].
+    pub nonce: String,
+    /// Standard base64 encoding of the device's signature over
+    /// [`attestation_message`].
+    pub signature: String,
+}
+
+/// Response of `POST /devices/{id}/attest`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DeviceSession {
+    /// A [`TokenIssuer`] token for the device carrying [`DEVICE_SCOPE`].
+    pub token: String,
+}
+
+/// The bytes a device signs to answer a challenge:
+/// `"qsgw-device-attest\0" || device_id || "\0" || nonce`.
+pub fn attestation_message(device_id: &str, nonce: &[u8]) -> Vec<u8> {
+    [
+        b"qsgw-device-attest\0".as_slice(),
+        device_id.as_bytes(),
+        b"\0",
+        nonce,
+    ]
+    .concat()
+}
+
+#[derive(Debug, Error)]
+pub enum DeviceError {
+    #[error("invalid registration: {0}")]
+    InvalidRequest(String),
+    #[error("device keys must be ML-DSA or hybrid, not {0}")]
+    UnsupportedAlgorithm(Algorithm),
+    #[error("{algorithm} public key must be {expected} bytes, got {actual}")]
+    InvalidPublicKey {
+        algorithm: Algorithm,
+        expected: usize,
+        actual: usize,
+    },
+    #[error("device {0:?} is already registered")]
+    DeviceExists(String),
+    #[error("public key is already registered to device {0:?}")]
+    KeyInUse(String),
+    #[error("device {0:?} not found")]
+    NotFound(String),
+    #[error("device {0:?} is not provisioned")]
+    NotProvisioned(String),
+    #[error("challenge failed: {0}")]
+    ChallengeFailed(&'static str),
+    #[error("attestation signature is invalid")]
+    AttestationFailed,
+    #[error("device {device_id:?} is locked for {retry_after}s after repeated failures")]
+    Locked { device_id: String, retry_after: u64 },
+    #[error("gateway signing failed: {0}")]
+    Signing(CryptoError),
+    #[error("device record could not be stored: {0}")]
+    Storage(CryptoError),
+    #[error("challenge nonce could not be generated: {0}")]
+    Nonce(CryptoError),
+}
+
+impl IntoResponse for DeviceError {
+    fn into_response(self) -> Response {
+        let (status, code) = match &self {
+            DeviceError::InvalidRequest(_) => (StatusCode::BAD_REQUEST, ErrorCode::InvalidArgument),
+            DeviceError::UnsupportedAlgorithm(_) => {
+                (StatusCode::BAD_REQUEST, ErrorCode::UnsupportedAlgorithm)
+            }
+            DeviceError::InvalidPublicKey { .. } => {
+                (StatusCode::BAD_REQUEST, ErrorCode::InvalidKeyMaterial)
+            }
+            DeviceError::DeviceExists(_) | DeviceError::KeyInUse(_) => {
+                (StatusCode::CONFLICT, ErrorCode::AlreadyExists)
+            }
+            DeviceError::NotFound(_) => (StatusCode::NOT_FOUND, ErrorCode::NotFound),
+            DeviceError::NotProvisioned(_) => {
+                (StatusCode::FORBIDDEN, ErrorCode::DeviceNotProvisioned)
+            }
+            DeviceError::ChallengeFailed(_) => {
+                (StatusCode::UNAUTHORIZED, ErrorCode::Unauthenticated)
+            }
+            DeviceError::AttestationFailed => {
+                (StatusCode::UNAUTHORIZED, ErrorCode::VerificationFailed)
+            }
+            DeviceError::Locked { .. } => {
+                (StatusCode::TOO_MANY_REQUESTS, ErrorCode::PermissionDenied)
+            }
+            DeviceError::Signing(_) => (StatusCode::SERVICE_UNAVAILABLE, ErrorCode::SigningFailed),
+            DeviceError::Storage(_) | DeviceError::Nonce(_) => {
+                (StatusCode::INTERNAL_SERVER_ERROR, ErrorCode::Internal)
+            }
+        };
+        let message = match &self {
+            DeviceError::Signing(e) | DeviceError::Storage(e) | DeviceError::Nonce(e) => {
+                tracing::error!(error = %e, "device operation failed");
+                "internal server error".to_string()
+            }
+            other => other.to_string(),
+        };
+        let mut response = error_response(status, code, &message);
+        if let DeviceError::Locked { retry_after, .. } = self {
+            response
+                .headers_mut()
+                .insert(header::RETRY_AFTER, retry_after.into());
+        }
+        response
+    }
+}
+
+/// An issued, unanswered challenge.
+struct PendingChallenge {
+    nonce: Vec<u8>,
+    expires_at: Instant,
+}
+
+/// Failed attestations since the device last succeeded.
+#[derive(Default)]
+struct Failures {
+    count: u32,
+    locked_until: Option<Instant>,
+}
+
+/// Registered devices, persisted in a [`KeyStore`]'s record store.
+pub struct DeviceRegistry {
+    keystore: Arc<KeyStore>,
+    signer: Arc<dyn RemoteSigner>,
+    validity: Duration,
+    devices: RwLock<HashMap<String, DeviceRecord>>,
+    tokens: TokenIssuer,
+    challenge_ttl: Duration,
+    lockout_threshold: u32,
+    lockout_duration: Duration,
+    challenges: Mutex<HashMap<String, PendingChallenge>>,
+    failures: Mutex<HashMap<String, Failures>>,
+    notifier: Option<Arc<WebhookNotifier>>,
+}
+
+impl DeviceRegistry {
+    /// Load the devices recorded in `keystore`, signing provisioning
+    /// documents and session tokens with `signer`, which should hold an
+    /// ML-DSA key.
+    ///
+    /// Records that cannot be parsed are logged and skipped.
+    pub fn new(
+        keystore: Arc<KeyStore>,
+        signer: Arc<dyn RemoteSigner>,
+    ) -> Result<Self, DeviceError> {
+        let mut devices = HashMap::new();
+        for record in keystore
+            .records(RECORD_KIND)
+            .map_err(DeviceError::Storage)?
+        {
+            match serde_json::from_slice::<DeviceRecord>(&record) {
+                Ok(device) => {
+                    devices.insert(device.device_id.clone(), device);
+                }
+                Err(e) => warn!(error = %e, "skipping unreadable device record"),
+            }
+        }
+        Ok(Self {
+            keystore,
+            tokens: TokenIssuer::new(signer.clone(), "qsgw", DEFAULT_SESSION_TTL),
+            signer,
+            validity: DEFAULT_PROVISIONING_VALIDITY,
+            devices: RwLock::new(devices),
+            challenge_ttl: DEFAULT_CHALLENGE_TTL,
+            lockout_threshold: DEFAULT_LOCKOUT_THRESHOLD,
+            lockout_duration: DEFAULT_LOCKOUT_DURATION,
+            challenges: Mutex::new(HashMap::new()),
+            failures: Mutex::new(HashMap::new()),
+            notifier: None,
+        })
+    }
+
+    /// Set how long issued provisioning documents are valid.
+    pub fn with_validity(mut self, validity: Duration) -> Self {
+        self.validity = validity;
+        self
+    }
+
+    /// Issue device session tokens with `tokens` instead of the gateway
+    /// signer.
+    pub fn with_token_issuer(mut self, tokens: TokenIssuer) -> Self {
+        self.tokens = tokens;
+        self
+    }
+
+    /// Set how long a challenge can be answered.
+    pub fn with_challenge_ttl(mut self, ttl: Duration) -> Self {
+        self.challenge_ttl = ttl;
+        self
+    }
+
+    /// Lock a device for `duration` after `threshold` consecutive failed
+    /// attestations.
+    pub fn with_lockout(mut self, threshold: u32, duration: Duration) -> Self {
+        self.lockout_threshold = threshold.max(1);
+        self.lockout_duration = duration;
+        self
+    }
+
+    /// Report lockouts to `notifier`.
+    pub fn with_notifier(mut self, notifier: Arc<WebhookNotifier>) -> Self {
+        self.notifier = Some(notifier);
+        self
+    }
+
+    /// The record for `device_id`, if registered.
+    pub fn get(&self, device_id: &str) -> Option<DeviceRecord> {
+        self.devices.read().unwrap().get(device_id).cloned()
+    }
+
+    /// Register a device and issue its provisioning document.
+    ///
+    /// Device IDs and public keys are unique across registrations, including
+    /// revoked ones. A device left pending by a failed signing may register
+    /// again with the same key.
+    pub async fn register(
+        &self,
+        request: RegisterRequest,
+    ) -> Result<SignedProvisioningDocument, DeviceError> {
+        let record = self.record_pending(request)?;
+
+        let now = unix_now();
+        let document = ProvisioningDocument {
+            device_id: record.device_id.clone(),
+            algorithm: record.algorithm,
+            public_key_fingerprint: record.fingerprint.clone(),
+            issuer_fingerprint: fingerprint(self.signer.public_key()),
+            not_before: now,
+            not_after: now + self.validity.as_secs(),
+        };
+        let signature = self
+            .signer
+            .sign(&document.signing_bytes())
+            .await
+            .map_err(DeviceError::Signing)?;
+
+        self.update(&record.device_id, |device| {
+            device.state = DeviceState::Provisioned;
+            device.provisioned_at = Some(now);
+            device.valid_until = Some(document.not_after);
+        })?;
+        info!(device_id = %record.device_id, algorithm = %record.algorithm, "device provisioned");
+
+        Ok(SignedProvisioningDocument {
+            document,
+            signature_algorithm: self.signer.algorithm(),
+            signature: STANDARD.encode(signature),
+        })
+    }
+
+    /// Revoke `device_id`. Revoking a revoked device changes nothing.
+    pub fn revoke(&self, device_id: &str) -> Result<DeviceRecord, DeviceError> {
+        let device = self.update(device_id, |device| {
+            if device.state != DeviceState::Revoked {
+                device.state = DeviceState::Revoked;
+                device.revoked_at = Some(unix_now());
+            }
+        })?;
+        info!(device_id, "device revoked");
+        Ok(device)
+    }
+
+    /// Issue a challenge to a provisioned device, replacing any it has
+    /// outstanding.
+    pub fn challenge(&self, device_id: &str) -> Result<Challenge, DeviceError> {
+        self.check_unlocked(device_id)?;
+        let device = self
+            .get(device_id)
+            .ok_or_else(|| DeviceError::NotFound(device_id.to_string()))?;
+        if !device.is_provisioned_at(unix_now()) {
+            return Err(DeviceError::NotProvisioned(device.device_id));
+        }
+
+        let mut nonce = vec![0u8; NONCE_LEN];
+        getrandom::fill(&mut nonce)
+            .map_err(|e| DeviceError::Nonce(CryptoError::Rng(e.to_string())))?;
+        let now = Instant::now();
+        let mut challenges = self.challenges.lock().unwrap();
+        challenges.retain(|_, c| c.expires_at > now);
+        challenges.insert(
+            device.device_id,
+            PendingChallenge {
+                nonce: nonce.clone(),
+                expires_at: now + self.challenge_ttl,
+            },
+        );
+        Ok(Challenge {
+            nonce: STANDARD.encode(nonce),
+            expires_in: self.challenge_ttl.as_secs(),
+        })
+    }
+
+    /// Check a device's answer to its outstanding challenge and issue it a
+    /// session token.
+    ///
+    /// The challenge is consumed whatever the outcome. Every failure counts
+    /// towards the device's lockout; success resets the count.
+    pub async fn attest(
+        &self,
+        device_id: &str,
+        request: AttestRequest,
+    ) -> Result<DeviceSession, DeviceError> {
+        self.check_unlocked(device_id)?;
+        let device = self
+            .get(device_id)
+            .ok_or_else(|| DeviceError::NotFound(device_id.to_string()))?;
+        if let Err(e) = self.verify_answer(&device, &request) {
+            self.record_failure(device_id);
+            return Err(e);
+        }
+        self.failures.lock().unwrap().remove(device_id);
+
+        let token = self
+            .tokens
+            .issue(device_id, &[DEVICE_SCOPE.to_string()])
+            .await
+            .map_err(DeviceError::Signing)?;
+        info!(device_id, "device attested");
+        Ok(DeviceSession { token })
+    }
+
+    fn verify_answer(
+        &self,
+        device: &DeviceRecord,
+        request: &AttestRequest,
+    ) -> Result<(), DeviceError> {
+        let challenge = self
+            .challenges
+            .lock()
+            .unwrap()
+            .remove(&device.device_id)
+            .ok_or(DeviceError::ChallengeFailed("no outstanding challenge"))?;
+        if challenge.expires_at <= Instant::now() {
+            return Err(DeviceError::ChallengeFailed("challenge expired"));
+        }
+        if STANDARD.decode(&request.nonce).ok().as_deref() != Some(challenge.nonce.as_slice()) {
+            return Err(DeviceError::ChallengeFailed("nonce does not match"));
+        }
+        if !device.is_provisioned_at(unix_now()) {
+            return Err(DeviceError::NotProvisioned(device.device_id.clone()));
+        }
+        let Algorithm::MlDsa(variant) = device.algorithm else {
+            return Err(DeviceError::UnsupportedAlgorithm(device.algorithm));
+        };
+        let signature = STANDARD
+            .decode(&request.signature)
+            .map_err(|_| DeviceError::InvalidRequest("signature is not base64".into()))?;
+        let verifier = MlDsaVerifier {
+            variant,
+            public_key: STANDARD
+                .decode(&device.public_key)
+                .expect("stored public keys are base64"),
+        };
+        let signature = MlDsaSignature { signature, variant };
+        let message = attestation_message(&device.device_id, &challenge.nonce);
+        match verifier.verify(&message, &signature) {
+            Ok(true) => Ok(()),
+            _ => Err(DeviceError::AttestationFailed),
+        }
+    }
+
+    /// Fail if `device_id` is locked, clearing an expired lock.
+    fn check_unlocked(&self, device_id: &str) -> Result<(), DeviceError> {
+        let mut failures = self.failures.lock().unwrap();
+        let Some(locked_until) = failures.get(device_id).and_then(|f| f.locked_until) else {
+            return Ok(());
+        };
+        let now = Instant::now();
+        if locked_until <= now {
+            failures.remove(device_id);
+            return Ok(());
+        }
+        Err(DeviceError::Locked {
+            device_id: device_id.to_string(),
+            retry_after: (locked_until - now).as_secs().max(1),
+        })
+    }
+
+    fn record_failure(&self, device_id: &str) {
+        let mut failures = self.failures.lock().unwrap();
+        let entry = failures.entry(device_id.to_string()).or_default();
+        entry.count += 1;
+        if entry.count >= self.lockout_threshold {
+            entry.locked_until = Some(Instant::now() + self.lockout_duration);
+            warn!(
+                target: "qsgw::audit",
+                device_id,
+                failures = entry.count,
+                locked_for_secs = self.lockout_duration.as_secs(),
+                "device locked after repeated attestation failures"
+            );
+            if let Some(notifier) = &self.notifier {
+                notifier.notify(GatewayEvent::AuthBruteForce {
+                    subject: device_id.to_string(),
+                    failures: entry.count,
+                    locked_for_secs: self.lockout_duration.as_secs(),
+                });
+            }
+            entry.count = 0;
+        }
+    }
+
+    /// Validate `request` and store it as a pending device.
+    fn record_pending(&self, request: RegisterRequest) -> Result<DeviceRecord, DeviceError> {
+        let device_id = request.device_id;
+        let id_valid = !device_id.is_empty()
+            && device_id.len() <= MAX_DEVICE_ID_LEN
+            && !device_id.starts_with('.')
+            && device_id
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'));
+        if !id_valid {
+            return Err(DeviceError::InvalidRequest(format!(
+                "device ID must be 1 to {MAX_DEVICE_ID_LEN} ASCII letters, digits, '-', '_' or '.'"
+            )));
+        }
+        let attestation = STANDARD
+            .decode(&request.attestation)
+            .map_err(|_| DeviceError::InvalidRequest("attestation is not base64".into()))?;
+        if attestation.is_empty() {
+            return Err(DeviceError::InvalidRequest("attestation is empty".into()));
+        }
+        let public_key = STANDARD
+            .decode(&request.public_key)
+            .map_err(|_| DeviceError::InvalidRequest("public key is not base64".into()))?;
+        let expected = public_key_len(request.algorithm)
+            .ok_or(DeviceError::UnsupportedAlgorithm(request.algorithm))?;
+        if public_key.len() != expected {
+            return Err(DeviceError::InvalidPublicKey {
+                algorithm: request.algorithm,
+                expected,
+                actual: public_key.len(),
+            });
+        }
+        let key_fingerprint = fingerprint(&public_key);
+
+        let mut devices = self.devices.write().unwrap();
+        if let Some(existing) = devices.get(&device_id) {
+            let retry =
+                existing.state == DeviceState::Pending && existing.fingerprint == key_fingerprint;
+            if !retry {
+                return Err(DeviceError::DeviceExists(device_id));
+            }
+        }
+        if let Some(owner) = devices
+            .values()
+            .find(|d| d.fingerprint == key_fingerprint && d.device_id != device_id)
+        {
+            return Err(DeviceError::KeyInUse(owner.device_id.clone()));
+        }
+
+        let record = DeviceRecord {
+            device_id: device_id.clone(),
+            state: DeviceState::Pending,
+            algorithm: request.algorithm,
+            public_key: STANDARD.encode(&public_key),
+            fingerprint: key_fingerprint,
+            attestation: STANDARD.encode(&attestation),
+            registered_at: unix_now(),
+            provisioned_at: None,
+            valid_until: None,
+            revoked_at: None,
+        };
+        self.persist(&record)?;
+        devices.insert(device_id, record.clone());
+        Ok(record)
+    }
+
+    /// Apply `change` to a device and persist the result.
+    fn update(
+        &self,
+        device_id: &str,
+        change: impl FnOnce(&mut DeviceRecord),
+    ) -> Result<DeviceRecord, DeviceError> {
+        let mut devices = self.devices.write().unwrap();
+        let device = devices
+            .get_mut(device_id)
+            .ok_or_else(|| DeviceError::NotFound(device_id.to_string()))?;
+        let mut updated = device.clone();
+        change(&mut updated);
+        self.persist(&updated)?;
+        *device = updated.clone();
+        Ok(updated)
+    }
+
+    fn persist(&self, record: &DeviceRecord) -> Result<(), DeviceError> {
+        let json = serde_json::to_vec(record).expect("record serializes to JSON");
+        self.keystore
+            .put_record(RECORD_KIND, &record.device_id, &json)
+            .map_err(DeviceError::Storage)
+    }
+}
+
+/// Public key length for a device key of `algorithm`, or `None` if it
+/// cannot identify a device.
+fn public_key_len(algorithm: Algorithm) -> Option<usize> {
+    match algorithm {
+        Algorithm::MlDsa(variant) => Some(variant.key_sizes().0),
+        Algorithm::Hybrid(variant) => match variant.components().1 {
+            Algorithm::MlDsa(pqc) => Some(32 + pqc.key_sizes().0),
+            Algorithm::MlKem(pqc) => Some(32 + pqc.key_sizes().0),
+            _ => None,
+        },
+        Algorithm::MlKem(_) | Algorithm::SlhDsa(_) => None,
+    }
+}
+
+/// Device routes over `registry`:
+///
+/// - `GET /devices/{id}`: the [`DeviceRecord`].
+/// - `POST /devices/{id}/challenge`: issue a [`Challenge`].
+/// - `POST /devices/{id}/attest`: answer it with an [`AttestRequest`] and
+///   reply with a [`DeviceSession`].
+///
+/// [`build_router`](crate::build_router) mounts these as `Default` when
+/// [`GatewayConfig::devices`](crate::GatewayConfig::devices) is set.
+pub fn router(registry: Arc<DeviceRegistry>) -> Router {
+    Router::new()
+        .route("/devices/{id}", get(device))
+        .route("/devices/{id}/challenge", post(challenge))
+        .route("/devices/{id}/attest", post(attest))
+        .with_state(registry)
+}
+
+/// Admin routes over `registry`:
+///
+/// - `POST /devices/register`: register a [`RegisterRequest`] and reply
+///   with a [`SignedProvisioningDocument`].
+/// - `POST /devices/{id}/revoke`: revoke the device and reply with its
+///   record.
+///
+/// [`build_router`](crate::build_router) mounts these as `AdminOnly`.
+pub fn admin_router(registry: Arc<DeviceRegistry>) -> Router {
+    Router::new()
+        .route("/devices/register", post(register))
+        .route("/devices/{id}/revoke", post(revoke))
+        .with_state(registry)
+}
+
+/// Middleware rejecting requests whose [`DEVICE_ID_HEADER`] does not name
+/// a provisioned device with 403 `DEVICE_NOT_PROVISIONED`.
+///
+/// Apply with [`axum::middleware::from_fn_with_state`] to device-only
+/// routes.
+pub async fn require_provisioned(
+    State(registry): State<Arc<DeviceRegistry>>,
+    req: Request<Body>,
+    next: Next,
+) -> Response {
+    let device = req
+        .headers()
+        .get(DEVICE_ID_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|id| registry.get(id));
+    match device {
+        Some(device) if device.is_provisioned_at(unix_now()) => next.run(req).await,
+        device => {
+            warn!(
+                device_id = device.as_ref().map(|d| d.device_id.as_str()),
+                state = ?device.as_ref().map(|d| d.state),
+                "request from unprovisioned device"
+            );
+            error_response(
+                StatusCode::FORBIDDEN,
+                ErrorCode::DeviceNotProvisioned,
+                "device not provisioned",
+            )
+        }
+    }
+}
+
+async fn register(
+    State(registry): State<Arc<DeviceRegistry>>,
+    Json(request): Json<RegisterRequest>,
+) -> Response {
+    match registry.register(request).await {
+        Ok(document) => (StatusCode::CREATED, Json(document)).into_response(),
+        Err(e) => e.into_response(),
+    }
+}
+
+async fn device(State(registry): State<Arc<DeviceRegistry>>, Path(id): Path<String>) -> Response {
+    match registry.get(&id) {
+        Some(device) => Json(device).into_response(),
+        None => DeviceError::NotFound(id).into_response(),
+    }
+}
+
+async fn revoke(State(registry): State<Arc<DeviceRegistry>>, Path(id): Path<String>) -> Response {
+    match registry.revoke(&id) {
+        Ok(device) => Json(device).into_response(),
+        Err(e) => e.into_response(),
+    }
+}
+
+async fn challenge(
+    State(registry): State<Arc<DeviceRegistry>>,
+    Path(id): Path<String>,
+) -> Response {
+    match registry.challenge(&id) {
+        Ok(challenge) => Json(challenge).into_response(),
+        Err(e) => e.into_response(),
+    }
+}
+
+async fn attest(
+    State(registry): State<Arc<DeviceRegistry>>,
+    Path(id): Path<String>,
+    Json(request): Json<AttestRequest>,
+) -> Response {
+    match registry.attest(&id, request).await {
+        Ok(session) => Json(session).into_response(),
+        Err(e) => e.into_response(),
+    }
+}
+
+fn error_response(status: StatusCode, code: ErrorCode, message: &str) -> Response {
+    (
+        status,
+        Json(serde_json::json!({
+            "error_code": code.as_str(),
+            "message": message,
+        })),
+    )
+        .into_response()
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::auth::TokenClaims;
+    use axum::middleware::from_fn_with_state;
+    use http_body_util::BodyExt;
+    use quantun_crypto::mldsa::{MlDsaKeyPair, MlDsaSignature, MlDsaVerifier};
+    use quantun_crypto::signer::LocalSigner;
+    use quantun_crypto::testing::TempDir;
+    use quantun_crypto::SecureBytes;
+    use quantun_types::MlDsaVariant;
+    use tower::ServiceExt;
+
+    fn setup(keystore: Arc<KeyStore>, gateway_key: &MlDsaKeyPair) -> (Arc<DeviceRegistry>, Router) {
+        let signer = Arc::new(LocalSigner::new(gateway_key.clone()));
+        let registry = Arc::new(DeviceRegistry::new(keystore, signer).unwrap());
+        let device_only = Router::new()
+            .route("/telemetry", post(|| async { "accepted" }))
+            .layer(from_fn_with_state(registry.clone(), require_provisioned));
+        let app = router(registry.clone())
+            .merge(admin_router(registry.clone()))
+            .merge(device_only);
+        (registry, app)
+    }
+
+    fn registration(device_id: &str, key: &MlDsaKeyPair) -> serde_json::Value {
+        serde_json::json!({
+            "device_id": device_id,
+            "attestation": STANDARD.encode(b"tpm-quote"),
+            "algorithm": Algorithm::MlDsa(key.variant),
+            "public_key": STANDARD.encode(&key.public_key),
+        })
+    }
+
+    async fn call(
+        app: &Router,
+        method: &str,
+        uri: &str,
+        device: Option<&str>,
+        body: Option<serde_json::Value>,
+    ) -> (StatusCode, serde_json::Value) {
+        let mut req = Request::builder()
+            .method(method)
+            .uri(uri)
+            .header("content-type", "application/json");
+        if let Some(device) = device {
+            req = req.header(DEVICE_ID_HEADER, device);
+        }
+        let body = body.map_or_else(Body::empty, |b| Body::from(b.to_string()));
+        let response = app.clone().oneshot(req.body(body).unwrap()).await.unwrap();
+        let status = response.status();
+        let bytes = response.into_body().collect().await.unwrap().to_bytes();
+        (status, serde_json::from_slice(&bytes).unwrap_or_default())
+    }
+
+    #[tokio::test]
+    async fn test_device_lifecycle() {
+        let gateway_key = MlDsaKeyPair::generate(MlDsaVariant::MlDsa65).unwrap();
+        let (_, app) = setup(Arc::new(KeyStore::new()), &gateway_key);
+        let device_key = MlDsaKeyPair::generate(MlDsaVariant::MlDsa44).unwrap();
+
+        // Unknown devices cannot reach device-only routes.
+        let (status, body) = call(&app, "POST", "/telemetry", Some("sensor-1"), None).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        assert_eq!(body["error_code"], "DEVICE_NOT_PROVISIONED");
+        let (status, _) = call(&app, "POST", "/telemetry", None, None).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+
+        let (status, body) = call(
+            &app,
+            "POST",
+            "/devices/register",
+            None,
+            Some(registration("sensor-1", &device_key)),
+        )
+        .await;
+        assert_eq!(status, StatusCode::CREATED);
+        let signed: SignedProvisioningDocument = serde_json::from_value(body).unwrap();
+        assert_eq!(signed.document.device_id, "sensor-1");
+        assert_eq!(
+            signed.document.public_key_fingerprint,
+            fingerprint(&device_key.public_key)
+        );
+        assert_eq!(
+            signed.document.issuer_fingerprint,
+            fingerprint(&gateway_key.public_key)
+        );
+        assert_eq!(
+            signed.document.not_after - signed.document.not_before,
+            DEFAULT_PROVISIONING_VALIDITY.as_secs()
+        );
+        let verifier = MlDsaVerifier {
+            variant: MlDsaVariant::MlDsa65,
+            public_key: gateway_key.public_key.clone(),
+        };
+        let signature = MlDsaSignature {
+            signature: STANDARD.decode(&signed.signature).unwrap(),
+            variant: MlDsaVariant::MlDsa65,
+        };
+        assert!(verifier
+            .verify(&signed.document.signing_bytes(), &signature)
+            .unwrap());
+
+        let (status, body) = call(&app, "GET", "/devices/sensor-1", None, None).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["state"], "provisioned");
+        assert_eq!(body["valid_until"], signed.document.not_after);
+
+        let (status, _) = call(&app, "POST", "/telemetry", Some("sensor-1"), None).await;
+        assert_eq!(status, StatusCode::OK);
+
+        let (status, body) = call(&app, "POST", "/devices/sensor-1/revoke", None, None).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["state"], "revoked");
+        assert!(body["revoked_at"].is_u64());
+
+        let (status, body) = call(&app, "POST", "/telemetry", Some("sensor-1"), None).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        assert_eq!(body["error_code"], "DEVICE_NOT_PROVISIONED");
+
+        // Revoked IDs stay taken.
+        let other_key = MlDsaKeyPair::generate(MlDsaVariant::MlDsa44).unwrap();
+        let (status, body) = call(
+            &app,
+            "POST",
+            "/devices/register",
+            None,
+            Some(registration("sensor-1", &other_key)),
+        )
+        .await;
+        assert_eq!(status, StatusCode::CONFLICT);
+        assert_eq!(body["error_code"], "ALREADY_EXISTS");
+
+        let (status, body) = call(&app, "GET", "/devices/unknown", None, None).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        assert_eq!(body["error_code"], "NOT_FOUND");
+        let (status, _) = call(&app, "POST", "/devices/unknown/revoke", None, None).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_registration_validation() {
+        let gateway_key = MlDsaKeyPair::generate(MlDsaVariant::MlDsa44).unwrap();
+        let (registry, app) = setup(Arc::new(KeyStore::new()), &gateway_key);
+        let device_key = MlDsaKeyPair::generate(MlDsaVariant::MlDsa44).unwrap();
+
+        let register =
+            |body: serde_json::Value| call(&app, "POST", "/devices/register", None, Some(body));
+
+        let (status, _) = register(registration("cam-1", &device_key)).await;
+        assert_eq!(status, StatusCode::CREATED);
+
+        // The same key cannot back a second device.
+        let (status, body) = register(registration("cam-2", &device_key)).await;
+        assert_eq!(status, StatusCode::CONFLICT);
+        assert_eq!(
+            body["message"],
+            "public key is already registered to device \"cam-1\""
+        );
+
+        let mut wrong_length = registration("cam-3", &device_key);
+        wrong_length["algorithm"] = serde_json::json!(Algorithm::MlDsa(MlDsaVariant::MlDsa65));
+        let (status, body) = register(wrong_length).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body["error_code"], "INVALID_KEY_MATERIAL");
+
+        let mut slh_dsa = registration("cam-3", &device_key);
+        slh_dsa["algorithm"] =
+            serde_json::json!(Algorithm::SlhDsa(quantun_types::SlhDsaVariant::Sha2_128s));
+        let (status, body) = register(slh_dsa).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body["error_code"], "UNSUPPORTED_ALGORITHM");
+
+        for device_id in ["", "../cam", "cam 3"] {
+            let (status, body) = register(registration(device_id, &device_key)).await;
+            assert_eq!(status, StatusCode::BAD_REQUEST);
+            assert_eq!(body["error_code"], "INVALID_ARGUMENT");
+        }
+        let mut no_attestation = registration("cam-3", &device_key);
+        no_attestation["attestation"] = "".into();
+        let (status, _) = register(no_attestation).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+
+        assert!(registry.get("cam-3").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_devices_persist_in_keystore() {
+        let dir = TempDir::new("devices");
+        let kek = || SecureBytes::new(vec![7; 32]);
+        let gateway_key = MlDsaKeyPair::generate(MlDsaVariant::MlDsa44).unwrap();
+        let device_key = MlDsaKeyPair::generate(MlDsaVariant::MlDsa44).unwrap();
+
+        let (keystore, _) = KeyStore::open(dir.path(), kek()).unwrap();
+        let (registry, app) = setup(Arc::new(keystore), &gateway_key);
+        let (status, _) = call(
+            &app,
+            "POST",
+            "/devices/register",
+            None,
+            Some(registration("gw-edge-7", &device_key)),
+        )
+        .await;
+        assert_eq!(status, StatusCode::CREATED);
+        let before = registry.get("gw-edge-7").unwrap();
+        drop((registry, app));
+
+        let (keystore, _) = KeyStore::open(dir.path(), kek()).unwrap();
+        let (registry, app) = setup(Arc::new(keystore), &gateway_key);
+        assert_eq!(registry.get("gw-edge-7"), Some(before));
+        let (status, _) = call(&app, "POST", "/telemetry", Some("gw-edge-7"), None).await;
+        assert_eq!(status, StatusCode::OK);
+    }
+
+    /// A registry with `device_key` provisioned as `device_id`.
+    async fn provisioned(
+        device_id: &str,
+        device_key: &MlDsaKeyPair,
+        configure: impl FnOnce(DeviceRegistry) -> DeviceRegistry,
+    ) -> (MlDsaKeyPair, Router) {
+        let gateway_key = MlDsaKeyPair::generate(MlDsaVariant::MlDsa44).unwrap();
+        let signer = Arc::new(LocalSigner::new(gateway_key.clone()));
+        let registry = configure(DeviceRegistry::new(Arc::new(KeyStore::new()), signer).unwrap());
+        let request = serde_json::from_value(registration(device_id, device_key)).unwrap();
+        registry.register(request).await.unwrap();
+        (gateway_key, router(Arc::new(registry)))
+    }
+
+    /// Request a challenge for `device_id` and answer it signed by `key`.
+    async fn answer(
+        app: &Router,
+        device_id: &str,
+        key: &MlDsaKeyPair,
+    ) -> (serde_json::Value, (StatusCode, serde_json::Value)) {
+        let uri = format!("/devices/{device_id}/challenge");
+        let (status, challenge) = call(app, "POST", &uri, None, None).await;
+        assert_eq!(status, StatusCode::OK);
+        let nonce = STANDARD
+            .decode(challenge["nonce"].as_str().unwrap())
+            .unwrap();
+        assert_eq!(nonce.len(), NONCE_LEN);
+        let signature = key.sign(&attestation_message(device_id, &nonce)).unwrap();
+        let body = serde_json::json!({
+            "nonce": challenge["nonce"],
+            "signature": STANDARD.encode(&signature.signature),
+        });
+        let uri = format!("/devices/{device_id}/attest");
+        let response = call(app, "POST", &uri, None, Some(body.clone())).await;
+        (body, response)
+    }
+
+    #[tokio::test]
+    async fn test_attestation_issues_session_token() {
+        let device_key = MlDsaKeyPair::generate(MlDsaVariant::MlDsa44).unwrap();
+        let (gateway_key, app) = provisioned("meter-1", &device_key, |r| r).await;
+
+        let (answer_body, (status, body)) = answer(&app, "meter-1", &device_key).await;
+        assert_eq!(status, StatusCode::OK);
+        let session: DeviceSession = serde_json::from_value(body).unwrap();
+        let (claims, payload, signature) = TokenClaims::decode(&session.token).unwrap();
+        assert_eq!(claims.sub, "meter-1");
+        assert_eq!(claims.scopes, [DEVICE_SCOPE]);
+        assert_eq!(claims.exp - claims.iat, DEFAULT_SESSION_TTL.as_secs());
+        let verifier = MlDsaVerifier {
+            variant: MlDsaVariant::MlDsa44,
+            public_key: gateway_key.public_key.clone(),
+        };
+        let signature = MlDsaSignature {
+            signature,
+            variant: MlDsaVariant::MlDsa44,
+        };
+        assert!(verifier.verify(payload.as_bytes(), &signature).unwrap());
+
+        // The nonce is single-use.
+        let (status, body) = call(
+            &app,
+            "POST",
+            "/devices/meter-1/attest",
+            None,
+            Some(answer_body),
+        )
+        .await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+        assert_eq!(
+            body["message"],
+            "challenge failed: no outstanding challenge"
+        );
+
+        let (status, _) = call(&app, "POST", "/devices/unknown/challenge", None, None).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_attestation_rejects_expired_nonce_and_wrong_key() {
+        let device_key = MlDsaKeyPair::generate(MlDsaVariant::MlDsa44).unwrap();
+        let (_, app) = provisioned("meter-2", &device_key, |r| {
+            r.with_challenge_ttl(Duration::ZERO)
+        })
+        .await;
+        let (_, (status, body)) = answer(&app, "meter-2", &device_key).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+        assert_eq!(body["message"], "challenge failed: challenge expired");
+
+        let (_, app) = provisioned("meter-3", &device_key, |r| r).await;
+        let other_key = MlDsaKeyPair::generate(MlDsaVariant::MlDsa44).unwrap();
+        let (_, (status, body)) = answer(&app, "meter-3", &other_key).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+        assert_eq!(body["error_code"], "VERIFICATION_FAILED");
+
+        let (_, (status, _)) = answer(&app, "meter-3", &device_key).await;
+        assert_eq!(status, StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_attestation_lockout() {
+        let device_key = MlDsaKeyPair::generate(MlDsaVariant::MlDsa44).unwrap();
+        let wrong_key = MlDsaKeyPair::generate(MlDsaVariant::MlDsa44).unwrap();
+        let (_, app) = provisioned("meter-4", &device_key, |r| {
+            r.with_lockout(3, Duration::from_secs(60))
+        })
+        .await;
+
+        // A success resets the count.
+        for _ in 0..2 {
+            let (_, (status, _)) = answer(&app, "meter-4", &wrong_key).await;
+            assert_eq!(status, StatusCode::UNAUTHORIZED);
+        }
+        let (_, (status, _)) = answer(&app, "meter-4", &device_key).await;
+        assert_eq!(status, StatusCode::OK);
+
+        for _ in 0..3 {
+            let (_, (status, _)) = answer(&app, "meter-4", &wrong_key).await;
+            assert_eq!(status, StatusCode::UNAUTHORIZED);
+        }
+        let response = app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/devices/meter-4/challenge")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        let retry_after: u64 = response.headers()[header::RETRY_AFTER]
+            .to_str()
+            .unwrap()
+            .parse()
+            .unwrap();
+        assert!((1..=60).contains(&retry_after));
+
+        let body = serde_json::json!({"nonce": "", "signature": ""});
+        let (status, _) = call(&app, "POST", "/devices/meter-4/attest", None, Some(body)).await;
+        assert_eq!(status, StatusCode::TOO_MANY_REQUESTS);
+    }
+}
@@ -0,0 +1,98 @@
+use quantun_tls::config::{PqcCipherSuite, TlsVersion};
+use quantun_types::{Algorithm, KeyType, SecurityLevel};
+use serde::Serialize;
+
+use crate::tls::build_tls_config;
+use crate::TlsPolicy;
+
+/// Path at which the discovery document is served.
+pub const DISCOVERY_PATH: &str = "/.well-known/qsgw-configuration";
+
+/// Machine-readable description of the gateway's cryptographic posture,
+/// analogous to an OIDC discovery document.
+#[derive(Debug, Clone, Serialize)]
+pub struct DiscoveryDocument {
+    pub service: &'static str,
+    pub tls_policy: String,
+    pub min_tls_version: TlsVersion,
+    pub hybrid_mode: bool,
+    pub cipher_suites: Vec<PqcCipherSuite>,
+    pub algorithms: Vec<AlgorithmDescriptor>,
+}
+
+/// A supported algorithm and its NIST security level.
+#[derive(Debug, Clone, Serialize)]
+pub struct AlgorithmDescriptor {
+    pub name: String,
+    pub security_level: SecurityLevel,
+    pub key_type: KeyType,
+}
+
+impl From<&Algorithm> for AlgorithmDescriptor {
+    fn from(alg: &Algorithm) -> Self {
+        Self {
+            name: alg.to_string(),
+            security_level: alg.security_level(),
+            key_type: alg.key_type(),
+        }
+    }
+}
+
+impl DiscoveryDocument {
+    /// Build the document for the given policy from the TLS configuration
+    /// that policy implies.
+    ///
+    /// Policies without PQC algorithms (`ClassicalAllowed`) advertise no
+    /// algorithms or PQC cipher suites.
+    pub fn for_policy(policy: TlsPolicy) -> Self {
+        let (min_tls_version, hybrid_mode, cipher_suites, algorithms) =
+            match build_tls_config(policy) {
+                Ok(config) => (
+                    config.min_tls_version,
+                    config.hybrid_mode,
+                    config.cipher_suites(),
+                    config
+                        .preferred_algorithms
+                        .iter()
+                        .map(AlgorithmDescriptor::from)
+                        .collect(),
+                ),
+                Err(_) => (TlsVersion::Tls13, false, Vec::new(), Vec::new()),
+            };
+
+        Self {
+            service: "qsgw-gateway",
+            tls_policy: format!("{:?}", policy),
+            min_tls_version,
+            hybrid_mode,
+            cipher_suites,
+            algorithms,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn classical_allowed_advertises_no_pqc() {
+        let doc = DiscoveryDocument::for_policy(TlsPolicy::ClassicalAllowed);
+        assert_eq!(doc.tls_policy, "ClassicalAllowed");
+        assert!(doc.algorithms.is_empty());
+        assert!(doc.cipher_suites.is_empty());
+    }
+
+    #[test]
+    fn pqc_only_lists_algorithms_with_levels() {
+        let doc = DiscoveryDocument::for_policy(TlsPolicy::PqcOnly);
+        assert!(!doc.hybrid_mode);
+        let mlkem = doc
+            .algorithms
+            .iter()
+            .find(|a| a.name == "ML-KEM-768")
+            .unwrap();
+        assert_eq!(mlkem.security_level, SecurityLevel::LEVEL_3);
+        assert_eq!(mlkem.key_type, KeyType::Kem);
+    }
+}
@@ -0,0 +1,112 @@
+//! Mapping of crypto and platform errors to gRPC status codes.
+//!
+//! Used when the gateway proxies gRPC traffic and must report failures as
+//! `grpc-status` rather than an HTTP status.
+
+use quantun_crypto::CryptoError;
+use quantun_types::ErrorCode;
+use tonic::{Code, Status};
+
+/// Convert a crypto error into a gRPC status carrying the error message.
+pub fn crypto_error_to_grpc_status(e: &CryptoError) -> Status {
+    Status::new(error_code_to_grpc_code(e.error_code()), e.to_string())
+}
+
+/// Map a platform error code onto the closest gRPC status code.
+pub fn error_code_to_grpc_code(code: ErrorCode) -> Code {
+    code.to_tonic_code()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The code each crypto error should map to. The match has no wildcard,
+    /// so a new variant does not compile until it is given a code here and
+    /// a sample below.
+    fn expected_code(err: &CryptoError) -> Code {
+        match err {
+            CryptoError::KeyGeneration { .. } => Code::Internal,
+            CryptoError::Encapsulation(_) => Code::Internal,
+            CryptoError::Decapsulation(_) => Code::Internal,
+            CryptoError::Decryption(_) => Code::Unauthenticated,
+            CryptoError::Signing(_) => Code::Internal,
+            CryptoError::Verification(_) => Code::Unauthenticated,
+            CryptoError::InvalidKeyMaterial(_) => Code::InvalidArgument,
+            CryptoError::UnsupportedAlgorithm(_) => Code::Unimplemented,
+            CryptoError::Serialization(_) => Code::Internal,
+            CryptoError::Rng(_) => Code::Internal,
+            CryptoError::KeyNotFound(_) => Code::NotFound,
+            // The sample is revoked.
+            CryptoError::KeyUnusable { .. } => Code::Unauthenticated,
+            CryptoError::SignerUnavailable(_) => Code::Unavailable,
+            CryptoError::NonceExhausted => Code::ResourceExhausted,
+            CryptoError::KeyWrap(_) => Code::Internal,
+            CryptoError::KeyUnwrap(_) => Code::Unauthenticated,
+        }
+    }
+
+    #[test]
+    fn crypto_errors_map_to_expected_codes() {
+        let samples = [
+            CryptoError::KeyGeneration {
+                algorithm: "ML-KEM-768".into(),
+                reason: "test".into(),
+            },
+            CryptoError::Encapsulation("test".into()),
+            CryptoError::Decapsulation("test".into()),
+            CryptoError::Decryption("test".into()),
+            CryptoError::Signing("test".into()),
+            CryptoError::Verification("test".into()),
+            CryptoError::InvalidKeyMaterial("test".into()),
+            CryptoError::UnsupportedAlgorithm("test".into()),
+            CryptoError::Serialization("test".into()),
+            CryptoError::Rng("test".into()),
+            CryptoError::KeyNotFound("test".into()),
+            CryptoError::KeyUnusable {
+                key_id: "test".into(),
+                code: ErrorCode::KeyRevoked,
+            },
+            CryptoError::SignerUnavailable("test".into()),
+            CryptoError::NonceExhausted,
+            CryptoError::KeyWrap("test".into()),
+            CryptoError::KeyUnwrap("test".into()),
+        ];
+
+        for err in samples {
+            let status = crypto_error_to_grpc_status(&err);
+            assert_eq!(status.code(), expected_code(&err), "{err}");
+            assert_eq!(status.message(), err.to_string());
+        }
+    }
+
+    #[test]
+    fn specific_error_codes_are_not_internal() {
+        for code in [
+            ErrorCode::InvalidArgument,
+            ErrorCode::NotFound,
+            ErrorCode::AlreadyExists,
+            ErrorCode::PermissionDenied,
+            ErrorCode::Unauthenticated,
+            ErrorCode::ServiceUnavailable,
+            ErrorCode::UnsupportedAlgorithm,
+            ErrorCode::VerificationFailed,
+            ErrorCode::InvalidKeyMaterial,
+            ErrorCode::KeyExpired,
+            ErrorCode::KeyRevoked,
+            ErrorCode::KeyExhausted,
+            ErrorCode::CertificateInvalid,
+            ErrorCode::ScanTimeout,
+        ] {
+            assert_ne!(error_code_to_grpc_code(code), Code::Internal, "{code}");
+        }
+    }
+
+    #[test]
+    fn grpc_code_numbers() {
+        assert_eq!(error_code_to_grpc_code(ErrorCode::Unauthenticated) as i32, 16);
+        assert_eq!(error_code_to_grpc_code(ErrorCode::InvalidKeyMaterial) as i32, 3);
+        assert_eq!(error_code_to_grpc_code(ErrorCode::UnsupportedAlgorithm) as i32, 12);
+        assert_eq!(error_code_to_grpc_code(ErrorCode::Internal) as i32, 13);
+    }
+}
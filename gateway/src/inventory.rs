@@ -0,0 +1,728 @@
+//! Cryptographic inventory of the gateway's configuration.
+//!
+//! [`CryptoInventory`] walks the certificates, keys and algorithms the
+//! gateway is configured with and classifies each by quantum vulnerability
+//! into an [`InventoryReport`], a cryptographic bill of materials for
+//! compliance reviews. [`router`] serves it as JSON or, with
+//! `?format=table`, as a plain-text table, and
+//! [`CryptoInventory::spawn_expiry_check`] reports certificates nearing
+//! expiry to a webhook notifier.
+
+use axum::extract::{Query, State};
+use axum::response::{IntoResponse, Response};
+use axum::routing::get;
+use axum::{Json, Router};
+use quantun_crypto::KeyStore;
+use quantun_tls::config::TlsConfig;
+use quantun_types::{Algorithm, AnyAlgorithm};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::task::JoinHandle;
+use tokio::time::MissedTickBehavior;
+
+use crate::auth::TokenIssuer;
+use crate::proxy::ProxyService;
+use crate::scanner::{Readiness, UpstreamScanner};
+use crate::tls::MtlsConfig;
+use crate::webhook::{GatewayEvent, WebhookNotifier};
+
+/// Path serving the [`InventoryReport`].
+pub const INVENTORY_PATH: &str = "/gateway/inventory";
+
+/// Default warning window for certificates nearing expiry: 30 days.
+pub const DEFAULT_EXPIRY_WARNING: Duration = Duration::from_secs(30 * 24 * 60 * 60);
+
+/// What an [`InventoryItem`] describes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ItemKind {
+    /// A certificate from a configured certificate or CA file.
+    Certificate,
+    /// A key held in the keystore.
+    Key,
+    /// An entry of the TLS `preferred_algorithms`.
+    PreferredAlgorithm,
+    /// The algorithm an auth token issuer signs with.
+    TokenAlgorithm,
+    /// TLS to an upstream, as last negotiated with the [`UpstreamScanner`].
+    UpstreamTls,
+}
+
+impl ItemKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ItemKind::Certificate => "certificate",
+            ItemKind::Key => "key",
+            ItemKind::PreferredAlgorithm => "preferred_algorithm",
+            ItemKind::TokenAlgorithm => "token_algorithm",
+            ItemKind::UpstreamTls => "upstream_tls",
+        }
+    }
+}
+
+/// Quantum vulnerability of an item, ordered from best to worst.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Classification {
+    /// Post-quantum, or symmetric with enough margin against Grover search.
+    QuantumSafe,
+    /// The algorithm could not be determined.
+    Unknown,
+    /// Broken by, or too weak against, a quantum adversary.
+    QuantumVulnerable,
+}
+
+impl Classification {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Classification::QuantumSafe => "quantum_safe",
+            Classification::Unknown => "unknown",
+            Classification::QuantumVulnerable => "quantum_vulnerable",
+        }
+    }
+
+    fn of(algorithm: Option<AnyAlgorithm>) -> Self {
+        match algorithm {
+            Some(a) if a.is_quantum_vulnerable() => Classification::QuantumVulnerable,
+            Some(_) => Classification::QuantumSafe,
+            None => Classification::Unknown,
+        }
+    }
+}
+
+/// One certificate, key or algorithm in use.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InventoryItem {
+    pub kind: ItemKind,
+    /// Where the item is configured, e.g. `tls.cert_path`.
+    pub source: String,
+    /// Certificate subject, key ID, algorithm, issuer or upstream name.
+    pub name: String,
+    /// Key algorithm for certificates, key exchange for upstreams.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub algorithm: Option<AnyAlgorithm>,
+    pub classification: Classification,
+    /// Algorithm of the certificate's signature.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signature_algorithm: Option<String>,
+    /// Unix time the item expires, in seconds.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expires_at: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub recommended_replacement: Option<Algorithm>,
+    /// Key state, or why the item could not be fully assessed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub note: Option<String>,
+}
+
+impl InventoryItem {
+    fn new(kind: ItemKind, source: &str, name: impl Into<String>) -> Self {
+        Self {
+            kind,
+            source: source.to_string(),
+            name: name.into(),
+            algorithm: None,
+            classification: Classification::Unknown,
+            signature_algorithm: None,
+            expires_at: None,
+            recommended_replacement: None,
+            note: None,
+        }
+    }
+
+    /// Set `algorithm` with its classification and replacement.
+    fn with_algorithm(mut self, algorithm: Option<AnyAlgorithm>) -> Self {
+        self.algorithm = algorithm;
+        self.classification = Classification::of(algorithm);
+        self.recommended_replacement = match algorithm {
+            Some(AnyAlgorithm::Classical(c)) => c.recommended_replacement(),
+            _ => None,
+        };
+        self
+    }
+}
+
+/// Item counts by [`Classification`].
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InventorySummary {
+    pub total: usize,
+    pub quantum_safe: usize,
+    pub quantum_vulnerable: usize,
+    pub unknown: usize,
+}
+
+/// Everything cryptographic the gateway is configured to use.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InventoryReport {
+    /// Unix time the report was generated, in seconds.
+    pub generated_at: u64,
+    pub summary: InventorySummary,
+    pub items: Vec<InventoryItem>,
+}
+
+impl InventoryReport {
+    fn new(items: Vec<InventoryItem>) -> Self {
+        let count = |c| items.iter().filter(|i| i.classification == c).count();
+        let summary = InventorySummary {
+            total: items.len(),
+            quantum_safe: count(Classification::QuantumSafe),
+            quantum_vulnerable: count(Classification::QuantumVulnerable),
+            unknown: count(Classification::Unknown),
+        };
+        Self {
+            generated_at: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs(),
+            summary,
+            items,
+        }
+    }
+
+    /// The items as an aligned plain-text table, followed by the summary.
+    pub fn to_table(&self) -> String {
+        const HEADER: [&str; 7] = [
+            "KIND",
+            "SOURCE",
+            "NAME",
+            "ALGORITHM",
+            "CLASSIFICATION",
+            "EXPIRES",
+            "REPLACEMENT",
+        ];
+        let or_dash = |s: Option<String>| s.unwrap_or_else(|| "-".into());
+        let rows: Vec<[String; 7]> = self
+            .items
+            .iter()
+            .map(|item| {
+                [
+                    item.kind.as_str().to_string(),
+                    item.source.clone(),
+                    item.name.clone(),
+                    or_dash(item.algorithm.map(|a| a.to_string())),
+                    item.classification.as_str().to_string(),
+                    or_dash(
+                        item.expires_at
+                            .map(|t| httpdate::fmt_http_date(UNIX_EPOCH + Duration::from_secs(t))),
+                    ),
+                    or_dash(item.recommended_replacement.map(|a| a.to_string())),
+                ]
+            })
+            .collect();
+
+        let mut widths = HEADER.map(str::len);
+        for row in &rows {
+            for (width, cell) in widths.iter_mut().zip(row) {
+                *width = (*width).max(cell.len());
+            }
+        }
+        let mut table = String::new();
+        for row in std::iter::once(HEADER.map(String::from)).chain(rows) {
+            let line: Vec<String> = row
+                .iter()
+                .zip(widths)
+                .map(|(cell, width)| format!("{cell:<width$}"))
+                .collect();
+            table.push_str(line.join("  ").trim_end());
+            table.push('\n');
+        }
+        let s = &self.summary;
+        table.push_str(&format!(
+            "\n{} items: {} quantum-safe, {} quantum-vulnerable, {} unknown\n",
+            s.total, s.quantum_safe, s.quantum_vulnerable, s.unknown
+        ));
+        table
+    }
+
+    /// A [`GatewayEvent::CertificateExpiring`] for each certificate that
+    /// expires within `window` of the report, or has already expired.
+    pub fn expiring_certificates(&self, window: Duration) -> Vec<GatewayEvent> {
+        let deadline = self.generated_at.saturating_add(window.as_secs());
+        self.items
+            .iter()
+            .filter(|item| item.kind == ItemKind::Certificate)
+            .filter_map(|item| {
+                let expires_at = item.expires_at.filter(|&at| at <= deadline)?;
+                Some(GatewayEvent::CertificateExpiring {
+                    subject: item.name.clone(),
+                    expires_at,
+                    days_remaining: expires_at.saturating_sub(self.generated_at) / 86_400,
+                })
+            })
+            .collect()
+    }
+}
+
+/// The configuration to inventory. Sources are read each time a report is
+/// generated, so it reflects key rotations and the latest upstream scan.
+#[derive(Default)]
+pub struct CryptoInventory {
+    tls: Option<TlsConfig>,
+    mtls: Option<MtlsConfig>,
+    keystore: Option<Arc<KeyStore>>,
+    token_issuers: Vec<(String, Algorithm)>,
+    proxy: Option<Arc<ProxyService>>,
+    scanner: Option<Arc<UpstreamScanner>>,
+}
+
+impl CryptoInventory {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The server certificate chain, CA bundle and `preferred_algorithms`.
+    pub fn with_tls(mut self, tls: TlsConfig) -> Self {
+        self.tls = Some(tls);
+        self
+    }
+
+    /// The CAs trusted to issue client certificates.
+    pub fn with_mtls(mut self, mtls: MtlsConfig) -> Self {
+        self.mtls = Some(mtls);
+        self
+    }
+
+    pub fn with_keystore(mut self, keystore: Arc<KeyStore>) -> Self {
+        self.keystore = Some(keystore);
+        self
+    }
+
+    pub fn with_token_issuer(mut self, issuer: &TokenIssuer) -> Self {
+        self.token_issuers
+            .push((issuer.issuer().to_string(), issuer.algorithm()));
+        self
+    }
+
+    /// The upstreams of `proxy`'s routes.
+    pub fn with_upstreams(mut self, proxy: Arc<ProxyService>) -> Self {
+        self.proxy = Some(proxy);
+        self
+    }
+
+    /// Take upstream key exchanges from `scanner`'s latest report.
+    pub fn with_scanner(mut self, scanner: Arc<UpstreamScanner>) -> Self {
+        self.scanner = Some(scanner);
+        self
+    }
+
+    pub fn report(&self) -> InventoryReport {
+        let mut items = Vec::new();
+        if let Some(tls) = &self.tls {
+            items.extend(certificate_items("tls.cert_path", &tls.cert_path));
+            if let Some(ca_path) = &tls.ca_path {
+                items.extend(certificate_items("tls.ca_path", ca_path));
+            }
+        }
+        if let Some(mtls) = &self.mtls {
+            items.extend(certificate_items("mtls.ca_path", &mtls.ca_path));
+        }
+        if let Some(tls) = &self.tls {
+            items.extend(tls.preferred_algorithms.iter().map(|alg| {
+                InventoryItem::new(
+                    ItemKind::PreferredAlgorithm,
+                    "tls.preferred_algorithms",
+                    alg.to_string(),
+                )
+                .with_algorithm(Some((*alg).into()))
+            }));
+        }
+        if let Some(keystore) = &self.keystore {
+            let mut keys = keystore.list();
+            keys.sort_by(|a, b| a.key_id.cmp(&b.key_id));
+            items.extend(keys.into_iter().map(|key| {
+                InventoryItem {
+                    expires_at: key.expires_at,
+                    note: Some(format!("state: {}", key.state)),
+                    ..InventoryItem::new(ItemKind::Key, "keystore", key.key_id)
+                        .with_algorithm(Some(key.algorithm.into()))
+                }
+            }));
+        }
+        items.extend(self.token_issuers.iter().map(|(issuer, alg)| {
+            InventoryItem::new(ItemKind::TokenAlgorithm, "auth.token_issuer", issuer)
+                .with_algorithm(Some((*alg).into()))
+        }));
+        if let Some(proxy) = &self.proxy {
+            items.extend(self.upstream_items(proxy));
+        }
+        InventoryReport::new(items)
+    }
+
+    /// Every `period` until the handle is aborted, report certificates
+    /// expiring within `window` to `notifier`. A certificate is reported
+    /// again at each check until it is replaced.
+    pub fn spawn_expiry_check(
+        self: Arc<Self>,
+        notifier: Arc<WebhookNotifier>,
+        window: Duration,
+        period: Duration,
+    ) -> JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(period);
+            interval.set_missed_tick_behavior(MissedTickBehavior::Skip);
+            loop {
+                interval.tick().await;
+                for event in self.report().expiring_certificates(window) {
+                    notifier.notify(event);
+                }
+            }
+        })
+    }
+
+    fn upstream_items(&self, proxy: &ProxyService) -> Vec<InventoryItem> {
+        let report = self.scanner.as_ref().and_then(|s| s.report());
+        let mut items: Vec<InventoryItem> = Vec::new();
+        for route in proxy.routes() {
+            let upstream = route.upstream;
+            if items.iter().any(|i| i.name == upstream.name) {
+                continue;
+            }
+            let assessment = report
+                .iter()
+                .flat_map(|r| r.upstreams.iter())
+                .find(|a| a.upstream == upstream.name);
+            let mut notes = Vec::new();
+            let mut item = InventoryItem::new(ItemKind::UpstreamTls, "routes", &upstream.name);
+            match assessment.and_then(|a| a.posture.as_ref()) {
+                Some(posture) => {
+                    item = item.with_algorithm(posture.key_exchange);
+                    item.signature_algorithm =
+                        Some(posture.certificate_signature_algorithm.clone());
+                    item.classification = item
+                        .classification
+                        .max(Classification::of(posture.certificate_key));
+                }
+                None if assessment.is_some_and(|a| a.readiness == Readiness::Unreachable) => {
+                    notes.push("unreachable at last scan".to_string());
+                }
+                None => notes.push("not scanned".to_string()),
+            }
+            if !upstream.tls_verify {
+                notes.push("certificate verification disabled".to_string());
+            }
+            item.note = (!notes.is_empty()).then(|| notes.join("; "));
+            items.push(item);
+        }
+        items
+    }
+}
+
+/// One item per certificate in the file at `path`, or a single item noting
+/// why it could not be read.
+fn certificate_items(source: &str, path: &Path) -> Vec<InventoryItem> {
+    let certs = match crate::tls::load_certificates(path) {
+        Ok(certs) => certs,
+        Err(e) => {
+            let mut item =
+                InventoryItem::new(ItemKind::Certificate, source, path.display().to_string());
+            item.note = Some(e.to_string());
+            return vec![item];
+        }
+    };
+    certs
+        .iter()
+        .map(|cert| {
+            let name = crate::tls::common_name(cert)
+                .unwrap_or_else(|| cert.tbs_certificate.subject.to_string());
+            let (key, _) = crate::scanner::classify_key(cert);
+            let signature_oid = cert.signature_algorithm.oid;
+            let signature = crate::tls::pqc_algorithm(&signature_oid).map(AnyAlgorithm::from);
+            let mut item =
+                InventoryItem::new(ItemKind::Certificate, source, name).with_algorithm(key);
+            // Every X.509 signature algorithm outside the PQC table is
+            // RSA, ECDSA or EdDSA.
+            if signature.is_none() {
+                item.classification = item.classification.max(Classification::QuantumVulnerable);
+            }
+            item.signature_algorithm =
+                Some(crate::scanner::signature_algorithm_name(&signature_oid));
+            item.expires_at = Some(
+                cert.tbs_certificate
+                    .validity
+                    .not_after
+                    .to_unix_duration()
+                    .as_secs(),
+            );
+            item
+        })
+        .collect()
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum Format {
+    #[default]
+    Json,
+    Table,
+}
+
+#[derive(Debug, Deserialize)]
+struct InventoryQuery {
+    #[serde(default)]
+    format: Format,
+}
+
+/// Routes over `inventory`:
+///
+/// - `GET /gateway/inventory?format=json|table`: a fresh
+///   [`InventoryReport`], as JSON by default.
+///
+/// [`build_router`](crate::build_router) mounts these as `Default` when
+/// [`GatewayConfig::inventory`](crate::GatewayConfig::inventory) is set.
+pub fn router(inventory: Arc<CryptoInventory>) -> Router {
+    Router::new()
+        .route(INVENTORY_PATH, get(inventory_report))
+        .with_state(inventory)
+}
+
+async fn inventory_report(
+    State(inventory): State<Arc<CryptoInventory>>,
+    Query(query): Query<InventoryQuery>,
+) -> Response {
+    let report = inventory.report();
+    match query.format {
+        Format::Json => Json(report).into_response(),
+        Format::Table => report.to_table().into_response(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::proxy::{MiddlewareProfile, Route, Upstream};
+    use axum::body::Body;
+    use http::{Request, StatusCode};
+    use quantun_crypto::signer::KeyStoreSigner;
+    use quantun_types::{ClassicalAlgorithm, KeyUsage, MlDsaVariant, MlKemVariant};
+    use tower::ServiceExt;
+
+    fn fixture(path: &str) -> String {
+        Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("testdata")
+            .join(path)
+            .display()
+            .to_string()
+    }
+
+    fn route(name: &str, tls_verify: bool) -> Route {
+        Route {
+            path_prefix: format!("/{name}"),
+            upstream: Upstream {
+                name: name.into(),
+                host: "127.0.0.1".into(),
+                port: 1,
+                is_healthy: true,
+                tls_verify,
+                tls: false,
+                weight: 1,
+            },
+            strip_prefix: false,
+            path_rewrite: None,
+            priority: 0,
+            coalesce: None,
+            cache: None,
+            tunnel: None,
+            unseal: None,
+            transform: None,
+            sse_filter: None,
+            allowed_methods: Vec::new(),
+            denied_methods: Vec::new(),
+            content_type: None,
+            middleware_profile: MiddlewareProfile::Default,
+        }
+    }
+
+    /// The inventory of `testdata/inventory/tls.json` with an mTLS CA, a
+    /// token issuer and two upstreams.
+    fn fixture_inventory() -> CryptoInventory {
+        let mut tls: TlsConfig =
+            serde_json::from_str(&std::fs::read_to_string(fixture("inventory/tls.json")).unwrap())
+                .unwrap();
+        // Fixture paths are relative to the crate.
+        let crate_dir = Path::new(env!("CARGO_MANIFEST_DIR"));
+        tls.cert_path = crate_dir.join(&tls.cert_path);
+        tls.ca_path = tls.ca_path.map(|ca| crate_dir.join(ca));
+
+        let signing_keys = Arc::new(KeyStore::new());
+        let key = signing_keys
+            .create(Algorithm::MlDsa(MlDsaVariant::MlDsa44), [KeyUsage::Sign])
+            .unwrap();
+        let signer = KeyStoreSigner::new(signing_keys, key).unwrap();
+        let issuer = TokenIssuer::new(Arc::new(signer), "qsgw", Duration::from_secs(60));
+
+        CryptoInventory::new()
+            .with_tls(tls)
+            .with_mtls(MtlsConfig {
+                ca_path: fixture("mtls/ca-ed25519.pem").into(),
+                require_pqc_client_cert: false,
+                allowed_cn_patterns: Vec::new(),
+            })
+            .with_token_issuer(&issuer)
+            .with_upstreams(Arc::new(ProxyService::new(
+                vec![route("billing", true), route("legacy", false)],
+                5,
+            )))
+    }
+
+    #[test]
+    fn test_fixture_inventory_snapshot() {
+        let report = fixture_inventory().report();
+        let mut json = serde_json::to_value(&report).unwrap();
+        json["generated_at"] = 0.into();
+        let expected: serde_json::Value = serde_json::from_str(
+            &std::fs::read_to_string(fixture("inventory/report.json")).unwrap(),
+        )
+        .unwrap();
+        assert_eq!(
+            json,
+            expected,
+            "report changed:\n{}",
+            serde_json::to_string_pretty(&json).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_certificates_nearing_expiry() {
+        let mut report = fixture_inventory().report();
+        // The server certificate expires ten days from this report.
+        report.generated_at = 4_945_897_367 - 10 * 86_400;
+
+        let events = report.expiring_certificates(Duration::from_secs(10 * 86_400));
+        let expiring = GatewayEvent::CertificateExpiring {
+            subject: "localhost".into(),
+            expires_at: 4_945_897_367,
+            days_remaining: 10,
+        };
+        // tls.cert_path and tls.ca_path; the client CA expires later.
+        assert_eq!(events, [expiring.clone(), expiring]);
+        let within = |days: u64| report.expiring_certificates(Duration::from_secs(days * 86_400));
+        assert_eq!(within(30).len(), 3);
+        assert!(within(1).is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_expiry_check_notifies() {
+        use crate::proxy::testing::MockUpstream;
+        use crate::webhook::{WebhookConfig, WebhookSigner};
+        use quantun_crypto::SecureBytes;
+
+        let receiver = MockUpstream::start().await.unwrap();
+        let notifier = WebhookNotifier::new(
+            WebhookConfig {
+                endpoints: vec![format!("http://{}/hooks", receiver.addr())],
+                ..WebhookConfig::default()
+            },
+            WebhookSigner::Hmac(SecureBytes::new(b"secret".to_vec())),
+        )
+        .unwrap();
+        let inventory = Arc::new(CryptoInventory::new().with_mtls(MtlsConfig {
+            ca_path: fixture("mtls/ca-ed25519.pem").into(),
+            require_pqc_client_cert: false,
+            allowed_cn_patterns: Vec::new(),
+        }));
+        // Every fixture certificate is within a 200-year window.
+        let task = inventory.spawn_expiry_check(
+            Arc::new(notifier),
+            Duration::from_secs(200 * 365 * 86_400),
+            Duration::from_secs(3600),
+        );
+
+        let posted = tokio::time::timeout(Duration::from_secs(5), async {
+            loop {
+                if let Some(posted) = receiver.requests().first() {
+                    return serde_json::from_slice::<serde_json::Value>(&posted.body).unwrap();
+                }
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .expect("event delivered");
+        task.abort();
+        assert_eq!(posted["event"], "certificate_expiring");
+        assert_eq!(posted["subject"], "qsgw test client CA (ED25519)");
+        assert_eq!(posted["expires_at"], 4_945_918_270u64);
+    }
+
+    #[test]
+    fn test_keys_and_scanned_upstreams() {
+        let keystore = Arc::new(KeyStore::new());
+        let key = keystore
+            .create(
+                Algorithm::MlKem(MlKemVariant::MlKem768),
+                [KeyUsage::KeyAgreement],
+            )
+            .unwrap();
+        keystore.revoke(&key).unwrap();
+        let proxy = Arc::new(ProxyService::new(vec![route("billing", true)], 5));
+        let inventory = CryptoInventory::new()
+            .with_tls(TlsConfig {
+                cert_path: fixture("inventory/missing.pem").into(),
+                ..TlsConfig::default()
+            })
+            .with_keystore(keystore)
+            .with_upstreams(proxy.clone())
+            .with_scanner(Arc::new(UpstreamScanner::new(proxy)));
+
+        let report = inventory.report();
+        let missing = &report.items[0];
+        assert_eq!(missing.kind, ItemKind::Certificate);
+        assert_eq!(missing.classification, Classification::Unknown);
+        assert!(missing.note.as_ref().unwrap().contains("missing.pem"));
+
+        let key_item = report
+            .items
+            .iter()
+            .find(|i| i.kind == ItemKind::Key)
+            .unwrap();
+        assert_eq!(key_item.name, key.key_id());
+        assert_eq!(key_item.classification, Classification::QuantumSafe);
+        assert_eq!(key_item.note.as_deref(), Some("state: deactivated"));
+
+        let upstream = report.items.last().unwrap();
+        assert_eq!(upstream.kind, ItemKind::UpstreamTls);
+        assert_eq!(upstream.note.as_deref(), Some("not scanned"));
+        assert_eq!(report.summary.total, report.items.len());
+    }
+
+    #[tokio::test]
+    async fn test_inventory_endpoint_formats() {
+        let app = router(Arc::new(fixture_inventory()));
+
+        let response = app
+            .clone()
+            .oneshot(Request::get(INVENTORY_PATH).body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let report: InventoryReport = serde_json::from_slice(&body).unwrap();
+        assert_eq!(
+            report.items[0].algorithm,
+            Some(ClassicalAlgorithm::EcdsaP256.into())
+        );
+
+        let response = app
+            .oneshot(
+                Request::get(format!("{INVENTORY_PATH}?format=table"))
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let table = std::str::from_utf8(&body).unwrap();
+        let lines: Vec<&str> = table.lines().collect();
+        assert!(lines[0].starts_with("KIND"));
+        assert!(lines[1].starts_with("certificate"));
+        assert!(lines[1].contains("quantum_vulnerable"));
+        assert!(lines[1].contains("ML-DSA-44"));
+        assert!(table.ends_with("quantum-vulnerable, 2 unknown\n"));
+    }
+}
@@ -0,0 +1,365 @@
+//! JWKS-style publication of the gateway's token-verification keys.
+//!
+//! `GET /.well-known/jwks.json` lists the signing keys a
+//! [`RotationScheduler`] publishes: the current key, predecessors still in
+//! their overlap window, and a pending successor once generated. Keys
+//! revoked as compromised stay listed with `"status": "revoked"`, so
+//! clients can tell a revoked key from one they have not fetched yet. The
+//! document is rebuilt from the scheduler on each request, so it follows
+//! rotations on its own. Its `ETag` lets clients poll with `If-None-Match`
+//! and get a `304` until the key set changes.
+//!
+//! Keys use the JOSE post-quantum key type `AKP`: the algorithm name in
+//! `alg` and the base64url public key in `pub`. No private parameters are
+//! emitted. The set is also served at `/.well-known/qsgw-keys`, where
+//! earlier clients fetch it. Both paths are under `/.well-known/`, which
+//! [`AuthConfig`](crate::auth::AuthConfig) bypasses by default.
+
+use axum::extract::State;
+use axum::response::{IntoResponse, Response};
+use axum::routing::get;
+use axum::{Json, Router};
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use http::{header, HeaderMap, StatusCode};
+use quantun_crypto::{CryptoError, CryptoResult, KeyStore};
+use quantun_types::{ErrorCode, KeyUsage};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::rotation::RotationScheduler;
+
+/// Path at which the key set is served.
+pub const JWKS_PATH: &str = "/.well-known/jwks.json";
+/// Earlier path of the key set, still served.
+pub const LEGACY_JWKS_PATH: &str = "/.well-known/qsgw-keys";
+/// How long clients may cache the key set when no max age is configured.
+pub const DEFAULT_JWKS_MAX_AGE: Duration = Duration::from_secs(300);
+
+/// A public verification key.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Jwk {
+    pub kty: String,
+    /// Hex SHA-256 of the public key.
+    pub kid: String,
+    pub alg: String,
+    #[serde(rename = "use")]
+    pub key_use: String,
+    /// Base64url encoding of the public key.
+    #[serde(rename = "pub")]
+    pub public_key: String,
+    /// Absent for keys that may be trusted.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub status: Option<JwkStatus>,
+}
+
+/// Why a published key must not be trusted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum JwkStatus {
+    /// The key was compromised; reject anything signed with it.
+    Revoked,
+}
+
+/// Response of `GET /.well-known/jwks.json`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct JwkSet {
+    pub keys: Vec<Jwk>,
+}
+
+impl JwkSet {
+    /// The trusted key with ID `kid`. Revoked keys are never returned.
+    pub fn find(&self, kid: &str) -> Option<&Jwk> {
+        self.keys
+            .iter()
+            .find(|k| k.kid == kid && k.status.is_none())
+    }
+
+    /// Whether the key with ID `kid` is published as revoked.
+    pub fn is_revoked(&self, kid: &str) -> bool {
+        self.keys
+            .iter()
+            .any(|k| k.kid == kid && k.status == Some(JwkStatus::Revoked))
+    }
+}
+
+/// Hex SHA-256 of `public_key`, used as its `kid`.
+pub fn fingerprint(public_key: &[u8]) -> String {
+    Sha256::digest(public_key)
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect()
+}
+
+/// Builds the key set from the signing slots of a [`RotationScheduler`].
+pub struct JwksService {
+    store: Arc<KeyStore>,
+    scheduler: Arc<RotationScheduler>,
+    slots: Vec<String>,
+    max_age: Duration,
+}
+
+impl JwksService {
+    /// Publish the keys of `slots`, which `scheduler` rotates over `store`.
+    /// Keys without the `Sign` usage are left out.
+    pub fn new(
+        store: Arc<KeyStore>,
+        scheduler: Arc<RotationScheduler>,
+        slots: impl IntoIterator<Item = impl Into<String>>,
+    ) -> Self {
+        Self {
+            store,
+            scheduler,
+            slots: slots.into_iter().map(Into::into).collect(),
+            max_age: DEFAULT_JWKS_MAX_AGE,
+        }
+    }
+
+    /// Set the `Cache-Control` max age. Keep it well under the rotation
+    /// lead time so clients see successors before they start signing.
+    pub fn with_max_age(mut self, max_age: Duration) -> Self {
+        self.max_age = max_age;
+        self
+    }
+
+    /// The currently published verification keys, followed by the revoked
+    /// ones.
+    pub fn key_set(&self) -> CryptoResult<JwkSet> {
+        let mut keys = Vec::new();
+        for slot in &self.slots {
+            let published = self.scheduler.published(slot).into_iter().map(|h| (h, None));
+            let revoked = self
+                .scheduler
+                .revoked(slot)
+                .into_iter()
+                .map(|h| (h, Some(JwkStatus::Revoked)));
+            for (handle, status) in published.chain(revoked) {
+                let metadata = self.store.metadata(&handle)?;
+                if !metadata.usages.contains(&KeyUsage::Sign) {
+                    continue;
+                }
+                let public_key = self.store.get_public(&handle)?;
+                keys.push(Jwk {
+                    kty: "AKP".into(),
+                    kid: fingerprint(&public_key),
+                    alg: metadata.algorithm.to_string(),
+                    key_use: "sig".into(),
+                    public_key: URL_SAFE_NO_PAD.encode(&public_key),
+                    status,
+                });
+            }
+        }
+        Ok(JwkSet { keys })
+    }
+}
+
+/// `GET /.well-known/jwks.json`, and its legacy path, over `service`.
+///
+/// [`build_router`](crate::build_router) mounts these as `NoAuth` when
+/// [`GatewayConfig::jwks`](crate::GatewayConfig::jwks) is set.
+pub fn router(service: Arc<JwksService>) -> Router {
+    Router::new()
+        .route(JWKS_PATH, get(serve_keys))
+        .route(LEGACY_JWKS_PATH, get(serve_keys))
+        .with_state(service)
+}
+
+async fn serve_keys(State(service): State<Arc<JwksService>>, headers: HeaderMap) -> Response {
+    let body = service.key_set().and_then(|set| {
+        serde_json::to_vec(&set).map_err(|e| CryptoError::Serialization(e.to_string()))
+    });
+    let body = match body {
+        Ok(body) => body,
+        Err(e) => {
+            tracing::error!(error = %e, "failed to build key set");
+            return (
+                StatusCode::SERVICE_UNAVAILABLE,
+                Json(serde_json::json!({
+                    "error_code": ErrorCode::Internal.as_str(),
+                    "message": "verification keys unavailable",
+                })),
+            )
+                .into_response();
+        }
+    };
+
+    let etag = format!("\"{}\"", fingerprint(&body));
+    let cache_control = format!("public, max-age={}", service.max_age.as_secs());
+    if etag_matches(&headers, &etag) {
+        return (
+            StatusCode::NOT_MODIFIED,
+            [(header::ETAG, etag), (header::CACHE_CONTROL, cache_control)],
+        )
+            .into_response();
+    }
+    (
+        [
+            (header::CONTENT_TYPE, "application/jwk-set+json".to_string()),
+            (header::ETAG, etag),
+            (header::CACHE_CONTROL, cache_control),
+        ],
+        body,
+    )
+        .into_response()
+}
+
+/// Whether `If-None-Match` lists `etag`, compared weakly, or `*`.
+fn etag_matches(headers: &HeaderMap, etag: &str) -> bool {
+    headers
+        .get_all(header::IF_NONE_MATCH)
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(','))
+        .map(str::trim)
+        .any(|tag| tag == "*" || tag.trim_start_matches("W/") == etag)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::auth::token::{TokenClaims, TokenIssuer};
+    use crate::auth::{AuthConfig, AuthPolicy};
+    use crate::metrics::GatewayMetrics;
+    use crate::rotation::{ManualClock, RotationPolicy, RotationSlotConfig};
+    use axum::body::Body;
+    use http::Request;
+    use http_body_util::BodyExt;
+    use quantun_crypto::mldsa::{MlDsaSignature, MlDsaVerifier};
+    use quantun_crypto::signer::KeyStoreSigner;
+    use quantun_types::{Algorithm, MlDsaVariant};
+    use tower::ServiceExt;
+
+    const SLOT: &str = "token-signing";
+
+    fn setup() -> (Arc<KeyStore>, Arc<RotationScheduler>, Router) {
+        let store = Arc::new(KeyStore::new());
+        let scheduler = Arc::new(RotationScheduler::new(
+            store.clone(),
+            Arc::new(ManualClock::new(1_000)),
+            Arc::new(GatewayMetrics::default()),
+        ));
+        scheduler
+            .add_slot(RotationSlotConfig {
+                name: SLOT.into(),
+                algorithm: Algorithm::MlDsa(MlDsaVariant::MlDsa44),
+                usages: vec![KeyUsage::Sign],
+                policy: RotationPolicy {
+                    interval: Duration::from_secs(3_600),
+                    lead_time: Duration::from_secs(600),
+                    overlap: Duration::from_secs(600),
+                },
+            })
+            .unwrap();
+        let service = JwksService::new(store.clone(), scheduler.clone(), [SLOT]);
+        (store, scheduler, router(Arc::new(service)))
+    }
+
+    async fn fetch(app: &Router, if_none_match: Option<&str>) -> Response {
+        let mut req = Request::get(JWKS_PATH);
+        if let Some(etag) = if_none_match {
+            req = req.header(header::IF_NONE_MATCH, etag);
+        }
+        app.clone()
+            .oneshot(req.body(Body::empty()).unwrap())
+            .await
+            .unwrap()
+    }
+
+    async fn key_set(response: Response) -> (String, JwkSet) {
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            response.headers()[header::CACHE_CONTROL],
+            "public, max-age=300"
+        );
+        let etag = response.headers()[header::ETAG]
+            .to_str()
+            .unwrap()
+            .to_string();
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        for key in json["keys"].as_array().unwrap() {
+            let mut names: Vec<_> = key.as_object().unwrap().keys().cloned().collect();
+            names.sort();
+            names.retain(|name| name != "status");
+            assert_eq!(names, ["alg", "kid", "kty", "pub", "use"]);
+        }
+        (etag, serde_json::from_slice(&body).unwrap())
+    }
+
+    fn verifies(set: &JwkSet, token: &str) -> bool {
+        let (_, payload, signature) = TokenClaims::decode(token).unwrap();
+        set.keys.iter().filter(|jwk| jwk.status.is_none()).any(|jwk| {
+            let verifier = MlDsaVerifier {
+                variant: MlDsaVariant::MlDsa44,
+                public_key: URL_SAFE_NO_PAD.decode(&jwk.public_key).unwrap(),
+            };
+            let sig = MlDsaSignature {
+                signature: signature.clone(),
+                variant: MlDsaVariant::MlDsa44,
+            };
+            verifier.verify(payload.as_bytes(), &sig).unwrap()
+        })
+    }
+
+    #[tokio::test]
+    async fn serves_keys_across_rotation() {
+        let (store, scheduler, app) = setup();
+        let first = scheduler.current(SLOT).unwrap();
+        let signer = KeyStoreSigner::new(store.clone(), first.clone()).unwrap();
+        let issuer = TokenIssuer::new(Arc::new(signer), "qsgw", Duration::from_secs(300));
+        let token = issuer.issue("alice", &["read".into()]).await.unwrap();
+
+        let (etag, set) = key_set(fetch(&app, None).await).await;
+        assert_eq!(set.keys.len(), 1);
+        let first_kid = fingerprint(&store.get_public(&first).unwrap());
+        let jwk = set.find(&first_kid).unwrap();
+        assert_eq!((jwk.kty.as_str(), jwk.alg.as_str()), ("AKP", "ML-DSA-44"));
+        assert!(verifies(&set, &token));
+        let legacy = Request::get(LEGACY_JWKS_PATH).body(Body::empty()).unwrap();
+        let (legacy_etag, _) = key_set(app.clone().oneshot(legacy).await.unwrap()).await;
+        assert_eq!(legacy_etag, etag);
+
+        let response = fetch(&app, Some(&etag)).await;
+        assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+        assert_eq!(response.headers()[header::ETAG], etag.as_str());
+
+        scheduler.force_rotate(SLOT, false).unwrap();
+        let second = scheduler.current(SLOT).unwrap();
+        let (new_etag, set) = key_set(fetch(&app, Some(&etag)).await).await;
+        assert_ne!(new_etag, etag);
+        assert_eq!(set.keys.len(), 2);
+        assert!(set.find(&first_kid).is_some());
+        assert!(set
+            .find(&fingerprint(&store.get_public(&second).unwrap()))
+            .is_some());
+        // Tokens signed before the rotation still verify during overlap.
+        assert!(verifies(&set, &token));
+    }
+
+    #[tokio::test]
+    async fn compromised_key_is_published_as_revoked() {
+        let (store, scheduler, app) = setup();
+        let first = scheduler.current(SLOT).unwrap();
+        let signer = KeyStoreSigner::new(store.clone(), first.clone()).unwrap();
+        let issuer = TokenIssuer::new(Arc::new(signer), "qsgw", Duration::from_secs(300));
+        let token = issuer.issue("alice", &["read".into()]).await.unwrap();
+
+        scheduler.force_rotate(SLOT, true).unwrap();
+        let (_, set) = key_set(fetch(&app, None).await).await;
+        assert_eq!(set.keys.len(), 2);
+        let first_kid = fingerprint(&store.get_public(&first).unwrap());
+        assert!(set.find(&first_kid).is_none());
+        assert!(set.is_revoked(&first_kid));
+        assert!(!set.is_revoked("unknown"));
+        assert!(!verifies(&set, &token));
+    }
+
+    #[test]
+    fn bypasses_auth_by_default() {
+        let policy = AuthPolicy::new(AuthConfig::default()).unwrap();
+        assert!(policy.is_bypassed(JWKS_PATH));
+        assert!(policy.is_bypassed(LEGACY_JWKS_PATH));
+    }
+}
@@ -0,0 +1,417 @@
+//! Application-layer key establishment for clients without PQC TLS.
+//!
+//! Clients fetch the gateway's KEM public key from `GET /kem/public-key`,
+//! encapsulate to it, and post the ciphertext to `POST /kem/exchange`. The
+//! gateway decapsulates with the keystore, derives a session key with
+//! [`derive_session_key`] over the ciphertext, and keeps it in a short-lived
+//! session table under a returned session ID. The key itself never leaves
+//! the gateway; the client derives the same key from its shared secret.
+
+use axum::body::Body;
+use axum::extract::State;
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use http::{header, Request, StatusCode};
+use quantun_crypto::derive::derive_session_key;
+use quantun_crypto::{CryptoError, CryptoResult, KeyHandle, KeyStore, SecureBytes};
+use quantun_types::{Algorithm, ErrorCode};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant};
+
+use crate::audit::caller_from_request;
+use crate::jwks::fingerprint;
+
+/// How long a session key stays available when no TTL is configured.
+pub const DEFAULT_SESSION_TTL: Duration = Duration::from_secs(300);
+/// Largest ciphertext body accepted by `POST /kem/exchange`.
+pub const MAX_EXCHANGE_BODY: usize = 16 * 1024;
+
+const SECONDS_PER_DAY: u64 = 86_400;
+
+/// Response of `GET /kem/public-key`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct KemPublicKey {
+    pub key_id: String,
+    pub algorithm: Algorithm,
+    /// Standard base64 encoding of the public key.
+    pub public_key: String,
+    /// Hex SHA-256 of the public key.
+    pub fingerprint: String,
+    /// When clients should refetch the key, in seconds since the Unix
+    /// epoch: the key's expiry, or the end of its recommended lifetime.
+    pub expires_at: u64,
+}
+
+/// JSON body of `POST /kem/exchange`. A body of any other content type is
+/// taken as the raw ciphertext.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExchangeRequest {
+    /// Standard base64 encoding of the KEM ciphertext.
+    pub ciphertext: String,
+}
+
+/// Response of `POST /kem/exchange`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExchangeResponse {
+    pub session_id: String,
+    pub key_id: String,
+    pub expires_in_secs: u64,
+}
+
+struct Session {
+    key: SecureBytes,
+    expires_at: Instant,
+}
+
+/// KEM key establishment over a keystore-held ML-KEM or hybrid key.
+pub struct KemService {
+    keystore: Arc<KeyStore>,
+    key: RwLock<KeyHandle>,
+    ttl: Duration,
+    sessions: Mutex<HashMap<String, Session>>,
+}
+
+impl KemService {
+    /// Serve `key`, which must be a KEM key with the `KeyAgreement` usage.
+    pub fn new(keystore: Arc<KeyStore>, key: KeyHandle) -> Self {
+        Self {
+            keystore,
+            key: RwLock::new(key),
+            ttl: DEFAULT_SESSION_TTL,
+            sessions: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Set how long session keys stay available.
+    pub fn with_session_ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = ttl;
+        self
+    }
+
+    /// Switch to a new KEM key, e.g. after a rotation. Existing sessions
+    /// are kept.
+    pub fn set_key(&self, key: KeyHandle) {
+        *self.key.write().unwrap() = key;
+    }
+
+    /// The current public key and its metadata.
+    pub fn public_key(&self) -> CryptoResult<KemPublicKey> {
+        let handle = self.key.read().unwrap().clone();
+        let public_key = self.keystore.get_public(&handle)?;
+        let metadata = self.keystore.metadata(&handle)?;
+        let lifetime =
+            u64::from(metadata.algorithm.recommended_max_key_age_days()) * SECONDS_PER_DAY;
+        let expires_at = metadata
+            .expires_at
+            .unwrap_or_else(|| metadata.activated_at.unwrap_or(metadata.created_at) + lifetime);
+        Ok(KemPublicKey {
+            key_id: handle.key_id().to_string(),
+            algorithm: metadata.algorithm,
+            public_key: STANDARD.encode(&public_key),
+            fingerprint: fingerprint(&public_key),
+            expires_at,
+        })
+    }
+
+    /// Session key for `session_id`, if it exists and has not expired.
+    pub fn session_key(&self, session_id: &str) -> Option<SecureBytes> {
+        let mut sessions = self.sessions.lock().unwrap();
+        let now = Instant::now();
+        sessions.retain(|_, s| s.expires_at > now);
+        sessions.get(session_id).map(|s| s.key.clone())
+    }
+
+    fn exchange<B>(&self, req: &Request<B>, ciphertext: &[u8]) -> CryptoResult<ExchangeResponse> {
+        let handle = self.key.read().unwrap().clone();
+        let caller = caller_from_request(req);
+        let shared_secret =
+            SecureBytes::new(self.keystore.decapsulate_as(&caller, &handle, ciphertext)?);
+        let key = derive_session_key(shared_secret.as_bytes(), ciphertext);
+
+        let mut id = [0u8; 16];
+        getrandom::fill(&mut id).map_err(|e| CryptoError::Rng(e.to_string()))?;
+        let session_id: String = id.iter().map(|b| format!("{b:02x}")).collect();
+
+        let now = Instant::now();
+        let mut sessions = self.sessions.lock().unwrap();
+        sessions.retain(|_, s| s.expires_at > now);
+        sessions.insert(
+            session_id.clone(),
+            Session {
+                key,
+                expires_at: now + self.ttl,
+            },
+        );
+        Ok(ExchangeResponse {
+            session_id,
+            key_id: handle.key_id().to_string(),
+            expires_in_secs: self.ttl.as_secs(),
+        })
+    }
+}
+
+/// KEM routes over `service`:
+///
+/// - `GET /kem/public-key`: the current [`KemPublicKey`].
+/// - `POST /kem/exchange`: decapsulate a ciphertext, sent as an
+///   [`ExchangeRequest`] or a raw body, and reply with an
+///   [`ExchangeResponse`].
+///
+/// [`build_router`](crate::build_router) mounts these as `Default`, inside
+/// the authentication and rate-limiting layers, when
+/// [`GatewayConfig::kem`](crate::GatewayConfig::kem) is set.
+pub fn router(service: Arc<KemService>) -> Router {
+    Router::new()
+        .route("/kem/public-key", get(public_key))
+        .route("/kem/exchange", post(exchange))
+        .with_state(service)
+}
+
+async fn public_key(State(service): State<Arc<KemService>>) -> Response {
+    match service.public_key() {
+        Ok(key) => Json(key).into_response(),
+        Err(e) => error_response(
+            StatusCode::SERVICE_UNAVAILABLE,
+            e.error_code(),
+            "KEM key unavailable",
+        ),
+    }
+}
+
+async fn exchange(State(service): State<Arc<KemService>>, req: Request<Body>) -> Response {
+    let (parts, body) = req.into_parts();
+    let Ok(body) = axum::body::to_bytes(body, MAX_EXCHANGE_BODY).await else {
+        return error_response(
+            StatusCode::PAYLOAD_TOO_LARGE,
+            ErrorCode::InvalidArgument,
+            "ciphertext too large",
+        );
+    };
+    let is_json = parts
+        .headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.starts_with("application/json"));
+    let ciphertext = if is_json {
+        let decoded = serde_json::from_slice::<ExchangeRequest>(&body)
+            .ok()
+            .and_then(|r| STANDARD.decode(r.ciphertext).ok());
+        match decoded {
+            Some(ciphertext) => ciphertext,
+            None => {
+                return error_response(
+                    StatusCode::BAD_REQUEST,
+                    ErrorCode::InvalidArgument,
+                    "expected {\"ciphertext\": <base64>}",
+                )
+            }
+        }
+    } else {
+        body.to_vec()
+    };
+
+    let req = Request::from_parts(parts, ());
+    match service.exchange(&req, &ciphertext) {
+        Ok(response) => Json(response).into_response(),
+        Err(e @ CryptoError::Decapsulation(_)) => error_response(
+            StatusCode::BAD_REQUEST,
+            e.error_code(),
+            "malformed ciphertext",
+        ),
+        Err(e @ (CryptoError::KeyUnusable { .. } | CryptoError::KeyNotFound(_))) => error_response(
+            StatusCode::SERVICE_UNAVAILABLE,
+            e.error_code(),
+            "KEM key unavailable",
+        ),
+        Err(e) => {
+            tracing::error!(error = %e, "KEM exchange failed");
+            error_response(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                ErrorCode::Internal,
+                "internal server error",
+            )
+        }
+    }
+}
+
+fn error_response(status: StatusCode, code: ErrorCode, message: &str) -> Response {
+    (
+        status,
+        Json(serde_json::json!({
+            "error_code": code.as_str(),
+            "message": message,
+        })),
+    )
+        .into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::auth::{auth_middleware, ApiKey, AuthConfig, AuthPolicy};
+    use http_body_util::BodyExt;
+    use quantun_crypto::mlkem::encapsulate_to;
+    use quantun_types::{KeyUsage, MlKemVariant};
+    use tower::ServiceExt;
+
+    fn service() -> Arc<KemService> {
+        let keystore = Arc::new(KeyStore::new());
+        let key = keystore
+            .create(
+                Algorithm::MlKem(MlKemVariant::MlKem768),
+                [KeyUsage::KeyAgreement],
+            )
+            .unwrap();
+        Arc::new(KemService::new(keystore, key))
+    }
+
+    async fn json<T: serde::de::DeserializeOwned>(response: Response) -> T {
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        serde_json::from_slice(&body).unwrap()
+    }
+
+    fn exchange_request(body: impl Into<Body>, content_type: &str) -> Request<Body> {
+        Request::post("/kem/exchange")
+            .header(header::CONTENT_TYPE, content_type)
+            .body(body.into())
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn client_and_gateway_derive_the_same_key() {
+        let service = service();
+        let app = router(service.clone());
+
+        let response = app
+            .clone()
+            .oneshot(Request::get("/kem/public-key").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let key: KemPublicKey = json(response).await;
+        let public_key = STANDARD.decode(&key.public_key).unwrap();
+        assert_eq!(key.fingerprint.len(), 64);
+        assert!(key.expires_at > key_created_at(&service));
+
+        // JSON body.
+        let enc = encapsulate_to(MlKemVariant::MlKem768, &public_key).unwrap();
+        let body = serde_json::to_vec(&ExchangeRequest {
+            ciphertext: STANDARD.encode(&enc.ciphertext),
+        })
+        .unwrap();
+        let response = app
+            .clone()
+            .oneshot(exchange_request(body, "application/json"))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let exchanged: ExchangeResponse = json(response).await;
+        assert_eq!(exchanged.key_id, key.key_id);
+        let client_key = derive_session_key(&enc.shared_secret, &enc.ciphertext);
+        let gateway_key = service.session_key(&exchanged.session_id).unwrap();
+        assert_eq!(client_key.as_bytes(), gateway_key.as_bytes());
+
+        // Raw body.
+        let enc = encapsulate_to(MlKemVariant::MlKem768, &public_key).unwrap();
+        let response = app
+            .oneshot(exchange_request(
+                enc.ciphertext.clone(),
+                "application/octet-stream",
+            ))
+            .await
+            .unwrap();
+        let raw: ExchangeResponse = json(response).await;
+        assert_ne!(raw.session_id, exchanged.session_id);
+        assert_eq!(
+            service.session_key(&raw.session_id).unwrap().as_bytes(),
+            derive_session_key(&enc.shared_secret, &enc.ciphertext).as_bytes()
+        );
+    }
+
+    fn key_created_at(service: &KemService) -> u64 {
+        let handle = service.key.read().unwrap().clone();
+        service.keystore.metadata(&handle).unwrap().created_at
+    }
+
+    #[tokio::test]
+    async fn malformed_ciphertext_is_a_bad_request() {
+        let app = router(service());
+
+        let response = app
+            .clone()
+            .oneshot(exchange_request(vec![0u8; 10], "application/octet-stream"))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let body: serde_json::Value = json(response).await;
+        assert_eq!(body["error_code"], "DECAPSULATION_FAILED");
+
+        let response = app
+            .oneshot(exchange_request(
+                r#"{"ciphertext": "%%"}"#,
+                "application/json",
+            ))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let body: serde_json::Value = json(response).await;
+        assert_eq!(body["error_code"], "INVALID_ARGUMENT");
+    }
+
+    #[test]
+    fn sessions_expire() {
+        let keystore = Arc::new(KeyStore::new());
+        let key = keystore
+            .create(
+                Algorithm::MlKem(MlKemVariant::MlKem512),
+                [KeyUsage::KeyAgreement],
+            )
+            .unwrap();
+        let public_key = keystore.get_public(&key).unwrap();
+        let service = KemService::new(keystore, key).with_session_ttl(Duration::ZERO);
+
+        let enc = encapsulate_to(MlKemVariant::MlKem512, &public_key).unwrap();
+        let req = Request::post("/kem/exchange").body(()).unwrap();
+        let exchanged = service.exchange(&req, &enc.ciphertext).unwrap();
+        assert!(service.session_key(&exchanged.session_id).is_none());
+    }
+
+    #[tokio::test]
+    async fn exchange_requires_auth() {
+        let policy = AuthPolicy::new(AuthConfig {
+            require_auth: true,
+            api_keys: vec![ApiKey {
+                id: "client-1".into(),
+                name: "legacy client".into(),
+                scopes: vec!["kem".into()],
+                signing_key: None,
+                metadata: HashMap::new(),
+                secret: None,
+            }],
+            ..AuthConfig::default()
+        })
+        .unwrap();
+        let app = router(service()).layer(axum::middleware::from_fn_with_state(
+            Arc::new(policy),
+            auth_middleware,
+        ));
+
+        let response = app
+            .clone()
+            .oneshot(exchange_request(vec![0u8; 10], "application/octet-stream"))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+        let mut req = exchange_request(vec![0u8; 10], "application/octet-stream");
+        req.headers_mut()
+            .insert("x-api-key", "client-1".parse().unwrap());
+        let response = app.oneshot(req).await.unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+}
@@ -1,19 +1,158 @@
+pub mod audit;
 pub mod auth;
+pub mod bench;
+pub mod builder;
+pub mod config_file;
+pub mod devices;
+pub mod discovery;
+#[cfg(feature = "grpc")]
+pub mod grpc_error;
+pub mod inventory;
+pub mod jwks;
+pub mod kem;
+pub mod keyfile;
+pub mod listener;
+pub mod maintenance;
+pub mod metrics;
 pub mod middleware;
 pub mod proxy;
+pub mod rate_limit;
+pub mod request_signature;
+pub mod response_signing;
+pub mod rotation;
+pub mod scanner;
+pub mod self_test;
+pub mod server;
+pub mod signer;
+pub mod telemetry;
+pub mod tenant;
 pub mod tls;
+pub mod tunnel;
+pub mod version;
+pub mod webhook;
 
-use axum::{routing::get, Router};
+use axum::body::Body;
+use axum::extract::State;
+use axum::response::{IntoResponse, Response};
+use axum::routing::{any, get, MethodRouter};
+use axum::Router;
+use http::{header, Request};
+use std::collections::HashSet;
+use std::convert::Infallible;
 use std::net::SocketAddr;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use tower::{Layer, Service, ServiceBuilder};
+
+use metrics::GatewayMetrics;
+use proxy::MiddlewareProfile;
 
 pub struct GatewayConfig {
     pub listen_addr: SocketAddr,
+    /// Acceptor tasks and `SO_REUSEPORT` handoff; see [`listener`].
+    pub listener: listener::ListenerConfig,
     pub tls_policy: TlsPolicy,
+    /// PQC algorithms the gateway supports, most preferred first. Clients
+    /// negotiate among them with the `algorithm-negotiation` feature; see
+    /// [`middleware::version_negotiation`].
+    pub preferred_algorithms: Vec<quantun_types::Algorithm>,
     pub max_connections: usize,
     pub upstream_timeout_secs: u64,
+    /// TLS session resumption for HTTPS upstreams; see
+    /// [`proxy::upstream_tls`].
+    pub upstream_tls: proxy::upstream_tls::UpstreamTlsConfig,
+    /// Ramp up discovered upstreams as they become healthy; see
+    /// [`proxy::slow_start`]. Off by default.
+    pub slow_start: Option<proxy::slow_start::SlowStartConfig>,
+    /// Close TLS connections whose handshake has not completed within
+    /// this many seconds.
+    pub handshake_timeout_secs: u64,
+    /// Shed requests with 503 once active connections exceed this count.
+    pub load_shed_threshold: Option<usize>,
+    /// Emit a `Server-Timing` header with handshake and upstream durations.
+    /// Off by default since it exposes internal timing.
+    pub server_timing: bool,
+    /// Answer handler panics with a `500` instead of dropping the
+    /// connection. Disable to let panics propagate, e.g. under a debugger.
+    pub catch_panics: bool,
+    /// Answer requests with `503` during deploys; see [`maintenance`].
+    pub maintenance: maintenance::MaintenanceConfig,
+    /// Crypto power-on self tests; see [`self_test`].
+    pub self_test: self_test::SelfTestConfig,
+    /// Methods refused on every proxied route; see [`proxy::methods`].
+    /// Defaults to [`proxy::DEFAULT_DENIED_METHODS`].
+    pub denied_methods: Vec<String>,
+    /// `X-Forwarded-Proto` sent upstream; pass to
+    /// [`proxy::ProxyService::with_forwarded_proto`]. Defaults to `https`.
+    pub forwarded_proto: proxy::ForwardedProto,
+    /// Egress proxies upstreams are reached through; see [`proxy::egress`].
+    /// Direct by default.
+    pub egress: proxy::egress::EgressConfig,
+    /// Require a client certificate (mutual TLS), checked with
+    /// [`tls::ClientCertVerifier::validate`]. Off by default.
+    pub mtls: Option<Arc<tls::ClientCertVerifier>>,
+    /// Whether an external TLS terminator in front of the gateway is trusted
+    /// to set the `x-tls-*` headers. Otherwise client-supplied copies are
+    /// dropped; see [`server::Termination`]. Off by default.
+    pub trusted_terminator: bool,
+    /// Proxies whose `X-Forwarded-For` entries are believed when working
+    /// out each request's [`middleware::TrueClientIp`]. Empty by default,
+    /// so the socket peer is the client.
+    pub trusted_proxies: Vec<ipnet::IpNet>,
+    /// Which requests may be served from TLS 1.3 early data; others get
+    /// `425 Too Early`. Defaults to rejecting all early data.
+    pub early_data: quantun_tls::config::EarlyDataPolicy,
+    pub tracing: telemetry::TracingConfig,
+    /// Routes served by [`GatewayConfig::proxy_service`]. Empty by default.
+    pub routes: Vec<proxy::Route>,
+    /// Gateway-to-gateway tunnels for routes with a `tunnel` peer, and the
+    /// address to accept them on; see [`tunnel`]. Off by default.
+    pub tunnel: Option<tunnel::TunnelSettings>,
+    /// Opens sealed bodies on routes with `unseal` set; see
+    /// [`proxy::sealed`]. Required by such routes.
+    pub unsealer: Option<Arc<proxy::sealed::Unsealer>>,
+    /// Authentication for routes whose [`MiddlewareProfile`] requires it.
+    /// Without a policy every route is open.
+    pub auth: Option<Arc<auth::AuthPolicy>>,
+    /// Per-client rate limiting on routes whose [`MiddlewareProfile`] rate
+    /// limits; see [`rate_limit`]. Off by default.
+    pub rate_limit: Option<Arc<rate_limit::RateLimiter>>,
+    /// Per-request tenant resolution; see [`tenant`]. Off by default.
+    pub tenant: Option<Arc<tenant::TenantPolicy>>,
+    /// Scheduled key rotation. When set, its admin endpoint is mounted as
+    /// `AdminOnly`; see [`rotation::router`].
+    pub rotation: Option<Arc<rotation::RotationScheduler>>,
+    /// The keystore whose key usage audit endpoint is mounted as
+    /// `AdminOnly` when set; see [`audit::router`].
+    pub keystore: Option<Arc<quantun_crypto::KeyStore>>,
+    /// Upstream PQC readiness scanning. When set, its reports are mounted
+    /// as `Default` and its rescan endpoint as `AdminOnly`; see
+    /// [`scanner::router`] and [`scanner::admin_router`].
+    pub scanner: Option<Arc<scanner::UpstreamScanner>>,
+    /// Application-layer key establishment. When set, its endpoints are
+    /// mounted as `Default`; see [`kem::router`].
+    pub kem: Option<Arc<kem::KemService>>,
+    /// Publication of token-verification keys. When set, the key set is
+    /// mounted as `NoAuth`; see [`jwks::router`].
+    pub jwks: Option<Arc<jwks::JwksService>>,
+    /// Device provisioning. When set, its device-facing endpoints are
+    /// mounted as `Default` and registration and revocation as
+    /// `AdminOnly`; see [`devices::router`] and [`devices::admin_router`].
+    pub devices: Option<Arc<devices::DeviceRegistry>>,
+    /// The cryptographic inventory. When set, its report is mounted as
+    /// `Default`; see [`inventory::router`].
+    pub inventory: Option<Arc<inventory::CryptoInventory>>,
+    /// Where request metrics are emitted. Defaults to
+    /// [`metrics::NoopMetricsSink`]. A sink that can be scraped, such as
+    /// [`metrics::PrometheusSink`], is served at [`metrics::METRICS_PATH`].
+    /// Connection metrics go to the sink of
+    /// the [`GatewayMetrics`] given to [`server::serve`]; see
+    /// [`GatewayMetrics::with_sink`].
+    pub metrics_sink: Arc<dyn metrics::MetricsSink>,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum TlsPolicy {
     PqcOnly,
     PqcPreferred,
@@ -25,28 +164,412 @@ impl Default for GatewayConfig {
     fn default() -> Self {
         Self {
             listen_addr: SocketAddr::from(([0, 0, 0, 0], 8443)),
+            listener: listener::ListenerConfig::default(),
             tls_policy: TlsPolicy::PqcPreferred,
+            preferred_algorithms: quantun_tls::config::TlsConfig::default().preferred_algorithms,
             max_connections: 10_000,
             upstream_timeout_secs: 30,
+            upstream_tls: proxy::upstream_tls::UpstreamTlsConfig::default(),
+            slow_start: None,
+            handshake_timeout_secs: 10,
+            load_shed_threshold: None,
+            server_timing: false,
+            catch_panics: true,
+            maintenance: maintenance::MaintenanceConfig::default(),
+            self_test: self_test::SelfTestConfig::default(),
+            denied_methods: proxy::DEFAULT_DENIED_METHODS.map(String::from).to_vec(),
+            forwarded_proto: proxy::ForwardedProto::default(),
+            egress: proxy::egress::EgressConfig::default(),
+            mtls: None,
+            trusted_terminator: false,
+            trusted_proxies: Vec::new(),
+            early_data: quantun_tls::config::EarlyDataPolicy::default(),
+            tracing: telemetry::TracingConfig::default(),
+            routes: Vec::new(),
+            tunnel: None,
+            unsealer: None,
+            auth: None,
+            rate_limit: None,
+            tenant: None,
+            rotation: None,
+            keystore: None,
+            scanner: None,
+            kem: None,
+            jwks: None,
+            devices: None,
+            inventory: None,
+            metrics_sink: Arc::new(metrics::NoopMetricsSink),
+        }
+    }
+}
+
+impl GatewayConfig {
+    /// Start a [`builder::GatewayConfigBuilder`] from the defaults.
+    pub fn builder() -> builder::GatewayConfigBuilder {
+        builder::GatewayConfigBuilder::new()
+    }
+
+    /// A proxy over [`GatewayConfig::routes`] with the configured upstream
+    /// timeout, upstream TLS, slow start, denied methods,
+    /// `X-Forwarded-Proto`, egress proxies, tunnels and unsealer.
+    pub fn proxy_service(&self) -> proxy::ProxyService {
+        let mut proxy = proxy::ProxyService::new(self.routes.clone(), self.upstream_timeout_secs)
+            .with_upstream_tls(self.upstream_tls.clone())
+            .with_denied_methods(self.denied_methods.clone())
+            .with_forwarded_proto(self.forwarded_proto)
+            .with_egress(self.egress.clone());
+        if let Some(tunnel) = &self.tunnel {
+            for connector in tunnel.connectors() {
+                proxy = proxy.with_tunnel(connector);
+            }
+        }
+        if let Some(unsealer) = &self.unsealer {
+            proxy = proxy.with_unsealer(unsealer.clone());
+        }
+        match &self.slow_start {
+            Some(slow_start) => proxy.with_slow_start(slow_start.clone()),
+            None => proxy,
         }
     }
 }
 
+/// The per-route middleware for `profile`: rate limiting against
+/// [`GatewayConfig::rate_limit`], authentication against
+/// [`GatewayConfig::auth`], and the admin scope check, as the
+/// profile requires, then tenant resolution against
+/// [`GatewayConfig::tenant`]. Admin-only routes refuse every request
+/// without an auth policy.
+pub fn profile_to_layer(
+    profile: MiddlewareProfile,
+    config: &GatewayConfig,
+) -> impl Layer<
+    axum::routing::Route,
+    Service: Service<
+        Request<Body>,
+        Response = Response,
+        Error = Infallible,
+        Future: Send + 'static,
+    > + Clone
+                 + Send
+                 + Sync
+                 + 'static,
+> + Clone
+       + Send
+       + Sync
+       + 'static {
+    let auth = config.auth.clone().filter(|_| profile.authenticates());
+    let admin = auth
+        .clone()
+        .filter(|_| profile == MiddlewareProfile::AdminOnly);
+    let limiter = config.rate_limit.clone().filter(|_| profile.rate_limits());
+    ServiceBuilder::new()
+        .option_layer(limiter.map(|limiter| {
+            axum::middleware::from_fn_with_state(limiter, rate_limit::rate_limit_middleware)
+        }))
+        .option_layer(
+            auth.map(|policy| axum::middleware::from_fn_with_state(policy, auth::auth_middleware)),
+        )
+        .option_layer(admin.map(|policy| {
+            axum::middleware::from_fn_with_state(policy, auth::admin_only_middleware)
+        }))
+        .option_layer(
+            (profile == MiddlewareProfile::AdminOnly && config.auth.is_none())
+                .then(|| axum::middleware::from_fn(auth::no_admin_middleware)),
+        )
+        .option_layer(config.tenant.clone().map(|policy| {
+            let resolver = tenant::TenantResolver {
+                policy,
+                auth: config.auth.clone(),
+                rate_limit: profile.rate_limits(),
+            };
+            axum::middleware::from_fn_with_state(resolver, tenant::tenant_middleware)
+        }))
+}
+
 pub fn build_router(config: &GatewayConfig) -> Router {
-    Router::new()
-        .route("/health", get(health_check))
+    build_router_with_metrics(config, Arc::new(GatewayMetrics::default()))
+}
+
+/// Build the router, sharing `metrics` with the caller (e.g. the listener
+/// that tracks active connections).
+///
+/// Each route runs behind the layer for its [`MiddlewareProfile`]: health
+/// and discovery routes are `NoAuth`, the maintenance admin route is
+/// `AdminOnly` and only mounted when [`GatewayConfig::auth`] is set,
+/// [`metrics::METRICS_PATH`] is only mounted for a sink that can be
+/// scraped, the endpoints of optional services such as
+/// [`GatewayConfig::rotation`] are mounted when the service is set, under
+/// the profiles their fields name, the route dry run at
+/// [`proxy::dry_run::DRY_RUN_PATH`] is `AdminOnly`, and
+/// [`GatewayConfig::routes`] are proxied under their own profiles. Proxied
+/// path prefixes must not overlap the gateway's own paths.
+pub fn build_router_with_metrics(config: &GatewayConfig, metrics: Arc<GatewayMetrics>) -> Router {
+    router(config, metrics, true)
+}
+
+/// [`build_router_with_metrics`], optionally without the PQC enforcement
+/// middleware so [`bench`] can measure its cost.
+pub(crate) fn router(
+    config: &GatewayConfig,
+    metrics: Arc<GatewayMetrics>,
+    pqc_enforcement: bool,
+) -> Router {
+    let discovery = discovery::DiscoveryDocument::for_policy(config.tls_policy);
+    let maintenance = Arc::new(maintenance::MaintenanceState::new(&config.maintenance));
+    let with_profile =
+        |route: MethodRouter<TlsPolicy>, profile| route.layer(profile_to_layer(profile, config));
+
+    let mut router = Router::new()
+        .route(
+            "/health",
+            with_profile(
+                get({
+                    let self_test = config.self_test.clone();
+                    move || health(self_test.clone())
+                }),
+                MiddlewareProfile::NoAuth,
+            ),
+        )
+        .route(
+            "/livez",
+            with_profile(get(health_check), MiddlewareProfile::NoAuth),
+        )
+        .route(
+            self_test::ADMIN_PATH,
+            with_profile(
+                self_test::admin_routes(config.self_test.clone()),
+                MiddlewareProfile::AdminOnly,
+            ),
+        )
+        .route(
+            discovery::DISCOVERY_PATH,
+            with_profile(
+                get(move || async move { axum::Json(discovery) }),
+                MiddlewareProfile::NoAuth,
+            ),
+        )
+        .route(
+            version::VERSION_PATH,
+            with_profile(
+                get({
+                    let info = version::VersionInfo::new(config.tls_policy);
+                    move || async move { axum::Json(info) }
+                }),
+                MiddlewareProfile::Default,
+            ),
+        )
         .route(
             "/gateway/stats",
+            with_profile(
+                get({
+                    let policy = config.tls_policy;
+                    let metrics = metrics.clone();
+                    move || stats(policy, metrics.clone())
+                }),
+                MiddlewareProfile::Default,
+            ),
+        );
+
+    if config.metrics_sink.scrape().is_some() {
+        let sink = config.metrics_sink.clone();
+        router = router.route(
+            metrics::METRICS_PATH,
+            with_profile(
+                get(move || async move {
+                    let content_type = (header::CONTENT_TYPE, metrics::EXPOSITION_CONTENT_TYPE);
+                    ([content_type], sink.scrape().unwrap_or_default())
+                }),
+                MiddlewareProfile::Default,
+            ),
+        );
+    }
+
+    // Anyone who can reach the toggle can take the gateway offline.
+    if config.auth.is_some() {
+        router = router.route(
+            maintenance::ADMIN_PATH,
+            with_profile(
+                maintenance::admin_routes(maintenance.clone()),
+                MiddlewareProfile::AdminOnly,
+            ),
+        );
+    }
+
+    // Routers of optional services, each behind a single profile.
+    let mount = |router: Router<TlsPolicy>, routes: Router, profile| {
+        let routes = routes.route_layer(profile_to_layer(profile, config));
+        router.merge(routes.with_state(()))
+    };
+    if let Some(scheduler) = &config.rotation {
+        let routes = rotation::router(scheduler.clone());
+        router = mount(router, routes, MiddlewareProfile::AdminOnly);
+    }
+    if let Some(keystore) = &config.keystore {
+        let routes = audit::router(keystore.clone());
+        router = mount(router, routes, MiddlewareProfile::AdminOnly);
+    }
+    if let Some(service) = &config.kem {
+        let routes = kem::router(service.clone());
+        router = mount(router, routes, MiddlewareProfile::Default);
+    }
+    if let Some(service) = &config.jwks {
+        let routes = jwks::router(service.clone());
+        router = mount(router, routes, MiddlewareProfile::NoAuth);
+    }
+    if let Some(crypto_inventory) = &config.inventory {
+        let routes = inventory::router(crypto_inventory.clone());
+        router = mount(router, routes, MiddlewareProfile::Default);
+    }
+    if let Some(registry) = &config.devices {
+        let routes = devices::router(registry.clone());
+        router = mount(router, routes, MiddlewareProfile::Default);
+        let routes = devices::admin_router(registry.clone());
+        router = mount(router, routes, MiddlewareProfile::AdminOnly);
+    }
+
+    let proxy = Arc::new(config.proxy_service().with_metrics(metrics.clone()));
+    router = router.route(
+        "/gateway/upstreams",
+        with_profile(
             get({
-                let policy = config.tls_policy;
-                move || stats(policy)
+                let proxy = proxy.clone();
+                move || async move { axum::Json(proxy.upstream_weights()) }
             }),
+            MiddlewareProfile::Default,
+        ),
+    );
+    let routes = proxy::dry_run::router(proxy.clone());
+    router = mount(router, routes, MiddlewareProfile::AdminOnly);
+    if let Some(upstream_scanner) = &config.scanner {
+        let routes = scanner::router(upstream_scanner.clone());
+        router = mount(router, routes, MiddlewareProfile::Default);
+        let routes = scanner::admin_router(upstream_scanner.clone());
+        router = mount(router, routes, MiddlewareProfile::AdminOnly);
+    }
+    let mut mounted = HashSet::new();
+    for route in proxy.routes() {
+        if !mounted.insert(route.path_prefix.clone()) {
+            continue;
+        }
+        let handler = with_profile(
+            any(proxy_request).with_state(proxy.clone()),
+            route.middleware_profile,
+        );
+        let prefix = route.path_prefix.trim_end_matches('/');
+        router = router
+            .route(
+                if prefix.is_empty() { "/" } else { prefix },
+                handler.clone(),
+            )
+            .route(&format!("{prefix}/{{*rest}}"), handler);
+    }
+
+    #[cfg(test)]
+    let router = router
+        .route(
+            "/test/panic",
+            with_profile(get(panic_handler), MiddlewareProfile::Default),
         )
-        .layer(axum::middleware::from_fn_with_state(
-            config.tls_policy,
+        .route(
+            "/test/client-ip",
+            with_profile(get(client_ip_handler), MiddlewareProfile::Default),
+        );
+
+    let router = if pqc_enforcement {
+        router.layer(axum::middleware::from_fn_with_state(
+            middleware::PqcEnforcement {
+                policy: config.tls_policy,
+                mtls: config.mtls.clone(),
+            },
             middleware::pqc_enforcement_middleware,
         ))
-        .with_state(config.tls_policy)
+    } else {
+        router
+    };
+    #[cfg(feature = "algorithm-negotiation")]
+    let router = router.layer(
+        middleware::version_negotiation::AlgorithmNegotiationLayer::new(
+            middleware::version_negotiation::AlgorithmNegotiator::new(
+                config.preferred_algorithms.clone(),
+            ),
+        ),
+    );
+    let router = match &config.tenant {
+        Some(policy) => router.layer(axum::middleware::from_fn_with_state(
+            (policy.clone(), metrics.clone()),
+            tenant::tenant_metrics_middleware,
+        )),
+        None => router,
+    };
+    let mut router = router
+        .layer(axum::middleware::from_fn_with_state(
+            config.early_data,
+            middleware::early_data_middleware,
+        ))
+        .layer(axum::middleware::from_fn_with_state(
+            maintenance,
+            maintenance::maintenance_middleware,
+        ))
+        .layer(axum::middleware::from_fn_with_state(
+            (config.metrics_sink.clone(), metrics.clone()),
+            middleware::request_metrics_middleware,
+        ))
+        // Outside every route's profile layers, so authentication, rate
+        // limiting and tenant resolution all see the client's address.
+        .layer(middleware::trusted_proxy_layer(
+            config.trusted_proxies.clone(),
+        ));
+
+    if config.server_timing {
+        router = router.layer(axum::middleware::from_fn(
+            middleware::server_timing_middleware,
+        ));
+    }
+
+    if let Some(threshold) = config.load_shed_threshold {
+        router = router.layer(middleware::load_shed_layer(threshold, metrics.clone()));
+    }
+
+    if config.catch_panics {
+        router = router.layer(middleware::panic_recovery_layer(metrics));
+    }
+
+    router.with_state(config.tls_policy)
+}
+
+/// Forward a request to the route [`proxy::ProxyService::find_route_for`]
+/// picks for its path and `Content-Type`.
+async fn proxy_request(
+    State(proxy): State<Arc<proxy::ProxyService>>,
+    req: Request<Body>,
+) -> Response {
+    let content_type = req
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok());
+    let Some(route) = proxy.find_route_for(req.uri().path(), content_type) else {
+        return proxy::ProxyError::NoHealthyUpstream.into_response();
+    };
+    proxy
+        .forward(&route, req)
+        .await
+        .unwrap_or_else(IntoResponse::into_response)
+}
+
+/// [`health_check`] with the latest crypto self-test report; `degraded`
+/// if a check failed.
+async fn health(self_test: self_test::SelfTestConfig) -> axum::Json<serde_json::Value> {
+    let section = self_test::health_section(&self_test);
+    let status = if section["passed"] == false {
+        "degraded"
+    } else {
+        "ok"
+    };
+    axum::Json(serde_json::json!({
+        "status": status,
+        "service": "qsgw-gateway",
+        "crypto_self_test": section,
+    }))
 }
 
 async fn health_check() -> axum::Json<serde_json::Value> {
@@ -56,12 +579,45 @@ async fn health_check() -> axum::Json<serde_json::Value> {
     }))
 }
 
-async fn stats(policy: TlsPolicy) -> axum::Json<serde_json::Value> {
+#[cfg(test)]
+async fn panic_handler() -> &'static str {
+    panic!("test panic")
+}
+
+#[cfg(test)]
+async fn client_ip_handler(client_ip: Option<axum::Extension<middleware::TrueClientIp>>) -> String {
+    client_ip.map(|ip| ip.0 .0.to_string()).unwrap_or_default()
+}
+
+async fn stats(policy: TlsPolicy, metrics: Arc<GatewayMetrics>) -> axum::Json<serde_json::Value> {
     axum::Json(serde_json::json!({
         "tls_policy": format!("{:?}", policy),
-        "active_connections": 0,
-        "pqc_sessions": 0,
-        "classical_sessions": 0,
+        "active_connections": metrics.active_connections.load(Ordering::Relaxed),
+        "connections_accepted": metrics.connections_accepted.load(Ordering::Relaxed),
+        "connections_closed": metrics.connections_closed.load(Ordering::Relaxed),
+        "handshakes_attempted": metrics.handshakes_attempted.load(Ordering::Relaxed),
+        "handshakes_succeeded": metrics.handshakes_succeeded.load(Ordering::Relaxed),
+        "handshake_failures": {
+            "timeout": metrics.handshake_timeouts.load(Ordering::Relaxed),
+            "protocol": metrics.handshake_protocol_failures.load(Ordering::Relaxed),
+            "certificate": metrics.handshake_certificate_failures.load(Ordering::Relaxed),
+            "io": metrics.handshake_io_failures.load(Ordering::Relaxed),
+        },
+        "shed_requests": metrics.shed_requests.load(Ordering::Relaxed),
+        "panic_count": metrics.panic_count.load(Ordering::Relaxed),
+        "transform_bypasses": metrics.transform_bypasses.load(Ordering::Relaxed),
+        "transform_failures": metrics.transform_failures.load(Ordering::Relaxed),
+        "remote_signs": metrics.remote_signs.load(Ordering::Relaxed),
+        "remote_sign_failures": metrics.remote_sign_failures.load(Ordering::Relaxed),
+        "remote_sign_latency_us": metrics.remote_sign_latency_us.load(Ordering::Relaxed),
+        "key_rotations": metrics.key_rotations.load(Ordering::Relaxed),
+        "keys_retired": metrics.keys_retired.load(Ordering::Relaxed),
+        "pqc_ready_percent": metrics.pqc_ready_percent.load(Ordering::Relaxed),
+        "risk_regressions": metrics.risk_regressions.load(Ordering::Relaxed),
+        "upstreams": metrics.upstream_stats(),
+        "tenants": metrics.tenant_stats(),
+        "pqc_sessions": metrics.pqc_sessions.load(Ordering::Relaxed),
+        "classical_sessions": metrics.classical_sessions.load(Ordering::Relaxed),
     }))
 }
 
@@ -102,4 +658,777 @@ mod tests {
 
         assert_eq!(response.status(), 200);
     }
+
+    #[tokio::test]
+    async fn test_stats_count_proxied_requests() {
+        use crate::proxy::transform::{JsonFields, TransformConfig, TransformStep};
+
+        let upstream = proxy::testing::MockUpstream::start().await.unwrap();
+        let config = GatewayConfig {
+            routes: vec![proxy::Route {
+                transform: Some(TransformConfig {
+                    request: vec![TransformStep::JsonFields(JsonFields {
+                        remove: vec!["secret".into()],
+                        ..Default::default()
+                    })],
+                    max_body_bytes: 16,
+                    ..Default::default()
+                }),
+                ..upstream.route("/api")
+            }],
+            ..GatewayConfig::default()
+        };
+        let app = build_router(&config);
+        let post = |body: &'static str| {
+            let req = Request::post("/api/items")
+                .header(header::CONTENT_TYPE, "application/json")
+                .body(Body::from(body))
+                .unwrap();
+            app.clone().oneshot(req)
+        };
+
+        assert_eq!(post(r#"{"secret":"s"}"#).await.unwrap().status(), 200);
+        let too_large = r#"{"padding":"0123456789"}"#;
+        assert_eq!(post(too_large).await.unwrap().status(), 200);
+        assert_eq!(post(r#"{"secret":"#).await.unwrap().status(), 502);
+
+        let response = app
+            .oneshot(Request::get("/gateway/stats").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let stats: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(stats["transform_bypasses"], 1);
+        assert_eq!(stats["transform_failures"], 1);
+        // The request that failed its transform never reached the upstream.
+        assert_eq!(
+            stats["upstreams"],
+            serde_json::json!([{
+                "upstream": "mock",
+                "requests": 2,
+                "successes": 2,
+                "server_errors": 0,
+                "timeouts": 0,
+                "connection_failures": 0,
+            }])
+        );
+    }
+
+    #[tokio::test]
+    async fn test_load_shedding() {
+        let config = GatewayConfig {
+            load_shed_threshold: Some(2),
+            ..GatewayConfig::default()
+        };
+        let metrics = Arc::new(GatewayMetrics::default());
+        let app = build_router_with_metrics(&config, metrics.clone());
+
+        metrics.active_connections.store(2, Ordering::Relaxed);
+        let response = app
+            .clone()
+            .oneshot(Request::builder().uri("/health").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), 200);
+
+        metrics.active_connections.store(3, Ordering::Relaxed);
+        let response = app
+            .oneshot(Request::builder().uri("/health").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), 503);
+        assert_eq!(response.headers()["retry-after"], "1");
+        assert_eq!(metrics.shed_requests.load(Ordering::Relaxed), 1);
+    }
+
+    #[tokio::test]
+    async fn test_panic_recovery() {
+        let metrics = Arc::new(GatewayMetrics::default());
+        let app = build_router_with_metrics(&GatewayConfig::default(), metrics.clone());
+
+        let response = app
+            .clone()
+            .oneshot(Request::builder().uri("/test/panic").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), 500);
+        let body = http_body_util::BodyExt::collect(response.into_body())
+            .await
+            .unwrap()
+            .to_bytes();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(json["error_code"], "INTERNAL");
+        assert_eq!(json["message"], "internal server error");
+        assert_eq!(metrics.panic_count.load(Ordering::Relaxed), 1);
+
+        let response = app
+            .oneshot(Request::builder().uri("/health").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), 200);
+    }
+
+    /// An auth policy requiring the API key `ops`, which has the admin
+    /// scope, or `reader`, which does not.
+    fn admin_and_reader_policy() -> Arc<auth::AuthPolicy> {
+        use crate::auth::{ApiKey, AuthConfig, AuthPolicy, ADMIN_SCOPE};
+
+        let key = |id: &str, scope: &str| ApiKey {
+            id: id.into(),
+            name: id.into(),
+            scopes: vec![scope.into()],
+            signing_key: None,
+            metadata: Default::default(),
+            secret: None,
+        };
+        let policy = AuthPolicy::new(AuthConfig {
+            require_auth: true,
+            api_keys: vec![key("ops", ADMIN_SCOPE), key("reader", "read")],
+            bypass_paths: Vec::new(),
+            ..AuthConfig::default()
+        })
+        .unwrap();
+        Arc::new(policy)
+    }
+
+    /// The status of a `method` request to `uri`, sent with `api_key`.
+    async fn status_of(
+        app: &Router,
+        method: &str,
+        uri: &str,
+        api_key: Option<&str>,
+    ) -> http::StatusCode {
+        let mut req = Request::builder().method(method).uri(uri);
+        if let Some(api_key) = api_key {
+            req = req.header("x-api-key", api_key);
+        }
+        let req = req.body(Body::empty()).unwrap();
+        app.clone().oneshot(req).await.unwrap().status()
+    }
+
+    #[tokio::test]
+    async fn test_route_middleware_profiles() {
+        use crate::proxy::testing::MockUpstream;
+
+        let upstream = MockUpstream::start().await.unwrap();
+        let config = GatewayConfig {
+            auth: Some(admin_and_reader_policy()),
+            routes: vec![
+                upstream.route("/api/data"),
+                proxy::Route {
+                    middleware_profile: MiddlewareProfile::NoAuth,
+                    ..upstream.route("/public")
+                },
+            ],
+            ..GatewayConfig::default()
+        };
+        let app = build_router(&config);
+        let status = |uri, api_key| status_of(&app, "GET", uri, api_key);
+
+        assert_eq!(status("/health", None).await, 200);
+        assert_eq!(status("/api/data", None).await, 401);
+        assert_eq!(status("/api/data/items", Some("bogus")).await, 403);
+        assert_eq!(status("/api/data/items", Some("reader")).await, 200);
+        assert_eq!(status("/public/page", None).await, 200);
+        assert_eq!(status("/gateway/stats", None).await, 401);
+        assert_eq!(status(version::VERSION_PATH, None).await, 401);
+
+        assert_eq!(status(maintenance::ADMIN_PATH, Some("reader")).await, 403);
+        assert_eq!(status(maintenance::ADMIN_PATH, Some("ops")).await, 200);
+
+        let paths: Vec<_> = upstream
+            .requests()
+            .into_iter()
+            .map(|r| r.uri.path().to_string())
+            .collect();
+        assert_eq!(paths, ["/items", "/page"]);
+    }
+
+    #[tokio::test]
+    async fn test_rate_limit_follows_route_profiles() {
+        use crate::proxy::testing::MockUpstream;
+        use crate::rate_limit::RateLimit;
+
+        let upstream = MockUpstream::start().await.unwrap();
+        let config = GatewayConfig::builder()
+            .rate_limit(RateLimit {
+                requests_per_second: 0.001,
+                burst: 1,
+            })
+            .build()
+            .unwrap();
+        let config = GatewayConfig {
+            routes: vec![
+                upstream.route("/api/data"),
+                proxy::Route {
+                    middleware_profile: MiddlewareProfile::NoRateLimit,
+                    ..upstream.route("/bulk")
+                },
+            ],
+            ..config
+        };
+        let app = build_router(&config);
+        let status = |uri| status_of(&app, "GET", uri, None);
+
+        assert_eq!(status("/api/data/items").await, 200);
+        assert_eq!(status("/api/data/items").await, 429);
+        for _ in 0..3 {
+            assert_eq!(status("/bulk/items").await, 200);
+        }
+    }
+
+    /// A scheduler over a new keystore with one ML-DSA-44 slot,
+    /// `token-signing`.
+    fn token_signing_scheduler(
+        keystore: Arc<quantun_crypto::KeyStore>,
+    ) -> Arc<rotation::RotationScheduler> {
+        use crate::rotation::{ManualClock, RotationPolicy, RotationScheduler, RotationSlotConfig};
+        use quantun_types::{Algorithm, KeyUsage, MlDsaVariant};
+
+        let scheduler = RotationScheduler::new(
+            keystore,
+            Arc::new(ManualClock::new(1_000)),
+            Arc::new(GatewayMetrics::default()),
+        );
+        scheduler
+            .add_slot(RotationSlotConfig {
+                name: "token-signing".into(),
+                algorithm: Algorithm::MlDsa(MlDsaVariant::MlDsa44),
+                usages: vec![KeyUsage::Sign],
+                policy: RotationPolicy {
+                    interval: std::time::Duration::from_secs(100),
+                    lead_time: std::time::Duration::from_secs(10),
+                    overlap: std::time::Duration::from_secs(20),
+                },
+            })
+            .unwrap();
+        Arc::new(scheduler)
+    }
+
+    #[tokio::test]
+    async fn test_rotation_endpoint_is_admin_only() {
+        let keystore = Arc::new(quantun_crypto::KeyStore::new());
+        let config = GatewayConfig::builder()
+            .auth(admin_and_reader_policy())
+            .rotation(token_signing_scheduler(keystore))
+            .build()
+            .unwrap();
+        let app = build_router(&config);
+        let rotate = "/admin/rotation/token-signing/rotate";
+
+        assert_eq!(status_of(&app, "POST", rotate, None).await, 401);
+        assert_eq!(status_of(&app, "POST", rotate, Some("reader")).await, 403);
+        assert_eq!(status_of(&app, "POST", rotate, Some("ops")).await, 200);
+        // The profile guards the mounted routes only, not the fallback.
+        assert_eq!(status_of(&app, "GET", "/no-such-path", None).await, 404);
+    }
+
+    #[tokio::test]
+    async fn test_key_usage_endpoint_is_admin_only() {
+        use quantun_types::{Algorithm, KeyUsage, MlDsaVariant};
+
+        let keystore = Arc::new(quantun_crypto::KeyStore::new());
+        let handle = keystore
+            .create(Algorithm::MlDsa(MlDsaVariant::MlDsa44), [KeyUsage::Sign])
+            .unwrap();
+        let config = GatewayConfig::builder()
+            .auth(admin_and_reader_policy())
+            .keystore(keystore)
+            .build()
+            .unwrap();
+        let app = build_router(&config);
+        let usage = format!("/admin/keys/{}/usage", handle.key_id());
+
+        assert_eq!(status_of(&app, "GET", &usage, None).await, 401);
+        assert_eq!(status_of(&app, "GET", &usage, Some("reader")).await, 403);
+        assert_eq!(status_of(&app, "GET", &usage, Some("ops")).await, 200);
+    }
+
+    #[tokio::test]
+    async fn test_kem_endpoints_need_authentication() {
+        use quantun_types::{Algorithm, KeyUsage, MlKemVariant};
+
+        let keystore = Arc::new(quantun_crypto::KeyStore::new());
+        let key = keystore
+            .create(
+                Algorithm::MlKem(MlKemVariant::MlKem768),
+                [KeyUsage::KeyAgreement],
+            )
+            .unwrap();
+        let config = GatewayConfig::builder()
+            .auth(admin_and_reader_policy())
+            .kem(Arc::new(kem::KemService::new(keystore, key)))
+            .build()
+            .unwrap();
+        let app = build_router(&config);
+        let key = "/kem/public-key";
+
+        assert_eq!(status_of(&app, "GET", key, None).await, 401);
+        assert_eq!(status_of(&app, "GET", key, Some("reader")).await, 200);
+    }
+
+    #[tokio::test]
+    async fn test_jwks_is_served_without_authentication() {
+        let keystore = Arc::new(quantun_crypto::KeyStore::new());
+        let scheduler = token_signing_scheduler(keystore.clone());
+        let service = jwks::JwksService::new(keystore, scheduler, ["token-signing"]);
+        let config = GatewayConfig::builder()
+            .auth(admin_and_reader_policy())
+            .jwks(Arc::new(service))
+            .build()
+            .unwrap();
+        let app = build_router(&config);
+
+        for path in [jwks::JWKS_PATH, jwks::LEGACY_JWKS_PATH] {
+            assert_eq!(status_of(&app, "GET", path, None).await, 200);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_device_registration_is_admin_only() {
+        use quantun_crypto::mldsa::MlDsaKeyPair;
+        use quantun_crypto::signer::LocalSigner;
+        use quantun_types::MlDsaVariant;
+
+        let gateway_key = MlDsaKeyPair::generate(MlDsaVariant::MlDsa44).unwrap();
+        let signer = Arc::new(LocalSigner::new(gateway_key));
+        let keystore = Arc::new(quantun_crypto::KeyStore::new());
+        let registry = devices::DeviceRegistry::new(keystore, signer).unwrap();
+        let config = GatewayConfig::builder()
+            .auth(admin_and_reader_policy())
+            .devices(Arc::new(registry))
+            .build()
+            .unwrap();
+        let app = build_router(&config);
+        let (register, revoke) = ("/devices/register", "/devices/cam-1/revoke");
+
+        for uri in [register, revoke] {
+            assert_eq!(status_of(&app, "POST", uri, None).await, 401);
+            assert_eq!(status_of(&app, "POST", uri, Some("reader")).await, 403);
+        }
+        // Past the profile, the missing body and unknown device are refused.
+        assert_eq!(status_of(&app, "POST", register, Some("ops")).await, 415);
+        assert_eq!(status_of(&app, "POST", revoke, Some("ops")).await, 404);
+        // Device-facing routes only need authentication.
+        let device = "/devices/cam-1";
+        assert_eq!(status_of(&app, "GET", device, Some("reader")).await, 404);
+    }
+
+    #[tokio::test]
+    async fn test_inventory_needs_authentication() {
+        let config = GatewayConfig::builder()
+            .auth(admin_and_reader_policy())
+            .inventory(Arc::new(inventory::CryptoInventory::new()))
+            .build()
+            .unwrap();
+        let app = build_router(&config);
+        let path = inventory::INVENTORY_PATH;
+
+        assert_eq!(status_of(&app, "GET", path, None).await, 401);
+        assert_eq!(status_of(&app, "GET", path, Some("reader")).await, 200);
+    }
+
+    #[tokio::test]
+    async fn test_dry_run_endpoint_is_admin_only() {
+        let config = GatewayConfig::builder()
+            .auth(admin_and_reader_policy())
+            .build()
+            .unwrap();
+        let app = build_router(&config);
+        let uri = format!("{}?path=/api/v1/users", proxy::dry_run::DRY_RUN_PATH);
+
+        assert_eq!(status_of(&app, "GET", &uri, None).await, 401);
+        assert_eq!(status_of(&app, "GET", &uri, Some("reader")).await, 403);
+        assert_eq!(status_of(&app, "GET", &uri, Some("ops")).await, 200);
+    }
+
+    #[tokio::test]
+    async fn test_risk_rescan_is_admin_only() {
+        let proxy = Arc::new(GatewayConfig::default().proxy_service());
+        let config = GatewayConfig::builder()
+            .auth(admin_and_reader_policy())
+            .scanner(Arc::new(scanner::UpstreamScanner::new(proxy)))
+            .build()
+            .unwrap();
+        let app = build_router(&config);
+        let (risk, scan) = (scanner::RISK_PATH, scanner::SCAN_PATH);
+
+        assert_eq!(status_of(&app, "GET", risk, None).await, 401);
+        assert_eq!(status_of(&app, "GET", risk, Some("reader")).await, 200);
+        assert_eq!(status_of(&app, "POST", scan, None).await, 401);
+        assert_eq!(status_of(&app, "POST", scan, Some("reader")).await, 403);
+        assert_eq!(status_of(&app, "POST", scan, Some("ops")).await, 200);
+    }
+
+    #[tokio::test]
+    async fn test_client_certificate_authenticates() {
+        use crate::auth::{AuthConfig, AuthPolicy, ADMIN_SCOPE};
+        use crate::scanner::AnyCertificate;
+        use http_body_util::BodyExt;
+        use hyper_util::rt::TokioIo;
+        use rustls::pki_types::pem::PemObject;
+        use rustls::pki_types::{CertificateDer, PrivateKeyDer, ServerName};
+        use std::time::Duration;
+
+        let testdata = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("testdata");
+        let policy = AuthPolicy::new(AuthConfig {
+            require_auth: true,
+            cert_identity_map: [("billing.clients.internal".into(), vec![ADMIN_SCOPE.into()])]
+                .into(),
+            bypass_paths: Vec::new(),
+            ..AuthConfig::default()
+        })
+        .unwrap();
+        let config = GatewayConfig::builder()
+            .tls_policy(TlsPolicy::Hybrid)
+            .mtls(tls::MtlsConfig {
+                ca_path: testdata.join("mtls/ca-ed25519.pem"),
+                require_pqc_client_cert: false,
+                allowed_cn_patterns: Vec::new(),
+            })
+            .auth(Arc::new(policy))
+            .build()
+            .unwrap();
+        let server_config = tls::server_config(
+            config.tls_policy,
+            &testdata.join("scanner/ecdsa-p256.pem"),
+            &testdata.join("scanner/ecdsa-p256.key"),
+            config.mtls.as_deref(),
+        )
+        .unwrap();
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let (stop, stopped) = tokio::sync::oneshot::channel::<()>();
+        let server = tokio::spawn(server::serve(
+            listener,
+            build_router(&config),
+            server::Termination::Tls(server::TlsTermination {
+                config: Arc::new(server_config),
+                handshake_timeout: Duration::from_secs(10),
+            }),
+            Arc::new(GatewayMetrics::default()),
+            async move {
+                let _ = stopped.await;
+            },
+        ));
+
+        // The client's certificate maps to the admin scope, so it may use
+        // the admin-only maintenance toggle without an API key.
+        let provider = Arc::new(rustls::crypto::aws_lc_rs::default_provider());
+        let cert = CertificateDer::from_pem_file(testdata.join("mtls/client-ed25519.pem")).unwrap();
+        let key = PrivateKeyDer::from_pem_file(testdata.join("mtls/client-ed25519.key")).unwrap();
+        let client = rustls::ClientConfig::builder_with_provider(provider.clone())
+            .with_safe_default_protocol_versions()
+            .unwrap()
+            .dangerous()
+            .with_custom_certificate_verifier(Arc::new(AnyCertificate(provider)))
+            .with_client_auth_cert(vec![cert], key)
+            .unwrap();
+        let stream = tokio_rustls::TlsConnector::from(Arc::new(client))
+            .connect(
+                ServerName::try_from("localhost").unwrap(),
+                tokio::net::TcpStream::connect(addr).await.unwrap(),
+            )
+            .await
+            .unwrap();
+        let (mut sender, conn) = hyper::client::conn::http1::handshake(TokioIo::new(stream))
+            .await
+            .unwrap();
+        tokio::spawn(conn);
+        let req = Request::get(maintenance::ADMIN_PATH)
+            .body(Body::empty())
+            .unwrap();
+        let response = sender.send_request(req).await.unwrap();
+        let status = response.status();
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        assert_eq!(status, 200, "{body:?}");
+
+        drop(sender);
+        stop.send(()).unwrap();
+        tokio::time::timeout(Duration::from_secs(5), server)
+            .await
+            .unwrap()
+            .unwrap();
+    }
+
+    fn request_with_handshake() -> Request<Body> {
+        let mut req = Request::builder().uri("/health").body(Body::empty()).unwrap();
+        req.extensions_mut().insert(tls::HandshakeInfo {
+            cipher_suite: "TLS_ML-KEM-768_AES_256_GCM_SHA384".into(),
+            tls_version: "TLSv1.3".into(),
+            kem_algorithm: Some("ML-KEM-768".into()),
+            sig_algorithm: Some("ML-DSA-65".into()),
+            is_pqc: true,
+            handshake_duration_ms: 42,
+        });
+        req
+    }
+
+    #[tokio::test]
+    async fn test_server_timing_header() {
+        let config = GatewayConfig {
+            server_timing: true,
+            ..GatewayConfig::default()
+        };
+        let response = build_router(&config)
+            .oneshot(request_with_handshake())
+            .await
+            .unwrap();
+        let header = response.headers()["server-timing"].to_str().unwrap();
+        let (name, dur) = header.split_once(";dur=").unwrap();
+        assert_eq!(name, "handshake");
+        assert_eq!(dur.parse::<f64>().unwrap(), 42.0);
+
+        let response = build_router(&GatewayConfig::default())
+            .oneshot(request_with_handshake())
+            .await
+            .unwrap();
+        assert!(response.headers().get("server-timing").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_maintenance_mode() {
+        use crate::auth::{ApiKey, AuthConfig, AuthPolicy, ADMIN_SCOPE};
+
+        let policy = AuthPolicy::new(AuthConfig {
+            require_auth: true,
+            api_keys: vec![ApiKey {
+                id: "ops".into(),
+                name: "ops".into(),
+                scopes: vec![ADMIN_SCOPE.into()],
+                signing_key: None,
+                metadata: Default::default(),
+                secret: None,
+            }],
+            bypass_paths: Vec::new(),
+            ..AuthConfig::default()
+        })
+        .unwrap();
+        let config = GatewayConfig {
+            auth: Some(Arc::new(policy)),
+            maintenance: maintenance::MaintenanceConfig {
+                retry_after: std::time::Duration::from_secs(300),
+                ..Default::default()
+            },
+            ..GatewayConfig::default()
+        };
+        let app = build_router(&config);
+        let get = |uri: &str| {
+            Request::builder()
+                .uri(uri)
+                .header("x-api-key", "ops")
+                .body(Body::empty())
+                .unwrap()
+        };
+        let set = |enabled: bool| {
+            Request::post(maintenance::ADMIN_PATH)
+                .header("x-api-key", "ops")
+                .header("content-type", "application/json")
+                .body(Body::from(format!(r#"{{"enabled":{enabled}}}"#)))
+                .unwrap()
+        };
+
+        let response = app.clone().oneshot(set(true)).await.unwrap();
+        assert_eq!(response.status(), 200);
+        assert!(config.maintenance.enabled.load(Ordering::Relaxed));
+
+        let response = app.clone().oneshot(get("/api/items")).await.unwrap();
+        assert_eq!(response.status(), 503);
+        assert_eq!(response.headers()["retry-after"], "300");
+        let body = http_body_util::BodyExt::collect(response.into_body())
+            .await
+            .unwrap()
+            .to_bytes();
+        assert_eq!(body, "gateway under maintenance");
+
+        for path in ["/livez", "/health", maintenance::ADMIN_PATH] {
+            let response = app.clone().oneshot(get(path)).await.unwrap();
+            assert_eq!(response.status(), 200, "{path}");
+        }
+        let response = app.clone().oneshot(get("/gateway/stats")).await.unwrap();
+        assert_eq!(response.status(), 503);
+
+        app.clone().oneshot(set(false)).await.unwrap();
+        let response = app.oneshot(get("/gateway/stats")).await.unwrap();
+        assert_eq!(response.status(), 200);
+    }
+
+    #[tokio::test]
+    async fn test_maintenance_toggle_needs_auth_policy() {
+        let app = build_router(&GatewayConfig::default());
+        let response = app
+            .clone()
+            .oneshot(
+                Request::post(maintenance::ADMIN_PATH)
+                    .header("content-type", "application/json")
+                    .body(Body::from(r#"{"enabled":true}"#))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), 404);
+
+        // Other admin routes are mounted but refuse everyone.
+        let response = app
+            .oneshot(
+                Request::get(self_test::ADMIN_PATH)
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), 403);
+    }
+
+    #[tokio::test]
+    async fn test_discovery_document() {
+        let config = GatewayConfig::default();
+        let app = build_router(&config);
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .uri("/.well-known/qsgw-configuration")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), 200);
+        let body = http_body_util::BodyExt::collect(response.into_body())
+            .await
+            .unwrap()
+            .to_bytes();
+        let doc: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(doc["tls_policy"], "PqcPreferred");
+        assert_eq!(doc["min_tls_version"], "Tls13");
+        assert!(doc["algorithms"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .any(|a| a["name"] == "ML-KEM-768" && a["security_level"] == 3));
+    }
+
+    /// A sink recording every call as `kind name labels`.
+    #[derive(Default)]
+    struct RecordingSink(std::sync::Mutex<Vec<String>>);
+
+    impl RecordingSink {
+        fn record(&self, kind: &str, name: &str, labels: &[(&str, &str)]) {
+            let labels: Vec<_> = labels.iter().map(|(k, v)| format!("{k}={v}")).collect();
+            let call = format!("{kind} {name} {}", labels.join(","));
+            self.0.lock().unwrap().push(call.trim_end().to_string());
+        }
+    }
+
+    impl metrics::MetricsSink for RecordingSink {
+        fn counter(&self, name: &str, value: u64, labels: &[(&str, &str)]) {
+            assert_eq!(value, 1);
+            self.record("counter", name, labels);
+        }
+
+        fn gauge(&self, name: &str, _value: f64, labels: &[(&str, &str)]) {
+            self.record("gauge", name, labels);
+        }
+
+        fn histogram(&self, name: &str, value: f64, labels: &[(&str, &str)]) {
+            assert!(value >= 0.0);
+            self.record("histogram", name, labels);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_proxied_request_emits_to_metrics_sink() {
+        let upstream = proxy::testing::MockUpstream::start().await.unwrap();
+        let sink = Arc::new(RecordingSink::default());
+        let addr = upstream.addr();
+        let config = GatewayConfig::builder()
+            .route(builder::RouteBuilder::new("/api").upstream(
+                "api",
+                addr.ip().to_string(),
+                addr.port(),
+            ))
+            .metrics_sink(sink.clone())
+            .build()
+            .unwrap();
+
+        let response = build_router(&config)
+            .oneshot(Request::post("/api/items").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), 200);
+
+        assert_eq!(
+            *sink.0.lock().unwrap(),
+            [
+                "gauge qsgw_connections_active",
+                "counter qsgw_requests_total method=POST,route=/api,status=200",
+                "histogram qsgw_request_duration_seconds method=POST,route=/api",
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_forwarded_for_is_honoured_only_from_trusted_proxies() {
+        let config = GatewayConfig::builder()
+            .trusted_proxies(vec!["10.0.0.0/8".parse().unwrap()])
+            .build()
+            .unwrap();
+        let app = build_router(&config);
+        let client_ip = |peer: &str| {
+            let mut req = Request::get("/test/client-ip")
+                .header("x-forwarded-for", "1.2.3.4, 10.0.0.7")
+                .body(Body::empty())
+                .unwrap();
+            let peer = axum::extract::ConnectInfo::<SocketAddr>(peer.parse().unwrap());
+            req.extensions_mut().insert(peer);
+            let app = app.clone();
+            async move {
+                let response = app.oneshot(req).await.unwrap();
+                let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+                    .await
+                    .unwrap();
+                String::from_utf8(body.to_vec()).unwrap()
+            }
+        };
+
+        assert_eq!(client_ip("10.0.0.2:40000").await, "1.2.3.4");
+        assert_eq!(client_ip("9.9.9.9:40000").await, "9.9.9.9");
+    }
+
+    #[tokio::test]
+    async fn test_prometheus_sink_is_served_at_metrics_path() {
+        let get = |path| Request::get(path).body(Body::empty()).unwrap();
+        let app = build_router(&GatewayConfig::default());
+        let response = app.oneshot(get(metrics::METRICS_PATH)).await.unwrap();
+        assert_eq!(response.status(), 404);
+
+        let config = config_file::ConfigFile::parse("metrics_sink: prometheus\n")
+            .unwrap()
+            .to_gateway_config()
+            .unwrap();
+        let app = build_router(&config);
+        let purge = Request::builder()
+            .method(http::Method::from_bytes(b"PURGE").unwrap())
+            .uri("/livez")
+            .body(Body::empty())
+            .unwrap();
+        let response = app.clone().oneshot(purge).await.unwrap();
+        assert_eq!(response.status(), 405);
+        let response = app.oneshot(get(metrics::METRICS_PATH)).await.unwrap();
+        assert_eq!(response.status(), 200);
+        assert_eq!(
+            response.headers()[header::CONTENT_TYPE],
+            metrics::EXPOSITION_CONTENT_TYPE
+        );
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let text = String::from_utf8(body.to_vec()).unwrap();
+        let purged = r#"qsgw_requests_total{method="other",route="-",status="405"} 1"#;
+        assert!(text.lines().any(|l| l == purged), "{text}");
+    }
 }
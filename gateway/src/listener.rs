@@ -0,0 +1,297 @@
+//! Listening sockets for [`server::serve_acceptors`](crate::server::serve_acceptors)
+//! and the handoff restart.
+//!
+//! With [`ListenerConfig::reuse_port`] each acceptor task gets its own
+//! socket bound with `SO_REUSEPORT`, and the kernel spreads new connections
+//! across them. Another process can then bind the same address, which is
+//! what a restart without downtime relies on:
+//!
+//! 1. The new process binds the address alongside the old one.
+//! 2. It reads the pid of the old process from the
+//!    [`pid_file`](ListenerConfig::pid_file), sends it `SIGUSR2` and
+//!    records its own pid.
+//! 3. The old process closes its sockets, so new connections only reach
+//!    the new one, drains its open connections and exits.
+//!
+//! A gateway holds a shared `flock` on its pid file for as long as it
+//! runs. A pid file nobody holds a lock on was left by a process that has
+//! died, and its pid may since have been reused by an unrelated process,
+//! so it is never signalled.
+
+use serde::{Deserialize, Serialize};
+use std::fs::{File, OpenOptions};
+use std::io::{self, Write};
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tokio::net::{TcpListener, TcpSocket};
+use tracing::{info, warn};
+
+/// Pending connections queued per socket.
+const BACKLOG: u32 = 1024;
+
+/// How the gateway listens on [`GatewayConfig::listen_addr`](crate::GatewayConfig::listen_addr).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ListenerConfig {
+    /// Bind one `SO_REUSEPORT` socket per acceptor, so that a new process
+    /// can take over the address. Unix only.
+    pub reuse_port: bool,
+    /// Tasks accepting connections. Without `reuse_port` they share one
+    /// socket.
+    pub acceptors: usize,
+    /// File recording the pid of the process serving the address. With
+    /// `reuse_port`, a starting process signals the pid found there to
+    /// hand off.
+    pub pid_file: Option<PathBuf>,
+}
+
+impl Default for ListenerConfig {
+    fn default() -> Self {
+        Self {
+            reuse_port: false,
+            acceptors: 1,
+            pid_file: None,
+        }
+    }
+}
+
+/// One listener per acceptor on `addr`. Must be called within a Tokio
+/// runtime.
+pub fn bind(addr: SocketAddr, config: &ListenerConfig) -> io::Result<Vec<Arc<TcpListener>>> {
+    let acceptors = config.acceptors.max(1);
+    if !config.reuse_port {
+        let listener = Arc::new(bind_socket(addr, false)?);
+        return Ok(vec![listener; acceptors]);
+    }
+    // Bind the first socket alone so that a port of 0 is resolved once and
+    // shared by the rest.
+    let first = bind_socket(addr, true)?;
+    let addr = first.local_addr()?;
+    let mut listeners = vec![Arc::new(first)];
+    for _ in 1..acceptors {
+        listeners.push(Arc::new(bind_socket(addr, true)?));
+    }
+    info!(%addr, acceptors, "bound SO_REUSEPORT listeners");
+    Ok(listeners)
+}
+
+fn bind_socket(addr: SocketAddr, reuse_port: bool) -> io::Result<TcpListener> {
+    let socket = match addr {
+        SocketAddr::V4(_) => TcpSocket::new_v4()?,
+        SocketAddr::V6(_) => TcpSocket::new_v6()?,
+    };
+    #[cfg(unix)]
+    {
+        socket.set_reuseaddr(true)?;
+        socket.set_reuseport(reuse_port)?;
+    }
+    #[cfg(not(unix))]
+    if reuse_port {
+        return Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "SO_REUSEPORT is only supported on Unix",
+        ));
+    }
+    socket.bind(addr)?;
+    socket.listen(BACKLOG)
+}
+
+/// The pid recorded in `path`, if the file exists.
+pub fn read_pid(path: &Path) -> io::Result<Option<u32>> {
+    match std::fs::read_to_string(path) {
+        Ok(text) => text.trim().parse().map(Some).map_err(|e| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("{}: {e}", path.display()),
+            )
+        }),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+/// A pid file recording this process. It stays locked until dropped, and
+/// is then removed unless a successor has recorded itself.
+#[derive(Debug)]
+pub struct PidFile {
+    path: PathBuf,
+    _file: File,
+}
+
+impl Drop for PidFile {
+    fn drop(&mut self) {
+        remove_pid(&self.path);
+    }
+}
+
+/// Record this process in `path`, holding the file's lock until the
+/// returned guard is dropped.
+pub fn write_pid(path: &Path) -> io::Result<PidFile> {
+    let mut file = OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(path)?;
+    #[cfg(unix)]
+    flock(&file, libc::LOCK_SH)?;
+    file.set_len(0)?;
+    file.write_all(format!("{}\n", std::process::id()).as_bytes())?;
+    Ok(PidFile {
+        path: path.to_path_buf(),
+        _file: file,
+    })
+}
+
+/// Apply the `flock` `operation` to `file`, returning false if it would
+/// block on another process's lock.
+#[cfg(unix)]
+fn flock(file: &File, operation: libc::c_int) -> io::Result<bool> {
+    use std::os::fd::AsRawFd;
+
+    // SAFETY: flock has no memory-safety preconditions.
+    if unsafe { libc::flock(file.as_raw_fd(), operation) } == 0 {
+        return Ok(true);
+    }
+    match io::Error::last_os_error() {
+        e if e.kind() == io::ErrorKind::WouldBlock => Ok(false),
+        e => Err(e),
+    }
+}
+
+/// Remove `path` if it still records this process, and not a successor.
+pub fn remove_pid(path: &Path) {
+    if matches!(read_pid(path), Ok(Some(pid)) if pid == std::process::id()) {
+        if let Err(e) = std::fs::remove_file(path) {
+            warn!(path = %path.display(), error = %e, "could not remove pid file");
+        }
+    }
+}
+
+/// Record this process in `path` and ask the process recorded there
+/// before to hand its address over. Call once listening. Returns the pid
+/// file and the pid signalled, if any. A stale pid file, one no running
+/// gateway holds locked, is overwritten without signalling anyone.
+///
+/// The file is written first so that the previous process, on exit, finds
+/// it no longer records itself and leaves it in place.
+#[cfg(unix)]
+pub fn take_over(path: &Path) -> io::Result<(PidFile, Option<u32>)> {
+    let previous = if previous_is_running(path)? {
+        match read_pid(path) {
+            Ok(pid) => pid.filter(|&pid| pid != std::process::id()),
+            Err(e) => {
+                warn!(path = %path.display(), error = %e, "ignoring unreadable pid file");
+                None
+            }
+        }
+    } else {
+        if let Ok(Some(pid)) = read_pid(path) {
+            info!(path = %path.display(), pid, "pid file is stale, not signalling");
+        }
+        None
+    };
+    let pid_file = write_pid(path)?;
+    info!(path = %path.display(), pid = std::process::id(), "wrote pid file");
+    let Some(pid) = previous else {
+        return Ok((pid_file, None));
+    };
+    // SAFETY: kill has no memory-safety preconditions.
+    if unsafe { libc::kill(pid as libc::pid_t, libc::SIGUSR2) } != 0 {
+        let e = io::Error::last_os_error();
+        info!(pid, error = %e, "previous process not running, nothing to hand off");
+        return Ok((pid_file, None));
+    }
+    info!(pid, "sent SIGUSR2 to the previous process to hand off");
+    Ok((pid_file, Some(pid)))
+}
+
+/// Whether a running gateway holds the lock on the pid file at `path`.
+#[cfg(unix)]
+fn previous_is_running(path: &Path) -> io::Result<bool> {
+    let file = match File::open(path) {
+        Ok(file) => file,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(false),
+        Err(e) => return Err(e),
+    };
+    // The probe's lock is released when `file` is closed.
+    Ok(!flock(&file, libc::LOCK_EX | libc::LOCK_NB)?)
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::os::unix::process::ExitStatusExt;
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    fn temp_path(name: &str) -> PathBuf {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_nanos();
+        std::env::temp_dir().join(format!("qsgw-{name}-{nanos}"))
+    }
+
+    #[tokio::test]
+    async fn test_reuse_port_listeners_share_an_address() {
+        let config = ListenerConfig {
+            reuse_port: true,
+            acceptors: 3,
+            pid_file: None,
+        };
+        let listeners = bind("127.0.0.1:0".parse().unwrap(), &config).unwrap();
+        assert_eq!(listeners.len(), 3);
+        let addr = listeners[0].local_addr().unwrap();
+        assert!(listeners.iter().all(|l| l.local_addr().unwrap() == addr));
+
+        // A successor can bind alongside; a plain bind cannot.
+        let successor = bind(addr, &config).unwrap();
+        assert_eq!(successor[0].local_addr().unwrap(), addr);
+        assert!(bind(addr, &ListenerConfig::default()).is_err());
+    }
+
+    #[test]
+    fn test_take_over_signals_the_recorded_process() {
+        let path = temp_path("pid");
+        let mut previous = std::process::Command::new("sleep")
+            .arg("30")
+            .spawn()
+            .unwrap();
+        std::fs::write(&path, format!("{}\n", previous.id())).unwrap();
+        // Stand in for the running gateway's lock.
+        let held = File::open(&path).unwrap();
+        assert!(flock(&held, libc::LOCK_SH).unwrap());
+
+        let (pid_file, signalled) = take_over(&path).unwrap();
+        assert_eq!(signalled, Some(previous.id()));
+        assert_eq!(previous.wait().unwrap().signal(), Some(libc::SIGUSR2));
+        assert_eq!(read_pid(&path).unwrap(), Some(std::process::id()));
+        drop(held);
+
+        // Our own pid is never signalled, and is removed on exit.
+        let (successor, signalled) = take_over(&path).unwrap();
+        assert_eq!(signalled, None);
+        drop((pid_file, successor));
+        assert_eq!(read_pid(&path).unwrap(), None);
+    }
+
+    #[test]
+    fn test_take_over_leaves_a_stale_pid_alone() {
+        let path = temp_path("stale-pid");
+        let mut unrelated = std::process::Command::new("sleep")
+            .arg("30")
+            .spawn()
+            .unwrap();
+        // A pid file no gateway holds locked, as after a crash.
+        std::fs::write(&path, format!("{}\n", unrelated.id())).unwrap();
+
+        let (pid_file, signalled) = take_over(&path).unwrap();
+        assert_eq!(signalled, None);
+        assert_eq!(read_pid(&path).unwrap(), Some(std::process::id()));
+        assert!(unrelated.try_wait().unwrap().is_none());
+        unrelated.kill().unwrap();
+        unrelated.wait().unwrap();
+        drop(pid_file);
+        assert!(!path.exists());
+    }
+}
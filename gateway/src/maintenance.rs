@@ -0,0 +1,129 @@
+//! Maintenance mode.
+//!
+//! While enabled, every request outside the exempt paths is answered with
+//! `503 Service Unavailable` and a `Retry-After` header, so the gateway can
+//! be drained for a deploy while health checks and the admin API stay
+//! live. The switch is flipped through `POST /admin/maintenance`, which is
+//! only mounted when an auth policy is configured, or directly through
+//! [`MaintenanceConfig::enabled`].
+
+use axum::body::Body;
+use axum::extract::State;
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, MethodRouter};
+use axum::Json;
+use http::{header, HeaderValue, Request, StatusCode};
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::info;
+
+use crate::auth::PathMatcher;
+
+/// Path of the admin endpoint that reports and toggles maintenance mode.
+pub const ADMIN_PATH: &str = "/admin/maintenance";
+
+#[derive(Debug, Clone)]
+pub struct MaintenanceConfig {
+    /// The switch. Shared, so the embedder can flip it without a request.
+    pub enabled: Arc<AtomicBool>,
+    /// Sent as `Retry-After`, in whole seconds.
+    pub retry_after: Duration,
+    /// Body of the `503` response.
+    pub message: String,
+    /// Path prefixes served normally during maintenance.
+    pub exempt_paths: Vec<String>,
+}
+
+impl Default for MaintenanceConfig {
+    fn default() -> Self {
+        Self {
+            enabled: Arc::new(AtomicBool::new(false)),
+            retry_after: Duration::from_secs(120),
+            message: "gateway under maintenance".into(),
+            exempt_paths: vec!["/health".into(), "/livez".into(), "/admin/".into()],
+        }
+    }
+}
+
+/// [`MaintenanceConfig`] with its exempt-path matcher built once, shared
+/// by the middleware and the admin endpoint.
+#[derive(Debug)]
+pub struct MaintenanceState {
+    enabled: Arc<AtomicBool>,
+    retry_after: HeaderValue,
+    message: String,
+    exempt: PathMatcher,
+}
+
+impl MaintenanceState {
+    pub fn new(config: &MaintenanceConfig) -> Self {
+        Self {
+            enabled: config.enabled.clone(),
+            retry_after: HeaderValue::from(config.retry_after.as_secs()),
+            message: config.message.clone(),
+            exempt: PathMatcher::new(&config.exempt_paths),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.load(Ordering::Relaxed)
+    }
+
+    pub fn set_enabled(&self, enabled: bool) {
+        if self.enabled.swap(enabled, Ordering::Relaxed) != enabled {
+            info!(enabled, "maintenance mode changed");
+        }
+    }
+}
+
+/// Answer non-exempt requests with `503` while maintenance mode is on.
+pub async fn maintenance_middleware(
+    State(state): State<Arc<MaintenanceState>>,
+    req: Request<Body>,
+    next: Next,
+) -> Response {
+    if !state.is_enabled() || state.exempt.matches(req.uri().path()) {
+        return next.run(req).await;
+    }
+    (
+        StatusCode::SERVICE_UNAVAILABLE,
+        [(header::RETRY_AFTER, state.retry_after.clone())],
+        state.message.clone(),
+    )
+        .into_response()
+}
+
+/// Body of `GET` and `POST` on [`ADMIN_PATH`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MaintenanceStatus {
+    pub enabled: bool,
+}
+
+/// Handlers for [`ADMIN_PATH`]:
+///
+/// - `GET`: the current [`MaintenanceStatus`].
+/// - `POST` with a [`MaintenanceStatus`] body: switch maintenance mode on
+///   or off and return the new status.
+pub fn admin_routes<S>(state: Arc<MaintenanceState>) -> MethodRouter<S>
+where
+    S: Clone + Send + Sync + 'static,
+{
+    get(status).post(set_status).with_state(state)
+}
+
+async fn status(State(state): State<Arc<MaintenanceState>>) -> Json<MaintenanceStatus> {
+    Json(MaintenanceStatus {
+        enabled: state.is_enabled(),
+    })
+}
+
+async fn set_status(
+    State(state): State<Arc<MaintenanceState>>,
+    Json(body): Json<MaintenanceStatus>,
+) -> Json<MaintenanceStatus> {
+    state.set_enabled(body.enabled);
+    status(State(state)).await
+}
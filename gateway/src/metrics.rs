@@ -0,0 +1,657 @@
+use http::Method;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant};
+
+/// Runtime counters shared between the router, middleware, and stats endpoint.
+#[derive(Debug, Default)]
+pub struct GatewayMetrics {
+    /// Connections currently being served.
+    pub active_connections: AtomicUsize,
+    /// Connections accepted since startup.
+    pub connections_accepted: AtomicU64,
+    /// Connections closed since startup.
+    pub connections_closed: AtomicU64,
+    /// Open connections whose TLS handshake negotiated a PQC or hybrid
+    /// key exchange.
+    pub pqc_sessions: AtomicUsize,
+    /// Open connections whose TLS handshake negotiated a classical key
+    /// exchange.
+    pub classical_sessions: AtomicUsize,
+    /// TLS handshakes started by the gateway's own termination.
+    pub handshakes_attempted: AtomicU64,
+    /// TLS handshakes completed.
+    pub handshakes_succeeded: AtomicU64,
+    /// Requests rejected by the load-shedding layer.
+    pub shed_requests: AtomicU64,
+    /// Handler panics caught by the panic-recovery layer.
+    pub panic_count: AtomicU64,
+    /// TLS connections closed for not completing the handshake in time.
+    pub handshake_timeouts: AtomicU64,
+    /// TLS handshakes that failed on a malformed or unacceptable message.
+    pub handshake_protocol_failures: AtomicU64,
+    /// TLS handshakes that failed on a certificate.
+    pub handshake_certificate_failures: AtomicU64,
+    /// TLS handshakes cut short by the connection closing or erroring.
+    pub handshake_io_failures: AtomicU64,
+    /// Bodies passed on untransformed for exceeding the route's size cap.
+    pub transform_bypasses: AtomicU64,
+    /// Body transformations that failed.
+    pub transform_failures: AtomicU64,
+    /// Signatures requested from a remote signer.
+    pub remote_signs: AtomicU64,
+    /// Remote signatures that failed after all retries.
+    pub remote_sign_failures: AtomicU64,
+    /// Cumulative remote signing latency, retries included, in microseconds.
+    pub remote_sign_latency_us: AtomicU64,
+    /// Key rotations performed by the rotation scheduler.
+    pub key_rotations: AtomicU64,
+    /// Predecessor keys revoked at the end of their overlap window.
+    pub keys_retired: AtomicU64,
+    /// Percentage of upstreams PQC-ready in the latest risk scan.
+    pub pqc_ready_percent: AtomicU64,
+    /// Upstreams whose TLS posture regressed between risk scans.
+    pub risk_regressions: AtomicU64,
+    /// Request outcomes by upstream name.
+    upstreams: Mutex<BTreeMap<String, UpstreamStats>>,
+    /// Request outcomes by tenant label.
+    tenants: Mutex<BTreeMap<String, TenantStats>>,
+    /// Where connection events are also emitted.
+    sink: SharedSink,
+}
+
+/// A [`MetricsSink`] held by [`GatewayMetrics`], no-op by default.
+#[derive(Clone)]
+struct SharedSink(Arc<dyn MetricsSink>);
+
+impl Default for SharedSink {
+    fn default() -> Self {
+        Self(Arc::new(NoopMetricsSink))
+    }
+}
+
+impl std::fmt::Debug for SharedSink {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("MetricsSink")
+    }
+}
+
+/// Why a TLS handshake failed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HandshakeFailure {
+    /// Not completed within the handshake timeout.
+    Timeout,
+    /// A malformed, unexpected or unacceptable message, or an alert.
+    Protocol,
+    /// The certificate was rejected or missing.
+    Certificate,
+    /// The connection closed or errored mid-handshake.
+    Io,
+}
+
+impl HandshakeFailure {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            HandshakeFailure::Timeout => "timeout",
+            HandshakeFailure::Protocol => "protocol",
+            HandshakeFailure::Certificate => "certificate",
+            HandshakeFailure::Io => "io",
+        }
+    }
+}
+
+/// An accepted connection, counted in [`GatewayMetrics`] until dropped;
+/// see [`GatewayMetrics::connection_opened`].
+#[derive(Debug)]
+pub struct ConnectionGuard {
+    metrics: Arc<GatewayMetrics>,
+    opened: Instant,
+    /// Whether a completed handshake negotiated PQC, if there was one.
+    pqc: Option<bool>,
+}
+
+impl ConnectionGuard {
+    /// Record the start of the connection's TLS handshake.
+    pub fn handshake_started(&self) {
+        self.metrics
+            .handshakes_attempted
+            .fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Record a handshake completed after `elapsed`, counting the
+    /// connection as an open PQC or classical session until it closes.
+    pub fn handshake_succeeded(&mut self, pqc: bool, elapsed: Duration) {
+        let metrics = &self.metrics;
+        metrics.handshakes_succeeded.fetch_add(1, Ordering::Relaxed);
+        let sessions = metrics.sessions(pqc);
+        let open = sessions.fetch_add(1, Ordering::Relaxed) + 1;
+        self.pqc = Some(pqc);
+
+        let sink = &metrics.sink.0;
+        sink.counter(TLS_HANDSHAKES_TOTAL, 1, &[("result", "success")]);
+        sink.histogram(
+            TLS_HANDSHAKE_DURATION_SECONDS,
+            elapsed.as_secs_f64(),
+            &[("family", family(pqc))],
+        );
+        sink.gauge(TLS_SESSIONS, open as f64, &[("family", family(pqc))]);
+    }
+
+    /// Record a failed handshake.
+    pub fn handshake_failed(&self, failure: HandshakeFailure) {
+        let metrics = &self.metrics;
+        match failure {
+            HandshakeFailure::Timeout => &metrics.handshake_timeouts,
+            HandshakeFailure::Protocol => &metrics.handshake_protocol_failures,
+            HandshakeFailure::Certificate => &metrics.handshake_certificate_failures,
+            HandshakeFailure::Io => &metrics.handshake_io_failures,
+        }
+        .fetch_add(1, Ordering::Relaxed);
+        metrics
+            .sink
+            .0
+            .counter(TLS_HANDSHAKES_TOTAL, 1, &[("result", failure.as_str())]);
+    }
+}
+
+impl Drop for ConnectionGuard {
+    fn drop(&mut self) {
+        let metrics = &self.metrics;
+        let open = metrics.active_connections.fetch_sub(1, Ordering::Relaxed) - 1;
+        metrics.connections_closed.fetch_add(1, Ordering::Relaxed);
+
+        let sink = &metrics.sink.0;
+        if let Some(pqc) = self.pqc {
+            let sessions = metrics.sessions(pqc).fetch_sub(1, Ordering::Relaxed) - 1;
+            sink.gauge(TLS_SESSIONS, sessions as f64, &[("family", family(pqc))]);
+        }
+        sink.counter(CONNECTIONS_CLOSED_TOTAL, 1, &[]);
+        sink.gauge(CONNECTIONS_ACTIVE, open as f64, &[]);
+        sink.histogram(
+            CONNECTION_DURATION_SECONDS,
+            self.opened.elapsed().as_secs_f64(),
+            &[],
+        );
+    }
+}
+
+/// The `family` label of a session.
+fn family(pqc: bool) -> &'static str {
+    if pqc {
+        "pqc"
+    } else {
+        "classical"
+    }
+}
+
+/// How a request to an upstream ended.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UpstreamOutcome {
+    /// The upstream answered with this status code.
+    Response(u16),
+    Timeout,
+    ConnectionFailed,
+}
+
+/// Request counters for one upstream, as listed under `upstreams` in
+/// `/gateway/stats`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct UpstreamStats {
+    pub upstream: String,
+    pub requests: u64,
+    /// Responses with a status below 500.
+    pub successes: u64,
+    /// Responses with a 5xx status.
+    pub server_errors: u64,
+    pub timeouts: u64,
+    pub connection_failures: u64,
+}
+
+/// Request counters for one tenant, as listed under `tenants` in
+/// `/gateway/stats`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct TenantStats {
+    /// The tenant, or [`OTHER_TENANT`] for tenants beyond the label limit.
+    pub tenant: String,
+    pub requests: u64,
+    /// Responses with a 4xx status.
+    pub client_errors: u64,
+    /// Responses with a 5xx status.
+    pub server_errors: u64,
+}
+
+/// Label shared by tenants seen after the label limit is reached.
+pub const OTHER_TENANT: &str = "other";
+
+impl GatewayMetrics {
+    /// Metrics that also emit connection events to `sink`.
+    pub fn with_sink(sink: Arc<dyn MetricsSink>) -> Self {
+        Self {
+            sink: SharedSink(sink),
+            ..Self::default()
+        }
+    }
+
+    /// Count a newly accepted connection as open until the returned guard
+    /// drops.
+    pub fn connection_opened(self: &Arc<Self>) -> ConnectionGuard {
+        let open = self.active_connections.fetch_add(1, Ordering::Relaxed) + 1;
+        self.connections_accepted.fetch_add(1, Ordering::Relaxed);
+        self.sink.0.counter(CONNECTIONS_TOTAL, 1, &[]);
+        self.sink.0.gauge(CONNECTIONS_ACTIVE, open as f64, &[]);
+        ConnectionGuard {
+            metrics: self.clone(),
+            opened: Instant::now(),
+            pqc: None,
+        }
+    }
+
+    /// The open session gauge for PQC or classical sessions.
+    fn sessions(&self, pqc: bool) -> &AtomicUsize {
+        if pqc {
+            &self.pqc_sessions
+        } else {
+            &self.classical_sessions
+        }
+    }
+
+    /// Record one remote sign call that took `elapsed` end to end.
+    pub fn record_remote_sign(&self, elapsed: Duration, ok: bool) {
+        self.remote_signs.fetch_add(1, Ordering::Relaxed);
+        self.remote_sign_latency_us
+            .fetch_add(elapsed.as_micros() as u64, Ordering::Relaxed);
+        if !ok {
+            self.remote_sign_failures.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    /// Record one request forwarded to `upstream`.
+    pub fn record_upstream(&self, upstream: &str, outcome: UpstreamOutcome) {
+        let mut upstreams = self.upstreams.lock().unwrap();
+        let stats = upstreams
+            .entry(upstream.to_string())
+            .or_insert_with(|| UpstreamStats {
+                upstream: upstream.to_string(),
+                ..UpstreamStats::default()
+            });
+        stats.requests += 1;
+        match outcome {
+            UpstreamOutcome::Response(status) if status >= 500 => stats.server_errors += 1,
+            UpstreamOutcome::Response(_) => stats.successes += 1,
+            UpstreamOutcome::Timeout => stats.timeouts += 1,
+            UpstreamOutcome::ConnectionFailed => stats.connection_failures += 1,
+        }
+    }
+
+    /// Counters for every upstream that has seen a request, by name.
+    pub fn upstream_stats(&self) -> Vec<UpstreamStats> {
+        self.upstreams.lock().unwrap().values().cloned().collect()
+    }
+
+    /// Record one response with `status` for `tenant`. Once `max_labels`
+    /// tenants have their own counters, new tenants are counted under
+    /// [`OTHER_TENANT`].
+    pub fn record_tenant(&self, tenant: &str, status: u16, max_labels: usize) {
+        let mut tenants = self.tenants.lock().unwrap();
+        let labelled = tenants.len() - usize::from(tenants.contains_key(OTHER_TENANT));
+        let label = if tenants.contains_key(tenant) || labelled < max_labels {
+            tenant
+        } else {
+            OTHER_TENANT
+        };
+        let stats = tenants
+            .entry(label.to_string())
+            .or_insert_with(|| TenantStats {
+                tenant: label.to_string(),
+                ..TenantStats::default()
+            });
+        stats.requests += 1;
+        match status {
+            400..=499 => stats.client_errors += 1,
+            500.. => stats.server_errors += 1,
+            _ => {}
+        }
+    }
+
+    /// Counters for every tenant label that has seen a request.
+    pub fn tenant_stats(&self) -> Vec<TenantStats> {
+        self.tenants.lock().unwrap().values().cloned().collect()
+    }
+}
+
+/// Requests served, by `method`, `route` and `status`.
+pub const REQUESTS_TOTAL: &str = "qsgw_requests_total";
+/// Time to the response headers, in seconds, by `method` and `route`.
+pub const REQUEST_DURATION_SECONDS: &str = "qsgw_request_duration_seconds";
+/// Connections open.
+pub const CONNECTIONS_ACTIVE: &str = "qsgw_connections_active";
+/// Connections accepted.
+pub const CONNECTIONS_TOTAL: &str = "qsgw_connections_total";
+/// Connections closed.
+pub const CONNECTIONS_CLOSED_TOTAL: &str = "qsgw_connections_closed_total";
+/// Connection lifetimes, in seconds.
+pub const CONNECTION_DURATION_SECONDS: &str = "qsgw_connection_duration_seconds";
+/// TLS handshakes by `result`: `success` or a [`HandshakeFailure`].
+pub const TLS_HANDSHAKES_TOTAL: &str = "qsgw_tls_handshakes_total";
+/// Completed TLS handshake durations, in seconds, by `family`.
+pub const TLS_HANDSHAKE_DURATION_SECONDS: &str = "qsgw_tls_handshake_duration_seconds";
+/// Open TLS sessions by negotiated `family`: `pqc` or `classical`.
+pub const TLS_SESSIONS: &str = "qsgw_tls_sessions";
+
+/// Where the router serves [`MetricsSink::scrape`].
+pub const METRICS_PATH: &str = "/metrics";
+
+/// `Content-Type` of the text exposition format served at [`METRICS_PATH`].
+pub const EXPOSITION_CONTENT_TYPE: &str = "text/plain; version=0.0.4; charset=utf-8";
+
+/// Label shared by request methods outside the standard set, which
+/// clients can otherwise mint without bound.
+pub const OTHER_METHOD: &str = "other";
+
+/// The `method` label for `method`: its name if it is one of the standard
+/// methods, or [`OTHER_METHOD`].
+pub fn method_label(method: &Method) -> &'static str {
+    match *method {
+        Method::GET => "GET",
+        Method::HEAD => "HEAD",
+        Method::POST => "POST",
+        Method::PUT => "PUT",
+        Method::DELETE => "DELETE",
+        Method::CONNECT => "CONNECT",
+        Method::OPTIONS => "OPTIONS",
+        Method::TRACE => "TRACE",
+        Method::PATCH => "PATCH",
+        _ => OTHER_METHOD,
+    }
+}
+
+/// Destination for the metrics the request middleware emits, set with
+/// [`GatewayConfigBuilder::metrics_sink`](crate::builder::GatewayConfigBuilder::metrics_sink).
+/// Implement it to ship metrics to StatsD or another backend.
+pub trait MetricsSink: Send + Sync {
+    /// Add `value` to the counter `name`.
+    fn counter(&self, name: &str, value: u64, labels: &[(&str, &str)]);
+
+    /// Set the gauge `name` to `value`.
+    fn gauge(&self, name: &str, value: f64, labels: &[(&str, &str)]);
+
+    /// Record one observation of `value` in the histogram `name`.
+    fn histogram(&self, name: &str, value: f64, labels: &[(&str, &str)]);
+
+    /// Everything recorded, in the Prometheus text exposition format, for
+    /// sinks that are scraped rather than pushing elsewhere. The router
+    /// serves it at [`METRICS_PATH`] when this is `Some`.
+    fn scrape(&self) -> Option<String> {
+        None
+    }
+}
+
+/// Which sink `qsgw` records metrics to, set by `metrics_sink` in the
+/// configuration file.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MetricsSinkKind {
+    /// [`NoopMetricsSink`].
+    #[default]
+    None,
+    /// [`PrometheusSink`], scraped from [`METRICS_PATH`].
+    Prometheus,
+}
+
+impl MetricsSinkKind {
+    /// A new, empty sink of this kind.
+    pub fn build(self) -> Arc<dyn MetricsSink> {
+        match self {
+            MetricsSinkKind::None => Arc::new(NoopMetricsSink),
+            MetricsSinkKind::Prometheus => Arc::new(PrometheusSink::new()),
+        }
+    }
+}
+
+/// A sink that drops everything; the default.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NoopMetricsSink;
+
+impl MetricsSink for NoopMetricsSink {
+    fn counter(&self, _name: &str, _value: u64, _labels: &[(&str, &str)]) {}
+
+    fn gauge(&self, _name: &str, _value: f64, _labels: &[(&str, &str)]) {}
+
+    fn histogram(&self, _name: &str, _value: f64, _labels: &[(&str, &str)]) {}
+}
+
+/// Upper bounds of [`PrometheusSink`] histogram buckets, in seconds.
+pub const HISTOGRAM_BUCKETS: [f64; 11] = [
+    0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0,
+];
+
+/// A sink that keeps metrics in memory for Prometheus to scrape from
+/// [`METRICS_PATH`].
+///
+/// A name keeps the kind it was first recorded as; samples of another
+/// kind under the same name are dropped. Recording into an existing series
+/// takes only read locks, so concurrent requests do not serialize on the
+/// sink.
+#[derive(Debug, Default)]
+pub struct PrometheusSink {
+    families: RwLock<BTreeMap<String, Family>>,
+}
+
+/// The series of one metric, keyed by their rendered labels.
+#[derive(Debug)]
+enum Family {
+    Counter(RwLock<BTreeMap<String, AtomicU64>>),
+    /// Values as [`f64::to_bits`].
+    Gauge(RwLock<BTreeMap<String, AtomicU64>>),
+    Histogram(RwLock<BTreeMap<String, Histogram>>),
+}
+
+#[derive(Debug, Default)]
+struct Histogram {
+    /// Observations in each bucket of [`HISTOGRAM_BUCKETS`], not
+    /// cumulative; the last counts those above every bound.
+    buckets: [AtomicU64; HISTOGRAM_BUCKETS.len() + 1],
+    /// The sum as [`f64::to_bits`].
+    sum: AtomicU64,
+    count: AtomicU64,
+}
+
+impl PrometheusSink {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Every metric in the Prometheus text exposition format.
+    pub fn render(&self) -> String {
+        let families = self.families.read().unwrap();
+        let mut out = String::new();
+        for (name, family) in families.iter() {
+            match family {
+                Family::Counter(series) => {
+                    let _ = writeln!(out, "# TYPE {name} counter");
+                    for (labels, value) in series.read().unwrap().iter() {
+                        let value = value.load(Ordering::Relaxed);
+                        let _ = writeln!(out, "{name}{} {value}", braced(labels));
+                    }
+                }
+                Family::Gauge(series) => {
+                    let _ = writeln!(out, "# TYPE {name} gauge");
+                    for (labels, value) in series.read().unwrap().iter() {
+                        let value = f64::from_bits(value.load(Ordering::Relaxed));
+                        let _ = writeln!(out, "{name}{} {value}", braced(labels));
+                    }
+                }
+                Family::Histogram(series) => {
+                    let _ = writeln!(out, "# TYPE {name} histogram");
+                    for (labels, histogram) in series.read().unwrap().iter() {
+                        let bounds = HISTOGRAM_BUCKETS.iter().map(f64::to_string);
+                        let mut cumulative = 0;
+                        for (bound, count) in bounds.chain(["+Inf".into()]).zip(&histogram.buckets)
+                        {
+                            cumulative += count.load(Ordering::Relaxed);
+                            let sep = if labels.is_empty() { "" } else { "," };
+                            let _ = writeln!(
+                                out,
+                                "{name}_bucket{{{labels}{sep}le=\"{bound}\"}} {cumulative}"
+                            );
+                        }
+                        let sum = f64::from_bits(histogram.sum.load(Ordering::Relaxed));
+                        let count = histogram.count.load(Ordering::Relaxed);
+                        let labels = braced(labels);
+                        let _ = writeln!(out, "{name}_sum{labels} {sum}");
+                        let _ = writeln!(out, "{name}_count{labels} {count}");
+                    }
+                }
+            }
+        }
+        out
+    }
+
+    /// Run `record` on the family `name`, creating it with `new` if this is
+    /// its first sample.
+    fn with_family(&self, name: &str, new: fn() -> Family, record: impl FnOnce(&Family)) {
+        if let Some(family) = self.families.read().unwrap().get(name) {
+            return record(family);
+        }
+        let mut families = self.families.write().unwrap();
+        record(families.entry(name.to_string()).or_insert_with(new));
+    }
+}
+
+/// Run `record` on the series for `labels`, creating it if this is its
+/// first sample.
+fn with_series<T: Default>(
+    series: &RwLock<BTreeMap<String, T>>,
+    labels: &[(&str, &str)],
+    record: impl FnOnce(&T),
+) {
+    let labels = render_labels(labels);
+    if let Some(value) = series.read().unwrap().get(&labels) {
+        return record(value);
+    }
+    record(series.write().unwrap().entry(labels).or_default());
+}
+
+/// Add `value` to the `f64` stored as bits in `cell`.
+fn add_f64(cell: &AtomicU64, value: f64) {
+    let _ = cell.fetch_update(Ordering::Relaxed, Ordering::Relaxed, |bits| {
+        Some((f64::from_bits(bits) + value).to_bits())
+    });
+}
+
+impl MetricsSink for PrometheusSink {
+    fn counter(&self, name: &str, value: u64, labels: &[(&str, &str)]) {
+        let new = || Family::Counter(RwLock::default());
+        self.with_family(name, new, |family| {
+            if let Family::Counter(series) = family {
+                with_series(series, labels, |count| {
+                    count.fetch_add(value, Ordering::Relaxed);
+                });
+            }
+        });
+    }
+
+    fn gauge(&self, name: &str, value: f64, labels: &[(&str, &str)]) {
+        let new = || Family::Gauge(RwLock::default());
+        self.with_family(name, new, |family| {
+            if let Family::Gauge(series) = family {
+                with_series(series, labels, |gauge| {
+                    gauge.store(value.to_bits(), Ordering::Relaxed);
+                });
+            }
+        });
+    }
+
+    fn histogram(&self, name: &str, value: f64, labels: &[(&str, &str)]) {
+        let new = || Family::Histogram(RwLock::default());
+        self.with_family(name, new, |family| {
+            if let Family::Histogram(series) = family {
+                with_series(series, labels, |histogram: &Histogram| {
+                    let bucket = HISTOGRAM_BUCKETS
+                        .iter()
+                        .position(|&bound| value <= bound)
+                        .unwrap_or(HISTOGRAM_BUCKETS.len());
+                    histogram.buckets[bucket].fetch_add(1, Ordering::Relaxed);
+                    add_f64(&histogram.sum, value);
+                    histogram.count.fetch_add(1, Ordering::Relaxed);
+                });
+            }
+        });
+    }
+
+    fn scrape(&self) -> Option<String> {
+        Some(self.render())
+    }
+}
+
+/// `labels` as `name="value",...`, values escaped.
+fn render_labels(labels: &[(&str, &str)]) -> String {
+    let mut out = String::new();
+    for (i, (name, value)) in labels.iter().enumerate() {
+        if i > 0 {
+            out.push(',');
+        }
+        let value = value
+            .replace('\\', "\\\\")
+            .replace('"', "\\\"")
+            .replace('\n', "\\n");
+        let _ = write!(out, "{name}=\"{value}\"");
+    }
+    out
+}
+
+/// Rendered labels in braces, or nothing if there are none.
+fn braced(labels: &str) -> String {
+    if labels.is_empty() {
+        String::new()
+    } else {
+        format!("{{{labels}}}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_prometheus_sink_renders_exposition_format() {
+        let sink = PrometheusSink::new();
+        sink.counter(REQUESTS_TOTAL, 1, &[("route", "/api"), ("status", "200")]);
+        sink.counter(REQUESTS_TOTAL, 2, &[("route", "/api"), ("status", "200")]);
+        sink.gauge(CONNECTIONS_ACTIVE, 3.0, &[]);
+        sink.histogram(REQUEST_DURATION_SECONDS, 0.02, &[("route", "a\"b")]);
+        sink.histogram(REQUEST_DURATION_SECONDS, 20.0, &[("route", "a\"b")]);
+        sink.gauge(REQUESTS_TOTAL, 9.0, &[]);
+
+        let text = sink.render();
+        let route = r#"route="a\"b""#;
+        for line in [
+            "# TYPE qsgw_connections_active gauge",
+            "qsgw_connections_active 3",
+            "# TYPE qsgw_requests_total counter",
+            r#"qsgw_requests_total{route="/api",status="200"} 3"#,
+            "# TYPE qsgw_request_duration_seconds histogram",
+            &format!(r#"qsgw_request_duration_seconds_bucket{{{route},le="0.01"}} 0"#),
+            &format!(r#"qsgw_request_duration_seconds_bucket{{{route},le="0.025"}} 1"#),
+            &format!(r#"qsgw_request_duration_seconds_bucket{{{route},le="10"}} 1"#),
+            &format!(r#"qsgw_request_duration_seconds_bucket{{{route},le="+Inf"}} 2"#),
+            &format!("qsgw_request_duration_seconds_sum{{{route}}} 20.02"),
+            &format!("qsgw_request_duration_seconds_count{{{route}}} 2"),
+        ] {
+            assert!(text.lines().any(|l| l == line), "{line:?} in\n{text}");
+        }
+        assert!(!text.contains("qsgw_requests_total 9"));
+    }
+
+    #[test]
+    fn test_unknown_methods_share_a_label() {
+        assert_eq!(method_label(&Method::PATCH), "PATCH");
+        for method in ["PURGE", "get", "X-RANDOM-1234"] {
+            let method = Method::from_bytes(method.as_bytes()).unwrap();
+            assert_eq!(method_label(&method), OTHER_METHOD);
+        }
+    }
+}
@@ -1,18 +1,62 @@
+#[cfg(feature = "algorithm-negotiation")]
+pub mod version_negotiation;
+
 use axum::{
     body::Body,
-    extract::State,
+    extract::{ConnectInfo, State},
     middleware::Next,
     response::{IntoResponse, Response},
 };
-use http::{Request, StatusCode};
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use http::{header, HeaderMap, HeaderValue, Method, Request, StatusCode};
+use hyper::body::{Body as HttpBody, Frame, SizeHint};
+use ipnet::IpNet;
+use std::any::Any;
+use std::future::Future;
+use std::net::{IpAddr, SocketAddr};
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::pin::Pin;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::task::{Context, Poll};
 use std::time::Instant;
-use tracing::info;
+use tower::{Layer, Service};
+use tracing::{error, info, warn};
+
+use quantun_tls::config::EarlyDataPolicy;
+use quantun_types::ErrorCode;
 
+use crate::metrics::{self, GatewayMetrics, MetricsSink};
+use crate::proxy::{MatchedRoute, UpstreamTiming};
+use crate::tenant::TenantId;
+use crate::tls::{ClientCertVerifier, HandshakeInfo, TlsError};
 use crate::TlsPolicy;
 
+/// Header carrying the client certificate as base64 DER, set by the TLS
+/// termination layer (which must strip it from incoming requests); see
+/// [`server::Termination`](crate::server::Termination).
+pub const CLIENT_CERT_HEADER: &str = "x-tls-client-cert";
+
+/// State of [`pqc_enforcement_middleware`].
+#[derive(Debug, Clone)]
+pub struct PqcEnforcement {
+    pub policy: TlsPolicy,
+    /// When set, requests need a valid client certificate, and the
+    /// resulting [`ClientIdentity`](crate::tls::ClientIdentity) is added to
+    /// their extensions.
+    pub mtls: Option<Arc<ClientCertVerifier>>,
+}
+
+impl From<TlsPolicy> for PqcEnforcement {
+    fn from(policy: TlsPolicy) -> Self {
+        Self { policy, mtls: None }
+    }
+}
+
 pub async fn pqc_enforcement_middleware(
-    State(policy): State<TlsPolicy>,
-    req: Request<Body>,
+    State(enforcement): State<PqcEnforcement>,
+    mut req: Request<Body>,
     next: Next,
 ) -> Response {
     let start = Instant::now();
@@ -28,43 +72,940 @@ pub async fn pqc_enforcement_middleware(
 
     let is_pqc = crate::tls::classify_cipher_suite(cipher_suite);
 
-    if policy == TlsPolicy::PqcOnly && !is_pqc && path != "/health" {
-        return (
-            StatusCode::FORBIDDEN,
-            "PQC-only policy: classical cipher suites not allowed",
+    if enforcement.policy == TlsPolicy::PqcOnly && !is_pqc && path != "/health" {
+        return TlsError::PolicyViolation(
+            "PQC-only policy: classical cipher suites not allowed".into(),
         )
-            .into_response();
+        .into_response();
+    }
+
+    if let Some(mtls) = enforcement.mtls.as_deref().filter(|_| path != "/health") {
+        match client_identity(req.headers(), mtls) {
+            Ok(identity) => {
+                req.extensions_mut().insert(identity);
+            }
+            Err(e) => return client_cert_rejection(e),
+        }
     }
 
     let response = next.run(req).await;
 
-    let duration = start.elapsed();
-    info!(
-        method = %method,
-        path = %path,
-        status = %response.status().as_u16(),
-        duration_ms = %duration.as_millis(),
-        pqc = is_pqc,
-        "request completed"
+    let matched = response.extensions().get::<MatchedRoute>();
+    let tenant = response.extensions().get::<TenantId>();
+    let log = RequestLog {
+        method: method.to_string(),
+        path,
+        status: response.status().as_u16(),
+        route: matched.map_or_else(|| "-".into(), |m| m.path_prefix.clone()),
+        upstream: matched.map_or_else(|| "-".into(), |m| m.upstream.clone()),
+        tenant: tenant.map_or_else(|| "-".into(), |t| t.0.clone()),
+        pqc: is_pqc,
+        start,
+    };
+
+    if let Some(len) = known_length(response.headers(), response.body().size_hint()) {
+        log.emit(len);
+        return response;
+    }
+
+    // Streamed body: log once it has been fully sent (or dropped).
+    let (parts, body) = response.into_parts();
+    Response::from_parts(
+        parts,
+        Body::new(CountingBody {
+            inner: body,
+            bytes_sent: 0,
+            log: Some(log),
+        }),
+    )
+}
+
+/// Report each request to the sink: [`metrics::REQUESTS_TOTAL`] and
+/// [`metrics::REQUEST_DURATION_SECONDS`], labelled with the
+/// [`metrics::method_label`] and the matched route's prefix (`-` for the
+/// gateway's own endpoints), and [`metrics::CONNECTIONS_ACTIVE`].
+pub async fn request_metrics_middleware(
+    State((sink, gateway)): State<(Arc<dyn MetricsSink>, Arc<GatewayMetrics>)>,
+    req: Request<Body>,
+    next: Next,
+) -> Response {
+    let start = Instant::now();
+    let method = metrics::method_label(req.method());
+    sink.gauge(
+        metrics::CONNECTIONS_ACTIVE,
+        gateway.active_connections.load(Ordering::Relaxed) as f64,
+        &[],
     );
 
+    let response = next.run(req).await;
+
+    let route = response
+        .extensions()
+        .get::<MatchedRoute>()
+        .map_or("-", |m| m.path_prefix.as_str());
+    let status = response.status();
+    sink.counter(
+        metrics::REQUESTS_TOTAL,
+        1,
+        &[
+            ("method", method),
+            ("route", route),
+            ("status", status.as_str()),
+        ],
+    );
+    sink.histogram(
+        metrics::REQUEST_DURATION_SECONDS,
+        start.elapsed().as_secs_f64(),
+        &[("method", method), ("route", route)],
+    );
     response
 }
 
-pub async fn rate_limit_middleware(
+/// Header marking a request received as TLS 1.3 early data, set to `1` by
+/// the TLS termination layer (RFC 8470).
+pub const EARLY_DATA_HEADER: &str = "early-data";
+
+/// Whether `policy` lets a `method` request be served from early data.
+pub fn early_data_allowed(policy: EarlyDataPolicy, method: &Method) -> bool {
+    match policy {
+        EarlyDataPolicy::Reject => false,
+        EarlyDataPolicy::AllowIdempotent => method.is_idempotent(),
+        EarlyDataPolicy::AllowAll => true,
+    }
+}
+
+/// Answer `425 Too Early` to early-data requests `policy` does not allow,
+/// so the client retries them once the handshake has completed.
+pub async fn early_data_middleware(
+    State(policy): State<EarlyDataPolicy>,
     req: Request<Body>,
     next: Next,
 ) -> Response {
+    let is_early = req
+        .headers()
+        .get(EARLY_DATA_HEADER)
+        .is_some_and(|v| v == "1");
+    if is_early && !early_data_allowed(policy, req.method()) {
+        info!(method = %req.method(), path = %req.uri().path(), "early data refused");
+        return (StatusCode::TOO_EARLY, "retry after the TLS handshake").into_response();
+    }
     next.run(req).await
 }
 
+/// Validate the certificate in [`CLIENT_CERT_HEADER`].
+fn client_identity(
+    headers: &HeaderMap,
+    mtls: &ClientCertVerifier,
+) -> Result<crate::tls::ClientIdentity, TlsError> {
+    let cert = headers
+        .get(CLIENT_CERT_HEADER)
+        .ok_or_else(|| TlsError::ClientCertRejected("no client certificate presented".into()))?;
+    let der = STANDARD
+        .decode(cert.as_bytes())
+        .map_err(|e| TlsError::ClientCertRejected(format!("invalid encoding: {e}")))?;
+    mtls.validate(&der)
+}
+
+fn client_cert_rejection(e: TlsError) -> Response {
+    match e {
+        TlsError::ClientCertRejected(_) => {
+            warn!(error = %e, "mTLS: client certificate rejected");
+            (StatusCode::FORBIDDEN, "client certificate required").into_response()
+        }
+        e => {
+            error!(error = %e, "mTLS: client certificates cannot be validated");
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
+/// Fields of the "request completed" log line.
+struct RequestLog {
+    method: String,
+    path: String,
+    status: u16,
+    route: String,
+    upstream: String,
+    tenant: String,
+    pqc: bool,
+    start: Instant,
+}
+
+impl RequestLog {
+    fn emit(self, bytes_sent: u64) {
+        info!(
+            method = %self.method,
+            path = %self.path,
+            status = %self.status,
+            duration_ms = %self.start.elapsed().as_millis(),
+            bytes_sent,
+            route = %self.route,
+            upstream = %self.upstream,
+            tenant = %self.tenant,
+            pqc = self.pqc,
+            "request completed"
+        );
+    }
+}
+
+/// Response size from `Content-Length`, or from the body's size hint when
+/// it is exact.
+fn known_length(headers: &HeaderMap, hint: SizeHint) -> Option<u64> {
+    headers
+        .get(header::CONTENT_LENGTH)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.parse().ok())
+        .or_else(|| hint.exact())
+}
+
+/// Body wrapper that counts data bytes as they are polled and emits the
+/// request log when dropped.
+struct CountingBody {
+    inner: Body,
+    bytes_sent: u64,
+    log: Option<RequestLog>,
+}
+
+impl HttpBody for CountingBody {
+    type Data = axum::body::Bytes;
+    type Error = axum::Error;
+
+    fn poll_frame(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Frame<Self::Data>, Self::Error>>> {
+        let poll = Pin::new(&mut self.inner).poll_frame(cx);
+        if let Poll::Ready(Some(Ok(frame))) = &poll {
+            if let Some(data) = frame.data_ref() {
+                self.bytes_sent += data.len() as u64;
+            }
+        }
+        poll
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.inner.is_end_stream()
+    }
+
+    fn size_hint(&self) -> SizeHint {
+        self.inner.size_hint()
+    }
+}
+
+impl Drop for CountingBody {
+    fn drop(&mut self) {
+        if let Some(log) = self.log.take() {
+            log.emit(self.bytes_sent);
+        }
+    }
+}
+
+/// Build a layer that sheds load with `503 Service Unavailable` once
+/// `metrics.active_connections` exceeds `max_queue_depth`.
+pub fn load_shed_layer(max_queue_depth: usize, metrics: Arc<GatewayMetrics>) -> LoadShedLayer {
+    LoadShedLayer {
+        max_queue_depth,
+        metrics,
+    }
+}
+
+/// Layer produced by [`load_shed_layer`].
+#[derive(Debug, Clone)]
+pub struct LoadShedLayer {
+    max_queue_depth: usize,
+    metrics: Arc<GatewayMetrics>,
+}
+
+impl<S> Layer<S> for LoadShedLayer {
+    type Service = LoadShed<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        LoadShed {
+            inner,
+            max_queue_depth: self.max_queue_depth,
+            metrics: self.metrics.clone(),
+        }
+    }
+}
+
+/// Service that rejects requests while the gateway is over capacity.
+#[derive(Debug, Clone)]
+pub struct LoadShed<S> {
+    inner: S,
+    max_queue_depth: usize,
+    metrics: Arc<GatewayMetrics>,
+}
+
+impl<S> Service<Request<Body>> for LoadShed<S>
+where
+    S: Service<Request<Body>, Response = Response>,
+    S::Future: Send + 'static,
+{
+    type Response = Response;
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Response, S::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: Request<Body>) -> Self::Future {
+        let active = self.metrics.active_connections.load(Ordering::Relaxed);
+        if active > self.max_queue_depth {
+            self.metrics.shed_requests.fetch_add(1, Ordering::Relaxed);
+            warn!(
+                active_connections = active,
+                max_queue_depth = self.max_queue_depth,
+                path = %req.uri().path(),
+                "shedding request: gateway over capacity"
+            );
+            let response = (
+                StatusCode::SERVICE_UNAVAILABLE,
+                [(header::RETRY_AFTER, "1")],
+                "gateway over capacity",
+            )
+                .into_response();
+            return Box::pin(async move { Ok(response) });
+        }
+
+        Box::pin(self.inner.call(req))
+    }
+}
+
+/// Add a `Server-Timing` header with the TLS handshake duration (from the
+/// request's [`HandshakeInfo`] extension) and the upstream duration (from the
+/// response's [`UpstreamTiming`] extension), when either is known.
+pub async fn server_timing_middleware(req: Request<Body>, next: Next) -> Response {
+    let handshake_ms = req
+        .extensions()
+        .get::<HandshakeInfo>()
+        .map(|h| h.handshake_duration_ms);
+
+    let mut response = next.run(req).await;
+
+    let mut entries = Vec::new();
+    if let Some(ms) = handshake_ms {
+        entries.push(format!("handshake;dur={ms}"));
+    }
+    if let Some(UpstreamTiming(elapsed)) = response.extensions().get::<UpstreamTiming>() {
+        entries.push(format!("upstream;dur={:.3}", elapsed.as_secs_f64() * 1000.0));
+    }
+
+    if !entries.is_empty() {
+        if let Ok(value) = HeaderValue::from_str(&entries.join(", ")) {
+            response.headers_mut().insert("server-timing", value);
+        }
+    }
+
+    response
+}
+
+/// Build a layer that turns a panicking handler into a `500` JSON error
+/// instead of a dropped connection, counting it in `metrics.panic_count`.
+/// The panic is logged with the request's `x-request-id`.
+///
+/// Only unwinding panics are caught. Stack overflow and allocation failure
+/// abort the process, as does every panic when built with `panic = "abort"`.
+pub fn panic_recovery_layer(metrics: Arc<GatewayMetrics>) -> PanicRecoveryLayer {
+    PanicRecoveryLayer { metrics }
+}
+
+/// Layer produced by [`panic_recovery_layer`].
+#[derive(Debug, Clone)]
+pub struct PanicRecoveryLayer {
+    metrics: Arc<GatewayMetrics>,
+}
+
+impl<S> Layer<S> for PanicRecoveryLayer {
+    type Service = PanicRecovery<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        PanicRecovery {
+            inner,
+            metrics: self.metrics.clone(),
+        }
+    }
+}
+
+/// Service that converts handler panics into `500 Internal Server Error`.
+#[derive(Debug, Clone)]
+pub struct PanicRecovery<S> {
+    inner: S,
+    metrics: Arc<GatewayMetrics>,
+}
+
+impl<S> Service<Request<Body>> for PanicRecovery<S>
+where
+    S: Service<Request<Body>, Response = Response>,
+    S::Future: Send + 'static,
+    S::Error: 'static,
+{
+    type Response = Response;
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Response, S::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: Request<Body>) -> Self::Future {
+        let metrics = self.metrics.clone();
+        let request_id = req
+            .headers()
+            .get("x-request-id")
+            .and_then(|v| v.to_str().ok())
+            .map(str::to_string);
+        let mut future = match catch_unwind(AssertUnwindSafe(|| self.inner.call(req))) {
+            Ok(future) => Box::pin(future),
+            Err(payload) => {
+                let response = panic_response(&metrics, request_id.as_deref(), payload);
+                return Box::pin(async move { Ok(response) });
+            }
+        };
+
+        Box::pin(std::future::poll_fn(move |cx| {
+            match catch_unwind(AssertUnwindSafe(|| future.as_mut().poll(cx))) {
+                Ok(poll) => poll,
+                Err(payload) => Poll::Ready(Ok(panic_response(
+                    &metrics,
+                    request_id.as_deref(),
+                    payload,
+                ))),
+            }
+        }))
+    }
+}
+
+fn panic_response(
+    metrics: &GatewayMetrics,
+    request_id: Option<&str>,
+    payload: Box<dyn Any + Send>,
+) -> Response {
+    metrics.panic_count.fetch_add(1, Ordering::Relaxed);
+
+    let message = payload
+        .downcast_ref::<&str>()
+        .copied()
+        .or_else(|| payload.downcast_ref::<String>().map(String::as_str))
+        .unwrap_or("non-string panic payload");
+    error!(panic = message, request_id, "request handler panicked");
+
+    (
+        StatusCode::INTERNAL_SERVER_ERROR,
+        axum::Json(serde_json::json!({
+            "error_code": ErrorCode::Internal.as_str(),
+            "message": "internal server error",
+            "request_id": request_id,
+        })),
+    )
+        .into_response()
+}
+
+/// Address of the client as seen past any trusted proxies, added to the
+/// request's extensions by [`trusted_proxy_layer`]. Use this rather than
+/// reading `X-Forwarded-For` directly.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TrueClientIp(pub IpAddr);
+
+/// Build a layer that sets each request's [`TrueClientIp`], trusting
+/// `X-Forwarded-For` hops only as far as they were added by proxies in
+/// `cidrs`.
+///
+/// An untrusted socket peer is the client, whatever the header says.
+/// Otherwise the header is walked from right to left, skipping trusted
+/// addresses, and the first untrusted one is the client; anything to its
+/// left could have been written by the client itself. If every hop is
+/// trusted, or a hop does not parse, the socket peer (axum's
+/// `ConnectInfo<SocketAddr>`) is used.
+pub fn trusted_proxy_layer(cidrs: Vec<IpNet>) -> TrustedProxyLayer {
+    TrustedProxyLayer {
+        trusted_cidrs: Arc::new(cidrs),
+    }
+}
+
+/// Layer produced by [`trusted_proxy_layer`].
+#[derive(Debug, Clone)]
+pub struct TrustedProxyLayer {
+    trusted_cidrs: Arc<Vec<IpNet>>,
+}
+
+impl<S> Layer<S> for TrustedProxyLayer {
+    type Service = TrustedProxyMiddleware<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        TrustedProxyMiddleware {
+            inner,
+            trusted_cidrs: self.trusted_cidrs.clone(),
+        }
+    }
+}
+
+/// Service that resolves [`TrueClientIp`] from the forwarding chain.
+#[derive(Debug, Clone)]
+pub struct TrustedProxyMiddleware<S> {
+    inner: S,
+    trusted_cidrs: Arc<Vec<IpNet>>,
+}
+
+impl<S> TrustedProxyMiddleware<S> {
+    fn is_trusted(&self, ip: IpAddr) -> bool {
+        self.trusted_cidrs.iter().any(|net| net.contains(&ip))
+    }
+
+    fn client_ip(&self, headers: &HeaderMap, peer: Option<IpAddr>) -> Option<IpAddr> {
+        if let Some(peer) = peer.filter(|ip| !self.is_trusted(*ip)) {
+            return Some(peer);
+        }
+        let hops: Vec<&str> = headers
+            .get_all("x-forwarded-for")
+            .iter()
+            .filter_map(|v| v.to_str().ok())
+            .flat_map(|v| v.split(','))
+            .map(str::trim)
+            .collect();
+        for hop in hops.into_iter().rev() {
+            let ip = hop
+                .parse::<IpAddr>()
+                .or_else(|_| hop.parse::<SocketAddr>().map(|addr| addr.ip()));
+            match ip {
+                Ok(ip) if self.is_trusted(ip) => continue,
+                Ok(ip) => return Some(ip),
+                Err(_) => break,
+            }
+        }
+        peer
+    }
+}
+
+impl<S> Service<Request<Body>> for TrustedProxyMiddleware<S>
+where
+    S: Service<Request<Body>>,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = S::Future;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut req: Request<Body>) -> Self::Future {
+        let peer = req
+            .extensions()
+            .get::<ConnectInfo<SocketAddr>>()
+            .map(|info| info.0.ip());
+        if let Some(ip) = self.client_ip(req.headers(), peer) {
+            req.extensions_mut().insert(TrueClientIp(ip));
+        }
+        self.inner.call(req)
+    }
+}
+
 #[cfg(test)]
 mod tests {
+    use super::*;
     use crate::tls::classify_cipher_suite;
+    use axum::{routing::get, Router};
+    use std::time::Duration;
+    use tower::ServiceExt;
+
+    #[tokio::test]
+    async fn test_server_timing_includes_upstream_duration() {
+        let app = Router::new()
+            .route(
+                "/proxied",
+                get(|| async {
+                    let mut response = "ok".into_response();
+                    response
+                        .extensions_mut()
+                        .insert(UpstreamTiming(Duration::from_micros(2500)));
+                    response
+                }),
+            )
+            .layer(axum::middleware::from_fn(server_timing_middleware));
+
+        let response = app
+            .oneshot(Request::builder().uri("/proxied").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.headers()["server-timing"], "upstream;dur=2.500");
+    }
+
+    #[derive(Clone, Default)]
+    struct CapturedLogs(Arc<std::sync::Mutex<Vec<u8>>>);
+
+    impl std::io::Write for CapturedLogs {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl CapturedLogs {
+        /// Capture log output on the current thread until the guard drops.
+        fn install() -> (Self, tracing::subscriber::DefaultGuard) {
+            let logs = Self::default();
+            let writer = logs.clone();
+            let subscriber = tracing_subscriber::fmt()
+                .with_ansi(false)
+                .with_writer(move || writer.clone())
+                .finish();
+            (logs, tracing::subscriber::set_default(subscriber))
+        }
+
+        fn completed_line(&self) -> String {
+            let output = String::from_utf8(self.0.lock().unwrap().clone()).unwrap();
+            output
+                .lines()
+                .find(|l| l.contains("request completed"))
+                .expect("request log emitted")
+                .to_string()
+        }
+    }
 
-    #[test]
-    fn test_pqc_classification_in_middleware() {
+    fn logged_app(handler: axum::routing::MethodRouter) -> Router {
+        Router::new()
+            .route("/api/items", handler)
+            .layer(axum::middleware::from_fn_with_state(
+                PqcEnforcement::from(TlsPolicy::PqcPreferred),
+                pqc_enforcement_middleware,
+            ))
+    }
+
+    #[tokio::test]
+    async fn test_request_log_records_size_and_route() {
+        let (logs, _guard) = CapturedLogs::install();
+        let app = logged_app(get(|| async {
+            let mut response = "hello".into_response();
+            response.extensions_mut().insert(MatchedRoute {
+                path_prefix: "/api".into(),
+                upstream: "items-svc".into(),
+            });
+            response.extensions_mut().insert(TenantId("acme".into()));
+            response
+        }));
+
+        app.oneshot(Request::builder().uri("/api/items").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+
+        let line = logs.completed_line();
+        assert!(line.contains("bytes_sent=5"), "{line}");
+        assert!(line.contains("route=/api"), "{line}");
+        assert!(line.contains("upstream=items-svc"), "{line}");
+        assert!(line.contains("tenant=acme"), "{line}");
+    }
+
+    #[tokio::test]
+    async fn test_panic_logged_with_request_id() {
+        let (logs, _guard) = CapturedLogs::install();
+        let metrics = Arc::new(GatewayMetrics::default());
+        let app = Router::new()
+            .route(
+                "/api/items",
+                get(|headers: HeaderMap| async move {
+                    let raw = headers["x-count"].to_str().unwrap();
+                    raw.parse::<u32>().unwrap().to_string()
+                }),
+            )
+            .layer(panic_recovery_layer(metrics.clone()));
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .uri("/api/items")
+                    .header("x-request-id", "req-42")
+                    .header("x-count", "many")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        let body = http_body_util::BodyExt::collect(response.into_body())
+            .await
+            .unwrap()
+            .to_bytes();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(json["error_code"], ErrorCode::Internal.as_str());
+        assert_eq!(json["request_id"], "req-42");
+        assert_eq!(metrics.panic_count.load(Ordering::Relaxed), 1);
+
+        let output = String::from_utf8(logs.0.lock().unwrap().clone()).unwrap();
+        let line = output
+            .lines()
+            .find(|l| l.contains("request handler panicked"))
+            .expect("panic logged");
+        assert!(line.contains("request_id=\"req-42\""), "{line}");
+    }
+
+    /// Body of unknown length yielding `chunks` one frame at a time.
+    struct Chunks(std::collections::VecDeque<&'static str>);
+
+    impl HttpBody for Chunks {
+        type Data = axum::body::Bytes;
+        type Error = std::convert::Infallible;
+
+        fn poll_frame(
+            mut self: Pin<&mut Self>,
+            _cx: &mut Context<'_>,
+        ) -> Poll<Option<Result<Frame<Self::Data>, Self::Error>>> {
+            Poll::Ready(self.0.pop_front().map(|c| Ok(Frame::data(c.into()))))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_request_log_counts_streamed_body() {
+        let (logs, _guard) = CapturedLogs::install();
+        let app = logged_app(get(|| async {
+            Body::new(Chunks(["abc", "defg"].into_iter().collect()))
+        }));
+
+        let response = app
+            .oneshot(Request::builder().uri("/api/items").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert!(response.headers().get(header::CONTENT_LENGTH).is_none());
+        let body = http_body_util::BodyExt::collect(response.into_body())
+            .await
+            .unwrap()
+            .to_bytes();
+        assert_eq!(body, "abcdefg");
+
+        let line = logs.completed_line();
+        assert!(line.contains("bytes_sent=7"), "{line}");
+        assert!(line.contains("route=-"), "{line}");
+    }
+
+    async fn resolved_ip(
+        trusted: &[&str],
+        forwarded: Option<&str>,
+        peer: Option<&str>,
+    ) -> Option<String> {
+        let cidrs = trusted.iter().map(|c| c.parse().unwrap()).collect();
+        let app = Router::new()
+            .route(
+                "/ip",
+                get(|req: Request<Body>| async move {
+                    req.extensions()
+                        .get::<TrueClientIp>()
+                        .map(|ip| ip.0.to_string())
+                        .unwrap_or_default()
+                }),
+            )
+            .layer(trusted_proxy_layer(cidrs));
+
+        let mut req = Request::builder().uri("/ip");
+        if let Some(forwarded) = forwarded {
+            req = req.header("x-forwarded-for", forwarded);
+        }
+        let mut req = req.body(Body::empty()).unwrap();
+        if let Some(peer) = peer {
+            req.extensions_mut()
+                .insert(ConnectInfo::<SocketAddr>(peer.parse().unwrap()));
+        }
+        let response = app.oneshot(req).await.unwrap();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let ip = String::from_utf8(body.to_vec()).unwrap();
+        (!ip.is_empty()).then_some(ip)
+    }
+
+    #[tokio::test]
+    async fn test_true_client_ip_skips_trusted_proxies() {
+        let ip = resolved_ip(&["10.0.0.1/32"], Some("1.2.3.4, 10.0.0.1"), None).await;
+        assert_eq!(ip.as_deref(), Some("1.2.3.4"));
+
+        // A spoofed leftmost entry is ignored in favour of the address the
+        // trusted proxy saw.
+        let ip = resolved_ip(
+            &["10.0.0.0/8"],
+            Some("6.6.6.6, 5.5.5.5, 10.0.0.1"),
+            Some("10.0.0.2:443"),
+        )
+        .await;
+        assert_eq!(ip.as_deref(), Some("5.5.5.5"));
+    }
+
+    #[tokio::test]
+    async fn test_true_client_ip_falls_back_to_peer() {
+        // Untrusted peer: the header is not believed at all.
+        let ip = resolved_ip(&["10.0.0.0/8"], Some("1.2.3.4"), Some("9.9.9.9:5000")).await;
+        assert_eq!(ip.as_deref(), Some("9.9.9.9"));
+
+        // Every hop trusted.
+        let ip = resolved_ip(&["10.0.0.0/8"], Some("10.1.1.1"), Some("10.0.0.2:443")).await;
+        assert_eq!(ip.as_deref(), Some("10.0.0.2"));
+
+        // Unparseable hop.
+        let ip = resolved_ip(
+            &["10.0.0.0/8"],
+            Some("junk, 10.1.1.1"),
+            Some("10.0.0.2:443"),
+        )
+        .await;
+        assert_eq!(ip.as_deref(), Some("10.0.0.2"));
+
+        assert_eq!(resolved_ip(&[], None, None).await, None);
+    }
+
+    #[tokio::test]
+    async fn test_mtls_requires_valid_client_cert() {
+        use crate::tls::ClientIdentity;
+        use axum::Extension;
+        use x509_cert::der::{DecodePem, Encode};
+
+        let fixtures = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("testdata/mtls");
+        let pem = std::fs::read(fixtures.join("client-ed25519.pem")).unwrap();
+        let der = x509_cert::Certificate::from_pem(pem)
+            .unwrap()
+            .to_der()
+            .unwrap();
+
+        let enforcement = PqcEnforcement {
+            policy: TlsPolicy::PqcPreferred,
+            mtls: Some(Arc::new(
+                ClientCertVerifier::load(crate::tls::MtlsConfig {
+                    ca_path: fixtures.join("ca-ed25519.pem"),
+                    require_pqc_client_cert: false,
+                    allowed_cn_patterns: vec!["*.clients.internal".into()],
+                })
+                .unwrap(),
+            )),
+        };
+        let app = Router::new()
+            .route(
+                "/whoami",
+                get(|Extension(id): Extension<ClientIdentity>| async move { id.common_name }),
+            )
+            .route("/health", get(|| async { "ok" }))
+            .layer(axum::middleware::from_fn_with_state(
+                enforcement,
+                pqc_enforcement_middleware,
+            ));
+        let send = |path: &str, cert: Option<String>| {
+            let mut req = Request::builder().uri(path);
+            if let Some(cert) = cert {
+                req = req.header(CLIENT_CERT_HEADER, cert);
+            }
+            app.clone().oneshot(req.body(Body::empty()).unwrap())
+        };
+
+        let response = send("/whoami", Some(STANDARD.encode(&der))).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        assert_eq!(body, "billing.clients.internal");
+
+        let response = send("/whoami", None).await.unwrap();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+        let response = send("/whoami", Some("not base64!".into())).await.unwrap();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+        let mut tampered = der.clone();
+        *tampered.last_mut().unwrap() ^= 1;
+        let response = send("/whoami", Some(STANDARD.encode(&tampered)))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+        let response = send("/health", None).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_early_data_gating() {
+        assert!(early_data_allowed(
+            EarlyDataPolicy::AllowIdempotent,
+            &Method::GET
+        ));
+        assert!(early_data_allowed(
+            EarlyDataPolicy::AllowIdempotent,
+            &Method::PUT
+        ));
+        assert!(!early_data_allowed(
+            EarlyDataPolicy::AllowIdempotent,
+            &Method::POST
+        ));
+        assert!(!early_data_allowed(
+            EarlyDataPolicy::AllowIdempotent,
+            &Method::PATCH
+        ));
+        assert!(early_data_allowed(EarlyDataPolicy::AllowAll, &Method::POST));
+        assert!(!early_data_allowed(EarlyDataPolicy::Reject, &Method::GET));
+
+        let app = |policy| {
+            Router::new()
+                .route(
+                    "/orders",
+                    get(|| async { "list" }).post(|| async { "created" }),
+                )
+                .layer(axum::middleware::from_fn_with_state(
+                    policy,
+                    early_data_middleware,
+                ))
+        };
+        let send = |policy, method: Method, early: bool| {
+            let mut req = Request::builder().method(method).uri("/orders");
+            if early {
+                req = req.header(EARLY_DATA_HEADER, "1");
+            }
+            app(policy).oneshot(req.body(Body::empty()).unwrap())
+        };
+
+        let policy = EarlyDataPolicy::AllowIdempotent;
+        let response = send(policy, Method::POST, true).await.unwrap();
+        assert_eq!(response.status(), StatusCode::TOO_EARLY);
+        let response = send(policy, Method::GET, true).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let response = send(policy, Method::POST, false).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let response = send(EarlyDataPolicy::Reject, Method::GET, true)
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::TOO_EARLY);
+        let response = send(EarlyDataPolicy::AllowAll, Method::POST, true)
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_pqc_classification_in_middleware() {
         assert!(classify_cipher_suite("TLS_ML-KEM-768_AES_256_GCM"));
         assert!(!classify_cipher_suite("TLS_ECDHE_RSA_AES_256_GCM"));
+
+        let app = Router::new()
+            .route("/api", get(|| async { "ok" }))
+            .layer(axum::middleware::from_fn_with_state(
+                PqcEnforcement::from(TlsPolicy::PqcOnly),
+                pqc_enforcement_middleware,
+            ));
+        let send = |suite: &'static str| {
+            app.clone().oneshot(
+                Request::builder()
+                    .uri("/api")
+                    .header("x-tls-cipher-suite", suite)
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+        };
+
+        let response = send("TLS_ML-KEM-768_AES_256_GCM").await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let response = send("TLS_ECDHE_RSA_AES_256_GCM").await.unwrap();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["error_code"], "PERMISSION_DENIED");
     }
 }
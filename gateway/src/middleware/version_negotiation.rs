@@ -0,0 +1,218 @@
+//! Agreement on a PQC algorithm between client and gateway, behind the
+//! `algorithm-negotiation` feature.
+//!
+//! A client lists the algorithms it supports in `X-Supported-PQC`, e.g.
+//! `ML-KEM-768, ML-KEM-1024`. The gateway picks one of its
+//! [`GatewayConfig::preferred_algorithms`](crate::GatewayConfig::preferred_algorithms)
+//! from the list and forwards the request with the choice in
+//! `X-Selected-PQC`. With nothing in common the request is answered with
+//! `406 Not Acceptable` listing what the gateway supports. Requests without
+//! `X-Supported-PQC` are passed on unchanged.
+
+use axum::body::Body;
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use http::{HeaderValue, Request, StatusCode};
+use quantun_types::{Algorithm, ErrorCode};
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use tower::{Layer, Service};
+use tracing::debug;
+
+/// Header listing the algorithms a client supports, comma-separated.
+pub const SUPPORTED_PQC_HEADER: &str = "x-supported-pqc";
+/// Header naming the algorithm chosen for the request.
+pub const SELECTED_PQC_HEADER: &str = "x-selected-pqc";
+
+/// Chooses an algorithm from those a client offers.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AlgorithmNegotiator {
+    preferred: Vec<Algorithm>,
+}
+
+impl AlgorithmNegotiator {
+    /// A negotiator favouring `preferred` in order.
+    pub fn new(preferred: Vec<Algorithm>) -> Self {
+        Self { preferred }
+    }
+
+    /// The algorithms the gateway supports, most preferred first.
+    pub fn supported(&self) -> &[Algorithm] {
+        &self.preferred
+    }
+
+    /// The most preferred algorithm that the client also `offered`, or
+    /// `None` when nothing is in common.
+    pub fn negotiate(&self, offered: &[Algorithm]) -> Option<Algorithm> {
+        self.preferred
+            .iter()
+            .find(|alg| offered.contains(alg))
+            .copied()
+    }
+}
+
+/// Algorithm names in an `X-Supported-PQC` value. Names the gateway does
+/// not know are skipped.
+pub fn parse_supported(value: &str) -> Vec<Algorithm> {
+    value
+        .split(',')
+        .filter_map(|name| name.parse().ok())
+        .collect()
+}
+
+/// Layer adding [`AlgorithmNegotiationMiddleware`].
+#[derive(Debug, Clone)]
+pub struct AlgorithmNegotiationLayer {
+    negotiator: Arc<AlgorithmNegotiator>,
+}
+
+impl AlgorithmNegotiationLayer {
+    pub fn new(negotiator: AlgorithmNegotiator) -> Self {
+        Self {
+            negotiator: Arc::new(negotiator),
+        }
+    }
+}
+
+impl<S> Layer<S> for AlgorithmNegotiationLayer {
+    type Service = AlgorithmNegotiationMiddleware<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        AlgorithmNegotiationMiddleware {
+            inner,
+            negotiator: self.negotiator.clone(),
+        }
+    }
+}
+
+/// Service that sets `X-Selected-PQC` on requests offering algorithms,
+/// and refuses those offering none the gateway supports.
+#[derive(Debug, Clone)]
+pub struct AlgorithmNegotiationMiddleware<S> {
+    inner: S,
+    negotiator: Arc<AlgorithmNegotiator>,
+}
+
+impl<S> Service<Request<Body>> for AlgorithmNegotiationMiddleware<S>
+where
+    S: Service<Request<Body>, Response = Response>,
+    S::Future: Send + 'static,
+{
+    type Response = Response;
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Response, S::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut req: Request<Body>) -> Self::Future {
+        // Only the gateway may name the selected algorithm.
+        req.headers_mut().remove(SELECTED_PQC_HEADER);
+        let Some(offered) = req.headers().get(SUPPORTED_PQC_HEADER) else {
+            return Box::pin(self.inner.call(req));
+        };
+        let offered = parse_supported(offered.to_str().unwrap_or(""));
+        match self.negotiator.negotiate(&offered) {
+            Some(selected) => {
+                let value = HeaderValue::from_str(&selected.to_string())
+                    .expect("algorithm names are valid header values");
+                req.headers_mut().insert(SELECTED_PQC_HEADER, value);
+                Box::pin(self.inner.call(req))
+            }
+            None => {
+                debug!(path = %req.uri().path(), "no PQC algorithm in common with the client");
+                let supported: Vec<String> = self
+                    .negotiator
+                    .supported()
+                    .iter()
+                    .map(ToString::to_string)
+                    .collect();
+                let response = (
+                    StatusCode::NOT_ACCEPTABLE,
+                    Json(serde_json::json!({
+                        "error_code": ErrorCode::UnsupportedAlgorithm.as_str(),
+                        "message": "no supported PQC algorithm in common",
+                        "supported": supported,
+                    })),
+                )
+                    .into_response();
+                Box::pin(async move { Ok(response) })
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::routing::get;
+    use axum::Router;
+    use http::HeaderMap;
+    use http_body_util::BodyExt;
+    use quantun_types::{HybridVariant, MlKemVariant};
+    use tower::ServiceExt;
+
+    fn app() -> Router {
+        let negotiator = AlgorithmNegotiator::new(vec![
+            Algorithm::Hybrid(HybridVariant::X25519MlKem768),
+            Algorithm::MlKem(MlKemVariant::MlKem768),
+            Algorithm::MlKem(MlKemVariant::MlKem1024),
+        ]);
+        Router::new()
+            .route(
+                "/echo",
+                get(|headers: HeaderMap| async move {
+                    headers
+                        .get(SELECTED_PQC_HEADER)
+                        .map_or("-".to_string(), |v| v.to_str().unwrap().to_string())
+                }),
+            )
+            .layer(AlgorithmNegotiationLayer::new(negotiator))
+    }
+
+    async fn send(supported: Option<&str>) -> (StatusCode, String) {
+        let mut req = Request::get("/echo").header(SELECTED_PQC_HEADER, "forged");
+        if let Some(supported) = supported {
+            req = req.header(SUPPORTED_PQC_HEADER, supported);
+        }
+        let response = app()
+            .oneshot(req.body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        let status = response.status();
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        (status, String::from_utf8(body.to_vec()).unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_overlapping_lists_select_the_preferred_algorithm() {
+        assert_eq!(
+            send(Some("ML-KEM-1024, ml-kem-768,FOO-1")).await,
+            (StatusCode::OK, "ML-KEM-768".to_string())
+        );
+        assert_eq!(
+            send(Some("ML-KEM-1024")).await,
+            (StatusCode::OK, "ML-KEM-1024".to_string())
+        );
+        // Without the header nothing is negotiated, and a forged choice is
+        // dropped.
+        assert_eq!(send(None).await, (StatusCode::OK, "-".to_string()));
+    }
+
+    #[tokio::test]
+    async fn test_disjoint_or_empty_lists_are_not_acceptable() {
+        for supported in ["ML-KEM-512,ML-DSA-65", ""] {
+            let (status, body) = send(Some(supported)).await;
+            assert_eq!(status, StatusCode::NOT_ACCEPTABLE, "{supported:?}");
+            let body: serde_json::Value = serde_json::from_str(&body).unwrap();
+            assert_eq!(body["error_code"], "UNSUPPORTED_ALGORITHM");
+            assert_eq!(
+                body["supported"],
+                serde_json::json!(["X25519-ML-KEM-768", "ML-KEM-768", "ML-KEM-1024"])
+            );
+        }
+    }
+}
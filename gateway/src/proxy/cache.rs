@@ -0,0 +1,436 @@
+//! In-memory caching of upstream GET responses.
+//!
+//! On a route with a [`CacheConfig`], a GET response is stored when its
+//! `Cache-Control` gives it a lifetime (`s-maxage` or `max-age`) and does
+//! not forbid shared caching (`no-store`, `no-cache`, `private`). Later GETs
+//! for the same path and query, agreeing on the headers named by `Vary`,
+//! are answered from the cache with an `Age` header until the entry goes
+//! stale. Responses are only buffered when their `Content-Length` is within
+//! [`CacheConfig::max_body_bytes`].
+//!
+//! Responses to requests with `Authorization` are only stored when marked
+//! `public` or given an `s-maxage`, and responses setting cookies are never
+//! stored. A request with `Cache-Control: no-cache` skips the cache but may
+//! refresh it; `no-store` bypasses it entirely.
+
+use axum::body::{Body, Bytes};
+use http::header::{AGE, AUTHORIZATION, CACHE_CONTROL, SET_COOKIE, VARY};
+use http::{response, HeaderMap, HeaderName, HeaderValue, Method, Request, Response, StatusCode};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use super::coalesce::content_length;
+use super::{ProxyError, Route};
+
+/// Default limit on the size of a cached response body.
+pub const DEFAULT_MAX_CACHED_BODY: usize = 1024 * 1024;
+
+/// Default limit on the responses cached for one route.
+pub const DEFAULT_MAX_CACHE_ENTRIES: usize = 1024;
+
+/// Opt-in response caching settings for a route.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CacheConfig {
+    /// Largest response body that is cached.
+    #[serde(default = "default_max_body_bytes")]
+    pub max_body_bytes: usize,
+    /// Most responses held for the route. When full, expired entries go
+    /// first, then those closest to expiry.
+    #[serde(default = "default_max_entries")]
+    pub max_entries: usize,
+}
+
+fn default_max_body_bytes() -> usize {
+    DEFAULT_MAX_CACHED_BODY
+}
+
+fn default_max_entries() -> usize {
+    DEFAULT_MAX_CACHE_ENTRIES
+}
+
+impl Default for CacheConfig {
+    fn default() -> Self {
+        Self {
+            max_body_bytes: DEFAULT_MAX_CACHED_BODY,
+            max_entries: DEFAULT_MAX_CACHE_ENTRIES,
+        }
+    }
+}
+
+/// What the cache needs from a request once it has been sent.
+pub(super) struct CacheRequest {
+    path_prefix: String,
+    path_and_query: String,
+    headers: HeaderMap,
+    no_cache: bool,
+}
+
+impl CacheRequest {
+    /// The cache request for `req`, or `None` if `route` does not cache,
+    /// the method is not GET, or the request forbids storing.
+    pub(super) fn for_request(route: &Route, req: &Request<Body>) -> Option<Self> {
+        route.cache.as_ref()?;
+        if req.method() != Method::GET {
+            return None;
+        }
+        let directives = CacheControl::parse(req.headers());
+        if directives.no_store {
+            return None;
+        }
+        Some(Self {
+            path_prefix: route.path_prefix.clone(),
+            path_and_query: req
+                .uri()
+                .path_and_query()
+                .map_or_else(|| "/".to_string(), |pq| pq.to_string()),
+            headers: req.headers().clone(),
+            no_cache: directives.no_cache,
+        })
+    }
+}
+
+/// `Cache-Control` directives the cache acts on.
+#[derive(Debug, Default)]
+struct CacheControl {
+    no_store: bool,
+    no_cache: bool,
+    private: bool,
+    public: bool,
+    max_age: Option<u64>,
+    s_maxage: Option<u64>,
+}
+
+impl CacheControl {
+    fn parse(headers: &HeaderMap) -> Self {
+        let mut directives = Self::default();
+        let values = headers
+            .get_all(CACHE_CONTROL)
+            .iter()
+            .filter_map(|v| v.to_str().ok());
+        for directive in values.flat_map(|v| v.split(',')) {
+            let (name, value) = match directive.split_once('=') {
+                Some((name, value)) => (name, Some(value.trim().trim_matches('"'))),
+                None => (directive, None),
+            };
+            let seconds = value.and_then(|v| v.parse().ok());
+            match name.trim().to_ascii_lowercase().as_str() {
+                "no-store" => directives.no_store = true,
+                "no-cache" => directives.no_cache = true,
+                "private" => directives.private = true,
+                "public" => directives.public = true,
+                "max-age" => directives.max_age = seconds,
+                "s-maxage" => directives.s_maxage = seconds,
+                _ => {}
+            }
+        }
+        directives
+    }
+}
+
+struct Entry {
+    /// Request headers named by the response's `Vary`, with their values.
+    vary: Vec<(HeaderName, Vec<HeaderValue>)>,
+    response: Arc<(response::Parts, Bytes)>,
+    stored: Instant,
+    /// The upstream's `Age` when stored.
+    initial_age: u64,
+    ttl: Duration,
+}
+
+impl Entry {
+    fn is_fresh(&self, now: Instant) -> bool {
+        now.duration_since(self.stored) < self.ttl
+    }
+
+    fn matches(&self, headers: &HeaderMap) -> bool {
+        self.vary
+            .iter()
+            .all(|(name, values)| headers.get_all(name).iter().eq(values.iter()))
+    }
+
+    fn expires(&self) -> Instant {
+        self.stored + self.ttl
+    }
+}
+
+/// Cached responses by route, then path and query.
+#[derive(Default)]
+pub(super) struct ResponseCache {
+    routes: Mutex<HashMap<String, HashMap<String, Vec<Entry>>>>,
+}
+
+impl ResponseCache {
+    /// A fresh cached response for `request`, with its `Age` set.
+    pub(super) fn get(&self, request: &CacheRequest) -> Option<Response<Body>> {
+        if request.no_cache {
+            return None;
+        }
+        let now = Instant::now();
+        let routes = self.routes.lock().unwrap();
+        let entry = routes
+            .get(&request.path_prefix)?
+            .get(&request.path_and_query)?
+            .iter()
+            .find(|entry| entry.is_fresh(now) && entry.matches(&request.headers))?;
+
+        let (parts, body) = &*entry.response;
+        let mut response = Response::from_parts(parts.clone(), Body::from(body.clone()));
+        let age = entry.initial_age + now.duration_since(entry.stored).as_secs();
+        response.headers_mut().insert(AGE, HeaderValue::from(age));
+        Some(response)
+    }
+
+    /// Store `response` if it is cacheable for `request`, returning it
+    /// either way.
+    pub(super) async fn store(
+        &self,
+        config: &CacheConfig,
+        request: CacheRequest,
+        response: Response<Body>,
+    ) -> Result<Response<Body>, ProxyError> {
+        let Some((ttl, initial_age)) = freshness(&request, &response) else {
+            return Ok(response);
+        };
+        if content_length(response.headers()).is_none_or(|len| len > config.max_body_bytes) {
+            return Ok(response);
+        }
+        let Some(vary) = vary(&request.headers, response.headers()) else {
+            return Ok(response);
+        };
+
+        let (parts, body) = response.into_parts();
+        let body = axum::body::to_bytes(body, config.max_body_bytes)
+            .await
+            .map_err(|e| {
+                ProxyError::ConnectionFailed(format!("failed to read upstream response: {e}"))
+            })?;
+        let entry = Entry {
+            vary,
+            response: Arc::new((parts.clone(), body.clone())),
+            stored: Instant::now(),
+            initial_age,
+            ttl,
+        };
+
+        let mut routes = self.routes.lock().unwrap();
+        let route = routes.entry(request.path_prefix).or_default();
+        if let Some(variants) = route.get_mut(&request.path_and_query) {
+            variants.retain(|e| e.vary != entry.vary);
+        }
+        make_room(route, config.max_entries, entry.stored);
+        if config.max_entries > 0 {
+            route.entry(request.path_and_query).or_default().push(entry);
+        }
+        Ok(Response::from_parts(parts, Body::from(body)))
+    }
+}
+
+/// Evict from `route` until it holds fewer than `max_entries` responses:
+/// first everything stale, then the entries closest to expiry.
+fn make_room(route: &mut HashMap<String, Vec<Entry>>, max_entries: usize, now: Instant) {
+    let len = |route: &HashMap<String, Vec<Entry>>| route.values().map(Vec::len).sum::<usize>();
+    if len(route) < max_entries {
+        return;
+    }
+    for variants in route.values_mut() {
+        variants.retain(|e| e.is_fresh(now));
+    }
+    route.retain(|_, variants| !variants.is_empty());
+    while len(route) >= max_entries.max(1) {
+        let Some(key) = route
+            .iter()
+            .flat_map(|(key, variants)| variants.iter().map(move |e| (e.expires(), key)))
+            .min()
+            .map(|(_, key)| key.clone())
+        else {
+            break;
+        };
+        let variants = route.get_mut(&key).expect("key just found");
+        let soonest = (0..variants.len())
+            .min_by_key(|&i| variants[i].expires())
+            .expect("variants are never empty");
+        variants.remove(soonest);
+        if variants.is_empty() {
+            route.remove(&key);
+        }
+    }
+}
+
+/// How long `response` stays fresh and its upstream `Age`, or `None` if it
+/// must not be stored.
+fn freshness(request: &CacheRequest, response: &Response<Body>) -> Option<(Duration, u64)> {
+    let cacheable_status = matches!(
+        response.status(),
+        StatusCode::OK
+            | StatusCode::NON_AUTHORITATIVE_INFORMATION
+            | StatusCode::NO_CONTENT
+            | StatusCode::MOVED_PERMANENTLY
+            | StatusCode::PERMANENT_REDIRECT
+            | StatusCode::NOT_FOUND
+            | StatusCode::GONE
+    );
+    let headers = response.headers();
+    let directives = CacheControl::parse(headers);
+    if !cacheable_status
+        || directives.no_store
+        || directives.no_cache
+        || directives.private
+        || headers.contains_key(SET_COOKIE)
+    {
+        return None;
+    }
+    if request.headers.contains_key(AUTHORIZATION)
+        && !directives.public
+        && directives.s_maxage.is_none()
+    {
+        return None;
+    }
+
+    let lifetime = directives.s_maxage.or(directives.max_age)?;
+    let age = headers
+        .get(AGE)
+        .and_then(|v| v.to_str().ok()?.parse().ok())
+        .unwrap_or(0);
+    (lifetime > age).then(|| (Duration::from_secs(lifetime - age), age))
+}
+
+/// The request headers `response` varies on, with their values in
+/// `request`; `None` for `Vary: *`.
+fn vary(request: &HeaderMap, response: &HeaderMap) -> Option<Vec<(HeaderName, Vec<HeaderValue>)>> {
+    let mut vary = Vec::new();
+    let names = response
+        .get_all(VARY)
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(','))
+        .map(str::trim)
+        .filter(|name| !name.is_empty());
+    for name in names {
+        if name == "*" {
+            return None;
+        }
+        let name = HeaderName::try_from(name).ok()?;
+        let values = request.get_all(&name).iter().cloned().collect();
+        vary.push((name, values));
+    }
+    Some(vary)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::proxy::testing::{MockResponse, MockUpstream};
+    use crate::proxy::ProxyService;
+
+    fn caching_service(mock: &MockUpstream) -> ProxyService {
+        let route = Route {
+            cache: Some(CacheConfig::default()),
+            ..mock.route("/api")
+        };
+        ProxyService::new(vec![route], 5)
+    }
+
+    async fn get(svc: &ProxyService, headers: &[(&str, &str)]) -> Response<Body> {
+        let route = svc.find_route("/api/items").unwrap();
+        let mut req = Request::get("/api/items?page=1");
+        for (name, value) in headers {
+            req = req.header(*name, *value);
+        }
+        svc.forward(&route, req.body(Body::empty()).unwrap())
+            .await
+            .unwrap()
+    }
+
+    async fn body(response: Response<Body>) -> Bytes {
+        axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap()
+    }
+
+    fn cacheable(cache_control: &'static str) -> MockResponse {
+        MockResponse::default()
+            .with_body("items")
+            .with_header(CACHE_CONTROL, HeaderValue::from_static(cache_control))
+    }
+
+    #[tokio::test]
+    async fn identical_gets_hit_the_upstream_once() {
+        let mock = MockUpstream::start().await.unwrap();
+        mock.set_fallback(cacheable("max-age=60"));
+        let svc = caching_service(&mock);
+
+        let first = get(&svc, &[]).await;
+        assert!(first.headers().get(AGE).is_none());
+        assert_eq!(body(first).await, "items");
+
+        let second = get(&svc, &[]).await;
+        assert_eq!(second.status(), StatusCode::OK);
+        assert_eq!(second.headers()[AGE], "0");
+        assert_eq!(body(second).await, "items");
+        assert_eq!(mock.requests().len(), 1);
+
+        // `no-cache` requests go upstream; `no-store` ones bypass the cache.
+        get(&svc, &[("cache-control", "no-cache")]).await;
+        get(&svc, &[("cache-control", "no-store")]).await;
+        assert_eq!(mock.requests().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn uncacheable_responses_are_not_stored() {
+        for cache_control in ["no-store", "private, max-age=60", "no-cache", "public"] {
+            let mock = MockUpstream::start().await.unwrap();
+            mock.set_fallback(cacheable(cache_control));
+            let svc = caching_service(&mock);
+            get(&svc, &[]).await;
+            get(&svc, &[]).await;
+            assert_eq!(mock.requests().len(), 2, "{cache_control}");
+        }
+
+        // Authorized requests share only explicitly public responses.
+        let mock = MockUpstream::start().await.unwrap();
+        mock.set_fallback(cacheable("max-age=60"));
+        let svc = caching_service(&mock);
+        get(&svc, &[("authorization", "Bearer a")]).await;
+        get(&svc, &[("authorization", "Bearer a")]).await;
+        assert_eq!(mock.requests().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn vary_headers_select_the_variant() {
+        let mock = MockUpstream::start().await.unwrap();
+        mock.set_fallback(
+            cacheable("max-age=60").with_header(VARY, HeaderValue::from_static("accept")),
+        );
+        let svc = caching_service(&mock);
+
+        get(&svc, &[("accept", "application/json")]).await;
+        get(&svc, &[("accept", "application/json")]).await;
+        assert_eq!(mock.requests().len(), 1);
+        get(&svc, &[("accept", "text/html")]).await;
+        assert_eq!(mock.requests().len(), 2);
+        get(&svc, &[("accept", "text/html")]).await;
+        get(&svc, &[("accept", "application/json")]).await;
+        assert_eq!(mock.requests().len(), 2);
+    }
+
+    #[test]
+    fn full_routes_evict_soonest_expiry() {
+        let now = Instant::now();
+        let entry = |ttl| Entry {
+            vary: Vec::new(),
+            response: Arc::new((Response::new(()).into_parts().0, Bytes::new())),
+            stored: now,
+            initial_age: 0,
+            ttl: Duration::from_secs(ttl),
+        };
+        let mut route = HashMap::from([
+            ("/a".to_string(), vec![entry(10)]),
+            ("/b".to_string(), vec![entry(5)]),
+            ("/c".to_string(), vec![entry(0)]),
+        ]);
+        make_room(&mut route, 2, now);
+        assert_eq!(route.keys().collect::<Vec<_>>(), ["/a"]);
+    }
+}
@@ -0,0 +1,372 @@
+//! Single-flight coalescing of identical idempotent upstream requests.
+//!
+//! On a route with a [`CoalesceConfig`], concurrent GET and HEAD requests
+//! that agree on method, path, query, credentials and the configured key
+//! headers share one upstream call. Only responses whose `Content-Length` is within
+//! [`CoalesceConfig::max_body_bytes`] are buffered and shared; for anything
+//! larger or streamed, the request that made the call gets the response and
+//! the others forward on their own.
+
+use axum::body::{Body, Bytes};
+use http::header::CONTENT_LENGTH;
+use http::{response, HeaderMap, HeaderValue, Method, Request, Response};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use tokio::sync::OnceCell;
+
+use super::{ProxyError, Route};
+
+/// Default limit on the size of a shared response body.
+pub const DEFAULT_MAX_COALESCED_BODY: usize = 1024 * 1024;
+
+/// Headers carrying caller credentials, which are always part of the key so
+/// that callers never receive each other's responses.
+const CREDENTIAL_HEADERS: [&str; 4] = [
+    "authorization",
+    "proxy-authorization",
+    "cookie",
+    "x-api-key",
+];
+
+/// Opt-in coalescing settings for a route.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CoalesceConfig {
+    /// Request headers that must match for requests to share a response,
+    /// in addition to `Authorization`, `Proxy-Authorization`, `Cookie` and
+    /// `X-Api-Key`, which always must.
+    #[serde(default)]
+    pub key_headers: Vec<String>,
+    /// Largest response body that is buffered and shared.
+    #[serde(default = "default_max_body_bytes")]
+    pub max_body_bytes: usize,
+}
+
+fn default_max_body_bytes() -> usize {
+    DEFAULT_MAX_COALESCED_BODY
+}
+
+impl Default for CoalesceConfig {
+    fn default() -> Self {
+        Self {
+            key_headers: Vec::new(),
+            max_body_bytes: DEFAULT_MAX_COALESCED_BODY,
+        }
+    }
+}
+
+/// What identifies requests that may share an upstream call.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub(super) struct CoalesceKey {
+    path_prefix: String,
+    method: Method,
+    path_and_query: String,
+    headers: Vec<Vec<HeaderValue>>,
+}
+
+impl CoalesceKey {
+    /// The key for `req`, or `None` if `route` does not coalesce or the
+    /// method is not GET or HEAD.
+    pub(super) fn for_request(route: &Route, req: &Request<Body>) -> Option<Self> {
+        let config = route.coalesce.as_ref()?;
+        if !matches!(*req.method(), Method::GET | Method::HEAD) {
+            return None;
+        }
+        Some(Self {
+            path_prefix: route.path_prefix.clone(),
+            method: req.method().clone(),
+            path_and_query: req
+                .uri()
+                .path_and_query()
+                .map_or_else(|| "/".to_string(), |pq| pq.to_string()),
+            headers: config
+                .key_headers
+                .iter()
+                .map(String::as_str)
+                .chain(CREDENTIAL_HEADERS)
+                .map(|name| {
+                    req.headers()
+                        .get_all(name)
+                        .iter()
+                        .cloned()
+                        .collect()
+                })
+                .collect(),
+        })
+    }
+}
+
+#[derive(Clone)]
+enum Shared {
+    Response(Arc<(response::Parts, Bytes)>),
+    Failed(ProxyError),
+    Unshareable,
+}
+
+/// Upstream calls in flight, by key.
+#[derive(Default)]
+pub(super) struct Coalescer {
+    inflight: Mutex<HashMap<CoalesceKey, Arc<OnceCell<Shared>>>>,
+}
+
+impl Coalescer {
+    /// Forward `req` with `send`, unless an identical request is already in
+    /// flight, in which case wait for and share its response.
+    pub(super) async fn forward<F, Fut>(
+        &self,
+        key: CoalesceKey,
+        max_body_bytes: usize,
+        req: Request<Body>,
+        send: F,
+    ) -> Result<Response<Body>, ProxyError>
+    where
+        F: Fn(Request<Body>) -> Fut,
+        Fut: Future<Output = Result<Response<Body>, ProxyError>>,
+    {
+        let cell = self
+            .inflight
+            .lock()
+            .unwrap()
+            .entry(key.clone())
+            .or_default()
+            .clone();
+
+        let mut req = Some(req);
+        let mut own = None;
+        let (req_slot, own_slot, send_ref) = (&mut req, &mut own, &send);
+        let shared = cell
+            .get_or_init(|| async move {
+                let req = req_slot.take().expect("request taken once");
+                match send_ref(req).await {
+                    Ok(response) => share(response, max_body_bytes, own_slot).await,
+                    Err(e) => Shared::Failed(e),
+                }
+            })
+            .await
+            .clone();
+
+        {
+            let mut inflight = self.inflight.lock().unwrap();
+            if inflight.get(&key).is_some_and(|c| Arc::ptr_eq(c, &cell)) {
+                inflight.remove(&key);
+            }
+        }
+
+        match shared {
+            Shared::Response(buffered) => {
+                let (parts, body) = &*buffered;
+                Ok(Response::from_parts(
+                    parts.clone(),
+                    Body::from(body.clone()),
+                ))
+            }
+            Shared::Failed(e) => Err(e),
+            Shared::Unshareable => match (own, req) {
+                (Some(response), _) => Ok(response),
+                (None, Some(req)) => send(req).await,
+                (None, None) => unreachable!("request neither sent nor kept"),
+            },
+        }
+    }
+}
+
+/// Buffer `response` for sharing if its declared length is within
+/// `max_body_bytes`, otherwise hand it back through `own`.
+async fn share(
+    response: Response<Body>,
+    max_body_bytes: usize,
+    own: &mut Option<Response<Body>>,
+) -> Shared {
+    match content_length(response.headers()) {
+        Some(len) if len <= max_body_bytes => {
+            let (parts, body) = response.into_parts();
+            match axum::body::to_bytes(body, max_body_bytes).await {
+                Ok(body) => Shared::Response(Arc::new((parts, body))),
+                Err(e) => Shared::Failed(ProxyError::ConnectionFailed(format!(
+                    "failed to read upstream response: {e}"
+                ))),
+            }
+        }
+        _ => {
+            *own = Some(response);
+            Shared::Unshareable
+        }
+    }
+}
+
+pub(super) fn content_length(headers: &HeaderMap) -> Option<usize> {
+    headers.get(CONTENT_LENGTH)?.to_str().ok()?.parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::proxy::testing::{MockResponse, MockUpstream};
+    use crate::proxy::{MiddlewareProfile, ProxyService};
+    use http::StatusCode;
+    use std::time::Duration;
+
+    async fn fire(svc: &Arc<ProxyService>, n: usize, method: Method) -> Vec<Response<Body>> {
+        let tasks: Vec<_> = (0..n)
+            .map(|_| {
+                let svc = svc.clone();
+                let method = method.clone();
+                tokio::spawn(async move {
+                    let route = svc.find_route("/api/items").unwrap();
+                    let req = Request::builder()
+                        .method(method)
+                        .uri("/api/items?page=1")
+                        .body(Body::empty())
+                        .unwrap();
+                    svc.forward(&route, req).await.unwrap()
+                })
+            })
+            .collect();
+        let mut responses = Vec::new();
+        for task in tasks {
+            responses.push(task.await.unwrap());
+        }
+        responses
+    }
+
+    fn coalescing_service(mock: &MockUpstream, config: CoalesceConfig) -> Arc<ProxyService> {
+        let route = Route {
+            coalesce: Some(config),
+            ..mock.route("/api")
+        };
+        Arc::new(ProxyService::new(vec![route], 5))
+    }
+
+    #[tokio::test]
+    async fn concurrent_identical_gets_share_one_upstream_call() {
+        let mock = MockUpstream::start().await.unwrap();
+        mock.set_fallback(
+            MockResponse::default()
+                .with_body("items")
+                .with_delay(Duration::from_millis(200)),
+        );
+        let svc = coalescing_service(&mock, CoalesceConfig::default());
+
+        let responses = fire(&svc, 16, Method::GET).await;
+        assert_eq!(mock.requests().len(), 1);
+        for response in responses {
+            assert_eq!(response.status(), StatusCode::OK);
+            let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+                .await
+                .unwrap();
+            assert_eq!(body, "items");
+        }
+
+        // Once the call completes, the next request goes upstream again.
+        fire(&svc, 1, Method::GET).await;
+        assert_eq!(mock.requests().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn writes_and_large_bodies_are_not_shared() {
+        let mock = MockUpstream::start().await.unwrap();
+        mock.set_fallback(
+            MockResponse::default()
+                .with_body("too large to share")
+                .with_delay(Duration::from_millis(100)),
+        );
+        let svc = coalescing_service(
+            &mock,
+            CoalesceConfig {
+                max_body_bytes: 4,
+                ..CoalesceConfig::default()
+            },
+        );
+
+        fire(&svc, 4, Method::POST).await;
+        assert_eq!(mock.requests().len(), 4);
+
+        for response in fire(&svc, 4, Method::GET).await {
+            let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+                .await
+                .unwrap();
+            assert_eq!(body, "too large to share");
+        }
+        assert_eq!(mock.requests().len(), 8);
+    }
+
+    #[test]
+    fn key_includes_selected_headers() {
+        let route = Route {
+            coalesce: Some(CoalesceConfig {
+                key_headers: vec!["accept".into()],
+                ..CoalesceConfig::default()
+            }),
+            cache: None,
+            path_prefix: "/api".into(),
+            upstream: crate::proxy::Upstream {
+                name: "svc".into(),
+                host: "127.0.0.1".into(),
+                port: 80,
+                is_healthy: true,
+                tls_verify: false,
+                tls: false,
+                weight: 1,
+            },
+            strip_prefix: false,
+            path_rewrite: None,
+            priority: 0,
+            tunnel: None,
+            unseal: None,
+            transform: None,
+            sse_filter: None,
+            allowed_methods: Vec::new(),
+            denied_methods: Vec::new(),
+            content_type: None,
+            middleware_profile: MiddlewareProfile::Default,
+        };
+        let req = |accept: &str| {
+            Request::get("/api/x")
+                .header("accept", accept)
+                .body(Body::empty())
+                .unwrap()
+        };
+        let json = CoalesceKey::for_request(&route, &req("application/json"));
+        assert!(json.is_some());
+        assert_eq!(
+            json,
+            CoalesceKey::for_request(&route, &req("application/json"))
+        );
+        assert_ne!(json, CoalesceKey::for_request(&route, &req("text/html")));
+
+        let uncoalesced = Route {
+            coalesce: None,
+            cache: None,
+            ..route
+        };
+        assert!(CoalesceKey::for_request(&uncoalesced, &req("application/json")).is_none());
+    }
+
+    #[tokio::test]
+    async fn callers_with_different_credentials_do_not_share() {
+        let mock = MockUpstream::start().await.unwrap();
+        mock.set_fallback(MockResponse::default().with_delay(Duration::from_millis(200)));
+        let svc = coalescing_service(&mock, CoalesceConfig::default());
+
+        let tasks: Vec<_> = ["authorization", "cookie", "x-api-key"]
+            .into_iter()
+            .flat_map(|header| [(header, "alice"), (header, "bob")])
+            .map(|(header, value)| {
+                let svc = svc.clone();
+                tokio::spawn(async move {
+                    let route = svc.find_route("/api/items").unwrap();
+                    let req = Request::get("/api/items")
+                        .header(header, value)
+                        .body(Body::empty())
+                        .unwrap();
+                    svc.forward(&route, req).await.unwrap()
+                })
+            })
+            .collect();
+        for task in tasks {
+            task.await.unwrap();
+        }
+        assert_eq!(mock.requests().len(), 6);
+    }
+}
@@ -0,0 +1,348 @@
+//! Kubernetes service discovery, behind the `k8s` feature.
+//!
+//! [`K8sServiceDiscovery`] watches the Endpoints of one service and keeps a
+//! shared list with an [`Upstream`] per ready pod address, for
+//! [`ProxyService::with_discovered_upstreams`](super::ProxyService::with_discovered_upstreams).
+//! Addresses that are not ready are left out; when the Endpoints resource
+//! is deleted the list is emptied. With a notifier, an address turning not
+//! ready is reported as an ejection, and one turning ready again as a
+//! restoration.
+
+use futures::StreamExt;
+use k8s_openapi::api::core::v1::Endpoints;
+use kube::runtime::watcher::{self, Event};
+use kube::runtime::WatchStreamExt;
+use kube::{Api, Client};
+use std::collections::BTreeSet;
+use std::sync::{Arc, RwLock};
+use tokio::task::JoinHandle;
+use tracing::{error, info, warn};
+
+use super::Upstream;
+use crate::webhook::{GatewayEvent, WebhookNotifier};
+
+#[derive(Clone)]
+pub struct K8sServiceDiscovery {
+    namespace: String,
+    service_name: String,
+    port: u16,
+    client: Option<Client>,
+    notifier: Option<Arc<WebhookNotifier>>,
+    endpoints: Arc<RwLock<Vec<Upstream>>>,
+    /// Addresses listed as not ready at the last update.
+    not_ready: BTreeSet<String>,
+}
+
+impl K8sServiceDiscovery {
+    /// Discovery of `service_name` in `namespace`, whose pods are reached on
+    /// `port`.
+    pub fn new(namespace: &str, service_name: &str, port: u16) -> Self {
+        Self {
+            namespace: namespace.to_string(),
+            service_name: service_name.to_string(),
+            port,
+            client: None,
+            notifier: None,
+            endpoints: Arc::new(RwLock::new(Vec::new())),
+            not_ready: BTreeSet::new(),
+        }
+    }
+
+    /// Watch through `client` instead of one inferred from the environment
+    /// on [`start`](Self::start).
+    pub fn with_client(mut self, client: Client) -> Self {
+        self.client = Some(client);
+        self
+    }
+
+    /// Report endpoints turning not ready, and ready again, to `notifier`.
+    pub fn with_notifier(mut self, notifier: Arc<WebhookNotifier>) -> Self {
+        self.notifier = Some(notifier);
+        self
+    }
+
+    /// The list kept up to date, to share with the proxy.
+    pub fn upstreams(&self) -> Arc<RwLock<Vec<Upstream>>> {
+        self.endpoints.clone()
+    }
+
+    /// A snapshot of the ready endpoints.
+    pub fn current_endpoints(&self) -> Vec<Upstream> {
+        self.endpoints.read().unwrap().clone()
+    }
+
+    /// Watch the service's Endpoints until the task is aborted. Watch
+    /// errors are retried with backoff.
+    pub fn start(self) -> JoinHandle<()> {
+        tokio::spawn(async move { self.run().await })
+    }
+
+    async fn run(mut self) {
+        let client = match self.client.clone() {
+            Some(client) => client,
+            None => match Client::try_default().await {
+                Ok(client) => client,
+                Err(e) => {
+                    error!(error = %e, "no Kubernetes client for service discovery");
+                    return;
+                }
+            },
+        };
+        let api: Api<Endpoints> = Api::namespaced(client, &self.namespace);
+        let config =
+            watcher::Config::default().fields(&format!("metadata.name={}", self.service_name));
+        let mut events = watcher::watcher(api, config).default_backoff().boxed();
+
+        // Whether the Endpoints were seen in the current (re)list.
+        let mut listed = false;
+        while let Some(event) = events.next().await {
+            match event {
+                Ok(Event::Init) => listed = false,
+                Ok(Event::InitApply(endpoints)) => {
+                    listed = true;
+                    self.apply(&endpoints);
+                }
+                Ok(Event::InitDone) if !listed => self.update(Vec::new(), BTreeSet::new()),
+                Ok(Event::InitDone) => {}
+                Ok(Event::Apply(endpoints)) => self.apply(&endpoints),
+                Ok(Event::Delete(_)) => self.update(Vec::new(), BTreeSet::new()),
+                Err(e) => warn!(
+                    service = %self.service_name,
+                    namespace = %self.namespace,
+                    error = %e,
+                    "endpoints watch failed"
+                ),
+            }
+        }
+    }
+
+    fn apply(&mut self, endpoints: &Endpoints) {
+        let addresses = |ready: bool| -> BTreeSet<String> {
+            endpoints
+                .subsets
+                .iter()
+                .flatten()
+                .flat_map(|subset| {
+                    let addresses = if ready {
+                        &subset.addresses
+                    } else {
+                        &subset.not_ready_addresses
+                    };
+                    addresses.iter().flatten()
+                })
+                .map(|address| address.ip.clone())
+                .collect()
+        };
+        let ready = addresses(true);
+        let not_ready = addresses(false).difference(&ready).cloned().collect();
+        self.update(self.upstreams_at(ready), not_ready);
+    }
+
+    /// An upstream per address in `hosts`.
+    fn upstreams_at(&self, hosts: BTreeSet<String>) -> Vec<Upstream> {
+        hosts
+            .into_iter()
+            .map(|host| Upstream {
+                name: self.service_name.clone(),
+                host,
+                port: self.port,
+                is_healthy: true,
+                tls_verify: false,
+                tls: false,
+                weight: 1,
+            })
+            .collect()
+    }
+
+    fn update(&mut self, upstreams: Vec<Upstream>, not_ready: BTreeSet<String>) {
+        let mut events = Vec::new();
+        let mut endpoints = self.endpoints.write().unwrap();
+        for added in upstreams.iter().filter(|u| !endpoints.contains(u)) {
+            info!(
+                service = %self.service_name,
+                namespace = %self.namespace,
+                endpoint = %format!("{}:{}", added.host, added.port),
+                "endpoint added"
+            );
+            if self.not_ready.contains(&added.host) {
+                events.push(GatewayEvent::upstream_restored(added));
+            }
+        }
+        for removed in endpoints.iter().filter(|u| !upstreams.contains(u)) {
+            info!(
+                service = %self.service_name,
+                namespace = %self.namespace,
+                endpoint = %format!("{}:{}", removed.host, removed.port),
+                "endpoint removed"
+            );
+            if not_ready.contains(&removed.host) {
+                let reason = "endpoint not ready";
+                events.push(GatewayEvent::upstream_ejected(removed, reason));
+            }
+        }
+        *endpoints = upstreams;
+        drop(endpoints);
+        self.not_ready = not_ready;
+        if let Some(notifier) = &self.notifier {
+            for event in events {
+                notifier.notify(event);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use http::{Request, Response};
+    use kube::client::Body;
+    use serde_json::json;
+    use std::time::Duration;
+
+    fn endpoints(ready: &[&str], not_ready: &[&str]) -> serde_json::Value {
+        let addresses = |ips: &[&str]| ips.iter().map(|ip| json!({ "ip": ip })).collect::<Vec<_>>();
+        json!({
+            "apiVersion": "v1",
+            "kind": "Endpoints",
+            "metadata": { "name": "api", "namespace": "shop", "resourceVersion": "2" },
+            "subsets": [{
+                "addresses": addresses(ready),
+                "notReadyAddresses": addresses(not_ready),
+                "ports": [{ "port": 8080 }],
+            }],
+        })
+    }
+
+    async fn wait_for(discovery: &K8sServiceDiscovery, count: usize) -> Vec<Upstream> {
+        tokio::time::timeout(Duration::from_secs(5), async {
+            loop {
+                let current = discovery.current_endpoints();
+                if current.len() == count {
+                    return current;
+                }
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .expect("endpoints updated")
+    }
+
+    #[tokio::test]
+    async fn test_added_endpoint_appears_in_current_endpoints() {
+        let (mock, mut handle) = tower_test::mock::pair::<Request<Body>, Response<Body>>();
+        let discovery =
+            K8sServiceDiscovery::new("shop", "api", 8080).with_client(Client::new(mock, "shop"));
+        let task = discovery.clone().start();
+
+        let (request, respond) = handle.next_request().await.expect("list request");
+        assert_eq!(request.uri().path(), "/api/v1/namespaces/shop/endpoints");
+        assert!(request
+            .uri()
+            .query()
+            .unwrap()
+            .contains("metadata.name%3Dapi"));
+        let list = json!({
+            "apiVersion": "v1",
+            "kind": "EndpointsList",
+            "metadata": { "resourceVersion": "1" },
+            "items": [endpoints(&["10.0.0.1"], &["10.0.0.9"])],
+        });
+        respond.send_response(Response::new(Body::from(
+            serde_json::to_vec(&list).unwrap(),
+        )));
+        let current = wait_for(&discovery, 1).await;
+        assert_eq!(current[0].host, "10.0.0.1");
+        assert_eq!(current[0].port, 8080);
+
+        let (request, respond) = handle.next_request().await.expect("watch request");
+        assert!(request.uri().query().unwrap().contains("watch=true"));
+        let mut event = serde_json::to_vec(&json!({
+            "type": "MODIFIED",
+            "object": endpoints(&["10.0.0.1", "10.0.0.2"], &[]),
+        }))
+        .unwrap();
+        event.push(b'\n');
+        respond.send_response(Response::new(Body::from(event)));
+
+        let hosts: Vec<String> = wait_for(&discovery, 2)
+            .await
+            .into_iter()
+            .map(|u| u.host)
+            .collect();
+        assert_eq!(hosts, ["10.0.0.1", "10.0.0.2"]);
+        task.abort();
+    }
+
+    #[tokio::test]
+    async fn test_readiness_changes_are_notified() {
+        use crate::proxy::testing::MockUpstream;
+        use crate::webhook::{WebhookConfig, WebhookSigner};
+        use quantun_crypto::SecureBytes;
+
+        let receiver = MockUpstream::start().await.unwrap();
+        let notifier = WebhookNotifier::new(
+            WebhookConfig {
+                endpoints: vec![format!("http://{}/hooks", receiver.addr())],
+                ..WebhookConfig::default()
+            },
+            WebhookSigner::Hmac(SecureBytes::new(b"secret".to_vec())),
+        )
+        .unwrap();
+        let (mock, mut handle) = tower_test::mock::pair::<Request<Body>, Response<Body>>();
+        let discovery = K8sServiceDiscovery::new("shop", "api", 8080)
+            .with_client(Client::new(mock, "shop"))
+            .with_notifier(Arc::new(notifier));
+        let task = discovery.clone().start();
+
+        let (_, respond) = handle.next_request().await.expect("list request");
+        let list = json!({
+            "apiVersion": "v1",
+            "kind": "EndpointsList",
+            "metadata": { "resourceVersion": "1" },
+            "items": [endpoints(&["10.0.0.1", "10.0.0.2"], &[])],
+        });
+        respond.send_response(Response::new(Body::from(
+            serde_json::to_vec(&list).unwrap(),
+        )));
+        wait_for(&discovery, 2).await;
+
+        // 10.0.0.2 turns not ready, and ready again.
+        let (_, respond) = handle.next_request().await.expect("watch request");
+        let modified = |ready: &[&str], not_ready: &[&str]| {
+            let object = endpoints(ready, not_ready);
+            let mut event =
+                serde_json::to_vec(&json!({ "type": "MODIFIED", "object": object })).unwrap();
+            event.push(b'\n');
+            event
+        };
+        let events = [
+            modified(&["10.0.0.1"], &["10.0.0.2"]),
+            modified(&["10.0.0.1", "10.0.0.2"], &[]),
+        ]
+        .concat();
+        respond.send_response(Response::new(Body::from(events)));
+
+        let posted = tokio::time::timeout(Duration::from_secs(5), async {
+            loop {
+                let mut posted: Vec<serde_json::Value> = receiver
+                    .requests()
+                    .iter()
+                    .map(|r| serde_json::from_slice(&r.body).unwrap())
+                    .collect();
+                if posted.len() == 2 {
+                    posted.sort_by_key(|e| e["event"].to_string());
+                    return posted;
+                }
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .expect("both events delivered");
+        assert_eq!(posted[0]["event"], "upstream_ejected");
+        assert_eq!(posted[0]["upstream"], "api (10.0.0.2:8080)");
+        assert_eq!(posted[0]["reason"], "endpoint not ready");
+        assert_eq!(posted[1]["event"], "upstream_restored");
+        assert_eq!(posted[1]["upstream"], "api (10.0.0.2:8080)");
+        task.abort();
+    }
+}
@@ -0,0 +1,278 @@
+//! Route matching without forwarding.
+//!
+//! [`ProxyService::dry_run_route`] reports which route a request would
+//! take, the upstream URL it would be sent to, and what the proxy would do
+//! to it on the way, so operators can check a route set before deploying
+//! it. Nothing is resolved or connected: the URL names the upstream host as
+//! configured rather than the address it resolves to.
+
+use axum::body::Body;
+use axum::extract::{Query, State};
+use axum::response::{IntoResponse, Response};
+use axum::routing::get;
+use axum::{Json, Router};
+use http::{Method, Request, StatusCode, Uri};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+use super::cache::CacheRequest;
+use super::coalesce::CoalesceKey;
+use super::{ForwardedProto, ProxyError, ProxyService, Route};
+
+/// Path of the admin endpoint serving [`ProxyService::dry_run_route`].
+pub const DRY_RUN_PATH: &str = "/admin/dry-run";
+
+/// The route a dry run matched.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RouteSummary {
+    pub path_prefix: String,
+    /// Name of the route's upstream.
+    pub upstream: String,
+    pub priority: i32,
+}
+
+impl From<&Route> for RouteSummary {
+    fn from(route: &Route) -> Self {
+        Self {
+            path_prefix: route.path_prefix.clone(),
+            upstream: route.upstream.name.clone(),
+            priority: route.priority,
+        }
+    }
+}
+
+/// What forwarding a request would do.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DryRunResult {
+    /// `None` if no healthy route matches.
+    pub matched_route: Option<RouteSummary>,
+    pub would_strip_prefix: bool,
+    pub upstream_url: Option<String>,
+    /// Changes made to the request, in the order they are applied.
+    pub applied_transforms: Vec<String>,
+}
+
+impl ProxyService {
+    /// Match a `method` request for `path` against the current routes as
+    /// [`ProxyService::forward`] would, without resolving or contacting
+    /// the upstream.
+    pub fn dry_run_route(&self, method: &str, path: &str) -> Result<DryRunResult, ProxyError> {
+        let method = Method::from_bytes(method.as_bytes())
+            .map_err(|e| ProxyError::RequestError(format!("invalid method: {e}")))?;
+        let uri: Uri = path
+            .parse()
+            .map_err(|e| ProxyError::RequestError(format!("invalid path: {e}")))?;
+
+        let Some(route) = self.find_route(uri.path()) else {
+            return Ok(DryRunResult {
+                matched_route: None,
+                would_strip_prefix: false,
+                upstream_url: None,
+                applied_transforms: Vec::new(),
+            });
+        };
+
+        let authority = format!("{}:{}", route.upstream.host, route.upstream.port);
+        let upstream_url = self.build_upstream_uri(&route, &authority, &uri)?;
+
+        let req = Request::builder()
+            .method(method)
+            .uri(uri)
+            .body(Body::empty())
+            .map_err(|e| ProxyError::RequestError(e.to_string()))?;
+        let mut applied_transforms = Vec::new();
+        if CacheRequest::for_request(&route, &req).is_some() {
+            applied_transforms.push("serve fresh cached responses".to_string());
+        }
+        if CoalesceKey::for_request(&route, &req).is_some() {
+            applied_transforms.push("coalesce identical requests".to_string());
+        }
+        if route.strip_prefix {
+            applied_transforms.push(format!("strip prefix {}", route.path_prefix));
+        }
+        if let Some(rewrite) = &route.path_rewrite {
+            applied_transforms.push(format!(
+                "rewrite path {} to {}",
+                rewrite.pattern, rewrite.replacement
+            ));
+        }
+        applied_transforms.extend([
+            "remove header connection".to_string(),
+            format!("set header host: {authority}"),
+            match self.forwarded_proto {
+                ForwardedProto::Auto => {
+                    "set header x-forwarded-proto from the listener scheme".to_string()
+                }
+                proto => format!("set header x-forwarded-proto: {}", proto.for_request(&req)),
+            },
+        ]);
+        if let Some(peer) = &route.tunnel {
+            applied_transforms.push(format!("forward through tunnel to {peer}"));
+        }
+
+        Ok(DryRunResult {
+            matched_route: Some(RouteSummary::from(&route)),
+            would_strip_prefix: route.strip_prefix,
+            upstream_url: Some(upstream_url.to_string()),
+            applied_transforms,
+        })
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct DryRunQuery {
+    method: Option<String>,
+    path: String,
+}
+
+/// Admin routes over `proxy`:
+///
+/// - `GET /admin/dry-run?method=GET&path=/api/v2/foo`: the
+///   [`DryRunResult`] for that request. `method` defaults to `GET`.
+///
+/// [`build_router`](crate::build_router) mounts these as `AdminOnly`.
+pub fn router(proxy: Arc<ProxyService>) -> Router {
+    Router::new()
+        .route(DRY_RUN_PATH, get(dry_run))
+        .with_state(proxy)
+}
+
+async fn dry_run(
+    State(proxy): State<Arc<ProxyService>>,
+    Query(query): Query<DryRunQuery>,
+) -> Response {
+    let method = query.method.as_deref().unwrap_or("GET");
+    match proxy.dry_run_route(method, &query.path) {
+        Ok(result) => Json(result).into_response(),
+        Err(e) => (StatusCode::BAD_REQUEST, e.to_string()).into_response(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::proxy::coalesce::CoalesceConfig;
+    use crate::proxy::{MiddlewareProfile, Upstream};
+    use http_body_util::BodyExt;
+    use tower::ServiceExt;
+
+    /// Routes whose upstream hosts cannot resolve, so any attempt to
+    /// forward would fail.
+    fn service() -> ProxyService {
+        let route = |prefix: &str, host: &str, strip_prefix, priority| Route {
+            path_prefix: prefix.into(),
+            upstream: Upstream {
+                name: host.split('.').next().unwrap().into(),
+                host: host.into(),
+                port: 8080,
+                is_healthy: true,
+                tls_verify: false,
+                tls: false,
+                weight: 1,
+            },
+            strip_prefix,
+            path_rewrite: None,
+            priority,
+            coalesce: None,
+            cache: None,
+            tunnel: None,
+            unseal: None,
+            transform: None,
+            sse_filter: None,
+            allowed_methods: Vec::new(),
+            denied_methods: Vec::new(),
+            content_type: None,
+            middleware_profile: MiddlewareProfile::Default,
+        };
+        ProxyService::new(
+            vec![
+                route("/api", "legacy.invalid", false, 100),
+                Route {
+                    coalesce: Some(CoalesceConfig::default()),
+                    ..route("/api/v2", "orders.invalid", true, 200)
+                },
+            ],
+            5,
+        )
+    }
+
+    #[test]
+    fn reports_upstream_url_and_transforms() {
+        let svc = service();
+
+        let result = svc.dry_run_route("GET", "/api/v2/foo").unwrap();
+        assert_eq!(
+            result.matched_route,
+            Some(RouteSummary {
+                path_prefix: "/api/v2".into(),
+                upstream: "orders".into(),
+                priority: 200,
+            })
+        );
+        assert!(result.would_strip_prefix);
+        assert_eq!(
+            result.upstream_url.as_deref(),
+            Some("http://orders.invalid:8080/foo")
+        );
+        assert_eq!(
+            result.applied_transforms,
+            [
+                "coalesce identical requests",
+                "strip prefix /api/v2",
+                "remove header connection",
+                "set header host: orders.invalid:8080",
+                "set header x-forwarded-proto: https",
+            ]
+        );
+
+        // Writes are never coalesced.
+        let result = svc.dry_run_route("POST", "/api/v2/foo").unwrap();
+        assert_eq!(result.applied_transforms[0], "strip prefix /api/v2");
+
+        let result = svc.dry_run_route("GET", "/api/v1/foo").unwrap();
+        assert!(!result.would_strip_prefix);
+        assert_eq!(
+            result.upstream_url.as_deref(),
+            Some("http://legacy.invalid:8080/api/v1/foo")
+        );
+        assert_eq!(result.applied_transforms.len(), 3);
+
+        let result = svc.dry_run_route("GET", "/other").unwrap();
+        assert_eq!(result.matched_route, None);
+        assert_eq!(result.upstream_url, None);
+
+        assert!(svc.dry_run_route("GET", "not a path").is_err());
+    }
+
+    #[tokio::test]
+    async fn dry_run_endpoint() {
+        let app = router(Arc::new(service()));
+        let response = app
+            .clone()
+            .oneshot(
+                Request::get("/admin/dry-run?method=HEAD&path=/api/v2/foo")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let result: DryRunResult = serde_json::from_slice(&body).unwrap();
+        assert_eq!(
+            result.upstream_url.as_deref(),
+            Some("http://orders.invalid:8080/foo")
+        );
+        assert_eq!(result.applied_transforms[0], "coalesce identical requests");
+
+        let response = app
+            .oneshot(
+                Request::get("/admin/dry-run?method=B%20AD&path=/api")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+}
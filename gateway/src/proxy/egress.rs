@@ -0,0 +1,497 @@
+//! Upstream connections through an egress proxy.
+//!
+//! Upstreams only reachable through a corporate proxy are connected to
+//! through an HTTP `CONNECT` tunnel, optionally with Basic credentials, or
+//! through SOCKS5. [`EgressConfig`] sets a default proxy and overrides it
+//! by upstream name. The proxy is given the upstream's host name to
+//! resolve, and once the tunnel is up the connection carries plain HTTP or
+//! TLS exactly as a direct one would. The risk scanner connects the same
+//! way, so its view of an upstream matches the data path.
+//!
+//! Failures before the tunnel is up, including not reaching the proxy at
+//! all, are reported as [`EgressError::Proxy`] naming the proxy, and by
+//! the proxy service as [`ProxyError::EgressProxy`](super::ProxyError::EgressProxy).
+
+use base64::Engine;
+use http::Uri;
+use hyper_util::rt::TokioIo;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::future::Future;
+use std::io;
+use std::net::IpAddr;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use thiserror::Error;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+
+/// Longest `CONNECT` response head read from a proxy.
+const MAX_RESPONSE_HEAD: usize = 8 * 1024;
+
+/// Which egress proxy, if any, each upstream is reached through.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct EgressConfig {
+    /// Proxy for upstreams without an entry in `upstreams`. Direct when
+    /// unset.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub default: Option<EgressProxy>,
+    /// Proxy by upstream name, overriding `default`; `null` connects
+    /// directly.
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub upstreams: BTreeMap<String, Option<EgressProxy>>,
+}
+
+impl EgressConfig {
+    /// The proxy to reach the upstream called `upstream` through.
+    pub fn proxy_for(&self, upstream: &str) -> Option<&EgressProxy> {
+        match self.upstreams.get(upstream) {
+            Some(proxy) => proxy.as_ref(),
+            None => self.default.as_ref(),
+        }
+    }
+
+    /// Every configured proxy.
+    pub fn proxies(&self) -> impl Iterator<Item = &EgressProxy> {
+        self.default.iter().chain(self.upstreams.values().flatten())
+    }
+}
+
+/// An egress proxy.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct EgressProxy {
+    pub protocol: EgressProtocol,
+    pub host: String,
+    pub port: u16,
+    /// Sent as `Proxy-Authorization: Basic`. Only for `http_connect`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub credentials: Option<ProxyCredentials>,
+}
+
+impl fmt::Display for EgressProxy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", authority(&self.host, self.port))
+    }
+}
+
+/// How the gateway asks an [`EgressProxy`] for a connection.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EgressProtocol {
+    /// An HTTP `CONNECT` tunnel.
+    HttpConnect,
+    /// SOCKS5 without authentication.
+    Socks5,
+}
+
+/// Basic credentials for an HTTP `CONNECT` proxy. `Debug` does not reveal
+/// the password.
+#[derive(Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ProxyCredentials {
+    pub username: String,
+    pub password: String,
+}
+
+impl fmt::Debug for ProxyCredentials {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ProxyCredentials")
+            .field("username", &self.username)
+            .field("password", &"[REDACTED]")
+            .finish()
+    }
+}
+
+/// Why a connection to an upstream could not be made.
+#[derive(Debug, Error)]
+pub enum EgressError {
+    /// Connecting directly to the upstream failed.
+    #[error("{0}")]
+    Connect(io::Error),
+    /// The proxy could not be reached or did not open a tunnel.
+    #[error("egress proxy {proxy} failed: {reason}")]
+    Proxy { proxy: String, reason: String },
+}
+
+impl EgressError {
+    fn proxy(proxy: &EgressProxy, reason: impl fmt::Display) -> Self {
+        EgressError::Proxy {
+            proxy: proxy.to_string(),
+            reason: reason.to_string(),
+        }
+    }
+}
+
+/// A TCP connection to `host:port`, through `proxy` if there is one.
+pub async fn connect(
+    proxy: Option<&EgressProxy>,
+    host: &str,
+    port: u16,
+) -> Result<TcpStream, EgressError> {
+    let Some(proxy) = proxy else {
+        return TcpStream::connect((host, port))
+            .await
+            .map_err(EgressError::Connect);
+    };
+    let mut stream = TcpStream::connect((proxy.host.as_str(), proxy.port))
+        .await
+        .map_err(|e| EgressError::proxy(proxy, e))?;
+    let opened = match proxy.protocol {
+        EgressProtocol::HttpConnect => http_connect(&mut stream, proxy, host, port).await,
+        EgressProtocol::Socks5 => socks5_connect(&mut stream, host, port).await,
+    };
+    opened.map_err(|e| EgressError::proxy(proxy, e))?;
+    Ok(stream)
+}
+
+/// `host:port`, with IPv6 addresses bracketed.
+pub(crate) fn authority(host: &str, port: u16) -> String {
+    if host.contains(':') {
+        format!("[{host}]:{port}")
+    } else {
+        format!("{host}:{port}")
+    }
+}
+
+async fn http_connect(
+    stream: &mut TcpStream,
+    proxy: &EgressProxy,
+    host: &str,
+    port: u16,
+) -> io::Result<()> {
+    let target = authority(host, port);
+    let mut head = format!("CONNECT {target} HTTP/1.1\r\nHost: {target}\r\n");
+    if let Some(credentials) = &proxy.credentials {
+        let token = base64::engine::general_purpose::STANDARD
+            .encode(format!("{}:{}", credentials.username, credentials.password));
+        head.push_str(&format!("Proxy-Authorization: Basic {token}\r\n"));
+    }
+    head.push_str("\r\n");
+    stream.write_all(head.as_bytes()).await?;
+
+    // Read a byte at a time so nothing after the head is consumed.
+    let mut response = Vec::new();
+    while !response.ends_with(b"\r\n\r\n") {
+        if response.len() == MAX_RESPONSE_HEAD {
+            return Err(io::Error::other("CONNECT response head too long"));
+        }
+        response.push(stream.read_u8().await?);
+    }
+    let status = std::str::from_utf8(&response)
+        .ok()
+        .and_then(|head| head.split_whitespace().nth(1))
+        .and_then(|status| status.parse::<u16>().ok())
+        .ok_or_else(|| io::Error::other("malformed CONNECT response"))?;
+    match status {
+        200..=299 => Ok(()),
+        407 => Err(io::Error::other("proxy authentication required (407)")),
+        status => Err(io::Error::other(format!("CONNECT refused with {status}"))),
+    }
+}
+
+async fn socks5_connect(stream: &mut TcpStream, host: &str, port: u16) -> io::Result<()> {
+    // Offer only "no authentication".
+    stream.write_all(&[5, 1, 0]).await?;
+    let mut choice = [0; 2];
+    stream.read_exact(&mut choice).await?;
+    if choice != [5, 0] {
+        return Err(io::Error::other(
+            "no acceptable SOCKS5 authentication method",
+        ));
+    }
+
+    let mut request = vec![5, 1, 0];
+    match host.parse::<IpAddr>() {
+        Ok(IpAddr::V4(ip)) => {
+            request.push(1);
+            request.extend(ip.octets());
+        }
+        Ok(IpAddr::V6(ip)) => {
+            request.push(4);
+            request.extend(ip.octets());
+        }
+        Err(_) => {
+            let len = u8::try_from(host.len())
+                .map_err(|_| io::Error::other("host name too long for SOCKS5"))?;
+            request.extend([3, len]);
+            request.extend(host.as_bytes());
+        }
+    }
+    request.extend(port.to_be_bytes());
+    stream.write_all(&request).await?;
+
+    let mut reply = [0; 4];
+    stream.read_exact(&mut reply).await?;
+    if reply[1] != 0 {
+        return Err(io::Error::other(socks5_failure(reply[1])));
+    }
+    let address_len = match reply[3] {
+        1 => 4,
+        4 => 16,
+        3 => usize::from(stream.read_u8().await?),
+        atyp => {
+            return Err(io::Error::other(format!(
+                "unknown SOCKS5 address type {atyp}"
+            )))
+        }
+    };
+    // The bound address and port are of no use to us.
+    let mut bound = vec![0; address_len + 2];
+    stream.read_exact(&mut bound).await?;
+    Ok(())
+}
+
+fn socks5_failure(reply: u8) -> String {
+    let reason = match reply {
+        1 => "general failure",
+        2 => "connection not allowed by ruleset",
+        3 => "network unreachable",
+        4 => "host unreachable",
+        5 => "connection refused",
+        6 => "TTL expired",
+        7 => "command not supported",
+        8 => "address type not supported",
+        _ => return format!("SOCKS5 reply {reply}"),
+    };
+    format!("SOCKS5 {reason}")
+}
+
+/// Connects hyper's client to the host and port of each URI, through the
+/// egress proxy if there is one.
+#[derive(Debug, Clone)]
+pub(super) struct EgressConnector {
+    proxy: Option<EgressProxy>,
+}
+
+impl EgressConnector {
+    pub(super) fn new(proxy: Option<EgressProxy>) -> Self {
+        Self { proxy }
+    }
+}
+
+impl tower::Service<Uri> for EgressConnector {
+    type Response = TokioIo<TcpStream>;
+    type Error = EgressError;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, EgressError>> + Send>>;
+
+    fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), EgressError>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, uri: Uri) -> Self::Future {
+        let proxy = self.proxy.clone();
+        Box::pin(async move {
+            let host = uri
+                .host()
+                .unwrap_or_default()
+                .trim_start_matches('[')
+                .trim_end_matches(']');
+            let port = uri
+                .port_u16()
+                .unwrap_or(if uri.scheme_str() == Some("https") {
+                    443
+                } else {
+                    80
+                });
+            connect(proxy.as_ref(), host, port).await.map(TokioIo::new)
+        })
+    }
+}
+
+/// The [`EgressError`] behind `error`, if there is one.
+pub(super) fn egress_failure<'a>(
+    error: &'a (dyn std::error::Error + 'static),
+) -> Option<&'a EgressError> {
+    let mut source = Some(error);
+    while let Some(error) = source {
+        if let Some(egress) = error.downcast_ref::<EgressError>() {
+            return Some(egress);
+        }
+        source = error.source();
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::proxy::testing::{MockConnectProxy, MockUpstream};
+    use crate::proxy::ProxyError;
+    use axum::body::Body;
+    use http::{Request, StatusCode};
+    use std::sync::{Arc, Mutex};
+    use tokio::net::TcpListener;
+
+    /// A SOCKS5 proxy without authentication that records each target
+    /// and tunnels to it.
+    async fn socks5_proxy() -> (EgressProxy, Arc<Mutex<Vec<(String, u16)>>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let targets = Arc::new(Mutex::new(Vec::new()));
+        let recorded = targets.clone();
+        tokio::spawn(async move {
+            while let Ok((mut client, _)) = listener.accept().await {
+                let recorded = recorded.clone();
+                tokio::spawn(async move {
+                    let mut greeting = [0; 3];
+                    client.read_exact(&mut greeting).await?;
+                    client.write_all(&[5, 0]).await?;
+                    let mut request = [0; 4];
+                    client.read_exact(&mut request).await?;
+                    let host = match request[3] {
+                        1 => {
+                            let mut ip = [0; 4];
+                            client.read_exact(&mut ip).await?;
+                            IpAddr::from(ip).to_string()
+                        }
+                        _ => {
+                            let mut name = vec![0; usize::from(client.read_u8().await?)];
+                            client.read_exact(&mut name).await?;
+                            String::from_utf8(name).unwrap()
+                        }
+                    };
+                    let port = client.read_u16().await?;
+                    recorded.lock().unwrap().push((host.clone(), port));
+                    let mut upstream = TcpStream::connect((host.as_str(), port)).await?;
+                    client.write_all(&[5, 0, 0, 1, 0, 0, 0, 0, 0, 0]).await?;
+                    tokio::io::copy_bidirectional(&mut client, &mut upstream).await?;
+                    io::Result::Ok(())
+                });
+            }
+        });
+        let proxy = EgressProxy {
+            protocol: EgressProtocol::Socks5,
+            host: addr.ip().to_string(),
+            port: addr.port(),
+            credentials: None,
+        };
+        (proxy, targets)
+    }
+
+    fn credentials(username: &str, password: &str) -> Option<ProxyCredentials> {
+        Some(ProxyCredentials {
+            username: username.into(),
+            password: password.into(),
+        })
+    }
+
+    async fn get(service: &crate::proxy::ProxyService) -> Result<StatusCode, ProxyError> {
+        let route = service.find_route("/api/items").unwrap();
+        let req = Request::get("/api/items").body(Body::empty()).unwrap();
+        Ok(service.forward(&route, req).await?.status())
+    }
+
+    #[tokio::test]
+    async fn test_requests_tunnel_through_connect_proxy() {
+        let upstream = MockUpstream::start().await.unwrap();
+        let proxy = MockConnectProxy::start(Some(("gw", "s3cret")))
+            .await
+            .unwrap();
+        let service = upstream.proxy_service("/api").with_egress(EgressConfig {
+            default: Some(EgressProxy {
+                credentials: credentials("gw", "s3cret"),
+                ..proxy.egress_proxy()
+            }),
+            ..EgressConfig::default()
+        });
+
+        assert_eq!(get(&service).await.unwrap(), StatusCode::OK);
+        assert_eq!(upstream.requests().len(), 1);
+        let heads = proxy.requests();
+        assert_eq!(heads.len(), 1);
+        let target = format!("CONNECT {} HTTP/1.1\r\n", upstream.addr());
+        assert!(heads[0].starts_with(&target), "{}", heads[0]);
+        // "gw:s3cret"
+        assert!(
+            heads[0].contains("Proxy-Authorization: Basic Z3c6czNjcmV0\r\n"),
+            "{}",
+            heads[0]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_proxy_failures_name_the_proxy() {
+        let upstream = MockUpstream::start().await.unwrap();
+        let proxy = MockConnectProxy::start(Some(("gw", "s3cret")))
+            .await
+            .unwrap();
+        let closed = TcpListener::bind("127.0.0.1:0")
+            .await
+            .unwrap()
+            .local_addr()
+            .unwrap();
+        let cases = [
+            (
+                EgressProxy {
+                    credentials: credentials("gw", "wrong"),
+                    ..proxy.egress_proxy()
+                },
+                "407",
+            ),
+            (
+                EgressProxy {
+                    credentials: None,
+                    ..proxy.egress_proxy()
+                },
+                "407",
+            ),
+            (
+                EgressProxy {
+                    port: closed.port(),
+                    ..proxy.egress_proxy()
+                },
+                "refused",
+            ),
+        ];
+        for (egress, reason_contains) in cases {
+            let service = upstream.proxy_service("/api").with_egress(EgressConfig {
+                default: Some(egress.clone()),
+                ..EgressConfig::default()
+            });
+            match get(&service).await {
+                Err(ProxyError::EgressProxy { proxy, reason }) => {
+                    assert_eq!(proxy, egress.to_string());
+                    assert!(reason.contains(reason_contains), "{reason}");
+                }
+                other => panic!("expected an egress proxy error, got {other:?}"),
+            }
+        }
+        assert!(upstream.requests().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_per_upstream_proxy_overrides_default() {
+        let upstream = MockUpstream::start().await.unwrap();
+        let connect_proxy = MockConnectProxy::start(None).await.unwrap();
+        let (socks, targets) = socks5_proxy().await;
+        let egress = |proxy: Option<EgressProxy>| EgressConfig {
+            default: Some(connect_proxy.egress_proxy()),
+            upstreams: BTreeMap::from([("mock".to_string(), proxy)]),
+        };
+
+        let service = upstream
+            .proxy_service("/api")
+            .with_egress(egress(Some(socks)));
+        assert_eq!(get(&service).await.unwrap(), StatusCode::OK);
+        let addr = upstream.addr();
+        assert_eq!(
+            *targets.lock().unwrap(),
+            [(addr.ip().to_string(), addr.port())]
+        );
+
+        let service = upstream.proxy_service("/api").with_egress(egress(None));
+        assert_eq!(get(&service).await.unwrap(), StatusCode::OK);
+        assert_eq!(upstream.requests().len(), 2);
+        assert!(connect_proxy.requests().is_empty());
+    }
+
+    #[test]
+    fn test_credentials_are_redacted() {
+        let debug = format!("{:?}", credentials("gw", "s3cret").unwrap());
+        assert!(debug.contains("gw"));
+        assert!(!debug.contains("s3cret"));
+    }
+}
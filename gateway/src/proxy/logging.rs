@@ -0,0 +1,306 @@
+//! Detailed logging of upstream exchanges, behind the `debug_logging`
+//! feature.
+//!
+//! With a [`RequestResponseLogger`] installed through
+//! [`ProxyService::with_logger`](super::ProxyService::with_logger), every
+//! upstream request emits one `debug` event carrying the method, upstream
+//! URL, headers, status and round-trip latency. Credential headers
+//! ([`SENSITIVE_HEADERS`]) are redacted. With `log_bodies` set, the
+//! first `max_body_log_bytes` of each body are logged as hex; bodies are
+//! only read as far as the preview needs and are otherwise still streamed.
+
+use axum::body::{Body, Bytes};
+use http::header::{AUTHORIZATION, COOKIE, PROXY_AUTHORIZATION, SET_COOKIE};
+use http::{HeaderMap, HeaderName, Request, Response};
+use http_body_util::BodyExt;
+use hyper::body::{Frame, SizeHint};
+use std::collections::VecDeque;
+use std::fmt::Write;
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::Instant;
+use tracing::debug;
+
+use super::ProxyError;
+
+const REDACTED: &str = "[redacted]";
+
+/// Headers whose values are never logged.
+const SENSITIVE_HEADERS: [HeaderName; 5] = [
+    AUTHORIZATION,
+    PROXY_AUTHORIZATION,
+    COOKIE,
+    SET_COOKIE,
+    HeaderName::from_static("x-api-key"),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RequestResponseLogger {
+    log_bodies: bool,
+    max_body_log_bytes: usize,
+}
+
+impl RequestResponseLogger {
+    pub fn new(log_bodies: bool, max_body_log_bytes: usize) -> Self {
+        Self {
+            log_bodies,
+            max_body_log_bytes,
+        }
+    }
+
+    /// Send `req` with `send` and log the exchange.
+    pub(super) async fn exchange<F, Fut>(
+        &self,
+        req: Request<Body>,
+        send: F,
+    ) -> Result<Response<Body>, ProxyError>
+    where
+        F: FnOnce(Request<Body>) -> Fut,
+        Fut: Future<Output = Result<Response<Body>, ProxyError>>,
+    {
+        let (parts, body) = req.into_parts();
+        let (request_body, body) = self.preview(body).await?;
+        let method = parts.method.clone();
+        let url = parts.uri.to_string();
+        let request_headers = redacted(&parts.headers);
+
+        let started = Instant::now();
+        let result = send(Request::from_parts(parts, body)).await;
+        let latency_ms = started.elapsed().as_secs_f64() * 1000.0;
+
+        let response = match result {
+            Ok(response) => response,
+            Err(e) => {
+                debug!(
+                    %method,
+                    %url,
+                    request_headers,
+                    request_body = request_body.as_deref(),
+                    latency_ms,
+                    error = %e,
+                    "upstream exchange failed"
+                );
+                return Err(e);
+            }
+        };
+        let (parts, body) = response.into_parts();
+        let (response_body, body) = self.preview(body).await?;
+        debug!(
+            %method,
+            %url,
+            request_headers,
+            request_body = request_body.as_deref(),
+            status = parts.status.as_u16(),
+            response_headers = redacted(&parts.headers),
+            response_body = response_body.as_deref(),
+            latency_ms,
+            "upstream exchange"
+        );
+        Ok(Response::from_parts(parts, body))
+    }
+
+    /// Hex of the first `max_body_log_bytes` of `body`, if bodies are
+    /// logged, and a body yielding the same data as `body`.
+    async fn preview(&self, mut body: Body) -> Result<(Option<String>, Body), ProxyError> {
+        if !self.log_bodies {
+            return Ok((None, body));
+        }
+        let mut read = VecDeque::new();
+        let mut preview = Vec::new();
+        while preview.len() < self.max_body_log_bytes {
+            let Some(frame) = body.frame().await else {
+                break;
+            };
+            let frame = frame.map_err(|e| ProxyError::RequestError(e.to_string()))?;
+            if let Some(data) = frame.data_ref() {
+                let take = data.len().min(self.max_body_log_bytes - preview.len());
+                preview.extend_from_slice(&data[..take]);
+            }
+            read.push_back(frame);
+        }
+        let hex = preview.iter().fold(String::new(), |mut s, b| {
+            let _ = write!(s, "{b:02x}");
+            s
+        });
+        Ok((Some(hex), Body::new(Replay { read, rest: body })))
+    }
+}
+
+/// `headers` formatted for the log, with credentials redacted.
+fn redacted(headers: &HeaderMap) -> String {
+    let mut out = String::from("{");
+    for (i, (name, value)) in headers.iter().enumerate() {
+        if i > 0 {
+            out.push_str(", ");
+        }
+        let value = if SENSITIVE_HEADERS.contains(name) {
+            REDACTED
+        } else {
+            value.to_str().unwrap_or("<binary>")
+        };
+        let _ = write!(out, "{name}: {value}");
+    }
+    out.push('}');
+    out
+}
+
+/// Frames already read for a preview, followed by the rest of the body.
+struct Replay {
+    read: VecDeque<Frame<Bytes>>,
+    rest: Body,
+}
+
+impl hyper::body::Body for Replay {
+    type Data = Bytes;
+    type Error = axum::Error;
+
+    fn poll_frame(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Frame<Bytes>, Self::Error>>> {
+        match self.read.pop_front() {
+            Some(frame) => Poll::Ready(Some(Ok(frame))),
+            None => Pin::new(&mut self.rest).poll_frame(cx),
+        }
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.read.is_empty() && self.rest.is_end_stream()
+    }
+
+    fn size_hint(&self) -> SizeHint {
+        let read: u64 = self
+            .read
+            .iter()
+            .filter_map(|f| f.data_ref())
+            .map(|d| d.len() as u64)
+            .sum();
+        let rest = self.rest.size_hint();
+        let mut hint = SizeHint::new();
+        hint.set_lower(rest.lower() + read);
+        if let Some(upper) = rest.upper() {
+            hint.set_upper(upper + read);
+        }
+        hint
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::proxy::testing::{MockResponse, MockUpstream};
+    use http::header::CONTENT_TYPE;
+    use http::{HeaderValue, StatusCode};
+    use std::sync::{Arc, Mutex};
+
+    #[derive(Clone, Default)]
+    struct CapturedLogs(Arc<Mutex<Vec<u8>>>);
+
+    impl std::io::Write for CapturedLogs {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl CapturedLogs {
+        fn install() -> (Self, tracing::subscriber::DefaultGuard) {
+            let logs = Self::default();
+            let writer = logs.clone();
+            let subscriber = tracing_subscriber::fmt()
+                .with_ansi(false)
+                .with_max_level(tracing::Level::DEBUG)
+                .with_writer(move || writer.clone())
+                .finish();
+            (logs, tracing::subscriber::set_default(subscriber))
+        }
+
+        fn exchange_line(&self) -> String {
+            let output = String::from_utf8(self.0.lock().unwrap().clone()).unwrap();
+            output
+                .lines()
+                .find(|l| l.contains("upstream exchange"))
+                .expect("exchange logged")
+                .to_string()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_logs_exchange_with_redacted_authorization() {
+        let (logs, _guard) = CapturedLogs::install();
+        let mock = MockUpstream::start().await.unwrap();
+        mock.enqueue(
+            MockResponse::new(StatusCode::CREATED)
+                .with_header(CONTENT_TYPE, HeaderValue::from_static("text/plain"))
+                .with_body("created"),
+        );
+        let proxy = mock
+            .proxy_service("/api")
+            .with_logger(RequestResponseLogger::new(true, 4));
+        let route = mock.route("/api");
+        let req = Request::post("/api/items")
+            .header(AUTHORIZATION, "Bearer secret-token")
+            .body(Body::from("hello"))
+            .unwrap();
+
+        let response = proxy.forward(&route, req).await.unwrap();
+        assert_eq!(response.status(), StatusCode::CREATED);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        assert_eq!(&body[..], b"created");
+        assert_eq!(&mock.requests()[0].body[..], b"hello");
+
+        let line = logs.exchange_line();
+        assert!(line.contains("DEBUG"), "{line}");
+        assert!(line.contains("method=POST"), "{line}");
+        assert!(
+            line.contains(&format!("url=http://{}/items", mock.addr())),
+            "{line}"
+        );
+        assert!(line.contains("authorization: [redacted]"), "{line}");
+        assert!(!line.contains("secret-token"), "{line}");
+        assert!(line.contains("request_body=\"68656c6c\""), "{line}");
+        assert!(line.contains("status=201"), "{line}");
+        assert!(line.contains("content-type: text/plain"), "{line}");
+        assert!(line.contains("response_body=\"63726561\""), "{line}");
+        assert!(line.contains("latency_ms="), "{line}");
+    }
+
+    #[test]
+    fn test_credential_headers_are_redacted() {
+        let mut headers = HeaderMap::new();
+        headers.insert(PROXY_AUTHORIZATION, HeaderValue::from_static("Basic abc"));
+        headers.insert("x-api-key", HeaderValue::from_static("qsgw_k_secret"));
+        headers.insert(COOKIE, HeaderValue::from_static("session=secret"));
+        headers.insert(SET_COOKIE, HeaderValue::from_static("id=secret"));
+        headers.insert("x-request-id", HeaderValue::from_static("abc"));
+
+        assert_eq!(
+            redacted(&headers),
+            "{proxy-authorization: [redacted], x-api-key: [redacted], \
+             cookie: [redacted], set-cookie: [redacted], x-request-id: abc}"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_bodies_not_logged_unless_enabled() {
+        let (logs, _guard) = CapturedLogs::install();
+        let mock = MockUpstream::start().await.unwrap();
+        let proxy = mock
+            .proxy_service("/api")
+            .with_logger(RequestResponseLogger::new(false, 64));
+        let req = Request::get("/api/items").body(Body::empty()).unwrap();
+        proxy.forward(&mock.route("/api"), req).await.unwrap();
+
+        let line = logs.exchange_line();
+        assert!(line.contains("status=200"), "{line}");
+        assert!(!line.contains("request_body"), "{line}");
+        assert!(!line.contains("response_body"), "{line}");
+    }
+}
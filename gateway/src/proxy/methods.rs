@@ -0,0 +1,180 @@
+//! Per-route allow and deny lists of HTTP methods.
+//!
+//! A request is refused with `405 Method Not Allowed`, before anything is
+//! sent upstream, when its method is denied for every route (`TRACE` and
+//! `CONNECT` unless [`ProxyService::with_denied_methods`] says otherwise),
+//! is in the route's `denied_methods`, or is missing from a non-empty
+//! `allowed_methods`. The `Allow` header lists what the route permits;
+//! without an allow list, that is the standard methods not denied.
+//!
+//! CORS preflights (`OPTIONS` with `Origin` and
+//! `Access-Control-Request-Method`) are never refused here, whatever the
+//! lists say about `OPTIONS`. They are forwarded so that the upstream can
+//! answer them, and the actual request is checked when it follows.
+
+use axum::body::Body;
+use http::header::{ACCESS_CONTROL_REQUEST_METHOD, ORIGIN};
+use http::{Method, Request};
+use tracing::debug;
+
+use super::{ProxyError, ProxyService, Route};
+
+/// Methods refused on every route unless configured otherwise.
+pub const DEFAULT_DENIED_METHODS: [&str; 2] = ["TRACE", "CONNECT"];
+
+/// Methods listed in `Allow` for routes without an allow list.
+const STANDARD_METHODS: [&str; 9] = [
+    "GET", "HEAD", "POST", "PUT", "DELETE", "CONNECT", "OPTIONS", "TRACE", "PATCH",
+];
+
+/// Whether `req` is a CORS preflight.
+pub fn is_preflight(req: &Request<Body>) -> bool {
+    req.method() == Method::OPTIONS
+        && req.headers().contains_key(ORIGIN)
+        && req.headers().contains_key(ACCESS_CONTROL_REQUEST_METHOD)
+}
+
+impl ProxyService {
+    /// Refuse `req` if its method is not permitted on `route`.
+    pub(super) fn check_method(
+        &self,
+        route: &Route,
+        req: &Request<Body>,
+    ) -> Result<(), ProxyError> {
+        if is_preflight(req) || self.permits(route, req.method().as_str()) {
+            return Ok(());
+        }
+        debug!(route = %route.path_prefix, method = %req.method(), "method not allowed");
+        Err(ProxyError::MethodNotAllowed(self.allowed_methods(route)))
+    }
+
+    /// Whether `method` may be forwarded on `route`.
+    fn permits(&self, route: &Route, method: &str) -> bool {
+        let listed = |methods: &[String]| methods.iter().any(|m| m.eq_ignore_ascii_case(method));
+        !listed(&self.denied_methods)
+            && !listed(&route.denied_methods)
+            && (route.allowed_methods.is_empty() || listed(&route.allowed_methods))
+    }
+
+    /// The methods to list in `Allow` for `route`.
+    fn allowed_methods(&self, route: &Route) -> Vec<String> {
+        let candidates = if route.allowed_methods.is_empty() {
+            STANDARD_METHODS.map(String::from).to_vec()
+        } else {
+            route
+                .allowed_methods
+                .iter()
+                .map(|m| m.to_ascii_uppercase())
+                .collect()
+        };
+        let mut allowed: Vec<String> = Vec::new();
+        for method in candidates {
+            if self.permits(route, &method) && !allowed.contains(&method) {
+                allowed.push(method);
+            }
+        }
+        allowed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::proxy::testing::{MockResponse, MockUpstream};
+    use http::header::ALLOW;
+    use http::StatusCode;
+    use http_body_util::BodyExt;
+
+    async fn send(
+        proxy: &ProxyService,
+        route: &Route,
+        req: http::request::Builder,
+    ) -> (StatusCode, Option<String>) {
+        let response = match proxy.forward(route, req.body(Body::empty()).unwrap()).await {
+            Ok(response) => response,
+            Err(e) => axum::response::IntoResponse::into_response(e),
+        };
+        let status = response.status();
+        let allow = response
+            .headers()
+            .get(ALLOW)
+            .map(|v| v.to_str().unwrap().to_string());
+        let _ = response.into_body().collect().await.unwrap();
+        (status, allow)
+    }
+
+    #[tokio::test]
+    async fn test_disallowed_method_gets_405_with_allow() {
+        let mock = MockUpstream::start().await.unwrap();
+        mock.set_fallback(MockResponse::new(StatusCode::OK));
+        let proxy = mock.proxy_service("/api");
+        let mut route = mock.route("/api");
+        route.allowed_methods = vec!["get".into(), "POST".into(), "DELETE".into(), "TRACE".into()];
+        route.denied_methods = vec!["DELETE".into()];
+
+        for method in ["PUT", "DELETE", "TRACE"] {
+            let req = Request::builder().method(method).uri("/api/x");
+            assert_eq!(
+                send(&proxy, &route, req).await,
+                (StatusCode::METHOD_NOT_ALLOWED, Some("GET, POST".into())),
+                "{method}"
+            );
+        }
+        assert!(mock.requests().is_empty());
+
+        assert_eq!(
+            send(&proxy, &route, Request::get("/api/x")).await,
+            (StatusCode::OK, None)
+        );
+        assert_eq!(mock.requests().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_trace_and_connect_are_denied_by_default() {
+        let mock = MockUpstream::start().await.unwrap();
+        mock.set_fallback(MockResponse::new(StatusCode::OK));
+        let route = mock.route("/api");
+        let trace = || Request::builder().method("TRACE").uri("/api/x");
+
+        let proxy = mock.proxy_service("/api");
+        assert_eq!(
+            send(&proxy, &route, trace()).await,
+            (
+                StatusCode::METHOD_NOT_ALLOWED,
+                Some("GET, HEAD, POST, PUT, DELETE, OPTIONS, PATCH".into())
+            )
+        );
+        assert!(mock.requests().is_empty());
+
+        let proxy = mock.proxy_service("/api").with_denied_methods(Vec::new());
+        assert_eq!(send(&proxy, &route, trace()).await, (StatusCode::OK, None));
+        assert_eq!(mock.requests()[0].method, Method::TRACE);
+    }
+
+    #[tokio::test]
+    async fn test_cors_preflights_pass_a_route_without_options() {
+        let mock = MockUpstream::start().await.unwrap();
+        mock.set_fallback(MockResponse::new(StatusCode::NO_CONTENT));
+        let proxy = mock.proxy_service("/api");
+        let mut route = mock.route("/api");
+        route.allowed_methods = vec!["GET".into()];
+
+        let preflight = Request::builder()
+            .method("OPTIONS")
+            .uri("/api/x")
+            .header(ORIGIN, "https://app.example")
+            .header(ACCESS_CONTROL_REQUEST_METHOD, "GET");
+        assert_eq!(
+            send(&proxy, &route, preflight).await,
+            (StatusCode::NO_CONTENT, None)
+        );
+        assert_eq!(mock.requests().len(), 1);
+
+        let options = Request::builder().method("OPTIONS").uri("/api/x");
+        assert_eq!(
+            send(&proxy, &route, options).await,
+            (StatusCode::METHOD_NOT_ALLOWED, Some("GET".into()))
+        );
+        assert_eq!(mock.requests().len(), 1);
+    }
+}
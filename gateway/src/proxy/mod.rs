@@ -1,79 +1,815 @@
+pub mod cache;
+pub mod coalesce;
+#[cfg(feature = "k8s")]
+pub mod discovery;
+pub mod dry_run;
+pub mod egress;
+pub mod methods;
+#[cfg(feature = "debug_logging")]
+pub mod logging;
+pub mod reload;
+pub mod resolver;
+pub mod rewrite;
+pub mod sealed;
+pub mod slow_start;
+pub mod sse;
+#[cfg(any(test, feature = "testing"))]
+pub mod testing;
+pub mod transform;
+pub mod upstream_tls;
+
 use axum::body::Body;
-use http::{Request, Response, Uri};
+use axum::response::IntoResponse;
+use axum::Json;
+use http::{HeaderValue, Method, Request, Response, StatusCode, Uri};
 use hyper_util::client::legacy::Client;
 use hyper_util::rt::TokioExecutor;
 use serde::{Deserialize, Serialize};
-use std::time::Duration;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::future::Future;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant};
 use thiserror::Error;
 use tracing::{error, info};
 
-#[derive(Debug, Error)]
+use crate::metrics::{GatewayMetrics, UpstreamOutcome};
+use crate::tls::HandshakeInfo;
+use crate::tunnel::TunnelConnector;
+use crate::webhook::{GatewayEvent, WebhookNotifier};
+use cache::{CacheConfig, CacheRequest, ResponseCache};
+use coalesce::{CoalesceConfig, CoalesceKey, Coalescer};
+use egress::{EgressConfig, EgressError, EgressProxy};
+use quantun_types::ErrorCode;
+use rewrite::PathRewrite;
+use sealed::{UnsealConfig, Unsealer};
+use slow_start::{Balancer, SlowStartConfig, UpstreamWeight};
+use sse::SseFilter;
+use transform::{TransformConfig, Transformer};
+use upstream_tls::{UpstreamTls, UpstreamTlsConfig};
+use resolver::{DnsCache, Resolver, SystemResolver};
+
+pub use methods::DEFAULT_DENIED_METHODS;
+
+/// Default interval after which upstream host names are re-resolved.
+pub const DEFAULT_RESOLVE_INTERVAL_SECS: u64 = 30;
+
+#[derive(Debug, Clone, Error)]
 pub enum ProxyError {
     #[error("upstream connection failed: {0}")]
     ConnectionFailed(String),
     #[error("upstream timeout")]
     Timeout,
+    /// The egress proxy, rather than the upstream, failed.
+    #[error("egress proxy {proxy} failed: {reason}")]
+    EgressProxy { proxy: String, reason: String },
     #[error("no healthy upstream available")]
     NoHealthyUpstream,
     #[error("request error: {0}")]
     RequestError(String),
+    #[error("invalid route configuration: {0}")]
+    InvalidConfig(String),
+    /// Deliberately carries no detail; the cause is logged at debug level.
+    #[error("sealed request body could not be opened")]
+    SealedBody,
+    /// Deliberately carries no detail; the cause is logged at warn level.
+    #[error("response could not be sealed")]
+    SealedResponse,
+    /// Deliberately carries no detail; the cause is logged at warn level.
+    #[error("body transformation failed")]
+    Transform,
+    /// Carries the methods the route permits, for the `Allow` header.
+    #[error("method not allowed")]
+    MethodNotAllowed(Vec<String>),
+}
+
+impl IntoResponse for ProxyError {
+    /// A JSON error that names the failure without upstream detail.
+    fn into_response(self) -> axum::response::Response {
+        let allow = match &self {
+            ProxyError::MethodNotAllowed(allow) => Some(allow.join(", ")),
+            _ => None,
+        };
+        let (status, code, message) = match self {
+            ProxyError::ConnectionFailed(_) => (
+                StatusCode::BAD_GATEWAY,
+                ErrorCode::Internal,
+                "upstream connection failed",
+            ),
+            ProxyError::EgressProxy { .. } => (
+                StatusCode::BAD_GATEWAY,
+                ErrorCode::Internal,
+                "egress proxy connection failed",
+            ),
+            ProxyError::Timeout => (
+                StatusCode::GATEWAY_TIMEOUT,
+                ErrorCode::Internal,
+                "upstream timeout",
+            ),
+            ProxyError::NoHealthyUpstream => (
+                StatusCode::SERVICE_UNAVAILABLE,
+                ErrorCode::Internal,
+                "no healthy upstream available",
+            ),
+            ProxyError::RequestError(_) => (
+                StatusCode::BAD_REQUEST,
+                ErrorCode::InvalidArgument,
+                "invalid request",
+            ),
+            ProxyError::InvalidConfig(_) => (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                ErrorCode::Internal,
+                "internal server error",
+            ),
+            ProxyError::SealedBody => (
+                StatusCode::BAD_REQUEST,
+                ErrorCode::DecapsulationFailed,
+                "sealed request body could not be opened",
+            ),
+            ProxyError::SealedResponse => (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                ErrorCode::EncapsulationFailed,
+                "response could not be sealed",
+            ),
+            ProxyError::Transform => (
+                StatusCode::BAD_GATEWAY,
+                ErrorCode::Internal,
+                "body transformation failed",
+            ),
+            ProxyError::MethodNotAllowed(_) => (
+                StatusCode::METHOD_NOT_ALLOWED,
+                ErrorCode::PermissionDenied,
+                "method not allowed",
+            ),
+        };
+        let mut response = (
+            status,
+            Json(serde_json::json!({
+                "error_code": code.as_str(),
+                "message": message,
+            })),
+        )
+            .into_response();
+        if let Some(allow) = allow.and_then(|allow| HeaderValue::from_str(&allow).ok()) {
+            response.headers_mut().insert(http::header::ALLOW, allow);
+        }
+        response
+    }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Upstream {
     pub name: String,
     pub host: String,
     pub port: u16,
     pub is_healthy: bool,
     pub tls_verify: bool,
+    /// Connect over HTTPS, resuming TLS sessions as
+    /// [`ProxyService::with_upstream_tls`] allows. Off unless set.
+    #[serde(default)]
+    pub tls: bool,
+    /// Share of requests relative to the other upstreams discovered for
+    /// the same name; see [`slow_start`]. Defaults to 1.
+    #[serde(default = "default_weight")]
+    pub weight: u32,
+}
+
+fn default_weight() -> u32 {
+    1
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Route {
+    /// Requests whose path starts with this prefix are routed here. See
+    /// [`normalize_path_prefix`]; `/` matches every path.
     pub path_prefix: String,
     pub upstream: Upstream,
     pub strip_prefix: bool,
+    /// Rewrite the path sent upstream, after any prefix is stripped. See
+    /// [`rewrite`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub path_rewrite: Option<PathRewrite>,
     pub priority: i32,
+    /// Share upstream calls between concurrent identical GET and HEAD
+    /// requests. Off unless set.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub coalesce: Option<CoalesceConfig>,
+    /// Serve cacheable GET responses from memory. Off unless set.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cache: Option<CacheConfig>,
+    /// Forward through the tunnel to this peer gateway instead of
+    /// connecting to the upstream, whose host and port are still sent as
+    /// `Host`. See [`ProxyService::with_tunnel`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tunnel: Option<String>,
+    /// Open sealed request bodies before forwarding. Needs
+    /// [`ProxyService::with_unsealer`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub unseal: Option<UnsealConfig>,
+    /// Transform request and response bodies. Custom steps need
+    /// [`ProxyService::with_transformer`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub transform: Option<TransformConfig>,
+    /// Filter `text/event-stream` responses through the filter registered
+    /// under this name with [`ProxyService::with_sse_filter`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sse_filter: Option<String>,
+    /// Only forward requests with these methods, compared ignoring case.
+    /// All methods when empty. Others get `405 Method Not Allowed`; see
+    /// [`methods`].
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub allowed_methods: Vec<String>,
+    /// Never forward requests with these methods, on top of those denied
+    /// for every route by [`ProxyService::with_denied_methods`].
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub denied_methods: Vec<String>,
+    /// Only match requests with this `Content-Type`, compared without
+    /// parameters and ignoring case. A value ending in `/`, such as
+    /// `multipart/`, matches any subtype. Requests without the header never
+    /// match.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub content_type: Option<String>,
+    /// Authentication and rate limiting applied to the route by
+    /// [`build_router`](crate::build_router).
+    #[serde(default)]
+    pub middleware_profile: MiddlewareProfile,
+}
+
+/// Which per-route middleware a route runs behind; see
+/// [`profile_to_layer`](crate::profile_to_layer).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MiddlewareProfile {
+    /// Authentication and rate limiting.
+    #[default]
+    Default,
+    /// Rate limiting only, for health checks and other public routes.
+    NoAuth,
+    /// Authentication only.
+    NoRateLimit,
+    /// Authentication by a caller holding the
+    /// [`ADMIN_SCOPE`](crate::auth::ADMIN_SCOPE), and rate limiting.
+    /// Without an auth policy every request is refused.
+    AdminOnly,
+}
+
+impl MiddlewareProfile {
+    /// Whether callers must authenticate.
+    pub fn authenticates(self) -> bool {
+        self != MiddlewareProfile::NoAuth
+    }
+
+    /// Whether requests are rate limited.
+    pub fn rate_limits(self) -> bool {
+        self != MiddlewareProfile::NoRateLimit
+    }
+}
+
+impl Route {
+    /// Whether a request with `content_type` satisfies the route's
+    /// `content_type` matcher.
+    pub fn matches_content_type(&self, content_type: Option<&str>) -> bool {
+        let Some(matcher) = &self.content_type else {
+            return true;
+        };
+        let Some(content_type) = content_type else {
+            return false;
+        };
+        let media_type = content_type.split(';').next().unwrap_or_default().trim();
+        if matcher.ends_with('/') {
+            media_type
+                .get(..matcher.len())
+                .is_some_and(|prefix| prefix.eq_ignore_ascii_case(matcher))
+        } else {
+            media_type.eq_ignore_ascii_case(matcher)
+        }
+    }
+
+    /// What identifies the route within a route set.
+    pub(crate) fn key(&self) -> (&str, Option<&str>) {
+        (&self.path_prefix, self.content_type.as_deref())
+    }
+}
+
+/// The `X-Forwarded-Proto` sent upstream.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ForwardedProto {
+    /// `https` for requests that arrived over TLS (those carrying a
+    /// [`HandshakeInfo`]), `http` otherwise.
+    Auto,
+    #[default]
+    Https,
+    /// For plain-HTTP development setups.
+    Http,
+}
+
+impl ForwardedProto {
+    /// The scheme to report for `req`.
+    pub fn for_request<B>(self, req: &Request<B>) -> &'static str {
+        match self {
+            ForwardedProto::Auto if req.extensions().get::<HandshakeInfo>().is_some() => "https",
+            ForwardedProto::Auto | ForwardedProto::Http => "http",
+            ForwardedProto::Https => "https",
+        }
+    }
+}
+
+/// Time spent in [`ProxyService::forward`], attached to the response's
+/// extensions for the `Server-Timing` header.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UpstreamTiming(pub Duration);
+
+/// The route a response was forwarded through, attached to the response's
+/// extensions for the request log.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MatchedRoute {
+    pub path_prefix: String,
+    pub upstream: String,
+}
+
+/// `prefix` with a leading `/` and no trailing `/`, so `api/` becomes
+/// `/api`. `None` if `prefix` is empty. The prefix `/` is kept as is and
+/// matches every path.
+pub fn normalize_path_prefix(prefix: &str) -> Option<String> {
+    let prefix = prefix.trim();
+    if prefix.is_empty() {
+        return None;
+    }
+    let prefix = prefix.trim_start_matches('/').trim_end_matches('/');
+    Some(format!("/{prefix}"))
+}
+
+/// Apply [`normalize_path_prefix`] to each route's prefix, leaving empty
+/// prefixes for [`validate_routes`] to reject.
+pub fn normalize_routes(routes: &mut [Route]) {
+    for route in routes {
+        if let Some(prefix) = normalize_path_prefix(&route.path_prefix) {
+            route.path_prefix = prefix;
+        }
+    }
+}
+
+/// Check that every route has a non-empty path prefix, unique after
+/// [`normalize_path_prefix`] together with its content type matcher, and
+/// an addressable upstream. Routes sharing a prefix must share a
+/// middleware profile.
+pub fn validate_routes(routes: &[Route]) -> Result<(), ProxyError> {
+    let mut prefixes = HashSet::new();
+    let mut profiles = HashMap::new();
+    for route in routes {
+        let Some(prefix) = normalize_path_prefix(&route.path_prefix) else {
+            return Err(ProxyError::InvalidConfig(format!(
+                "route to upstream {:?} has an empty path prefix",
+                route.upstream.name
+            )));
+        };
+        if route
+            .content_type
+            .as_deref()
+            .is_some_and(|c| c.trim().is_empty())
+        {
+            return Err(ProxyError::InvalidConfig(format!(
+                "route {:?} has an empty content type",
+                route.path_prefix
+            )));
+        }
+        if !prefixes.insert((prefix.clone(), route.content_type.as_deref())) {
+            return Err(ProxyError::InvalidConfig(match &route.content_type {
+                Some(content_type) => format!(
+                    "duplicate path prefix {:?} for content type {content_type:?}",
+                    route.path_prefix
+                ),
+                None => format!("duplicate path prefix {:?}", route.path_prefix),
+            }));
+        }
+        let profile = *profiles.entry(prefix).or_insert(route.middleware_profile);
+        if profile != route.middleware_profile {
+            return Err(ProxyError::InvalidConfig(format!(
+                "routes for path prefix {:?} use different middleware profiles",
+                route.path_prefix
+            )));
+        }
+        if let Some(method) = route
+            .allowed_methods
+            .iter()
+            .chain(&route.denied_methods)
+            .find(|m| Method::from_bytes(m.as_bytes()).is_err())
+        {
+            return Err(ProxyError::InvalidConfig(format!(
+                "route {:?} lists an invalid method {method:?}",
+                route.path_prefix
+            )));
+        }
+        if route.upstream.host.is_empty() || route.upstream.port == 0 {
+            return Err(ProxyError::InvalidConfig(format!(
+                "upstream {:?} needs a host and a non-zero port",
+                route.upstream.name
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// Upstreams found at runtime for one upstream name.
+struct DiscoveredUpstreams {
+    upstreams: Arc<RwLock<Vec<Upstream>>>,
+    balancer: Mutex<Balancer>,
 }
 
 pub struct ProxyService {
-    routes: Vec<Route>,
+    routes: Arc<RwLock<Vec<Route>>>,
     timeout: Duration,
+    resolver: Arc<dyn Resolver>,
+    resolve_interval: Duration,
+    dns_cache: DnsCache,
+    coalescer: Coalescer,
+    cache: ResponseCache,
+    metrics: Option<Arc<GatewayMetrics>>,
+    tunnels: HashMap<String, Arc<TunnelConnector>>,
+    forwarded_proto: ForwardedProto,
+    unsealer: Option<Arc<Unsealer>>,
+    transformers: HashMap<String, Arc<dyn Transformer>>,
+    sse_filters: HashMap<String, Arc<dyn SseFilter>>,
+    denied_methods: Vec<String>,
+    discovered: HashMap<String, DiscoveredUpstreams>,
+    slow_start: Option<SlowStartConfig>,
+    notifier: Option<Arc<WebhookNotifier>>,
+    upstream_tls: UpstreamTls,
+    egress: EgressConfig,
+    #[cfg(feature = "debug_logging")]
+    logger: Option<logging::RequestResponseLogger>,
 }
 
 impl ProxyService {
-    pub fn new(routes: Vec<Route>, timeout_secs: u64) -> Self {
+    /// A proxy over `routes`, their prefixes normalized with
+    /// [`normalize_routes`].
+    pub fn new(mut routes: Vec<Route>, timeout_secs: u64) -> Self {
+        normalize_routes(&mut routes);
+        let resolver: Arc<dyn Resolver> = Arc::new(SystemResolver);
+        let resolve_interval = Duration::from_secs(DEFAULT_RESOLVE_INTERVAL_SECS);
         Self {
-            routes,
+            routes: Arc::new(RwLock::new(routes)),
             timeout: Duration::from_secs(timeout_secs),
+            dns_cache: DnsCache::new(resolver.clone(), resolve_interval),
+            resolver,
+            resolve_interval,
+            coalescer: Coalescer::default(),
+            cache: ResponseCache::default(),
+            metrics: None,
+            tunnels: HashMap::new(),
+            forwarded_proto: ForwardedProto::default(),
+            unsealer: None,
+            transformers: HashMap::new(),
+            sse_filters: HashMap::new(),
+            denied_methods: DEFAULT_DENIED_METHODS.map(String::from).to_vec(),
+            discovered: HashMap::new(),
+            slow_start: None,
+            notifier: None,
+            upstream_tls: UpstreamTls::new(&UpstreamTlsConfig::default()),
+            egress: EgressConfig::default(),
+            #[cfg(feature = "debug_logging")]
+            logger: None,
+        }
+    }
+
+    /// Set how often upstream host names are re-resolved. Zero re-resolves
+    /// on every request.
+    pub fn with_resolve_interval(mut self, resolve_interval_secs: u64) -> Self {
+        self.resolve_interval = Duration::from_secs(resolve_interval_secs);
+        self.dns_cache = DnsCache::new(self.resolver.clone(), self.resolve_interval);
+        self
+    }
+
+    /// Replace the resolver used for upstream host names.
+    pub fn with_resolver(mut self, resolver: Arc<dyn Resolver>) -> Self {
+        self.dns_cache = DnsCache::new(resolver.clone(), self.resolve_interval);
+        self.resolver = resolver;
+        self
+    }
+
+    /// Record per-upstream request outcomes in `metrics`.
+    pub fn with_metrics(mut self, metrics: Arc<GatewayMetrics>) -> Self {
+        self.metrics = Some(metrics);
+        self
+    }
+
+    /// Make `tunnel` available to routes whose `tunnel` names its peer.
+    pub fn with_tunnel(mut self, tunnel: Arc<TunnelConnector>) -> Self {
+        self.tunnels.insert(tunnel.peer().to_string(), tunnel);
+        self
+    }
+
+    /// Set how `X-Forwarded-Proto` is chosen, usually from
+    /// [`GatewayConfig::forwarded_proto`](crate::GatewayConfig::forwarded_proto).
+    pub fn with_forwarded_proto(mut self, forwarded_proto: ForwardedProto) -> Self {
+        self.forwarded_proto = forwarded_proto;
+        self
+    }
+
+    /// Open sealed bodies on routes with `unseal` set using `unsealer`.
+    pub fn with_unsealer(mut self, unsealer: Arc<Unsealer>) -> Self {
+        self.unsealer = Some(unsealer);
+        self
+    }
+
+    /// Make `transformer` available to routes with a
+    /// [`Custom`](transform::TransformStep::Custom) step naming it.
+    pub fn with_transformer(
+        mut self,
+        name: impl Into<String>,
+        transformer: Arc<dyn Transformer>,
+    ) -> Self {
+        self.transformers.insert(name.into(), transformer);
+        self
+    }
+
+    /// Make `filter` available to routes whose `sse_filter` names it.
+    pub fn with_sse_filter(mut self, name: impl Into<String>, filter: Arc<dyn SseFilter>) -> Self {
+        self.sse_filters.insert(name.into(), filter);
+        self
+    }
+
+    /// Refuse requests with these methods on every route, instead of
+    /// [`DEFAULT_DENIED_METHODS`]. An empty list lets them through.
+    pub fn with_denied_methods(mut self, methods: Vec<String>) -> Self {
+        self.denied_methods = methods;
+        self
+    }
+
+    /// Send requests for the upstream called `name` to the healthy entries
+    /// of `upstreams`, in proportion to their weights, as kept up to date
+    /// by [`discovery::K8sServiceDiscovery`] for example. While none are
+    /// healthy the route's own upstream address is used.
+    pub fn with_discovered_upstreams(
+        mut self,
+        name: impl Into<String>,
+        upstreams: Arc<RwLock<Vec<Upstream>>>,
+    ) -> Self {
+        self.discovered.insert(
+            name.into(),
+            DiscoveredUpstreams {
+                upstreams,
+                balancer: Mutex::new(Balancer::default()),
+            },
+        );
+        self
+    }
+
+    /// Ramp up the weight of discovered upstreams as they become healthy.
+    /// See [`slow_start`].
+    pub fn with_slow_start(mut self, config: SlowStartConfig) -> Self {
+        self.slow_start = Some(config);
+        self
+    }
+
+    /// Report route reloads, and discovered upstreams turning unhealthy or
+    /// healthy again, to `notifier`. Health changes are noticed when the
+    /// next request for the upstream's name is routed.
+    pub fn with_notifier(mut self, notifier: Arc<WebhookNotifier>) -> Self {
+        self.notifier = Some(notifier);
+        self
+    }
+
+    /// Set how TLS sessions with upstreams that have `tls` set are resumed.
+    /// See [`upstream_tls`].
+    pub fn with_upstream_tls(mut self, config: UpstreamTlsConfig) -> Self {
+        self.upstream_tls = UpstreamTls::new(&config);
+        self
+    }
+
+    /// Reach upstreams through the egress proxies `egress` names for them.
+    /// See [`egress`].
+    pub fn with_egress(mut self, egress: EgressConfig) -> Self {
+        self.egress = egress;
+        self
+    }
+
+    /// The egress proxy the upstream called `upstream` is reached through.
+    pub fn egress_proxy(&self, upstream: &str) -> Option<&EgressProxy> {
+        self.egress.proxy_for(upstream)
+    }
+
+    /// Log every upstream request and response at `debug` level.
+    #[cfg(feature = "debug_logging")]
+    pub fn with_logger(mut self, logger: logging::RequestResponseLogger) -> Self {
+        self.logger = Some(logger);
+        self
+    }
+
+    /// Resolve the address to connect to for `upstream`, load-balancing
+    /// across its discovered upstreams, if any, and its cached A/AAAA
+    /// records.
+    pub async fn resolve_upstream(&self, upstream: &Upstream) -> Result<SocketAddr, ProxyError> {
+        let discovered = self.discovered_upstream(&upstream.name);
+        let upstream = discovered.as_ref().unwrap_or(upstream);
+        self.dns_cache
+            .next_addr(&upstream.host, upstream.port)
+            .await
+            .map_err(|e| {
+                ProxyError::ConnectionFailed(format!(
+                    "failed to resolve {}: {e}",
+                    upstream.host
+                ))
+            })
+    }
+
+    /// Where to connect to for `upstream`: the address from
+    /// [`ProxyService::resolve_upstream`], or through an egress proxy the
+    /// host name for the proxy to resolve.
+    async fn upstream_authority(&self, upstream: &Upstream) -> Result<String, ProxyError> {
+        if self.egress_proxy(&upstream.name).is_none() {
+            return Ok(self.resolve_upstream(upstream).await?.to_string());
+        }
+        let discovered = self.discovered_upstream(&upstream.name);
+        let upstream = discovered.as_ref().unwrap_or(upstream);
+        Ok(egress::authority(&upstream.host, upstream.port))
+    }
+
+    /// The next healthy discovered upstream for `name`.
+    fn discovered_upstream(&self, name: &str) -> Option<Upstream> {
+        let discovered = self.discovered.get(name)?;
+        let upstreams = discovered.upstreams.read().unwrap();
+        let healthy: Vec<&Upstream> = upstreams.iter().filter(|u| u.is_healthy).collect();
+        let mut balancer = discovered.balancer.lock().unwrap();
+        if let Some(notifier) = &self.notifier {
+            for upstream in balancer.health_changes(&upstreams) {
+                notifier.notify(if upstream.is_healthy {
+                    GatewayEvent::upstream_restored(upstream)
+                } else {
+                    GatewayEvent::upstream_ejected(upstream, "marked unhealthy")
+                });
+            }
+        }
+        balancer
+            .pick(&healthy, self.slow_start.as_ref(), Instant::now())
+            .cloned()
+    }
+
+    /// The weights of every discovered upstream, by name.
+    pub fn upstream_weights(&self) -> Vec<UpstreamWeight> {
+        let now = Instant::now();
+        let mut groups: Vec<_> = self.discovered.iter().collect();
+        groups.sort_by_key(|(name, _)| *name);
+        let mut weights = Vec::new();
+        for (group, discovered) in groups {
+            let balancer = discovered.balancer.lock().unwrap();
+            for upstream in discovered.upstreams.read().unwrap().iter() {
+                let effective_weight = if upstream.is_healthy {
+                    balancer.effective_weight(upstream, self.slow_start.as_ref(), now)
+                } else {
+                    0.0
+                };
+                weights.push(UpstreamWeight {
+                    group: group.clone(),
+                    name: upstream.name.clone(),
+                    host: upstream.host.clone(),
+                    port: upstream.port,
+                    is_healthy: upstream.is_healthy,
+                    weight: upstream.weight,
+                    effective_weight,
+                });
+            }
         }
+        weights
     }
 
-    pub fn find_route(&self, path: &str) -> Option<&Route> {
+    /// The route for a request to `path` without a `Content-Type`.
+    pub fn find_route(&self, path: &str) -> Option<Route> {
+        self.find_route_for(path, None)
+    }
+
+    /// The highest-priority healthy route matching `path` and
+    /// `content_type`. At equal priority a route with a `content_type`
+    /// matcher wins over one without.
+    pub fn find_route_for(&self, path: &str, content_type: Option<&str>) -> Option<Route> {
         self.routes
+            .read()
+            .unwrap()
             .iter()
-            .filter(|r| path.starts_with(&r.path_prefix) && r.upstream.is_healthy)
-            .max_by_key(|r| r.priority)
+            .filter(|r| {
+                path.starts_with(&r.path_prefix)
+                    && r.upstream.is_healthy
+                    && r.matches_content_type(content_type)
+            })
+            .max_by_key(|r| (r.priority, r.content_type.is_some()))
+            .cloned()
     }
 
+    /// A snapshot of the current routes.
+    pub fn routes(&self) -> Vec<Route> {
+        self.routes.read().unwrap().clone()
+    }
+
+    /// Validate `routes` and swap them in for the current set. On error the
+    /// current routes are kept.
+    pub fn replace_routes(&self, routes: Vec<Route>) -> Result<reload::RouteDiff, ProxyError> {
+        reload::swap_routes(&self.routes, routes)
+    }
+
+    /// Forward `req` to the route's upstream, answering from the response
+    /// cache if the route caches and sharing the call with identical
+    /// in-flight requests if it coalesces. Requests with a method the route
+    /// does not permit are refused first. Sealed bodies on routes that
+    /// unseal are opened first and are never cached or coalesced. Bodies
+    /// are transformed, on routes with `transform` set, for each upstream
+    /// call, and event streams filtered on routes with `sse_filter` set.
     pub async fn forward(
         &self,
         route: &Route,
-        mut req: Request<Body>,
+        req: Request<Body>,
     ) -> Result<Response<Body>, ProxyError> {
-        let upstream_uri = self.build_upstream_uri(route, req.uri())?;
-        *req.uri_mut() = upstream_uri;
+        self.check_method(route, &req)?;
+        if let Some(config) = route.unseal.as_ref().filter(|_| sealed::is_sealed(&req)) {
+            let Some(unsealer) = &self.unsealer else {
+                error!(route = %route.path_prefix, "route unseals but no unsealer is configured");
+                return Err(ProxyError::SealedBody);
+            };
+            let (req, reply_key) = unsealer.unseal(config, req).await?;
+            let response = self.send_upstream(route, req).await?;
+            return if config.seal_response {
+                sealed::seal_response(config, &reply_key, response).await
+            } else {
+                Ok(response)
+            };
+        }
 
-        // Remove hop-by-hop headers
-        let headers = req.headers_mut();
-        headers.remove("host");
-        headers.remove("connection");
+        let cache = route
+            .cache
+            .as_ref()
+            .zip(CacheRequest::for_request(route, &req));
+        if let Some(cached) = cache.as_ref().and_then(|(_, request)| self.cache.get(request)) {
+            return Ok(cached);
+        }
+        let response = match (&route.coalesce, CoalesceKey::for_request(route, &req)) {
+            (Some(config), Some(key)) => {
+                self.coalescer
+                    .forward(key, config.max_body_bytes, req, |req| {
+                        self.send_upstream(route, req)
+                    })
+                    .await
+            }
+            _ => self.send_upstream(route, req).await,
+        }?;
+        match cache {
+            Some((config, request)) => self.cache.store(config, request, response).await,
+            None => Ok(response),
+        }
+    }
 
-        // Add forwarding headers
-        headers.insert(
-            "X-Forwarded-Proto",
-            "https".parse().unwrap(),
-        );
+    async fn send_upstream(
+        &self,
+        route: &Route,
+        req: Request<Body>,
+    ) -> Result<Response<Body>, ProxyError> {
+        let req = self.transform_request(route, req).await?;
+        let result = self.try_send_upstream(route, req).await;
+        if let Some(metrics) = &self.metrics {
+            let outcome = match &result {
+                Ok(response) => Some(UpstreamOutcome::Response(response.status().as_u16())),
+                Err(ProxyError::Timeout) => Some(UpstreamOutcome::Timeout),
+                Err(ProxyError::ConnectionFailed(_)) => Some(UpstreamOutcome::ConnectionFailed),
+                // The egress proxy failed, not the upstream.
+                Err(ProxyError::EgressProxy { .. }) => None,
+                // Rejected before anything was sent.
+                Err(_) => None,
+            };
+            if let Some(outcome) = outcome {
+                metrics.record_upstream(&route.upstream.name, outcome);
+            }
+        }
+        let response = self.transform_response(route, result?).await?;
+        self.filter_events(route, response)
+    }
+
+    async fn try_send_upstream(
+        &self,
+        route: &Route,
+        req: Request<Body>,
+    ) -> Result<Response<Body>, ProxyError> {
+        let started = Instant::now();
+        let mut response = match &route.tunnel {
+            Some(peer) => self.send_through_tunnel(route, peer, req).await?,
+            None => self.send_direct(route, req).await?,
+        };
+        response
+            .extensions_mut()
+            .insert(UpstreamTiming(started.elapsed()));
+        response.extensions_mut().insert(MatchedRoute {
+            path_prefix: route.path_prefix.clone(),
+            upstream: route.upstream.name.clone(),
+        });
+        Ok(response)
+    }
+
+    async fn send_direct(
+        &self,
+        route: &Route,
+        mut req: Request<Body>,
+    ) -> Result<Response<Body>, ProxyError> {
+        let authority = self.upstream_authority(&route.upstream).await?;
+        self.rewrite_request(route, authority, &mut req)?;
 
         info!(
             upstream = %route.upstream.name,
@@ -81,36 +817,135 @@ impl ProxyService {
             "forwarding request"
         );
 
-        let client = Client::builder(TokioExecutor::new()).build_http::<Body>();
+        let proxy = self.egress_proxy(&route.upstream.name).cloned();
+        let client = Client::builder(TokioExecutor::new())
+            .build::<_, Body>(self.upstream_tls.connector(&route.upstream, proxy));
 
-        let response = tokio::time::timeout(self.timeout, client.request(req))
-            .await
-            .map_err(|_| ProxyError::Timeout)?
-            .map_err(|e| {
-                error!(error = %e, "upstream request failed");
-                ProxyError::ConnectionFailed(e.to_string())
-            })?;
+        self.send_logged(req, |req| async move {
+            let response = tokio::time::timeout(self.timeout, client.request(req))
+                .await
+                .map_err(|_| ProxyError::Timeout)?
+                .map_err(|e| {
+                    if let Some(EgressError::Proxy { proxy, reason }) = egress::egress_failure(&e) {
+                        error!(%proxy, %reason, "egress proxy failed");
+                        return ProxyError::EgressProxy {
+                            proxy: proxy.clone(),
+                            reason: reason.clone(),
+                        };
+                    }
+                    error!(error = %e, "upstream request failed");
+                    ProxyError::ConnectionFailed(e.to_string())
+                })?;
+
+            // Map the hyper Incoming body to axum Body
+            let (parts, incoming) = response.into_parts();
+            Ok(Response::from_parts(parts, Body::new(incoming)))
+        })
+        .await
+    }
+
+    async fn send_through_tunnel(
+        &self,
+        route: &Route,
+        peer: &str,
+        mut req: Request<Body>,
+    ) -> Result<Response<Body>, ProxyError> {
+        let tunnel = self
+            .tunnels
+            .get(peer)
+            .ok_or_else(|| ProxyError::ConnectionFailed(format!("no tunnel to {peer}")))?;
+        let authority = format!("{}:{}", route.upstream.host, route.upstream.port);
+        self.rewrite_request(route, authority, &mut req)?;
 
-        // Map the hyper Incoming body to axum Body
-        let (parts, incoming) = response.into_parts();
-        let body = Body::new(incoming);
-        Ok(Response::from_parts(parts, body))
+        info!(
+            upstream = %route.upstream.name,
+            tunnel = %peer,
+            path = %req.uri(),
+            "forwarding request through tunnel"
+        );
+
+        self.send_logged(req, |req| async move {
+            tokio::time::timeout(self.timeout, tunnel.send(req))
+                .await
+                .map_err(|_| ProxyError::Timeout)?
+                .map_err(|e| {
+                    error!(error = %e, tunnel = %peer, "tunnel request failed");
+                    ProxyError::ConnectionFailed(e.to_string())
+                })
+        })
+        .await
+    }
+
+    /// Send the rewritten `req` with `send`, through the logger if one is
+    /// installed.
+    async fn send_logged<F, Fut>(
+        &self,
+        req: Request<Body>,
+        send: F,
+    ) -> Result<Response<Body>, ProxyError>
+    where
+        F: FnOnce(Request<Body>) -> Fut,
+        Fut: Future<Output = Result<Response<Body>, ProxyError>>,
+    {
+        #[cfg(feature = "debug_logging")]
+        if let Some(logger) = &self.logger {
+            return logger.exchange(req, send).await;
+        }
+        send(req).await
     }
 
-    fn build_upstream_uri(&self, route: &Route, original: &Uri) -> Result<Uri, ProxyError> {
+    /// Point `req` at `authority` and set the forwarding headers.
+    fn rewrite_request(
+        &self,
+        route: &Route,
+        authority: impl fmt::Display,
+        req: &mut Request<Body>,
+    ) -> Result<(), ProxyError> {
+        let upstream_uri = self.build_upstream_uri(route, authority, req.uri())?;
+        *req.uri_mut() = upstream_uri;
+        let proto = self.forwarded_proto.for_request(req);
+
+        // Remove hop-by-hop headers
+        let headers = req.headers_mut();
+        headers.remove("connection");
+
+        // Preserve the upstream's name for virtual hosting, since the URI
+        // may carry the resolved address.
+        let host = format!("{}:{}", route.upstream.host, route.upstream.port);
+        headers.insert(
+            "host",
+            HeaderValue::from_str(&host)
+                .map_err(|e| ProxyError::RequestError(e.to_string()))?,
+        );
+
+        // Add forwarding headers
+        headers.insert("X-Forwarded-Proto", HeaderValue::from_static(proto));
+        Ok(())
+    }
+
+    fn build_upstream_uri(
+        &self,
+        route: &Route,
+        authority: impl fmt::Display,
+        original: &Uri,
+    ) -> Result<Uri, ProxyError> {
         let path = if route.strip_prefix {
             original
                 .path()
-                .strip_prefix(&route.path_prefix)
+                .strip_prefix(route.path_prefix.trim_end_matches('/'))
                 .unwrap_or(original.path())
         } else {
             original.path()
         };
+        let path = match &route.path_rewrite {
+            Some(rewrite) => rewrite.apply(path).ok_or_else(|| {
+                ProxyError::RequestError(format!("path {path:?} rewrites to a relative path"))
+            })?,
+            None => path.into(),
+        };
 
-        let uri_string = format!(
-            "http://{}:{}{}",
-            route.upstream.host, route.upstream.port, path
-        );
+        let scheme = if route.upstream.tls { "https" } else { "http" };
+        let uri_string = format!("{scheme}://{authority}{path}");
 
         uri_string
             .parse::<Uri>()
@@ -129,6 +964,8 @@ mod tests {
             port: 8080,
             is_healthy: true,
             tls_verify: false,
+            tls: false,
+            weight: 1,
         }
     }
 
@@ -139,13 +976,35 @@ mod tests {
                 path_prefix: "/api".into(),
                 upstream: test_upstream(),
                 strip_prefix: false,
+                path_rewrite: None,
                 priority: 100,
+                coalesce: None,
+                cache: None,
+                tunnel: None,
+                unseal: None,
+                transform: None,
+                sse_filter: None,
+                allowed_methods: Vec::new(),
+                denied_methods: Vec::new(),
+                content_type: None,
+                middleware_profile: MiddlewareProfile::Default,
             },
             Route {
                 path_prefix: "/api/v2".into(),
                 upstream: test_upstream(),
                 strip_prefix: true,
+                path_rewrite: None,
                 priority: 200,
+                coalesce: None,
+                cache: None,
+                tunnel: None,
+                unseal: None,
+                transform: None,
+                sse_filter: None,
+                allowed_methods: Vec::new(),
+                denied_methods: Vec::new(),
+                content_type: None,
+                middleware_profile: MiddlewareProfile::Default,
             },
         ];
 
@@ -159,4 +1018,521 @@ mod tests {
 
         assert!(svc.find_route("/other").is_none());
     }
+
+    #[test]
+    fn test_path_prefixes_are_normalized() {
+        assert_eq!(normalize_path_prefix("api").as_deref(), Some("/api"));
+        assert_eq!(
+            normalize_path_prefix("/api/v2/").as_deref(),
+            Some("/api/v2")
+        );
+        assert_eq!(normalize_path_prefix("/").as_deref(), Some("/"));
+        assert_eq!(normalize_path_prefix(""), None);
+
+        let route = |prefix: &str| Route {
+            path_prefix: prefix.into(),
+            upstream: test_upstream(),
+            strip_prefix: true,
+            path_rewrite: None,
+            priority: 0,
+            coalesce: None,
+            cache: None,
+            tunnel: None,
+            unseal: None,
+            transform: None,
+            sse_filter: None,
+            allowed_methods: Vec::new(),
+            denied_methods: Vec::new(),
+            content_type: None,
+            middleware_profile: MiddlewareProfile::Default,
+        };
+        for prefix in ["/api/v2/", "/api/v2"] {
+            let svc = ProxyService::new(vec![route(prefix)], 30);
+            let found = svc.find_route("/api/v2/users").unwrap();
+            assert_eq!(found.path_prefix, "/api/v2");
+            let uri = svc
+                .build_upstream_uri(&found, "upstream:80", &"/api/v2/users".parse().unwrap())
+                .unwrap();
+            assert_eq!(uri, "http://upstream:80/users");
+            assert!(svc.find_route("/api/v1").is_none());
+        }
+        assert_eq!(
+            ProxyService::new(vec![route("api")], 30).routes()[0].path_prefix,
+            "/api"
+        );
+
+        let root = ProxyService::new(vec![route("/")], 30);
+        let found = root.find_route("/anything").unwrap();
+        let uri = root
+            .build_upstream_uri(&found, "upstream:80", &"/anything".parse().unwrap())
+            .unwrap();
+        assert_eq!(uri, "http://upstream:80/anything");
+
+        assert!(validate_routes(&[route("")]).is_err());
+        assert!(validate_routes(&[route("/api"), route("api/")]).is_err());
+    }
+
+    #[test]
+    fn test_path_rewrite_substitutes_captures() {
+        let route = Route {
+            path_prefix: "/v1".into(),
+            upstream: test_upstream(),
+            strip_prefix: false,
+            path_rewrite: Some(PathRewrite::new(r"^/v1/users/(\d+)", "/internal/u/$1").unwrap()),
+            priority: 0,
+            coalesce: None,
+            cache: None,
+            tunnel: None,
+            unseal: None,
+            transform: None,
+            sse_filter: None,
+            allowed_methods: Vec::new(),
+            denied_methods: Vec::new(),
+            content_type: None,
+            middleware_profile: MiddlewareProfile::Default,
+        };
+        let svc = ProxyService::new(vec![route.clone()], 30);
+        let uri = |path: &str| {
+            svc.build_upstream_uri(&route, "upstream:80", &path.parse().unwrap())
+                .unwrap()
+        };
+
+        assert_eq!(uri("/v1/users/42"), "http://upstream:80/internal/u/42");
+        assert_eq!(
+            uri("/v1/users/42/keys"),
+            "http://upstream:80/internal/u/42/keys"
+        );
+
+        // Applied after the prefix is stripped.
+        let stripped = Route {
+            strip_prefix: true,
+            path_rewrite: Some(PathRewrite::new(r"^/users/(?<id>\d+)$", "/u/${id}").unwrap()),
+            ..route.clone()
+        };
+        let uri = svc
+            .build_upstream_uri(&stripped, "upstream:80", &"/v1/users/7".parse().unwrap())
+            .unwrap();
+        assert_eq!(uri, "http://upstream:80/u/7");
+    }
+
+    #[test]
+    fn test_path_rewrite_passes_non_matching_paths_through() {
+        let route = crate::builder::RouteBuilder::new("/v1")
+            .upstream("test-svc", "127.0.0.1", 8080)
+            .path_rewrite(PathRewrite::new(r"^/v1/users/(\d+)", "/internal/u/$1").unwrap())
+            .build()
+            .unwrap();
+        let svc = ProxyService::new(vec![route.clone()], 30);
+        for path in ["/v1/users/me", "/v1/keys", "/v1/users"] {
+            let uri = svc
+                .build_upstream_uri(&route, "upstream:80", &path.parse().unwrap())
+                .unwrap();
+            assert_eq!(uri.path(), path);
+        }
+
+        let yaml = "pattern: ^/v1/users/(\\d+)\nreplacement: /internal/u/$1\n";
+        let parsed: PathRewrite = serde_yaml::from_str(yaml).unwrap();
+        assert_eq!(Some(parsed), route.path_rewrite);
+        assert!(serde_yaml::from_str::<PathRewrite>("pattern: '('\nreplacement: /\n").is_err());
+    }
+
+    #[test]
+    fn test_path_rewrite_cannot_change_the_authority() {
+        let route = |replacement: &str| {
+            crate::builder::RouteBuilder::new("/v1")
+                .upstream("test-svc", "127.0.0.1", 8080)
+                .path_rewrite(PathRewrite::new(r"^/v1/(.*)", replacement).unwrap())
+                .build()
+                .unwrap()
+        };
+        let svc = ProxyService::new(Vec::new(), 30);
+        let uri = |route: &Route, path: &str| {
+            svc.build_upstream_uri(route, "upstream:80", &path.parse().unwrap())
+        };
+
+        let relative = route("$1");
+        for path in ["/v1/@evil.example/x", "/v1/x"] {
+            assert!(matches!(
+                uri(&relative, path),
+                Err(ProxyError::RequestError(_))
+            ));
+        }
+        let absolute = uri(&route("/$1"), "/v1/@evil.example/x").unwrap();
+        assert_eq!(absolute.host(), Some("upstream"));
+        assert_eq!(absolute.path(), "/@evil.example/x");
+
+        for replacement in ["$1", "'@evil.example/x'", "internal/$1"] {
+            let yaml = format!("pattern: ^/v1/(.*)\nreplacement: {replacement}\n");
+            let parsed = serde_yaml::from_str::<PathRewrite>(&yaml);
+            assert!(parsed.is_err(), "{replacement}");
+        }
+    }
+
+    #[test]
+    fn test_find_route_by_content_type() {
+        let route = |name: &str, content_type: Option<&str>| Route {
+            path_prefix: "/files".into(),
+            upstream: Upstream {
+                name: name.into(),
+                ..test_upstream()
+            },
+            strip_prefix: false,
+            path_rewrite: None,
+            priority: 0,
+            coalesce: None,
+            cache: None,
+            tunnel: None,
+            unseal: None,
+            transform: None,
+            sse_filter: None,
+            allowed_methods: Vec::new(),
+            denied_methods: Vec::new(),
+            content_type: content_type.map(Into::into),
+            middleware_profile: MiddlewareProfile::Default,
+        };
+        let svc = ProxyService::new(
+            vec![
+                route("api", None),
+                route("uploads", Some("multipart/")),
+                route("images", Some("image/png")),
+            ],
+            30,
+        );
+
+        let upstream = |content_type| {
+            svc.find_route_for("/files/a", content_type)
+                .unwrap()
+                .upstream
+                .name
+        };
+        assert_eq!(upstream(Some("multipart/form-data; boundary=x")), "uploads");
+        assert_eq!(upstream(Some("Multipart/Mixed")), "uploads");
+        assert_eq!(upstream(Some("application/json")), "api");
+        assert_eq!(upstream(Some("image/PNG")), "images");
+        assert_eq!(upstream(Some("image/pngx")), "api");
+        assert_eq!(upstream(None), "api");
+        assert_eq!(svc.find_route("/files/a").unwrap().upstream.name, "api");
+
+        // Without a catch-all route, unmatched content types find nothing.
+        svc.replace_routes(vec![route("uploads", Some("multipart/"))])
+            .unwrap();
+        assert!(svc
+            .find_route_for("/files/a", Some("application/json"))
+            .is_none());
+        assert!(svc.find_route("/files/a").is_none());
+
+        for invalid in [
+            vec![
+                route("a", Some("multipart/")),
+                route("b", Some("multipart/")),
+            ],
+            vec![route("a", Some(" "))],
+        ] {
+            assert!(matches!(
+                svc.replace_routes(invalid),
+                Err(ProxyError::InvalidConfig(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn test_replace_routes_validates() {
+        let route = |prefix: &str| Route {
+            path_prefix: prefix.into(),
+            upstream: test_upstream(),
+            strip_prefix: false,
+            path_rewrite: None,
+            priority: 0,
+            coalesce: None,
+            cache: None,
+            tunnel: None,
+            unseal: None,
+            transform: None,
+            sse_filter: None,
+            allowed_methods: Vec::new(),
+            denied_methods: Vec::new(),
+            content_type: None,
+            middleware_profile: MiddlewareProfile::Default,
+        };
+        let svc = ProxyService::new(vec![route("/api")], 30);
+
+        for invalid in [
+            vec![route("")],
+            vec![route("/a"), route("/a/")],
+            vec![Route {
+                upstream: Upstream {
+                    port: 0,
+                    ..test_upstream()
+                },
+                ..route("/a")
+            }],
+        ] {
+            assert!(matches!(
+                svc.replace_routes(invalid),
+                Err(ProxyError::InvalidConfig(_))
+            ));
+        }
+        assert_eq!(svc.routes(), [route("/api")]);
+
+        svc.replace_routes(vec![route("/v2")]).unwrap();
+        assert!(svc.find_route("/api/x").is_none());
+        assert!(svc.find_route("/v2/x").is_some());
+    }
+
+    #[tokio::test]
+    async fn test_upstream_counters() {
+        use crate::proxy::testing::{MockResponse, MockUpstream};
+        use http::StatusCode;
+        use tower::ServiceExt;
+
+        let healthy = MockUpstream::start().await.unwrap();
+        let failing = MockUpstream::start().await.unwrap();
+        failing.set_fallback(MockResponse::new(StatusCode::BAD_GATEWAY));
+        let closed = std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap();
+        let routes = vec![
+            Route {
+                upstream: healthy.upstream("healthy"),
+                ..healthy.route("/ok")
+            },
+            Route {
+                upstream: failing.upstream("failing"),
+                ..failing.route("/fail")
+            },
+            Route {
+                upstream: Upstream {
+                    name: "down".into(),
+                    host: closed.ip().to_string(),
+                    port: closed.port(),
+                    is_healthy: true,
+                    tls_verify: false,
+                    tls: false,
+                    weight: 1,
+                },
+                ..healthy.route("/down")
+            },
+        ];
+        let metrics = Arc::new(GatewayMetrics::default());
+        let svc = ProxyService::new(routes, 5).with_metrics(metrics.clone());
+
+        for path in ["/ok/a", "/ok/b", "/fail/a", "/down/a"] {
+            let route = svc.find_route(path).unwrap();
+            let req = Request::get(path).body(Body::empty()).unwrap();
+            let _ = svc.forward(&route, req).await;
+        }
+
+        let stats = metrics.upstream_stats();
+        let by_name = |name: &str| stats.iter().find(|s| s.upstream == name).unwrap();
+        let healthy = by_name("healthy");
+        assert_eq!(
+            (healthy.requests, healthy.successes, healthy.server_errors),
+            (2, 2, 0)
+        );
+        let failing = by_name("failing");
+        assert_eq!(
+            (failing.requests, failing.successes, failing.server_errors),
+            (1, 0, 1)
+        );
+        let down = by_name("down");
+        assert_eq!(
+            (down.requests, down.successes, down.connection_failures),
+            (1, 0, 1)
+        );
+
+        let app = crate::build_router_with_metrics(&crate::GatewayConfig::default(), metrics);
+        let response = app
+            .oneshot(Request::get("/gateway/stats").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        let upstreams = json["upstreams"].as_array().unwrap();
+        assert_eq!(upstreams.len(), 3);
+        assert_eq!(upstreams[1]["upstream"], "failing");
+        assert_eq!(upstreams[1]["server_errors"], 1);
+    }
+
+    #[tokio::test]
+    async fn test_discovered_upstreams_round_robin() {
+        use crate::proxy::testing::MockUpstream;
+
+        let first = MockUpstream::start().await.unwrap();
+        let second = MockUpstream::start().await.unwrap();
+        let closed = std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap();
+        let route = Route {
+            upstream: Upstream {
+                name: "api".into(),
+                host: closed.ip().to_string(),
+                port: closed.port(),
+                is_healthy: true,
+                tls_verify: false,
+                tls: false,
+                weight: 1,
+            },
+            ..first.route("/api")
+        };
+        let discovered = Arc::new(RwLock::new(Vec::new()));
+        let svc = ProxyService::new(vec![route.clone()], 5)
+            .with_discovered_upstreams("api", discovered.clone());
+
+        let req = || Request::get("/api/x").body(Body::empty()).unwrap();
+        assert!(matches!(
+            svc.forward(&route, req()).await,
+            Err(ProxyError::ConnectionFailed(_))
+        ));
+
+        *discovered.write().unwrap() = vec![first.upstream("api"), second.upstream("api")];
+        for _ in 0..4 {
+            svc.forward(&route, req()).await.unwrap();
+        }
+        assert_eq!(first.requests().len(), 2);
+        assert_eq!(second.requests().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_upstream_weights_show_slow_start() {
+        use crate::proxy::testing::MockUpstream;
+
+        let mock = MockUpstream::start().await.unwrap();
+        let route = Route {
+            upstream: mock.upstream("api"),
+            ..mock.route("/api")
+        };
+        let discovered = Arc::new(RwLock::new(vec![
+            Upstream {
+                weight: 4,
+                ..mock.upstream("api")
+            },
+            Upstream {
+                port: 1,
+                is_healthy: false,
+                ..mock.upstream("api")
+            },
+        ]));
+        let svc = ProxyService::new(vec![route.clone()], 5)
+            .with_discovered_upstreams("api", discovered)
+            .with_slow_start(SlowStartConfig {
+                window_secs: 3600,
+                min_fraction: 0.25,
+                ..SlowStartConfig::default()
+            });
+
+        let req = Request::get("/api/x").body(Body::empty()).unwrap();
+        svc.forward(&route, req).await.unwrap();
+        let weights = svc.upstream_weights();
+        assert_eq!(weights.len(), 2);
+        assert_eq!((weights[0].weight, weights[1].weight), (4, 1));
+        assert!((weights[0].effective_weight - 1.0).abs() < 0.01, "{weights:?}");
+        assert!(!weights[1].is_healthy);
+        assert_eq!(weights[1].effective_weight, 0.0);
+    }
+
+    #[tokio::test]
+    async fn test_discovered_upstream_health_changes_are_notified() {
+        use crate::proxy::testing::MockUpstream;
+        use crate::webhook::{WebhookConfig, WebhookSigner};
+        use quantun_crypto::SecureBytes;
+
+        let mock = MockUpstream::start().await.unwrap();
+        let receiver = MockUpstream::start().await.unwrap();
+        let notifier = WebhookNotifier::new(
+            WebhookConfig {
+                endpoints: vec![format!("http://{}/hooks", receiver.addr())],
+                ..WebhookConfig::default()
+            },
+            WebhookSigner::Hmac(SecureBytes::new(b"secret".to_vec())),
+        )
+        .unwrap();
+        let route = Route {
+            upstream: mock.upstream("api"),
+            ..mock.route("/api")
+        };
+        let discovered = Arc::new(RwLock::new(vec![
+            mock.upstream("api"),
+            Upstream {
+                port: 1,
+                ..mock.upstream("api")
+            },
+        ]));
+        let svc = ProxyService::new(vec![route.clone()], 5)
+            .with_discovered_upstreams("api", discovered.clone())
+            .with_notifier(Arc::new(notifier));
+        let forward = || {
+            let req = Request::get("/api/x").body(Body::empty()).unwrap();
+            svc.forward(&route, req)
+        };
+
+        forward().await.unwrap();
+        discovered.write().unwrap()[1].is_healthy = false;
+        forward().await.unwrap();
+        discovered.write().unwrap()[1].is_healthy = true;
+        let _ = forward().await;
+
+        let events = tokio::time::timeout(Duration::from_secs(5), async {
+            loop {
+                let mut events: Vec<serde_json::Value> = receiver
+                    .requests()
+                    .iter()
+                    .map(|r| serde_json::from_slice(&r.body).unwrap())
+                    .collect();
+                if events.len() == 2 {
+                    events.sort_by_key(|e| e["event"].to_string());
+                    return events;
+                }
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .expect("both events delivered");
+        let upstream = "api (127.0.0.1:1)";
+        assert_eq!(events[0]["event"], "upstream_ejected");
+        assert_eq!(events[0]["upstream"], upstream);
+        assert_eq!(events[0]["reason"], "marked unhealthy");
+        assert_eq!(events[1]["event"], "upstream_restored");
+        assert_eq!(events[1]["upstream"], upstream);
+    }
+
+    #[tokio::test]
+    async fn test_forwarded_proto() {
+        use crate::proxy::testing::MockUpstream;
+
+        let mock = MockUpstream::start().await.unwrap();
+        let forwarded_proto = |mode: ForwardedProto, tls: bool| {
+            let svc = mock.proxy_service("/api").with_forwarded_proto(mode);
+            let route = mock.route("/api");
+            async move {
+                let mut req = Request::get("/api/x").body(Body::empty()).unwrap();
+                if tls {
+                    req.extensions_mut().insert(HandshakeInfo {
+                        cipher_suite: "TLS_AES_256_GCM_SHA384".into(),
+                        tls_version: "TLSv1.3".into(),
+                        kem_algorithm: None,
+                        sig_algorithm: None,
+                        is_pqc: false,
+                        handshake_duration_ms: 1,
+                    });
+                }
+                svc.forward(&route, req).await.unwrap();
+            }
+        };
+
+        forwarded_proto(ForwardedProto::Https, false).await;
+        forwarded_proto(ForwardedProto::Http, true).await;
+        forwarded_proto(ForwardedProto::Auto, false).await;
+        forwarded_proto(ForwardedProto::Auto, true).await;
+
+        let sent: Vec<_> = mock
+            .requests()
+            .iter()
+            .map(|r| r.headers["x-forwarded-proto"].to_str().unwrap().to_string())
+            .collect();
+        assert_eq!(sent, ["https", "http", "http", "https"]);
+    }
 }
@@ -0,0 +1,260 @@
+//! Hot reload of proxy routes from a JSON file.
+//!
+//! [`ProxyService::watch_config_file`] loads a JSON array of [`Route`]s and
+//! swaps in the new set whenever the file changes. An invalid file is logged
+//! and ignored, leaving the current routes in place.
+
+use notify::{Event, RecursiveMode, Watcher};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+use tracing::{error, info};
+
+use super::{normalize_routes, validate_routes, ProxyError, ProxyService, Route};
+
+/// Quiet period after the last file event before the routes are reloaded.
+pub const RELOAD_DEBOUNCE: Duration = Duration::from_millis(100);
+
+/// Route changes made by a reload, keyed by path prefix and content type.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct RouteDiff {
+    pub added: usize,
+    pub removed: usize,
+    pub changed: usize,
+}
+
+impl RouteDiff {
+    pub fn between(old: &[Route], new: &[Route]) -> Self {
+        let old: HashMap<_, _> = old.iter().map(|r| (r.key(), r)).collect();
+        let mut diff = Self::default();
+        for route in new {
+            match old.get(&route.key()) {
+                None => diff.added += 1,
+                Some(previous) if *previous != route => diff.changed += 1,
+                Some(_) => {}
+            }
+        }
+        diff.removed = old.len() + diff.added - new.len();
+        diff
+    }
+}
+
+/// Read and parse a JSON routes file.
+pub fn load_routes(path: &Path) -> Result<Vec<Route>, ProxyError> {
+    let contents = std::fs::read(path).map_err(|e| {
+        ProxyError::InvalidConfig(format!("failed to read {}: {e}", path.display()))
+    })?;
+    serde_json::from_slice(&contents)
+        .map_err(|e| ProxyError::InvalidConfig(format!("failed to parse {}: {e}", path.display())))
+}
+
+pub(super) fn swap_routes(
+    current: &RwLock<Vec<Route>>,
+    mut routes: Vec<Route>,
+) -> Result<RouteDiff, ProxyError> {
+    validate_routes(&routes)?;
+    normalize_routes(&mut routes);
+    let mut current = current.write().unwrap();
+    let diff = RouteDiff::between(&current, &routes);
+    *current = routes;
+    Ok(diff)
+}
+
+impl ProxyService {
+    /// Load routes from the JSON file at `path`, then reload them whenever
+    /// the file changes.
+    ///
+    /// Fails if the file cannot be loaded initially or watched. Later
+    /// reloads that fail keep the current routes and log an error; those
+    /// that succeed are reported to the notifier, if any. Aborting the
+    /// returned task stops watching.
+    pub fn watch_config_file(&self, path: PathBuf) -> Result<JoinHandle<()>, ProxyError> {
+        let diff = self.replace_routes(load_routes(&path)?)?;
+        info!(path = %path.display(), routes = diff.added, "loaded routes");
+
+        let file_name = path
+            .file_name()
+            .ok_or_else(|| ProxyError::InvalidConfig(format!("{} is not a file", path.display())))?
+            .to_owned();
+        // Watch the directory so editors that replace the file by rename
+        // are still seen.
+        let dir = match path.parent() {
+            Some(dir) if !dir.as_os_str().is_empty() => dir.to_path_buf(),
+            _ => PathBuf::from("."),
+        };
+
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let mut watcher = notify::recommended_watcher(move |event: notify::Result<Event>| {
+            let Ok(event) = event else { return };
+            let relevant = !event.kind.is_access()
+                && event
+                    .paths
+                    .iter()
+                    .any(|p| p.file_name() == Some(file_name.as_os_str()));
+            if relevant {
+                let _ = tx.send(());
+            }
+        })
+        .map_err(|e| ProxyError::InvalidConfig(format!("failed to watch routes: {e}")))?;
+        watcher
+            .watch(&dir, RecursiveMode::NonRecursive)
+            .map_err(|e| {
+                ProxyError::InvalidConfig(format!("failed to watch {}: {e}", dir.display()))
+            })?;
+
+        let routes = self.routes.clone();
+        let notifier = self.notifier.clone();
+        Ok(tokio::spawn(async move {
+            let _watcher = watcher;
+            while rx.recv().await.is_some() {
+                loop {
+                    match tokio::time::timeout(RELOAD_DEBOUNCE, rx.recv()).await {
+                        Ok(Some(())) => continue,
+                        Ok(None) => return,
+                        Err(_) => break,
+                    }
+                }
+                match load_routes(&path).and_then(|new| swap_routes(&routes, new)) {
+                    Ok(diff) => {
+                        info!(
+                            path = %path.display(),
+                            added = diff.added,
+                            removed = diff.removed,
+                            changed = diff.changed,
+                            "reloaded routes"
+                        );
+                        if let Some(notifier) = &notifier {
+                            notifier.notify(diff.into());
+                        }
+                    }
+                    Err(e) => error!(
+                        path = %path.display(),
+                        error = %e,
+                        "route reload failed, keeping current routes"
+                    ),
+                }
+            }
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::proxy::testing::{MockResponse, MockUpstream};
+    use crate::proxy::{MiddlewareProfile, Upstream};
+    use axum::body::Body;
+    use http::Request;
+    use quantun_crypto::testing::TempDir;
+    use std::time::Instant;
+
+    fn write_routes(path: &Path, routes: &[Route]) {
+        std::fs::write(path, serde_json::to_vec(routes).unwrap()).unwrap();
+    }
+
+    async fn wait_for(svc: &ProxyService, path: &str) -> Route {
+        let deadline = Instant::now() + Duration::from_secs(5);
+        loop {
+            if let Some(route) = svc.find_route(path) {
+                return route;
+            }
+            assert!(
+                Instant::now() < deadline,
+                "no route for {path} after reload"
+            );
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+    }
+
+    async fn get(svc: &ProxyService, path: &str) -> String {
+        let route = svc.find_route(path).unwrap();
+        let req = Request::get(path).body(Body::empty()).unwrap();
+        let response = svc.forward(&route, req).await.unwrap();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        String::from_utf8(body.to_vec()).unwrap()
+    }
+
+    #[test]
+    fn diff_counts_by_prefix() {
+        let route = |prefix: &str, priority| Route {
+            priority,
+            path_prefix: prefix.into(),
+            upstream: Upstream {
+                name: "svc".into(),
+                host: "127.0.0.1".into(),
+                port: 80,
+                is_healthy: true,
+                tls_verify: false,
+                tls: false,
+                weight: 1,
+            },
+            strip_prefix: false,
+            path_rewrite: None,
+            coalesce: None,
+            cache: None,
+            tunnel: None,
+            unseal: None,
+            transform: None,
+            sse_filter: None,
+            allowed_methods: Vec::new(),
+            denied_methods: Vec::new(),
+            content_type: None,
+            middleware_profile: MiddlewareProfile::Default,
+        };
+        let old = [route("/a", 0), route("/b", 0), route("/c", 0)];
+        let new = [route("/a", 0), route("/b", 1), route("/d", 0)];
+        assert_eq!(
+            RouteDiff::between(&old, &new),
+            RouteDiff {
+                added: 1,
+                removed: 1,
+                changed: 1,
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn reloads_routes_when_file_changes() {
+        let first = MockUpstream::start().await.unwrap();
+        first.set_fallback(MockResponse::default().with_body("first"));
+        let second = MockUpstream::start().await.unwrap();
+        second.set_fallback(MockResponse::default().with_body("second"));
+
+        let dir = TempDir::new("routes");
+        let path = dir.join("routes.json");
+        write_routes(&path, &[first.route("/v1")]);
+
+        let svc = ProxyService::new(Vec::new(), 5);
+        let watcher = svc.watch_config_file(path.clone()).unwrap();
+        assert_eq!(get(&svc, "/v1/items").await, "first");
+
+        write_routes(&path, &[second.route("/v2")]);
+        wait_for(&svc, "/v2/items").await;
+        assert!(svc.find_route("/v1/items").is_none());
+        assert_eq!(get(&svc, "/v2/items").await, "second");
+
+        // A broken file leaves the current routes in place.
+        std::fs::write(&path, b"[{").unwrap();
+        tokio::time::sleep(RELOAD_DEBOUNCE * 3).await;
+        assert_eq!(svc.routes(), [second.route("/v2")]);
+
+        watcher.abort();
+    }
+
+    #[test]
+    fn initial_load_must_succeed() {
+        let dir = TempDir::new("routes");
+        let path = dir.join("routes.json");
+        let svc = ProxyService::new(Vec::new(), 5);
+        assert!(matches!(
+            svc.watch_config_file(path),
+            Err(ProxyError::InvalidConfig(_))
+        ));
+    }
+}
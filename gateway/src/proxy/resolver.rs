@@ -0,0 +1,163 @@
+use std::collections::HashMap;
+use std::future::Future;
+use std::io;
+use std::net::{IpAddr, SocketAddr};
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Boxed future returned by [`Resolver::resolve`].
+pub type ResolveFuture<'a> = Pin<Box<dyn Future<Output = io::Result<Vec<SocketAddr>>> + Send + 'a>>;
+
+/// Resolves an upstream host name to its A/AAAA records.
+pub trait Resolver: Send + Sync {
+    fn resolve<'a>(&'a self, host: &'a str, port: u16) -> ResolveFuture<'a>;
+}
+
+/// Resolver backed by the system's `getaddrinfo`.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct SystemResolver;
+
+impl Resolver for SystemResolver {
+    fn resolve<'a>(&'a self, host: &'a str, port: u16) -> ResolveFuture<'a> {
+        Box::pin(async move { Ok(tokio::net::lookup_host((host, port)).await?.collect()) })
+    }
+}
+
+struct CacheEntry {
+    addrs: Vec<SocketAddr>,
+    resolved_at: Instant,
+    next: usize,
+}
+
+/// Per-host cache of resolved addresses, refreshed every `interval` and
+/// handed out round-robin.
+pub struct DnsCache {
+    resolver: Arc<dyn Resolver>,
+    interval: Duration,
+    entries: Mutex<HashMap<(String, u16), CacheEntry>>,
+}
+
+impl DnsCache {
+    pub fn new(resolver: Arc<dyn Resolver>, interval: Duration) -> Self {
+        Self {
+            resolver,
+            interval,
+            entries: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Return the next address for `host:port`, re-resolving if the cached
+    /// records are older than the refresh interval.
+    ///
+    /// IP literals are returned as-is without touching the resolver.
+    pub async fn next_addr(&self, host: &str, port: u16) -> io::Result<SocketAddr> {
+        if let Ok(ip) = host.parse::<IpAddr>() {
+            return Ok(SocketAddr::new(ip, port));
+        }
+
+        let key = (host.to_string(), port);
+        if let Some(addr) = self.pick(&key, false) {
+            return Ok(addr);
+        }
+
+        let addrs = self.resolver.resolve(host, port).await?;
+        if addrs.is_empty() {
+            return Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("no addresses found for {host}"),
+            ));
+        }
+
+        let mut entries = self.entries.lock().unwrap();
+        let next = entries.get(&key).map_or(0, |e| e.next);
+        entries.insert(
+            key.clone(),
+            CacheEntry {
+                addrs,
+                resolved_at: Instant::now(),
+                next,
+            },
+        );
+        drop(entries);
+
+        self.pick(&key, true)
+            .ok_or_else(|| io::Error::other("resolver cache entry vanished"))
+    }
+
+    fn pick(&self, key: &(String, u16), allow_stale: bool) -> Option<SocketAddr> {
+        let mut entries = self.entries.lock().unwrap();
+        let entry = entries.get_mut(key)?;
+        if !allow_stale && entry.resolved_at.elapsed() >= self.interval {
+            return None;
+        }
+        let addr = entry.addrs[entry.next % entry.addrs.len()];
+        entry.next = entry.next.wrapping_add(1);
+        Some(addr)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    struct StubResolver {
+        addrs: Vec<SocketAddr>,
+        calls: AtomicUsize,
+    }
+
+    impl Resolver for StubResolver {
+        fn resolve<'a>(&'a self, _host: &'a str, _port: u16) -> ResolveFuture<'a> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            let addrs = self.addrs.clone();
+            Box::pin(async move { Ok(addrs) })
+        }
+    }
+
+    fn stub() -> Arc<StubResolver> {
+        Arc::new(StubResolver {
+            addrs: vec![
+                "10.0.0.1:8080".parse().unwrap(),
+                "10.0.0.2:8080".parse().unwrap(),
+            ],
+            calls: AtomicUsize::new(0),
+        })
+    }
+
+    #[tokio::test]
+    async fn round_robins_across_resolved_addresses() {
+        let resolver = stub();
+        let cache = DnsCache::new(resolver.clone(), Duration::from_secs(60));
+
+        let mut seen = HashSet::new();
+        for _ in 0..4 {
+            seen.insert(cache.next_addr("svc.internal", 8080).await.unwrap());
+        }
+
+        assert_eq!(seen.len(), 2);
+        assert_eq!(resolver.calls.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn re_resolves_after_interval() {
+        let resolver = stub();
+        let cache = DnsCache::new(resolver.clone(), Duration::ZERO);
+
+        cache.next_addr("svc.internal", 8080).await.unwrap();
+        cache.next_addr("svc.internal", 8080).await.unwrap();
+
+        assert_eq!(resolver.calls.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn ip_literals_bypass_resolver() {
+        let resolver = stub();
+        let cache = DnsCache::new(resolver.clone(), Duration::from_secs(60));
+
+        let addr = cache.next_addr("127.0.0.1", 9000).await.unwrap();
+        assert_eq!(addr, "127.0.0.1:9000".parse().unwrap());
+        assert_eq!(resolver.calls.load(Ordering::SeqCst), 0);
+    }
+}
@@ -0,0 +1,79 @@
+//! Regex rewriting of the path sent upstream.
+//!
+//! On a route with `path_rewrite` set, the path left after prefix
+//! handling has the first match of `pattern` replaced by `replacement`,
+//! in which `$1` or `${name}` stand for the captured groups. Patterns are
+//! not anchored unless they say so, and a path that does not match is
+//! forwarded unrewritten.
+//!
+//! The rewritten path is placed straight after the upstream's authority,
+//! so it must start with `/`: otherwise it would be read as part of the
+//! authority, e.g. `@host/x` as userinfo for another host. Replacements
+//! that do not start with `/` are rejected when the route is loaded, and
+//! a request whose path still rewrites to one is refused.
+
+use regex::Regex;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::borrow::Cow;
+
+/// Rewrites the upstream path of a route; see [`Route::path_rewrite`](super::Route::path_rewrite).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PathRewrite {
+    #[serde(
+        serialize_with = "serialize_pattern",
+        deserialize_with = "deserialize_pattern"
+    )]
+    pub pattern: Regex,
+    #[serde(deserialize_with = "deserialize_replacement")]
+    pub replacement: String,
+}
+
+impl PathRewrite {
+    /// Rewrite `pattern` matches with `replacement`, which may refer to
+    /// captured groups.
+    pub fn new(pattern: &str, replacement: impl Into<String>) -> Result<Self, regex::Error> {
+        Ok(Self {
+            pattern: Regex::new(pattern)?,
+            replacement: replacement.into(),
+        })
+    }
+
+    /// `path` with the first match of the pattern replaced, or unchanged if
+    /// there is none. `None` if the rewritten path does not start with `/`.
+    pub fn apply<'a>(&self, path: &'a str) -> Option<Cow<'a, str>> {
+        match self.pattern.replace(path, self.replacement.as_str()) {
+            // No match.
+            Cow::Borrowed(path) => Some(Cow::Borrowed(path)),
+            Cow::Owned(path) if path.starts_with('/') => Some(Cow::Owned(path)),
+            Cow::Owned(_) => None,
+        }
+    }
+}
+
+impl PartialEq for PathRewrite {
+    fn eq(&self, other: &Self) -> bool {
+        self.pattern.as_str() == other.pattern.as_str() && self.replacement == other.replacement
+    }
+}
+
+impl Eq for PathRewrite {}
+
+fn serialize_pattern<S: Serializer>(pattern: &Regex, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(pattern.as_str())
+}
+
+fn deserialize_pattern<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Regex, D::Error> {
+    let pattern = String::deserialize(deserializer)?;
+    Regex::new(&pattern).map_err(serde::de::Error::custom)
+}
+
+fn deserialize_replacement<'de, D: Deserializer<'de>>(deserializer: D) -> Result<String, D::Error> {
+    let replacement = String::deserialize(deserializer)?;
+    if !replacement.starts_with('/') {
+        return Err(serde::de::Error::custom(format!(
+            "path rewrite replacement {replacement:?} must start with '/'"
+        )));
+    }
+    Ok(replacement)
+}
@@ -0,0 +1,438 @@
+//! Sealed request bodies for upstreams that only understand plaintext.
+//!
+//! A client whose path to the gateway crosses untrusted networks seals the
+//! body to the gateway's hybrid KEM key with
+//! [`Envelope::seal_with_reply_key`], using `"{method} {path}"` of the
+//! request as the AAD, and sends the [`SealedEnvelope::to_bytes`] encoding
+//! as [`SEALED_CONTENT_TYPE`]. On a route with an [`UnsealConfig`], the
+//! gateway opens it with its keystore key and forwards the plaintext under
+//! the content type from [`SEALED_CONTENT_TYPE_HEADER`]. With
+//! [`UnsealConfig::seal_response`] the upstream's response is sealed back
+//! under the envelope's [`ReplyKey`].
+//!
+//! Each envelope is accepted once: the [`Unsealer`] remembers the last
+//! [`DEFAULT_REPLAY_CACHE_SIZE`] envelopes opened under its current key and
+//! rejects a repeat. Every failure to open is reported as
+//! [`ProxyError::SealedBody`], and every failure to seal a response as
+//! [`ProxyError::SealedResponse`], without detail.
+
+use axum::body::Body;
+use http::header::{CONTENT_LENGTH, CONTENT_TYPE, TRANSFER_ENCODING};
+use http::{HeaderValue, Request, Response};
+use quantun_crypto::envelope::{Envelope, ReplyKey, SealedEnvelope};
+use quantun_crypto::{KeyHandle, KeyStore, SecureBytes};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{HashSet, VecDeque};
+use std::sync::{Arc, Mutex, RwLock};
+use tracing::{debug, warn};
+
+use super::ProxyError;
+use crate::audit::caller_from_request;
+
+/// Content type of a sealed body.
+pub const SEALED_CONTENT_TYPE: &str = "application/qsgw-sealed";
+
+/// Content type of the plaintext inside a sealed body, sent alongside it.
+/// The upstream gets `application/json` if a sealed request omits it.
+pub const SEALED_CONTENT_TYPE_HEADER: &str = "x-qsgw-sealed-content-type";
+
+/// Default limit on the size of an unsealed request or sealed response.
+pub const DEFAULT_MAX_PLAINTEXT_BYTES: usize = 1024 * 1024;
+
+/// Envelopes remembered per key to reject replays.
+pub const DEFAULT_REPLAY_CACHE_SIZE: usize = 65_536;
+
+/// Envelope bytes around the plaintext, with room for ML-KEM-1024.
+const MAX_ENVELOPE_OVERHEAD: usize = 1 + 32 + 4 + 1568 + 32 + 16;
+
+/// Opt-in unsealing for a route.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UnsealConfig {
+    /// Largest plaintext accepted in a sealed request, and largest
+    /// response body sealed on the way back.
+    #[serde(default = "default_max_plaintext_bytes")]
+    pub max_plaintext_bytes: usize,
+    /// Seal the upstream's response to sealed requests.
+    #[serde(default)]
+    pub seal_response: bool,
+}
+
+fn default_max_plaintext_bytes() -> usize {
+    DEFAULT_MAX_PLAINTEXT_BYTES
+}
+
+impl Default for UnsealConfig {
+    fn default() -> Self {
+        Self {
+            max_plaintext_bytes: DEFAULT_MAX_PLAINTEXT_BYTES,
+            seal_response: false,
+        }
+    }
+}
+
+/// Opens sealed bodies with a keystore-held hybrid KEM key.
+pub struct Unsealer {
+    keystore: Arc<KeyStore>,
+    key: RwLock<KeyHandle>,
+    seen: Mutex<SeenEnvelopes>,
+}
+
+impl Unsealer {
+    /// Open with `key`, a hybrid KEM key with the `KeyAgreement` usage.
+    pub fn new(keystore: Arc<KeyStore>, key: KeyHandle) -> Self {
+        Self {
+            keystore,
+            key: RwLock::new(key),
+            seen: Mutex::new(SeenEnvelopes::new(DEFAULT_REPLAY_CACHE_SIZE)),
+        }
+    }
+
+    /// Remember the last `envelopes` envelopes instead of
+    /// [`DEFAULT_REPLAY_CACHE_SIZE`].
+    pub fn with_replay_cache_size(self, envelopes: usize) -> Self {
+        *self.seen.lock().unwrap() = SeenEnvelopes::new(envelopes);
+        self
+    }
+
+    /// Switch to a new key, e.g. after a rotation. Envelopes sealed to the
+    /// old key no longer open, so the replay cache starts over.
+    pub fn set_key(&self, key: KeyHandle) {
+        *self.key.write().unwrap() = key;
+        self.seen.lock().unwrap().clear();
+    }
+
+    /// Replace the sealed body of `req` with its plaintext, returning the
+    /// key for sealing the reply.
+    pub(super) async fn unseal(
+        &self,
+        config: &UnsealConfig,
+        req: Request<Body>,
+    ) -> Result<(Request<Body>, ReplyKey), ProxyError> {
+        let (mut parts, body) = req.into_parts();
+        let limit = config.max_plaintext_bytes + MAX_ENVELOPE_OVERHEAD;
+        let sealed = axum::body::to_bytes(body, limit)
+            .await
+            .map_err(|e| rejected(format!("reading sealed body: {e}")))?;
+
+        let envelope = SealedEnvelope::from_bytes(&sealed).map_err(rejected)?;
+        let handle = self.key.read().unwrap().clone();
+        let caller = caller_from_request(&Request::from_parts(parts.clone(), ()));
+        let shared_secret = self
+            .keystore
+            .decapsulate_as(&caller, &handle, &envelope.kem_ciphertext())
+            .map(SecureBytes::new)
+            .map_err(rejected)?;
+        let aad = format!("{} {}", parts.method, parts.uri.path());
+        let plaintext =
+            Envelope::open_with_shared_secret(shared_secret.as_bytes(), &envelope, aad.as_bytes())
+                .map_err(rejected)?;
+        if plaintext.len() > config.max_plaintext_bytes {
+            return Err(rejected(format!(
+                "plaintext of {} bytes exceeds {}",
+                plaintext.len(),
+                config.max_plaintext_bytes
+            )));
+        }
+        let digest = Sha256::digest(envelope.kem_ciphertext()).into();
+        if !self.seen.lock().unwrap().insert(digest) {
+            return Err(rejected("envelope already opened"));
+        }
+
+        let content_type = parts
+            .headers
+            .remove(SEALED_CONTENT_TYPE_HEADER)
+            .unwrap_or_else(|| HeaderValue::from_static("application/json"));
+        parts.headers.insert(CONTENT_TYPE, content_type);
+        parts.headers.remove(TRANSFER_ENCODING);
+        parts
+            .headers
+            .insert(CONTENT_LENGTH, HeaderValue::from(plaintext.len()));
+        let reply_key = ReplyKey::from_shared_secret(shared_secret.as_bytes());
+        Ok((Request::from_parts(parts, Body::from(plaintext)), reply_key))
+    }
+}
+
+/// Envelope digests in the order they were opened, oldest evicted first.
+struct SeenEnvelopes {
+    order: VecDeque<[u8; 32]>,
+    digests: HashSet<[u8; 32]>,
+    capacity: usize,
+}
+
+impl SeenEnvelopes {
+    fn new(capacity: usize) -> Self {
+        Self {
+            order: VecDeque::new(),
+            digests: HashSet::new(),
+            capacity,
+        }
+    }
+
+    /// Record `digest`, returning false if it was already seen.
+    fn insert(&mut self, digest: [u8; 32]) -> bool {
+        if !self.digests.insert(digest) {
+            return false;
+        }
+        self.order.push_back(digest);
+        if self.order.len() > self.capacity {
+            if let Some(oldest) = self.order.pop_front() {
+                self.digests.remove(&oldest);
+            }
+        }
+        true
+    }
+
+    fn clear(&mut self) {
+        self.order.clear();
+        self.digests.clear();
+    }
+}
+
+/// Whether `req` carries a sealed body.
+pub(super) fn is_sealed(req: &Request<Body>) -> bool {
+    req.headers()
+        .get(CONTENT_TYPE)
+        .is_some_and(|v| v.as_bytes() == SEALED_CONTENT_TYPE.as_bytes())
+}
+
+/// Seal the body of `response` under `reply_key`.
+pub(super) async fn seal_response(
+    config: &UnsealConfig,
+    reply_key: &ReplyKey,
+    response: Response<Body>,
+) -> Result<Response<Body>, ProxyError> {
+    let (mut parts, body) = response.into_parts();
+    let body = axum::body::to_bytes(body, config.max_plaintext_bytes)
+        .await
+        .map_err(|e| not_sealed(format!("reading upstream response: {e}")))?;
+    let sealed = reply_key.seal(&body).map_err(not_sealed)?;
+
+    if let Some(content_type) = parts.headers.remove(CONTENT_TYPE) {
+        parts
+            .headers
+            .insert(SEALED_CONTENT_TYPE_HEADER, content_type);
+    }
+    parts
+        .headers
+        .insert(CONTENT_TYPE, HeaderValue::from_static(SEALED_CONTENT_TYPE));
+    parts.headers.remove(TRANSFER_ENCODING);
+    parts
+        .headers
+        .insert(CONTENT_LENGTH, HeaderValue::from(sealed.len()));
+    Ok(Response::from_parts(parts, Body::from(sealed)))
+}
+
+fn rejected(reason: impl std::fmt::Display) -> ProxyError {
+    debug!(%reason, "sealed request body rejected");
+    ProxyError::SealedBody
+}
+
+fn not_sealed(reason: impl std::fmt::Display) -> ProxyError {
+    warn!(%reason, "response to sealed request could not be sealed");
+    ProxyError::SealedResponse
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::proxy::testing::{MockResponse, MockUpstream};
+    use crate::proxy::{MiddlewareProfile, ProxyService, Route};
+    use crate::GatewayConfig;
+    use axum::response::IntoResponse;
+    use http::StatusCode;
+    use quantun_crypto::hybrid::HybridKemKeyPair;
+    use quantun_types::{Algorithm, HybridVariant, KeyUsage};
+
+    struct Fixture {
+        mock: MockUpstream,
+        proxy: ProxyService,
+        route: Route,
+        public: HybridKemKeyPair,
+    }
+
+    async fn fixture(config: UnsealConfig) -> Fixture {
+        let keystore = Arc::new(KeyStore::new());
+        let key = keystore
+            .create(
+                Algorithm::Hybrid(HybridVariant::X25519MlKem768),
+                [KeyUsage::KeyAgreement],
+            )
+            .unwrap();
+        let public =
+            HybridKemKeyPair::from_public_key(&keystore.get_public(&key).unwrap()).unwrap();
+
+        let mock = MockUpstream::start().await.unwrap();
+        let route = Route {
+            unseal: Some(config),
+            transform: None,
+            sse_filter: None,
+            allowed_methods: Vec::new(),
+            denied_methods: Vec::new(),
+            content_type: None,
+            middleware_profile: MiddlewareProfile::Default,
+            ..mock.route("/api")
+        };
+        let proxy = GatewayConfig {
+            routes: vec![route.clone()],
+            unsealer: Some(Arc::new(Unsealer::new(keystore, key))),
+            ..GatewayConfig::default()
+        }
+        .proxy_service();
+        Fixture {
+            mock,
+            proxy,
+            route,
+            public,
+        }
+    }
+
+    fn sealed_request(body: Vec<u8>) -> Request<Body> {
+        Request::post("/api/orders")
+            .header(CONTENT_TYPE, SEALED_CONTENT_TYPE)
+            .header(SEALED_CONTENT_TYPE_HEADER, "application/json")
+            .body(Body::from(body))
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn upstream_sees_plaintext_and_reply_is_sealed() {
+        let f = fixture(UnsealConfig {
+            seal_response: true,
+            ..UnsealConfig::default()
+        })
+        .await;
+        f.mock.set_fallback(
+            MockResponse::new(StatusCode::CREATED)
+                .with_header(CONTENT_TYPE, HeaderValue::from_static("application/json"))
+                .with_body(r#"{"id":7}"#),
+        );
+
+        let (envelope, reply_key) =
+            Envelope::seal_with_reply_key(&f.public, br#"{"qty":2}"#, b"POST /api/orders").unwrap();
+        let response = f
+            .proxy
+            .forward(&f.route, sealed_request(envelope.to_bytes()))
+            .await
+            .unwrap();
+
+        let seen = &f.mock.requests()[0];
+        assert_eq!(seen.body, br#"{"qty":2}"#.as_slice());
+        assert_eq!(seen.headers[CONTENT_TYPE], "application/json");
+        assert!(seen.headers.get(SEALED_CONTENT_TYPE_HEADER).is_none());
+
+        assert_eq!(response.status(), StatusCode::CREATED);
+        assert_eq!(response.headers()[CONTENT_TYPE], SEALED_CONTENT_TYPE);
+        assert_eq!(
+            response.headers()[SEALED_CONTENT_TYPE_HEADER],
+            "application/json"
+        );
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        assert_ne!(body, br#"{"id":7}"#.as_slice());
+        assert_eq!(reply_key.open(&body).unwrap(), br#"{"id":7}"#);
+
+        // Plaintext requests pass through untouched.
+        let req = Request::post("/api/orders")
+            .header(CONTENT_TYPE, "application/json")
+            .body(Body::from("{}"))
+            .unwrap();
+        let response = f.proxy.forward(&f.route, req).await.unwrap();
+        assert_eq!(response.headers()[CONTENT_TYPE], "application/json");
+    }
+
+    #[tokio::test]
+    async fn tampered_or_oversized_bodies_are_rejected() {
+        let f = fixture(UnsealConfig {
+            max_plaintext_bytes: 16,
+            ..UnsealConfig::default()
+        })
+        .await;
+
+        let mut tampered = Envelope::seal(&f.public, b"{}", b"POST /api/orders")
+            .unwrap()
+            .to_bytes();
+        let last = tampered.len() - 1;
+        tampered[last] ^= 1;
+        let wrong_path = Envelope::seal(&f.public, b"{}", b"POST /api/refunds").unwrap();
+        let oversized = Envelope::seal(&f.public, &[b'x'; 17], b"POST /api/orders").unwrap();
+
+        for body in [
+            tampered,
+            wrong_path.to_bytes(),
+            oversized.to_bytes(),
+            b"not an envelope".to_vec(),
+        ] {
+            let err = f
+                .proxy
+                .forward(&f.route, sealed_request(body))
+                .await
+                .unwrap_err();
+            assert!(matches!(err, ProxyError::SealedBody));
+
+            let response = err.into_response();
+            assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+            let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+                .await
+                .unwrap();
+            let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+            assert_eq!(json["error_code"], "DECAPSULATION_FAILED");
+            assert_eq!(json["message"], "sealed request body could not be opened");
+        }
+        assert!(f.mock.requests().is_empty());
+    }
+    #[tokio::test]
+    async fn replayed_envelopes_are_rejected() {
+        let f = fixture(UnsealConfig::default()).await;
+        let envelope = Envelope::seal(&f.public, b"{}", b"POST /api/orders")
+            .unwrap()
+            .to_bytes();
+
+        f.proxy
+            .forward(&f.route, sealed_request(envelope.clone()))
+            .await
+            .unwrap();
+        let err = f
+            .proxy
+            .forward(&f.route, sealed_request(envelope))
+            .await
+            .unwrap_err();
+        assert!(matches!(err, ProxyError::SealedBody));
+        assert_eq!(f.mock.requests().len(), 1);
+    }
+
+    #[test]
+    fn replay_cache_evicts_oldest() {
+        let mut seen = SeenEnvelopes::new(2);
+        assert!(seen.insert([1; 32]));
+        assert!(!seen.insert([1; 32]));
+        assert!(seen.insert([2; 32]));
+        assert!(seen.insert([3; 32]));
+        assert!(seen.insert([1; 32]));
+        assert!(!seen.insert([3; 32]));
+    }
+
+    #[tokio::test]
+    async fn unsealable_response_is_a_server_error() {
+        let f = fixture(UnsealConfig {
+            max_plaintext_bytes: 4,
+            seal_response: true,
+        })
+        .await;
+        f.mock
+            .set_fallback(MockResponse::default().with_body("longer than four bytes"));
+
+        let envelope = Envelope::seal(&f.public, b"{}", b"POST /api/orders").unwrap();
+        let err = f
+            .proxy
+            .forward(&f.route, sealed_request(envelope.to_bytes()))
+            .await
+            .unwrap_err();
+        assert!(matches!(err, ProxyError::SealedResponse));
+        assert_eq!(
+            err.into_response().status(),
+            StatusCode::INTERNAL_SERVER_ERROR
+        );
+    }
+}
@@ -0,0 +1,319 @@
+//! Weighted selection among discovered upstreams, with slow start for
+//! those that have just become healthy.
+//!
+//! Requests for an upstream name with
+//! [`ProxyService::with_discovered_upstreams`](super::ProxyService::with_discovered_upstreams)
+//! are spread over its healthy entries by smooth weighted round-robin, in
+//! proportion to each [`Upstream::weight`]. With a [`SlowStartConfig`], an
+//! upstream that joins the healthy list, on startup or after an outage,
+//! starts at `min_fraction` of its weight and ramps up to the full weight
+//! over `window_secs`, so that it is not knocked over again by a full
+//! share of traffic the moment it recovers. An upstream that drops out of
+//! the healthy list, whatever took it out, ramps up again when it returns.
+//! The balancer also notes when a listed upstream's `is_healthy` flips, so
+//! that the proxy can report ejections and recoveries.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use super::Upstream;
+
+/// Default [`SlowStartConfig::window_secs`].
+pub const DEFAULT_SLOW_START_WINDOW_SECS: u64 = 30;
+/// Default [`SlowStartConfig::min_fraction`].
+pub const DEFAULT_SLOW_START_MIN_FRACTION: f64 = 0.1;
+
+/// How a recovered upstream's weight is ramped up.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SlowStartConfig {
+    /// Seconds from joining the healthy list to the full weight.
+    pub window_secs: u64,
+    pub ramp: Ramp,
+    /// Fraction of its weight an upstream starts at, above 0 and at most 1.
+    pub min_fraction: f64,
+}
+
+impl Default for SlowStartConfig {
+    fn default() -> Self {
+        Self {
+            window_secs: DEFAULT_SLOW_START_WINDOW_SECS,
+            ramp: Ramp::Linear,
+            min_fraction: DEFAULT_SLOW_START_MIN_FRACTION,
+        }
+    }
+}
+
+/// Shape of the ramp from `min_fraction` to the full weight.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Ramp {
+    /// Grow by the same amount each second.
+    #[default]
+    Linear,
+    /// Grow by the same factor each second: slowly at first, then faster.
+    Exponential,
+}
+
+impl SlowStartConfig {
+    /// The fraction of its weight an upstream healthy for `elapsed` gets.
+    pub fn factor(&self, elapsed: Duration) -> f64 {
+        let window = Duration::from_secs(self.window_secs);
+        if elapsed >= window {
+            return 1.0;
+        }
+        let progress = elapsed.as_secs_f64() / window.as_secs_f64();
+        let min = self.min_fraction;
+        match self.ramp {
+            Ramp::Linear => min + (1.0 - min) * progress,
+            Ramp::Exponential => min.powf(1.0 - progress),
+        }
+    }
+}
+
+/// An upstream's configured and current weight, as listed by
+/// `/gateway/upstreams`.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct UpstreamWeight {
+    /// The upstream name the entry was discovered for.
+    pub group: String,
+    pub name: String,
+    pub host: String,
+    pub port: u16,
+    pub is_healthy: bool,
+    pub weight: u32,
+    /// The weight selection uses now: ramped during slow start, `0` while
+    /// unhealthy.
+    pub effective_weight: f64,
+}
+
+/// Selection state for the entries discovered for one upstream name.
+#[derive(Debug, Default)]
+pub(super) struct Balancer {
+    peers: HashMap<(String, u16), Peer>,
+    /// Last seen `is_healthy` of each listed upstream.
+    health: HashMap<(String, u16), bool>,
+}
+
+#[derive(Debug)]
+struct Peer {
+    healthy_since: Instant,
+    /// Smooth weighted round-robin credit.
+    current: f64,
+}
+
+fn key(upstream: &Upstream) -> (String, u16) {
+    (upstream.host.clone(), upstream.port)
+}
+
+impl Balancer {
+    /// The next of `healthy` to send a request to at `now`, or `None` if
+    /// there are none.
+    pub(super) fn pick<'a>(
+        &mut self,
+        healthy: &[&'a Upstream],
+        slow_start: Option<&SlowStartConfig>,
+        now: Instant,
+    ) -> Option<&'a Upstream> {
+        self.observe(healthy, now);
+        let mut total = 0.0;
+        let mut best: Option<(usize, f64)> = None;
+        for (i, upstream) in healthy.iter().enumerate() {
+            let weight = self.effective_weight(upstream, slow_start, now);
+            let peer = self.peers.get_mut(&key(upstream)).expect("observed");
+            peer.current += weight;
+            total += weight;
+            if best.is_none_or(|(_, current)| peer.current > current) {
+                best = Some((i, peer.current));
+            }
+        }
+        let (chosen, _) = best?;
+        let upstream = healthy[chosen];
+        self.peers
+            .get_mut(&key(upstream))
+            .expect("observed")
+            .current -= total;
+        Some(upstream)
+    }
+
+    /// `upstream`'s weight at `now`, ramped if it is still in its slow
+    /// start window. One not yet seen healthy is at the start of it.
+    pub(super) fn effective_weight(
+        &self,
+        upstream: &Upstream,
+        slow_start: Option<&SlowStartConfig>,
+        now: Instant,
+    ) -> f64 {
+        let Some(config) = slow_start else {
+            return f64::from(upstream.weight);
+        };
+        let healthy_for = self
+            .peers
+            .get(&key(upstream))
+            .map_or(Duration::ZERO, |peer| {
+                now.saturating_duration_since(peer.healthy_since)
+            });
+        f64::from(upstream.weight) * config.factor(healthy_for)
+    }
+
+    /// The upstreams whose `is_healthy` changed since the last call. Those
+    /// not listed before are only recorded, and those no longer listed are
+    /// forgotten.
+    pub(super) fn health_changes<'a>(&mut self, upstreams: &'a [Upstream]) -> Vec<&'a Upstream> {
+        let previous = std::mem::take(&mut self.health);
+        let mut changed = Vec::new();
+        for upstream in upstreams {
+            let key = key(upstream);
+            if previous.get(&key) == Some(&!upstream.is_healthy) {
+                changed.push(upstream);
+            }
+            self.health.insert(key, upstream.is_healthy);
+        }
+        changed
+    }
+
+    /// Start the slow start clock of upstreams new to the healthy list,
+    /// and forget those that have left it.
+    fn observe(&mut self, healthy: &[&Upstream], now: Instant) {
+        self.peers
+            .retain(|(host, port), _| healthy.iter().any(|u| &u.host == host && u.port == *port));
+        for upstream in healthy {
+            self.peers.entry(key(upstream)).or_insert(Peer {
+                healthy_since: now,
+                current: 0.0,
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn upstream(port: u16, weight: u32) -> Upstream {
+        Upstream {
+            name: "api".into(),
+            host: "10.0.0.1".into(),
+            port,
+            is_healthy: true,
+            tls_verify: false,
+            tls: false,
+            weight,
+        }
+    }
+
+    /// How many of `picks` requests starting at `at` go to `port`.
+    fn share(
+        balancer: &mut Balancer,
+        healthy: &[&Upstream],
+        config: &SlowStartConfig,
+        at: Instant,
+        port: u16,
+        picks: usize,
+    ) -> usize {
+        (0..picks)
+            .filter(|_| balancer.pick(healthy, Some(config), at).unwrap().port == port)
+            .count()
+    }
+
+    #[test]
+    fn test_recovered_upstream_share_grows_over_the_window() {
+        let config = SlowStartConfig {
+            window_secs: 40,
+            ..SlowStartConfig::default()
+        };
+        let (steady, recovering) = (upstream(1, 3), upstream(2, 3));
+        let mut balancer = Balancer::default();
+        let start = Instant::now();
+
+        // The steady upstream takes everything while the other is down, and
+        // is past its own window by the time the other recovers.
+        assert_eq!(share(&mut balancer, &[&steady], &config, start, 1, 10), 10);
+        let healthy = [&steady, &recovering];
+        let recovered = start + Duration::from_secs(100);
+        let shares: Vec<usize> = [0, 10, 20, 30, 40, 50]
+            .into_iter()
+            .map(|secs| {
+                let at = recovered + Duration::from_secs(secs);
+                share(&mut balancer, &healthy, &config, at, 2, 200)
+            })
+            .collect();
+
+        // Linear from 10% of the weight: shares of 0.1/1.1, 0.325/1.325, ...
+        assert!(shares[0] <= 20, "{shares:?}");
+        assert!(
+            shares.windows(2).all(|w| w[0] < w[1] || w[1] == 100),
+            "{shares:?}"
+        );
+        assert_eq!(shares[4..], [100, 100]);
+    }
+
+    #[test]
+    fn test_upstream_ramps_again_after_leaving_the_healthy_list() {
+        let config = SlowStartConfig::default();
+        let (a, b) = (upstream(1, 1), upstream(2, 1));
+        let mut balancer = Balancer::default();
+        let start = Instant::now();
+        let later = start + Duration::from_secs(60);
+
+        balancer.pick(&[&a, &b], Some(&config), start);
+        assert_eq!(balancer.effective_weight(&b, Some(&config), later), 1.0);
+        balancer.pick(&[&a], Some(&config), later);
+        balancer.pick(&[&a, &b], Some(&config), later);
+        assert!((balancer.effective_weight(&b, Some(&config), later) - 0.1).abs() < 1e-9);
+        assert_eq!(balancer.effective_weight(&b, None, later), 1.0);
+    }
+
+    #[test]
+    fn test_health_changes_report_flips_only() {
+        let mut balancer = Balancer::default();
+        let (a, b) = (upstream(1, 1), upstream(2, 1));
+        let down = |u: &Upstream| Upstream {
+            is_healthy: false,
+            ..u.clone()
+        };
+        let ports = |changed: Vec<&Upstream>| changed.iter().map(|u| u.port).collect::<Vec<_>>();
+
+        // Upstreams first listed unhealthy are not reported.
+        assert!(balancer.health_changes(&[a.clone(), down(&b)]).is_empty());
+        let flipped = [down(&a), b.clone()];
+        assert_eq!(ports(balancer.health_changes(&flipped)), [1, 2]);
+        assert!(balancer.health_changes(&flipped).is_empty());
+        // Leaving the list is not a health change, and re-listing starts over.
+        assert!(balancer.health_changes(std::slice::from_ref(&b)).is_empty());
+        assert!(balancer.health_changes(&[a.clone(), b.clone()]).is_empty());
+    }
+
+    #[test]
+    fn test_weighted_selection_without_slow_start() {
+        let (light, heavy) = (upstream(1, 1), upstream(2, 3));
+        let mut balancer = Balancer::default();
+        let now = Instant::now();
+        let picks: Vec<u16> = (0..8)
+            .map(|_| balancer.pick(&[&light, &heavy], None, now).unwrap().port)
+            .collect();
+        assert_eq!(picks, [2, 1, 2, 2, 2, 1, 2, 2]);
+    }
+
+    #[test]
+    fn test_ramp_shapes() {
+        let linear = SlowStartConfig {
+            window_secs: 10,
+            ramp: Ramp::Linear,
+            min_fraction: 0.01,
+        };
+        let exponential = SlowStartConfig {
+            ramp: Ramp::Exponential,
+            ..linear.clone()
+        };
+        let secs = Duration::from_secs;
+        for config in [&linear, &exponential] {
+            assert!((config.factor(secs(0)) - 0.01).abs() < 1e-9);
+            assert_eq!(config.factor(secs(10)), 1.0);
+            assert_eq!(config.factor(secs(11)), 1.0);
+        }
+        assert!((linear.factor(secs(5)) - 0.505).abs() < 1e-9);
+        assert!((exponential.factor(secs(5)) - 0.1).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,373 @@
+//! Filtering of Server-Sent Events streams.
+//!
+//! On a route with `sse_filter` set, `text/event-stream` responses are run
+//! through the [`SseFilter`] registered under that name with
+//! [`ProxyService::with_sse_filter`]. The stream is never buffered whole:
+//! each chunk from the upstream is split into complete events, which are
+//! filtered and sent on as one chunk, so clients get events as promptly as
+//! the upstream flushes them. A partial event is held until the rest
+//! arrives, up to [`MAX_EVENT_BYTES`]; a longer event ends the stream with
+//! an error. One still incomplete when the stream ends is dropped, as
+//! clients would discard it anyway. Other responses, and compressed
+//! streams, are not filtered.
+
+use axum::body::{Body, Bytes};
+use http::header::{CONTENT_ENCODING, CONTENT_LENGTH, CONTENT_TYPE};
+use http::{HeaderMap, Response};
+use hyper::body::Frame;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{ready, Context, Poll};
+use tracing::{error, warn};
+
+use super::{ProxyError, ProxyService, Route};
+
+/// Largest event held while waiting for the blank line that ends it.
+pub const MAX_EVENT_BYTES: usize = 1024 * 1024;
+
+/// What to do with one event.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SseAction {
+    /// Send the event unchanged.
+    Pass,
+    /// Leave the event out.
+    Drop,
+    /// Send these lines instead. The blank line ending the event is added.
+    Rewrite(String),
+}
+
+/// Decides the fate of each event on a filtered stream.
+pub trait SseFilter: Send + Sync {
+    /// The action for `event`: its lines, such as `id: 7\ndata: hello`,
+    /// without the blank line that ends it. Events that are not valid
+    /// UTF-8 are passed without being seen.
+    fn filter(&self, event: &str) -> SseAction;
+}
+
+/// Whether `headers` describe an uncompressed event stream.
+pub fn is_event_stream(headers: &HeaderMap) -> bool {
+    let media_type = headers
+        .get(CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.split(';').next())
+        .unwrap_or("");
+    media_type.trim().eq_ignore_ascii_case("text/event-stream")
+        && !headers.contains_key(CONTENT_ENCODING)
+}
+
+impl ProxyService {
+    /// `response` with its events filtered, if the route has a filter and
+    /// the response is an event stream.
+    pub(super) fn filter_events(
+        &self,
+        route: &Route,
+        response: Response<Body>,
+    ) -> Result<Response<Body>, ProxyError> {
+        let Some(name) = &route.sse_filter else {
+            return Ok(response);
+        };
+        if !is_event_stream(response.headers()) {
+            return Ok(response);
+        }
+        let Some(filter) = self.sse_filters.get(name) else {
+            error!(route = %route.path_prefix, filter = %name, "route names an unknown SSE filter");
+            return Err(ProxyError::InvalidConfig(format!(
+                "unknown SSE filter {name}"
+            )));
+        };
+        let (mut parts, body) = response.into_parts();
+        parts.headers.remove(CONTENT_LENGTH);
+        let body = Body::new(FilteredEvents::new(body, filter.clone(), MAX_EVENT_BYTES));
+        Ok(Response::from_parts(parts, body))
+    }
+}
+
+/// An event stream passed through a filter, chunk by chunk.
+struct FilteredEvents {
+    inner: Body,
+    filter: Arc<dyn SseFilter>,
+    /// Data received after the last complete event.
+    pending: Vec<u8>,
+    /// How far into `pending` the end of its first event was looked for.
+    scan: Scan,
+    max_event_bytes: usize,
+    done: bool,
+}
+
+impl FilteredEvents {
+    fn new(inner: Body, filter: Arc<dyn SseFilter>, max_event_bytes: usize) -> Self {
+        Self {
+            inner,
+            filter,
+            pending: Vec::new(),
+            scan: Scan::default(),
+            max_event_bytes,
+            done: false,
+        }
+    }
+
+    /// The filtered output for the complete events in `pending`, which
+    /// keeps only the trailing partial event.
+    fn drain_events(&mut self) -> Vec<u8> {
+        let mut out = Vec::new();
+        let mut start = 0;
+        while let Some(len) = event_len(&self.pending[start..], &mut self.scan) {
+            let event = &self.pending[start..start + len];
+            let lines = trim_event_end(event);
+            match std::str::from_utf8(lines).map(|lines| self.filter.filter(lines)) {
+                Ok(SseAction::Drop) => {}
+                Ok(SseAction::Rewrite(lines)) => {
+                    out.extend_from_slice(lines.as_bytes());
+                    out.extend_from_slice(b"\n\n");
+                }
+                Ok(SseAction::Pass) | Err(_) => out.extend_from_slice(event),
+            }
+            start += len;
+        }
+        self.pending.drain(..start);
+        out
+    }
+}
+
+impl hyper::body::Body for FilteredEvents {
+    type Data = Bytes;
+    type Error = axum::Error;
+
+    fn poll_frame(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Frame<Bytes>, Self::Error>>> {
+        loop {
+            if self.done {
+                return Poll::Ready(None);
+            }
+            match ready!(Pin::new(&mut self.inner).poll_frame(cx)) {
+                Some(Ok(frame)) => match frame.into_data() {
+                    Ok(data) => {
+                        self.pending.extend_from_slice(&data);
+                        let out = self.drain_events();
+                        if self.pending.len() > self.max_event_bytes {
+                            warn!(
+                                limit = self.max_event_bytes,
+                                "upstream event too large, ending the stream"
+                            );
+                            self.done = true;
+                            self.pending = Vec::new();
+                            let e = ProxyError::ConnectionFailed(format!(
+                                "event stream event exceeds {} bytes",
+                                self.max_event_bytes
+                            ));
+                            return Poll::Ready(Some(Err(axum::Error::new(e))));
+                        }
+                        if !out.is_empty() {
+                            return Poll::Ready(Some(Ok(Frame::data(out.into()))));
+                        }
+                    }
+                    Err(frame) => return Poll::Ready(Some(Ok(frame))),
+                },
+                Some(Err(e)) => return Poll::Ready(Some(Err(e))),
+                None => {
+                    // An unterminated last event is never filtered, so it
+                    // is dropped rather than passed on.
+                    self.done = true;
+                    self.pending = Vec::new();
+                }
+            }
+        }
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.done
+    }
+}
+
+/// Where the search for the end of an event stopped.
+#[derive(Debug, Clone, Copy)]
+struct Scan {
+    pos: usize,
+    line_start: bool,
+}
+
+impl Default for Scan {
+    fn default() -> Self {
+        Self {
+            pos: 0,
+            line_start: true,
+        }
+    }
+}
+
+/// The length of the first complete event in `buf`, up to and including
+/// the blank line that ends it. The search resumes from `scan`, which is
+/// left where it stopped, so each byte is looked at once.
+fn event_len(buf: &[u8], scan: &mut Scan) -> Option<usize> {
+    let Scan {
+        pos: mut i,
+        mut line_start,
+    } = *scan;
+    while i < buf.len() {
+        let eol = match buf[i] {
+            b'\n' => 1,
+            // A lone CR at the end may be the first half of a CRLF.
+            b'\r' if i + 1 == buf.len() => break,
+            b'\r' if buf[i + 1] == b'\n' => 2,
+            b'\r' => 1,
+            _ => {
+                line_start = false;
+                i += 1;
+                continue;
+            }
+        };
+        i += eol;
+        if line_start {
+            *scan = Scan::default();
+            return Some(i);
+        }
+        line_start = true;
+    }
+    *scan = Scan { pos: i, line_start };
+    None
+}
+
+/// `event` without the line endings that close it.
+fn trim_event_end(event: &[u8]) -> &[u8] {
+    let end = event
+        .iter()
+        .rposition(|&b| b != b'\n' && b != b'\r')
+        .map_or(0, |i| i + 1);
+    &event[..end]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::proxy::testing::{MockResponse, MockUpstream};
+    use http::{HeaderValue, Request, StatusCode};
+    use http_body_util::BodyExt;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// Drops every other event.
+    #[derive(Default)]
+    struct EveryOther(AtomicUsize);
+
+    impl SseFilter for EveryOther {
+        fn filter(&self, _event: &str) -> SseAction {
+            if self.0.fetch_add(1, Ordering::Relaxed).is_multiple_of(2) {
+                SseAction::Pass
+            } else {
+                SseAction::Drop
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_filter_drops_every_other_event() {
+        let mock = MockUpstream::start().await.unwrap();
+        mock.enqueue(
+            MockResponse::new(StatusCode::OK)
+                .with_header(CONTENT_TYPE, HeaderValue::from_static("text/event-stream"))
+                .with_body("id: 1\ndata: a\n\nid: 2\ndata: b\n\nid: 3\r\ndata: c\r\n\r\nid: 4\ndata: d\n\n"),
+        );
+        mock.enqueue(MockResponse::new(StatusCode::OK).with_body("one\n\ntwo\n\n"));
+        let proxy = mock
+            .proxy_service("/events")
+            .with_sse_filter("every-other", Arc::new(EveryOther::default()));
+        let mut route = mock.route("/events");
+        route.sse_filter = Some("every-other".into());
+
+        let req = Request::get("/events/feed").body(Body::empty()).unwrap();
+        let response = proxy.forward(&route, req).await.unwrap();
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        assert_eq!(&body[..], b"id: 1\ndata: a\n\nid: 3\r\ndata: c\r\n\r\n");
+
+        let req = Request::get("/events/plain").body(Body::empty()).unwrap();
+        let response = proxy.forward(&route, req).await.unwrap();
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        assert_eq!(&body[..], b"one\n\ntwo\n\n");
+    }
+
+    struct Shout;
+
+    impl SseFilter for Shout {
+        fn filter(&self, event: &str) -> SseAction {
+            SseAction::Rewrite(event.to_uppercase())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_events_are_sent_as_soon_as_complete() {
+        let (tx, rx) = futures::channel::mpsc::unbounded::<Result<Bytes, std::io::Error>>();
+        let mut body = Body::new(FilteredEvents::new(
+            Body::from_stream(rx),
+            Arc::new(Shout),
+            MAX_EVENT_BYTES,
+        ));
+
+        tx.unbounded_send(Ok(Bytes::from("data: a\n\ndata: b")))
+            .unwrap();
+        let frame = body.frame().await.unwrap().unwrap();
+        assert_eq!(frame.into_data().unwrap(), "DATA: A\n\n");
+
+        tx.unbounded_send(Ok(Bytes::from("\n\r"))).unwrap();
+        tx.unbounded_send(Ok(Bytes::from("\n"))).unwrap();
+        let frame = body.frame().await.unwrap().unwrap();
+        assert_eq!(frame.into_data().unwrap(), "DATA: B\n\n");
+
+        tx.unbounded_send(Ok(Bytes::from("data: partial"))).unwrap();
+        drop(tx);
+        assert!(body.frame().await.is_none());
+    }
+
+    /// Drops every event.
+    struct DropAll;
+
+    impl SseFilter for DropAll {
+        fn filter(&self, _event: &str) -> SseAction {
+            SseAction::Drop
+        }
+    }
+
+    #[tokio::test]
+    async fn test_unterminated_last_event_is_not_passed() {
+        let (tx, rx) = futures::channel::mpsc::unbounded::<Result<Bytes, std::io::Error>>();
+        let body = Body::new(FilteredEvents::new(
+            Body::from_stream(rx),
+            Arc::new(DropAll),
+            MAX_EVENT_BYTES,
+        ));
+        tx.unbounded_send(Ok(Bytes::from("data: a\n\ndata: secret\n")))
+            .unwrap();
+        drop(tx);
+        assert!(body.collect().await.unwrap().to_bytes().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_oversized_event_ends_the_stream() {
+        let (tx, rx) = futures::channel::mpsc::unbounded::<Result<Bytes, std::io::Error>>();
+        let mut body = Body::new(FilteredEvents::new(
+            Body::from_stream(rx),
+            Arc::new(Shout),
+            16,
+        ));
+
+        tx.unbounded_send(Ok(Bytes::from("data: a\n\ndata: ")))
+            .unwrap();
+        let frame = body.frame().await.unwrap().unwrap();
+        assert_eq!(frame.into_data().unwrap(), "DATA: A\n\n");
+        tx.unbounded_send(Ok(Bytes::from("0123456789"))).unwrap();
+        tx.unbounded_send(Ok(Bytes::from("0123456789"))).unwrap();
+        assert!(body.frame().await.unwrap().is_err());
+        assert!(body.frame().await.is_none());
+    }
+
+    #[test]
+    fn test_event_scan_resumes() {
+        let mut scan = Scan::default();
+        assert_eq!(event_len(b"data: a\n", &mut scan), None);
+        assert_eq!((scan.pos, scan.line_start), (8, true));
+        assert_eq!(event_len(b"data: a\n\r", &mut scan), None);
+        assert_eq!(scan.pos, 8);
+        assert_eq!(event_len(b"data: a\n\r\nid: 2", &mut scan), Some(10));
+        assert_eq!((scan.pos, scan.line_start), (0, true));
+    }
+}
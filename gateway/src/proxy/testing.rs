@@ -0,0 +1,344 @@
+//! In-process upstream and egress proxy for exercising the proxy path end
+//! to end.
+//!
+//! Available in unit tests and, for downstream crates, behind the `testing`
+//! feature.
+
+use axum::body::{Body, Bytes};
+use axum::extract::State;
+use axum::response::Response;
+use axum::Router;
+use base64::Engine;
+use http::{HeaderMap, HeaderName, HeaderValue, Method, Request, StatusCode, Uri};
+use std::collections::VecDeque;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::task::JoinHandle;
+
+use super::egress::{EgressProtocol, EgressProxy};
+use super::{MiddlewareProfile, ProxyService, Route, Upstream};
+
+/// A request as received by a [`MockUpstream`].
+#[derive(Debug, Clone)]
+pub struct RecordedRequest {
+    pub method: Method,
+    pub uri: Uri,
+    pub headers: HeaderMap,
+    pub body: Bytes,
+}
+
+/// A programmed response returned by a [`MockUpstream`].
+#[derive(Debug, Clone)]
+pub struct MockResponse {
+    pub status: StatusCode,
+    pub headers: Vec<(HeaderName, HeaderValue)>,
+    pub body: Bytes,
+    /// How long the upstream waits before responding.
+    pub delay: Duration,
+}
+
+impl MockResponse {
+    pub fn new(status: StatusCode) -> Self {
+        Self {
+            status,
+            headers: Vec::new(),
+            body: Bytes::new(),
+            delay: Duration::ZERO,
+        }
+    }
+
+    pub fn with_body(mut self, body: impl Into<Bytes>) -> Self {
+        self.body = body.into();
+        self
+    }
+
+    pub fn with_header(mut self, name: HeaderName, value: HeaderValue) -> Self {
+        self.headers.push((name, value));
+        self
+    }
+
+    pub fn with_delay(mut self, delay: Duration) -> Self {
+        self.delay = delay;
+        self
+    }
+
+    fn into_response(self) -> Response {
+        let mut response = Response::new(Body::from(self.body));
+        *response.status_mut() = self.status;
+        response.headers_mut().extend(self.headers);
+        response
+    }
+}
+
+impl Default for MockResponse {
+    fn default() -> Self {
+        Self::new(StatusCode::OK)
+    }
+}
+
+#[derive(Default)]
+struct MockState {
+    requests: Mutex<Vec<RecordedRequest>>,
+    queued: Mutex<VecDeque<MockResponse>>,
+    fallback: Mutex<MockResponse>,
+}
+
+/// HTTP server on an ephemeral localhost port that records every request
+/// and replies with queued responses, then a fallback (`200 OK` by default).
+///
+/// The server stops when the `MockUpstream` is dropped.
+pub struct MockUpstream {
+    addr: SocketAddr,
+    state: Arc<MockState>,
+    task: JoinHandle<()>,
+}
+
+impl MockUpstream {
+    /// Bind to `127.0.0.1:0` and start serving.
+    pub async fn start() -> std::io::Result<Self> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let addr = listener.local_addr()?;
+        let state = Arc::new(MockState::default());
+
+        let app = Router::new().fallback(handle).with_state(state.clone());
+        let task = tokio::spawn(async move {
+            let _ = axum::serve(listener, app).await;
+        });
+
+        Ok(Self { addr, state, task })
+    }
+
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// Queue a response for the next unanswered request.
+    pub fn enqueue(&self, response: MockResponse) {
+        self.state.queued.lock().unwrap().push_back(response);
+    }
+
+    /// Set the response used once the queue is empty.
+    pub fn set_fallback(&self, response: MockResponse) {
+        *self.state.fallback.lock().unwrap() = response;
+    }
+
+    /// Requests received so far, in arrival order.
+    pub fn requests(&self) -> Vec<RecordedRequest> {
+        self.state.requests.lock().unwrap().clone()
+    }
+
+    /// A healthy upstream pointing at this server.
+    pub fn upstream(&self, name: &str) -> Upstream {
+        Upstream {
+            name: name.to_string(),
+            host: self.addr.ip().to_string(),
+            port: self.addr.port(),
+            is_healthy: true,
+            tls_verify: false,
+            tls: false,
+            weight: 1,
+        }
+    }
+
+    /// A route forwarding `path_prefix` to this server, with the prefix stripped.
+    pub fn route(&self, path_prefix: &str) -> Route {
+        Route {
+            path_prefix: path_prefix.to_string(),
+            upstream: self.upstream("mock"),
+            strip_prefix: true,
+            path_rewrite: None,
+            priority: 0,
+            coalesce: None,
+            cache: None,
+            tunnel: None,
+            unseal: None,
+            transform: None,
+            sse_filter: None,
+            allowed_methods: Vec::new(),
+            denied_methods: Vec::new(),
+            content_type: None,
+            middleware_profile: MiddlewareProfile::Default,
+        }
+    }
+
+    /// A proxy service with a single [`MockUpstream::route`] for `path_prefix`.
+    pub fn proxy_service(&self, path_prefix: &str) -> ProxyService {
+        ProxyService::new(vec![self.route(path_prefix)], 5)
+    }
+}
+
+impl Drop for MockUpstream {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+/// HTTP `CONNECT` proxy on an ephemeral localhost port that records each
+/// request head and tunnels to the requested target. With credentials set,
+/// requests without a matching `Proxy-Authorization` get `407`.
+///
+/// The proxy stops accepting when the `MockConnectProxy` is dropped.
+pub struct MockConnectProxy {
+    addr: SocketAddr,
+    requests: Arc<Mutex<Vec<String>>>,
+    task: JoinHandle<()>,
+}
+
+impl MockConnectProxy {
+    /// Bind to `127.0.0.1:0` and start accepting, requiring `credentials`
+    /// (username and password) if given.
+    pub async fn start(credentials: Option<(&str, &str)>) -> std::io::Result<Self> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let addr = listener.local_addr()?;
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let expected = credentials.map(|(username, password)| {
+            let token =
+                base64::engine::general_purpose::STANDARD.encode(format!("{username}:{password}"));
+            format!("Basic {token}")
+        });
+
+        let recorded = requests.clone();
+        let task = tokio::spawn(async move {
+            while let Ok((client, _)) = listener.accept().await {
+                let recorded = recorded.clone();
+                let expected = expected.clone();
+                tokio::spawn(tunnel(client, recorded, expected));
+            }
+        });
+
+        Ok(Self {
+            addr,
+            requests,
+            task,
+        })
+    }
+
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// `CONNECT` request heads received so far, in arrival order.
+    pub fn requests(&self) -> Vec<String> {
+        self.requests.lock().unwrap().clone()
+    }
+
+    /// An [`EgressProxy`] pointing at this proxy, without credentials.
+    pub fn egress_proxy(&self) -> EgressProxy {
+        EgressProxy {
+            protocol: EgressProtocol::HttpConnect,
+            host: self.addr.ip().to_string(),
+            port: self.addr.port(),
+            credentials: None,
+        }
+    }
+}
+
+impl Drop for MockConnectProxy {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+async fn tunnel(
+    mut client: TcpStream,
+    requests: Arc<Mutex<Vec<String>>>,
+    expected: Option<String>,
+) -> std::io::Result<()> {
+    let mut head = Vec::new();
+    while !head.ends_with(b"\r\n\r\n") {
+        head.push(client.read_u8().await?);
+    }
+    let head = String::from_utf8_lossy(&head).into_owned();
+    requests.lock().unwrap().push(head.clone());
+
+    let authorized = expected.is_none_or(|expected| {
+        head.lines().any(|line| {
+            line.split_once(':').is_some_and(|(name, value)| {
+                name.eq_ignore_ascii_case("proxy-authorization") && value.trim() == expected
+            })
+        })
+    });
+    if !authorized {
+        return client
+            .write_all(b"HTTP/1.1 407 Proxy Authentication Required\r\n\r\n")
+            .await;
+    }
+    let target = head.split_whitespace().nth(1).unwrap_or_default();
+    let Ok(mut upstream) = TcpStream::connect(target).await else {
+        return client.write_all(b"HTTP/1.1 502 Bad Gateway\r\n\r\n").await;
+    };
+    client
+        .write_all(b"HTTP/1.1 200 Connection established\r\n\r\n")
+        .await?;
+    tokio::io::copy_bidirectional(&mut client, &mut upstream).await?;
+    Ok(())
+}
+
+async fn handle(State(state): State<Arc<MockState>>, req: Request<Body>) -> Response {
+    let (parts, body) = req.into_parts();
+    let body = axum::body::to_bytes(body, usize::MAX)
+        .await
+        .unwrap_or_default();
+    state.requests.lock().unwrap().push(RecordedRequest {
+        method: parts.method,
+        uri: parts.uri,
+        headers: parts.headers,
+        body,
+    });
+
+    let queued = state.queued.lock().unwrap().pop_front();
+    let response = queued.unwrap_or_else(|| state.fallback.lock().unwrap().clone());
+    if !response.delay.is_zero() {
+        tokio::time::sleep(response.delay).await;
+    }
+    response.into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use http::header::CONTENT_TYPE;
+
+    #[tokio::test]
+    async fn request_reaches_mock_and_is_recorded() {
+        let mock = MockUpstream::start().await.unwrap();
+        mock.enqueue(
+            MockResponse::new(StatusCode::CREATED)
+                .with_header(CONTENT_TYPE, HeaderValue::from_static("text/plain"))
+                .with_body("created"),
+        );
+        let service = mock.proxy_service("/api");
+        let route = service.find_route("/api/items").unwrap();
+
+        let req = Request::builder()
+            .method(Method::POST)
+            .uri("/api/items")
+            .body(Body::from("hello"))
+            .unwrap();
+        let response = service.forward(&route, req).await.unwrap();
+        assert_eq!(response.status(), StatusCode::CREATED);
+        assert_eq!(response.headers()[CONTENT_TYPE], "text/plain");
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        assert_eq!(body, "created");
+
+        let requests = mock.requests();
+        assert_eq!(requests.len(), 1);
+        let recorded = &requests[0];
+        assert_eq!(recorded.method, Method::POST);
+        assert_eq!(recorded.uri.path(), "/items");
+        assert_eq!(recorded.headers["x-forwarded-proto"], "https");
+        assert_eq!(recorded.headers["host"], mock.addr().to_string());
+        assert_eq!(recorded.body, "hello");
+
+        // Queue drained: the fallback answers.
+        let req = Request::builder().uri("/api/other").body(Body::empty()).unwrap();
+        let response = service.forward(&route, req).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(mock.requests().len(), 2);
+    }
+}
@@ -0,0 +1,546 @@
+//! Transformation of request and response bodies.
+//!
+//! On a route with a [`TransformConfig`], the request steps run on each body
+//! sent upstream and the response steps on each body returned, in order.
+//! Every step is a [`Transformer`]: the built-in [`JsonFields`] and
+//! [`HeaderClaims`], or a custom one registered with
+//! [`ProxyService::with_transformer`]. Steps only see bodies whose
+//! `Content-Type` they apply to.
+//!
+//! Bodies are only buffered when their length is known and within
+//! [`TransformConfig::max_body_bytes`]; larger or streamed bodies pass
+//! through untransformed and are counted in
+//! [`GatewayMetrics::transform_bypasses`](crate::metrics::GatewayMetrics::transform_bypasses).
+//! Empty bodies and bodies with a `Content-Encoding` are never transformed.
+
+use axum::body::{Body, Bytes};
+use http::header::{CONTENT_ENCODING, CONTENT_LENGTH, CONTENT_TYPE, TRANSFER_ENCODING};
+use http::{HeaderMap, HeaderValue, Request, Response};
+use hyper::body::Body as _;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::collections::BTreeMap;
+use std::sync::atomic::Ordering;
+use thiserror::Error;
+use tracing::{debug, warn};
+
+use super::coalesce::content_length;
+use super::{ProxyError, ProxyService, Route};
+
+/// Default limit on the size of a transformed body.
+pub const DEFAULT_MAX_TRANSFORM_BODY: usize = 256 * 1024;
+
+#[derive(Debug, Clone, Error)]
+#[error("{0}")]
+pub struct TransformError(pub String);
+
+/// A synchronous rewrite of a buffered body.
+pub trait Transformer: Send + Sync {
+    /// Whether bodies with `content_type` are transformed. JSON media
+    /// types only, unless overridden.
+    fn applies_to(&self, content_type: Option<&str>) -> bool {
+        is_json(content_type)
+    }
+
+    /// The new body for `body`, sent with `headers`.
+    fn transform(&self, headers: &HeaderMap, body: Bytes) -> Result<Bytes, TransformError>;
+}
+
+/// Opt-in body transformation settings for a route.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TransformConfig {
+    /// Steps applied to request bodies.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub request: Vec<TransformStep>,
+    /// Steps applied to response bodies.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub response: Vec<TransformStep>,
+    /// Largest body that is transformed.
+    #[serde(default = "default_max_body_bytes")]
+    pub max_body_bytes: usize,
+    #[serde(default)]
+    pub on_failure: OnTransformFailure,
+}
+
+fn default_max_body_bytes() -> usize {
+    DEFAULT_MAX_TRANSFORM_BODY
+}
+
+impl Default for TransformConfig {
+    fn default() -> Self {
+        Self {
+            request: Vec::new(),
+            response: Vec::new(),
+            max_body_bytes: DEFAULT_MAX_TRANSFORM_BODY,
+            on_failure: OnTransformFailure::default(),
+        }
+    }
+}
+
+/// What happens to a body whose transformation fails.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OnTransformFailure {
+    /// Answer 502 Bad Gateway.
+    #[default]
+    BadGateway,
+    /// Forward the body unchanged.
+    PassThrough,
+}
+
+/// One transformation of a route.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum TransformStep {
+    JsonFields(JsonFields),
+    HeaderClaims(HeaderClaims),
+    /// The transformer registered under `name` with
+    /// [`ProxyService::with_transformer`].
+    Custom {
+        name: String,
+    },
+}
+
+/// Removes and sets fields of a JSON object body. Paths are dot-separated
+/// object keys, such as `user.id`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct JsonFields {
+    /// Fields set, by path, creating missing parent objects. Applied after
+    /// `remove`.
+    #[serde(default)]
+    pub set: BTreeMap<String, Value>,
+    /// Fields removed, by path. Missing fields are ignored.
+    #[serde(default)]
+    pub remove: Vec<String>,
+}
+
+impl Transformer for JsonFields {
+    fn transform(&self, _headers: &HeaderMap, body: Bytes) -> Result<Bytes, TransformError> {
+        let mut json = parse(&body)?;
+        for path in &self.remove {
+            remove_path(&mut json, path);
+        }
+        for (path, value) in &self.set {
+            set_path(&mut json, path, value.clone())?;
+        }
+        serialize(&json)
+    }
+}
+
+/// Copies header values into a JSON object body as string fields. A claim
+/// whose header is absent is removed from the body, so clients cannot
+/// supply it themselves.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HeaderClaims {
+    /// Body path written, by header name.
+    pub claims: BTreeMap<String, String>,
+}
+
+impl Transformer for HeaderClaims {
+    fn transform(&self, headers: &HeaderMap, body: Bytes) -> Result<Bytes, TransformError> {
+        let mut json = parse(&body)?;
+        for (header, path) in &self.claims {
+            match headers.get(header.as_str()) {
+                Some(value) => {
+                    let value = value.to_str().map_err(|_| {
+                        TransformError(format!("header {header} is not visible ASCII"))
+                    })?;
+                    set_path(&mut json, path, Value::String(value.to_string()))?;
+                }
+                None => remove_path(&mut json, path),
+            }
+        }
+        serialize(&json)
+    }
+}
+
+/// Whether `content_type` is `application/json` or a `+json` type.
+pub fn is_json(content_type: Option<&str>) -> bool {
+    let Some(content_type) = content_type else {
+        return false;
+    };
+    let media_type = content_type
+        .split(';')
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .to_ascii_lowercase();
+    media_type == "application/json" || media_type.ends_with("+json")
+}
+
+fn parse(body: &[u8]) -> Result<Value, TransformError> {
+    serde_json::from_slice(body).map_err(|e| TransformError(format!("malformed JSON: {e}")))
+}
+
+fn serialize(json: &Value) -> Result<Bytes, TransformError> {
+    serde_json::to_vec(json)
+        .map(Bytes::from)
+        .map_err(|e| TransformError(e.to_string()))
+}
+
+fn set_path(root: &mut Value, path: &str, value: Value) -> Result<(), TransformError> {
+    let (parents, field) = split_path(path);
+    let mut node = root;
+    for segment in parents {
+        node = object(node, path)?
+            .entry(segment)
+            .or_insert_with(|| Value::Object(Map::new()));
+    }
+    object(node, path)?.insert(field.to_string(), value);
+    Ok(())
+}
+
+fn remove_path(root: &mut Value, path: &str) {
+    let (parents, field) = split_path(path);
+    let mut node = root;
+    for segment in parents {
+        match node.get_mut(segment) {
+            Some(child) => node = child,
+            None => return,
+        }
+    }
+    if let Some(object) = node.as_object_mut() {
+        object.remove(field);
+    }
+}
+
+/// The parent segments and final key of `path`.
+fn split_path(path: &str) -> (Vec<&str>, &str) {
+    let mut segments: Vec<&str> = path.split('.').collect();
+    let field = segments.pop().unwrap_or_default();
+    (segments, field)
+}
+
+fn object<'a>(
+    node: &'a mut Value,
+    path: &str,
+) -> Result<&'a mut Map<String, Value>, TransformError> {
+    node.as_object_mut()
+        .ok_or_else(|| TransformError(format!("{path} is not inside a JSON object")))
+}
+
+/// Which way a body is travelling.
+#[derive(Debug, Clone, Copy)]
+enum Direction {
+    Request,
+    Response,
+}
+
+impl Direction {
+    fn as_str(self) -> &'static str {
+        match self {
+            Direction::Request => "request",
+            Direction::Response => "response",
+        }
+    }
+}
+
+impl ProxyService {
+    /// `req` with the route's request steps applied to its body.
+    pub(super) async fn transform_request(
+        &self,
+        route: &Route,
+        req: Request<Body>,
+    ) -> Result<Request<Body>, ProxyError> {
+        let Some(config) = &route.transform else {
+            return Ok(req);
+        };
+        let (mut parts, body) = req.into_parts();
+        let body = self
+            .transform_body(route, config, Direction::Request, &mut parts.headers, body)
+            .await?;
+        Ok(Request::from_parts(parts, body))
+    }
+
+    /// `response` with the route's response steps applied to its body.
+    pub(super) async fn transform_response(
+        &self,
+        route: &Route,
+        response: Response<Body>,
+    ) -> Result<Response<Body>, ProxyError> {
+        let Some(config) = &route.transform else {
+            return Ok(response);
+        };
+        let (mut parts, body) = response.into_parts();
+        let body = self
+            .transform_body(route, config, Direction::Response, &mut parts.headers, body)
+            .await?;
+        Ok(Response::from_parts(parts, body))
+    }
+
+    async fn transform_body(
+        &self,
+        route: &Route,
+        config: &TransformConfig,
+        direction: Direction,
+        headers: &mut HeaderMap,
+        body: Body,
+    ) -> Result<Body, ProxyError> {
+        let steps = match direction {
+            Direction::Request => &config.request,
+            Direction::Response => &config.response,
+        };
+        if steps.is_empty() || headers.contains_key(CONTENT_ENCODING) {
+            return Ok(body);
+        }
+        let transformers = match steps
+            .iter()
+            .map(|step| self.transformer(step))
+            .collect::<Result<Vec<_>, _>>()
+        {
+            Ok(transformers) => transformers,
+            Err(e) => return self.transform_failed(route, config, direction, e, body),
+        };
+        let content_type = headers.get(CONTENT_TYPE).and_then(|v| v.to_str().ok());
+        let transformers: Vec<_> = transformers
+            .into_iter()
+            .filter(|t| t.applies_to(content_type))
+            .collect();
+        if transformers.is_empty() {
+            return Ok(body);
+        }
+
+        let len = content_length(headers).or_else(|| {
+            body.size_hint()
+                .exact()
+                .and_then(|n| usize::try_from(n).ok())
+        });
+        match len {
+            Some(0) => return Ok(body),
+            Some(len) if len <= config.max_body_bytes => {}
+            _ => {
+                if let Some(metrics) = &self.metrics {
+                    metrics.transform_bypasses.fetch_add(1, Ordering::Relaxed);
+                }
+                debug!(
+                    route = %route.path_prefix,
+                    direction = direction.as_str(),
+                    ?len,
+                    "body too large to transform"
+                );
+                return Ok(body);
+            }
+        }
+        let original = axum::body::to_bytes(body, config.max_body_bytes)
+            .await
+            .map_err(|e| match direction {
+                Direction::Request => ProxyError::RequestError(e.to_string()),
+                Direction::Response => ProxyError::ConnectionFailed(e.to_string()),
+            })?;
+
+        let result = transformers
+            .iter()
+            .try_fold(original.clone(), |body, t| t.transform(headers, body));
+        match result {
+            Ok(transformed) => {
+                headers.remove(TRANSFER_ENCODING);
+                headers.insert(CONTENT_LENGTH, HeaderValue::from(transformed.len()));
+                Ok(Body::from(transformed))
+            }
+            Err(e) => self.transform_failed(route, config, direction, e, Body::from(original)),
+        }
+    }
+
+    fn transformer<'a>(
+        &'a self,
+        step: &'a TransformStep,
+    ) -> Result<&'a dyn Transformer, TransformError> {
+        match step {
+            TransformStep::JsonFields(fields) => Ok(fields),
+            TransformStep::HeaderClaims(claims) => Ok(claims),
+            TransformStep::Custom { name } => self
+                .transformers
+                .get(name)
+                .map(|t| t.as_ref())
+                .ok_or_else(|| TransformError(format!("no transformer named {name:?}"))),
+        }
+    }
+
+    /// Count and log a failed transformation, then pass `body` on or fail
+    /// as the route asks.
+    fn transform_failed(
+        &self,
+        route: &Route,
+        config: &TransformConfig,
+        direction: Direction,
+        error: TransformError,
+        body: Body,
+    ) -> Result<Body, ProxyError> {
+        if let Some(metrics) = &self.metrics {
+            metrics.transform_failures.fetch_add(1, Ordering::Relaxed);
+        }
+        warn!(
+            route = %route.path_prefix,
+            direction = direction.as_str(),
+            %error,
+            "body transformation failed"
+        );
+        match config.on_failure {
+            OnTransformFailure::BadGateway => Err(ProxyError::Transform),
+            OnTransformFailure::PassThrough => Ok(body),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metrics::GatewayMetrics;
+    use crate::proxy::testing::{MockResponse, MockUpstream};
+    use axum::response::IntoResponse;
+    use http::StatusCode;
+    use serde_json::json;
+    use std::sync::Arc;
+
+    fn json_request(body: &'static str) -> Request<Body> {
+        Request::post("/api/items")
+            .header(CONTENT_TYPE, "application/json")
+            .body(Body::from(body))
+            .unwrap()
+    }
+
+    async fn body_json(response: Response<Body>) -> Value {
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        serde_json::from_slice(&body).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_json_fields_rewrite_request_and_response() {
+        let mock = MockUpstream::start().await.unwrap();
+        mock.enqueue(
+            MockResponse::new(StatusCode::OK)
+                .with_header(CONTENT_TYPE, HeaderValue::from_static("application/json"))
+                .with_body(r#"{"id":7,"internal":{"shard":3,"owner":"a"}}"#),
+        );
+        let proxy = mock.proxy_service("/api");
+        let mut route = mock.route("/api");
+        route.transform = Some(TransformConfig {
+            request: vec![TransformStep::JsonFields(JsonFields {
+                set: BTreeMap::from([("meta.source".to_string(), json!("gateway"))]),
+                remove: vec!["debug".to_string(), "missing.field".to_string()],
+            })],
+            response: vec![TransformStep::JsonFields(JsonFields {
+                remove: vec!["internal.shard".to_string()],
+                ..Default::default()
+            })],
+            ..Default::default()
+        });
+
+        let req = json_request(r#"{"name":"x","debug":true}"#);
+        let response = proxy.forward(&route, req).await.unwrap();
+        let expected = r#"{"id":7,"internal":{"owner":"a"}}"#;
+        assert_eq!(
+            response.headers()[CONTENT_LENGTH],
+            expected.len().to_string()
+        );
+        assert_eq!(
+            body_json(response).await,
+            json!({"id": 7, "internal": {"owner": "a"}})
+        );
+
+        let sent = &mock.requests()[0];
+        let sent_json: Value = serde_json::from_slice(&sent.body).unwrap();
+        assert_eq!(
+            sent_json,
+            json!({"name": "x", "meta": {"source": "gateway"}})
+        );
+        assert_eq!(sent.headers[CONTENT_LENGTH], sent.body.len().to_string());
+    }
+
+    #[tokio::test]
+    async fn test_header_claims_copied_into_body() {
+        let mock = MockUpstream::start().await.unwrap();
+        let proxy = mock.proxy_service("/api");
+        let mut route = mock.route("/api");
+        route.transform = Some(TransformConfig {
+            request: vec![TransformStep::HeaderClaims(HeaderClaims {
+                claims: BTreeMap::from([
+                    ("x-user-id".to_string(), "claims.user".to_string()),
+                    ("x-tenant-id".to_string(), "claims.tenant".to_string()),
+                ]),
+            })],
+            ..Default::default()
+        });
+
+        let mut req = json_request(r#"{"claims":{"tenant":"forged"},"q":1}"#);
+        req.headers_mut()
+            .insert("x-user-id", HeaderValue::from_static("u-42"));
+        proxy.forward(&route, req).await.unwrap();
+
+        let sent: Value = serde_json::from_slice(&mock.requests()[0].body).unwrap();
+        assert_eq!(sent, json!({"claims": {"user": "u-42"}, "q": 1}));
+
+        // Bodies of other types are left alone.
+        let req = Request::post("/api/items")
+            .header(CONTENT_TYPE, "text/plain")
+            .header("x-user-id", "u-42")
+            .body(Body::from("not json"))
+            .unwrap();
+        proxy.forward(&route, req).await.unwrap();
+        assert_eq!(&mock.requests()[1].body[..], b"not json");
+    }
+
+    #[tokio::test]
+    async fn test_bodies_over_cap_bypass_transformation() {
+        let mock = MockUpstream::start().await.unwrap();
+        let metrics = Arc::new(GatewayMetrics::default());
+        let proxy = mock.proxy_service("/api").with_metrics(metrics.clone());
+        let mut route = mock.route("/api");
+        route.transform = Some(TransformConfig {
+            request: vec![TransformStep::JsonFields(JsonFields {
+                remove: vec!["secret".to_string()],
+                ..Default::default()
+            })],
+            max_body_bytes: 16,
+            ..Default::default()
+        });
+
+        let body = r#"{"secret":"s","padding":"0123456789"}"#;
+        proxy.forward(&route, json_request(body)).await.unwrap();
+        assert_eq!(&mock.requests()[0].body[..], body.as_bytes());
+        assert_eq!(metrics.transform_bypasses.load(Ordering::Relaxed), 1);
+        assert_eq!(metrics.transform_failures.load(Ordering::Relaxed), 0);
+
+        proxy
+            .forward(&route, json_request(r#"{"secret":"s"}"#))
+            .await
+            .unwrap();
+        assert_eq!(&mock.requests()[1].body[..], b"{}");
+        assert_eq!(metrics.transform_bypasses.load(Ordering::Relaxed), 1);
+    }
+
+    #[tokio::test]
+    async fn test_malformed_json_fails_or_passes_through() {
+        let mock = MockUpstream::start().await.unwrap();
+        let metrics = Arc::new(GatewayMetrics::default());
+        let proxy = mock.proxy_service("/api").with_metrics(metrics.clone());
+        let mut route = mock.route("/api");
+        route.transform = Some(TransformConfig {
+            request: vec![TransformStep::JsonFields(JsonFields {
+                remove: vec!["secret".to_string()],
+                ..Default::default()
+            })],
+            ..Default::default()
+        });
+
+        let err = proxy
+            .forward(&route, json_request(r#"{"secret":"#))
+            .await
+            .unwrap_err();
+        assert!(matches!(err, ProxyError::Transform));
+        let response = err.into_response();
+        assert_eq!(response.status(), StatusCode::BAD_GATEWAY);
+        assert_eq!(body_json(response).await["error_code"], "INTERNAL");
+        assert!(mock.requests().is_empty());
+
+        route.transform.as_mut().unwrap().on_failure = OnTransformFailure::PassThrough;
+        proxy
+            .forward(&route, json_request(r#"{"secret":"#))
+            .await
+            .unwrap();
+        assert_eq!(&mock.requests()[0].body[..], br#"{"secret":"#);
+        assert_eq!(metrics.transform_failures.load(Ordering::Relaxed), 2);
+    }
+}
@@ -0,0 +1,257 @@
+//! HTTPS to upstreams with [`Upstream::tls`] set.
+//!
+//! Every upstream request opens a new connection, so without resumption
+//! each one pays for a full handshake, PQC key exchange included. The
+//! client keeps the session tickets and IDs upstreams hand out in a bounded
+//! in-memory cache shared by the [`ProxyService`](super::ProxyService), and
+//! offers them on the next connection to the same host, which then skips
+//! the key exchange and certificate checks. Sessions from upstreams that
+//! skip certificate verification are cached apart, so they are never
+//! resumed with an upstream that requires it.
+
+use http::Uri;
+use hyper_rustls::HttpsConnector;
+use rustls::client::Resumption;
+use rustls::crypto::aws_lc_rs;
+use rustls::pki_types::ServerName;
+use rustls::{ClientConfig, RootCertStore};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+use super::egress::{EgressConnector, EgressProxy};
+use super::Upstream;
+use crate::scanner::AnyCertificate;
+
+/// Default limit on the TLS sessions kept for resumption.
+pub const DEFAULT_SESSION_CACHE_SIZE: usize = 256;
+
+/// TLS settings for connections to upstreams.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct UpstreamTlsConfig {
+    /// Resume earlier sessions with an upstream instead of doing a full
+    /// handshake.
+    pub session_resumption: bool,
+    /// Most sessions kept, across all upstreams, for those that verify
+    /// certificates and again for those that don't.
+    pub session_cache_size: usize,
+}
+
+impl Default for UpstreamTlsConfig {
+    fn default() -> Self {
+        Self {
+            session_resumption: true,
+            session_cache_size: DEFAULT_SESSION_CACHE_SIZE,
+        }
+    }
+}
+
+/// Client TLS configurations, each holding its session cache.
+pub(super) struct UpstreamTls {
+    verified: ClientConfig,
+    unverified: ClientConfig,
+}
+
+impl UpstreamTls {
+    pub(super) fn new(config: &UpstreamTlsConfig) -> Self {
+        let provider = Arc::new(aws_lc_rs::default_provider());
+        let builder = ClientConfig::builder_with_provider(provider.clone())
+            .with_safe_default_protocol_versions()
+            .expect("the default provider supports the default versions");
+        let mut verified = builder
+            .clone()
+            .with_root_certificates(RootCertStore {
+                roots: webpki_roots::TLS_SERVER_ROOTS.to_vec(),
+            })
+            .with_no_client_auth();
+        let mut unverified = builder
+            .dangerous()
+            .with_custom_certificate_verifier(Arc::new(AnyCertificate(provider)))
+            .with_no_client_auth();
+        for client in [&mut verified, &mut unverified] {
+            client.resumption = if config.session_resumption {
+                Resumption::in_memory_sessions(config.session_cache_size)
+            } else {
+                Resumption::disabled()
+            };
+        }
+        Self {
+            verified,
+            unverified,
+        }
+    }
+
+    /// A connector for `upstream`, through `egress` if set, speaking TLS
+    /// for `https` URIs and checking the certificate against the upstream's
+    /// host rather than the resolved address the URI carries.
+    pub(super) fn connector(
+        &self,
+        upstream: &Upstream,
+        egress: Option<EgressProxy>,
+    ) -> HttpsConnector<EgressConnector> {
+        let config = if upstream.tls_verify {
+            &self.verified
+        } else {
+            &self.unverified
+        };
+        let host = upstream.host.clone();
+        hyper_rustls::HttpsConnectorBuilder::new()
+            .with_tls_config(config.clone())
+            .https_or_http()
+            .with_server_name_resolver(move |_: &Uri| ServerName::try_from(host.clone()))
+            .enable_http1()
+            .wrap_connector(EgressConnector::new(egress))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::proxy::{MiddlewareProfile, ProxyService, Route};
+    use axum::body::Body;
+    use http::{Request, Response, StatusCode};
+    use hyper::service::service_fn;
+    use hyper_util::rt::TokioIo;
+    use rustls::pki_types::pem::PemObject;
+    use rustls::pki_types::{CertificateDer, PrivateKeyDer};
+    use rustls::{HandshakeKind, ServerConfig};
+    use std::net::SocketAddr;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use tokio::net::TcpListener;
+    use tokio_rustls::TlsAcceptor;
+
+    /// Handshakes completed by a [`tls_upstream`], by kind.
+    #[derive(Default)]
+    struct Handshakes {
+        full: AtomicUsize,
+        resumed: AtomicUsize,
+    }
+
+    /// An HTTPS upstream answering `200` with a self-signed certificate,
+    /// counting its handshakes.
+    async fn tls_upstream() -> (SocketAddr, Arc<Handshakes>) {
+        let dir = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("testdata/scanner");
+        let certs = CertificateDer::pem_file_iter(dir.join("ecdsa-p256.pem"))
+            .unwrap()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        let key = PrivateKeyDer::from_pem_file(dir.join("ecdsa-p256.key")).unwrap();
+        let config = ServerConfig::builder_with_provider(Arc::new(aws_lc_rs::default_provider()))
+            .with_safe_default_protocol_versions()
+            .unwrap()
+            .with_no_client_auth()
+            .with_single_cert(certs, key)
+            .unwrap();
+        let acceptor = TlsAcceptor::from(Arc::new(config));
+        let handshakes = Arc::new(Handshakes::default());
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let counts = handshakes.clone();
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                let acceptor = acceptor.clone();
+                let counts = counts.clone();
+                tokio::spawn(async move {
+                    let Ok(stream) = acceptor.accept(stream).await else {
+                        return;
+                    };
+                    match stream.get_ref().1.handshake_kind() {
+                        Some(HandshakeKind::Resumed) => &counts.resumed,
+                        _ => &counts.full,
+                    }
+                    .fetch_add(1, Ordering::SeqCst);
+                    let service = service_fn(|_req| async {
+                        Ok::<_, std::convert::Infallible>(Response::new(Body::empty()))
+                    });
+                    let _ = hyper::server::conn::http1::Builder::new()
+                        .serve_connection(TokioIo::new(stream), service)
+                        .await;
+                });
+            }
+        });
+        (addr, handshakes)
+    }
+
+    fn route(addr: SocketAddr) -> Route {
+        Route {
+            path_prefix: "/secure".into(),
+            upstream: Upstream {
+                name: "secure".into(),
+                host: addr.ip().to_string(),
+                port: addr.port(),
+                is_healthy: true,
+                tls_verify: false,
+                tls: true,
+                weight: 1,
+            },
+            strip_prefix: false,
+            path_rewrite: None,
+            priority: 0,
+            coalesce: None,
+            cache: None,
+            tunnel: None,
+            unseal: None,
+            transform: None,
+            sse_filter: None,
+            allowed_methods: Vec::new(),
+            denied_methods: Vec::new(),
+            content_type: None,
+            middleware_profile: MiddlewareProfile::Default,
+        }
+    }
+
+    /// Send two requests through a proxy with `config` and return the
+    /// upstream's (full, resumed) handshake counts.
+    async fn handshakes_for_two_requests(config: UpstreamTlsConfig) -> (usize, usize) {
+        let (addr, handshakes) = tls_upstream().await;
+        let route = route(addr);
+        let proxy = ProxyService::new(vec![route.clone()], 5).with_upstream_tls(config);
+        for _ in 0..2 {
+            let req = Request::get("/secure").body(Body::empty()).unwrap();
+            let response = proxy.forward(&route, req).await.unwrap();
+            assert_eq!(response.status(), StatusCode::OK);
+        }
+        (
+            handshakes.full.load(Ordering::SeqCst),
+            handshakes.resumed.load(Ordering::SeqCst),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_second_connection_resumes_the_session() {
+        assert_eq!(
+            handshakes_for_two_requests(UpstreamTlsConfig::default()).await,
+            (1, 1)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_disabled_resumption_does_full_handshakes() {
+        let config = UpstreamTlsConfig {
+            session_resumption: false,
+            ..UpstreamTlsConfig::default()
+        };
+        assert_eq!(handshakes_for_two_requests(config).await, (2, 0));
+    }
+
+    #[tokio::test]
+    async fn test_tls_through_egress_proxy() {
+        use crate::proxy::egress::EgressConfig;
+        use crate::proxy::testing::MockConnectProxy;
+
+        let (addr, handshakes) = tls_upstream().await;
+        let egress = MockConnectProxy::start(None).await.unwrap();
+        let route = route(addr);
+        let proxy = ProxyService::new(vec![route.clone()], 5).with_egress(EgressConfig {
+            default: Some(egress.egress_proxy()),
+            ..EgressConfig::default()
+        });
+
+        let req = Request::get("/secure").body(Body::empty()).unwrap();
+        let response = proxy.forward(&route, req).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(handshakes.full.load(Ordering::SeqCst), 1);
+        assert!(egress.requests()[0].starts_with(&format!("CONNECT {addr} ")));
+    }
+}
@@ -0,0 +1,230 @@
+//! Per-client rate limiting.
+//!
+//! [`rate_limit_middleware`] gives each client a token bucket of
+//! [`RateLimit::burst`] requests, refilled at
+//! [`RateLimit::requests_per_second`], on routes whose profile rate limits.
+//! Clients are told apart by their [`TrueClientIp`], so the limit follows
+//! the client rather than the last proxy when `trusted_proxies` is set.
+//! Requests without one share a single bucket. A client whose bucket is
+//! empty gets `429 Too Many Requests` with a `Retry-After` header.
+//!
+//! [`TenantPolicy`](crate::tenant::TenantPolicy) limits tenants with the
+//! same buckets.
+
+use axum::{
+    body::Body,
+    extract::State,
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use http::{header, Request, StatusCode};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::net::IpAddr;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use thiserror::Error;
+use tracing::info;
+
+use crate::middleware::TrueClientIp;
+
+/// Buckets kept before full ones are dropped.
+const MAX_TRACKED_BUCKETS: usize = 10_000;
+
+/// Requests each client may make: `burst` at once, refilled at
+/// `requests_per_second`.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RateLimit {
+    pub requests_per_second: f64,
+    pub burst: u32,
+}
+
+impl RateLimit {
+    /// Whether the limit refuses every request.
+    pub(crate) fn is_empty(self) -> bool {
+        self.burst == 0 || self.requests_per_second <= 0.0
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum RateLimitError {
+    #[error("rate limit must allow at least one request")]
+    Empty,
+}
+
+/// The per-client buckets of [`rate_limit_middleware`].
+#[derive(Debug)]
+pub struct RateLimiter {
+    limit: RateLimit,
+    buckets: Buckets<Option<IpAddr>>,
+}
+
+impl RateLimiter {
+    pub fn new(limit: RateLimit) -> Result<Self, RateLimitError> {
+        if limit.is_empty() {
+            return Err(RateLimitError::Empty);
+        }
+        Ok(Self {
+            limit,
+            buckets: Buckets::default(),
+        })
+    }
+
+    pub fn limit(&self) -> RateLimit {
+        self.limit
+    }
+
+    /// Take a request from `client`'s bucket, or return how long until one
+    /// is available.
+    pub fn try_acquire(&self, client: Option<IpAddr>) -> Result<(), Duration> {
+        self.buckets.try_acquire(&client, self.limit)
+    }
+}
+
+pub async fn rate_limit_middleware(
+    State(limiter): State<Arc<RateLimiter>>,
+    req: Request<Body>,
+    next: Next,
+) -> Response {
+    let client = req.extensions().get::<TrueClientIp>().map(|ip| ip.0);
+    if let Err(retry_after) = limiter.try_acquire(client) {
+        info!(client = ?client, "rate limit exceeded");
+        return too_many_requests(retry_after, "rate limit exceeded");
+    }
+    next.run(req).await
+}
+
+/// `429 Too Many Requests` telling the client to retry after `retry_after`,
+/// rounded up to whole seconds.
+pub(crate) fn too_many_requests(retry_after: Duration, message: &'static str) -> Response {
+    let retry_after = retry_after.as_secs_f64().ceil().max(1.0) as u64;
+    (
+        StatusCode::TOO_MANY_REQUESTS,
+        [(header::RETRY_AFTER, retry_after.to_string())],
+        message,
+    )
+        .into_response()
+}
+
+/// Token buckets by key.
+#[derive(Debug)]
+pub(crate) struct Buckets<K> {
+    buckets: Mutex<HashMap<K, Bucket>>,
+}
+
+impl<K> Default for Buckets<K> {
+    fn default() -> Self {
+        Self {
+            buckets: Mutex::new(HashMap::new()),
+        }
+    }
+}
+
+impl<K: Clone + Eq + Hash> Buckets<K> {
+    /// Take a request from `key`'s bucket, or return how long until one is
+    /// available. `limit` must not be empty.
+    pub(crate) fn try_acquire(&self, key: &K, limit: RateLimit) -> Result<(), Duration> {
+        let now = Instant::now();
+        let mut buckets = self.buckets.lock().unwrap();
+        if buckets.len() >= MAX_TRACKED_BUCKETS && !buckets.contains_key(key) {
+            // A full bucket is the same as a new one, so dropping it loses
+            // nothing.
+            buckets.retain(|_, bucket| bucket.refill(now, limit) < f64::from(limit.burst));
+        }
+        let bucket = buckets.entry(key.clone()).or_insert(Bucket {
+            tokens: f64::from(limit.burst),
+            updated: now,
+        });
+        if bucket.refill(now, limit) >= 1.0 {
+            bucket.tokens -= 1.0;
+            Ok(())
+        } else {
+            Err(Duration::from_secs_f64(
+                (1.0 - bucket.tokens) / limit.requests_per_second,
+            ))
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+impl Bucket {
+    /// Add the tokens earned since the last refill; returns the new count.
+    fn refill(&mut self, now: Instant, limit: RateLimit) -> f64 {
+        let earned = now.duration_since(self.updated).as_secs_f64() * limit.requests_per_second;
+        self.tokens = (self.tokens + earned).min(f64::from(limit.burst));
+        self.updated = now;
+        self.tokens
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::middleware::from_fn_with_state;
+    use axum::routing::get;
+    use axum::Router;
+    use tower::ServiceExt;
+
+    fn limiter(requests_per_second: f64, burst: u32) -> Arc<RateLimiter> {
+        let limit = RateLimit {
+            requests_per_second,
+            burst,
+        };
+        Arc::new(RateLimiter::new(limit).unwrap())
+    }
+
+    #[test]
+    fn test_rejects_empty_limits() {
+        for (requests_per_second, burst) in [(1.0, 0), (0.0, 1), (-1.0, 1)] {
+            let limit = RateLimit {
+                requests_per_second,
+                burst,
+            };
+            assert_eq!(RateLimiter::new(limit).unwrap_err(), RateLimitError::Empty);
+        }
+    }
+
+    #[test]
+    fn test_buckets_are_per_client() {
+        let limiter = limiter(0.001, 2);
+        let (noisy, quiet) = (Some([10, 0, 0, 1].into()), Some([10, 0, 0, 2].into()));
+
+        assert!(limiter.try_acquire(noisy).is_ok());
+        assert!(limiter.try_acquire(noisy).is_ok());
+        let retry_after = limiter.try_acquire(noisy).unwrap_err();
+        assert!(retry_after > Duration::from_secs(900), "{retry_after:?}");
+
+        assert!(limiter.try_acquire(quiet).is_ok());
+        // Clients without an address share a bucket.
+        assert!(limiter.try_acquire(None).is_ok());
+        assert!(limiter.try_acquire(None).is_ok());
+        assert!(limiter.try_acquire(None).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_middleware_keys_on_true_client_ip() {
+        let app = Router::new()
+            .route("/api", get(|| async { "ok" }))
+            .layer(from_fn_with_state(limiter(0.001, 1), rate_limit_middleware));
+        let request = |ip: [u8; 4]| {
+            let mut req = Request::get("/api").body(Body::empty()).unwrap();
+            req.extensions_mut().insert(TrueClientIp(ip.into()));
+            req
+        };
+
+        let response = app.clone().oneshot(request([1, 2, 3, 4])).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let response = app.clone().oneshot(request([1, 2, 3, 4])).await.unwrap();
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert!(response.headers().contains_key(header::RETRY_AFTER));
+        let response = app.oneshot(request([5, 6, 7, 8])).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+}
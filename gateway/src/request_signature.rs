@@ -0,0 +1,623 @@
+//! Verification of signed requests from registered clients.
+//!
+//! Partners with a [`ClientSigningKey`] on their [`ApiKey`] sign requests
+//! in the style of HTTP message signatures (RFC 9421), mirroring
+//! [response signing](crate::response_signing):
+//!
+//! ```text
+//! "@method": POST
+//! "@path": /orders
+//! "content-digest": sha-256=:<base64>:
+//! "@signature-params": ("@method" "@path" "content-digest");created=1792314000;expires=1792314060;alg="ed25519"
+//! ```
+//!
+//! The request carries `Content-Digest`, `X-QSGW-Signature-Input`, and
+//! `X-QSGW-Signature`. On the configured paths,
+//! [`request_signature_middleware`] looks up the client's key by the
+//! [`AuthenticatedKey`] set by [`auth_middleware`](crate::auth::auth_middleware),
+//! or by `x-api-key` where auth is not enforced. It requires `@method`,
+//! `@path`, `content-digest`, and [`RequestSignatureConfig::covered_headers`]
+//! to be covered, checks the `created`/`expires` window, digests the body
+//! as it is read up to [`RequestSignatureConfig::max_body_bytes`], and
+//! verifies the signature. Failures are answered with `401` and the
+//! [`RequestSignatureError::reason`] in the `reason` field.
+
+use axum::body::Body;
+use axum::extract::State;
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use ed25519_dalek::{Signature, VerifyingKey};
+use http::{request, HeaderName, Request, StatusCode};
+use hyper::body::Body as HttpBody;
+use quantun_crypto::mldsa::{MlDsaSignature, MlDsaVerifier};
+use quantun_types::{ErrorCode, MlDsaVariant};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use thiserror::Error;
+use tracing::warn;
+
+use crate::auth::{ApiKey, AuthenticatedKey, PathMatcher};
+use crate::middleware::TrueClientIp;
+use crate::response_signing::{read_body, SIGNATURE_HEADER, SIGNATURE_INPUT_HEADER};
+
+/// Default limit on the size of a verified request body.
+pub const DEFAULT_MAX_VERIFIED_BODY: usize = 4 * 1024 * 1024;
+
+const CONTENT_DIGEST: HeaderName = HeaderName::from_static("content-digest");
+/// Components every signature must cover.
+const REQUIRED_COMPONENTS: [&str; 3] = ["@method", "@path", "content-digest"];
+
+/// Which requests must be signed, and how strictly.
+#[derive(Debug, Clone)]
+pub struct RequestSignatureConfig {
+    /// Request path prefixes that require a signature.
+    pub paths: Vec<String>,
+    /// Headers every signature must cover, besides `@method`, `@path`,
+    /// and `content-digest`.
+    pub covered_headers: Vec<String>,
+    /// Oldest `created` accepted, and the lifetime of signatures without
+    /// `expires`.
+    pub max_age: Duration,
+    /// How far ahead of the gateway's clock `created` may be.
+    pub clock_skew: Duration,
+    /// Largest body that is read and verified.
+    pub max_body_bytes: usize,
+}
+
+impl Default for RequestSignatureConfig {
+    fn default() -> Self {
+        Self {
+            paths: Vec::new(),
+            covered_headers: Vec::new(),
+            max_age: Duration::from_secs(300),
+            clock_skew: Duration::from_secs(30),
+            max_body_bytes: DEFAULT_MAX_VERIFIED_BODY,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ClientKeyAlgorithm {
+    MlDsa(MlDsaVariant),
+    Ed25519,
+}
+
+/// The `alg` signature parameter: `ML-DSA-65` or `ed25519`.
+impl fmt::Display for ClientKeyAlgorithm {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ClientKeyAlgorithm::MlDsa(variant) => variant.fmt(f),
+            ClientKeyAlgorithm::Ed25519 => f.write_str("ed25519"),
+        }
+    }
+}
+
+/// A client's registered request-signing public key.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ClientSigningKey {
+    pub algorithm: ClientKeyAlgorithm,
+    pub public_key: Vec<u8>,
+}
+
+impl ClientSigningKey {
+    /// Whether `signature` over `message` verifies under this key.
+    pub fn verify(&self, message: &[u8], signature: &[u8]) -> bool {
+        match self.algorithm {
+            ClientKeyAlgorithm::MlDsa(variant) => {
+                let verifier = MlDsaVerifier {
+                    variant,
+                    public_key: self.public_key.clone(),
+                };
+                let signature = MlDsaSignature {
+                    signature: signature.to_vec(),
+                    variant,
+                };
+                matches!(verifier.verify(message, &signature), Ok(true))
+            }
+            ClientKeyAlgorithm::Ed25519 => {
+                let Ok(key) = <[u8; 32]>::try_from(self.public_key.as_slice()) else {
+                    return false;
+                };
+                let (Ok(key), Ok(signature)) = (
+                    VerifyingKey::from_bytes(&key),
+                    Signature::from_slice(signature),
+                ) else {
+                    return false;
+                };
+                key.verify_strict(message, &signature).is_ok()
+            }
+        }
+    }
+}
+
+/// Why a request signature was rejected.
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum RequestSignatureError {
+    #[error("client has no registered signing key")]
+    UnknownClient,
+    #[error("missing {0} header")]
+    MissingHeader(String),
+    #[error("malformed {0}")]
+    Malformed(&'static str),
+    #[error("signature does not cover {0}")]
+    UncoveredComponent(String),
+    #[error("signature created in the future")]
+    NotYetValid,
+    #[error("signature expired")]
+    Expired,
+    #[error("signature algorithm does not match the client's key")]
+    AlgorithmMismatch,
+    #[error("body larger than {0} bytes")]
+    BodyTooLarge(usize),
+    #[error("failed to read body")]
+    BodyError,
+    #[error("body does not match content-digest")]
+    DigestMismatch,
+    #[error("invalid signature")]
+    InvalidSignature,
+}
+
+impl RequestSignatureError {
+    /// Stable identifier sent in the `reason` field of the rejection.
+    pub fn reason(&self) -> &'static str {
+        match self {
+            Self::UnknownClient => "unknown-client",
+            Self::MissingHeader(_) => "missing-header",
+            Self::Malformed(_) => "malformed-signature",
+            Self::UncoveredComponent(_) => "uncovered-component",
+            Self::NotYetValid => "not-yet-valid",
+            Self::Expired => "expired",
+            Self::AlgorithmMismatch => "algorithm-mismatch",
+            Self::BodyTooLarge(_) => "body-too-large",
+            Self::BodyError => "body-error",
+            Self::DigestMismatch => "digest-mismatch",
+            Self::InvalidSignature => "invalid-signature",
+        }
+    }
+
+    fn status(&self) -> StatusCode {
+        match self {
+            Self::BodyTooLarge(_) => StatusCode::PAYLOAD_TOO_LARGE,
+            Self::BodyError => StatusCode::BAD_REQUEST,
+            _ => StatusCode::UNAUTHORIZED,
+        }
+    }
+}
+
+/// [`RequestSignatureConfig`] with the client keys it verifies against,
+/// shared with [`request_signature_middleware`] as router state.
+#[derive(Debug)]
+pub struct RequestSignatureVerifier {
+    paths: PathMatcher,
+    required: Vec<String>,
+    max_age: u64,
+    clock_skew: u64,
+    max_body_bytes: usize,
+    keys: HashMap<String, ClientSigningKey>,
+}
+
+impl RequestSignatureVerifier {
+    /// Verify requests on `config.paths` against the signing keys
+    /// registered on `api_keys`.
+    pub fn new(config: &RequestSignatureConfig, api_keys: &[ApiKey]) -> Self {
+        let required = REQUIRED_COMPONENTS
+            .iter()
+            .map(|c| c.to_string())
+            .chain(
+                config
+                    .covered_headers
+                    .iter()
+                    .map(|h| h.to_ascii_lowercase()),
+            )
+            .collect();
+        let keys = api_keys
+            .iter()
+            .filter_map(|k| Some((k.id.clone(), k.signing_key.clone()?)))
+            .collect();
+        Self {
+            paths: PathMatcher::new(&config.paths),
+            required,
+            max_age: config.max_age.as_secs(),
+            clock_skew: config.clock_skew.as_secs(),
+            max_body_bytes: config.max_body_bytes,
+            keys,
+        }
+    }
+
+    /// Verify `req` at `now` (Unix seconds) and return it with its body
+    /// intact.
+    pub async fn verify(
+        &self,
+        req: Request<Body>,
+        now: u64,
+    ) -> Result<Request<Body>, RequestSignatureError> {
+        let key = self.client_key(&req)?;
+        let (parts, body) = req.into_parts();
+
+        let input = SignatureInput::parse(header(&parts, &SIGNATURE_INPUT_HEADER)?)?;
+        if let Some(missing) = self
+            .required
+            .iter()
+            .find(|c| !input.components.contains(&c.as_str()))
+        {
+            return Err(RequestSignatureError::UncoveredComponent(missing.clone()));
+        }
+        if input.created > now.saturating_add(self.clock_skew) {
+            return Err(RequestSignatureError::NotYetValid);
+        }
+        let expires = input.created.saturating_add(self.max_age);
+        if now > input.expires.map_or(expires, |e| e.min(expires)) {
+            return Err(RequestSignatureError::Expired);
+        }
+        if input
+            .alg
+            .is_some_and(|alg| alg != key.algorithm.to_string())
+        {
+            return Err(RequestSignatureError::AlgorithmMismatch);
+        }
+        let signature = header(&parts, &SIGNATURE_HEADER)?
+            .strip_prefix("sig1=:")
+            .and_then(|s| s.strip_suffix(':'))
+            .and_then(|s| STANDARD.decode(s).ok())
+            .ok_or(RequestSignatureError::Malformed("x-qsgw-signature header"))?;
+        let base = signature_base(&parts, &input)?;
+
+        if body
+            .size_hint()
+            .exact()
+            .is_some_and(|len| len > self.max_body_bytes as u64)
+        {
+            return Err(RequestSignatureError::BodyTooLarge(self.max_body_bytes));
+        }
+        let (replay, digest) = read_body(body, self.max_body_bytes).await;
+        let Some(digest) = digest else {
+            return Err(if replay.error.is_some() {
+                RequestSignatureError::BodyError
+            } else {
+                RequestSignatureError::BodyTooLarge(self.max_body_bytes)
+            });
+        };
+        if header(&parts, &CONTENT_DIGEST)? != format!("sha-256=:{}:", STANDARD.encode(digest)) {
+            return Err(RequestSignatureError::DigestMismatch);
+        }
+
+        if !key.verify(base.as_bytes(), &signature) {
+            return Err(RequestSignatureError::InvalidSignature);
+        }
+        Ok(Request::from_parts(parts, Body::new(replay)))
+    }
+
+    fn client_key(&self, req: &Request<Body>) -> Result<&ClientSigningKey, RequestSignatureError> {
+        let id = match req.extensions().get::<AuthenticatedKey>() {
+            Some(key) => Some(key.id.as_str()),
+            None => req.headers().get("x-api-key").and_then(|v| v.to_str().ok()),
+        };
+        id.and_then(|id| self.keys.get(id))
+            .ok_or(RequestSignatureError::UnknownClient)
+    }
+}
+
+/// Reject requests on the configured paths whose signature does not
+/// verify.
+pub async fn request_signature_middleware(
+    State(verifier): State<Arc<RequestSignatureVerifier>>,
+    req: Request<Body>,
+    next: Next,
+) -> Response {
+    if !verifier.paths.matches(req.uri().path()) {
+        return next.run(req).await;
+    }
+    let client_ip = req.extensions().get::<TrueClientIp>().map(|ip| ip.0);
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    match verifier.verify(req, now).await {
+        Ok(req) => next.run(req).await,
+        Err(e) => {
+            warn!(
+                ?client_ip,
+                reason = e.reason(),
+                "request signature rejected"
+            );
+            let error_code = match e.status() {
+                StatusCode::UNAUTHORIZED => ErrorCode::Unauthenticated,
+                _ => ErrorCode::InvalidArgument,
+            };
+            (
+                e.status(),
+                Json(serde_json::json!({
+                    "error_code": error_code.as_str(),
+                    "reason": e.reason(),
+                    "message": e.to_string(),
+                })),
+            )
+                .into_response()
+        }
+    }
+}
+
+fn header<'a>(
+    parts: &'a request::Parts,
+    name: &HeaderName,
+) -> Result<&'a str, RequestSignatureError> {
+    parts
+        .headers
+        .get(name)
+        .ok_or_else(|| RequestSignatureError::MissingHeader(name.to_string()))?
+        .to_str()
+        .map_err(|_| RequestSignatureError::Malformed("header value"))
+}
+
+/// Parsed `X-QSGW-Signature-Input`.
+struct SignatureInput<'a> {
+    components: Vec<&'a str>,
+    /// Everything after `sig1=`, as it appears in the signature base.
+    params: &'a str,
+    created: u64,
+    expires: Option<u64>,
+    alg: Option<&'a str>,
+}
+
+impl<'a> SignatureInput<'a> {
+    fn parse(value: &'a str) -> Result<Self, RequestSignatureError> {
+        let malformed = || RequestSignatureError::Malformed("x-qsgw-signature-input header");
+        let params = value.strip_prefix("sig1=").ok_or_else(malformed)?;
+        let (list, rest) = params
+            .strip_prefix('(')
+            .and_then(|p| p.split_once(')'))
+            .ok_or_else(malformed)?;
+        let components = list
+            .split_whitespace()
+            .map(|c| c.strip_prefix('"')?.strip_suffix('"'))
+            .collect::<Option<Vec<_>>>()
+            .ok_or_else(malformed)?;
+
+        let (mut created, mut expires, mut alg) = (None, None, None);
+        if !rest.is_empty() {
+            for param in rest.strip_prefix(';').ok_or_else(malformed)?.split(';') {
+                let (name, value) = param.split_once('=').ok_or_else(malformed)?;
+                match name {
+                    "created" => created = Some(value.parse().map_err(|_| malformed())?),
+                    "expires" => expires = Some(value.parse().map_err(|_| malformed())?),
+                    "alg" => {
+                        let value = value.strip_prefix('"').and_then(|v| v.strip_suffix('"'));
+                        alg = Some(value.ok_or_else(malformed)?);
+                    }
+                    _ => {}
+                }
+            }
+        }
+        Ok(Self {
+            components,
+            params,
+            created: created.ok_or_else(malformed)?,
+            expires,
+            alg,
+        })
+    }
+}
+
+fn signature_base(
+    parts: &request::Parts,
+    input: &SignatureInput<'_>,
+) -> Result<String, RequestSignatureError> {
+    let mut base = String::new();
+    for &component in &input.components {
+        let value = match component {
+            "@method" => parts.method.to_string(),
+            "@path" => parts.uri.path().to_string(),
+            "@query" => format!("?{}", parts.uri.query().unwrap_or("")),
+            derived if derived.starts_with('@') => {
+                return Err(RequestSignatureError::Malformed("covered component"))
+            }
+            name => {
+                let values = parts
+                    .headers
+                    .get_all(name)
+                    .iter()
+                    .map(|v| v.to_str().map(str::trim))
+                    .collect::<Result<Vec<_>, _>>()
+                    .map_err(|_| RequestSignatureError::Malformed("header value"))?;
+                if values.is_empty() {
+                    return Err(RequestSignatureError::MissingHeader(name.to_string()));
+                }
+                values.join(", ")
+            }
+        };
+        base.push_str(&format!("\"{component}\": {value}\n"));
+    }
+    base.push_str(&format!("\"@signature-params\": {}", input.params));
+    Ok(base)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::routing::post;
+    use axum::Router;
+    use ed25519_dalek::{Signer, SigningKey};
+    use http_body_util::BodyExt;
+    use quantun_crypto::mldsa::MlDsaKeyPair;
+    use sha2::{Digest, Sha256};
+    use tower::ServiceExt;
+
+    const NOW: u64 = 1_700_000_000;
+
+    enum ClientKey {
+        Ed25519(SigningKey),
+        MlDsa(MlDsaKeyPair),
+    }
+
+    impl ClientKey {
+        fn registered(&self) -> ClientSigningKey {
+            match self {
+                ClientKey::Ed25519(key) => ClientSigningKey {
+                    algorithm: ClientKeyAlgorithm::Ed25519,
+                    public_key: key.verifying_key().to_bytes().to_vec(),
+                },
+                ClientKey::MlDsa(key) => ClientSigningKey {
+                    algorithm: ClientKeyAlgorithm::MlDsa(key.variant),
+                    public_key: key.public_key.clone(),
+                },
+            }
+        }
+
+        fn sign(&self, message: &[u8]) -> Vec<u8> {
+            match self {
+                ClientKey::Ed25519(key) => key.sign(message).to_bytes().to_vec(),
+                ClientKey::MlDsa(key) => key.sign(message).unwrap().signature,
+            }
+        }
+    }
+
+    fn verifier(key: &ClientKey) -> RequestSignatureVerifier {
+        let config = RequestSignatureConfig {
+            paths: vec!["/orders".into()],
+            covered_headers: vec!["X-Partner-Ref".into()],
+            ..RequestSignatureConfig::default()
+        };
+        let api_key = ApiKey {
+            id: "partner-key".into(),
+            name: "partner".into(),
+            scopes: vec!["orders".into()],
+            signing_key: Some(key.registered()),
+            metadata: HashMap::new(),
+            secret: None,
+        };
+        RequestSignatureVerifier::new(&config, &[api_key])
+    }
+
+    /// A `POST /orders` signed by `key`, created at `created`.
+    fn signed_request(key: &ClientKey, created: u64, body: &'static str) -> Request<Body> {
+        let alg = key.registered().algorithm;
+        let input = format!(
+            r#"sig1=("@method" "@path" "content-digest" "x-partner-ref");created={created};expires={};alg="{alg}""#,
+            created + 60
+        );
+        let req = Request::post("/orders")
+            .header("x-api-key", "partner-key")
+            .header("x-partner-ref", "po-1001")
+            .header(
+                CONTENT_DIGEST,
+                format!("sha-256=:{}:", STANDARD.encode(Sha256::digest(body))),
+            )
+            .header(SIGNATURE_INPUT_HEADER, &input)
+            .body(Body::from(body))
+            .unwrap();
+        let (mut parts, body) = req.into_parts();
+        let base = signature_base(&parts, &SignatureInput::parse(&input).unwrap()).unwrap();
+        let signature = format!("sig1=:{}:", STANDARD.encode(key.sign(base.as_bytes())));
+        parts
+            .headers
+            .insert(SIGNATURE_HEADER, signature.parse().unwrap());
+        Request::from_parts(parts, body)
+    }
+
+    fn ed25519_key(seed: u8) -> ClientKey {
+        ClientKey::Ed25519(SigningKey::from_bytes(&[seed; 32]))
+    }
+
+    #[tokio::test]
+    async fn valid_signatures_verify() {
+        let keys = [
+            ed25519_key(7),
+            ClientKey::MlDsa(MlDsaKeyPair::generate(MlDsaVariant::MlDsa44).unwrap()),
+        ];
+        for key in keys {
+            let app = Router::new()
+                .route("/orders", post(|body: String| async move { body }))
+                .layer(axum::middleware::from_fn_with_state(
+                    Arc::new(verifier(&key)),
+                    request_signature_middleware,
+                ));
+
+            let now = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap()
+                .as_secs();
+            let req = signed_request(&key, now, r#"{"sku":"a-1"}"#);
+            let response = app.clone().oneshot(req).await.unwrap();
+            assert_eq!(response.status(), StatusCode::OK);
+            let body = response.into_body().collect().await.unwrap().to_bytes();
+            assert_eq!(body, r#"{"sku":"a-1"}"#);
+
+            let req = signed_request(&key, NOW, "{}");
+            let response = app.oneshot(req).await.unwrap();
+            assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+            let body = response.into_body().collect().await.unwrap().to_bytes();
+            let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+            assert_eq!(json["error_code"], "UNAUTHENTICATED");
+            assert_eq!(json["reason"], "expired");
+        }
+    }
+
+    #[tokio::test]
+    async fn freshness_window_is_enforced() {
+        let key = ed25519_key(7);
+        let verifier = verifier(&key);
+        let verify = |created, now| verifier.verify(signed_request(&key, created, "{}"), now);
+
+        assert_eq!(
+            verify(NOW, NOW + 61).await.unwrap_err(),
+            RequestSignatureError::Expired
+        );
+        assert_eq!(
+            verify(NOW + 31, NOW).await.unwrap_err(),
+            RequestSignatureError::NotYetValid
+        );
+        assert!(verify(NOW + 30, NOW).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn wrong_key_is_rejected() {
+        let registered = ed25519_key(7);
+        let req = signed_request(&ed25519_key(8), NOW, "{}");
+        assert_eq!(
+            verifier(&registered).verify(req, NOW).await.unwrap_err(),
+            RequestSignatureError::InvalidSignature
+        );
+
+        let mldsa = ClientKey::MlDsa(MlDsaKeyPair::generate(MlDsaVariant::MlDsa44).unwrap());
+        let req = signed_request(&mldsa, NOW, "{}");
+        assert_eq!(
+            verifier(&registered).verify(req, NOW).await.unwrap_err(),
+            RequestSignatureError::AlgorithmMismatch
+        );
+    }
+
+    #[tokio::test]
+    async fn tampering_is_detected() {
+        let key = ed25519_key(7);
+        let verifier = verifier(&key);
+
+        let mut req = signed_request(&key, NOW, "{}");
+        req.headers_mut()
+            .insert("x-partner-ref", "po-9999".parse().unwrap());
+        assert_eq!(
+            verifier.verify(req, NOW).await.unwrap_err(),
+            RequestSignatureError::InvalidSignature
+        );
+
+        let req = signed_request(&key, NOW, "{}");
+        let (parts, _) = req.into_parts();
+        let req = Request::from_parts(parts, Body::from(r#"{"sku":"b-2"}"#));
+        assert_eq!(
+            verifier.verify(req, NOW).await.unwrap_err(),
+            RequestSignatureError::DigestMismatch
+        );
+
+        let mut req = signed_request(&key, NOW, "{}");
+        req.headers_mut().remove("x-partner-ref");
+        assert_eq!(
+            verifier.verify(req, NOW).await.unwrap_err(),
+            RequestSignatureError::MissingHeader("x-partner-ref".into())
+        );
+    }
+}
@@ -0,0 +1,586 @@
+//! Integrity signatures on gateway responses.
+//!
+//! [`response_signing_layer`] signs responses on configured path prefixes
+//! with an ML-DSA key so an archive can later show a response is the one
+//! the gateway sent. The body is read through a SHA-256 digest; the digest,
+//! the `Date`, the request's `x-request-id`, and the status are then signed
+//! in the style of HTTP message signatures (RFC 9421):
+//!
+//! ```text
+//! "content-digest": sha-256=:<base64>:
+//! "date": Sun, 18 Oct 2026 09:00:00 GMT
+//! "x-request-id": req-42
+//! "@status": 200
+//! "@signature-params": ("content-digest" "date" "x-request-id" "@status");created=1792314000;keyid="<kid>";alg="ML-DSA-65"
+//! ```
+//!
+//! The response carries `Content-Digest`, `X-QSGW-Signature-Input` (the
+//! signature parameters), `X-QSGW-Signature`, and `X-QSGW-Key-Id`, the
+//! signing key's `kid` in the [JWKS](crate::jwks) document; the request ID
+//! is echoed in `x-request-id` so the response verifies on its own. Bodies
+//! larger than [`ResponseSigningConfig::max_body_bytes`] are passed through
+//! unsigned with `X-QSGW-Signature-Skipped` set, as are responses that
+//! could not be signed. [`verify_response_signature`] checks a signed
+//! response.
+
+use axum::body::{Body, Bytes};
+use axum::response::Response;
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use http::{header, HeaderMap, HeaderName, HeaderValue, Request, StatusCode};
+use http_body_util::BodyExt;
+use hyper::body::{Body as HttpBody, Frame, SizeHint};
+use quantun_crypto::mldsa::{MlDsaSignature, MlDsaVerifier};
+use quantun_crypto::{CryptoError, CryptoResult, RemoteSigner};
+use quantun_types::Algorithm;
+use sha2::{Digest, Sha256};
+use std::collections::VecDeque;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::time::{SystemTime, UNIX_EPOCH};
+use thiserror::Error;
+use tower::{Layer, Service};
+use tracing::warn;
+
+use crate::auth::PathMatcher;
+use crate::jwks::fingerprint;
+
+/// Default limit on the size of a signed response body.
+pub const DEFAULT_MAX_SIGNED_BODY: usize = 4 * 1024 * 1024;
+
+pub const SIGNATURE_HEADER: HeaderName = HeaderName::from_static("x-qsgw-signature");
+pub const SIGNATURE_INPUT_HEADER: HeaderName = HeaderName::from_static("x-qsgw-signature-input");
+pub const KEY_ID_HEADER: HeaderName = HeaderName::from_static("x-qsgw-key-id");
+/// Set instead of a signature when a response was left unsigned, with the
+/// reason: `body-too-large`, `body-error`, or `signing-failed`.
+pub const SIGNATURE_SKIPPED_HEADER: HeaderName =
+    HeaderName::from_static("x-qsgw-signature-skipped");
+
+const CONTENT_DIGEST: HeaderName = HeaderName::from_static("content-digest");
+const REQUEST_ID: HeaderName = HeaderName::from_static("x-request-id");
+const COMPONENTS: &str = r#"("content-digest" "date" "x-request-id" "@status")"#;
+
+/// Which responses to sign.
+#[derive(Debug, Clone)]
+pub struct ResponseSigningConfig {
+    /// Request path prefixes whose responses are signed.
+    pub paths: Vec<String>,
+    /// Largest body that is buffered and signed.
+    pub max_body_bytes: usize,
+}
+
+impl Default for ResponseSigningConfig {
+    fn default() -> Self {
+        Self {
+            paths: Vec::new(),
+            max_body_bytes: DEFAULT_MAX_SIGNED_BODY,
+        }
+    }
+}
+
+/// Why a response signature did not verify.
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum ResponseSignatureError {
+    #[error("missing {0} header")]
+    MissingHeader(&'static str),
+    #[error("malformed {0} header")]
+    Malformed(&'static str),
+    #[error("body does not match content-digest")]
+    DigestMismatch,
+    #[error("signed by a different key or algorithm")]
+    KeyMismatch,
+    #[error("invalid signature")]
+    InvalidSignature,
+}
+
+/// Build a layer that signs responses to requests on `config.paths` with
+/// `signer`, which must hold an ML-DSA key.
+pub fn response_signing_layer(
+    config: ResponseSigningConfig,
+    signer: Arc<dyn RemoteSigner>,
+) -> CryptoResult<ResponseSigningLayer> {
+    let alg = match signer.algorithm() {
+        alg @ Algorithm::MlDsa(_) => alg.to_string(),
+        other => {
+            return Err(CryptoError::UnsupportedAlgorithm(format!(
+                "response signing needs an ML-DSA key, got {other}"
+            )))
+        }
+    };
+    Ok(ResponseSigningLayer {
+        inner: Arc::new(SigningState {
+            paths: PathMatcher::new(&config.paths),
+            max_body_bytes: config.max_body_bytes,
+            key_id: fingerprint(signer.public_key()),
+            alg,
+            signer,
+        }),
+    })
+}
+
+struct SigningState {
+    paths: PathMatcher,
+    max_body_bytes: usize,
+    key_id: String,
+    alg: String,
+    signer: Arc<dyn RemoteSigner>,
+}
+
+/// Layer produced by [`response_signing_layer`].
+#[derive(Clone)]
+pub struct ResponseSigningLayer {
+    inner: Arc<SigningState>,
+}
+
+impl<S> Layer<S> for ResponseSigningLayer {
+    type Service = ResponseSigning<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        ResponseSigning {
+            inner,
+            state: self.inner.clone(),
+        }
+    }
+}
+
+/// Service that signs responses on the configured paths.
+#[derive(Clone)]
+pub struct ResponseSigning<S> {
+    inner: S,
+    state: Arc<SigningState>,
+}
+
+impl<S> Service<Request<Body>> for ResponseSigning<S>
+where
+    S: Service<Request<Body>, Response = Response>,
+    S::Future: Send + 'static,
+{
+    type Response = Response;
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Response, S::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: Request<Body>) -> Self::Future {
+        if !self.state.paths.matches(req.uri().path()) {
+            return Box::pin(self.inner.call(req));
+        }
+        let request_id = req.headers().get(REQUEST_ID).cloned();
+        let state = self.state.clone();
+        let future = self.inner.call(req);
+        Box::pin(async move {
+            let response = future.await?;
+            Ok(sign_response(&state, response, request_id).await)
+        })
+    }
+}
+
+async fn sign_response(
+    state: &SigningState,
+    response: Response,
+    request_id: Option<HeaderValue>,
+) -> Response {
+    let (mut parts, body) = response.into_parts();
+    let declared = body.size_hint().exact();
+    if declared.is_some_and(|len| len > state.max_body_bytes as u64) {
+        return skipped(Response::from_parts(parts, body), "body-too-large");
+    }
+
+    let (replay, digest) = read_body(body, state.max_body_bytes).await;
+    let Some(digest) = digest else {
+        let reason = if replay.error.is_some() {
+            "body-error"
+        } else {
+            "body-too-large"
+        };
+        return skipped(Response::from_parts(parts, Body::new(replay)), reason);
+    };
+
+    let headers = &mut parts.headers;
+    let content_digest = format!("sha-256=:{}:", STANDARD.encode(digest));
+    headers.insert(CONTENT_DIGEST, header_value(&content_digest));
+    if !headers.contains_key(header::DATE) {
+        let date = httpdate::fmt_http_date(SystemTime::now());
+        headers.insert(header::DATE, header_value(&date));
+    }
+    if let Some(id) = request_id {
+        headers.entry(REQUEST_ID).or_insert(id);
+    }
+    let created = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    let params = format!(
+        r#"{COMPONENTS};created={created};keyid="{}";alg="{}""#,
+        state.key_id, state.alg
+    );
+    let base = signature_base(parts.status, headers, &params);
+
+    match state.signer.sign(base.as_bytes()).await {
+        Ok(signature) => {
+            let headers = &mut parts.headers;
+            headers.insert(
+                SIGNATURE_INPUT_HEADER,
+                header_value(&format!("sig1={params}")),
+            );
+            headers.insert(
+                SIGNATURE_HEADER,
+                header_value(&format!("sig1=:{}:", STANDARD.encode(signature))),
+            );
+            headers.insert(KEY_ID_HEADER, header_value(&state.key_id));
+            Response::from_parts(parts, Body::new(replay))
+        }
+        Err(e) => {
+            warn!(error = %e, "response signing failed");
+            skipped(
+                Response::from_parts(parts, Body::new(replay)),
+                "signing-failed",
+            )
+        }
+    }
+}
+
+fn skipped(mut response: Response, reason: &'static str) -> Response {
+    response
+        .headers_mut()
+        .insert(SIGNATURE_SKIPPED_HEADER, HeaderValue::from_static(reason));
+    response
+}
+
+/// Values here are base64, decimal, hex, or an HTTP date, all valid header
+/// characters.
+fn header_value(value: &str) -> HeaderValue {
+    HeaderValue::from_str(value).expect("signature header values are visible ASCII")
+}
+
+/// Read `body` frame by frame through SHA-256. Returns a replay of
+/// everything read plus the unread remainder, and the digest if the whole
+/// body was read within `max_body_bytes`.
+pub(crate) async fn read_body(
+    mut body: Body,
+    max_body_bytes: usize,
+) -> (Replay, Option<[u8; 32]>) {
+    let mut replay = Replay::default();
+    let mut hasher = Sha256::new();
+    let mut len = 0;
+    while let Some(frame) = body.frame().await {
+        let frame = match frame {
+            Ok(frame) => frame,
+            Err(e) => {
+                replay.error = Some(e);
+                return (replay, None);
+            }
+        };
+        match frame.into_data() {
+            Ok(data) => {
+                len += data.len();
+                hasher.update(&data);
+                replay.chunks.push_back(data);
+                if len > max_body_bytes {
+                    replay.rest = Some(body);
+                    return (replay, None);
+                }
+            }
+            Err(frame) => {
+                if let Ok(trailers) = frame.into_trailers() {
+                    replay.trailers = Some(trailers);
+                }
+            }
+        }
+    }
+    (replay, Some(hasher.finalize().into()))
+}
+
+/// Body that yields already-read chunks, then the unread remainder or the
+/// read error, then any trailers.
+#[derive(Default)]
+pub(crate) struct Replay {
+    chunks: VecDeque<Bytes>,
+    rest: Option<Body>,
+    pub(crate) error: Option<axum::Error>,
+    trailers: Option<HeaderMap>,
+}
+
+impl HttpBody for Replay {
+    type Data = Bytes;
+    type Error = axum::Error;
+
+    fn poll_frame(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Frame<Self::Data>, Self::Error>>> {
+        if let Some(chunk) = self.chunks.pop_front() {
+            return Poll::Ready(Some(Ok(Frame::data(chunk))));
+        }
+        if let Some(rest) = self.rest.as_mut() {
+            return Pin::new(rest).poll_frame(cx);
+        }
+        if let Some(e) = self.error.take() {
+            return Poll::Ready(Some(Err(e)));
+        }
+        Poll::Ready(self.trailers.take().map(|t| Ok(Frame::trailers(t))))
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.chunks.is_empty()
+            && self.rest.as_ref().is_none_or(|rest| rest.is_end_stream())
+            && self.error.is_none()
+            && self.trailers.is_none()
+    }
+
+    fn size_hint(&self) -> SizeHint {
+        let buffered: u64 = self.chunks.iter().map(|c| c.len() as u64).sum();
+        match &self.rest {
+            Some(rest) => {
+                let rest = rest.size_hint();
+                let mut hint = SizeHint::new();
+                hint.set_lower(buffered + rest.lower());
+                if let Some(upper) = rest.upper() {
+                    hint.set_upper(buffered + upper);
+                }
+                hint
+            }
+            None => SizeHint::with_exact(buffered),
+        }
+    }
+}
+
+fn signature_base(status: StatusCode, headers: &HeaderMap, params: &str) -> String {
+    let value = |name: &HeaderName| {
+        headers
+            .get(name)
+            .and_then(|v| v.to_str().ok())
+            .unwrap_or("")
+            .to_string()
+    };
+    format!(
+        "\"content-digest\": {}\n\"date\": {}\n\"x-request-id\": {}\n\"@status\": {}\n\"@signature-params\": {params}",
+        value(&CONTENT_DIGEST),
+        value(&header::DATE),
+        value(&REQUEST_ID),
+        status.as_u16(),
+    )
+}
+
+/// Check a response signed by [`response_signing_layer`] against
+/// `verifier`, given its status, headers, and complete body.
+pub fn verify_response_signature(
+    status: StatusCode,
+    headers: &HeaderMap,
+    body: &[u8],
+    verifier: &MlDsaVerifier,
+) -> Result<(), ResponseSignatureError> {
+    let get = |name: &HeaderName, label: &'static str| {
+        headers
+            .get(name)
+            .ok_or(ResponseSignatureError::MissingHeader(label))?
+            .to_str()
+            .map_err(|_| ResponseSignatureError::Malformed(label))
+    };
+
+    let expected = format!("sha-256=:{}:", STANDARD.encode(Sha256::digest(body)));
+    if get(&CONTENT_DIGEST, "content-digest")? != expected {
+        return Err(ResponseSignatureError::DigestMismatch);
+    }
+
+    let params = get(&SIGNATURE_INPUT_HEADER, "x-qsgw-signature-input")?
+        .strip_prefix("sig1=")
+        .filter(|p| p.starts_with(COMPONENTS))
+        .ok_or(ResponseSignatureError::Malformed("x-qsgw-signature-input"))?;
+    let param = |name: &str| {
+        params
+            .split(';')
+            .find_map(|p| p.strip_prefix(name)?.strip_prefix("=\"")?.strip_suffix('"'))
+    };
+    if param("keyid") != Some(fingerprint(&verifier.public_key).as_str())
+        || param("alg") != Some(verifier.variant.to_string().as_str())
+    {
+        return Err(ResponseSignatureError::KeyMismatch);
+    }
+
+    let signature = get(&SIGNATURE_HEADER, "x-qsgw-signature")?
+        .strip_prefix("sig1=:")
+        .and_then(|s| s.strip_suffix(':'))
+        .and_then(|s| STANDARD.decode(s).ok())
+        .ok_or(ResponseSignatureError::Malformed("x-qsgw-signature"))?;
+    let signature = MlDsaSignature {
+        signature,
+        variant: verifier.variant,
+    };
+    let base = signature_base(status, headers, params);
+    match verifier.verify(base.as_bytes(), &signature) {
+        Ok(true) => Ok(()),
+        _ => Err(ResponseSignatureError::InvalidSignature),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::jwks::JwksService;
+    use crate::metrics::GatewayMetrics;
+    use crate::rotation::{ManualClock, RotationPolicy, RotationScheduler, RotationSlotConfig};
+    use axum::routing::get;
+    use axum::Router;
+    use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+    use quantun_crypto::signer::KeyStoreSigner;
+    use quantun_crypto::KeyStore;
+    use quantun_types::{KeyUsage, MlDsaVariant};
+    use std::time::Duration;
+    use tower::ServiceExt;
+
+    const SLOT: &str = "response-signing";
+
+    struct Fixture {
+        app: Router,
+        jwks: JwksService,
+    }
+
+    fn fixture(max_body_bytes: usize) -> Fixture {
+        let store = Arc::new(KeyStore::new());
+        let scheduler = Arc::new(RotationScheduler::new(
+            store.clone(),
+            Arc::new(ManualClock::new(1_000)),
+            Arc::new(GatewayMetrics::default()),
+        ));
+        let key = scheduler
+            .add_slot(RotationSlotConfig {
+                name: SLOT.into(),
+                algorithm: Algorithm::MlDsa(MlDsaVariant::MlDsa44),
+                usages: vec![KeyUsage::Sign],
+                policy: RotationPolicy {
+                    interval: Duration::from_secs(3_600),
+                    lead_time: Duration::from_secs(600),
+                    overlap: Duration::from_secs(600),
+                },
+            })
+            .unwrap();
+        let signer = KeyStoreSigner::new(store.clone(), key).unwrap();
+        let layer = response_signing_layer(
+            ResponseSigningConfig {
+                paths: vec!["/signed".into()],
+                max_body_bytes,
+            },
+            Arc::new(signer),
+        )
+        .unwrap();
+
+        let app = Router::new()
+            .route("/signed/small", get(|| async { "archived record" }))
+            .route(
+                "/signed/stream",
+                get(|| async {
+                    let chunks = ["part one, ", "part two, ", "part three"];
+                    Body::new(Chunked(chunks.into_iter().map(Bytes::from).collect()))
+                }),
+            )
+            .route("/plain", get(|| async { "not signed" }))
+            .layer(layer);
+        Fixture {
+            app,
+            jwks: JwksService::new(store, scheduler, [SLOT]),
+        }
+    }
+
+    /// Body that yields each chunk as its own frame, with no known length.
+    struct Chunked(VecDeque<Bytes>);
+
+    impl HttpBody for Chunked {
+        type Data = Bytes;
+        type Error = std::convert::Infallible;
+
+        fn poll_frame(
+            mut self: Pin<&mut Self>,
+            _cx: &mut Context<'_>,
+        ) -> Poll<Option<Result<Frame<Bytes>, Self::Error>>> {
+            Poll::Ready(self.0.pop_front().map(|c| Ok(Frame::data(c))))
+        }
+    }
+
+    async fn get_path(fixture: &Fixture, path: &str) -> (StatusCode, HeaderMap, Bytes) {
+        let req = Request::get(path)
+            .header("x-request-id", "req-7")
+            .body(Body::empty())
+            .unwrap();
+        let response = fixture.app.clone().oneshot(req).await.unwrap();
+        let (parts, body) = response.into_parts();
+        let body = body.collect().await.unwrap().to_bytes();
+        (parts.status, parts.headers, body)
+    }
+
+    /// The verifier for the key named by `headers`, as published in JWKS.
+    fn jwks_verifier(fixture: &Fixture, headers: &HeaderMap) -> MlDsaVerifier {
+        let set = fixture.jwks.key_set().unwrap();
+        let kid = headers[KEY_ID_HEADER].to_str().unwrap();
+        let jwk = set.find(kid).expect("signing key published in JWKS");
+        MlDsaVerifier {
+            variant: MlDsaVariant::MlDsa44,
+            public_key: URL_SAFE_NO_PAD.decode(&jwk.public_key).unwrap(),
+        }
+    }
+
+    #[tokio::test]
+    async fn signed_response_verifies_against_jwks_key() {
+        let fixture = fixture(DEFAULT_MAX_SIGNED_BODY);
+        let (status, headers, body) = get_path(&fixture, "/signed/small").await;
+        assert_eq!(body, "archived record");
+        assert_eq!(headers["x-request-id"], "req-7");
+        assert!(headers.contains_key(header::DATE));
+        let verifier = jwks_verifier(&fixture, &headers);
+        assert_eq!(
+            verify_response_signature(status, &headers, &body, &verifier),
+            Ok(())
+        );
+
+        assert_eq!(
+            verify_response_signature(status, &headers, b"archived recorD", &verifier),
+            Err(ResponseSignatureError::DigestMismatch)
+        );
+        assert_eq!(
+            verify_response_signature(StatusCode::CREATED, &headers, &body, &verifier),
+            Err(ResponseSignatureError::InvalidSignature)
+        );
+        let mut tampered = headers.clone();
+        tampered.insert("x-request-id", HeaderValue::from_static("req-8"));
+        assert_eq!(
+            verify_response_signature(status, &tampered, &body, &verifier),
+            Err(ResponseSignatureError::InvalidSignature)
+        );
+
+        let (_, headers, _) = get_path(&fixture, "/plain").await;
+        assert!(!headers.contains_key(SIGNATURE_HEADER));
+        assert!(!headers.contains_key(SIGNATURE_SKIPPED_HEADER));
+    }
+
+    #[tokio::test]
+    async fn streamed_body_is_signed_whole() {
+        let fixture = fixture(DEFAULT_MAX_SIGNED_BODY);
+        let (status, headers, body) = get_path(&fixture, "/signed/stream").await;
+        assert_eq!(body, "part one, part two, part three");
+        let verifier = jwks_verifier(&fixture, &headers);
+        assert_eq!(
+            verify_response_signature(status, &headers, &body, &verifier),
+            Ok(())
+        );
+    }
+
+    #[tokio::test]
+    async fn bodies_over_the_cap_pass_through_unsigned() {
+        let fixture = fixture(12);
+        // Streamed: the cap is hit mid-body and the rest is passed through.
+        let (status, headers, body) = get_path(&fixture, "/signed/stream").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body, "part one, part two, part three");
+        assert_eq!(headers[SIGNATURE_SKIPPED_HEADER], "body-too-large");
+        assert!(!headers.contains_key(SIGNATURE_HEADER));
+
+        // Known length: skipped without reading.
+        let (_, headers, body) = get_path(&fixture, "/signed/small").await;
+        assert_eq!(body, "archived record");
+        assert_eq!(headers[SIGNATURE_SKIPPED_HEADER], "body-too-large");
+    }
+}
@@ -0,0 +1,561 @@
+//! Scheduled rotation of gateway keys.
+//!
+//! Each rotation slot (e.g. the token-signing key) has one current key. Ahead
+//! of the rotation instant the scheduler generates a successor in the
+//! `PendingActivation` state so verifiers can fetch its public key early; at
+//! the rotation instant the successor becomes current and the predecessor
+//! stays active for the overlap window, for verification and decapsulation
+//! of in-flight traffic, before it is revoked.
+//!
+//! [`RotationScheduler::force_rotate`] rotates immediately. When the current
+//! key is compromised it is marked so at once, skipping the overlap, and is
+//! listed by [`RotationScheduler::revoked`] so verifiers can be told not to
+//! trust it.
+
+use axum::extract::{Path, Query, State};
+use axum::response::{IntoResponse, Response};
+use axum::routing::post;
+use axum::{Json, Router};
+use http::StatusCode;
+use quantun_crypto::{CryptoError, CryptoResult, KeyHandle, KeyStore};
+use quantun_types::{Algorithm, KeyUsage};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::sync::broadcast;
+use tokio::task::JoinHandle;
+use tracing::{info, warn};
+
+use crate::metrics::GatewayMetrics;
+
+/// Source of the current time, in seconds since the Unix epoch.
+pub trait Clock: Send + Sync {
+    fn now(&self) -> u64;
+}
+
+/// Wall-clock time.
+#[derive(Debug, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> u64 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0)
+    }
+}
+
+/// Clock that only moves when told to, for tests.
+#[derive(Debug, Default)]
+pub struct ManualClock(AtomicU64);
+
+impl ManualClock {
+    pub fn new(now: u64) -> Self {
+        Self(AtomicU64::new(now))
+    }
+
+    pub fn advance(&self, by: Duration) {
+        self.0.fetch_add(by.as_secs(), Ordering::SeqCst);
+    }
+}
+
+impl Clock for ManualClock {
+    fn now(&self) -> u64 {
+        self.0.load(Ordering::SeqCst)
+    }
+}
+
+/// Timing of a slot's rotations.
+#[derive(Debug, Clone, Copy)]
+pub struct RotationPolicy {
+    /// Time each key is current.
+    pub interval: Duration,
+    /// How long before the rotation instant the successor is generated and
+    /// published.
+    pub lead_time: Duration,
+    /// How long the predecessor stays usable after the rotation instant.
+    pub overlap: Duration,
+}
+
+/// A key kept under rotation.
+#[derive(Debug, Clone)]
+pub struct RotationSlotConfig {
+    pub name: String,
+    pub algorithm: Algorithm,
+    pub usages: Vec<KeyUsage>,
+    pub policy: RotationPolicy,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RotationEventKind {
+    /// A successor was generated and published.
+    SuccessorCreated,
+    /// The successor became the current key.
+    Activated,
+    /// A predecessor reached the end of its overlap and was revoked.
+    Retired,
+    /// The current key was marked compromised by a forced rotation.
+    Compromised,
+}
+
+/// A key lifecycle transition made by the scheduler.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct RotationEvent {
+    pub slot: String,
+    pub kind: RotationEventKind,
+    pub key_id: String,
+    pub at: u64,
+}
+
+struct Slot {
+    config: RotationSlotConfig,
+    current: KeyHandle,
+    rotates_at: u64,
+    successor: Option<KeyHandle>,
+    /// Previous keys still inside their overlap, with their retirement time.
+    predecessors: Vec<(KeyHandle, u64)>,
+    /// Keys marked compromised by a forced rotation.
+    compromised: Vec<KeyHandle>,
+}
+
+/// Drives key rotations for a set of slots over a [`KeyStore`].
+pub struct RotationScheduler {
+    store: Arc<KeyStore>,
+    clock: Arc<dyn Clock>,
+    metrics: Arc<GatewayMetrics>,
+    slots: Mutex<HashMap<String, Slot>>,
+    events: broadcast::Sender<RotationEvent>,
+}
+
+impl RotationScheduler {
+    pub fn new(store: Arc<KeyStore>, clock: Arc<dyn Clock>, metrics: Arc<GatewayMetrics>) -> Self {
+        Self {
+            store,
+            clock,
+            metrics,
+            slots: Mutex::new(HashMap::new()),
+            events: broadcast::channel(64).0,
+        }
+    }
+
+    /// Start rotating a new slot with a freshly generated current key.
+    pub fn add_slot(&self, config: RotationSlotConfig) -> CryptoResult<KeyHandle> {
+        let current = self
+            .store
+            .create(config.algorithm, config.usages.iter().copied())?;
+        let slot = Slot {
+            rotates_at: self.clock.now() + config.policy.interval.as_secs(),
+            current: current.clone(),
+            successor: None,
+            predecessors: Vec::new(),
+            compromised: Vec::new(),
+            config,
+        };
+        self.slots
+            .lock()
+            .unwrap()
+            .insert(slot.config.name.clone(), slot);
+        Ok(current)
+    }
+
+    /// Receive every [`RotationEvent`] from now on.
+    pub fn subscribe(&self) -> broadcast::Receiver<RotationEvent> {
+        self.events.subscribe()
+    }
+
+    /// The key to sign or encapsulate with for `slot`.
+    pub fn current(&self, slot: &str) -> Option<KeyHandle> {
+        self.slots
+            .lock()
+            .unwrap()
+            .get(slot)
+            .map(|s| s.current.clone())
+    }
+
+    /// Keys whose public halves should be published for `slot`: the
+    /// pending successor, the current key, and predecessors in overlap.
+    pub fn published(&self, slot: &str) -> Vec<KeyHandle> {
+        let slots = self.slots.lock().unwrap();
+        let Some(slot) = slots.get(slot) else {
+            return Vec::new();
+        };
+        slot.successor
+            .iter()
+            .chain([&slot.current])
+            .chain(slot.predecessors.iter().map(|(h, _)| h))
+            .cloned()
+            .collect()
+    }
+
+    /// Keys of `slot` that were compromised and must no longer be trusted.
+    pub fn revoked(&self, slot: &str) -> Vec<KeyHandle> {
+        self.slots
+            .lock()
+            .unwrap()
+            .get(slot)
+            .map(|s| s.compromised.clone())
+            .unwrap_or_default()
+    }
+
+    /// Apply every transition due at the clock's current time.
+    ///
+    /// Slot state only changes once the keystore has applied a transition,
+    /// and if one fails the events for those already made are still
+    /// emitted before the error is returned, so the next tick retries only
+    /// what is left.
+    pub fn tick(&self) -> CryptoResult<Vec<RotationEvent>> {
+        let now = self.clock.now();
+        let mut events = Vec::new();
+        let result = self.apply_due(now, &mut events);
+        self.emit(&events);
+        result.map(|()| events)
+    }
+
+    fn apply_due(&self, now: u64, events: &mut Vec<RotationEvent>) -> CryptoResult<()> {
+        let mut slots = self.slots.lock().unwrap();
+        for slot in slots.values_mut() {
+            let policy = slot.config.policy;
+
+            if slot.successor.is_none() && now + policy.lead_time.as_secs() >= slot.rotates_at {
+                let successor = self.create_successor(slot)?;
+                events.push(event(
+                    slot,
+                    RotationEventKind::SuccessorCreated,
+                    &successor,
+                    now,
+                ));
+            }
+
+            if now >= slot.rotates_at {
+                let (activated, _) = self.rotate(slot, now)?;
+                events.push(event(slot, RotationEventKind::Activated, &activated, now));
+            }
+
+            while let Some(i) = slot.predecessors.iter().position(|(_, at)| now >= *at) {
+                let handle = slot.predecessors[i].0.clone();
+                self.store.revoke(&handle)?;
+                slot.predecessors.remove(i);
+                self.metrics.keys_retired.fetch_add(1, Ordering::Relaxed);
+                events.push(event(slot, RotationEventKind::Retired, &handle, now));
+            }
+        }
+        Ok(())
+    }
+
+    /// Rotate `slot` now. If `compromised`, the outgoing key is marked
+    /// compromised immediately instead of entering the overlap window.
+    pub fn force_rotate(&self, slot: &str, compromised: bool) -> CryptoResult<Vec<RotationEvent>> {
+        let now = self.clock.now();
+        let mut events = Vec::new();
+        let result = self.rotate_now(slot, compromised, now, &mut events);
+        self.emit(&events);
+        result.map(|()| events)
+    }
+
+    fn rotate_now(
+        &self,
+        slot: &str,
+        compromised: bool,
+        now: u64,
+        events: &mut Vec<RotationEvent>,
+    ) -> CryptoResult<()> {
+        let mut slots = self.slots.lock().unwrap();
+        let slot = slots
+            .get_mut(slot)
+            .ok_or_else(|| CryptoError::KeyNotFound(format!("rotation slot {slot}")))?;
+
+        if slot.successor.is_none() {
+            let successor = self.create_successor(slot)?;
+            events.push(event(
+                slot,
+                RotationEventKind::SuccessorCreated,
+                &successor,
+                now,
+            ));
+        }
+        let (activated, previous) = self.rotate(slot, now)?;
+        events.push(event(slot, RotationEventKind::Activated, &activated, now));
+
+        if compromised {
+            self.store.mark_compromised(&previous)?;
+            slot.predecessors.retain(|(h, _)| *h != previous);
+            slot.compromised.push(previous.clone());
+            events.push(event(slot, RotationEventKind::Compromised, &previous, now));
+        }
+        Ok(())
+    }
+
+    /// Call [`RotationScheduler::tick`] every `period` until aborted.
+    pub fn spawn(self: Arc<Self>, period: Duration) -> JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(period);
+            loop {
+                interval.tick().await;
+                if let Err(e) = self.tick() {
+                    warn!(error = %e, "key rotation failed");
+                }
+            }
+        })
+    }
+
+    fn create_successor(&self, slot: &mut Slot) -> CryptoResult<KeyHandle> {
+        let successor = self
+            .store
+            .create_pending(slot.config.algorithm, slot.config.usages.iter().copied())?;
+        slot.successor = Some(successor.clone());
+        Ok(successor)
+    }
+
+    /// Promote the successor (generating one if needed) and move the current
+    /// key into overlap. Returns the new and previous current keys.
+    fn rotate(&self, slot: &mut Slot, now: u64) -> CryptoResult<(KeyHandle, KeyHandle)> {
+        if slot.successor.is_none() {
+            self.create_successor(slot)?;
+        }
+        let successor = slot.successor.take().expect("successor created above");
+        self.store.activate(&successor)?;
+
+        let previous = std::mem::replace(&mut slot.current, successor.clone());
+        let policy = slot.config.policy;
+        slot.predecessors
+            .push((previous.clone(), now + policy.overlap.as_secs()));
+        slot.rotates_at = now + policy.interval.as_secs();
+        self.metrics.key_rotations.fetch_add(1, Ordering::Relaxed);
+        Ok((successor, previous))
+    }
+
+    fn emit(&self, events: &[RotationEvent]) {
+        for e in events {
+            info!(slot = %e.slot, kind = ?e.kind, key_id = %e.key_id, at = e.at, "key rotation event");
+            // No subscribers is fine.
+            let _ = self.events.send(e.clone());
+        }
+    }
+}
+
+fn event(slot: &Slot, kind: RotationEventKind, handle: &KeyHandle, at: u64) -> RotationEvent {
+    RotationEvent {
+        slot: slot.config.name.clone(),
+        kind,
+        key_id: handle.key_id().to_string(),
+        at,
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct RotateQuery {
+    #[serde(default)]
+    compromised: bool,
+}
+
+/// Admin routes over `scheduler`:
+///
+/// - `POST /admin/rotation/{slot}/rotate?compromised=true|false`: rotate
+///   now and return the resulting events.
+///
+/// [`build_router`](crate::build_router) mounts these as `AdminOnly` when
+/// [`GatewayConfig::rotation`](crate::GatewayConfig::rotation) is set.
+pub fn router(scheduler: Arc<RotationScheduler>) -> Router {
+    Router::new()
+        .route("/admin/rotation/{slot}/rotate", post(force_rotate))
+        .with_state(scheduler)
+}
+
+async fn force_rotate(
+    State(scheduler): State<Arc<RotationScheduler>>,
+    Path(slot): Path<String>,
+    Query(query): Query<RotateQuery>,
+) -> Response {
+    match scheduler.force_rotate(&slot, query.compromised) {
+        Ok(events) => Json(events).into_response(),
+        Err(CryptoError::KeyNotFound(_)) => {
+            (StatusCode::NOT_FOUND, "unknown rotation slot").into_response()
+        }
+        Err(e) => {
+            warn!(slot = %slot, error = %e, "forced rotation failed");
+            (StatusCode::INTERNAL_SERVER_ERROR, "rotation failed").into_response()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::Body;
+    use http::Request;
+    use quantun_types::{KeyState, MlDsaVariant};
+    use tower::ServiceExt;
+
+    const SLOT: &str = "token-signing";
+
+    fn setup() -> (
+        Arc<KeyStore>,
+        Arc<ManualClock>,
+        Arc<GatewayMetrics>,
+        RotationScheduler,
+    ) {
+        let store = Arc::new(KeyStore::new());
+        let clock = Arc::new(ManualClock::new(1_000));
+        let metrics = Arc::new(GatewayMetrics::default());
+        let scheduler = RotationScheduler::new(store.clone(), clock.clone(), metrics.clone());
+        scheduler
+            .add_slot(RotationSlotConfig {
+                name: SLOT.into(),
+                algorithm: Algorithm::MlDsa(MlDsaVariant::MlDsa44),
+                usages: vec![KeyUsage::Sign],
+                policy: RotationPolicy {
+                    interval: Duration::from_secs(100),
+                    lead_time: Duration::from_secs(10),
+                    overlap: Duration::from_secs(20),
+                },
+            })
+            .unwrap();
+        (store, clock, metrics, scheduler)
+    }
+
+    fn kinds(events: &[RotationEvent]) -> Vec<RotationEventKind> {
+        events.iter().map(|e| e.kind).collect()
+    }
+
+    fn state(store: &KeyStore, handle: &KeyHandle) -> KeyState {
+        store.metadata(handle).unwrap().state
+    }
+
+    #[test]
+    fn full_rotation_cycle() {
+        let (store, clock, metrics, scheduler) = setup();
+        let mut events = scheduler.subscribe();
+        let first = scheduler.current(SLOT).unwrap();
+
+        clock.advance(Duration::from_secs(50));
+        assert!(scheduler.tick().unwrap().is_empty());
+
+        // Lead time reached: successor published but not yet signing.
+        clock.advance(Duration::from_secs(40));
+        let tick = scheduler.tick().unwrap();
+        assert_eq!(kinds(&tick), [RotationEventKind::SuccessorCreated]);
+        let successor = scheduler.published(SLOT)[0].clone();
+        assert_eq!(state(&store, &successor), KeyState::PendingActivation);
+        assert_eq!(
+            scheduler.published(SLOT),
+            [successor.clone(), first.clone()]
+        );
+        assert_eq!(scheduler.current(SLOT).unwrap(), first);
+
+        // Rotation instant: successor signs, predecessor still usable.
+        clock.advance(Duration::from_secs(10));
+        let tick = scheduler.tick().unwrap();
+        assert_eq!(kinds(&tick), [RotationEventKind::Activated]);
+        assert_eq!(scheduler.current(SLOT).unwrap(), successor);
+        assert!(store.sign(&successor, b"m").is_ok());
+        assert!(store.sign(&first, b"m").is_ok());
+
+        // Overlap over: predecessor revoked but its public key remains.
+        clock.advance(Duration::from_secs(20));
+        let tick = scheduler.tick().unwrap();
+        assert_eq!(kinds(&tick), [RotationEventKind::Retired]);
+        assert_eq!(state(&store, &first), KeyState::Deactivated);
+        assert!(store.get_public(&first).is_ok());
+        assert_eq!(scheduler.published(SLOT), [successor]);
+
+        assert_eq!(metrics.key_rotations.load(Ordering::Relaxed), 1);
+        assert_eq!(metrics.keys_retired.load(Ordering::Relaxed), 1);
+        let received: Vec<_> = std::iter::from_fn(|| events.try_recv().ok())
+            .map(|e| e.kind)
+            .collect();
+        assert_eq!(
+            received,
+            [
+                RotationEventKind::SuccessorCreated,
+                RotationEventKind::Activated,
+                RotationEventKind::Retired,
+            ]
+        );
+    }
+
+    #[test]
+    fn compromise_skips_overlap() {
+        let (store, _clock, _metrics, scheduler) = setup();
+        let first = scheduler.current(SLOT).unwrap();
+
+        let events = scheduler.force_rotate(SLOT, true).unwrap();
+        assert_eq!(
+            kinds(&events),
+            [
+                RotationEventKind::SuccessorCreated,
+                RotationEventKind::Activated,
+                RotationEventKind::Compromised,
+            ]
+        );
+        let current = scheduler.current(SLOT).unwrap();
+        assert_ne!(current, first);
+        assert!(store.sign(&current, b"m").is_ok());
+        assert!(store.sign(&first, b"m").is_err());
+        assert_eq!(state(&store, &first), KeyState::Compromised);
+        assert!(store.get_public(&first).is_ok());
+        assert_eq!(scheduler.published(SLOT), [current]);
+        assert_eq!(scheduler.revoked(SLOT), [first]);
+    }
+
+    #[test]
+    fn failed_retirement_keeps_earlier_events_and_retries() {
+        let (store, clock, _metrics, scheduler) = setup();
+        let mut events = scheduler.subscribe();
+        let first = scheduler.current(SLOT).unwrap();
+        scheduler.force_rotate(SLOT, false).unwrap();
+        while events.try_recv().is_ok() {}
+
+        // Revoking the predecessor fails, but the rotation due in the same
+        // tick has already happened and must still be announced.
+        store.revoke(&first).unwrap();
+        clock.advance(Duration::from_secs(100));
+        assert!(scheduler.tick().is_err());
+        let received: Vec<_> = std::iter::from_fn(|| events.try_recv().ok())
+            .map(|e| e.kind)
+            .collect();
+        assert_eq!(
+            received,
+            [
+                RotationEventKind::SuccessorCreated,
+                RotationEventKind::Activated,
+            ]
+        );
+        assert!(scheduler.published(SLOT).contains(&first));
+    }
+
+    #[tokio::test]
+    async fn admin_endpoint_forces_rotation() {
+        let (_store, _clock, _metrics, scheduler) = setup();
+        let scheduler = Arc::new(scheduler);
+        let first = scheduler.current(SLOT).unwrap();
+        let app = router(scheduler.clone());
+
+        let response = app
+            .clone()
+            .oneshot(
+                Request::post(format!("/admin/rotation/{SLOT}/rotate"))
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_ne!(scheduler.current(SLOT).unwrap(), first);
+        // Plain forced rotation keeps the predecessor in overlap.
+        assert!(scheduler.published(SLOT).contains(&first));
+
+        let response = app
+            .oneshot(
+                Request::post("/admin/rotation/unknown/rotate")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+}
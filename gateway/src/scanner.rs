@@ -0,0 +1,1230 @@
+//! Upstream TLS posture scanning.
+//!
+//! [`UpstreamScanner`] completes a TLS handshake with every upstream of the
+//! proxy's routes, records what was negotiated, and classifies it with
+//! [`AnyAlgorithm`] into a [`Readiness`] verdict and a risk score, so
+//! operators can see which upstreams would survive a stricter
+//! [`TlsPolicy`](crate::TlsPolicy) before tightening it.
+//!
+//! The scanner offers the hybrid and pure ML-KEM groups first and accepts
+//! any certificate: it reports posture, it does not authenticate upstreams.
+//! Upstreams reached through an egress proxy are scanned through it too.
+//!
+//! [`UpstreamScanner::spawn`] rescans on a schedule. Each upstream keeps a
+//! bounded [`HistoryEntry`] history, and a [`RegressionAlert`] is raised
+//! when an upstream's readiness drops between scans, e.g. from hybrid to
+//! classical-only after a load balancer change.
+
+use axum::body::Body;
+use axum::extract::{Path, State};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use http::{header, Request, StatusCode, Uri};
+use hyper_util::client::legacy::connect::HttpConnector;
+use hyper_util::client::legacy::Client;
+use hyper_util::rt::TokioExecutor;
+use quantun_types::{
+    Algorithm, AnyAlgorithm, ClassicalAlgorithm, ErrorCode, HybridVariant, MlKemVariant,
+};
+use rustls::client::danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier};
+use rustls::crypto::{aws_lc_rs, CryptoProvider};
+use rustls::pki_types::{CertificateDer, ServerName, UnixTime};
+use rustls::{ClientConfig, DigitallySignedStruct, NamedGroup, ProtocolVersion, SignatureScheme};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::atomic::Ordering;
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use thiserror::Error;
+use tokio::sync::broadcast;
+use tokio::task::{JoinHandle, JoinSet};
+use tokio::time::MissedTickBehavior;
+use tokio_rustls::TlsConnector;
+use tracing::{debug, info, warn};
+use x509_cert::der::asn1::{ObjectIdentifier, UintRef};
+use x509_cert::der::{Decode, Reader, SliceReader};
+use x509_cert::Certificate;
+
+use crate::metrics::GatewayMetrics;
+use crate::proxy::egress::{self, EgressError, EgressProxy};
+use crate::proxy::{ProxyService, Route};
+
+/// Path serving the latest [`RiskReport`].
+pub const RISK_PATH: &str = "/gateway/risk";
+
+/// Admin path that runs a new scan.
+pub const SCAN_PATH: &str = "/admin/risk/scan";
+
+/// Path prefix serving the [`HistoryEntry`] list of one upstream.
+pub const HISTORY_PATH: &str = "/gateway/risk/history";
+
+/// Default limit on connecting to and handshaking with one upstream.
+pub const DEFAULT_SCAN_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Default number of results kept per upstream.
+pub const DEFAULT_HISTORY_LIMIT: usize = 50;
+
+const OID_RSA: ObjectIdentifier = ObjectIdentifier::new_unwrap("1.2.840.113549.1.1.1");
+const OID_EC: ObjectIdentifier = ObjectIdentifier::new_unwrap("1.2.840.10045.2.1");
+const OID_P256: ObjectIdentifier = ObjectIdentifier::new_unwrap("1.2.840.10045.3.1.7");
+const OID_P384: ObjectIdentifier = ObjectIdentifier::new_unwrap("1.3.132.0.34");
+const OID_ED25519: ObjectIdentifier = ObjectIdentifier::new_unwrap("1.3.101.112");
+
+/// Certificate signature algorithms by OID, for the report.
+const SIGNATURE_ALGORITHMS: &[(&str, &str)] = &[
+    ("1.2.840.113549.1.1.5", "sha1WithRSAEncryption"),
+    ("1.2.840.113549.1.1.10", "RSASSA-PSS"),
+    ("1.2.840.113549.1.1.11", "sha256WithRSAEncryption"),
+    ("1.2.840.113549.1.1.12", "sha384WithRSAEncryption"),
+    ("1.2.840.113549.1.1.13", "sha512WithRSAEncryption"),
+    ("1.2.840.10045.4.3.2", "ecdsa-with-SHA256"),
+    ("1.2.840.10045.4.3.3", "ecdsa-with-SHA384"),
+    ("1.2.840.10045.4.3.4", "ecdsa-with-SHA512"),
+    ("1.3.101.112", "Ed25519"),
+];
+
+/// Why an upstream could not be assessed.
+#[derive(Debug, Error)]
+pub enum ScanError {
+    #[error("no TLS handshake within {0:?}")]
+    Timeout(Duration),
+    #[error("connection failed: {0}")]
+    Connect(std::io::Error),
+    #[error("egress proxy {proxy} failed: {reason}")]
+    EgressProxy { proxy: String, reason: String },
+    #[error("TLS handshake failed: {0}")]
+    Handshake(std::io::Error),
+    #[error("invalid server name: {0}")]
+    ServerName(String),
+}
+
+impl ScanError {
+    pub fn error_code(&self) -> ErrorCode {
+        match self {
+            ScanError::Timeout(_) => ErrorCode::ScanTimeout,
+            _ => ErrorCode::AssessmentFailed,
+        }
+    }
+}
+
+/// An upstream TLS endpoint to scan.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ScanTarget {
+    /// Name of the upstream.
+    pub name: String,
+    pub host: String,
+    pub port: u16,
+}
+
+impl ScanTarget {
+    /// The distinct upstreams of `routes`, in route order.
+    pub fn from_routes(routes: &[Route]) -> Vec<Self> {
+        let mut targets: Vec<Self> = Vec::new();
+        for route in routes {
+            let upstream = &route.upstream;
+            if !targets.iter().any(|t| t.name == upstream.name) {
+                targets.push(Self {
+                    name: upstream.name.clone(),
+                    host: upstream.host.clone(),
+                    port: upstream.port,
+                });
+            }
+        }
+        targets
+    }
+}
+
+/// How ready an upstream is for a PQC-only policy.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Readiness {
+    /// PQC key exchange and a PQC certificate.
+    PqcReady,
+    /// PQC (usually hybrid) key exchange, classical certificate.
+    HybridCapable,
+    /// Classical key exchange only.
+    ClassicalOnly,
+    /// No TLS handshake could be completed.
+    Unreachable,
+}
+
+impl Readiness {
+    /// Position from most (0) to least ready; `None` for
+    /// [`Readiness::Unreachable`], which says nothing about posture.
+    fn rank(self) -> Option<u8> {
+        match self {
+            Readiness::PqcReady => Some(0),
+            Readiness::HybridCapable => Some(1),
+            Readiness::ClassicalOnly => Some(2),
+            Readiness::Unreachable => None,
+        }
+    }
+}
+
+/// What an upstream negotiated with the scanner.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TlsPosture {
+    pub protocol_version: String,
+    pub key_exchange_group: String,
+    /// Hybrid groups other than X25519MLKEM768 are classified by their
+    /// ML-KEM component. `None` if unrecognized.
+    pub key_exchange: Option<AnyAlgorithm>,
+    pub cipher_suite: String,
+    /// `None` for ciphers outside [`ClassicalAlgorithm`], e.g. ChaCha20.
+    pub cipher: Option<AnyAlgorithm>,
+    /// Name (or OID, if unrecognized) of the certificate's signature
+    /// algorithm.
+    pub certificate_signature_algorithm: String,
+    pub certificate_key: Option<AnyAlgorithm>,
+    /// RSA modulus or curve size; encoded public key size for other keys.
+    pub certificate_key_bits: Option<u32>,
+}
+
+impl TlsPosture {
+    pub fn readiness(&self) -> Readiness {
+        let is_pqc = |alg: Option<AnyAlgorithm>| alg.is_some_and(|a| a.is_pqc());
+        match (is_pqc(self.key_exchange), is_pqc(self.certificate_key)) {
+            (true, true) => Readiness::PqcReady,
+            (true, false) => Readiness::HybridCapable,
+            (false, _) => Readiness::ClassicalOnly,
+        }
+    }
+
+    /// Risk from 0 (PQC throughout) to 100. Quantum-vulnerable key exchange
+    /// weighs most, since recorded traffic can be decrypted later; then the
+    /// certificate key, TLS below 1.3 and a weak or unknown cipher.
+    pub fn risk_score(&self) -> u8 {
+        let vulnerable = |alg: Option<AnyAlgorithm>| alg.is_none_or(|a| a.is_quantum_vulnerable());
+        let mut score = 0;
+        if vulnerable(self.key_exchange) {
+            score += 50;
+        }
+        if vulnerable(self.certificate_key) {
+            score += 25;
+        }
+        if self.protocol_version != format!("{:?}", ProtocolVersion::TLSv1_3) {
+            score += 15;
+        }
+        if self.cipher.is_some_and(|a| a.is_quantum_vulnerable()) {
+            score += 10;
+        }
+        score
+    }
+}
+
+/// A failed assessment, in the gateway's error body shape.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ScanFailure {
+    pub error_code: String,
+    pub message: String,
+}
+
+/// The verdict for one upstream.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UpstreamAssessment {
+    pub upstream: String,
+    /// `host:port` scanned.
+    pub address: String,
+    pub readiness: Readiness,
+    /// See [`TlsPosture::risk_score`]; 100 for unreachable upstreams.
+    pub risk_score: u8,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub posture: Option<TlsPosture>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<ScanFailure>,
+}
+
+/// The result of one scan over every upstream.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RiskReport {
+    /// Unix time the scan finished, in seconds.
+    pub scanned_at: u64,
+    pub upstreams: Vec<UpstreamAssessment>,
+}
+
+impl RiskReport {
+    /// Percentage of upstreams that are [`Readiness::PqcReady`], rounded
+    /// down; 0 for a report without upstreams.
+    pub fn pqc_ready_percent(&self) -> u64 {
+        let ready = self
+            .upstreams
+            .iter()
+            .filter(|a| a.readiness == Readiness::PqcReady)
+            .count();
+        (ready * 100)
+            .checked_div(self.upstreams.len())
+            .unwrap_or_default() as u64
+    }
+}
+
+/// One scan result in an upstream's history.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HistoryEntry {
+    /// Unix time of the scan, in seconds.
+    pub scanned_at: u64,
+    pub readiness: Readiness,
+    pub risk_score: u8,
+    /// Change in risk score since the previous entry; 0 for the first.
+    pub risk_delta: i16,
+}
+
+/// The history of one upstream, oldest first.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UpstreamHistory {
+    pub upstream: String,
+    pub entries: Vec<HistoryEntry>,
+}
+
+/// An upstream negotiated less PQC than at its last successful scan.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RegressionAlert {
+    pub upstream: String,
+    pub address: String,
+    pub previous: Readiness,
+    /// Unix time of the scan that saw `previous`, in seconds.
+    pub previous_scanned_at: u64,
+    pub current: Readiness,
+    pub scanned_at: u64,
+}
+
+/// Scans the upstreams of a [`ProxyService`] and keeps the latest report
+/// and a history per upstream.
+pub struct UpstreamScanner {
+    proxy: Arc<ProxyService>,
+    timeout: Duration,
+    connector: TlsConnector,
+    report: RwLock<Option<Arc<RiskReport>>>,
+    history_limit: usize,
+    history: Mutex<HashMap<String, VecDeque<HistoryEntry>>>,
+    /// Upstreams with a handshake in progress.
+    in_flight: Arc<Mutex<HashSet<String>>>,
+    alerts: broadcast::Sender<RegressionAlert>,
+    webhook: Option<Uri>,
+    client: Client<HttpConnector, Body>,
+    metrics: Option<Arc<GatewayMetrics>>,
+}
+
+impl UpstreamScanner {
+    pub fn new(proxy: Arc<ProxyService>) -> Self {
+        let mut provider = aws_lc_rs::default_provider();
+        provider.kx_groups = vec![
+            aws_lc_rs::kx_group::X25519MLKEM768,
+            aws_lc_rs::kx_group::SECP256R1MLKEM768,
+            aws_lc_rs::kx_group::X25519,
+            aws_lc_rs::kx_group::SECP256R1,
+            aws_lc_rs::kx_group::SECP384R1,
+            aws_lc_rs::kx_group::MLKEM768,
+            aws_lc_rs::kx_group::MLKEM1024,
+        ];
+        let provider = Arc::new(provider);
+        let config = ClientConfig::builder_with_provider(provider.clone())
+            .with_safe_default_protocol_versions()
+            .expect("the default provider supports the default versions")
+            .dangerous()
+            .with_custom_certificate_verifier(Arc::new(AnyCertificate(provider)))
+            .with_no_client_auth();
+        Self {
+            proxy,
+            timeout: DEFAULT_SCAN_TIMEOUT,
+            connector: TlsConnector::from(Arc::new(config)),
+            report: RwLock::new(None),
+            history_limit: DEFAULT_HISTORY_LIMIT,
+            history: Mutex::new(HashMap::new()),
+            in_flight: Arc::default(),
+            alerts: broadcast::channel(64).0,
+            webhook: None,
+            client: Client::builder(TokioExecutor::new()).build_http(),
+            metrics: None,
+        }
+    }
+
+    /// Limit on connecting to and handshaking with each upstream.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Number of results kept per upstream.
+    pub fn with_history_limit(mut self, limit: usize) -> Self {
+        self.history_limit = limit.max(1);
+        self
+    }
+
+    /// POST each [`RegressionAlert`] as JSON to `url`.
+    pub fn with_webhook(mut self, url: Uri) -> Self {
+        self.webhook = Some(url);
+        self
+    }
+
+    /// Publish the PQC-ready percentage and regression count to `metrics`.
+    pub fn with_metrics(mut self, metrics: Arc<GatewayMetrics>) -> Self {
+        self.metrics = Some(metrics);
+        self
+    }
+
+    /// The latest report, if a scan has run.
+    pub fn report(&self) -> Option<Arc<RiskReport>> {
+        self.report.read().unwrap().clone()
+    }
+
+    /// The results kept for `upstream`, oldest first, or `None` if it has
+    /// never been scanned.
+    pub fn history(&self, upstream: &str) -> Option<Vec<HistoryEntry>> {
+        let history = self.history.lock().unwrap();
+        history.get(upstream).map(|h| h.iter().cloned().collect())
+    }
+
+    /// Receive every [`RegressionAlert`] from now on.
+    pub fn subscribe(&self) -> broadcast::Receiver<RegressionAlert> {
+        self.alerts.subscribe()
+    }
+
+    /// Scan every `period` until the handle is aborted.
+    ///
+    /// Each run is a task of its own, so one that panics is logged and the
+    /// schedule carries on. Runs never overlap: ticks missed while a slow
+    /// run finishes are skipped rather than queued.
+    pub fn spawn(self: Arc<Self>, period: Duration) -> JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(period);
+            interval.set_missed_tick_behavior(MissedTickBehavior::Skip);
+            loop {
+                interval.tick().await;
+                let scanner = self.clone();
+                if let Err(e) = tokio::spawn(async move { scanner.scan().await }).await {
+                    warn!(error = %e, "scheduled upstream TLS scan failed");
+                }
+            }
+        })
+    }
+
+    /// Scan every upstream concurrently and store the report.
+    ///
+    /// An upstream still being scanned by an earlier call is not scanned
+    /// again; the report carries its previous assessment, if any.
+    pub async fn scan(&self) -> Arc<RiskReport> {
+        let previous = self.report();
+        let targets = ScanTarget::from_routes(&self.proxy.routes());
+        let mut scans = JoinSet::new();
+        let mut upstreams = Vec::new();
+        for (i, target) in targets.into_iter().enumerate() {
+            let Some(claim) = InFlight::claim(&self.in_flight, &target.name) else {
+                debug!(upstream = %target.name, "upstream TLS scan already in progress");
+                let carried = previous
+                    .iter()
+                    .flat_map(|r| r.upstreams.iter())
+                    .find(|a| a.upstream == target.name);
+                upstreams.extend(carried.map(|a| (i, a.clone(), false)));
+                continue;
+            };
+            let connector = self.connector.clone();
+            let egress = self.proxy.egress_proxy(&target.name).cloned();
+            let timeout = self.timeout;
+            scans.spawn(async move {
+                let assessment = assess(&connector, egress.as_ref(), &target, timeout).await;
+                drop(claim);
+                (i, assessment, true)
+            });
+        }
+        while let Some(joined) = scans.join_next().await {
+            match joined {
+                Ok(scanned) => upstreams.push(scanned),
+                Err(e) => warn!(error = %e, "upstream TLS scan task failed"),
+            }
+        }
+        upstreams.sort_by_key(|(i, _, _)| *i);
+
+        let scanned_at = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        for (_, assessment, _) in upstreams.iter().filter(|(_, _, fresh)| *fresh) {
+            if let Some(alert) = self.record(assessment, scanned_at) {
+                self.alert(alert);
+            }
+        }
+        let report = Arc::new(RiskReport {
+            scanned_at,
+            upstreams: upstreams.into_iter().map(|(_, a, _)| a).collect(),
+        });
+        if let Some(metrics) = &self.metrics {
+            metrics
+                .pqc_ready_percent
+                .store(report.pqc_ready_percent(), Ordering::Relaxed);
+        }
+        info!(
+            upstreams = report.upstreams.len(),
+            classical_only = report
+                .upstreams
+                .iter()
+                .filter(|a| a.readiness == Readiness::ClassicalOnly)
+                .count(),
+            "upstream TLS scan complete"
+        );
+        *self.report.write().unwrap() = Some(report.clone());
+        report
+    }
+
+    /// Append `assessment` to its upstream's history, returning an alert if
+    /// it is less ready than the last reachable entry.
+    fn record(&self, assessment: &UpstreamAssessment, scanned_at: u64) -> Option<RegressionAlert> {
+        let mut history = self.history.lock().unwrap();
+        let entries = history.entry(assessment.upstream.clone()).or_default();
+
+        let risk_delta = entries.back().map_or(0, |last| {
+            i16::from(assessment.risk_score) - i16::from(last.risk_score)
+        });
+        let last_reachable = entries.iter().rev().find(|e| e.readiness.rank().is_some());
+        let alert = match (
+            last_reachable.and_then(|e| e.readiness.rank()),
+            assessment.readiness.rank(),
+        ) {
+            (Some(before), Some(now)) if now > before => {
+                let last = last_reachable.expect("ranked entries are reachable");
+                Some(RegressionAlert {
+                    upstream: assessment.upstream.clone(),
+                    address: assessment.address.clone(),
+                    previous: last.readiness,
+                    previous_scanned_at: last.scanned_at,
+                    current: assessment.readiness,
+                    scanned_at,
+                })
+            }
+            _ => None,
+        };
+
+        entries.push_back(HistoryEntry {
+            scanned_at,
+            readiness: assessment.readiness,
+            risk_score: assessment.risk_score,
+            risk_delta,
+        });
+        while entries.len() > self.history_limit {
+            entries.pop_front();
+        }
+        alert
+    }
+
+    fn alert(&self, alert: RegressionAlert) {
+        warn!(
+            upstream = %alert.upstream,
+            address = %alert.address,
+            previous = ?alert.previous,
+            current = ?alert.current,
+            "upstream TLS posture regressed"
+        );
+        if let Some(metrics) = &self.metrics {
+            metrics.risk_regressions.fetch_add(1, Ordering::Relaxed);
+        }
+        if let Some(url) = &self.webhook {
+            let client = self.client.clone();
+            let url = url.clone();
+            let body = serde_json::to_vec(&alert).expect("alerts serialize");
+            tokio::spawn(async move {
+                let req = Request::post(url)
+                    .header(header::CONTENT_TYPE, "application/json")
+                    .body(Body::from(body))
+                    .expect("static request parts are valid");
+                match client.request(req).await {
+                    Ok(response) if response.status().is_success() => {}
+                    Ok(response) => {
+                        warn!(status = %response.status(), "risk alert webhook rejected alert")
+                    }
+                    Err(e) => warn!(error = %e, "risk alert webhook unreachable"),
+                }
+            });
+        }
+        // No subscribers is fine.
+        let _ = self.alerts.send(alert);
+    }
+}
+
+/// Marks an upstream as being scanned until dropped.
+struct InFlight {
+    upstreams: Arc<Mutex<HashSet<String>>>,
+    upstream: String,
+}
+
+impl InFlight {
+    fn claim(upstreams: &Arc<Mutex<HashSet<String>>>, upstream: &str) -> Option<Self> {
+        upstreams
+            .lock()
+            .unwrap()
+            .insert(upstream.to_string())
+            .then(|| Self {
+                upstreams: upstreams.clone(),
+                upstream: upstream.to_string(),
+            })
+    }
+}
+
+impl Drop for InFlight {
+    fn drop(&mut self) {
+        self.upstreams.lock().unwrap().remove(&self.upstream);
+    }
+}
+
+async fn assess(
+    connector: &TlsConnector,
+    egress: Option<&EgressProxy>,
+    target: &ScanTarget,
+    timeout: Duration,
+) -> UpstreamAssessment {
+    let address = format!("{}:{}", target.host, target.port);
+    let (readiness, risk_score, posture, error) =
+        match tokio::time::timeout(timeout, handshake(connector, egress, target))
+            .await
+            .unwrap_or(Err(ScanError::Timeout(timeout)))
+        {
+            Ok(posture) => (
+                posture.readiness(),
+                posture.risk_score(),
+                Some(posture),
+                None,
+            ),
+            Err(e) => {
+                warn!(upstream = %target.name, %address, error = %e, "upstream TLS scan failed");
+                let failure = ScanFailure {
+                    error_code: e.error_code().as_str().to_string(),
+                    message: e.to_string(),
+                };
+                (Readiness::Unreachable, 100, None, Some(failure))
+            }
+        };
+    UpstreamAssessment {
+        upstream: target.name.clone(),
+        address,
+        readiness,
+        risk_score,
+        posture,
+        error,
+    }
+}
+
+async fn handshake(
+    connector: &TlsConnector,
+    egress: Option<&EgressProxy>,
+    target: &ScanTarget,
+) -> Result<TlsPosture, ScanError> {
+    let server_name = ServerName::try_from(target.host.clone())
+        .map_err(|e| ScanError::ServerName(e.to_string()))?;
+    let tcp = egress::connect(egress, &target.host, target.port)
+        .await
+        .map_err(|e| match e {
+            EgressError::Connect(e) => ScanError::Connect(e),
+            EgressError::Proxy { proxy, reason } => ScanError::EgressProxy { proxy, reason },
+        })?;
+    let tls = connector
+        .connect(server_name, tcp)
+        .await
+        .map_err(ScanError::Handshake)?;
+
+    let (_, conn) = tls.get_ref();
+    let group = conn.negotiated_key_exchange_group().map(|g| g.name());
+    let suite = conn.negotiated_cipher_suite().map(|s| s.suite());
+    let cert = conn
+        .peer_certificates()
+        .and_then(|certs| certs.first())
+        .and_then(|der| Certificate::from_der(der).ok());
+
+    let mut posture = TlsPosture {
+        protocol_version: conn
+            .protocol_version()
+            .map_or_else(|| "unknown".into(), |v| format!("{v:?}")),
+        key_exchange_group: group.map_or_else(|| "unknown".into(), |g| format!("{g:?}")),
+        key_exchange: group.and_then(classify_group),
+        cipher_suite: suite.map_or_else(|| "unknown".into(), |s| format!("{s:?}")),
+        cipher: suite.and_then(|s| classify_cipher(&format!("{s:?}"))),
+        certificate_signature_algorithm: "unknown".into(),
+        certificate_key: None,
+        certificate_key_bits: None,
+    };
+    if let Some(cert) = cert {
+        let oid = cert.signature_algorithm.oid;
+        posture.certificate_signature_algorithm = signature_algorithm_name(&oid);
+        (posture.certificate_key, posture.certificate_key_bits) = classify_key(&cert);
+    }
+    Ok(posture)
+}
+
+fn classify_group(group: NamedGroup) -> Option<AnyAlgorithm> {
+    Some(match group {
+        NamedGroup::X25519MLKEM768 => Algorithm::Hybrid(HybridVariant::X25519MlKem768).into(),
+        NamedGroup::secp256r1MLKEM768 | NamedGroup::MLKEM768 => {
+            Algorithm::MlKem(MlKemVariant::MlKem768).into()
+        }
+        NamedGroup::MLKEM512 => Algorithm::MlKem(MlKemVariant::MlKem512).into(),
+        NamedGroup::MLKEM1024 => Algorithm::MlKem(MlKemVariant::MlKem1024).into(),
+        NamedGroup::X25519 => ClassicalAlgorithm::X25519.into(),
+        // ECDH over the same curves as the ECDSA variants.
+        NamedGroup::secp256r1 => ClassicalAlgorithm::EcdsaP256.into(),
+        NamedGroup::secp384r1 => ClassicalAlgorithm::EcdsaP384.into(),
+        NamedGroup::FFDHE2048 => ClassicalAlgorithm::Dh2048.into(),
+        NamedGroup::FFDHE3072 => ClassicalAlgorithm::Dh3072.into(),
+        _ => return None,
+    })
+}
+
+fn classify_cipher(suite: &str) -> Option<AnyAlgorithm> {
+    if suite.contains("AES_128") {
+        Some(ClassicalAlgorithm::Aes128.into())
+    } else if suite.contains("AES_256") {
+        Some(ClassicalAlgorithm::Aes256.into())
+    } else {
+        None
+    }
+}
+
+pub(crate) fn signature_algorithm_name(oid: &ObjectIdentifier) -> String {
+    if let Some(alg) = crate::tls::pqc_algorithm(oid) {
+        return alg.to_string();
+    }
+    let oid = oid.to_string();
+    SIGNATURE_ALGORITHMS
+        .iter()
+        .find(|(known, _)| *known == oid)
+        .map_or(oid, |(_, name)| name.to_string())
+}
+
+/// The certificate key's algorithm and size.
+pub(crate) fn classify_key(cert: &Certificate) -> (Option<AnyAlgorithm>, Option<u32>) {
+    let spki = &cert.tbs_certificate.subject_public_key_info;
+    let key = spki.subject_public_key.raw_bytes();
+    let oid = spki.algorithm.oid;
+
+    if let Some(alg) = crate::tls::pqc_algorithm(&oid) {
+        return (Some(alg.into()), Some(key.len() as u32 * 8));
+    }
+    if oid == OID_ED25519 {
+        return (Some(ClassicalAlgorithm::Ed25519.into()), Some(256));
+    }
+    if oid == OID_EC {
+        let curve = spki
+            .algorithm
+            .parameters
+            .as_ref()
+            .and_then(|p| p.decode_as::<ObjectIdentifier>().ok());
+        return match curve {
+            Some(c) if c == OID_P256 => (Some(ClassicalAlgorithm::EcdsaP256.into()), Some(256)),
+            Some(c) if c == OID_P384 => (Some(ClassicalAlgorithm::EcdsaP384.into()), Some(384)),
+            _ => (None, None),
+        };
+    }
+    if oid == OID_RSA {
+        let Some(bits) = rsa_modulus_bits(key) else {
+            return (None, None);
+        };
+        let alg = match bits {
+            0..2560 => ClassicalAlgorithm::Rsa2048,
+            2560..3584 => ClassicalAlgorithm::Rsa3072,
+            _ => ClassicalAlgorithm::Rsa4096,
+        };
+        return (Some(alg.into()), Some(bits));
+    }
+    (None, None)
+}
+
+/// Size of the modulus in a PKCS#1 `RSAPublicKey`.
+fn rsa_modulus_bits(key: &[u8]) -> Option<u32> {
+    let mut reader = SliceReader::new(key).ok()?;
+    let modulus = reader
+        .sequence(|r| {
+            let modulus = UintRef::decode(r)?;
+            UintRef::decode(r)?;
+            Ok(modulus)
+        })
+        .ok()?;
+    let bytes = modulus.as_bytes();
+    let first = *bytes.first()?;
+    Some(bytes.len() as u32 * 8 - first.leading_zeros())
+}
+
+/// Accepts any certificate while still checking handshake signatures, so
+/// the scan completes against self-signed and private-CA upstreams.
+#[derive(Debug)]
+pub(crate) struct AnyCertificate(pub(crate) Arc<CryptoProvider>);
+
+impl ServerCertVerifier for AnyCertificate {
+    fn verify_server_cert(
+        &self,
+        _end_entity: &CertificateDer<'_>,
+        _intermediates: &[CertificateDer<'_>],
+        _server_name: &ServerName<'_>,
+        _ocsp_response: &[u8],
+        _now: UnixTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        Ok(ServerCertVerified::assertion())
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        rustls::crypto::verify_tls12_signature(
+            message,
+            cert,
+            dss,
+            &self.0.signature_verification_algorithms,
+        )
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        rustls::crypto::verify_tls13_signature(
+            message,
+            cert,
+            dss,
+            &self.0.signature_verification_algorithms,
+        )
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.0.signature_verification_algorithms.supported_schemes()
+    }
+}
+
+/// Routes over `scanner`:
+///
+/// - `GET /gateway/risk`: the latest [`RiskReport`], scanning first if
+///   none has run.
+/// - `GET /gateway/risk/history/{upstream}`: the [`UpstreamHistory`] of
+///   one upstream.
+///
+/// [`build_router`](crate::build_router) mounts these as `Default` when
+/// [`GatewayConfig::scanner`](crate::GatewayConfig::scanner) is set.
+pub fn router(scanner: Arc<UpstreamScanner>) -> Router {
+    Router::new()
+        .route(RISK_PATH, get(risk))
+        .route(&format!("{HISTORY_PATH}/{{upstream}}"), get(history))
+        .with_state(scanner)
+}
+
+/// Admin routes over `scanner`:
+///
+/// - `POST /admin/risk/scan`: scan now and return the new report.
+///
+/// A scan connects to every upstream, so [`build_router`](crate::build_router)
+/// mounts these as `AdminOnly`.
+pub fn admin_router(scanner: Arc<UpstreamScanner>) -> Router {
+    Router::new()
+        .route(SCAN_PATH, post(rescan))
+        .with_state(scanner)
+}
+
+async fn risk(State(scanner): State<Arc<UpstreamScanner>>) -> Response {
+    let report = match scanner.report() {
+        Some(report) => report,
+        None => scanner.scan().await,
+    };
+    Json(report.as_ref().clone()).into_response()
+}
+
+async fn history(
+    State(scanner): State<Arc<UpstreamScanner>>,
+    Path(upstream): Path<String>,
+) -> Response {
+    match scanner.history(&upstream) {
+        Some(entries) => Json(UpstreamHistory { upstream, entries }).into_response(),
+        None => (
+            StatusCode::NOT_FOUND,
+            Json(serde_json::json!({
+                "error_code": ErrorCode::NotFound.as_str(),
+                "message": format!("no scan history for upstream {upstream}"),
+            })),
+        )
+            .into_response(),
+    }
+}
+
+async fn rescan(State(scanner): State<Arc<UpstreamScanner>>) -> Response {
+    Json(scanner.scan().await.as_ref().clone()).into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::proxy::testing::MockUpstream;
+    use crate::proxy::{MiddlewareProfile, Upstream};
+    use rustls::crypto::SupportedKxGroup;
+    use rustls::pki_types::pem::PemObject;
+    use rustls::pki_types::PrivateKeyDer;
+    use rustls::ServerConfig;
+    use std::net::SocketAddr;
+    use tokio::net::TcpListener;
+    use tokio_rustls::TlsAcceptor;
+    use tower::ServiceExt;
+
+    /// A TLS 1.3 server offering only `groups`, with the certificate and
+    /// key `testdata/scanner/{identity}.{pem,key}`.
+    async fn tls_server(groups: &[&'static dyn SupportedKxGroup], identity: &str) -> SocketAddr {
+        let dir = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("testdata/scanner");
+        let certs = CertificateDer::pem_file_iter(dir.join(format!("{identity}.pem")))
+            .unwrap()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        let key = PrivateKeyDer::from_pem_file(dir.join(format!("{identity}.key"))).unwrap();
+
+        let provider = CryptoProvider {
+            kx_groups: groups.to_vec(),
+            ..aws_lc_rs::default_provider()
+        };
+        let config = ServerConfig::builder_with_provider(Arc::new(provider))
+            .with_protocol_versions(&[&rustls::version::TLS13])
+            .unwrap()
+            .with_no_client_auth()
+            .with_single_cert(certs, key)
+            .unwrap();
+        let acceptor = TlsAcceptor::from(Arc::new(config));
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                let acceptor = acceptor.clone();
+                tokio::spawn(async move {
+                    let _ = acceptor.accept(stream).await;
+                });
+            }
+        });
+        addr
+    }
+
+    fn route(name: &str, addr: SocketAddr) -> Route {
+        Route {
+            path_prefix: format!("/{name}"),
+            upstream: Upstream {
+                name: name.into(),
+                host: addr.ip().to_string(),
+                port: addr.port(),
+                is_healthy: true,
+                tls_verify: false,
+                tls: false,
+                weight: 1,
+            },
+            strip_prefix: false,
+            path_rewrite: None,
+            priority: 0,
+            coalesce: None,
+            cache: None,
+            tunnel: None,
+            unseal: None,
+            transform: None,
+            sse_filter: None,
+            allowed_methods: Vec::new(),
+            denied_methods: Vec::new(),
+            content_type: None,
+            middleware_profile: MiddlewareProfile::Default,
+        }
+    }
+
+    fn scanner(routes: Vec<Route>) -> UpstreamScanner {
+        UpstreamScanner::new(Arc::new(ProxyService::new(routes, 5)))
+            .with_timeout(Duration::from_millis(500))
+    }
+
+    /// Wait until `upstream` has at least `len` history entries.
+    async fn history_of_len(
+        scanner: &UpstreamScanner,
+        upstream: &str,
+        len: usize,
+    ) -> Vec<HistoryEntry> {
+        tokio::time::timeout(Duration::from_secs(10), async {
+            loop {
+                match scanner.history(upstream) {
+                    Some(entries) if entries.len() >= len => return entries,
+                    _ => tokio::time::sleep(Duration::from_millis(20)).await,
+                }
+            }
+        })
+        .await
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_scheduled_scans_alert_on_regression_once() {
+        use aws_lc_rs::kx_group::{X25519, X25519MLKEM768};
+
+        let pqc = tls_server(&[X25519MLKEM768], "mldsa65").await;
+        let hybrid = tls_server(&[X25519MLKEM768, X25519], "ecdsa-p256").await;
+        let classical = tls_server(&[X25519], "ecdsa-p256").await;
+        let webhook = MockUpstream::start().await.unwrap();
+        let metrics = Arc::new(GatewayMetrics::default());
+        let proxy = Arc::new(ProxyService::new(
+            vec![route("pqc", pqc), route("api", hybrid)],
+            5,
+        ));
+        let scanner = Arc::new(
+            UpstreamScanner::new(proxy.clone())
+                .with_timeout(Duration::from_millis(500))
+                .with_history_limit(16)
+                .with_metrics(metrics.clone())
+                .with_webhook(format!("http://{}/alerts", webhook.addr()).parse().unwrap()),
+        );
+        let mut alerts = scanner.subscribe();
+        let schedule = scanner.clone().spawn(Duration::from_millis(100));
+
+        let before = history_of_len(&scanner, "api", 1).await;
+        assert_eq!(before[0].readiness, Readiness::HybridCapable);
+        assert_eq!(metrics.pqc_ready_percent.load(Ordering::Relaxed), 50);
+
+        // A load balancer change drops the hybrid group.
+        proxy
+            .replace_routes(vec![route("pqc", pqc), route("api", classical)])
+            .unwrap();
+        let alert = tokio::time::timeout(Duration::from_secs(10), alerts.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(alert.upstream, "api");
+        assert_eq!(alert.previous, Readiness::HybridCapable);
+        assert_eq!(alert.current, Readiness::ClassicalOnly);
+
+        // Later runs see the same posture and stay quiet.
+        let seen = scanner.history("api").unwrap().len();
+        let entries = history_of_len(&scanner, "api", seen + 2).await;
+        schedule.abort();
+        assert!(matches!(
+            alerts.try_recv(),
+            Err(broadcast::error::TryRecvError::Empty)
+        ));
+        assert_eq!(metrics.risk_regressions.load(Ordering::Relaxed), 1);
+        assert_eq!(metrics.pqc_ready_percent.load(Ordering::Relaxed), 50);
+
+        let change = entries
+            .iter()
+            .find(|e| e.readiness == Readiness::ClassicalOnly)
+            .unwrap();
+        assert_eq!(change.risk_delta, 50);
+        assert!(scanner
+            .history("pqc")
+            .unwrap()
+            .iter()
+            .all(|e| e.readiness == Readiness::PqcReady && e.risk_delta == 0));
+
+        let posted = webhook.requests();
+        assert_eq!(posted.len(), 1);
+        assert_eq!(posted[0].uri.path(), "/alerts");
+        let body: RegressionAlert = serde_json::from_slice(&posted[0].body).unwrap();
+        assert_eq!(body, alert);
+    }
+
+    #[tokio::test]
+    async fn test_overlapping_scans_skip_busy_upstreams() {
+        // Accepts connections but never answers the ClientHello.
+        let silent = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let scanner =
+            scanner(vec![route("slow", silent.local_addr().unwrap())]).with_history_limit(2);
+
+        let (first, second) = tokio::join!(scanner.scan(), scanner.scan());
+        assert_eq!(first.upstreams[0].readiness, Readiness::Unreachable);
+        assert!(second.upstreams.is_empty());
+        assert_eq!(scanner.history("slow").unwrap().len(), 1);
+
+        // Unreachable results are not regressions.
+        let mut alerts = scanner.subscribe();
+        let third = scanner.scan().await;
+        assert_eq!(third.upstreams.len(), 1);
+        assert_eq!(scanner.history("slow").unwrap().len(), 2);
+        scanner.scan().await;
+        assert_eq!(scanner.history("slow").unwrap().len(), 2);
+        assert!(alerts.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_scan_classifies_upstreams() {
+        use aws_lc_rs::kx_group::{SECP256R1, X25519, X25519MLKEM768};
+
+        let pqc = tls_server(&[X25519MLKEM768], "mldsa65").await;
+        let hybrid = tls_server(&[X25519MLKEM768, X25519], "ecdsa-p256").await;
+        let classical = tls_server(&[X25519, SECP256R1], "ecdsa-p256").await;
+        let scanner = scanner(vec![
+            route("pqc", pqc),
+            route("hybrid", hybrid),
+            route("classical", classical),
+            route("pqc-again", pqc),
+            Route {
+                path_prefix: "/pqc/v2".into(),
+                ..route("pqc", pqc)
+            },
+        ]);
+
+        let report = scanner.scan().await;
+        let names: Vec<_> = report
+            .upstreams
+            .iter()
+            .map(|a| a.upstream.as_str())
+            .collect();
+        assert_eq!(names, ["pqc", "hybrid", "classical", "pqc-again"]);
+
+        let pqc = &report.upstreams[0];
+        assert_eq!(pqc.readiness, Readiness::PqcReady);
+        assert_eq!(pqc.risk_score, 0);
+        let posture = pqc.posture.as_ref().unwrap();
+        assert_eq!(posture.protocol_version, "TLSv1_3");
+        assert_eq!(posture.key_exchange_group, "X25519MLKEM768");
+        assert_eq!(
+            posture.key_exchange,
+            Some(Algorithm::Hybrid(HybridVariant::X25519MlKem768).into())
+        );
+        assert_eq!(posture.certificate_signature_algorithm, "ML-DSA-65");
+        assert_eq!(
+            posture.certificate_key,
+            Some(Algorithm::MlDsa(quantun_types::MlDsaVariant::MlDsa65).into())
+        );
+
+        let hybrid = &report.upstreams[1];
+        assert_eq!(hybrid.readiness, Readiness::HybridCapable);
+        assert_eq!(hybrid.risk_score, 25);
+        let posture = hybrid.posture.as_ref().unwrap();
+        assert_eq!(posture.certificate_signature_algorithm, "ecdsa-with-SHA256");
+        assert_eq!(
+            posture.certificate_key,
+            Some(ClassicalAlgorithm::EcdsaP256.into())
+        );
+        assert_eq!(posture.certificate_key_bits, Some(256));
+        assert!(posture.cipher.is_some());
+
+        let classical = &report.upstreams[2];
+        assert_eq!(classical.readiness, Readiness::ClassicalOnly);
+        assert_eq!(classical.risk_score, 75);
+        let posture = classical.posture.as_ref().unwrap();
+        assert_eq!(posture.key_exchange_group, "X25519");
+        assert_eq!(
+            posture.key_exchange,
+            Some(ClassicalAlgorithm::X25519.into())
+        );
+
+        assert_eq!(scanner.report().unwrap(), report);
+    }
+
+    #[tokio::test]
+    async fn test_unreachable_upstreams() {
+        // Accepts connections but never answers the ClientHello.
+        let silent = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let silent_addr = silent.local_addr().unwrap();
+        let closed = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let closed_addr = closed.local_addr().unwrap();
+        drop(closed);
+
+        let scanner = scanner(vec![
+            route("silent", silent_addr),
+            route("closed", closed_addr),
+        ]);
+        let report = scanner.scan().await;
+        drop(silent);
+
+        for (assessment, code) in report
+            .upstreams
+            .iter()
+            .zip(["SCAN_TIMEOUT", "ASSESSMENT_FAILED"])
+        {
+            assert_eq!(assessment.readiness, Readiness::Unreachable);
+            assert_eq!(assessment.risk_score, 100);
+            assert!(assessment.posture.is_none());
+            assert_eq!(assessment.error.as_ref().unwrap().error_code, code);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_scans_through_the_egress_proxy() {
+        use crate::proxy::egress::EgressConfig;
+        use crate::proxy::testing::MockConnectProxy;
+
+        let hybrid = tls_server(&[aws_lc_rs::kx_group::X25519MLKEM768], "ecdsa-p256").await;
+        let proxy = MockConnectProxy::start(None).await.unwrap();
+        let closed = TcpListener::bind("127.0.0.1:0")
+            .await
+            .unwrap()
+            .local_addr()
+            .unwrap();
+        let egress = EgressConfig {
+            default: Some(proxy.egress_proxy()),
+            upstreams: [(
+                "closed".to_string(),
+                Some(EgressProxy {
+                    port: closed.port(),
+                    ..proxy.egress_proxy()
+                }),
+            )]
+            .into(),
+        };
+        let routes = vec![route("hybrid", hybrid), route("closed", hybrid)];
+        let scanner =
+            UpstreamScanner::new(Arc::new(ProxyService::new(routes, 5).with_egress(egress)))
+                .with_timeout(Duration::from_millis(500));
+
+        let report = scanner.scan().await;
+        let posture = report.upstreams[0].posture.as_ref().unwrap();
+        assert_eq!(posture.key_exchange_group, "X25519MLKEM768");
+        let requests = proxy.requests();
+        assert_eq!(requests.len(), 1);
+        assert!(requests[0].starts_with(&format!("CONNECT {hybrid} ")));
+
+        let failed = &report.upstreams[1];
+        assert_eq!(failed.readiness, Readiness::Unreachable);
+        let message = &failed.error.as_ref().unwrap().message;
+        let expected = format!("egress proxy 127.0.0.1:{} failed", closed.port());
+        assert!(message.starts_with(&expected), "{message}");
+    }
+
+    #[tokio::test]
+    async fn test_risk_endpoints() {
+        let classical = tls_server(&[aws_lc_rs::kx_group::X25519], "ecdsa-p256").await;
+        let scanner = Arc::new(scanner(vec![route("classical", classical)]));
+        let app = router(scanner.clone()).merge(admin_router(scanner.clone()));
+
+        let get_report = |req: Request<Body>| {
+            let app = app.clone();
+            async move {
+                let response = app.oneshot(req).await.unwrap();
+                assert_eq!(response.status(), StatusCode::OK);
+                let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+                    .await
+                    .unwrap();
+                serde_json::from_slice::<serde_json::Value>(&body).unwrap()
+            }
+        };
+
+        // The first read scans.
+        assert!(scanner.report().is_none());
+        let report = get_report(Request::get(RISK_PATH).body(Body::empty()).unwrap()).await;
+        assert_eq!(report["upstreams"][0]["readiness"], "classical_only");
+        assert_eq!(
+            report["upstreams"][0]["posture"]["key_exchange"]["Classical"],
+            "X25519"
+        );
+        let first = scanner.report().unwrap();
+
+        let rescanned = get_report(Request::post(SCAN_PATH).body(Body::empty()).unwrap()).await;
+        assert_eq!(rescanned["upstreams"], report["upstreams"]);
+        assert!(!Arc::ptr_eq(&first, &scanner.report().unwrap()));
+
+        let history = get_report(
+            Request::get(format!("{HISTORY_PATH}/classical"))
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await;
+        assert_eq!(history["upstream"], "classical");
+        assert_eq!(history["entries"].as_array().unwrap().len(), 2);
+        assert_eq!(history["entries"][1]["readiness"], "classical_only");
+        assert_eq!(history["entries"][1]["risk_delta"], 0);
+
+        let response = app
+            .oneshot(
+                Request::get(format!("{HISTORY_PATH}/unknown"))
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+}
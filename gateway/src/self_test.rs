@@ -0,0 +1,245 @@
+//! Crypto power-on self tests.
+//!
+//! `qsgw serve` runs [`SelfTestConfig::run_at_startup`] before binding, so
+//! a broken crypto stack is caught before traffic flows. The latest report
+//! is included in `/health` and can be refreshed through
+//! `POST /admin/crypto/self-test`.
+
+use axum::extract::State;
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, MethodRouter};
+use axum::Json;
+use http::StatusCode;
+use quantun_crypto::self_test::{power_on_self_test_with, EntropySource, OsEntropy};
+use quantun_crypto::SelfTestReport;
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, RwLock};
+use thiserror::Error;
+use tracing::{error, info, warn};
+
+/// Path of the admin endpoint that reports and re-runs the self tests.
+pub const ADMIN_PATH: &str = "/admin/crypto/self-test";
+
+/// What startup does when a self test fails.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SelfTestFailure {
+    /// Refuse to start.
+    #[default]
+    Abort,
+    /// Log the failure and start anyway.
+    Warn,
+}
+
+#[derive(Debug, Error)]
+#[error("crypto self test failed: {}", failed_names(.0))]
+pub struct SelfTestError(pub SelfTestReport);
+
+fn failed_names(report: &SelfTestReport) -> String {
+    report
+        .failures()
+        .map(|c| c.name.as_str())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+#[derive(Clone)]
+pub struct SelfTestConfig {
+    pub on_failure: SelfTestFailure,
+    /// Source for the RNG check; [`OsEntropy`] unless replaced.
+    pub entropy: Arc<dyn EntropySource>,
+    /// The latest report. Shared, so routes built from the config see runs
+    /// made after they were built.
+    pub latest: Arc<RwLock<Option<SelfTestReport>>>,
+}
+
+impl Default for SelfTestConfig {
+    fn default() -> Self {
+        Self {
+            on_failure: SelfTestFailure::default(),
+            entropy: Arc::new(OsEntropy),
+            latest: Arc::default(),
+        }
+    }
+}
+
+impl SelfTestConfig {
+    /// Run the self tests, log each result, and record the report.
+    pub fn run(&self) -> SelfTestReport {
+        let report = power_on_self_test_with(self.entropy.as_ref());
+        for check in &report.checks {
+            let duration_us = check.duration_us;
+            match &check.error {
+                None => info!(check = %check.name, duration_us, "crypto self test passed"),
+                Some(e) => {
+                    error!(check = %check.name, duration_us, error = %e, "crypto self test failed")
+                }
+            }
+        }
+        *self.latest.write().unwrap() = Some(report.clone());
+        report
+    }
+
+    /// [`SelfTestConfig::run`], failing if a check fails and
+    /// [`SelfTestConfig::on_failure`] is [`SelfTestFailure::Abort`].
+    pub fn run_at_startup(&self) -> Result<SelfTestReport, SelfTestError> {
+        let report = self.run();
+        if report.passed() {
+            return Ok(report);
+        }
+        match self.on_failure {
+            SelfTestFailure::Abort => Err(SelfTestError(report)),
+            SelfTestFailure::Warn => {
+                warn!(
+                    failed = %failed_names(&report),
+                    "starting despite failed crypto self tests"
+                );
+                Ok(report)
+            }
+        }
+    }
+
+    pub fn latest(&self) -> Option<SelfTestReport> {
+        self.latest.read().unwrap().clone()
+    }
+}
+
+/// The `crypto_self_test` section of `/health`: the latest report with an
+/// overall `passed`, or `null` if the tests have not run.
+pub fn health_section(config: &SelfTestConfig) -> serde_json::Value {
+    config.latest().map_or(serde_json::Value::Null, |report| {
+        serde_json::json!({
+            "passed": report.passed(),
+            "run_at": report.run_at,
+            "checks": report.checks,
+        })
+    })
+}
+
+/// Handlers for [`ADMIN_PATH`]:
+///
+/// - `GET`: the latest report, or `404` if the tests have not run.
+/// - `POST`: run the tests now and return the new report.
+pub fn admin_routes<S>(config: SelfTestConfig) -> MethodRouter<S>
+where
+    S: Clone + Send + Sync + 'static,
+{
+    get(latest).post(rerun).with_state(config)
+}
+
+async fn latest(State(config): State<SelfTestConfig>) -> Response {
+    match config.latest() {
+        Some(_) => Json(health_section(&config)).into_response(),
+        None => StatusCode::NOT_FOUND.into_response(),
+    }
+}
+
+async fn rerun(State(config): State<SelfTestConfig>) -> Response {
+    let run = config.clone();
+    match tokio::task::spawn_blocking(move || run.run()).await {
+        Ok(_) => Json(health_section(&config)).into_response(),
+        Err(_) => StatusCode::INTERNAL_SERVER_ERROR.into_response(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{build_router, GatewayConfig};
+    use axum::body::Body;
+    use http::Request;
+    use tower::ServiceExt;
+
+    /// Returns the same bytes on every call.
+    struct StuckEntropy;
+
+    impl EntropySource for StuckEntropy {
+        fn fill(&self, dest: &mut [u8]) -> Result<(), String> {
+            for (i, b) in dest.iter_mut().enumerate() {
+                *b = i as u8;
+            }
+            Ok(())
+        }
+    }
+
+    async fn get_json(app: &axum::Router, method: &str, uri: &str) -> serde_json::Value {
+        let response = app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .method(method)
+                    .uri(uri)
+                    .header("x-api-key", "ops")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        serde_json::from_slice(&body).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_failing_entropy_aborts_or_warns_at_startup() {
+        let mut self_test = SelfTestConfig {
+            entropy: Arc::new(StuckEntropy),
+            ..SelfTestConfig::default()
+        };
+        let err = self_test.run_at_startup().unwrap_err();
+        assert_eq!(err.to_string(), "crypto self test failed: RNG");
+
+        self_test.on_failure = SelfTestFailure::Warn;
+        let report = self_test.run_at_startup().unwrap();
+        assert!(!report.passed());
+
+        let config = GatewayConfig {
+            self_test,
+            ..GatewayConfig::default()
+        };
+        let health = get_json(&build_router(&config), "GET", "/health").await;
+        assert_eq!(health["status"], "degraded");
+        let section = &health["crypto_self_test"];
+        assert_eq!(section["passed"], false);
+        assert_eq!(section["checks"][0]["name"], "RNG");
+        assert_eq!(section["checks"][0]["passed"], false);
+        assert_eq!(section["checks"][1]["passed"], true);
+    }
+
+    #[tokio::test]
+    async fn test_admin_endpoint_reruns_self_tests() {
+        use crate::auth::{ApiKey, AuthConfig, AuthPolicy, ADMIN_SCOPE};
+
+        let policy = AuthPolicy::new(AuthConfig {
+            require_auth: true,
+            api_keys: vec![ApiKey {
+                id: "ops".into(),
+                name: "ops".into(),
+                scopes: vec![ADMIN_SCOPE.into()],
+                signing_key: None,
+                metadata: Default::default(),
+                secret: None,
+            }],
+            ..AuthConfig::default()
+        })
+        .unwrap();
+        let config = GatewayConfig {
+            auth: Some(Arc::new(policy)),
+            ..GatewayConfig::default()
+        };
+        let app = build_router(&config);
+        let health = get_json(&app, "GET", "/health").await;
+        assert_eq!(health["status"], "ok");
+        assert!(health["crypto_self_test"].is_null());
+
+        let report = get_json(&app, "POST", ADMIN_PATH).await;
+        assert_eq!(report["passed"], true);
+        assert!(report["run_at"].as_u64().unwrap() > 0);
+
+        let health = get_json(&app, "GET", "/health").await;
+        assert_eq!(health["crypto_self_test"], report);
+        assert_eq!(get_json(&app, "GET", ADMIN_PATH).await, report);
+    }
+}
@@ -0,0 +1,759 @@
+//! Serving the gateway [`Router`] on a TCP listener until shutdown.
+//!
+//! Given a rustls config, the gateway terminates TLS itself and acts as the
+//! termination layer the middleware expects: each request carries the
+//! negotiated `x-tls-cipher-suite`, the client certificate if one was
+//! presented, and a [`HandshakeInfo`] extension. Without one it serves plain
+//! HTTP. Client-supplied copies of the terminator's headers are dropped
+//! unless an external terminator is trusted to set them; see
+//! [`Termination`].
+
+use axum::body::Body;
+use axum::extract::ConnectInfo;
+use axum::Router;
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use http::{HeaderValue, Request};
+use hyper::body::Incoming;
+use hyper_util::rt::{TokioExecutor, TokioIo};
+use hyper_util::server::conn::auto;
+use hyper_util::server::graceful::{GracefulShutdown, Watcher};
+use std::future::Future;
+use std::io;
+use std::net::SocketAddr;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::net::TcpListener;
+use tokio::sync::watch;
+use tokio::task::JoinSet;
+use tokio_rustls::TlsAcceptor;
+use tower::ServiceExt;
+use tracing::{debug, info, warn};
+
+use crate::metrics::{GatewayMetrics, HandshakeFailure};
+use crate::middleware::{CLIENT_CERT_HEADER, EARLY_DATA_HEADER};
+use crate::tls::{self, HandshakeInfo};
+
+/// Header naming the negotiated cipher suite, read by
+/// [`pqc_enforcement_middleware`](crate::middleware::pqc_enforcement_middleware).
+pub const CIPHER_SUITE_HEADER: &str = "x-tls-cipher-suite";
+
+/// Headers only the TLS terminator may set.
+const TERMINATOR_HEADERS: [&str; 3] = [CIPHER_SUITE_HEADER, CLIENT_CERT_HEADER, EARLY_DATA_HEADER];
+
+/// In-process TLS termination for [`serve`].
+#[derive(Clone)]
+pub struct TlsTermination {
+    pub config: Arc<rustls::ServerConfig>,
+    /// Connections whose handshake takes longer are closed and counted in
+    /// `metrics.handshake_timeouts`; see
+    /// [`GatewayConfig::handshake_timeout_secs`](crate::GatewayConfig::handshake_timeout_secs).
+    pub handshake_timeout: Duration,
+}
+
+/// Who terminates TLS for the connections [`serve`] accepts.
+#[derive(Clone)]
+pub enum Termination {
+    /// The gateway, in-process.
+    Tls(TlsTermination),
+    /// An external terminator, trusted to set the terminator's headers.
+    TrustedProxy,
+    /// Nobody the gateway trusts: plain HTTP, with the terminator's headers
+    /// dropped.
+    Plain,
+}
+
+/// How accepted connections are set up, from a [`Termination`].
+#[derive(Clone)]
+enum Acceptor {
+    Tls(TlsAcceptor, Duration),
+    Plain { trust_headers: bool },
+}
+
+/// What requests on one connection learn from the in-process handshake.
+#[derive(Clone)]
+struct Handshake {
+    info: HandshakeInfo,
+    /// The client's end-entity certificate, encoded for
+    /// [`CLIENT_CERT_HEADER`].
+    client_cert: Option<HeaderValue>,
+}
+
+/// Serve `router` on `listener` until `shutdown` completes, then stop
+/// accepting and wait for open connections to finish.
+///
+/// Connections, their TLS handshakes and sessions are counted in
+/// `metrics`, and emitted to its sink; see
+/// [`GatewayMetrics::connection_opened`].
+pub async fn serve(
+    listener: TcpListener,
+    router: Router,
+    termination: Termination,
+    metrics: Arc<GatewayMetrics>,
+    shutdown: impl Future<Output = ()>,
+) {
+    serve_acceptors(
+        vec![Arc::new(listener)],
+        router,
+        termination,
+        metrics,
+        shutdown,
+    )
+    .await;
+}
+
+/// [`serve`] with an accept task per entry of `listeners`, as bound by
+/// [`listener::bind`](crate::listener::bind). Entries may share a socket.
+/// On shutdown every listener is closed before open connections are
+/// drained.
+pub async fn serve_acceptors(
+    listeners: Vec<Arc<TcpListener>>,
+    router: Router,
+    termination: Termination,
+    metrics: Arc<GatewayMetrics>,
+    shutdown: impl Future<Output = ()>,
+) {
+    let acceptor = match termination {
+        Termination::Tls(tls) => {
+            Acceptor::Tls(TlsAcceptor::from(tls.config), tls.handshake_timeout)
+        }
+        Termination::TrustedProxy => Acceptor::Plain {
+            trust_headers: true,
+        },
+        Termination::Plain => Acceptor::Plain {
+            trust_headers: false,
+        },
+    };
+    let graceful = Arc::new(GracefulShutdown::new());
+    let (stop, stopped) = watch::channel(false);
+    let mut tasks = JoinSet::new();
+    for (index, listener) in listeners.into_iter().enumerate() {
+        tasks.spawn(accept_loop(
+            index,
+            listener,
+            router.clone(),
+            acceptor.clone(),
+            metrics.clone(),
+            graceful.clone(),
+            stopped.clone(),
+        ));
+    }
+    info!(acceptors = tasks.len(), "accepting connections");
+
+    shutdown.await;
+    info!("shutting down, closing listeners");
+    let _ = stop.send(true);
+    while tasks.join_next().await.is_some() {}
+    let graceful = Arc::into_inner(graceful).expect("accept tasks have finished");
+    info!(
+        open = metrics.active_connections.load(Ordering::Relaxed),
+        "listeners closed, draining open connections"
+    );
+    graceful.shutdown().await;
+    info!("open connections drained");
+}
+
+/// Accept connections on `listener` until `stopped` changes.
+async fn accept_loop(
+    index: usize,
+    listener: Arc<TcpListener>,
+    router: Router,
+    acceptor: Acceptor,
+    metrics: Arc<GatewayMetrics>,
+    graceful: Arc<GracefulShutdown>,
+    mut stopped: watch::Receiver<bool>,
+) {
+    debug!(acceptor = index, "acceptor started");
+    loop {
+        let (stream, peer) = tokio::select! {
+            accepted = listener.accept() => match accepted {
+                Ok(accepted) => accepted,
+                Err(e) => {
+                    warn!(acceptor = index, error = %e, "accept failed");
+                    tokio::time::sleep(Duration::from_millis(100)).await;
+                    continue;
+                }
+            },
+            _ = stopped.changed() => break,
+        };
+        let router = router.clone();
+        let acceptor = acceptor.clone();
+        let metrics = metrics.clone();
+        let watcher = graceful.watcher();
+        tokio::spawn(async move {
+            let mut connection = metrics.connection_opened();
+            match acceptor {
+                Acceptor::Plain { trust_headers } => {
+                    serve_connection(stream, peer, router, None, trust_headers, watcher).await
+                }
+                Acceptor::Tls(acceptor, handshake_timeout) => {
+                    connection.handshake_started();
+                    let start = Instant::now();
+                    match tokio::time::timeout(handshake_timeout, acceptor.accept(stream)).await {
+                        Ok(Ok(stream)) => {
+                            let elapsed = start.elapsed();
+                            let session = stream.get_ref().1;
+                            let handshake = Handshake {
+                                info: tls::handshake_info(session, elapsed),
+                                client_cert: session
+                                    .peer_certificates()
+                                    .and_then(|certs| certs.first())
+                                    .and_then(|cert| {
+                                        HeaderValue::from_str(&STANDARD.encode(cert)).ok()
+                                    }),
+                            };
+                            connection.handshake_succeeded(handshake.info.is_pqc, elapsed);
+                            serve_connection(stream, peer, router, Some(handshake), false, watcher)
+                                .await;
+                        }
+                        Ok(Err(e)) => {
+                            connection.handshake_failed(handshake_failure(&e));
+                            debug!(%peer, error = %e, "TLS handshake failed");
+                        }
+                        Err(_) => {
+                            connection.handshake_failed(HandshakeFailure::Timeout);
+                            debug!(%peer, "TLS handshake timed out");
+                        }
+                    }
+                }
+            }
+        });
+    }
+    debug!(acceptor = index, "acceptor stopped");
+}
+
+/// Classify an error from [`TlsAcceptor::accept`].
+fn handshake_failure(e: &io::Error) -> HandshakeFailure {
+    match e.get_ref().and_then(|e| e.downcast_ref::<rustls::Error>()) {
+        Some(rustls::Error::InvalidCertificate(_) | rustls::Error::NoCertificatesPresented) => {
+            HandshakeFailure::Certificate
+        }
+        Some(_) => HandshakeFailure::Protocol,
+        None => HandshakeFailure::Io,
+    }
+}
+
+async fn serve_connection<I>(
+    io: I,
+    peer: SocketAddr,
+    router: Router,
+    handshake: Option<Handshake>,
+    trust_headers: bool,
+    watcher: Watcher,
+) where
+    I: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+{
+    let service = hyper::service::service_fn(move |req: Request<Incoming>| {
+        let mut req = req.map(Body::new);
+        req.extensions_mut().insert(ConnectInfo(peer));
+        if !trust_headers {
+            for name in TERMINATOR_HEADERS {
+                req.headers_mut().remove(name);
+            }
+        }
+        if let Some(handshake) = &handshake {
+            if let Ok(suite) = HeaderValue::from_str(&handshake.info.cipher_suite) {
+                req.headers_mut().insert(CIPHER_SUITE_HEADER, suite);
+            }
+            if let Some(cert) = &handshake.client_cert {
+                req.headers_mut().insert(CLIENT_CERT_HEADER, cert.clone());
+            }
+            req.extensions_mut().insert(handshake.info.clone());
+        }
+        router.clone().oneshot(req)
+    });
+    let builder = auto::Builder::new(TokioExecutor::new());
+    let conn = builder.serve_connection_with_upgrades(TokioIo::new(io), service);
+    if let Err(e) = watcher.watch(conn.into_owned()).await {
+        debug!(%peer, error = %e, "connection closed with error");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scanner::AnyCertificate;
+    use crate::TlsPolicy;
+    use axum::routing::get;
+    use http::HeaderMap;
+    use http_body_util::BodyExt;
+    use rustls::crypto::aws_lc_rs;
+    use rustls::pki_types::ServerName;
+    use std::path::Path;
+    use tokio::net::TcpStream;
+    use tokio_rustls::TlsConnector;
+
+    fn termination(handshake_timeout: Duration) -> Termination {
+        tls_termination(handshake_timeout, None)
+    }
+
+    fn tls_termination(
+        handshake_timeout: Duration,
+        client_certs: Option<&tls::ClientCertVerifier>,
+    ) -> Termination {
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("testdata/scanner");
+        let config = tls::server_config(
+            TlsPolicy::Hybrid,
+            &dir.join("ecdsa-p256.pem"),
+            &dir.join("ecdsa-p256.key"),
+            client_certs,
+        )
+        .unwrap();
+        Termination::Tls(TlsTermination {
+            config: Arc::new(config),
+            handshake_timeout,
+        })
+    }
+
+    #[tokio::test]
+    async fn test_tls_termination_sets_handshake_headers() {
+        let router = Router::new().route(
+            "/echo",
+            get(|headers: HeaderMap, req: Request<Body>| async move {
+                let handshake = req.extensions().get::<HandshakeInfo>().unwrap();
+                format!(
+                    "{} {} {}",
+                    headers[CIPHER_SUITE_HEADER].to_str().unwrap(),
+                    handshake.kem_algorithm.as_deref().unwrap_or("-"),
+                    headers.contains_key(CLIENT_CERT_HEADER),
+                )
+            }),
+        );
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let metrics = Arc::new(GatewayMetrics::default());
+        let (stop, stopped) = tokio::sync::oneshot::channel::<()>();
+        let server = tokio::spawn(serve(
+            listener,
+            router,
+            termination(Duration::from_secs(10)),
+            metrics.clone(),
+            async move {
+                let _ = stopped.await;
+            },
+        ));
+
+        let provider = Arc::new(aws_lc_rs::default_provider());
+        let client = rustls::ClientConfig::builder_with_provider(provider.clone())
+            .with_safe_default_protocol_versions()
+            .unwrap()
+            .dangerous()
+            .with_custom_certificate_verifier(Arc::new(AnyCertificate(provider)))
+            .with_no_client_auth();
+        let stream = TlsConnector::from(Arc::new(client))
+            .connect(
+                ServerName::try_from("localhost").unwrap(),
+                TcpStream::connect(addr).await.unwrap(),
+            )
+            .await
+            .unwrap();
+        let (mut sender, conn) = hyper::client::conn::http1::handshake(TokioIo::new(stream))
+            .await
+            .unwrap();
+        tokio::spawn(conn);
+
+        let req = Request::get("/echo")
+            .header(CIPHER_SUITE_HEADER, "forged")
+            .header(CLIENT_CERT_HEADER, "forged")
+            .body(Body::empty())
+            .unwrap();
+        let body = sender.send_request(req).await.unwrap().into_body();
+        let body = body.collect().await.unwrap().to_bytes();
+        assert_eq!(
+            std::str::from_utf8(&body).unwrap(),
+            "TLS_X25519-ML-KEM-768_AES_256_GCM_SHA384 X25519-ML-KEM-768 false"
+        );
+        assert_eq!(metrics.active_connections.load(Ordering::Relaxed), 1);
+
+        drop(sender);
+        stop.send(()).unwrap();
+        tokio::time::timeout(Duration::from_secs(5), server)
+            .await
+            .unwrap()
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_plain_http_drops_terminator_headers() {
+        for (termination, forwarded) in [
+            (Termination::Plain, "- -"),
+            (Termination::TrustedProxy, "forged forged"),
+        ] {
+            let router = Router::new().route(
+                "/echo",
+                get(|headers: HeaderMap| async move {
+                    let header = |name| headers.get(name).map_or("-", |v| v.to_str().unwrap());
+                    format!(
+                        "{} {}",
+                        header(CIPHER_SUITE_HEADER),
+                        header(CLIENT_CERT_HEADER)
+                    )
+                }),
+            );
+            let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let addr = listener.local_addr().unwrap();
+            let (stop, stopped) = tokio::sync::oneshot::channel::<()>();
+            let server = tokio::spawn(serve(
+                listener,
+                router,
+                termination,
+                Arc::new(GatewayMetrics::default()),
+                async move {
+                    let _ = stopped.await;
+                },
+            ));
+
+            let stream = TcpStream::connect(addr).await.unwrap();
+            let (mut sender, conn) = hyper::client::conn::http1::handshake(TokioIo::new(stream))
+                .await
+                .unwrap();
+            tokio::spawn(conn);
+            let req = Request::get("/echo")
+                .header(CIPHER_SUITE_HEADER, "forged")
+                .header(CLIENT_CERT_HEADER, "forged")
+                .body(Body::empty())
+                .unwrap();
+            let body = sender.send_request(req).await.unwrap().into_body();
+            let body = body.collect().await.unwrap().to_bytes();
+            assert_eq!(std::str::from_utf8(&body).unwrap(), forwarded);
+
+            drop(sender);
+            stop.send(()).unwrap();
+            tokio::time::timeout(Duration::from_secs(5), server)
+                .await
+                .unwrap()
+                .unwrap();
+        }
+    }
+
+    #[tokio::test]
+    async fn test_tls_client_certificate_identifies_the_client() {
+        use crate::middleware::{pqc_enforcement_middleware, PqcEnforcement};
+        use crate::tls::{ClientCertVerifier, ClientIdentity, MtlsConfig};
+        use axum::Extension;
+        use rustls::pki_types::pem::PemObject;
+        use rustls::pki_types::{CertificateDer, PrivateKeyDer};
+
+        let fixtures = Path::new(env!("CARGO_MANIFEST_DIR")).join("testdata/mtls");
+        let verifier = Arc::new(
+            ClientCertVerifier::load(MtlsConfig {
+                ca_path: fixtures.join("ca-ed25519.pem"),
+                require_pqc_client_cert: false,
+                allowed_cn_patterns: vec![],
+            })
+            .unwrap(),
+        );
+        let router = Router::new()
+            .route(
+                "/whoami",
+                get(|Extension(id): Extension<ClientIdentity>| async move { id.common_name }),
+            )
+            .layer(axum::middleware::from_fn_with_state(
+                PqcEnforcement {
+                    policy: TlsPolicy::Hybrid,
+                    mtls: Some(verifier.clone()),
+                },
+                pqc_enforcement_middleware,
+            ));
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let (stop, stopped) = tokio::sync::oneshot::channel::<()>();
+        let server = tokio::spawn(serve(
+            listener,
+            router,
+            tls_termination(Duration::from_secs(10), Some(&verifier)),
+            Arc::new(GatewayMetrics::default()),
+            async move {
+                let _ = stopped.await;
+            },
+        ));
+
+        let whoami = |client: rustls::ClientConfig| async move {
+            let stream = TlsConnector::from(Arc::new(client))
+                .connect(
+                    ServerName::try_from("localhost").unwrap(),
+                    TcpStream::connect(addr).await.unwrap(),
+                )
+                .await
+                .unwrap();
+            let (mut sender, conn) = hyper::client::conn::http1::handshake(TokioIo::new(stream))
+                .await
+                .unwrap();
+            tokio::spawn(conn);
+            let req = Request::get("/whoami")
+                .header(CLIENT_CERT_HEADER, "forged")
+                .body(Body::empty())
+                .unwrap();
+            let response = sender.send_request(req).await.unwrap();
+            let status = response.status();
+            let body = response.into_body().collect().await.unwrap().to_bytes();
+            (status, String::from_utf8(body.to_vec()).unwrap())
+        };
+        let provider = Arc::new(aws_lc_rs::default_provider());
+        let client = rustls::ClientConfig::builder_with_provider(provider.clone())
+            .with_safe_default_protocol_versions()
+            .unwrap()
+            .dangerous()
+            .with_custom_certificate_verifier(Arc::new(AnyCertificate(provider)));
+
+        let cert = CertificateDer::from_pem_file(fixtures.join("client-ed25519.pem")).unwrap();
+        let key = PrivateKeyDer::from_pem_file(fixtures.join("client-ed25519.key")).unwrap();
+        let with_cert = client.clone().with_client_auth_cert(vec![cert], key);
+        let (status, body) = whoami(with_cert.unwrap()).await;
+        assert_eq!(status, http::StatusCode::OK);
+        assert_eq!(body, "billing.clients.internal");
+
+        // Without a certificate the handshake succeeds but the request is
+        // refused, forged header or not.
+        let (status, _) = whoami(client.with_no_client_auth()).await;
+        assert_eq!(status, http::StatusCode::FORBIDDEN);
+
+        stop.send(()).unwrap();
+        tokio::time::timeout(Duration::from_secs(5), server)
+            .await
+            .unwrap()
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_stalled_handshake_is_dropped() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let metrics = Arc::new(GatewayMetrics::default());
+        let (stop, stopped) = tokio::sync::oneshot::channel::<()>();
+        let server = tokio::spawn(serve(
+            listener,
+            Router::new(),
+            termination(Duration::from_millis(200)),
+            metrics.clone(),
+            async move {
+                let _ = stopped.await;
+            },
+        ));
+
+        // Connect but never send a ClientHello.
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        let mut buf = [0u8; 16];
+        let read = tokio::time::timeout(
+            Duration::from_secs(5),
+            tokio::io::AsyncReadExt::read(&mut stream, &mut buf),
+        )
+        .await
+        .expect("server closes the connection");
+        assert!(matches!(read, Ok(0) | Err(_)), "{read:?}");
+        assert_eq!(metrics.handshake_timeouts.load(Ordering::Relaxed), 1);
+        // The connection is released just after its socket closes.
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while metrics.active_connections.load(Ordering::Relaxed) != 0 {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .expect("connection released");
+
+        stop.send(()).unwrap();
+        tokio::time::timeout(Duration::from_secs(5), server)
+            .await
+            .unwrap()
+            .unwrap();
+    }
+
+    /// A TLS connection to `addr` offering only `kx_groups`.
+    async fn connect_tls(
+        addr: SocketAddr,
+        kx_groups: Vec<&'static dyn rustls::crypto::SupportedKxGroup>,
+    ) -> tokio_rustls::client::TlsStream<TcpStream> {
+        let provider = Arc::new(rustls::crypto::CryptoProvider {
+            kx_groups,
+            ..aws_lc_rs::default_provider()
+        });
+        let client = rustls::ClientConfig::builder_with_provider(provider.clone())
+            .with_safe_default_protocol_versions()
+            .unwrap()
+            .dangerous()
+            .with_custom_certificate_verifier(Arc::new(AnyCertificate(provider)))
+            .with_no_client_auth();
+        TlsConnector::from(Arc::new(client))
+            .connect(
+                ServerName::try_from("localhost").unwrap(),
+                TcpStream::connect(addr).await.unwrap(),
+            )
+            .await
+            .unwrap()
+    }
+
+    /// Wait for `done` to hold of `metrics`.
+    async fn until(metrics: &GatewayMetrics, done: impl Fn(&GatewayMetrics) -> bool) {
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while !done(metrics) {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .expect("metrics reached");
+    }
+
+    #[tokio::test]
+    async fn test_connection_metrics_track_handshakes_and_sessions() {
+        use crate::metrics::PrometheusSink;
+        use aws_lc_rs::kx_group::{X25519, X25519MLKEM768};
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let sink = Arc::new(PrometheusSink::new());
+        let metrics = Arc::new(GatewayMetrics::with_sink(sink.clone()));
+        let (stop, stopped) = tokio::sync::oneshot::channel::<()>();
+        let server = tokio::spawn(serve(
+            listener,
+            Router::new(),
+            termination(Duration::from_secs(10)),
+            metrics.clone(),
+            async move {
+                let _ = stopped.await;
+            },
+        ));
+        let load = |counter: &std::sync::atomic::AtomicU64| counter.load(Ordering::Relaxed);
+        let sessions = |m: &GatewayMetrics| {
+            (
+                m.pqc_sessions.load(Ordering::Relaxed),
+                m.classical_sessions.load(Ordering::Relaxed),
+            )
+        };
+        let rendered = |line: &str| sink.render().lines().any(|l| l == line);
+
+        // Sessions are open while their connections are.
+        let pqc = connect_tls(addr, vec![X25519MLKEM768]).await;
+        let classical = connect_tls(addr, vec![X25519]).await;
+        until(&metrics, |m| sessions(m) == (1, 1)).await;
+        assert_eq!(metrics.active_connections.load(Ordering::Relaxed), 2);
+        assert!(rendered(r#"qsgw_tls_sessions{family="pqc"} 1"#));
+        assert!(rendered(r#"qsgw_tls_sessions{family="classical"} 1"#));
+
+        drop(pqc);
+        until(&metrics, |m| sessions(m) == (0, 1)).await;
+        assert!(rendered(r#"qsgw_tls_sessions{family="pqc"} 0"#));
+        drop(classical);
+        until(&metrics, |m| sessions(m) == (0, 0)).await;
+
+        // Failed handshakes are classified and open no session.
+        let mut plaintext = TcpStream::connect(addr).await.unwrap();
+        tokio::io::AsyncWriteExt::write_all(&mut plaintext, b"GET / HTTP/1.1\r\n\r\n")
+            .await
+            .unwrap();
+        until(&metrics, |m| load(&m.handshake_protocol_failures) == 1).await;
+        drop(TcpStream::connect(addr).await.unwrap());
+        until(&metrics, |m| load(&m.handshake_io_failures) == 1).await;
+        drop(plaintext);
+        until(&metrics, |m| load(&m.connections_closed) == 4).await;
+
+        assert_eq!(load(&metrics.connections_accepted), 4);
+        assert_eq!(metrics.active_connections.load(Ordering::Relaxed), 0);
+        assert_eq!(load(&metrics.handshakes_attempted), 4);
+        assert_eq!(load(&metrics.handshakes_succeeded), 2);
+        assert_eq!(sessions(&metrics), (0, 0));
+        for line in [
+            "qsgw_connections_total 4",
+            "qsgw_connections_closed_total 4",
+            "qsgw_connections_active 0",
+            "qsgw_connection_duration_seconds_count 4",
+            r#"qsgw_tls_handshakes_total{result="success"} 2"#,
+            r#"qsgw_tls_handshakes_total{result="protocol"} 1"#,
+            r#"qsgw_tls_handshakes_total{result="io"} 1"#,
+            r#"qsgw_tls_sessions{family="classical"} 0"#,
+        ] {
+            assert!(rendered(line), "{line:?} in\n{}", sink.render());
+        }
+
+        stop.send(()).unwrap();
+        tokio::time::timeout(Duration::from_secs(5), server)
+            .await
+            .unwrap()
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_acceptors_serve_then_close_before_draining() {
+        let release = Arc::new(tokio::sync::Notify::new());
+        let router = {
+            let release = release.clone();
+            Router::new().route("/ok", get(|| async { "ok" })).route(
+                "/slow",
+                get(move || async move {
+                    release.notified().await;
+                    "slow"
+                }),
+            )
+        };
+        let config = crate::listener::ListenerConfig {
+            reuse_port: true,
+            acceptors: 4,
+            pid_file: None,
+        };
+        let listeners = crate::listener::bind("127.0.0.1:0".parse().unwrap(), &config).unwrap();
+        let addr = listeners[0].local_addr().unwrap();
+        let metrics = Arc::new(GatewayMetrics::default());
+        let (stop, stopped) = tokio::sync::oneshot::channel::<()>();
+        let server = tokio::spawn(serve_acceptors(
+            listeners,
+            router,
+            Termination::Plain,
+            metrics.clone(),
+            async move {
+                let _ = stopped.await;
+            },
+        ));
+
+        async fn get_path(addr: SocketAddr, path: &str) -> String {
+            let stream = TcpStream::connect(addr).await.unwrap();
+            let (mut sender, conn) = hyper::client::conn::http1::handshake(TokioIo::new(stream))
+                .await
+                .unwrap();
+            tokio::spawn(conn);
+            let req = Request::get(path).body(Body::empty()).unwrap();
+            let body = sender.send_request(req).await.unwrap().into_body();
+            let body = body.collect().await.unwrap().to_bytes();
+            String::from_utf8(body.to_vec()).unwrap()
+        }
+
+        let requests: Vec<_> = (0..16)
+            .map(|_| tokio::spawn(get_path(addr, "/ok")))
+            .collect();
+        for request in requests {
+            assert_eq!(request.await.unwrap(), "ok");
+        }
+
+        let connections = |n: usize| {
+            let metrics = metrics.clone();
+            tokio::time::timeout(Duration::from_secs(5), async move {
+                while metrics.active_connections.load(Ordering::Relaxed) != n {
+                    tokio::time::sleep(Duration::from_millis(10)).await;
+                }
+            })
+        };
+        connections(0).await.expect("finished connections released");
+        let slow = tokio::spawn(get_path(addr, "/slow"));
+        connections(1).await.expect("slow request in flight");
+        stop.send(()).unwrap();
+
+        // Every listener closes while the slow request is still open.
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while TcpStream::connect(addr).await.is_ok() {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .expect("listeners closed");
+        assert!(!server.is_finished());
+
+        release.notify_one();
+        assert_eq!(slow.await.unwrap(), "slow");
+        tokio::time::timeout(Duration::from_secs(5), server)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(metrics.active_connections.load(Ordering::Relaxed), 0);
+    }
+}
@@ -0,0 +1,292 @@
+//! Remote signing backends for gateway-held keys.
+//!
+//! [`HttpSigner`] talks to an external signing service so production keys
+//! never enter gateway memory. [`ManagedSigner`] wraps any
+//! [`RemoteSigner`] with a per-attempt timeout, retries on
+//! [`CryptoError::SignerUnavailable`], and records latency in
+//! [`GatewayMetrics`].
+
+use async_trait::async_trait;
+use axum::body::{Body, Bytes};
+use http::{header, Method, Request, StatusCode};
+use http_body_util::BodyExt;
+use hyper_util::client::legacy::connect::HttpConnector;
+use hyper_util::client::legacy::Client;
+use hyper_util::rt::TokioExecutor;
+use quantun_crypto::{CryptoError, CryptoResult, RemoteSigner};
+use quantun_types::Algorithm;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tracing::warn;
+
+use crate::metrics::GatewayMetrics;
+
+/// Timeout and retry settings for a [`ManagedSigner`].
+#[derive(Debug, Clone, Copy)]
+pub struct SignerPolicy {
+    /// Limit on each individual attempt.
+    pub timeout: Duration,
+    /// Total attempts, including the first.
+    pub max_attempts: u32,
+    /// Delay before the first retry, doubled for each subsequent one.
+    pub retry_backoff: Duration,
+}
+
+impl Default for SignerPolicy {
+    fn default() -> Self {
+        Self {
+            timeout: Duration::from_secs(2),
+            max_attempts: 3,
+            retry_backoff: Duration::from_millis(50),
+        }
+    }
+}
+
+/// A [`RemoteSigner`] with timeouts, retries, and latency metrics.
+pub struct ManagedSigner {
+    inner: Arc<dyn RemoteSigner>,
+    policy: SignerPolicy,
+    metrics: Arc<GatewayMetrics>,
+}
+
+impl ManagedSigner {
+    pub fn new(
+        inner: Arc<dyn RemoteSigner>,
+        policy: SignerPolicy,
+        metrics: Arc<GatewayMetrics>,
+    ) -> Self {
+        Self {
+            inner,
+            policy,
+            metrics,
+        }
+    }
+
+    async fn attempt(&self, message: &[u8]) -> CryptoResult<Vec<u8>> {
+        tokio::time::timeout(self.policy.timeout, self.inner.sign(message))
+            .await
+            .unwrap_or_else(|_| {
+                Err(CryptoError::SignerUnavailable(format!(
+                    "no response within {:?}",
+                    self.policy.timeout
+                )))
+            })
+    }
+}
+
+#[async_trait]
+impl RemoteSigner for ManagedSigner {
+    fn algorithm(&self) -> Algorithm {
+        self.inner.algorithm()
+    }
+
+    fn public_key(&self) -> &[u8] {
+        self.inner.public_key()
+    }
+
+    async fn sign(&self, message: &[u8]) -> CryptoResult<Vec<u8>> {
+        let started = Instant::now();
+        let mut backoff = self.policy.retry_backoff;
+        let mut attempt = 1;
+        let result = loop {
+            match self.attempt(message).await {
+                Err(CryptoError::SignerUnavailable(reason))
+                    if attempt < self.policy.max_attempts =>
+                {
+                    warn!(attempt, %reason, "remote sign failed, retrying");
+                    tokio::time::sleep(backoff).await;
+                    backoff *= 2;
+                    attempt += 1;
+                }
+                result => break result,
+            }
+        };
+        self.metrics
+            .record_remote_sign(started.elapsed(), result.is_ok());
+        result
+    }
+
+    async fn health_check(&self) -> CryptoResult<()> {
+        tokio::time::timeout(self.policy.timeout, self.inner.health_check())
+            .await
+            .unwrap_or_else(|_| {
+                Err(CryptoError::SignerUnavailable(
+                    "health check timed out".into(),
+                ))
+            })
+    }
+}
+
+/// Client for an HTTP signing service.
+///
+/// The service exposes three endpoints under a base URL:
+///
+/// - `GET /public-key`: the encoded public key.
+/// - `POST /sign`: the message as the body; replies with the signature.
+/// - `GET /health`: any 2xx when the key is usable.
+///
+/// Bodies are raw bytes. Connection errors and 5xx replies map to
+/// [`CryptoError::SignerUnavailable`]; 4xx replies map to
+/// [`CryptoError::Signing`].
+pub struct HttpSigner {
+    client: Client<HttpConnector, Body>,
+    base_url: String,
+    algorithm: Algorithm,
+    public_key: Vec<u8>,
+}
+
+impl HttpSigner {
+    /// Connect to the service at `base_url` and fetch its public key.
+    pub async fn connect(base_url: &str, algorithm: Algorithm) -> CryptoResult<Self> {
+        let mut signer = Self {
+            client: Client::builder(TokioExecutor::new()).build_http(),
+            base_url: base_url.trim_end_matches('/').to_string(),
+            algorithm,
+            public_key: Vec::new(),
+        };
+        signer.public_key = signer
+            .call(Method::GET, "/public-key", Bytes::new())
+            .await?
+            .to_vec();
+        Ok(signer)
+    }
+
+    async fn call(&self, method: Method, path: &str, body: Bytes) -> CryptoResult<Bytes> {
+        let req = Request::builder()
+            .method(method)
+            .uri(format!("{}{path}", self.base_url))
+            .header(header::CONTENT_TYPE, "application/octet-stream")
+            .body(Body::from(body))
+            .map_err(|e| CryptoError::Signing(format!("invalid signer request: {e}")))?;
+
+        let response = self
+            .client
+            .request(req)
+            .await
+            .map_err(|e| CryptoError::SignerUnavailable(e.to_string()))?;
+        let status = response.status();
+        let body = response
+            .into_body()
+            .collect()
+            .await
+            .map_err(|e| CryptoError::SignerUnavailable(e.to_string()))?
+            .to_bytes();
+
+        match status {
+            s if s.is_success() => Ok(body),
+            s if s.is_server_error() || s == StatusCode::TOO_MANY_REQUESTS => Err(
+                CryptoError::SignerUnavailable(format!("signing service returned {s}")),
+            ),
+            s => Err(CryptoError::Signing(format!(
+                "signing service rejected {path}: {s}"
+            ))),
+        }
+    }
+}
+
+#[async_trait]
+impl RemoteSigner for HttpSigner {
+    fn algorithm(&self) -> Algorithm {
+        self.algorithm
+    }
+
+    fn public_key(&self) -> &[u8] {
+        &self.public_key
+    }
+
+    async fn sign(&self, message: &[u8]) -> CryptoResult<Vec<u8>> {
+        self.call(Method::POST, "/sign", Bytes::copy_from_slice(message))
+            .await
+            .map(|b| b.to_vec())
+    }
+
+    async fn health_check(&self) -> CryptoResult<()> {
+        self.call(Method::GET, "/health", Bytes::new())
+            .await
+            .map(drop)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::proxy::testing::{MockResponse, MockUpstream};
+    use quantun_crypto::mldsa::MlDsaKeyPair;
+    use quantun_crypto::signer::MockSigner;
+    use quantun_types::MlDsaVariant;
+    use std::sync::atomic::Ordering;
+
+    fn policy() -> SignerPolicy {
+        SignerPolicy {
+            timeout: Duration::from_millis(100),
+            max_attempts: 3,
+            retry_backoff: Duration::from_millis(1),
+        }
+    }
+
+    fn mock() -> Arc<MockSigner> {
+        Arc::new(MockSigner::new(
+            MlDsaKeyPair::generate(MlDsaVariant::MlDsa44).unwrap(),
+        ))
+    }
+
+    #[tokio::test]
+    async fn retries_transient_failures_and_timeouts() {
+        let mock = mock();
+        let metrics = Arc::new(GatewayMetrics::default());
+        let signer = ManagedSigner::new(mock.clone(), policy(), metrics.clone());
+
+        mock.fail_next(1);
+        mock.hang_next(1);
+        assert!(signer.sign(b"m").await.is_ok());
+        assert_eq!(mock.sign_calls(), 3);
+
+        mock.fail_next(3);
+        assert!(matches!(
+            signer.sign(b"m").await,
+            Err(CryptoError::SignerUnavailable(_))
+        ));
+        assert_eq!(mock.sign_calls(), 6);
+
+        assert_eq!(metrics.remote_signs.load(Ordering::Relaxed), 2);
+        assert_eq!(metrics.remote_sign_failures.load(Ordering::Relaxed), 1);
+        assert!(metrics.remote_sign_latency_us.load(Ordering::Relaxed) >= 100_000);
+    }
+
+    #[tokio::test]
+    async fn http_signer_round_trip() {
+        let keypair = MlDsaKeyPair::generate(MlDsaVariant::MlDsa44).unwrap();
+        let signature = keypair.sign(b"payload").unwrap();
+        let upstream = MockUpstream::start().await.unwrap();
+        upstream.enqueue(MockResponse::default().with_body(keypair.public_key.clone()));
+        upstream.enqueue(MockResponse::new(StatusCode::SERVICE_UNAVAILABLE));
+        upstream.enqueue(MockResponse::default().with_body(signature.signature.clone()));
+        upstream.enqueue(MockResponse::new(StatusCode::BAD_REQUEST));
+
+        let http = HttpSigner::connect(
+            &format!("http://{}/", upstream.addr()),
+            Algorithm::MlDsa(MlDsaVariant::MlDsa44),
+        )
+        .await
+        .unwrap();
+        assert_eq!(http.public_key(), keypair.public_key.as_slice());
+
+        let signer = ManagedSigner::new(
+            Arc::new(http),
+            policy(),
+            Arc::new(GatewayMetrics::default()),
+        );
+        assert_eq!(signer.sign(b"payload").await.unwrap(), signature.signature);
+        // 4xx is final: no retry.
+        assert!(matches!(
+            signer.sign(b"payload").await,
+            Err(CryptoError::Signing(_))
+        ));
+        signer.health_check().await.unwrap();
+
+        let requests = upstream.requests();
+        let paths: Vec<_> = requests.iter().map(|r| r.uri.path()).collect();
+        assert_eq!(paths, ["/public-key", "/sign", "/sign", "/sign", "/health"]);
+        assert_eq!(requests[1].body, "payload");
+    }
+}
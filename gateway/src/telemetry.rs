@@ -0,0 +1,237 @@
+//! Tracing subscriber setup for the gateway.
+//!
+//! [`init_tracing`] installs a global subscriber that writes text or JSON
+//! logs to stdout, optionally to a rolling file, and, with the `otlp`
+//! feature, exports spans to an OpenTelemetry collector. Call
+//! [`shutdown_tracing`] at graceful shutdown to flush buffered output.
+
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::sync::Mutex;
+use thiserror::Error;
+use tracing_appender::non_blocking::WorkerGuard;
+use tracing_appender::rolling::{RollingFileAppender, Rotation};
+use tracing_subscriber::fmt::format::{DefaultFields, Format};
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{fmt, EnvFilter, Layer, Registry};
+
+/// Filter used when none is configured.
+pub const DEFAULT_FILTER: &str = "info,quantun_crypto=debug";
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    #[default]
+    Text,
+    Json,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogRotation {
+    Minutely,
+    Hourly,
+    #[default]
+    Daily,
+    Never,
+}
+
+impl From<LogRotation> for Rotation {
+    fn from(rotation: LogRotation) -> Self {
+        match rotation {
+            LogRotation::Minutely => Rotation::MINUTELY,
+            LogRotation::Hourly => Rotation::HOURLY,
+            LogRotation::Daily => Rotation::DAILY,
+            LogRotation::Never => Rotation::NEVER,
+        }
+    }
+}
+
+/// Rolling log file written alongside stdout.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LogFileConfig {
+    pub directory: PathBuf,
+    /// File name prefix; the rotation date is appended.
+    pub prefix: String,
+    #[serde(default)]
+    pub rotation: LogRotation,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TracingConfig {
+    pub format: LogFormat,
+    /// `RUST_LOG`-style directives, e.g. `info,quantun_crypto=debug`.
+    pub filter: String,
+    pub file: Option<LogFileConfig>,
+    /// OTLP/HTTP collector endpoint. Requires the `otlp` feature.
+    pub otlp_endpoint: Option<String>,
+}
+
+impl Default for TracingConfig {
+    fn default() -> Self {
+        Self {
+            format: LogFormat::Text,
+            filter: DEFAULT_FILTER.to_string(),
+            file: None,
+            otlp_endpoint: None,
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum TelemetryError {
+    #[error("invalid tracing filter: {0}")]
+    InvalidFilter(String),
+    #[error("tracing already initialized: {0}")]
+    AlreadyInitialized(String),
+    #[error("OTLP export requires the `otlp` feature")]
+    OtlpDisabled,
+    #[error("OTLP exporter setup failed: {0}")]
+    Otlp(String),
+}
+
+type BoxedLayer = Box<dyn Layer<Registry> + Send + Sync>;
+
+/// Resources flushed by [`shutdown_tracing`].
+#[derive(Default)]
+struct Handles {
+    file_guard: Option<WorkerGuard>,
+    #[cfg(feature = "otlp")]
+    tracer_provider: Option<opentelemetry_sdk::trace::SdkTracerProvider>,
+}
+
+static HANDLES: Mutex<Option<Handles>> = Mutex::new(None);
+
+/// Install the global tracing subscriber described by `config`.
+///
+/// Fails if a global subscriber is already set.
+pub fn init_tracing(config: &TracingConfig) -> Result<(), TelemetryError> {
+    let filter = EnvFilter::try_new(&config.filter)
+        .map_err(|e| TelemetryError::InvalidFilter(e.to_string()))?;
+    let mut handles = Handles::default();
+    let mut layers: Vec<BoxedLayer> = vec![fmt_layer(config.format, fmt::layer())];
+
+    if let Some(file) = &config.file {
+        let appender =
+            RollingFileAppender::new(file.rotation.into(), &file.directory, &file.prefix);
+        let (writer, guard) = tracing_appender::non_blocking(appender);
+        layers.push(fmt_layer(
+            config.format,
+            fmt::layer().with_ansi(false).with_writer(writer),
+        ));
+        handles.file_guard = Some(guard);
+    }
+
+    if let Some(endpoint) = &config.otlp_endpoint {
+        #[cfg(feature = "otlp")]
+        {
+            let (layer, provider) = otlp_layer(endpoint)?;
+            layers.push(layer);
+            handles.tracer_provider = Some(provider);
+        }
+        #[cfg(not(feature = "otlp"))]
+        {
+            let _ = endpoint;
+            return Err(TelemetryError::OtlpDisabled);
+        }
+    }
+
+    tracing_subscriber::registry()
+        .with(layers)
+        .with(filter)
+        .try_init()
+        .map_err(|e| TelemetryError::AlreadyInitialized(e.to_string()))?;
+    *HANDLES.lock().unwrap() = Some(handles);
+    Ok(())
+}
+
+/// Flush the OTLP exporter and file writer installed by [`init_tracing`].
+///
+/// Logging continues to stdout afterwards, which is where a failed OTLP
+/// flush is reported.
+pub fn shutdown_tracing() {
+    let Some(handles) = HANDLES.lock().unwrap().take() else {
+        return;
+    };
+    #[cfg(feature = "otlp")]
+    if let Some(provider) = handles.tracer_provider {
+        if let Err(e) = provider.shutdown() {
+            tracing::warn!(error = %e, "failed to flush OTLP exporter");
+        }
+    }
+    drop(handles.file_guard);
+}
+
+fn fmt_layer<W>(
+    format: LogFormat,
+    layer: fmt::Layer<Registry, DefaultFields, Format, W>,
+) -> BoxedLayer
+where
+    W: for<'w> fmt::MakeWriter<'w> + Send + Sync + 'static,
+{
+    match format {
+        LogFormat::Text => layer.boxed(),
+        LogFormat::Json => layer.json().boxed(),
+    }
+}
+
+#[cfg(feature = "otlp")]
+fn otlp_layer(
+    endpoint: &str,
+) -> Result<(BoxedLayer, opentelemetry_sdk::trace::SdkTracerProvider), TelemetryError> {
+    use opentelemetry::trace::TracerProvider;
+    use opentelemetry_otlp::{SpanExporter, WithExportConfig};
+    use opentelemetry_sdk::trace::SdkTracerProvider;
+    use opentelemetry_sdk::Resource;
+
+    let exporter = SpanExporter::builder()
+        .with_http()
+        .with_endpoint(endpoint)
+        .build()
+        .map_err(|e| TelemetryError::Otlp(e.to_string()))?;
+    let provider = SdkTracerProvider::builder()
+        .with_batch_exporter(exporter)
+        .with_resource(Resource::builder().with_service_name("qsgw").build())
+        .build();
+    let tracer = provider.tracer(env!("CARGO_PKG_NAME"));
+    let layer = tracing_opentelemetry::layer().with_tracer(tracer).boxed();
+    Ok((layer, provider))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn config_deserializes_with_defaults() {
+        let config: TracingConfig = serde_json::from_str(r#"{"format": "json"}"#).unwrap();
+        assert_eq!(config.format, LogFormat::Json);
+        assert_eq!(config.filter, DEFAULT_FILTER);
+        assert!(config.file.is_none());
+    }
+
+    #[test]
+    fn rejects_invalid_filter() {
+        let config = TracingConfig {
+            filter: "info,=[".into(),
+            ..TracingConfig::default()
+        };
+        assert!(matches!(
+            init_tracing(&config),
+            Err(TelemetryError::InvalidFilter(_))
+        ));
+    }
+
+    #[test]
+    fn init_text_format_and_log() {
+        init_tracing(&TracingConfig::default()).unwrap();
+        tracing::info!(component = "telemetry-test", "tracing initialized");
+        assert!(matches!(
+            init_tracing(&TracingConfig::default()),
+            Err(TelemetryError::AlreadyInitialized(_))
+        ));
+        shutdown_tracing();
+    }
+}
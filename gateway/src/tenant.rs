@@ -0,0 +1,433 @@
+//! Tenant resolution for multi-tenant deployments.
+//!
+//! [`tenant_middleware`] takes each request's tenant from the first of
+//! [`TenantConfig::sources`] that yields one, checks it against
+//! [`TenantConfig::pattern`], and adds a [`TenantId`] to the request's
+//! extensions, for the audit log, and to the response's, for the access
+//! log and [`tenant_metrics_middleware`]. Requests under
+//! [`TenantConfig::required_paths`] without a valid tenant get `400`; on
+//! other paths a malformed tenant is ignored.
+//!
+//! With [`TenantConfig::rate_limit`] set, each tenant draws from its own
+//! token bucket on routes whose profile rate limits.
+
+use axum::{
+    body::Body,
+    extract::State,
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Json,
+};
+use http::{Request, StatusCode};
+use quantun_types::ErrorCode;
+use regex::Regex;
+use std::sync::Arc;
+use std::time::Duration;
+use thiserror::Error;
+use tracing::{info, warn};
+
+use crate::auth::{AuthPolicy, AuthenticatedKey, PathMatcher};
+use crate::metrics::GatewayMetrics;
+use crate::rate_limit::{too_many_requests, Buckets, RateLimit};
+
+/// Header the tenant is read from by default.
+pub const DEFAULT_TENANT_HEADER: &str = "x-tenant-id";
+/// Default [`TenantConfig::pattern`]: 1 to 64 letters, digits, `_` or `-`,
+/// not starting with `_` or `-`.
+pub const DEFAULT_TENANT_PATTERN: &str = "^[A-Za-z0-9][A-Za-z0-9_-]{0,63}$";
+/// Default [`TenantConfig::max_tenant_labels`].
+pub const DEFAULT_MAX_TENANT_LABELS: usize = 100;
+
+/// The tenant a request belongs to.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct TenantId(pub String);
+
+/// Where a request's tenant comes from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TenantSource {
+    /// The value of this request header.
+    Header(String),
+    /// This entry of the authenticating API key's
+    /// [`metadata`](crate::auth::ApiKey::metadata).
+    ApiKeyMetadata(String),
+}
+
+/// Requests each tenant may make: `burst` at once, refilled at
+/// `requests_per_second`.
+pub type TenantRateLimit = RateLimit;
+
+#[derive(Debug, Clone)]
+pub struct TenantConfig {
+    /// Tried in order; the first source with a value wins.
+    pub sources: Vec<TenantSource>,
+    /// Regular expression a tenant must match.
+    pub pattern: String,
+    /// Path prefixes whose requests must carry a valid tenant.
+    pub required_paths: Vec<String>,
+    /// Tenants given their own counters in `/gateway/stats` before the
+    /// rest are counted as `other`.
+    pub max_tenant_labels: usize,
+    pub rate_limit: Option<TenantRateLimit>,
+}
+
+impl Default for TenantConfig {
+    fn default() -> Self {
+        Self {
+            sources: vec![TenantSource::Header(DEFAULT_TENANT_HEADER.into())],
+            pattern: DEFAULT_TENANT_PATTERN.into(),
+            required_paths: Vec::new(),
+            max_tenant_labels: DEFAULT_MAX_TENANT_LABELS,
+            rate_limit: None,
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum TenantConfigError {
+    #[error("invalid tenant pattern: {0}")]
+    InvalidPattern(#[from] regex::Error),
+    #[error("tenant rate limit must allow at least one request")]
+    EmptyRateLimit,
+}
+
+/// Why a request has no usable tenant.
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum TenantError {
+    #[error("tenant required")]
+    Missing,
+    #[error("malformed tenant")]
+    Malformed,
+}
+
+impl IntoResponse for TenantError {
+    fn into_response(self) -> Response {
+        (
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({
+                "error_code": ErrorCode::InvalidArgument.as_str(),
+                "message": self.to_string(),
+            })),
+        )
+            .into_response()
+    }
+}
+
+/// Validated tenant configuration with its pattern compiled once, and the
+/// per-tenant rate limit buckets.
+#[derive(Debug)]
+pub struct TenantPolicy {
+    config: TenantConfig,
+    pattern: Regex,
+    required: PathMatcher,
+    buckets: Buckets<String>,
+}
+
+impl TenantPolicy {
+    pub fn new(config: TenantConfig) -> Result<Self, TenantConfigError> {
+        if config.rate_limit.is_some_and(RateLimit::is_empty) {
+            return Err(TenantConfigError::EmptyRateLimit);
+        }
+        let pattern = Regex::new(&config.pattern)?;
+        let required = PathMatcher::new(&config.required_paths);
+        Ok(Self {
+            config,
+            pattern,
+            required,
+            buckets: Buckets::default(),
+        })
+    }
+
+    pub fn config(&self) -> &TenantConfig {
+        &self.config
+    }
+
+    /// Whether requests for `path` must carry a valid tenant.
+    pub fn is_required(&self, path: &str) -> bool {
+        self.required.matches(path)
+    }
+
+    /// The tenant of `req`, `None` if no source yields one. API key
+    /// metadata is looked up in `auth`.
+    pub fn resolve<B>(
+        &self,
+        req: &Request<B>,
+        auth: Option<&AuthPolicy>,
+    ) -> Result<Option<TenantId>, TenantError> {
+        let Some(value) = self
+            .config
+            .sources
+            .iter()
+            .find_map(|source| source_value(source, req, auth))
+        else {
+            return Ok(None);
+        };
+        if !self.pattern.is_match(&value) {
+            return Err(TenantError::Malformed);
+        }
+        Ok(Some(TenantId(value)))
+    }
+
+    /// Take a request from `tenant`'s bucket, or return how long until one
+    /// is available.
+    fn try_acquire(&self, tenant: &TenantId) -> Result<(), Duration> {
+        match self.config.rate_limit {
+            Some(limit) => self.buckets.try_acquire(&tenant.0, limit),
+            None => Ok(()),
+        }
+    }
+}
+
+fn source_value<B>(
+    source: &TenantSource,
+    req: &Request<B>,
+    auth: Option<&AuthPolicy>,
+) -> Option<String> {
+    match source {
+        TenantSource::Header(name) => req
+            .headers()
+            .get(name.as_str())
+            .and_then(|v| v.to_str().ok())
+            .map(str::to_string),
+        TenantSource::ApiKeyMetadata(field) => {
+            let AuthenticatedKey { id, .. } = req.extensions().get::<AuthenticatedKey>()?;
+            auth?
+                .config()
+                .api_keys
+                .iter()
+                .find(|k| &k.id == id)?
+                .metadata
+                .get(field)
+                .cloned()
+        }
+    }
+}
+
+/// State of [`tenant_middleware`].
+#[derive(Debug, Clone)]
+pub struct TenantResolver {
+    pub policy: Arc<TenantPolicy>,
+    /// Needed for [`TenantSource::ApiKeyMetadata`]; the middleware must
+    /// then run inside [`auth_middleware`](crate::auth::auth_middleware).
+    pub auth: Option<Arc<AuthPolicy>>,
+    /// Apply [`TenantConfig::rate_limit`].
+    pub rate_limit: bool,
+}
+
+pub async fn tenant_middleware(
+    State(resolver): State<TenantResolver>,
+    mut req: Request<Body>,
+    next: Next,
+) -> Response {
+    let policy = &resolver.policy;
+    let required = policy.is_required(req.uri().path());
+    let tenant = match policy.resolve(&req, resolver.auth.as_deref()) {
+        Ok(Some(tenant)) => tenant,
+        Ok(None) if !required => return next.run(req).await,
+        Ok(None) => return reject(TenantError::Missing, req.uri().path()),
+        Err(e) if required => return reject(e, req.uri().path()),
+        Err(_) => return next.run(req).await,
+    };
+
+    if resolver.rate_limit {
+        if let Err(retry_after) = policy.try_acquire(&tenant) {
+            info!(tenant = %tenant.0, "tenant rate limit exceeded");
+            let mut response = too_many_requests(retry_after, "tenant rate limit exceeded");
+            response.extensions_mut().insert(tenant);
+            return response;
+        }
+    }
+
+    req.extensions_mut().insert(tenant.clone());
+    let mut response = next.run(req).await;
+    response.extensions_mut().insert(tenant);
+    response
+}
+
+fn reject(e: TenantError, path: &str) -> Response {
+    warn!(path, reason = %e, "request rejected");
+    e.into_response()
+}
+
+/// Count each response carrying a [`TenantId`] in `metrics`, under at
+/// most [`TenantConfig::max_tenant_labels`] labels. Layer outside the
+/// routes that run [`tenant_middleware`].
+pub async fn tenant_metrics_middleware(
+    State((policy, metrics)): State<(Arc<TenantPolicy>, Arc<GatewayMetrics>)>,
+    req: Request<Body>,
+    next: Next,
+) -> Response {
+    let response = next.run(req).await;
+    if let Some(TenantId(tenant)) = response.extensions().get::<TenantId>() {
+        metrics.record_tenant(
+            tenant,
+            response.status().as_u16(),
+            policy.config().max_tenant_labels,
+        );
+    }
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::auth::{auth_middleware, ApiKey, AuthConfig};
+    use crate::metrics::OTHER_TENANT;
+    use crate::proxy::testing::MockUpstream;
+    use crate::GatewayConfig;
+    use axum::{middleware::from_fn_with_state, routing::get, Extension, Router};
+    use http::header;
+    use std::collections::HashMap;
+    use tower::ServiceExt;
+
+    fn policy(config: TenantConfig) -> Arc<TenantPolicy> {
+        Arc::new(TenantPolicy::new(config).unwrap())
+    }
+
+    async fn send(app: &Router, req: Request<Body>) -> (StatusCode, String) {
+        let response = app.clone().oneshot(req).await.unwrap();
+        let status = response.status();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        (status, String::from_utf8(body.to_vec()).unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_resolves_from_key_metadata_then_header() {
+        let auth = Arc::new(
+            AuthPolicy::new(AuthConfig {
+                require_auth: true,
+                api_keys: vec![
+                    ApiKey {
+                        id: "acme-key".into(),
+                        name: "acme".into(),
+                        scopes: Vec::new(),
+                        signing_key: None,
+                        metadata: HashMap::from([("tenant".into(), "acme".into())]),
+                        secret: None,
+                    },
+                    ApiKey {
+                        id: "shared-key".into(),
+                        name: "shared".into(),
+                        scopes: Vec::new(),
+                        signing_key: None,
+                        metadata: HashMap::new(),
+                        secret: None,
+                    },
+                ],
+                ..AuthConfig::default()
+            })
+            .unwrap(),
+        );
+        let resolver = TenantResolver {
+            policy: policy(TenantConfig {
+                sources: vec![
+                    TenantSource::ApiKeyMetadata("tenant".into()),
+                    TenantSource::Header(DEFAULT_TENANT_HEADER.into()),
+                ],
+                required_paths: vec!["/api".into()],
+                ..TenantConfig::default()
+            }),
+            auth: Some(auth.clone()),
+            rate_limit: false,
+        };
+        let app = Router::new()
+            .route(
+                "/api",
+                get(|Extension(TenantId(tenant)): Extension<TenantId>| async move { tenant }),
+            )
+            .layer(from_fn_with_state(resolver, tenant_middleware))
+            .layer(from_fn_with_state(auth, auth_middleware));
+        let request = |key: &str, tenant: Option<&str>| {
+            let mut req = Request::get("/api").header("x-api-key", key);
+            if let Some(tenant) = tenant {
+                req = req.header(DEFAULT_TENANT_HEADER, tenant);
+            }
+            req.body(Body::empty()).unwrap()
+        };
+
+        let ok = |tenant: &str| (StatusCode::OK, tenant.to_string());
+        assert_eq!(send(&app, request("acme-key", None)).await, ok("acme"));
+        assert_eq!(
+            send(&app, request("acme-key", Some("beta"))).await,
+            ok("acme")
+        );
+        assert_eq!(
+            send(&app, request("shared-key", Some("beta"))).await,
+            ok("beta")
+        );
+
+        for tenant in [None, Some("../etc"), Some("")] {
+            let (status, body) = send(&app, request("shared-key", tenant)).await;
+            assert_eq!(status, StatusCode::BAD_REQUEST);
+            let body: serde_json::Value = serde_json::from_str(&body).unwrap();
+            assert_eq!(body["error_code"], "INVALID_ARGUMENT");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_rate_limits_are_per_tenant() {
+        let resolver = TenantResolver {
+            policy: policy(TenantConfig {
+                rate_limit: Some(TenantRateLimit {
+                    requests_per_second: 0.001,
+                    burst: 2,
+                }),
+                ..TenantConfig::default()
+            }),
+            auth: None,
+            rate_limit: true,
+        };
+        let app = Router::new()
+            .route("/api", get(|| async { "ok" }))
+            .layer(from_fn_with_state(resolver, tenant_middleware));
+        let request = |tenant: &str| {
+            Request::get("/api")
+                .header(DEFAULT_TENANT_HEADER, tenant)
+                .body(Body::empty())
+                .unwrap()
+        };
+
+        for _ in 0..2 {
+            assert_eq!(send(&app, request("noisy")).await.0, StatusCode::OK);
+        }
+        let response = app.clone().oneshot(request("noisy")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert!(response.headers().contains_key(header::RETRY_AFTER));
+
+        assert_eq!(send(&app, request("quiet")).await.0, StatusCode::OK);
+        assert_eq!(send(&app, request("quiet")).await.0, StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_stats_cap_tenant_labels() {
+        let mock = MockUpstream::start().await.unwrap();
+        let config = GatewayConfig {
+            routes: vec![mock.route("/api")],
+            tenant: Some(policy(TenantConfig {
+                max_tenant_labels: 2,
+                ..TenantConfig::default()
+            })),
+            ..GatewayConfig::default()
+        };
+        let metrics = Arc::new(GatewayMetrics::default());
+        let app = crate::build_router_with_metrics(&config, metrics.clone());
+
+        for tenant in ["a", "b", "c", "a", "d"] {
+            let req = Request::get("/api/items")
+                .header(DEFAULT_TENANT_HEADER, tenant)
+                .body(Body::empty())
+                .unwrap();
+            assert_eq!(send(&app, req).await.0, StatusCode::OK);
+        }
+        let requests: Vec<_> = metrics
+            .tenant_stats()
+            .into_iter()
+            .map(|s| (s.tenant, s.requests))
+            .collect();
+        assert_eq!(
+            requests,
+            [("a".into(), 2), ("b".into(), 1), (OTHER_TENANT.into(), 2)]
+        );
+    }
+}
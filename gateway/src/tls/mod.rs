@@ -1,8 +1,24 @@
-use quantun_tls::config::{TlsConfig, TlsVersion};
-use quantun_types::algorithm::{MlKemVariant, MlDsaVariant};
+use quantun_crypto::mldsa::{MlDsaSignature, MlDsaVerifier};
+use quantun_tls::config::{TlsConfig, TlsConfigError, TlsVersion};
+use quantun_types::algorithm::{MlKemVariant, MlDsaVariant, SlhDsaVariant};
+use quantun_types::{Algorithm, ErrorCode, KeyType};
+use axum::response::{IntoResponse, Response};
+use http::StatusCode;
+use rustls::crypto::SupportedKxGroup;
+use rustls::pki_types::pem::PemObject;
+use rustls::pki_types::{CertificateDer, PrivateKeyDer};
+use rustls::NamedGroup;
 use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
 use thiserror::Error;
-use tracing::info;
+use tracing::{info, warn};
+use x509_cert::der::asn1::{PrintableStringRef, Utf8StringRef};
+use x509_cert::der::{Decode, Encode};
+use x509_cert::ext::pkix::BasicConstraints;
+use x509_cert::spki::ObjectIdentifier;
+use x509_cert::Certificate;
 
 use crate::TlsPolicy;
 
@@ -14,6 +30,50 @@ pub enum TlsError {
     PolicyViolation(String),
     #[error("configuration error: {0}")]
     ConfigError(String),
+    #[error("client certificate rejected: {0}")]
+    ClientCertRejected(String),
+}
+
+impl TlsError {
+    pub fn to_status_code(&self) -> StatusCode {
+        match self {
+            TlsError::NoPqcCipherSuites => StatusCode::SERVICE_UNAVAILABLE,
+            TlsError::PolicyViolation(_) | TlsError::ClientCertRejected(_) => StatusCode::FORBIDDEN,
+            TlsError::ConfigError(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    pub fn error_code(&self) -> ErrorCode {
+        match self {
+            TlsError::NoPqcCipherSuites => ErrorCode::TlsHandshakeFailed,
+            TlsError::PolicyViolation(_) => ErrorCode::PermissionDenied,
+            TlsError::ConfigError(_) => ErrorCode::Internal,
+            TlsError::ClientCertRejected(_) => ErrorCode::CertificateInvalid,
+        }
+    }
+
+    /// A JSON `{"error_code", "message"}` response with
+    /// [`TlsError::to_status_code`].
+    pub fn into_http_response(self) -> Response {
+        let body = serde_json::json!({
+            "error_code": self.error_code().as_str(),
+            "message": self.to_string(),
+        });
+        (self.to_status_code(), axum::Json(body)).into_response()
+    }
+}
+
+impl IntoResponse for TlsError {
+    fn into_response(self) -> Response {
+        self.into_http_response()
+    }
+}
+
+/// A [`TlsConfigError`] reaches HTTP callers as [`TlsError::ConfigError`].
+impl From<TlsConfigError> for TlsError {
+    fn from(e: TlsConfigError) -> Self {
+        TlsError::ConfigError(e.to_string())
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -62,19 +122,549 @@ pub fn build_tls_config(policy: TlsPolicy) -> Result<TlsConfig, TlsError> {
         }
     }
 
-    config.validate().map_err(|e| TlsError::ConfigError(e.to_string()))?;
+    for alg in config
+        .preferred_algorithms
+        .iter()
+        .filter(|a| !a.is_standardized())
+    {
+        warn!(algorithm = %alg, "preferred TLS algorithm is not a finalized standard");
+    }
+    warn_deprecated(
+        &config.preferred_algorithms,
+        "preferred TLS algorithm is deprecated",
+    );
+
+    config.validate()?;
     Ok(config)
 }
 
+/// Log `message` at warn level for each deprecated algorithm in `algs`.
+fn warn_deprecated<'a>(algs: impl IntoIterator<Item = &'a Algorithm>, message: &str) {
+    for alg in algs {
+        if let Some(reason) = alg.deprecation_status() {
+            warn!(algorithm = %alg, reason, "{message}");
+        }
+    }
+}
+
 pub fn classify_cipher_suite(cipher_suite: &str) -> bool {
     let pqc_indicators = ["ML-KEM", "ML-DSA", "SLH-DSA", "KYBER", "DILITHIUM"];
     pqc_indicators.iter().any(|p| cipher_suite.contains(p))
 }
 
+/// Negotiate a handshake with a client offering `client_groups` for key
+/// exchange and `client_sigs` for signatures, without a network.
+///
+/// Each side is chosen with [`TlsConfig::select_algorithm`]. When nothing
+/// PQC is in common the handshake falls back to classical, unless the
+/// config is PQC-only (`hybrid_mode` off), which fails with
+/// [`TlsError::NoPqcCipherSuites`].
+pub fn simulate_handshake(
+    config: &TlsConfig,
+    client_groups: &[Algorithm],
+    client_sigs: &[Algorithm],
+) -> Result<HandshakeInfo, TlsError> {
+    let select = |offered: &[Algorithm], types: [KeyType; 2]| {
+        let offered: Vec<_> = offered
+            .iter()
+            .copied()
+            .filter(|alg| types.contains(&alg.key_type()))
+            .collect();
+        config.select_algorithm(&offered)
+    };
+    let kem = select(client_groups, [KeyType::Kem, KeyType::HybridKem]);
+    let sig = select(client_sigs, [KeyType::Signature, KeyType::HybridSignature]);
+    warn_deprecated(
+        kem.iter().chain(&sig),
+        "negotiated TLS algorithm is deprecated",
+    );
+    if !config.hybrid_mode && (kem.is_none() || sig.is_none()) {
+        return Err(TlsError::NoPqcCipherSuites);
+    }
+
+    let cipher_suite = match kem {
+        Some(kem) => format!("TLS_{kem}_AES_256_GCM_SHA384"),
+        None => "TLS_AES_256_GCM_SHA384".to_string(),
+    };
+    Ok(HandshakeInfo {
+        is_pqc: classify_cipher_suite(&cipher_suite),
+        cipher_suite,
+        tls_version: "TLSv1.3".into(),
+        kem_algorithm: kem.map(|alg| alg.to_string()),
+        sig_algorithm: sig.map(|alg| alg.to_string()),
+        handshake_duration_ms: 0,
+    })
+}
+
+/// A rustls server config for terminating TLS in-process under `policy`,
+/// with the PEM certificate chain at `cert_path` and private key at
+/// `key_path`. With `client_certs`, clients are asked for a certificate
+/// issued by its CAs.
+///
+/// PQC-only offers ML-KEM key exchange alone; PQC-preferred and hybrid
+/// lead with X25519MLKEM768 and keep classical fallbacks; classical-allowed
+/// uses the rustls defaults and is the only policy permitting TLS 1.2.
+pub fn server_config(
+    policy: TlsPolicy,
+    cert_path: &Path,
+    key_path: &Path,
+    client_certs: Option<&ClientCertVerifier>,
+) -> Result<rustls::ServerConfig, TlsError> {
+    let certs = CertificateDer::pem_file_iter(cert_path)
+        .and_then(|certs| certs.collect::<Result<Vec<_>, _>>())
+        .map_err(|e| TlsError::ConfigError(format!("reading {}: {e}", cert_path.display())))?;
+    let key = PrivateKeyDer::from_pem_file(key_path)
+        .map_err(|e| TlsError::ConfigError(format!("reading {}: {e}", key_path.display())))?;
+
+    let provider = Arc::new(rustls::crypto::CryptoProvider {
+        kx_groups: kx_groups(policy),
+        ..rustls::crypto::aws_lc_rs::default_provider()
+    });
+    let versions: &[&rustls::SupportedProtocolVersion] = match policy {
+        TlsPolicy::ClassicalAllowed => rustls::ALL_VERSIONS,
+        _ => &[&rustls::version::TLS13],
+    };
+    let builder = rustls::ServerConfig::builder_with_provider(provider.clone())
+        .with_protocol_versions(versions)
+        .map_err(|e| TlsError::ConfigError(e.to_string()))?;
+    let builder = match client_certs {
+        Some(verifier) => builder.with_client_cert_verifier(verifier.rustls_verifier(provider)?),
+        None => builder.with_no_client_auth(),
+    };
+    let mut config = builder
+        .with_single_cert(certs, key)
+        .map_err(|e| TlsError::ConfigError(e.to_string()))?;
+    config.alpn_protocols = vec![b"h2".to_vec(), b"http/1.1".to_vec()];
+    info!(?policy, "TLS termination configured");
+    Ok(config)
+}
+
+/// Key exchange groups offered under `policy`, most preferred first.
+fn kx_groups(policy: TlsPolicy) -> Vec<&'static dyn SupportedKxGroup> {
+    use rustls::crypto::aws_lc_rs::kx_group::{
+        MLKEM1024, MLKEM768, SECP256R1, X25519, X25519MLKEM768,
+    };
+    match policy {
+        TlsPolicy::PqcOnly => vec![MLKEM768, MLKEM1024],
+        TlsPolicy::PqcPreferred => vec![X25519MLKEM768, MLKEM768, MLKEM1024, X25519, SECP256R1],
+        TlsPolicy::Hybrid => vec![X25519MLKEM768, X25519, SECP256R1],
+        TlsPolicy::ClassicalAllowed => rustls::crypto::aws_lc_rs::DEFAULT_KX_GROUPS.to_vec(),
+    }
+}
+
+/// The [`HandshakeInfo`] for a completed rustls server handshake that took
+/// `duration`.
+///
+/// The cipher suite is named as in [`simulate_handshake`], with the
+/// PQC or hybrid key exchange group spliced in, so
+/// [`classify_cipher_suite`] recognizes it.
+pub fn handshake_info(conn: &rustls::ServerConnection, duration: Duration) -> HandshakeInfo {
+    let kem = conn
+        .negotiated_key_exchange_group()
+        .and_then(|group| match group.name() {
+            NamedGroup::MLKEM512 => Some("ML-KEM-512"),
+            NamedGroup::MLKEM768 => Some("ML-KEM-768"),
+            NamedGroup::MLKEM1024 => Some("ML-KEM-1024"),
+            NamedGroup::X25519MLKEM768 => Some("X25519-ML-KEM-768"),
+            NamedGroup::secp256r1MLKEM768 => Some("SECP256R1-ML-KEM-768"),
+            _ => None,
+        });
+    let suite = conn
+        .negotiated_cipher_suite()
+        .and_then(|suite| suite.suite().as_str())
+        .unwrap_or("unknown");
+    let cipher_suite = match kem {
+        Some(kem) => format!(
+            "TLS_{kem}_{}",
+            suite
+                .trim_start_matches("TLS13_")
+                .trim_start_matches("TLS_")
+        ),
+        None => suite.to_string(),
+    };
+    let tls_version = match conn.protocol_version() {
+        Some(rustls::ProtocolVersion::TLSv1_3) => "TLSv1.3",
+        Some(rustls::ProtocolVersion::TLSv1_2) => "TLSv1.2",
+        _ => "unknown",
+    };
+    HandshakeInfo {
+        is_pqc: classify_cipher_suite(&cipher_suite),
+        cipher_suite,
+        tls_version: tls_version.into(),
+        kem_algorithm: kem.map(str::to_string),
+        sig_algorithm: None,
+        handshake_duration_ms: duration.as_millis() as u64,
+    }
+}
+
+/// Client certificate validation for mutual TLS.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MtlsConfig {
+    /// PEM bundle (or a single DER certificate) of CAs trusted to issue
+    /// client certificates.
+    pub ca_path: PathBuf,
+    /// Reject client certificates whose key is not a PQC algorithm.
+    #[serde(default)]
+    pub require_pqc_client_cert: bool,
+    /// Accepted subject common names; `*` matches any run of characters.
+    /// Empty accepts every name.
+    #[serde(default)]
+    pub allowed_cn_patterns: Vec<String>,
+}
+
+/// The authenticated client behind a validated certificate.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ClientIdentity {
+    pub common_name: String,
+    /// Hex SHA-256 of the certificate DER.
+    pub fingerprint: String,
+    /// Algorithm of the certificate's key; `None` for classical keys.
+    pub algorithm: Option<Algorithm>,
+}
+
+const OID_COMMON_NAME: ObjectIdentifier = ObjectIdentifier::new_unwrap("2.5.4.3");
+const OID_ED25519: ObjectIdentifier = ObjectIdentifier::new_unwrap("1.3.101.112");
+
+/// PQC key algorithms by their X.509 OID (NIST CSOR).
+const PQC_OIDS: &[(&str, Algorithm)] = &[
+    (
+        "2.16.840.1.101.3.4.3.17",
+        Algorithm::MlDsa(MlDsaVariant::MlDsa44),
+    ),
+    (
+        "2.16.840.1.101.3.4.3.18",
+        Algorithm::MlDsa(MlDsaVariant::MlDsa65),
+    ),
+    (
+        "2.16.840.1.101.3.4.3.19",
+        Algorithm::MlDsa(MlDsaVariant::MlDsa87),
+    ),
+    (
+        "2.16.840.1.101.3.4.3.20",
+        Algorithm::SlhDsa(SlhDsaVariant::Sha2_128s),
+    ),
+    (
+        "2.16.840.1.101.3.4.3.21",
+        Algorithm::SlhDsa(SlhDsaVariant::Sha2_128f),
+    ),
+    (
+        "2.16.840.1.101.3.4.3.22",
+        Algorithm::SlhDsa(SlhDsaVariant::Sha2_192s),
+    ),
+    (
+        "2.16.840.1.101.3.4.3.23",
+        Algorithm::SlhDsa(SlhDsaVariant::Sha2_192f),
+    ),
+    (
+        "2.16.840.1.101.3.4.3.24",
+        Algorithm::SlhDsa(SlhDsaVariant::Sha2_256s),
+    ),
+    (
+        "2.16.840.1.101.3.4.3.25",
+        Algorithm::SlhDsa(SlhDsaVariant::Sha2_256f),
+    ),
+    (
+        "2.16.840.1.101.3.4.3.26",
+        Algorithm::SlhDsa(SlhDsaVariant::Shake_128s),
+    ),
+    (
+        "2.16.840.1.101.3.4.3.27",
+        Algorithm::SlhDsa(SlhDsaVariant::Shake_128f),
+    ),
+    (
+        "2.16.840.1.101.3.4.3.28",
+        Algorithm::SlhDsa(SlhDsaVariant::Shake_192s),
+    ),
+    (
+        "2.16.840.1.101.3.4.3.29",
+        Algorithm::SlhDsa(SlhDsaVariant::Shake_192f),
+    ),
+    (
+        "2.16.840.1.101.3.4.3.30",
+        Algorithm::SlhDsa(SlhDsaVariant::Shake_256s),
+    ),
+    (
+        "2.16.840.1.101.3.4.3.31",
+        Algorithm::SlhDsa(SlhDsaVariant::Shake_256f),
+    ),
+    (
+        "2.16.840.1.101.3.4.4.1",
+        Algorithm::MlKem(MlKemVariant::MlKem512),
+    ),
+    (
+        "2.16.840.1.101.3.4.4.2",
+        Algorithm::MlKem(MlKemVariant::MlKem768),
+    ),
+    (
+        "2.16.840.1.101.3.4.4.3",
+        Algorithm::MlKem(MlKemVariant::MlKem1024),
+    ),
+];
+
+/// An [`MtlsConfig`] with its CAs loaded and checked, once, when the
+/// gateway is configured.
+#[derive(Debug)]
+pub struct ClientCertVerifier {
+    config: MtlsConfig,
+    cas: Vec<Certificate>,
+}
+
+impl ClientCertVerifier {
+    /// Load the CAs in `config.ca_path`. Each must be marked as a CA by its
+    /// basicConstraints extension.
+    pub fn load(config: MtlsConfig) -> Result<Self, TlsError> {
+        let cas = load_certificates(&config.ca_path)?;
+        if cas.is_empty() {
+            return Err(TlsError::ConfigError(format!(
+                "{}: no CA certificates",
+                config.ca_path.display()
+            )));
+        }
+        for ca in &cas {
+            let is_ca = ca
+                .tbs_certificate
+                .get::<BasicConstraints>()
+                .map_err(|e| TlsError::ConfigError(format!("{}: {e}", config.ca_path.display())))?
+                .is_some_and(|(_, constraints)| constraints.ca);
+            if !is_ca {
+                return Err(TlsError::ConfigError(format!(
+                    "{}: {} is not a CA certificate",
+                    config.ca_path.display(),
+                    ca.tbs_certificate.subject
+                )));
+            }
+        }
+        Ok(Self { config, cas })
+    }
+
+    pub fn config(&self) -> &MtlsConfig {
+        &self.config
+    }
+
+    /// A rustls verifier that asks TLS clients for a certificate issued by
+    /// one of the CAs, for in-process termination. Clients without one
+    /// still connect, so `/health` stays reachable; their other requests
+    /// are rejected by [`pqc_enforcement_middleware`].
+    ///
+    /// rustls checks the chain with the signature algorithms of its crypto
+    /// provider, which do not include ML-DSA; PQC client certificates need
+    /// an external terminator.
+    ///
+    /// [`pqc_enforcement_middleware`]: crate::middleware::pqc_enforcement_middleware
+    pub fn rustls_verifier(
+        &self,
+        provider: Arc<rustls::crypto::CryptoProvider>,
+    ) -> Result<Arc<dyn rustls::server::danger::ClientCertVerifier>, TlsError> {
+        let mut roots = rustls::RootCertStore::empty();
+        for ca in &self.cas {
+            let der = ca
+                .to_der()
+                .map_err(|e| TlsError::ConfigError(format!("encoding CA: {e}")))?;
+            roots
+                .add(CertificateDer::from(der))
+                .map_err(|e| TlsError::ConfigError(format!("adding CA: {e}")))?;
+        }
+        rustls::server::WebPkiClientVerifier::builder_with_provider(Arc::new(roots), provider)
+            .allow_unauthenticated()
+            .build()
+            .map_err(|e| TlsError::ConfigError(e.to_string()))
+    }
+
+    /// Validate a DER client certificate.
+    ///
+    /// The certificate must be within its validity period, signed by one of
+    /// the CAs (Ed25519 and ML-DSA signatures are supported), and have a
+    /// common name matching `allowed_cn_patterns`.
+    pub fn validate(&self, cert_der: &[u8]) -> Result<ClientIdentity, TlsError> {
+        let cert = Certificate::from_der(cert_der)
+            .map_err(|e| TlsError::ClientCertRejected(format!("malformed certificate: {e}")))?;
+        let tbs = &cert.tbs_certificate;
+
+        let now = SystemTime::now();
+        if now < tbs.validity.not_before.to_system_time() {
+            return Err(TlsError::ClientCertRejected(
+                "certificate is not yet valid".into(),
+            ));
+        }
+        if now > tbs.validity.not_after.to_system_time() {
+            return Err(TlsError::ClientCertRejected(
+                "certificate has expired".into(),
+            ));
+        }
+
+        let tbs_der = tbs
+            .to_der()
+            .map_err(|e| TlsError::ClientCertRejected(format!("malformed certificate: {e}")))?;
+        let chained = self
+            .cas
+            .iter()
+            .filter(|ca| ca.tbs_certificate.subject == tbs.issuer)
+            .any(|ca| verify_signature(ca, &cert, &tbs_der));
+        if !chained {
+            return Err(TlsError::ClientCertRejected(
+                "certificate is not signed by a trusted CA".into(),
+            ));
+        }
+
+        let key_oid = tbs.subject_public_key_info.algorithm.oid;
+        let algorithm = pqc_algorithm(&key_oid);
+        if self.config.require_pqc_client_cert && algorithm.is_none() {
+            return Err(TlsError::ClientCertRejected(format!(
+                "certificate key algorithm {key_oid} is not post-quantum"
+            )));
+        }
+
+        let common_name = common_name(&cert)
+            .ok_or_else(|| TlsError::ClientCertRejected("certificate has no common name".into()))?;
+        if !self.config.allowed_cn_patterns.is_empty()
+            && !self
+                .config
+                .allowed_cn_patterns
+                .iter()
+                .any(|p| cn_matches(p, &common_name))
+        {
+            return Err(TlsError::ClientCertRejected(format!(
+                "common name {common_name} is not allowed"
+            )));
+        }
+
+        Ok(ClientIdentity {
+            common_name,
+            fingerprint: crate::jwks::fingerprint(cert_der),
+            algorithm,
+        })
+    }
+}
+
+/// The PQC algorithm identified by an X.509 key or signature OID.
+pub(crate) fn pqc_algorithm(oid: &ObjectIdentifier) -> Option<Algorithm> {
+    let oid = oid.to_string();
+    PQC_OIDS
+        .iter()
+        .find(|(known, _)| *known == oid)
+        .map(|(_, alg)| *alg)
+}
+
+/// The X.509 OID of a PQC key algorithm; `None` for hybrids.
+pub(crate) fn pqc_oid(alg: Algorithm) -> Option<ObjectIdentifier> {
+    PQC_OIDS
+        .iter()
+        .find(|(_, known)| *known == alg)
+        .map(|(oid, _)| ObjectIdentifier::new_unwrap(oid))
+}
+
+/// The certificates in a PEM bundle or a single DER certificate.
+pub(crate) fn load_certificates(path: &Path) -> Result<Vec<Certificate>, TlsError> {
+    let bytes = std::fs::read(path)
+        .map_err(|e| TlsError::ConfigError(format!("reading {}: {e}", path.display())))?;
+    Certificate::load_pem_chain(&bytes)
+        .or_else(|_| Certificate::from_der(&bytes).map(|cert| vec![cert]))
+        .map_err(|e| TlsError::ConfigError(format!("parsing {}: {e}", path.display())))
+}
+
+/// Whether `ca`'s key produced the signature on `cert`.
+fn verify_signature(ca: &Certificate, cert: &Certificate, tbs_der: &[u8]) -> bool {
+    let ca_key = &ca.tbs_certificate.subject_public_key_info;
+    let (Some(key), Some(signature)) = (
+        ca_key.subject_public_key.as_bytes(),
+        cert.signature.as_bytes(),
+    ) else {
+        return false;
+    };
+    let sig_oid = cert.signature_algorithm.oid;
+    if ca_key.algorithm.oid != sig_oid {
+        return false;
+    }
+
+    if sig_oid == OID_ED25519 {
+        let (Ok(key), Ok(signature)) = (
+            <[u8; 32]>::try_from(key),
+            ed25519_dalek::Signature::from_slice(signature),
+        ) else {
+            return false;
+        };
+        return ed25519_dalek::VerifyingKey::from_bytes(&key)
+            .is_ok_and(|key| key.verify_strict(tbs_der, &signature).is_ok());
+    }
+
+    match pqc_algorithm(&sig_oid) {
+        Some(Algorithm::MlDsa(variant)) => {
+            let verifier = MlDsaVerifier {
+                variant,
+                public_key: key.to_vec(),
+            };
+            let signature = MlDsaSignature {
+                signature: signature.to_vec(),
+                variant,
+            };
+            verifier.verify(tbs_der, &signature).unwrap_or(false)
+        }
+        _ => false,
+    }
+}
+
+pub(crate) fn common_name(cert: &Certificate) -> Option<String> {
+    cert.tbs_certificate
+        .subject
+        .0
+        .iter()
+        .flat_map(|rdn| rdn.0.iter())
+        .find(|atv| atv.oid == OID_COMMON_NAME)
+        .and_then(|atv| {
+            Utf8StringRef::try_from(&atv.value)
+                .map(|s| s.as_str().to_string())
+                .or_else(|_| {
+                    PrintableStringRef::try_from(&atv.value).map(|s| s.as_str().to_string())
+                })
+                .ok()
+        })
+}
+
+/// Match `name` against `pattern`, where `*` matches any run of characters.
+fn cn_matches(pattern: &str, name: &str) -> bool {
+    match pattern.split_once('*') {
+        None => pattern == name,
+        Some((prefix, rest)) => {
+            let Some(name) = name.strip_prefix(prefix) else {
+                return false;
+            };
+            (0..=name.len())
+                .filter(|&i| name.is_char_boundary(i))
+                .any(|i| cn_matches(rest, &name[i..]))
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[tokio::test]
+    async fn test_tls_error_into_http_response() {
+        let response = TlsError::PolicyViolation("classical cipher suite".into()).into_response();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["error_code"], "PERMISSION_DENIED");
+        assert_eq!(
+            body["message"],
+            "TLS policy violation: classical cipher suite"
+        );
+
+        assert_eq!(
+            TlsError::NoPqcCipherSuites.to_status_code(),
+            StatusCode::SERVICE_UNAVAILABLE
+        );
+        let config_error = TlsError::from(TlsConfigError::NoAlgorithms);
+        assert_eq!(
+            config_error.to_status_code(),
+            StatusCode::INTERNAL_SERVER_ERROR
+        );
+        assert_eq!(config_error.error_code(), ErrorCode::Internal);
+    }
+
     #[test]
     fn test_build_tls_config_pqc_preferred() {
         let config = build_tls_config(TlsPolicy::PqcPreferred).unwrap();
@@ -88,4 +678,156 @@ mod tests {
         assert!(classify_cipher_suite("TLS_ML-KEM-768_AES_256_GCM_SHA384"));
         assert!(!classify_cipher_suite("TLS_AES_256_GCM_SHA384"));
     }
+
+    #[test]
+    fn test_simulate_pqc_handshake() {
+        let config = build_tls_config(TlsPolicy::PqcPreferred).unwrap();
+        let info = simulate_handshake(
+            &config,
+            &[
+                Algorithm::MlKem(MlKemVariant::MlKem768),
+                Algorithm::MlKem(MlKemVariant::MlKem1024),
+            ],
+            &[Algorithm::MlDsa(MlDsaVariant::MlDsa65)],
+        )
+        .unwrap();
+        assert!(info.is_pqc);
+        assert_eq!(info.cipher_suite, "TLS_ML-KEM-1024_AES_256_GCM_SHA384");
+        assert_eq!(info.kem_algorithm.as_deref(), Some("ML-KEM-1024"));
+        assert_eq!(info.sig_algorithm.as_deref(), Some("ML-DSA-65"));
+        assert_eq!(info.tls_version, "TLSv1.3");
+
+        // Hybrid configs fall back to classical.
+        let info = simulate_handshake(&config, &[], &[]).unwrap();
+        assert!(!info.is_pqc);
+        assert_eq!(info.kem_algorithm, None);
+    }
+
+    #[test]
+    fn test_simulate_pqc_only_handshake_with_classical_client() {
+        let config = build_tls_config(TlsPolicy::PqcOnly).unwrap();
+        assert!(matches!(
+            simulate_handshake(&config, &[], &[]),
+            Err(TlsError::NoPqcCipherSuites)
+        ));
+        // Offering a PQC group is not enough without a PQC signature.
+        assert!(matches!(
+            simulate_handshake(&config, &[Algorithm::MlKem(MlKemVariant::MlKem768)], &[]),
+            Err(TlsError::NoPqcCipherSuites)
+        ));
+        let info = simulate_handshake(
+            &config,
+            &[Algorithm::MlKem(MlKemVariant::MlKem768)],
+            &[Algorithm::MlDsa(MlDsaVariant::MlDsa65)],
+        )
+        .unwrap();
+        assert!(info.is_pqc);
+    }
+
+    fn fixture(name: &str) -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("testdata/mtls")
+            .join(name)
+    }
+
+    fn cert_der(name: &str) -> Vec<u8> {
+        use x509_cert::der::DecodePem;
+        let pem = std::fs::read(fixture(name)).unwrap();
+        Certificate::from_pem(pem).unwrap().to_der().unwrap()
+    }
+
+    fn mtls(ca: &str) -> MtlsConfig {
+        MtlsConfig {
+            ca_path: fixture(ca),
+            require_pqc_client_cert: false,
+            allowed_cn_patterns: vec![],
+        }
+    }
+
+    fn validate(cert_der: &[u8], config: MtlsConfig) -> Result<ClientIdentity, TlsError> {
+        ClientCertVerifier::load(config).unwrap().validate(cert_der)
+    }
+
+    fn rejected(result: Result<ClientIdentity, TlsError>) -> String {
+        match result {
+            Err(TlsError::ClientCertRejected(reason)) => reason,
+            other => panic!("expected rejection, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_validate_client_cert() {
+        let der = cert_der("client-ed25519.pem");
+        let identity = validate(&der, mtls("ca-ed25519.pem")).unwrap();
+        assert_eq!(identity.common_name, "billing.clients.internal");
+        assert_eq!(identity.fingerprint, crate::jwks::fingerprint(&der));
+        assert_eq!(identity.algorithm, None);
+
+        let der = cert_der("client-mldsa65.pem");
+        let config = MtlsConfig {
+            require_pqc_client_cert: true,
+            ..mtls("ca-mldsa65.pem")
+        };
+        let identity = validate(&der, config).unwrap();
+        assert_eq!(identity.common_name, "ledger.clients.internal");
+        assert_eq!(
+            identity.algorithm,
+            Some(Algorithm::MlDsa(MlDsaVariant::MlDsa65))
+        );
+    }
+
+    #[test]
+    fn test_validate_client_cert_rejections() {
+        let ed25519 = cert_der("client-ed25519.pem");
+
+        let config = MtlsConfig {
+            require_pqc_client_cert: true,
+            ..mtls("ca-ed25519.pem")
+        };
+        assert!(rejected(validate(&ed25519, config)).contains("not post-quantum"));
+
+        let reason = rejected(validate(&ed25519, mtls("ca-mldsa65.pem")));
+        assert!(reason.contains("trusted CA"));
+
+        let expired = cert_der("client-expired.pem");
+        let reason = rejected(validate(&expired, mtls("ca-ed25519.pem")));
+        assert!(reason.contains("expired"));
+
+        let mut tampered = ed25519.clone();
+        let last = tampered.len() - 1;
+        tampered[last] ^= 1;
+        let reason = rejected(validate(&tampered, mtls("ca-ed25519.pem")));
+        assert!(reason.contains("trusted CA"));
+
+        assert!(rejected(validate(b"junk", mtls("ca-ed25519.pem"))).contains("malformed"));
+
+        assert!(matches!(
+            ClientCertVerifier::load(mtls("missing.pem")),
+            Err(TlsError::ConfigError(_))
+        ));
+    }
+
+    #[test]
+    fn test_client_ca_must_be_a_ca() {
+        let err = ClientCertVerifier::load(mtls("client-ed25519.pem")).unwrap_err();
+        assert!(err.to_string().contains("not a CA certificate"), "{err}");
+        assert!(ClientCertVerifier::load(mtls("ca-mldsa65.pem")).is_ok());
+    }
+
+    #[test]
+    fn test_allowed_cn_patterns() {
+        let der = cert_der("client-ed25519.pem");
+        let with_patterns = |patterns: &[&str]| MtlsConfig {
+            allowed_cn_patterns: patterns.iter().map(|p| p.to_string()).collect(),
+            ..mtls("ca-ed25519.pem")
+        };
+
+        assert!(validate(&der, with_patterns(&["*.clients.internal"])).is_ok());
+        assert!(validate(&der, with_patterns(&["ledger.*", "billing.*"])).is_ok());
+        let reason = rejected(validate(
+            &der,
+            with_patterns(&["ledger.*", "*.example.com"]),
+        ));
+        assert!(reason.contains("billing.clients.internal"));
+    }
 }
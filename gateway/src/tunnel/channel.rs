@@ -0,0 +1,279 @@
+//! Encrypted, length-prefixed framing over a tunnel connection.
+//!
+//! Each frame is `len:u32 || AES-256-GCM(message)`. Every direction has
+//! its own key and a 64-bit sequence number used as the nonce. Once a
+//! direction has carried `rekey_after_bytes` or is `rekey_after` old, the
+//! sender seals a `KeyUpdate` under the current key and both ends move to
+//! the next key, derived from the current one, with the sequence reset.
+
+use aes_gcm::aead::{Aead, KeyInit};
+use aes_gcm::{Aes256Gcm, Nonce};
+use quantun_crypto::derive::derive_session_key;
+use quantun_crypto::SecureBytes;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+use tracing::debug;
+
+use super::codec::Message;
+use super::TunnelError;
+
+/// Messages queued for the writer before senders wait.
+const QUEUE_DEPTH: usize = 64;
+
+/// Per-direction keys agreed by the handshake.
+pub(super) struct SessionKeys {
+    pub(super) send: SecureBytes,
+    pub(super) recv: SecureBytes,
+}
+
+/// When to move a direction to its next key, and the largest frame read.
+#[derive(Debug, Clone, Copy)]
+pub(super) struct Limits {
+    pub(super) rekey_after_bytes: u64,
+    pub(super) rekey_after: Duration,
+    pub(super) max_frame_bytes: usize,
+}
+
+struct DirectionKey {
+    key: SecureBytes,
+    cipher: Aes256Gcm,
+    seq: u64,
+    bytes: u64,
+    since: Instant,
+}
+
+impl DirectionKey {
+    fn new(key: SecureBytes) -> Self {
+        let cipher = Aes256Gcm::new_from_slice(key.as_bytes()).expect("session keys are 32 bytes");
+        Self {
+            key,
+            cipher,
+            seq: 0,
+            bytes: 0,
+            since: Instant::now(),
+        }
+    }
+
+    fn nonce(&mut self) -> Result<Nonce<aes_gcm::aead::consts::U12>, TunnelError> {
+        let mut nonce = [0u8; 12];
+        nonce[4..].copy_from_slice(&self.seq.to_be_bytes());
+        self.seq = self
+            .seq
+            .checked_add(1)
+            .ok_or_else(|| TunnelError::Protocol("sequence number exhausted".into()))?;
+        Ok(nonce.into())
+    }
+
+    fn seal(&mut self, plaintext: &[u8]) -> Result<Vec<u8>, TunnelError> {
+        let nonce = self.nonce()?;
+        self.bytes += plaintext.len() as u64;
+        self.cipher
+            .encrypt(&nonce, plaintext)
+            .map_err(|_| TunnelError::Protocol("frame encryption failed".into()))
+    }
+
+    fn open(&mut self, ciphertext: &[u8]) -> Result<Vec<u8>, TunnelError> {
+        let nonce = self.nonce()?;
+        self.cipher
+            .decrypt(&nonce, ciphertext)
+            .map_err(|_| TunnelError::Protocol("frame authentication failed".into()))
+    }
+
+    fn is_spent(&self, limits: &Limits) -> bool {
+        self.bytes >= limits.rekey_after_bytes || self.since.elapsed() >= limits.rekey_after
+    }
+
+    fn update(&mut self) {
+        *self = Self::new(derive_session_key(
+            self.key.as_bytes(),
+            b"tunnel-key-update",
+        ));
+    }
+}
+
+/// Aborts the channel's tasks when dropped.
+pub(super) struct Tasks(Vec<JoinHandle<()>>);
+
+impl Tasks {
+    pub(super) fn push(&mut self, task: JoinHandle<()>) {
+        self.0.push(task);
+    }
+}
+
+impl Drop for Tasks {
+    fn drop(&mut self) {
+        for task in &self.0 {
+            task.abort();
+        }
+    }
+}
+
+/// Run the encrypted channel over `stream`. Encoded messages sent on the
+/// returned sender are sealed and written in order; opened messages arrive on the
+/// receiver, which closes when the connection fails or the peer hangs up.
+/// Key updates are handled here and counted in `key_updates`.
+pub(super) fn start<S>(
+    stream: S,
+    keys: SessionKeys,
+    limits: Limits,
+    key_updates: Arc<AtomicU64>,
+) -> (mpsc::Sender<Vec<u8>>, mpsc::Receiver<Message>, Tasks)
+where
+    S: AsyncRead + AsyncWrite + Send + 'static,
+{
+    let (mut reader, mut writer) = tokio::io::split(stream);
+    let (out_tx, mut out_rx) = mpsc::channel::<Vec<u8>>(QUEUE_DEPTH);
+    let (in_tx, in_rx) = mpsc::channel(QUEUE_DEPTH);
+
+    let mut recv = DirectionKey::new(keys.recv);
+    let read_task = tokio::spawn(async move {
+        loop {
+            let result = async {
+                let Some(frame) = read_frame(&mut reader, limits.max_frame_bytes).await? else {
+                    return Ok(None);
+                };
+                Message::decode(&recv.open(&frame)?).map(Some)
+            }
+            .await;
+            match result {
+                Ok(Some(Message::KeyUpdate)) => recv.update(),
+                Ok(Some(message)) => {
+                    if in_tx.send(message).await.is_err() {
+                        break;
+                    }
+                }
+                Ok(None) => break,
+                Err(e) => {
+                    debug!(error = %e, "tunnel reader stopped");
+                    break;
+                }
+            }
+        }
+    });
+
+    let reader_handle = read_task.abort_handle();
+    let mut send = DirectionKey::new(keys.send);
+    let write_task = tokio::spawn(async move {
+        while let Some(message) = out_rx.recv().await {
+            let result = async {
+                if send.is_spent(&limits) {
+                    let frame = send.seal(&Message::KeyUpdate.encode())?;
+                    write_frame(&mut writer, &frame).await?;
+                    send.update();
+                    key_updates.fetch_add(1, Ordering::Relaxed);
+                }
+                let frame = send.seal(&message)?;
+                write_frame(&mut writer, &frame).await
+            }
+            .await;
+            if let Err(e) = result {
+                debug!(error = %e, "tunnel writer stopped");
+                break;
+            }
+        }
+        // Nothing more can be sent, so stop reading too: that closes the
+        // receiver and the connection is seen as closed.
+        reader_handle.abort();
+    });
+
+    (out_tx, in_rx, Tasks(vec![write_task, read_task]))
+}
+
+pub(super) async fn write_frame<W>(writer: &mut W, payload: &[u8]) -> Result<(), TunnelError>
+where
+    W: AsyncWrite + Unpin,
+{
+    let len = u32::try_from(payload.len())
+        .map_err(|_| TunnelError::Protocol("frame too large".into()))?;
+    writer.write_all(&len.to_be_bytes()).await?;
+    writer.write_all(payload).await?;
+    writer.flush().await?;
+    Ok(())
+}
+
+/// Read one frame, or `None` if the peer closed the connection between
+/// frames.
+pub(super) async fn read_frame<R>(
+    reader: &mut R,
+    max_frame_bytes: usize,
+) -> Result<Option<Vec<u8>>, TunnelError>
+where
+    R: AsyncRead + Unpin,
+{
+    let mut len = [0u8; 4];
+    match reader.read_exact(&mut len).await {
+        Ok(_) => {}
+        Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => return Ok(None),
+        Err(e) => return Err(e.into()),
+    }
+    let len = u32::from_be_bytes(len) as usize;
+    if len > max_frame_bytes {
+        return Err(TunnelError::Protocol(format!(
+            "frame of {len} bytes exceeds {max_frame_bytes}"
+        )));
+    }
+    let mut frame = vec![0u8; len];
+    reader.read_exact(&mut frame).await?;
+    Ok(Some(frame))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::pin::Pin;
+    use std::task::{Context, Poll};
+    use tokio::io::ReadBuf;
+
+    /// A stream that never delivers data and fails every write.
+    struct BrokenWrites;
+
+    impl AsyncRead for BrokenWrites {
+        fn poll_read(
+            self: Pin<&mut Self>,
+            _: &mut Context<'_>,
+            _: &mut ReadBuf<'_>,
+        ) -> Poll<std::io::Result<()>> {
+            Poll::Pending
+        }
+    }
+
+    impl AsyncWrite for BrokenWrites {
+        fn poll_write(
+            self: Pin<&mut Self>,
+            _: &mut Context<'_>,
+            _: &[u8],
+        ) -> Poll<std::io::Result<usize>> {
+            Poll::Ready(Err(std::io::ErrorKind::BrokenPipe.into()))
+        }
+
+        fn poll_flush(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn poll_shutdown(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+            Poll::Ready(Ok(()))
+        }
+    }
+
+    #[tokio::test]
+    async fn failed_writer_closes_the_receiver() {
+        let keys = SessionKeys {
+            send: SecureBytes::new(vec![1; 32]),
+            recv: SecureBytes::new(vec![2; 32]),
+        };
+        let limits = Limits {
+            rekey_after_bytes: u64::MAX,
+            rekey_after: Duration::from_secs(3600),
+            max_frame_bytes: 1024,
+        };
+        let (outgoing, mut incoming, _tasks) =
+            start(BrokenWrites, keys, limits, Arc::new(AtomicU64::new(0)));
+        outgoing.send(b"hello".to_vec()).await.unwrap();
+        let closed = tokio::time::timeout(Duration::from_secs(5), incoming.recv()).await;
+        assert!(matches!(closed, Ok(None)));
+    }
+}
@@ -0,0 +1,158 @@
+//! Binary encoding of the messages carried inside tunnel frames.
+//!
+//! ```text
+//! message  = kind:u8 id:u64 body
+//! request  = method:str16 uri:str16 headers body:bytes32
+//! response = status:u16 headers body:bytes32
+//! headers  = count:u16 (name:str16 value:bytes16)*
+//! ```
+//!
+//! `KeyUpdate` has id 0 and no body.
+
+use axum::body::Bytes;
+use http::{HeaderMap, HeaderName, HeaderValue, Method, Request, Response, StatusCode, Uri};
+
+use super::TunnelError;
+
+const REQUEST: u8 = 0;
+const RESPONSE: u8 = 1;
+const KEY_UPDATE: u8 = 2;
+
+#[derive(Debug)]
+pub(super) enum Message {
+    Request {
+        id: u64,
+        request: Request<Bytes>,
+    },
+    Response {
+        id: u64,
+        response: Response<Bytes>,
+    },
+    /// The sender switches to its next key after this message.
+    KeyUpdate,
+}
+
+impl Message {
+    pub(super) fn encode(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+        match self {
+            Message::Request { id, request } => {
+                out.push(REQUEST);
+                out.extend_from_slice(&id.to_be_bytes());
+                put16(&mut out, request.method().as_str().as_bytes());
+                put16(&mut out, request.uri().to_string().as_bytes());
+                put_headers(&mut out, request.headers());
+                put32(&mut out, request.body());
+            }
+            Message::Response { id, response } => {
+                out.push(RESPONSE);
+                out.extend_from_slice(&id.to_be_bytes());
+                out.extend_from_slice(&response.status().as_u16().to_be_bytes());
+                put_headers(&mut out, response.headers());
+                put32(&mut out, response.body());
+            }
+            Message::KeyUpdate => {
+                out.push(KEY_UPDATE);
+                out.extend_from_slice(&0u64.to_be_bytes());
+            }
+        }
+        out
+    }
+
+    pub(super) fn decode(bytes: &[u8]) -> Result<Self, TunnelError> {
+        let mut input = Reader(bytes);
+        let kind = input.take(1)?[0];
+        let id = u64::from_be_bytes(input.take(8)?.try_into().expect("8 bytes"));
+        let message = match kind {
+            REQUEST => {
+                let method =
+                    Method::from_bytes(input.get16()?).map_err(|_| malformed("invalid method"))?;
+                let uri = Uri::try_from(input.get16()?).map_err(|_| malformed("invalid URI"))?;
+                let headers = input.headers()?;
+                let mut request = Request::new(Bytes::copy_from_slice(input.get32()?));
+                *request.method_mut() = method;
+                *request.uri_mut() = uri;
+                *request.headers_mut() = headers;
+                Message::Request { id, request }
+            }
+            RESPONSE => {
+                let status = u16::from_be_bytes(input.take(2)?.try_into().expect("2 bytes"));
+                let status =
+                    StatusCode::from_u16(status).map_err(|_| malformed("invalid status"))?;
+                let headers = input.headers()?;
+                let mut response = Response::new(Bytes::copy_from_slice(input.get32()?));
+                *response.status_mut() = status;
+                *response.headers_mut() = headers;
+                Message::Response { id, response }
+            }
+            KEY_UPDATE => Message::KeyUpdate,
+            other => return Err(malformed(&format!("unknown message kind {other}"))),
+        };
+        if !input.0.is_empty() {
+            return Err(malformed("trailing bytes"));
+        }
+        Ok(message)
+    }
+}
+
+fn malformed(what: &str) -> TunnelError {
+    TunnelError::Protocol(what.to_string())
+}
+
+fn put16(out: &mut Vec<u8>, bytes: &[u8]) {
+    let len = u16::try_from(bytes.len()).expect("field fits in 64 KiB");
+    out.extend_from_slice(&len.to_be_bytes());
+    out.extend_from_slice(bytes);
+}
+
+fn put32(out: &mut Vec<u8>, bytes: &[u8]) {
+    let len = u32::try_from(bytes.len()).expect("body fits in 4 GiB");
+    out.extend_from_slice(&len.to_be_bytes());
+    out.extend_from_slice(bytes);
+}
+
+/// Header names and values are bounded by hyper well below 64 KiB.
+fn put_headers(out: &mut Vec<u8>, headers: &HeaderMap) {
+    let count = u16::try_from(headers.len()).expect("fewer than 65536 headers");
+    out.extend_from_slice(&count.to_be_bytes());
+    for (name, value) in headers {
+        put16(out, name.as_str().as_bytes());
+        put16(out, value.as_bytes());
+    }
+}
+
+struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, n: usize) -> Result<&'a [u8], TunnelError> {
+        if self.0.len() < n {
+            return Err(malformed("truncated message"));
+        }
+        let (head, rest) = self.0.split_at(n);
+        self.0 = rest;
+        Ok(head)
+    }
+
+    fn get16(&mut self) -> Result<&'a [u8], TunnelError> {
+        let len = u16::from_be_bytes(self.take(2)?.try_into().expect("2 bytes"));
+        self.take(len.into())
+    }
+
+    fn get32(&mut self) -> Result<&'a [u8], TunnelError> {
+        let len = u32::from_be_bytes(self.take(4)?.try_into().expect("4 bytes"));
+        self.take(len as usize)
+    }
+
+    fn headers(&mut self) -> Result<HeaderMap, TunnelError> {
+        let count = u16::from_be_bytes(self.take(2)?.try_into().expect("2 bytes"));
+        let mut headers = HeaderMap::with_capacity(count.into());
+        for _ in 0..count {
+            let name = HeaderName::from_bytes(self.get16()?)
+                .map_err(|_| malformed("invalid header name"))?;
+            let value = HeaderValue::from_bytes(self.get16()?)
+                .map_err(|_| malformed("invalid header value"))?;
+            headers.append(name, value);
+        }
+        Ok(headers)
+    }
+}
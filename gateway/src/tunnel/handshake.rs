@@ -0,0 +1,186 @@
+//! Mutually authenticated tunnel handshake.
+//!
+//! ```text
+//! initiator                                   responder
+//! ClientHello { name, ephemeral hybrid KEM key }  ->
+//!       <-  ServerHello { name, ciphertext signed by responder identity }
+//! ClientFinished { ML-DSA signature over the transcript }  ->
+//! ```
+//!
+//! The responder proves its identity by signing the encapsulation (and the
+//! shared secret is bound to its key); the initiator proves its own by
+//! signing a hash of both hellos. Each direction's AES key is derived from
+//! the shared secret and the transcript hash.
+
+use quantun_crypto::derive::derive_session_key;
+use quantun_crypto::hybrid::{AuthenticatedHybridEncapsulated, HybridKemKeyPair};
+use quantun_crypto::mldsa::{MlDsaSignature, MlDsaVerifier};
+use quantun_crypto::SecureBytes;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tokio::io::{AsyncRead, AsyncWrite};
+
+use super::channel::{read_frame, write_frame, SessionKeys};
+use super::{TunnelConfig, TunnelError, TunnelPeer};
+
+/// Prefix of the message the initiator signs in `ClientFinished`.
+const FINISHED_CONTEXT: &[u8] = b"qsgw-tunnel-finished-v1";
+
+/// Handshake messages are small; anything larger is rejected unread.
+const MAX_HANDSHAKE_FRAME: usize = 64 * 1024;
+
+#[derive(Serialize, Deserialize)]
+struct ClientHello {
+    name: String,
+    ephemeral: HybridKemKeyPair,
+}
+
+#[derive(Serialize, Deserialize)]
+struct ServerHello {
+    name: String,
+    encapsulated: AuthenticatedHybridEncapsulated,
+}
+
+#[derive(Serialize, Deserialize)]
+struct ClientFinished {
+    signature: Vec<u8>,
+}
+
+/// Run the initiator side against the peer named `peer`.
+pub(super) async fn initiate<S>(
+    stream: &mut S,
+    config: &TunnelConfig,
+    peer: &str,
+) -> Result<SessionKeys, TunnelError>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    let verifier = config
+        .peers
+        .get(peer)
+        .ok_or_else(|| TunnelError::UnknownPeer(peer.to_string()))?;
+
+    let ephemeral = HybridKemKeyPair::generate()?;
+    let client_hello = serde_json::to_vec(&ClientHello {
+        name: config.name.clone(),
+        ephemeral: ephemeral.clone(),
+    })
+    .map_err(|e| TunnelError::Handshake(e.to_string()))?;
+    write_frame(stream, &client_hello).await?;
+
+    let server_hello_bytes = read_message(stream).await?;
+    let server_hello: ServerHello = decode(&server_hello_bytes)?;
+    if server_hello.name != peer {
+        return Err(TunnelError::Handshake(format!(
+            "expected peer {peer}, got {}",
+            server_hello.name
+        )));
+    }
+    let secret = ephemeral
+        .decapsulate_authenticated(verifier, &server_hello.encapsulated)
+        .map(SecureBytes::new)
+        .map_err(|e| TunnelError::Handshake(format!("peer authentication failed: {e}")))?;
+
+    let transcript = transcript_hash(&client_hello, &server_hello_bytes);
+    let signature = config.identity.sign(&finished_message(&transcript))?;
+    let finished = serde_json::to_vec(&ClientFinished {
+        signature: signature.signature,
+    })
+    .map_err(|e| TunnelError::Handshake(e.to_string()))?;
+    write_frame(stream, &finished).await?;
+
+    let (i2r, r2i) = session_keys(secret.as_bytes(), &transcript);
+    Ok(SessionKeys {
+        send: i2r,
+        recv: r2i,
+    })
+}
+
+/// Run the responder side, returning the keys and the authenticated peer.
+pub(super) async fn respond<S>(
+    stream: &mut S,
+    config: &TunnelConfig,
+) -> Result<(SessionKeys, TunnelPeer), TunnelError>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    let client_hello_bytes = read_message(stream).await?;
+    let client_hello: ClientHello = decode(&client_hello_bytes)?;
+    let verifier = config
+        .peers
+        .get(&client_hello.name)
+        .ok_or_else(|| TunnelError::UnknownPeer(client_hello.name.clone()))?;
+
+    let encapsulated =
+        HybridKemKeyPair::encapsulate_authenticated(&client_hello.ephemeral, &config.identity)?;
+    let server_hello = serde_json::to_vec(&ServerHello {
+        name: config.name.clone(),
+        encapsulated: encapsulated.clone(),
+    })
+    .map_err(|e| TunnelError::Handshake(e.to_string()))?;
+    write_frame(stream, &server_hello).await?;
+
+    let transcript = transcript_hash(&client_hello_bytes, &server_hello);
+    let finished: ClientFinished = decode(&read_message(stream).await?)?;
+    let signature = MlDsaSignature {
+        signature: finished.signature,
+        variant: verifier.variant,
+    };
+    if !verify(verifier, &finished_message(&transcript), &signature) {
+        return Err(TunnelError::Handshake(format!(
+            "peer {} failed to prove its identity",
+            client_hello.name
+        )));
+    }
+
+    let (i2r, r2i) = session_keys(&encapsulated.encapsulated.shared_secret, &transcript);
+    let peer = TunnelPeer {
+        fingerprint: crate::jwks::fingerprint(&verifier.public_key),
+        name: client_hello.name,
+    };
+    Ok((
+        SessionKeys {
+            send: r2i,
+            recv: i2r,
+        },
+        peer,
+    ))
+}
+
+fn verify(verifier: &MlDsaVerifier, message: &[u8], signature: &MlDsaSignature) -> bool {
+    verifier.verify(message, signature).unwrap_or(false)
+}
+
+async fn read_message<S>(stream: &mut S) -> Result<Vec<u8>, TunnelError>
+where
+    S: AsyncRead + Unpin,
+{
+    read_frame(stream, MAX_HANDSHAKE_FRAME)
+        .await?
+        .ok_or_else(|| TunnelError::Handshake("connection closed during handshake".into()))
+}
+
+fn decode<T: DeserializeOwned>(bytes: &[u8]) -> Result<T, TunnelError> {
+    serde_json::from_slice(bytes).map_err(|e| TunnelError::Handshake(e.to_string()))
+}
+
+/// SHA-256 over both hellos as sent, each length-prefixed.
+fn transcript_hash(client_hello: &[u8], server_hello: &[u8]) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    for message in [client_hello, server_hello] {
+        hasher.update((message.len() as u32).to_be_bytes());
+        hasher.update(message);
+    }
+    hasher.finalize().into()
+}
+
+fn finished_message(transcript: &[u8; 32]) -> Vec<u8> {
+    [FINISHED_CONTEXT, transcript].concat()
+}
+
+/// Initiator-to-responder and responder-to-initiator keys.
+fn session_keys(secret: &[u8], transcript: &[u8; 32]) -> (SecureBytes, SecureBytes) {
+    let key = |label: &[u8]| derive_session_key(secret, &[label, transcript].concat());
+    (key(b"tunnel-i2r"), key(b"tunnel-r2i"))
+}
@@ -0,0 +1,613 @@
+//! Gateway-to-gateway tunnel.
+//!
+//! A [`TunnelConnector`] forwards requests to a peer gateway over a single
+//! TCP connection protected by a hybrid (X25519 + ML-KEM-768) key exchange,
+//! mutual ML-DSA authentication and AES-256-GCM framing. On the peer, a
+//! [`TunnelAcceptor`] feeds each request into its local router with the
+//! authenticated [`TunnelPeer`] in the request extensions.
+//!
+//! Bodies are buffered, not streamed: a request or response must fit in
+//! [`TunnelConfig::max_frame_bytes`]. Each direction moves to a fresh key
+//! after [`TunnelConfig::rekey_after_bytes`] or [`TunnelConfig::rekey_after`],
+//! and a connector whose connection fails reconnects on its next request.
+
+mod channel;
+mod codec;
+mod handshake;
+
+use axum::body::{Body, Bytes};
+use axum::Router;
+use http::{HeaderValue, Request, Response, StatusCode};
+use quantun_crypto::mldsa::{MlDsaKeyPair, MlDsaVerifier};
+use quantun_crypto::CryptoError;
+use std::collections::HashMap;
+use std::io;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use thiserror::Error;
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::{mpsc, oneshot};
+use tokio::task::JoinSet;
+use tower::ServiceExt;
+use tracing::{debug, info, warn};
+
+use channel::{Limits, Tasks};
+use codec::Message;
+
+/// Bytes a direction carries before it moves to a new key.
+pub const DEFAULT_REKEY_BYTES: u64 = 1 << 30;
+
+/// Age after which a direction moves to a new key.
+pub const DEFAULT_REKEY_INTERVAL: Duration = Duration::from_secs(600);
+
+/// Largest frame, and so the largest request or response, carried.
+pub const DEFAULT_MAX_FRAME_BYTES: usize = 16 * 1024 * 1024;
+
+/// `x-tls-cipher-suite` set on requests arriving through a tunnel, so the
+/// receiving gateway's PQC policy treats them as post-quantum protected.
+pub const TUNNEL_CIPHER_SUITE: &str = "QSGW-TUNNEL_X25519-ML-KEM-768_ML-DSA_AES_256_GCM";
+
+/// Bytes added to each frame by AES-GCM.
+const TAG_LEN: usize = 16;
+
+#[derive(Debug, Error)]
+pub enum TunnelError {
+    #[error("tunnel I/O error: {0}")]
+    Io(#[from] io::Error),
+    #[error("tunnel handshake failed: {0}")]
+    Handshake(String),
+    #[error("unknown tunnel peer: {0}")]
+    UnknownPeer(String),
+    #[error("tunnel protocol error: {0}")]
+    Protocol(String),
+    #[error("tunnel crypto error: {0}")]
+    Crypto(#[from] CryptoError),
+    #[error("tunnel connection closed")]
+    Closed,
+}
+
+/// This gateway's tunnel identity and the peers it will talk to.
+#[derive(Clone)]
+pub struct TunnelConfig {
+    /// Name presented to peers; they look up our key under it.
+    pub name: String,
+    /// Signs our side of every handshake.
+    pub identity: MlDsaKeyPair,
+    /// Keys of the gateways allowed on the other end, by name.
+    pub peers: HashMap<String, MlDsaVerifier>,
+    pub rekey_after_bytes: u64,
+    pub rekey_after: Duration,
+    pub max_frame_bytes: usize,
+    pub handshake_timeout: Duration,
+}
+
+impl TunnelConfig {
+    pub fn new(name: impl Into<String>, identity: MlDsaKeyPair) -> Self {
+        Self {
+            name: name.into(),
+            identity,
+            peers: HashMap::new(),
+            rekey_after_bytes: DEFAULT_REKEY_BYTES,
+            rekey_after: DEFAULT_REKEY_INTERVAL,
+            max_frame_bytes: DEFAULT_MAX_FRAME_BYTES,
+            handshake_timeout: Duration::from_secs(10),
+        }
+    }
+
+    /// Trust `verifier` as the key of the gateway called `name`.
+    pub fn with_peer(mut self, name: impl Into<String>, verifier: MlDsaVerifier) -> Self {
+        self.peers.insert(name.into(), verifier);
+        self
+    }
+
+    fn limits(&self) -> Limits {
+        Limits {
+            rekey_after_bytes: self.rekey_after_bytes,
+            rekey_after: self.rekey_after,
+            max_frame_bytes: self.max_frame_bytes,
+        }
+    }
+
+    /// Encode `message`, failing if it would not fit in a frame.
+    fn encode(&self, message: &Message) -> Result<Vec<u8>, TunnelError> {
+        let encoded = message.encode();
+        if encoded.len() + TAG_LEN > self.max_frame_bytes {
+            return Err(TunnelError::Protocol(format!(
+                "message of {} bytes exceeds the {} byte frame limit",
+                encoded.len(),
+                self.max_frame_bytes
+            )));
+        }
+        Ok(encoded)
+    }
+}
+
+/// A gateway's tunnel setup: its [`TunnelConfig`], where it accepts tunnels
+/// and where it dials the peers that routes forward to. See
+/// [`GatewayConfig::tunnel`](crate::GatewayConfig::tunnel).
+#[derive(Clone)]
+pub struct TunnelSettings {
+    pub config: TunnelConfig,
+    /// Accept tunnels from peers on this address; see [`TunnelAcceptor`].
+    pub listen_addr: Option<SocketAddr>,
+    /// Where to reach each peer this gateway dials, by name. Peers that only
+    /// connect in need no address.
+    pub peer_addrs: HashMap<String, SocketAddr>,
+}
+
+impl TunnelSettings {
+    pub fn new(config: TunnelConfig) -> Self {
+        Self {
+            config,
+            listen_addr: None,
+            peer_addrs: HashMap::new(),
+        }
+    }
+
+    pub fn listen(mut self, addr: SocketAddr) -> Self {
+        self.listen_addr = Some(addr);
+        self
+    }
+
+    /// Dial the peer called `name` at `addr`.
+    pub fn with_peer_addr(mut self, name: impl Into<String>, addr: SocketAddr) -> Self {
+        self.peer_addrs.insert(name.into(), addr);
+        self
+    }
+
+    /// A connector for each peer with an address.
+    pub fn connectors(&self) -> impl Iterator<Item = Arc<TunnelConnector>> + '_ {
+        self.peer_addrs
+            .iter()
+            .map(|(peer, addr)| Arc::new(TunnelConnector::new(self.config.clone(), peer, *addr)))
+    }
+}
+
+/// The authenticated gateway a request arrived from, in the extensions of
+/// requests handled by a [`TunnelAcceptor`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TunnelPeer {
+    pub name: String,
+    /// Hex SHA-256 of the peer's ML-DSA public key.
+    pub fingerprint: String,
+}
+
+type Pending = Arc<Mutex<HashMap<u64, oneshot::Sender<Response<Bytes>>>>>;
+
+/// An established tunnel; its tasks stop when it is dropped.
+struct Connection {
+    outgoing: mpsc::Sender<Vec<u8>>,
+    pending: Pending,
+    next_id: AtomicU64,
+    closed: Arc<AtomicBool>,
+    _tasks: Tasks,
+}
+
+impl Connection {
+    fn is_closed(&self) -> bool {
+        self.closed.load(Ordering::SeqCst)
+    }
+}
+
+/// Sends requests to one peer gateway, connecting on first use and
+/// reconnecting after the connection fails.
+pub struct TunnelConnector {
+    config: TunnelConfig,
+    peer: String,
+    addr: SocketAddr,
+    connection: tokio::sync::Mutex<Option<Arc<Connection>>>,
+    key_updates: Arc<AtomicU64>,
+}
+
+impl TunnelConnector {
+    /// A connector to the gateway `peer` (a key in `config.peers`) at
+    /// `addr`. Nothing is connected until the first [`send`](Self::send).
+    pub fn new(config: TunnelConfig, peer: impl Into<String>, addr: SocketAddr) -> Self {
+        Self {
+            config,
+            peer: peer.into(),
+            addr,
+            connection: tokio::sync::Mutex::new(None),
+            key_updates: Arc::new(AtomicU64::new(0)),
+        }
+    }
+
+    /// Name of the peer gateway.
+    pub fn peer(&self) -> &str {
+        &self.peer
+    }
+
+    /// Whether a tunnel is currently up.
+    pub async fn is_connected(&self) -> bool {
+        self.connection
+            .lock()
+            .await
+            .as_ref()
+            .is_some_and(|conn| !conn.is_closed())
+    }
+
+    /// Key updates sent by this side since it was created.
+    pub fn key_updates(&self) -> u64 {
+        self.key_updates.load(Ordering::Relaxed)
+    }
+
+    /// Send `req` through the tunnel and wait for the peer's response.
+    /// The request body is buffered first.
+    pub async fn send(&self, req: Request<Body>) -> Result<Response<Body>, TunnelError> {
+        let (parts, body) = req.into_parts();
+        let body = axum::body::to_bytes(body, self.config.max_frame_bytes)
+            .await
+            .map_err(|e| TunnelError::Protocol(format!("request body: {e}")))?;
+        let request = Request::from_parts(parts, body);
+
+        let conn = self.connection().await?;
+        let id = conn.next_id.fetch_add(1, Ordering::Relaxed);
+        let encoded = self.config.encode(&Message::Request { id, request })?;
+
+        let (tx, rx) = oneshot::channel();
+        conn.pending.lock().unwrap().insert(id, tx);
+        // The dispatcher marks the connection closed before failing pending
+        // requests, so a request added after that is caught here.
+        if conn.is_closed() || conn.outgoing.send(encoded).await.is_err() {
+            conn.pending.lock().unwrap().remove(&id);
+            return Err(TunnelError::Closed);
+        }
+        let response = rx.await.map_err(|_| TunnelError::Closed)?;
+        Ok(response.map(Body::from))
+    }
+
+    async fn connection(&self) -> Result<Arc<Connection>, TunnelError> {
+        let mut slot = self.connection.lock().await;
+        if let Some(conn) = slot.as_ref().filter(|conn| !conn.is_closed()) {
+            return Ok(conn.clone());
+        }
+        *slot = None;
+        let conn = Arc::new(self.connect().await?);
+        *slot = Some(conn.clone());
+        Ok(conn)
+    }
+
+    async fn connect(&self) -> Result<Connection, TunnelError> {
+        let mut stream = TcpStream::connect(self.addr).await?;
+        stream.set_nodelay(true)?;
+        let keys = tokio::time::timeout(
+            self.config.handshake_timeout,
+            handshake::initiate(&mut stream, &self.config, &self.peer),
+        )
+        .await
+        .map_err(|_| TunnelError::Handshake("timed out".into()))??;
+        info!(peer = %self.peer, addr = %self.addr, "tunnel established");
+
+        let (outgoing, mut incoming, mut tasks) =
+            channel::start(stream, keys, self.config.limits(), self.key_updates.clone());
+        let pending = Pending::default();
+        let closed = Arc::new(AtomicBool::new(false));
+        tasks.push(tokio::spawn({
+            let pending = pending.clone();
+            let closed = closed.clone();
+            let peer = self.peer.clone();
+            async move {
+                while let Some(message) = incoming.recv().await {
+                    match message {
+                        Message::Response { id, response } => {
+                            if let Some(tx) = pending.lock().unwrap().remove(&id) {
+                                let _ = tx.send(response);
+                            }
+                        }
+                        other => debug!(peer = %peer, ?other, "unexpected tunnel message"),
+                    }
+                }
+                closed.store(true, Ordering::SeqCst);
+                pending.lock().unwrap().clear();
+                info!(peer = %peer, "tunnel closed");
+            }
+        }));
+
+        Ok(Connection {
+            outgoing,
+            pending,
+            next_id: AtomicU64::new(0),
+            closed,
+            _tasks: tasks,
+        })
+    }
+}
+
+/// Accepts tunnels from peer gateways and serves their requests with a
+/// local router.
+pub struct TunnelAcceptor {
+    config: TunnelConfig,
+    router: Router,
+    key_updates: Arc<AtomicU64>,
+}
+
+impl TunnelAcceptor {
+    pub fn new(config: TunnelConfig, router: Router) -> Self {
+        Self {
+            config,
+            router,
+            key_updates: Arc::new(AtomicU64::new(0)),
+        }
+    }
+
+    /// Key updates sent by this side across all tunnels.
+    pub fn key_updates(&self) -> u64 {
+        self.key_updates.load(Ordering::Relaxed)
+    }
+
+    /// Accept tunnels on `listener` until an accept fails. Dropping the
+    /// future closes every tunnel it accepted.
+    pub async fn serve(self: Arc<Self>, listener: TcpListener) -> io::Result<()> {
+        let mut tunnels = JoinSet::new();
+        loop {
+            tokio::select! {
+                accepted = listener.accept() => {
+                    let (stream, addr) = accepted?;
+                    let acceptor = self.clone();
+                    tunnels.spawn(async move {
+                        if let Err(e) = acceptor.handle(stream).await {
+                            warn!(%addr, error = %e, "tunnel failed");
+                        }
+                    });
+                }
+                Some(_) = tunnels.join_next(), if !tunnels.is_empty() => {}
+            }
+        }
+    }
+
+    /// Authenticate the peer on `stream`, then serve its requests until it
+    /// disconnects.
+    pub async fn handle(&self, mut stream: TcpStream) -> Result<(), TunnelError> {
+        stream.set_nodelay(true)?;
+        let (keys, peer) = tokio::time::timeout(
+            self.config.handshake_timeout,
+            handshake::respond(&mut stream, &self.config),
+        )
+        .await
+        .map_err(|_| TunnelError::Handshake("timed out".into()))??;
+        info!(peer = %peer.name, "tunnel accepted");
+
+        let (outgoing, mut incoming, _tasks) =
+            channel::start(stream, keys, self.config.limits(), self.key_updates.clone());
+        let mut in_flight = JoinSet::new();
+        loop {
+            tokio::select! {
+                message = incoming.recv() => match message {
+                    Some(Message::Request { id, request }) => {
+                        let router = self.router.clone();
+                        let config = self.config.clone();
+                        let peer = peer.clone();
+                        let outgoing = outgoing.clone();
+                        in_flight.spawn(async move {
+                            let response = serve_request(router, &config, peer, request).await;
+                            let message = Message::Response { id, response };
+                            let encoded = config.encode(&message).unwrap_or_else(|e| {
+                                let response = error_response(StatusCode::BAD_GATEWAY, &e);
+                                Message::Response { id, response }.encode()
+                            });
+                            let _ = outgoing.send(encoded).await;
+                        });
+                    }
+                    Some(other) => debug!(peer = %peer.name, ?other, "unexpected tunnel message"),
+                    None => break,
+                },
+                Some(_) = in_flight.join_next(), if !in_flight.is_empty() => {}
+            }
+        }
+        info!(peer = %peer.name, "tunnel closed");
+        Ok(())
+    }
+}
+
+async fn serve_request(
+    router: Router,
+    config: &TunnelConfig,
+    peer: TunnelPeer,
+    request: Request<Bytes>,
+) -> Response<Bytes> {
+    let mut request = request.map(Body::from);
+    request.headers_mut().insert(
+        "x-tls-cipher-suite",
+        HeaderValue::from_static(TUNNEL_CIPHER_SUITE),
+    );
+    request.extensions_mut().insert(peer);
+
+    let response = match router.oneshot(request).await {
+        Ok(response) => response,
+        Err(infallible) => match infallible {},
+    };
+    let (parts, body) = response.into_parts();
+    match axum::body::to_bytes(body, config.max_frame_bytes).await {
+        Ok(body) => Response::from_parts(parts, body),
+        Err(e) => error_response(
+            StatusCode::BAD_GATEWAY,
+            &TunnelError::Protocol(format!("response body: {e}")),
+        ),
+    }
+}
+
+fn error_response(status: StatusCode, error: &TunnelError) -> Response<Bytes> {
+    let mut response = Response::new(Bytes::from(error.to_string()));
+    *response.status_mut() = status;
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::proxy::{MiddlewareProfile, ProxyService, Route, Upstream};
+    use crate::{build_router, GatewayConfig, TlsPolicy};
+    use axum::routing::get;
+    use axum::Extension;
+    use quantun_types::MlDsaVariant;
+    use tokio::task::JoinHandle;
+
+    /// Configs for gateways "dc1" and "dc2" that trust each other.
+    fn configs() -> (TunnelConfig, TunnelConfig) {
+        let dc1 = MlDsaKeyPair::generate(MlDsaVariant::MlDsa65).unwrap();
+        let dc2 = MlDsaKeyPair::generate(MlDsaVariant::MlDsa65).unwrap();
+        (
+            TunnelConfig::new("dc1", dc1.clone()).with_peer("dc2", dc2.to_verifier()),
+            TunnelConfig::new("dc2", dc2).with_peer("dc1", dc1.to_verifier()),
+        )
+    }
+
+    /// The full gateway router plus a route echoing the tunnel peer.
+    fn remote_router() -> Router {
+        let config = GatewayConfig {
+            tls_policy: TlsPolicy::PqcOnly,
+            ..GatewayConfig::default()
+        };
+        build_router(&config).route(
+            "/whoami",
+            get(|Extension(peer): Extension<TunnelPeer>| async move { peer.name }),
+        )
+    }
+
+    fn serve(
+        config: TunnelConfig,
+        listener: TcpListener,
+    ) -> (Arc<TunnelAcceptor>, JoinHandle<io::Result<()>>) {
+        let acceptor = Arc::new(TunnelAcceptor::new(config, remote_router()));
+        let server = tokio::spawn(acceptor.clone().serve(listener));
+        (acceptor, server)
+    }
+
+    async fn body_string(response: Response<Body>) -> String {
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        String::from_utf8(body.to_vec()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn proxies_requests_to_peer_gateway() {
+        let (dc1, dc2) = configs();
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let (_acceptor, _server) = serve(dc2, listener);
+
+        let route = Route {
+            path_prefix: "/dc2".into(),
+            upstream: Upstream {
+                name: "dc2-gateway".into(),
+                host: "gateway.dc2.internal".into(),
+                port: 8443,
+                is_healthy: true,
+                tls_verify: false,
+                tls: false,
+                weight: 1,
+            },
+            strip_prefix: true,
+            path_rewrite: None,
+            priority: 0,
+            coalesce: None,
+            cache: None,
+            tunnel: Some("dc2".into()),
+            unseal: None,
+            transform: None,
+            sse_filter: None,
+            allowed_methods: Vec::new(),
+            denied_methods: Vec::new(),
+            content_type: None,
+            middleware_profile: MiddlewareProfile::Default,
+        };
+        let proxy = ProxyService::new(vec![route.clone()], 5)
+            .with_tunnel(Arc::new(TunnelConnector::new(dc1, "dc2", addr)));
+
+        // The remote gateway enforces PQC-only, which tunnelled requests
+        // satisfy.
+        let req = Request::get("/dc2/gateway/stats")
+            .body(Body::empty())
+            .unwrap();
+        let response = proxy.forward(&route, req).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert!(body_string(response).await.contains("PqcOnly"));
+
+        let req = Request::get("/dc2/whoami").body(Body::empty()).unwrap();
+        let response = proxy.forward(&route, req).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(body_string(response).await, "dc1");
+
+        let req = Request::get("/dc2/missing").body(Body::empty()).unwrap();
+        let response = proxy.forward(&route, req).await.unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn rekeys_after_byte_budget() {
+        let (mut dc1, mut dc2) = configs();
+        dc1.rekey_after_bytes = 1;
+        dc2.rekey_after_bytes = 1;
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let (acceptor, _server) = serve(dc2, listener);
+        let connector = TunnelConnector::new(dc1, "dc2", addr);
+
+        for _ in 0..3 {
+            let req = Request::get("/whoami").body(Body::empty()).unwrap();
+            let response = connector.send(req).await.unwrap();
+            assert_eq!(body_string(response).await, "dc1");
+        }
+        assert_eq!(connector.key_updates(), 2);
+        assert_eq!(acceptor.key_updates(), 2);
+    }
+
+    #[tokio::test]
+    async fn reconnects_after_peer_restart() {
+        let (dc1, dc2) = configs();
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let (_acceptor, server) = serve(dc2.clone(), listener);
+        let connector = TunnelConnector::new(dc1, "dc2", addr);
+
+        let req = || Request::get("/whoami").body(Body::empty()).unwrap();
+        connector.send(req()).await.unwrap();
+        assert!(connector.is_connected().await);
+
+        server.abort();
+        let _ = server.await;
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while connector.is_connected().await {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .unwrap();
+        assert!(matches!(
+            connector.send(req()).await,
+            Err(TunnelError::Io(_))
+        ));
+
+        let (_acceptor, _server) = serve(dc2, TcpListener::bind(addr).await.unwrap());
+        let response = connector.send(req()).await.unwrap();
+        assert_eq!(body_string(response).await, "dc1");
+        assert!(connector.is_connected().await);
+    }
+
+    #[tokio::test]
+    async fn rejects_untrusted_peers() {
+        let (dc1, mut dc2) = configs();
+        dc2.peers.clear();
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let (_acceptor, _server) = serve(dc2, listener);
+
+        let connector = TunnelConnector::new(dc1.clone(), "dc2", addr);
+        let req = Request::get("/whoami").body(Body::empty()).unwrap();
+        assert!(matches!(
+            connector.send(req).await,
+            Err(TunnelError::Handshake(_))
+        ));
+
+        // Not in our own peer list either.
+        let connector = TunnelConnector::new(dc1, "dc3", addr);
+        let req = Request::get("/whoami").body(Body::empty()).unwrap();
+        assert!(matches!(
+            connector.send(req).await,
+            Err(TunnelError::UnknownPeer(_))
+        ));
+    }
+}
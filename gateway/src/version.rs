@@ -0,0 +1,128 @@
+//! `GET /gateway/version`: what is running, for incident triage.
+//!
+//! Tooling parses the response, so fields are only ever added; a breaking
+//! change bumps [`SCHEMA_VERSION`]. The path is on the default auth bypass
+//! list; remove it from
+//! [`AuthConfig::bypass_paths`](crate::auth::AuthConfig::bypass_paths) to
+//! require authentication.
+
+use quantun_types::Algorithm;
+use serde::{Deserialize, Serialize};
+
+use crate::TlsPolicy;
+
+pub const VERSION_PATH: &str = "/gateway/version";
+
+/// Version of the response shape.
+pub const SCHEMA_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VersionInfo {
+    pub schema_version: u32,
+    /// The gateway crate version.
+    pub version: String,
+    /// Commit the binary was built from, or `unknown`.
+    pub git_sha: String,
+    /// Unix seconds at which the binary was built.
+    pub build_timestamp: u64,
+    pub tls_policy: TlsPolicy,
+    pub algorithms: Vec<AlgorithmInfo>,
+    /// Enabled Cargo features: the crypto families from
+    /// [`quantun_crypto::enabled_features`], then the gateway's optional
+    /// `grpc` and `otlp`.
+    pub features: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AlgorithmInfo {
+    pub name: String,
+    /// NIST security level, 1 through 5.
+    pub security_level: u8,
+}
+
+impl VersionInfo {
+    pub fn new(tls_policy: TlsPolicy) -> Self {
+        Self {
+            schema_version: SCHEMA_VERSION,
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            git_sha: env!("QSGW_GIT_SHA").to_string(),
+            build_timestamp: env!("QSGW_BUILD_TIMESTAMP")
+                .parse()
+                .expect("build script sets a numeric timestamp"),
+            tls_policy,
+            algorithms: Algorithm::ALL
+                .iter()
+                .map(|a| AlgorithmInfo {
+                    name: a.to_string(),
+                    security_level: a.security_level().as_u8(),
+                })
+                .collect(),
+            features: quantun_crypto::enabled_features()
+                .into_iter()
+                .chain(cfg!(feature = "grpc").then_some("grpc"))
+                .chain(cfg!(feature = "otlp").then_some("otlp"))
+                .map(String::from)
+                .collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::auth::{AuthConfig, AuthPolicy};
+    use crate::{build_router, GatewayConfig};
+    use axum::body::Body;
+    use http::{Request, StatusCode};
+    use serde_json::Value;
+    use std::sync::Arc;
+    use tower::ServiceExt;
+
+    #[tokio::test]
+    async fn test_version_endpoint_fields() {
+        let config = GatewayConfig::default();
+        let response = build_router(&config)
+            .oneshot(Request::get(VERSION_PATH).body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let json: Value = serde_json::from_slice(&body).unwrap();
+
+        assert_eq!(json["schema_version"], SCHEMA_VERSION);
+        assert_eq!(json["version"], env!("CARGO_PKG_VERSION"));
+        assert!(!json["git_sha"].as_str().unwrap().is_empty());
+        assert!(json["build_timestamp"].as_u64().unwrap() > 0);
+        assert_eq!(json["tls_policy"], "pqc_preferred");
+        let algorithms = json["algorithms"].as_array().unwrap();
+        assert_eq!(algorithms.len(), Algorithm::ALL.len());
+        assert!(algorithms.iter().all(|a| a["name"].is_string()));
+        assert!(algorithms
+            .iter()
+            .all(|a| (1..=5).contains(&a["security_level"].as_u64().unwrap())));
+        let features = json["features"].as_array().unwrap();
+        assert!(features.contains(&"mlkem".into()));
+        assert!(features.iter().all(Value::is_string));
+        assert_eq!(json.as_object().unwrap().len(), 7);
+    }
+
+    #[tokio::test]
+    async fn test_version_endpoint_bypasses_auth_by_default() {
+        let policy = AuthPolicy::new(AuthConfig {
+            require_auth: true,
+            ..AuthConfig::default()
+        })
+        .unwrap();
+        let config = GatewayConfig {
+            auth: Some(Arc::new(policy)),
+            ..GatewayConfig::default()
+        };
+        let response = build_router(&config)
+            .oneshot(Request::get(VERSION_PATH).body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+}
@@ -0,0 +1,761 @@
+//! Webhook notifications of gateway lifecycle and security events.
+//!
+//! A [`WebhookNotifier`] POSTs each [`GatewayEvent`] passing its filter to
+//! every configured endpoint, as JSON carrying the event's fields, its
+//! `event` kind, and a `timestamp`:
+//!
+//! ```text
+//! {"timestamp":1792314000,"event":"key_rotated","slot":"api","kind":"activated","key_id":"k2","at":1792314000}
+//! ```
+//!
+//! Upstream ejections and restorations are reported by
+//! [`ProxyService::with_notifier`](crate::proxy::ProxyService::with_notifier)
+//! and by Kubernetes discovery, and expiring certificates by
+//! [`CryptoInventory::spawn_expiry_check`](crate::inventory::CryptoInventory::spawn_expiry_check).
+//!
+//! Payloads are signed so receivers can authenticate them. The signed
+//! message is `<timestamp>.<body>`, with the timestamp also sent in
+//! `X-QSGW-Webhook-Timestamp`. The base64 signature is sent in
+//! `X-QSGW-Webhook-Signature` as `hmac-sha256=<sig>`, or as `<alg>=<sig>`
+//! for an ML-DSA key, whose JWKS `kid` is then sent in `X-QSGW-Key-Id`.
+//!
+//! Connection failures, timeouts, 429 and 5xx replies are retried with
+//! exponential backoff up to [`WebhookConfig::max_attempts`] times.
+//! Deliveries that still fail, or are refused with another status, are
+//! dead-lettered: logged at error level and appended as a JSON line to
+//! [`WebhookConfig::dead_letter_path`], if set.
+
+use axum::body::{Body, Bytes};
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use hmac::{Hmac, Mac};
+use http::uri::Scheme;
+use http::{header, HeaderName, HeaderValue, Request, StatusCode, Uri};
+use hyper_rustls::HttpsConnector;
+use hyper_util::client::legacy::connect::HttpConnector;
+use hyper_util::client::legacy::Client;
+use hyper_util::rt::TokioExecutor;
+use quantun_crypto::{RemoteSigner, SecureBytes};
+use quantun_types::Algorithm;
+use rustls::crypto::aws_lc_rs;
+use rustls::{ClientConfig, RootCertStore};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use std::collections::HashSet;
+use std::io::Write;
+use std::net::IpAddr;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use thiserror::Error;
+use tokio::sync::broadcast;
+use tokio::task::JoinHandle;
+use tracing::{error, warn};
+
+use crate::jwks::fingerprint;
+use crate::proxy::egress::authority;
+use crate::proxy::reload::RouteDiff;
+use crate::proxy::Upstream;
+use crate::response_signing::KEY_ID_HEADER;
+use crate::rotation::RotationEvent;
+use crate::scanner::RegressionAlert;
+
+/// Default number of delivery attempts per endpoint.
+pub const DEFAULT_MAX_ATTEMPTS: u32 = 5;
+
+/// Default wait before the first retry.
+pub const DEFAULT_INITIAL_BACKOFF_MS: u64 = 500;
+
+/// Default limit on each delivery attempt.
+pub const DEFAULT_DELIVERY_TIMEOUT_SECS: u64 = 10;
+
+/// Longest wait between attempts.
+const MAX_BACKOFF: Duration = Duration::from_secs(60);
+
+pub const EVENT_HEADER: HeaderName = HeaderName::from_static("x-qsgw-event");
+pub const WEBHOOK_TIMESTAMP_HEADER: HeaderName =
+    HeaderName::from_static("x-qsgw-webhook-timestamp");
+pub const WEBHOOK_SIGNATURE_HEADER: HeaderName =
+    HeaderName::from_static("x-qsgw-webhook-signature");
+
+/// The kinds of [`GatewayEvent`], for filtering.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EventKind {
+    PolicyDowngrade,
+    UpstreamEjected,
+    UpstreamRestored,
+    CertificateExpiring,
+    KeyRotated,
+    AuthBruteForce,
+    ConfigReloaded,
+}
+
+impl EventKind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            EventKind::PolicyDowngrade => "policy_downgrade",
+            EventKind::UpstreamEjected => "upstream_ejected",
+            EventKind::UpstreamRestored => "upstream_restored",
+            EventKind::CertificateExpiring => "certificate_expiring",
+            EventKind::KeyRotated => "key_rotated",
+            EventKind::AuthBruteForce => "auth_brute_force",
+            EventKind::ConfigReloaded => "config_reloaded",
+        }
+    }
+}
+
+/// A lifecycle or security event worth pushing to operators.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum GatewayEvent {
+    /// An upstream negotiated less PQC than at its last scan.
+    PolicyDowngrade(RegressionAlert),
+    /// An upstream was taken out of rotation. `upstream` is its name and
+    /// address, e.g. `api (10.0.0.2:8080)`.
+    UpstreamEjected { upstream: String, reason: String },
+    /// An ejected upstream was put back into rotation.
+    UpstreamRestored { upstream: String },
+    /// A certificate expires within the warning window.
+    CertificateExpiring {
+        subject: String,
+        /// Unix time of expiry, in seconds.
+        expires_at: u64,
+        days_remaining: u64,
+    },
+    /// The rotation scheduler changed a key's state.
+    KeyRotated(RotationEvent),
+    /// A client was locked out after repeated authentication failures.
+    AuthBruteForce {
+        subject: String,
+        failures: u32,
+        locked_for_secs: u64,
+    },
+    /// Routes were reloaded from the config file.
+    ConfigReloaded(RouteDiff),
+}
+
+impl GatewayEvent {
+    /// `upstream` was taken out of rotation because of `reason`.
+    pub fn upstream_ejected(upstream: &Upstream, reason: impl Into<String>) -> Self {
+        GatewayEvent::UpstreamEjected {
+            upstream: upstream_label(upstream),
+            reason: reason.into(),
+        }
+    }
+
+    /// `upstream` was put back into rotation.
+    pub fn upstream_restored(upstream: &Upstream) -> Self {
+        GatewayEvent::UpstreamRestored {
+            upstream: upstream_label(upstream),
+        }
+    }
+
+    pub fn kind(&self) -> EventKind {
+        match self {
+            GatewayEvent::PolicyDowngrade(_) => EventKind::PolicyDowngrade,
+            GatewayEvent::UpstreamEjected { .. } => EventKind::UpstreamEjected,
+            GatewayEvent::UpstreamRestored { .. } => EventKind::UpstreamRestored,
+            GatewayEvent::CertificateExpiring { .. } => EventKind::CertificateExpiring,
+            GatewayEvent::KeyRotated(_) => EventKind::KeyRotated,
+            GatewayEvent::AuthBruteForce { .. } => EventKind::AuthBruteForce,
+            GatewayEvent::ConfigReloaded(_) => EventKind::ConfigReloaded,
+        }
+    }
+}
+
+fn upstream_label(upstream: &Upstream) -> String {
+    format!(
+        "{} ({})",
+        upstream.name,
+        authority(&upstream.host, upstream.port)
+    )
+}
+
+impl From<RegressionAlert> for GatewayEvent {
+    fn from(alert: RegressionAlert) -> Self {
+        GatewayEvent::PolicyDowngrade(alert)
+    }
+}
+
+impl From<RotationEvent> for GatewayEvent {
+    fn from(event: RotationEvent) -> Self {
+        GatewayEvent::KeyRotated(event)
+    }
+}
+
+impl From<RouteDiff> for GatewayEvent {
+    fn from(diff: RouteDiff) -> Self {
+        GatewayEvent::ConfigReloaded(diff)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WebhookConfig {
+    /// Receiver URLs. They must use `https`, except on loopback addresses.
+    pub endpoints: Vec<String>,
+    /// Event kinds delivered; every kind when empty.
+    #[serde(default)]
+    pub events: Vec<EventKind>,
+    /// Delivery attempts per endpoint, the first included.
+    #[serde(default = "default_max_attempts")]
+    pub max_attempts: u32,
+    /// Wait before the first retry, doubled for each retry after it.
+    #[serde(default = "default_initial_backoff_ms")]
+    pub initial_backoff_ms: u64,
+    /// Limit on each delivery attempt.
+    #[serde(default = "default_timeout_secs")]
+    pub timeout_secs: u64,
+    /// File that failed deliveries are appended to.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dead_letter_path: Option<PathBuf>,
+}
+
+fn default_max_attempts() -> u32 {
+    DEFAULT_MAX_ATTEMPTS
+}
+
+fn default_initial_backoff_ms() -> u64 {
+    DEFAULT_INITIAL_BACKOFF_MS
+}
+
+fn default_timeout_secs() -> u64 {
+    DEFAULT_DELIVERY_TIMEOUT_SECS
+}
+
+impl Default for WebhookConfig {
+    fn default() -> Self {
+        Self {
+            endpoints: Vec::new(),
+            events: Vec::new(),
+            max_attempts: DEFAULT_MAX_ATTEMPTS,
+            initial_backoff_ms: DEFAULT_INITIAL_BACKOFF_MS,
+            timeout_secs: DEFAULT_DELIVERY_TIMEOUT_SECS,
+            dead_letter_path: None,
+        }
+    }
+}
+
+/// How webhook payloads are signed.
+pub enum WebhookSigner {
+    /// HMAC-SHA256 under a secret shared with the receivers.
+    Hmac(SecureBytes),
+    /// An ML-DSA key, verifiable against the gateway's JWKS document.
+    MlDsa(Arc<dyn RemoteSigner>),
+}
+
+#[derive(Debug, Error)]
+pub enum WebhookError {
+    #[error("no webhook endpoints configured")]
+    NoEndpoints,
+    #[error("invalid webhook endpoint {0:?}")]
+    InvalidEndpoint(String),
+    #[error("webhook endpoint {0} must use https")]
+    InsecureEndpoint(String),
+    #[error("max_attempts must be at least 1")]
+    ZeroAttempts,
+    #[error("the HMAC secret is empty")]
+    EmptySecret,
+    #[error("webhook signing needs an ML-DSA key, got {0}")]
+    UnsupportedKey(Algorithm),
+}
+
+/// Delivers [`GatewayEvent`]s to webhook endpoints.
+pub struct WebhookNotifier {
+    endpoints: Vec<Uri>,
+    events: HashSet<EventKind>,
+    signer: WebhookSigner,
+    key_id: Option<String>,
+    max_attempts: u32,
+    initial_backoff: Duration,
+    timeout: Duration,
+    dead_letter_path: Option<PathBuf>,
+    client: Client<HttpsConnector<HttpConnector>, Body>,
+}
+
+impl WebhookNotifier {
+    /// A notifier for `config` signing with `signer`. Receivers' certificates
+    /// are verified against the Mozilla root store.
+    pub fn new(config: WebhookConfig, signer: WebhookSigner) -> Result<Self, WebhookError> {
+        if config.endpoints.is_empty() {
+            return Err(WebhookError::NoEndpoints);
+        }
+        if config.max_attempts == 0 {
+            return Err(WebhookError::ZeroAttempts);
+        }
+        let endpoints = config
+            .endpoints
+            .iter()
+            .map(|endpoint| parse_endpoint(endpoint))
+            .collect::<Result<Vec<_>, _>>()?;
+        let key_id = match &signer {
+            WebhookSigner::Hmac(secret) if secret.as_bytes().is_empty() => {
+                return Err(WebhookError::EmptySecret)
+            }
+            WebhookSigner::Hmac(_) => None,
+            WebhookSigner::MlDsa(signer) => match signer.algorithm() {
+                Algorithm::MlDsa(_) => Some(fingerprint(signer.public_key())),
+                other => return Err(WebhookError::UnsupportedKey(other)),
+            },
+        };
+
+        let tls = ClientConfig::builder_with_provider(Arc::new(aws_lc_rs::default_provider()))
+            .with_safe_default_protocol_versions()
+            .expect("the default provider supports the default versions")
+            .with_root_certificates(RootCertStore {
+                roots: webpki_roots::TLS_SERVER_ROOTS.to_vec(),
+            })
+            .with_no_client_auth();
+        let connector = hyper_rustls::HttpsConnectorBuilder::new()
+            .with_tls_config(tls)
+            .https_or_http()
+            .enable_http1()
+            .build();
+
+        Ok(Self {
+            endpoints,
+            events: config.events.into_iter().collect(),
+            signer,
+            key_id,
+            max_attempts: config.max_attempts,
+            initial_backoff: Duration::from_millis(config.initial_backoff_ms),
+            timeout: Duration::from_secs(config.timeout_secs),
+            dead_letter_path: config.dead_letter_path,
+            client: Client::builder(TokioExecutor::new()).build(connector),
+        })
+    }
+
+    /// Whether events of `kind` are delivered.
+    pub fn wants(&self, kind: EventKind) -> bool {
+        self.events.is_empty() || self.events.contains(&kind)
+    }
+
+    /// Deliver `event` in the background.
+    pub fn notify(self: &Arc<Self>, event: GatewayEvent) {
+        if !self.wants(event.kind()) {
+            return;
+        }
+        let notifier = self.clone();
+        tokio::spawn(async move { notifier.deliver(event).await });
+    }
+
+    /// Deliver every event received on `events` until its sender closes,
+    /// such as [`RotationScheduler::subscribe`](crate::rotation::RotationScheduler::subscribe)
+    /// or [`UpstreamScanner::subscribe`](crate::scanner::UpstreamScanner::subscribe).
+    pub fn forward<T>(self: &Arc<Self>, mut events: broadcast::Receiver<T>) -> JoinHandle<()>
+    where
+        T: Into<GatewayEvent> + Clone + Send + 'static,
+    {
+        let notifier = self.clone();
+        tokio::spawn(async move {
+            loop {
+                match events.recv().await {
+                    Ok(event) => notifier.notify(event.into()),
+                    Err(broadcast::error::RecvError::Lagged(missed)) => {
+                        warn!(missed, "webhook notifier fell behind, events dropped")
+                    }
+                    Err(broadcast::error::RecvError::Closed) => return,
+                }
+            }
+        })
+    }
+
+    /// Deliver `event` to every endpoint, returning once each delivery has
+    /// succeeded or been dead-lettered. Ignores the event filter.
+    pub async fn deliver(&self, event: GatewayEvent) {
+        let kind = event.kind();
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        let body = Bytes::from(
+            serde_json::to_vec(&Payload {
+                timestamp,
+                event: &event,
+            })
+            .expect("events serialize"),
+        );
+        let signature = match self.sign(timestamp, &body).await {
+            Ok(signature) => signature,
+            Err(e) => {
+                for endpoint in &self.endpoints {
+                    self.dead_letter(endpoint, kind, 0, &e, &body);
+                }
+                return;
+            }
+        };
+        let deliveries = self
+            .endpoints
+            .iter()
+            .map(|endpoint| self.deliver_to(endpoint, kind, timestamp, &signature, body.clone()));
+        futures::future::join_all(deliveries).await;
+    }
+
+    /// The `X-QSGW-Webhook-Signature` value for `body` sent at `timestamp`.
+    async fn sign(&self, timestamp: u64, body: &[u8]) -> Result<String, String> {
+        let mut message = format!("{timestamp}.").into_bytes();
+        message.extend_from_slice(body);
+        match &self.signer {
+            WebhookSigner::Hmac(secret) => {
+                let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes())
+                    .expect("HMAC accepts keys of any length");
+                mac.update(&message);
+                Ok(format!(
+                    "hmac-sha256={}",
+                    STANDARD.encode(mac.finalize().into_bytes())
+                ))
+            }
+            WebhookSigner::MlDsa(signer) => signer
+                .sign(&message)
+                .await
+                .map(|signature| format!("{}={}", signer.algorithm(), STANDARD.encode(signature)))
+                .map_err(|e| format!("signing failed: {e}")),
+        }
+    }
+
+    async fn deliver_to(
+        &self,
+        endpoint: &Uri,
+        kind: EventKind,
+        timestamp: u64,
+        signature: &str,
+        body: Bytes,
+    ) {
+        let mut backoff = self.initial_backoff;
+        let mut attempts = 0;
+        let error = loop {
+            attempts += 1;
+            let retryable = match self.send(endpoint, kind, timestamp, signature, &body).await {
+                Ok(()) => return,
+                Err(Failure::Retryable(e)) if attempts < self.max_attempts => e,
+                Err(Failure::Retryable(e) | Failure::Refused(e)) => break e,
+            };
+            warn!(
+                endpoint = %endpoint,
+                event = kind.as_str(),
+                attempt = attempts,
+                error = %retryable,
+                "webhook delivery failed, retrying"
+            );
+            tokio::time::sleep(backoff).await;
+            backoff = (backoff * 2).min(MAX_BACKOFF);
+        };
+        self.dead_letter(endpoint, kind, attempts, &error, &body);
+    }
+
+    async fn send(
+        &self,
+        endpoint: &Uri,
+        kind: EventKind,
+        timestamp: u64,
+        signature: &str,
+        body: &Bytes,
+    ) -> Result<(), Failure> {
+        let mut req = Request::post(endpoint.clone())
+            .header(header::CONTENT_TYPE, "application/json")
+            .header(EVENT_HEADER, kind.as_str())
+            .header(WEBHOOK_TIMESTAMP_HEADER, timestamp)
+            .header(WEBHOOK_SIGNATURE_HEADER, signature)
+            .body(Body::from(body.clone()))
+            .map_err(|e| Failure::Refused(e.to_string()))?;
+        if let Some(key_id) = &self.key_id {
+            let key_id = HeaderValue::from_str(key_id).expect("fingerprints are hex");
+            req.headers_mut().insert(KEY_ID_HEADER, key_id);
+        }
+        let response = tokio::time::timeout(self.timeout, self.client.request(req))
+            .await
+            .map_err(|_| Failure::Retryable("timed out".into()))?
+            .map_err(|e| Failure::Retryable(e.to_string()))?;
+        match response.status() {
+            s if s.is_success() => Ok(()),
+            s if s.is_server_error() || s == StatusCode::TOO_MANY_REQUESTS => {
+                Err(Failure::Retryable(format!("receiver returned {s}")))
+            }
+            s => Err(Failure::Refused(format!("receiver returned {s}"))),
+        }
+    }
+
+    fn dead_letter(
+        &self,
+        endpoint: &Uri,
+        kind: EventKind,
+        attempts: u32,
+        error: &str,
+        body: &[u8],
+    ) {
+        error!(
+            endpoint = %endpoint,
+            event = kind.as_str(),
+            attempts,
+            error,
+            "webhook delivery failed, dead-lettered"
+        );
+        let Some(path) = &self.dead_letter_path else {
+            return;
+        };
+        let record = serde_json::json!({
+            "endpoint": endpoint.to_string(),
+            "attempts": attempts,
+            "error": error,
+            "payload": serde_json::from_slice::<serde_json::Value>(body).ok(),
+        });
+        let mut line = serde_json::to_vec(&record).expect("records serialize");
+        line.push(b'\n');
+        let written = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .and_then(|mut file| file.write_all(&line));
+        if let Err(e) = written {
+            error!(path = %path.display(), error = %e, "could not write webhook dead letter");
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct Payload<'a> {
+    timestamp: u64,
+    #[serde(flatten)]
+    event: &'a GatewayEvent,
+}
+
+/// Why an attempt failed.
+enum Failure {
+    Retryable(String),
+    Refused(String),
+}
+
+fn parse_endpoint(endpoint: &str) -> Result<Uri, WebhookError> {
+    let uri: Uri = endpoint
+        .parse()
+        .map_err(|_| WebhookError::InvalidEndpoint(endpoint.to_string()))?;
+    let Some(host) = uri.host() else {
+        return Err(WebhookError::InvalidEndpoint(endpoint.to_string()));
+    };
+    let loopback = host == "localhost"
+        || host
+            .trim_start_matches('[')
+            .trim_end_matches(']')
+            .parse::<IpAddr>()
+            .is_ok_and(|ip| ip.is_loopback());
+    match uri.scheme() {
+        Some(scheme) if *scheme == Scheme::HTTPS => Ok(uri),
+        Some(scheme) if *scheme == Scheme::HTTP && loopback => Ok(uri),
+        Some(scheme) if *scheme == Scheme::HTTP => {
+            Err(WebhookError::InsecureEndpoint(endpoint.to_string()))
+        }
+        _ => Err(WebhookError::InvalidEndpoint(endpoint.to_string())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::proxy::testing::{MockResponse, MockUpstream};
+    use crate::rotation::RotationEventKind;
+    use quantun_crypto::mldsa::{MlDsaSignature, MlDsaVerifier};
+    use quantun_crypto::signer::KeyStoreSigner;
+    use quantun_crypto::KeyStore;
+    use quantun_types::{KeyUsage, MlDsaVariant};
+
+    fn rotation() -> GatewayEvent {
+        GatewayEvent::KeyRotated(RotationEvent {
+            slot: "api".into(),
+            kind: RotationEventKind::Activated,
+            key_id: "k2".into(),
+            at: 1_000,
+        })
+    }
+
+    fn config(receiver: &MockUpstream) -> WebhookConfig {
+        WebhookConfig {
+            endpoints: vec![format!("http://{}/hooks", receiver.addr())],
+            initial_backoff_ms: 1,
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_payload_signed_with_hmac() {
+        let receiver = MockUpstream::start().await.unwrap();
+        let notifier = WebhookNotifier::new(
+            config(&receiver),
+            WebhookSigner::Hmac(SecureBytes::new(b"shared secret".to_vec())),
+        )
+        .unwrap();
+        notifier.deliver(rotation()).await;
+
+        let posted = &receiver.requests()[0];
+        assert_eq!(posted.uri.path(), "/hooks");
+        assert_eq!(posted.headers[EVENT_HEADER], "key_rotated");
+        let payload: serde_json::Value = serde_json::from_slice(&posted.body).unwrap();
+        assert_eq!(payload["event"], "key_rotated");
+        assert_eq!(payload["kind"], "activated");
+        assert_eq!(payload["key_id"], "k2");
+
+        let timestamp = posted.headers[WEBHOOK_TIMESTAMP_HEADER].to_str().unwrap();
+        assert_eq!(payload["timestamp"].to_string(), timestamp);
+        let mut mac = Hmac::<Sha256>::new_from_slice(b"shared secret").unwrap();
+        mac.update(format!("{timestamp}.").as_bytes());
+        mac.update(&posted.body);
+        let expected = format!(
+            "hmac-sha256={}",
+            STANDARD.encode(mac.finalize().into_bytes())
+        );
+        assert_eq!(posted.headers[WEBHOOK_SIGNATURE_HEADER], expected.as_str());
+        assert!(posted.headers.get(KEY_ID_HEADER).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_payload_signed_with_ml_dsa() {
+        let receiver = MockUpstream::start().await.unwrap();
+        let store = Arc::new(KeyStore::new());
+        let key = store
+            .create(Algorithm::MlDsa(MlDsaVariant::MlDsa44), [KeyUsage::Sign])
+            .unwrap();
+        let signer = KeyStoreSigner::new(store, key).unwrap();
+        let public_key = signer.public_key().to_vec();
+        let notifier =
+            WebhookNotifier::new(config(&receiver), WebhookSigner::MlDsa(Arc::new(signer)))
+                .unwrap();
+        notifier.deliver(rotation()).await;
+
+        let posted = &receiver.requests()[0];
+        assert_eq!(
+            posted.headers[KEY_ID_HEADER],
+            fingerprint(&public_key).as_str()
+        );
+        let signature = posted.headers[WEBHOOK_SIGNATURE_HEADER]
+            .to_str()
+            .unwrap()
+            .strip_prefix("ML-DSA-44=")
+            .unwrap();
+        let signature = MlDsaSignature {
+            signature: STANDARD.decode(signature).unwrap(),
+            variant: MlDsaVariant::MlDsa44,
+        };
+        let timestamp = posted.headers[WEBHOOK_TIMESTAMP_HEADER].to_str().unwrap();
+        let mut message = format!("{timestamp}.").into_bytes();
+        message.extend_from_slice(&posted.body);
+        let verifier = MlDsaVerifier {
+            public_key,
+            variant: MlDsaVariant::MlDsa44,
+        };
+        assert!(verifier.verify(&message, &signature).unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_retries_server_errors_then_dead_letters() {
+        let receiver = MockUpstream::start().await.unwrap();
+        receiver.enqueue(MockResponse::new(StatusCode::INTERNAL_SERVER_ERROR));
+        receiver.enqueue(MockResponse::new(StatusCode::SERVICE_UNAVAILABLE));
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_nanos();
+        let dead_letters = std::env::temp_dir().join(format!("qsgw-dead-letters-{nanos}.jsonl"));
+        let notifier = WebhookNotifier::new(
+            WebhookConfig {
+                max_attempts: 3,
+                dead_letter_path: Some(dead_letters.clone()),
+                ..config(&receiver)
+            },
+            WebhookSigner::Hmac(SecureBytes::new(b"secret".to_vec())),
+        )
+        .unwrap();
+
+        notifier.deliver(rotation()).await;
+        assert_eq!(receiver.requests().len(), 3);
+        assert!(!dead_letters.exists());
+
+        receiver.set_fallback(MockResponse::new(StatusCode::INTERNAL_SERVER_ERROR));
+        notifier.deliver(rotation()).await;
+        assert_eq!(receiver.requests().len(), 6);
+        let lines = std::fs::read_to_string(&dead_letters).unwrap();
+        std::fs::remove_file(&dead_letters).unwrap();
+        let record: serde_json::Value = serde_json::from_str(lines.trim()).unwrap();
+        assert_eq!(record["attempts"], 3);
+        assert_eq!(
+            record["error"],
+            "receiver returned 500 Internal Server Error"
+        );
+        assert_eq!(record["payload"]["event"], "key_rotated");
+
+        // Other refusals are not retried.
+        receiver.set_fallback(MockResponse::new(StatusCode::BAD_REQUEST));
+        let notifier = WebhookNotifier::new(
+            config(&receiver),
+            WebhookSigner::Hmac(SecureBytes::new(b"secret".to_vec())),
+        )
+        .unwrap();
+        notifier.deliver(rotation()).await;
+        assert_eq!(receiver.requests().len(), 7);
+    }
+
+    #[tokio::test]
+    async fn test_filter_selects_event_kinds() {
+        let receiver = MockUpstream::start().await.unwrap();
+        let notifier = Arc::new(
+            WebhookNotifier::new(
+                WebhookConfig {
+                    events: vec![EventKind::ConfigReloaded],
+                    ..config(&receiver)
+                },
+                WebhookSigner::Hmac(SecureBytes::new(b"secret".to_vec())),
+            )
+            .unwrap(),
+        );
+        assert!(notifier.wants(EventKind::ConfigReloaded));
+        assert!(!notifier.wants(EventKind::KeyRotated));
+
+        notifier.notify(rotation());
+        notifier.notify(GatewayEvent::ConfigReloaded(RouteDiff {
+            added: 1,
+            removed: 0,
+            changed: 2,
+        }));
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while receiver.requests().is_empty() {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .unwrap();
+        tokio::time::sleep(Duration::from_millis(50)).await;
+
+        let posted = receiver.requests();
+        assert_eq!(posted.len(), 1);
+        let payload: serde_json::Value = serde_json::from_slice(&posted[0].body).unwrap();
+        assert_eq!(payload["event"], "config_reloaded");
+        assert_eq!(payload["changed"], 2);
+    }
+
+    #[test]
+    fn test_endpoints_must_use_https() {
+        let signer = || WebhookSigner::Hmac(SecureBytes::new(b"secret".to_vec()));
+        let with = |endpoint: &str| {
+            WebhookNotifier::new(
+                WebhookConfig {
+                    endpoints: vec![endpoint.to_string()],
+                    ..Default::default()
+                },
+                signer(),
+            )
+        };
+        assert!(with("https://soc.example.com/hooks").is_ok());
+        assert!(with("http://127.0.0.1:9000/hooks").is_ok());
+        assert!(with("http://[::1]:9000/hooks").is_ok());
+        assert!(matches!(
+            with("http://soc.example.com/hooks"),
+            Err(WebhookError::InsecureEndpoint(_))
+        ));
+        assert!(matches!(
+            with("soc.example.com"),
+            Err(WebhookError::InvalidEndpoint(_))
+        ));
+        assert!(matches!(
+            WebhookNotifier::new(WebhookConfig::default(), signer()),
+            Err(WebhookError::NoEndpoints)
+        ));
+    }
+}
@@ -19,6 +19,9 @@ pub struct TlsConfig {
     pub mutual_tls: bool,
     /// Whether to enable hybrid key exchange (classical + PQC).
     pub hybrid_mode: bool,
+    /// Which requests may be served from TLS 1.3 early data (0-RTT).
+    #[serde(default)]
+    pub early_data: EarlyDataPolicy,
 }
 
 /// Supported TLS protocol versions.
@@ -28,6 +31,19 @@ pub enum TlsVersion {
     Tls13,
 }
 
+/// Handling of requests received as TLS 1.3 early data (0-RTT), which an
+/// attacker can replay.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum EarlyDataPolicy {
+    /// Answer every early-data request with `425 Too Early`.
+    #[default]
+    Reject,
+    /// Serve early data for idempotent methods only.
+    AllowIdempotent,
+    /// Serve all early data.
+    AllowAll,
+}
+
 /// Quantum-safe cipher suite identifier.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum PqcCipherSuite {
@@ -52,6 +68,7 @@ impl Default for TlsConfig {
             min_tls_version: TlsVersion::Tls13,
             mutual_tls: false,
             hybrid_mode: true,
+            early_data: EarlyDataPolicy::Reject,
         }
     }
 }
@@ -69,6 +86,7 @@ impl TlsConfig {
             min_tls_version: TlsVersion::Tls13,
             mutual_tls: false,
             hybrid_mode: true,
+            early_data: EarlyDataPolicy::Reject,
         }
     }
 
@@ -87,6 +105,20 @@ impl TlsConfig {
         Ok(())
     }
 
+    /// Choose an algorithm from those a peer `offered`.
+    ///
+    /// Picks the mutually supported algorithm with the highest security
+    /// level, breaking ties by position in `preferred_algorithms`. Returns
+    /// `None` when nothing is in common.
+    pub fn select_algorithm(&self, offered: &[Algorithm]) -> Option<Algorithm> {
+        self.preferred_algorithms
+            .iter()
+            .enumerate()
+            .filter(|(_, alg)| offered.contains(alg))
+            .max_by_key(|(rank, alg)| (alg.security_level(), std::cmp::Reverse(*rank)))
+            .map(|(_, alg)| *alg)
+    }
+
     /// Return the list of cipher suites implied by this configuration.
     pub fn cipher_suites(&self) -> Vec<PqcCipherSuite> {
         if self.hybrid_mode {
@@ -116,6 +148,7 @@ pub enum TlsConfigError {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use quantun_types::{MlDsaVariant, MlKemVariant, SlhDsaVariant};
 
     #[test]
     fn default_config_is_valid() {
@@ -144,6 +177,55 @@ mod tests {
         assert!(cfg.validate().is_err());
     }
 
+    fn negotiation_config() -> TlsConfig {
+        TlsConfig {
+            preferred_algorithms: vec![
+                Algorithm::MlKem(MlKemVariant::MlKem768),
+                Algorithm::MlDsa(MlDsaVariant::MlDsa65),
+                Algorithm::MlKem(MlKemVariant::MlKem1024),
+                Algorithm::MlKem(MlKemVariant::MlKem512),
+            ],
+            ..TlsConfig::default()
+        }
+    }
+
+    #[test]
+    fn select_prefers_highest_common_security_level() {
+        let cfg = negotiation_config();
+
+        let offered = [
+            Algorithm::MlKem(MlKemVariant::MlKem512),
+            Algorithm::MlKem(MlKemVariant::MlKem1024),
+            Algorithm::MlKem(MlKemVariant::MlKem768),
+        ];
+        assert_eq!(
+            cfg.select_algorithm(&offered),
+            Some(Algorithm::MlKem(MlKemVariant::MlKem1024))
+        );
+
+        // ML-KEM-768 and ML-DSA-65 are both level 3: server order wins,
+        // regardless of the order the client offered them in.
+        let offered = [
+            Algorithm::MlDsa(MlDsaVariant::MlDsa65),
+            Algorithm::MlKem(MlKemVariant::MlKem768),
+        ];
+        assert_eq!(
+            cfg.select_algorithm(&offered),
+            Some(Algorithm::MlKem(MlKemVariant::MlKem768))
+        );
+    }
+
+    #[test]
+    fn select_with_disjoint_sets_is_none() {
+        let cfg = negotiation_config();
+        let offered = [
+            Algorithm::MlDsa(MlDsaVariant::MlDsa87),
+            Algorithm::SlhDsa(SlhDsaVariant::Sha2_128f),
+        ];
+        assert_eq!(cfg.select_algorithm(&offered), None);
+        assert_eq!(cfg.select_algorithm(&[]), None);
+    }
+
     #[test]
     fn hybrid_cipher_suites() {
         let cfg = TlsConfig::default();
@@ -0,0 +1,281 @@
+//! Fingerprints of TLS ClientHello messages.
+//!
+//! [`TlsFingerprint::from_client_hello`] pulls the offered cipher suites,
+//! extensions, and key exchange groups out of a raw ClientHello
+//! (RFC 8446 §4.1.2) so the gateway can tell PQC-capable clients from
+//! classical-only ones before the handshake completes.
+//! [`TlsFingerprint::ja3_hash`] produces the JA3 hash used by existing
+//! fingerprint databases.
+
+use md5::{Digest, Md5};
+use quantun_crypto::{CryptoError, CryptoResult};
+use serde::{Deserialize, Serialize};
+
+/// Hybrid and pure ML-KEM key exchange groups: `SecP256r1MLKEM768`,
+/// `X25519MLKEM768`, and `SecP384r1MLKEM1024` from
+/// draft-ietf-tls-ecdhe-mlkem, the pre-standard `X25519Kyber768Draft00`,
+/// and `MLKEM512`/`768`/`1024` from draft-connolly-tls-mlkem-key-agreement.
+pub const PQC_GROUPS: [u16; 7] = [0x11EB, 0x11EC, 0x11ED, 0x6399, 0x0200, 0x0201, 0x0202];
+
+const HANDSHAKE_RECORD: u8 = 0x16;
+const CLIENT_HELLO: u8 = 0x01;
+const EXT_SUPPORTED_GROUPS: u16 = 0x000a;
+const EXT_EC_POINT_FORMATS: u16 = 0x000b;
+
+/// What a client offered in its ClientHello, in the order it offered it.
+///
+/// GREASE values (RFC 8701) are kept here and left out of the JA3 hash.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TlsFingerprint {
+    /// `legacy_version`; `0x0303` for TLS 1.2 and 1.3 clients alike.
+    pub legacy_version: u16,
+    pub cipher_suites: Vec<u16>,
+    /// Extension types.
+    pub extensions: Vec<u16>,
+    /// Named groups from the `supported_groups` extension.
+    pub supported_groups: Vec<u16>,
+    /// Formats from the `ec_point_formats` extension.
+    pub ec_point_formats: Vec<u8>,
+    /// Whether any of [`PQC_GROUPS`] is offered.
+    pub has_pqc_groups: bool,
+}
+
+impl TlsFingerprint {
+    /// Parse a ClientHello handshake message, with or without its TLS
+    /// record header. The whole message must be in `bytes`.
+    pub fn from_client_hello(bytes: &[u8]) -> CryptoResult<Self> {
+        let mut input = Reader(bytes);
+        if bytes.first() == Some(&HANDSHAKE_RECORD) {
+            input.take(3, "record header")?;
+            input = Reader(input.vec16("record")?);
+        }
+
+        if input.u8("handshake type")? != CLIENT_HELLO {
+            return Err(malformed("not a ClientHello"));
+        }
+        let len = input.u24("handshake length")?;
+        let mut hello = Reader(input.take(len, "ClientHello body")?);
+
+        let legacy_version = hello.u16("legacy_version")?;
+        hello.take(32, "random")?;
+        hello.vec8("legacy_session_id")?;
+        let cipher_suites = Reader(hello.vec16("cipher_suites")?).u16_list("cipher_suites")?;
+        hello.vec8("legacy_compression_methods")?;
+
+        let mut extensions = Vec::new();
+        let mut supported_groups = Vec::new();
+        let mut ec_point_formats = Vec::new();
+        // Extensions are optional before TLS 1.3.
+        if !hello.0.is_empty() {
+            let mut list = Reader(hello.vec16("extensions")?);
+            while !list.0.is_empty() {
+                let ext_type = list.u16("extension type")?;
+                let mut data = Reader(list.vec16("extension data")?);
+                match ext_type {
+                    EXT_SUPPORTED_GROUPS => {
+                        supported_groups =
+                            Reader(data.vec16("supported_groups")?).u16_list("supported_groups")?;
+                    }
+                    EXT_EC_POINT_FORMATS => {
+                        ec_point_formats = data.vec8("ec_point_formats")?.to_vec();
+                    }
+                    _ => {}
+                }
+                extensions.push(ext_type);
+            }
+        }
+
+        let has_pqc_groups = supported_groups.iter().any(|g| PQC_GROUPS.contains(g));
+        Ok(Self {
+            legacy_version,
+            cipher_suites,
+            extensions,
+            supported_groups,
+            ec_point_formats,
+            has_pqc_groups,
+        })
+    }
+
+    /// The JA3 string: version, ciphers, extensions, groups, and point
+    /// formats in decimal, GREASE values removed.
+    pub fn ja3_string(&self) -> String {
+        fn join<T: Copy + Into<u16>>(values: &[T]) -> String {
+            values
+                .iter()
+                .map(|&v| v.into())
+                .filter(|&v| !is_grease(v))
+                .map(|v| v.to_string())
+                .collect::<Vec<_>>()
+                .join("-")
+        }
+        format!(
+            "{},{},{},{},{}",
+            self.legacy_version,
+            join(&self.cipher_suites),
+            join(&self.extensions),
+            join(&self.supported_groups),
+            join(&self.ec_point_formats),
+        )
+    }
+
+    /// Hex MD5 of [`TlsFingerprint::ja3_string`].
+    pub fn ja3_hash(&self) -> String {
+        Md5::digest(self.ja3_string())
+            .iter()
+            .map(|b| format!("{b:02x}"))
+            .collect()
+    }
+}
+
+/// GREASE values have the form `0x?a?a` with equal bytes.
+fn is_grease(value: u16) -> bool {
+    value & 0x0f0f == 0x0a0a && value >> 8 == value & 0xff
+}
+
+fn malformed(what: &str) -> CryptoError {
+    CryptoError::Serialization(format!("malformed ClientHello: {what}"))
+}
+
+/// Big-endian cursor over a byte slice.
+struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, n: usize, what: &str) -> CryptoResult<&'a [u8]> {
+        if self.0.len() < n {
+            return Err(malformed(&format!("truncated {what}")));
+        }
+        let (head, rest) = self.0.split_at(n);
+        self.0 = rest;
+        Ok(head)
+    }
+
+    fn u8(&mut self, what: &str) -> CryptoResult<u8> {
+        Ok(self.take(1, what)?[0])
+    }
+
+    fn u16(&mut self, what: &str) -> CryptoResult<u16> {
+        let b = self.take(2, what)?;
+        Ok(u16::from_be_bytes([b[0], b[1]]))
+    }
+
+    fn u24(&mut self, what: &str) -> CryptoResult<usize> {
+        let b = self.take(3, what)?;
+        Ok(usize::from(b[0]) << 16 | usize::from(b[1]) << 8 | usize::from(b[2]))
+    }
+
+    /// A vector with a one-byte length prefix.
+    fn vec8(&mut self, what: &str) -> CryptoResult<&'a [u8]> {
+        let len = self.u8(what)?;
+        self.take(len.into(), what)
+    }
+
+    /// A vector with a two-byte length prefix.
+    fn vec16(&mut self, what: &str) -> CryptoResult<&'a [u8]> {
+        let len = self.u16(what)?;
+        self.take(len.into(), what)
+    }
+
+    fn u16_list(mut self, what: &str) -> CryptoResult<Vec<u16>> {
+        if !self.0.len().is_multiple_of(2) {
+            return Err(malformed(&format!("odd-length {what}")));
+        }
+        let mut values = Vec::with_capacity(self.0.len() / 2);
+        while !self.0.is_empty() {
+            values.push(self.u16(what)?);
+        }
+        Ok(values)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// TLS 1.3 ClientHello in a handshake record, offering X25519MLKEM768
+    /// along with GREASE values in the ciphers, extensions, and groups.
+    #[rustfmt::skip]
+    const PQC_HELLO: [u8; 133] = [
+        0x16, 0x03, 0x01, 0x00, 0x80, 0x01, 0x00, 0x00, 0x7c, 0x03, 0x03, 0x11,
+        0x11, 0x11, 0x11, 0x11, 0x11, 0x11, 0x11, 0x11, 0x11, 0x11, 0x11, 0x11,
+        0x11, 0x11, 0x11, 0x11, 0x11, 0x11, 0x11, 0x11, 0x11, 0x11, 0x11, 0x11,
+        0x11, 0x11, 0x11, 0x11, 0x11, 0x11, 0x11, 0x20, 0x22, 0x22, 0x22, 0x22,
+        0x22, 0x22, 0x22, 0x22, 0x22, 0x22, 0x22, 0x22, 0x22, 0x22, 0x22, 0x22,
+        0x22, 0x22, 0x22, 0x22, 0x22, 0x22, 0x22, 0x22, 0x22, 0x22, 0x22, 0x22,
+        0x22, 0x22, 0x22, 0x22, 0x00, 0x08, 0x0a, 0x0a, 0x13, 0x01, 0x13, 0x02,
+        0x13, 0x03, 0x01, 0x00, 0x00, 0x2b, 0x0a, 0x0a, 0x00, 0x00, 0x00, 0x17,
+        0x00, 0x00, 0x00, 0x0a, 0x00, 0x0a, 0x00, 0x08, 0x2a, 0x2a, 0x11, 0xec,
+        0x00, 0x1d, 0x00, 0x17, 0x00, 0x0b, 0x00, 0x02, 0x01, 0x00, 0x00, 0x2b,
+        0x00, 0x03, 0x02, 0x03, 0x04, 0x00, 0x0d, 0x00, 0x04, 0x00, 0x02, 0x04,
+        0x03,
+    ];
+
+    /// Classical TLS 1.2-style ClientHello without a record header, with
+    /// SNI for example.com.
+    #[rustfmt::skip]
+    const CLASSICAL_HELLO: [u8; 122] = [
+        0x01, 0x00, 0x00, 0x76, 0x03, 0x03, 0x11, 0x11, 0x11, 0x11, 0x11, 0x11,
+        0x11, 0x11, 0x11, 0x11, 0x11, 0x11, 0x11, 0x11, 0x11, 0x11, 0x11, 0x11,
+        0x11, 0x11, 0x11, 0x11, 0x11, 0x11, 0x11, 0x11, 0x11, 0x11, 0x11, 0x11,
+        0x11, 0x11, 0x20, 0x22, 0x22, 0x22, 0x22, 0x22, 0x22, 0x22, 0x22, 0x22,
+        0x22, 0x22, 0x22, 0x22, 0x22, 0x22, 0x22, 0x22, 0x22, 0x22, 0x22, 0x22,
+        0x22, 0x22, 0x22, 0x22, 0x22, 0x22, 0x22, 0x22, 0x22, 0x22, 0x22, 0x00,
+        0x06, 0xc0, 0x2b, 0xc0, 0x2f, 0x00, 0x9c, 0x01, 0x00, 0x00, 0x27, 0x00,
+        0x00, 0x00, 0x10, 0x00, 0x0e, 0x00, 0x00, 0x0b, 0x65, 0x78, 0x61, 0x6d,
+        0x70, 0x6c, 0x65, 0x2e, 0x63, 0x6f, 0x6d, 0x00, 0x0a, 0x00, 0x08, 0x00,
+        0x06, 0x00, 0x1d, 0x00, 0x17, 0x00, 0x18, 0x00, 0x0b, 0x00, 0x03, 0x02,
+        0x00, 0x01,
+    ];
+
+    #[test]
+    fn parses_pqc_client_hello() {
+        let fp = TlsFingerprint::from_client_hello(&PQC_HELLO).unwrap();
+        assert_eq!(fp.legacy_version, 0x0303);
+        assert_eq!(fp.cipher_suites, [0x0a0a, 0x1301, 0x1302, 0x1303]);
+        assert_eq!(
+            fp.extensions,
+            [0x0a0a, 0x0017, 0x000a, 0x000b, 0x002b, 0x000d]
+        );
+        assert_eq!(fp.supported_groups, [0x2a2a, 0x11ec, 0x001d, 0x0017]);
+        assert_eq!(fp.ec_point_formats, [0]);
+        assert!(fp.has_pqc_groups);
+        assert_eq!(
+            fp.ja3_string(),
+            "771,4865-4866-4867,23-10-11-43-13,4588-29-23,0"
+        );
+        assert_eq!(fp.ja3_hash(), "b1bb9e493e2476fdb5857dd46a47b1f7");
+    }
+
+    #[test]
+    fn parses_classical_client_hello() {
+        let fp = TlsFingerprint::from_client_hello(&CLASSICAL_HELLO).unwrap();
+        assert_eq!(fp.cipher_suites, [0xc02b, 0xc02f, 0x009c]);
+        assert_eq!(fp.extensions, [0x0000, 0x000a, 0x000b]);
+        assert_eq!(fp.supported_groups, [0x001d, 0x0017, 0x0018]);
+        assert_eq!(fp.ec_point_formats, [0, 1]);
+        assert!(!fp.has_pqc_groups);
+        assert_eq!(fp.ja3_string(), "771,49195-49199-156,0-10-11,29-23-24,0-1");
+        assert_eq!(fp.ja3_hash(), "19460bef180d3419c706f0a6036fdab1");
+    }
+
+    #[test]
+    fn rejects_malformed_input() {
+        for len in [0, 4, 9, 60, PQC_HELLO.len() - 1] {
+            assert!(
+                TlsFingerprint::from_client_hello(&PQC_HELLO[..len]).is_err(),
+                "accepted {len} bytes"
+            );
+        }
+        // ServerHello handshake type.
+        let mut server_hello = CLASSICAL_HELLO;
+        server_hello[0] = 0x02;
+        assert!(TlsFingerprint::from_client_hello(&server_hello).is_err());
+    }
+
+    #[test]
+    fn grease_values() {
+        assert!(is_grease(0x0a0a));
+        assert!(is_grease(0xfafa));
+        assert!(!is_grease(0x0a1a));
+        assert!(!is_grease(0x1301));
+    }
+}
@@ -1,3 +1,5 @@
 pub mod config;
+pub mod fingerprint;
 
 pub use config::{PqcCipherSuite, TlsConfig, TlsConfigError, TlsVersion};
+pub use fingerprint::TlsFingerprint;
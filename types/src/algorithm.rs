@@ -1,5 +1,9 @@
+use crate::classical::ClassicalAlgorithm;
 use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
 use std::fmt;
+use std::str::FromStr;
+use thiserror::Error;
 
 /// Post-quantum key encapsulation mechanism variants (FIPS 203).
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
@@ -19,6 +23,7 @@ pub enum MlDsaVariant {
 
 /// Stateless hash-based digital signature variants (FIPS 205).
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[allow(non_camel_case_types)]
 pub enum SlhDsaVariant {
     Sha2_128s,
     Sha2_128f,
@@ -26,6 +31,12 @@ pub enum SlhDsaVariant {
     Sha2_192f,
     Sha2_256s,
     Sha2_256f,
+    Shake_128s,
+    Shake_128f,
+    Shake_192s,
+    Shake_192f,
+    Shake_256s,
+    Shake_256f,
 }
 
 /// Hybrid algorithms combining classical and post-quantum schemes.
@@ -62,7 +73,75 @@ pub enum KeyUsage {
     Wrap,
 }
 
+/// NIST post-quantum security category (1 through 5).
+///
+/// Ordered, so levels can be compared directly; [`SecurityLevel::meets`]
+/// reads better at policy call sites.
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize,
+)]
+#[serde(transparent)]
+pub struct SecurityLevel(u8);
+
+impl SecurityLevel {
+    pub const LEVEL_1: SecurityLevel = SecurityLevel(1);
+    pub const LEVEL_2: SecurityLevel = SecurityLevel(2);
+    pub const LEVEL_3: SecurityLevel = SecurityLevel(3);
+    pub const LEVEL_4: SecurityLevel = SecurityLevel(4);
+    pub const LEVEL_5: SecurityLevel = SecurityLevel(5);
+
+    /// Construct a level, returning `None` outside 1..=5.
+    pub fn new(level: u8) -> Option<Self> {
+        (1..=5).contains(&level).then_some(SecurityLevel(level))
+    }
+
+    /// The raw level number.
+    pub fn as_u8(&self) -> u8 {
+        self.0
+    }
+
+    /// Whether this level is at least `required`.
+    pub fn meets(&self, required: SecurityLevel) -> bool {
+        *self >= required
+    }
+}
+
+impl fmt::Display for SecurityLevel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "NIST Level {}", self.0)
+    }
+}
+
+/// Error returned when parsing an unknown algorithm name.
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+#[error("unknown algorithm: {0}")]
+pub struct ParseAlgorithmError(pub String);
+
 impl Algorithm {
+    /// Every algorithm variant, in declaration order.
+    pub const ALL: [Algorithm; 20] = [
+        Algorithm::MlKem(MlKemVariant::MlKem512),
+        Algorithm::MlKem(MlKemVariant::MlKem768),
+        Algorithm::MlKem(MlKemVariant::MlKem1024),
+        Algorithm::MlDsa(MlDsaVariant::MlDsa44),
+        Algorithm::MlDsa(MlDsaVariant::MlDsa65),
+        Algorithm::MlDsa(MlDsaVariant::MlDsa87),
+        Algorithm::SlhDsa(SlhDsaVariant::Sha2_128s),
+        Algorithm::SlhDsa(SlhDsaVariant::Sha2_128f),
+        Algorithm::SlhDsa(SlhDsaVariant::Sha2_192s),
+        Algorithm::SlhDsa(SlhDsaVariant::Sha2_192f),
+        Algorithm::SlhDsa(SlhDsaVariant::Sha2_256s),
+        Algorithm::SlhDsa(SlhDsaVariant::Sha2_256f),
+        Algorithm::SlhDsa(SlhDsaVariant::Shake_128s),
+        Algorithm::SlhDsa(SlhDsaVariant::Shake_128f),
+        Algorithm::SlhDsa(SlhDsaVariant::Shake_192s),
+        Algorithm::SlhDsa(SlhDsaVariant::Shake_192f),
+        Algorithm::SlhDsa(SlhDsaVariant::Shake_256s),
+        Algorithm::SlhDsa(SlhDsaVariant::Shake_256f),
+        Algorithm::Hybrid(HybridVariant::X25519MlKem768),
+        Algorithm::Hybrid(HybridVariant::Ed25519MlDsa65),
+    ];
+
     /// Returns the key type implied by this algorithm.
     pub fn key_type(&self) -> KeyType {
         match self {
@@ -74,21 +153,123 @@ impl Algorithm {
     }
 
     /// NIST security level (1 through 5).
-    pub fn security_level(&self) -> u8 {
+    ///
+    /// A hybrid's level is that of its PQC component: the classical half
+    /// contributes nothing against a quantum adversary.
+    pub fn security_level(&self) -> SecurityLevel {
         match self {
-            Algorithm::MlKem(MlKemVariant::MlKem512) => 1,
-            Algorithm::MlKem(MlKemVariant::MlKem768) => 3,
-            Algorithm::MlKem(MlKemVariant::MlKem1024) => 5,
-            Algorithm::MlDsa(MlDsaVariant::MlDsa44) => 2,
-            Algorithm::MlDsa(MlDsaVariant::MlDsa65) => 3,
-            Algorithm::MlDsa(MlDsaVariant::MlDsa87) => 5,
+            Algorithm::MlKem(MlKemVariant::MlKem512) => SecurityLevel::LEVEL_1,
+            Algorithm::MlKem(MlKemVariant::MlKem768) => SecurityLevel::LEVEL_3,
+            Algorithm::MlKem(MlKemVariant::MlKem1024) => SecurityLevel::LEVEL_5,
+            Algorithm::MlDsa(MlDsaVariant::MlDsa44) => SecurityLevel::LEVEL_2,
+            Algorithm::MlDsa(MlDsaVariant::MlDsa65) => SecurityLevel::LEVEL_3,
+            Algorithm::MlDsa(MlDsaVariant::MlDsa87) => SecurityLevel::LEVEL_5,
             Algorithm::SlhDsa(v) => match v {
-                SlhDsaVariant::Sha2_128s | SlhDsaVariant::Sha2_128f => 1,
-                SlhDsaVariant::Sha2_192s | SlhDsaVariant::Sha2_192f => 3,
-                SlhDsaVariant::Sha2_256s | SlhDsaVariant::Sha2_256f => 5,
+                SlhDsaVariant::Sha2_128s
+                | SlhDsaVariant::Sha2_128f
+                | SlhDsaVariant::Shake_128s
+                | SlhDsaVariant::Shake_128f => SecurityLevel::LEVEL_1,
+                SlhDsaVariant::Sha2_192s
+                | SlhDsaVariant::Sha2_192f
+                | SlhDsaVariant::Shake_192s
+                | SlhDsaVariant::Shake_192f => SecurityLevel::LEVEL_3,
+                SlhDsaVariant::Sha2_256s
+                | SlhDsaVariant::Sha2_256f
+                | SlhDsaVariant::Shake_256s
+                | SlhDsaVariant::Shake_256f => SecurityLevel::LEVEL_5,
             },
-            Algorithm::Hybrid(HybridVariant::X25519MlKem768) => 3,
-            Algorithm::Hybrid(HybridVariant::Ed25519MlDsa65) => 3,
+            Algorithm::Hybrid(v) => v.components().1.security_level(),
+        }
+    }
+
+    /// Conservative maximum age, in days, before a key should be rotated
+    /// (cryptoperiod guidance after NIST SP 800-57 and SP 800-131A).
+    ///
+    /// SLH-DSA keys get the shortest period since their security rests on
+    /// hash function margins, which may erode. A hybrid follows its PQC
+    /// component.
+    pub fn recommended_max_key_age_days(&self) -> u32 {
+        match self {
+            Algorithm::MlKem(MlKemVariant::MlKem512) => 365,
+            Algorithm::MlKem(MlKemVariant::MlKem768) => 730,
+            Algorithm::MlKem(MlKemVariant::MlKem1024) => 1095,
+            Algorithm::MlDsa(MlDsaVariant::MlDsa44) => 365,
+            Algorithm::MlDsa(MlDsaVariant::MlDsa65) => 730,
+            Algorithm::MlDsa(MlDsaVariant::MlDsa87) => 1095,
+            Algorithm::SlhDsa(_) => 365,
+            Algorithm::Hybrid(v) => v.components().1.recommended_max_key_age_days(),
+        }
+    }
+
+    /// Whether this algorithm's security level is at least `level`.
+    pub fn at_least_level(&self, level: SecurityLevel) -> bool {
+        self.security_level().meets(level)
+    }
+
+    /// Compare by security level. At equal levels a hybrid is preferred,
+    /// since its classical half still protects against a break of the
+    /// lattice assumptions, then SLH-DSA, whose security rests only on the
+    /// hash function, then ML-KEM and ML-DSA. Otherwise-equal algorithms,
+    /// such as ML-KEM-768 and ML-DSA-65, compare equal.
+    pub fn compare_strength(&self, other: &Algorithm) -> Ordering {
+        let rank = |alg: &Algorithm| match alg {
+            Algorithm::MlKem(_) | Algorithm::MlDsa(_) => 0,
+            Algorithm::SlhDsa(_) => 1,
+            Algorithm::Hybrid(_) => 2,
+        };
+        self.security_level()
+            .cmp(&other.security_level())
+            .then_with(|| rank(self).cmp(&rank(other)))
+    }
+
+    /// Whether new keys may be generated for this algorithm. Every
+    /// FIPS 203/204/205 variant, and every hybrid built on one, is approved.
+    pub fn is_approved_for_new_keys(&self) -> bool {
+        true
+    }
+
+    /// Whether the algorithm is a finalized NIST standard. Hybrids follow
+    /// IETF drafts and are not.
+    pub fn is_standardized(&self) -> bool {
+        self.standardization_reference().is_some()
+    }
+
+    /// The standard defining the algorithm, e.g. `FIPS 203`, or `None` for
+    /// draft-based hybrids.
+    pub fn standardization_reference(&self) -> Option<&'static str> {
+        match self {
+            Algorithm::MlKem(_) => Some("FIPS 203"),
+            Algorithm::MlDsa(_) => Some("FIPS 204"),
+            Algorithm::SlhDsa(_) => Some("FIPS 205"),
+            Algorithm::Hybrid(_) => None,
+        }
+    }
+
+    /// Why the algorithm is deprecated for new use, or `None` if it is not.
+    /// Deprecated algorithms still work but are warned about when
+    /// configured or negotiated.
+    pub fn deprecation_status(&self) -> Option<&'static str> {
+        match self {
+            Algorithm::MlKem(MlKemVariant::MlKem512) => {
+                Some("ML-KEM-512 only reaches NIST security level 1; use ML-KEM-768 or ML-KEM-1024")
+            }
+            _ => None,
+        }
+    }
+}
+
+impl HybridVariant {
+    /// The (classical, post-quantum) algorithms combined by this hybrid.
+    pub fn components(&self) -> (ClassicalAlgorithm, Algorithm) {
+        match self {
+            HybridVariant::X25519MlKem768 => (
+                ClassicalAlgorithm::X25519,
+                Algorithm::MlKem(MlKemVariant::MlKem768),
+            ),
+            HybridVariant::Ed25519MlDsa65 => (
+                ClassicalAlgorithm::Ed25519,
+                Algorithm::MlDsa(MlDsaVariant::MlDsa65),
+            ),
         }
     }
 }
@@ -104,6 +285,18 @@ impl fmt::Display for Algorithm {
     }
 }
 
+impl FromStr for Algorithm {
+    type Err = ParseAlgorithmError;
+
+    /// Parse a display name such as `ML-DSA-65`, case-insensitively.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Algorithm::ALL
+            .into_iter()
+            .find(|a| a.to_string().eq_ignore_ascii_case(s.trim()))
+            .ok_or_else(|| ParseAlgorithmError(s.to_string()))
+    }
+}
+
 impl MlKemVariant {
     /// Returns (public_key_bytes, secret_key_bytes) per NIST spec.
     pub fn key_sizes(&self) -> (usize, usize) {
@@ -148,21 +341,30 @@ impl SlhDsaVariant {
     /// Returns (public_key_bytes, secret_key_bytes) per NIST spec.
     pub fn key_sizes(&self) -> (usize, usize) {
         match self {
-            SlhDsaVariant::Sha2_128s | SlhDsaVariant::Sha2_128f => (32, 64),
-            SlhDsaVariant::Sha2_192s | SlhDsaVariant::Sha2_192f => (48, 96),
-            SlhDsaVariant::Sha2_256s | SlhDsaVariant::Sha2_256f => (64, 128),
+            SlhDsaVariant::Sha2_128s
+            | SlhDsaVariant::Sha2_128f
+            | SlhDsaVariant::Shake_128s
+            | SlhDsaVariant::Shake_128f => (32, 64),
+            SlhDsaVariant::Sha2_192s
+            | SlhDsaVariant::Sha2_192f
+            | SlhDsaVariant::Shake_192s
+            | SlhDsaVariant::Shake_192f => (48, 96),
+            SlhDsaVariant::Sha2_256s
+            | SlhDsaVariant::Sha2_256f
+            | SlhDsaVariant::Shake_256s
+            | SlhDsaVariant::Shake_256f => (64, 128),
         }
     }
 
     /// Signature size in bytes. "s" variants are small/slow, "f" are fast/large.
     pub fn signature_size(&self) -> usize {
         match self {
-            SlhDsaVariant::Sha2_128s => 7856,
-            SlhDsaVariant::Sha2_128f => 17088,
-            SlhDsaVariant::Sha2_192s => 16224,
-            SlhDsaVariant::Sha2_192f => 35664,
-            SlhDsaVariant::Sha2_256s => 29792,
-            SlhDsaVariant::Sha2_256f => 49856,
+            SlhDsaVariant::Sha2_128s | SlhDsaVariant::Shake_128s => 7856,
+            SlhDsaVariant::Sha2_128f | SlhDsaVariant::Shake_128f => 17088,
+            SlhDsaVariant::Sha2_192s | SlhDsaVariant::Shake_192s => 16224,
+            SlhDsaVariant::Sha2_192f | SlhDsaVariant::Shake_192f => 35664,
+            SlhDsaVariant::Sha2_256s | SlhDsaVariant::Shake_256s => 29792,
+            SlhDsaVariant::Sha2_256f | SlhDsaVariant::Shake_256f => 49856,
         }
     }
 
@@ -170,7 +372,12 @@ impl SlhDsaVariant {
     pub fn is_small(&self) -> bool {
         matches!(
             self,
-            SlhDsaVariant::Sha2_128s | SlhDsaVariant::Sha2_192s | SlhDsaVariant::Sha2_256s
+            SlhDsaVariant::Sha2_128s
+                | SlhDsaVariant::Sha2_192s
+                | SlhDsaVariant::Sha2_256s
+                | SlhDsaVariant::Shake_128s
+                | SlhDsaVariant::Shake_192s
+                | SlhDsaVariant::Shake_256s
         )
     }
 }
@@ -204,6 +411,12 @@ impl fmt::Display for SlhDsaVariant {
             SlhDsaVariant::Sha2_192f => write!(f, "SLH-DSA-SHA2-192f"),
             SlhDsaVariant::Sha2_256s => write!(f, "SLH-DSA-SHA2-256s"),
             SlhDsaVariant::Sha2_256f => write!(f, "SLH-DSA-SHA2-256f"),
+            SlhDsaVariant::Shake_128s => write!(f, "SLH-DSA-SHAKE-128s"),
+            SlhDsaVariant::Shake_128f => write!(f, "SLH-DSA-SHAKE-128f"),
+            SlhDsaVariant::Shake_192s => write!(f, "SLH-DSA-SHAKE-192s"),
+            SlhDsaVariant::Shake_192f => write!(f, "SLH-DSA-SHAKE-192f"),
+            SlhDsaVariant::Shake_256s => write!(f, "SLH-DSA-SHAKE-256s"),
+            SlhDsaVariant::Shake_256f => write!(f, "SLH-DSA-SHAKE-256f"),
         }
     }
 }
@@ -223,10 +436,107 @@ mod tests {
 
     #[test]
     fn algorithm_security_levels() {
-        assert_eq!(Algorithm::MlKem(MlKemVariant::MlKem512).security_level(), 1);
-        assert_eq!(Algorithm::MlKem(MlKemVariant::MlKem768).security_level(), 3);
-        assert_eq!(Algorithm::MlKem(MlKemVariant::MlKem1024).security_level(), 5);
-        assert_eq!(Algorithm::MlDsa(MlDsaVariant::MlDsa87).security_level(), 5);
+        assert_eq!(Algorithm::MlKem(MlKemVariant::MlKem512).security_level().as_u8(), 1);
+        assert_eq!(Algorithm::MlKem(MlKemVariant::MlKem768).security_level().as_u8(), 3);
+        assert_eq!(Algorithm::MlKem(MlKemVariant::MlKem1024).security_level().as_u8(), 5);
+        assert_eq!(Algorithm::MlDsa(MlDsaVariant::MlDsa87).security_level().as_u8(), 5);
+    }
+
+    #[test]
+    fn recommended_max_key_ages() {
+        let expected = [
+            (Algorithm::MlKem(MlKemVariant::MlKem512), 365),
+            (Algorithm::MlKem(MlKemVariant::MlKem768), 730),
+            (Algorithm::MlKem(MlKemVariant::MlKem1024), 1095),
+            (Algorithm::MlDsa(MlDsaVariant::MlDsa44), 365),
+            (Algorithm::MlDsa(MlDsaVariant::MlDsa65), 730),
+            (Algorithm::MlDsa(MlDsaVariant::MlDsa87), 1095),
+            (Algorithm::SlhDsa(SlhDsaVariant::Sha2_128s), 365),
+            (Algorithm::SlhDsa(SlhDsaVariant::Sha2_128f), 365),
+            (Algorithm::SlhDsa(SlhDsaVariant::Sha2_192s), 365),
+            (Algorithm::SlhDsa(SlhDsaVariant::Sha2_192f), 365),
+            (Algorithm::SlhDsa(SlhDsaVariant::Sha2_256s), 365),
+            (Algorithm::SlhDsa(SlhDsaVariant::Sha2_256f), 365),
+            (Algorithm::Hybrid(HybridVariant::X25519MlKem768), 730),
+            (Algorithm::Hybrid(HybridVariant::Ed25519MlDsa65), 730),
+        ];
+        for (alg, days) in expected {
+            assert_eq!(alg.recommended_max_key_age_days(), days, "{alg}");
+            assert!(alg.is_approved_for_new_keys(), "{alg}");
+        }
+    }
+
+    #[test]
+    fn security_level_ordering() {
+        assert!(SecurityLevel::LEVEL_1 < SecurityLevel::LEVEL_3);
+        assert!(SecurityLevel::LEVEL_5.meets(SecurityLevel::LEVEL_3));
+        assert!(SecurityLevel::LEVEL_3.meets(SecurityLevel::LEVEL_3));
+        assert!(!SecurityLevel::LEVEL_2.meets(SecurityLevel::LEVEL_3));
+        assert_eq!(SecurityLevel::new(4), Some(SecurityLevel::LEVEL_4));
+        assert_eq!(SecurityLevel::new(0), None);
+        assert_eq!(SecurityLevel::new(6), None);
+    }
+
+    #[test]
+    fn compare_strength() {
+        let kem512 = Algorithm::MlKem(MlKemVariant::MlKem512);
+        let kem768 = Algorithm::MlKem(MlKemVariant::MlKem768);
+        let kem1024 = Algorithm::MlKem(MlKemVariant::MlKem1024);
+        assert_eq!(kem1024.compare_strength(&kem512), Ordering::Greater);
+        assert_eq!(kem512.compare_strength(&kem1024), Ordering::Less);
+        assert!(kem1024.at_least_level(SecurityLevel::LEVEL_5));
+        assert!(kem768.at_least_level(SecurityLevel::LEVEL_3));
+        assert!(!kem512.at_least_level(SecurityLevel::LEVEL_3));
+
+        // Level 3 ties: hybrid, then SLH-DSA, then ML-KEM and ML-DSA.
+        let hybrid = Algorithm::Hybrid(HybridVariant::X25519MlKem768);
+        let slh = Algorithm::SlhDsa(SlhDsaVariant::Sha2_192s);
+        let dsa65 = Algorithm::MlDsa(MlDsaVariant::MlDsa65);
+        assert_eq!(hybrid.compare_strength(&kem768), Ordering::Greater);
+        assert_eq!(slh.compare_strength(&kem768), Ordering::Greater);
+        assert_eq!(hybrid.compare_strength(&slh), Ordering::Greater);
+        assert_eq!(kem768.compare_strength(&dsa65), Ordering::Equal);
+        assert_eq!(
+            slh.compare_strength(&Algorithm::SlhDsa(SlhDsaVariant::Sha2_192f)),
+            Ordering::Equal
+        );
+        // Level still dominates the preference.
+        assert_eq!(hybrid.compare_strength(&kem1024), Ordering::Less);
+
+        let mut algs = vec![hybrid, kem1024, kem512, dsa65, slh];
+        algs.sort_by(|a, b| b.compare_strength(a));
+        assert_eq!(algs, [kem1024, hybrid, slh, dsa65, kem512]);
+    }
+
+    #[test]
+    fn security_level_display() {
+        assert_eq!(SecurityLevel::LEVEL_3.to_string(), "NIST Level 3");
+    }
+
+    #[test]
+    fn hybrid_level_follows_pqc_component() {
+        for variant in [HybridVariant::X25519MlKem768, HybridVariant::Ed25519MlDsa65] {
+            let (_, pqc) = variant.components();
+            assert_eq!(Algorithm::Hybrid(variant).security_level(), pqc.security_level());
+        }
+    }
+
+    #[test]
+    fn hybrid_components() {
+        assert_eq!(
+            HybridVariant::X25519MlKem768.components(),
+            (
+                ClassicalAlgorithm::X25519,
+                Algorithm::MlKem(MlKemVariant::MlKem768)
+            )
+        );
+        assert_eq!(
+            HybridVariant::Ed25519MlDsa65.components(),
+            (
+                ClassicalAlgorithm::Ed25519,
+                Algorithm::MlDsa(MlDsaVariant::MlDsa65)
+            )
+        );
     }
 
     #[test]
@@ -256,4 +566,64 @@ mod tests {
             "X25519-ML-KEM-768"
         );
     }
+
+    #[test]
+    fn algorithm_from_str_round_trips_display() {
+        for alg in Algorithm::ALL {
+            assert_eq!(alg.to_string().parse::<Algorithm>(), Ok(alg));
+        }
+        assert_eq!(
+            " slh-dsa-sha2-128F ".parse(),
+            Ok(Algorithm::SlhDsa(SlhDsaVariant::Sha2_128f))
+        );
+        assert_eq!(
+            "ML-DSA-66".parse::<Algorithm>(),
+            Err(ParseAlgorithmError("ML-DSA-66".into()))
+        );
+    }
+
+    #[test]
+    fn standardized_algorithms_have_references() {
+        for v in [
+            SlhDsaVariant::Sha2_128s,
+            SlhDsaVariant::Sha2_128f,
+            SlhDsaVariant::Sha2_192s,
+            SlhDsaVariant::Sha2_192f,
+            SlhDsaVariant::Sha2_256s,
+            SlhDsaVariant::Sha2_256f,
+            SlhDsaVariant::Shake_128s,
+            SlhDsaVariant::Shake_128f,
+            SlhDsaVariant::Shake_192s,
+            SlhDsaVariant::Shake_192f,
+            SlhDsaVariant::Shake_256s,
+            SlhDsaVariant::Shake_256f,
+        ] {
+            assert!(Algorithm::SlhDsa(v).is_standardized());
+        }
+        for v in [HybridVariant::X25519MlKem768, HybridVariant::Ed25519MlDsa65] {
+            assert!(!Algorithm::Hybrid(v).is_standardized());
+            assert_eq!(Algorithm::Hybrid(v).standardization_reference(), None);
+        }
+        for alg in Algorithm::ALL.iter().filter(|a| a.is_standardized()) {
+            assert!(!alg.standardization_reference().unwrap().is_empty());
+        }
+        assert_eq!(
+            Algorithm::MlKem(MlKemVariant::MlKem768).standardization_reference(),
+            Some("FIPS 203")
+        );
+    }
+
+    #[test]
+    fn deprecated_algorithms_give_a_reason() {
+        let status = Algorithm::MlKem(MlKemVariant::MlKem512).deprecation_status();
+        assert!(status.unwrap().contains("level 1"));
+        assert_eq!(
+            Algorithm::MlKem(MlKemVariant::MlKem768).deprecation_status(),
+            None
+        );
+        assert_eq!(
+            Algorithm::Hybrid(HybridVariant::X25519MlKem768).deprecation_status(),
+            None
+        );
+    }
 }
@@ -0,0 +1,299 @@
+use crate::algorithm::{Algorithm, HybridVariant, MlDsaVariant, MlKemVariant};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+use thiserror::Error;
+
+/// Classical (pre-quantum) algorithms that deployments are migrating away from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum ClassicalAlgorithm {
+    Rsa2048,
+    Rsa3072,
+    Rsa4096,
+    EcdsaP256,
+    EcdsaP384,
+    Ed25519,
+    X25519,
+    Dh2048,
+    Dh3072,
+    Aes128,
+    Aes256,
+    Sha1,
+    Sha256,
+}
+
+/// Algorithm from either family, as reported by scanners and inventories.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum AnyAlgorithm {
+    Pqc(Algorithm),
+    Classical(ClassicalAlgorithm),
+}
+
+/// Error returned when parsing an unknown classical algorithm name.
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+#[error("unknown classical algorithm: {0}")]
+pub struct ParseClassicalAlgorithmError(pub String);
+
+impl ClassicalAlgorithm {
+    /// All classical algorithms, in declaration order.
+    pub const ALL: [ClassicalAlgorithm; 13] = [
+        ClassicalAlgorithm::Rsa2048,
+        ClassicalAlgorithm::Rsa3072,
+        ClassicalAlgorithm::Rsa4096,
+        ClassicalAlgorithm::EcdsaP256,
+        ClassicalAlgorithm::EcdsaP384,
+        ClassicalAlgorithm::Ed25519,
+        ClassicalAlgorithm::X25519,
+        ClassicalAlgorithm::Dh2048,
+        ClassicalAlgorithm::Dh3072,
+        ClassicalAlgorithm::Aes128,
+        ClassicalAlgorithm::Aes256,
+        ClassicalAlgorithm::Sha1,
+        ClassicalAlgorithm::Sha256,
+    ];
+
+    /// Whether this is a public-key algorithm (broken outright by Shor's algorithm).
+    pub fn is_asymmetric(&self) -> bool {
+        !matches!(
+            self,
+            ClassicalAlgorithm::Aes128
+                | ClassicalAlgorithm::Aes256
+                | ClassicalAlgorithm::Sha1
+                | ClassicalAlgorithm::Sha256
+        )
+    }
+
+    /// Classical security strength in bits (NIST SP 800-57 Part 1).
+    ///
+    /// Hash functions report collision resistance.
+    pub fn classical_security_bits(&self) -> u16 {
+        match self {
+            ClassicalAlgorithm::Rsa2048 | ClassicalAlgorithm::Dh2048 => 112,
+            ClassicalAlgorithm::Rsa3072 | ClassicalAlgorithm::Dh3072 => 128,
+            ClassicalAlgorithm::Rsa4096 => 152,
+            ClassicalAlgorithm::EcdsaP256
+            | ClassicalAlgorithm::Ed25519
+            | ClassicalAlgorithm::X25519 => 128,
+            ClassicalAlgorithm::EcdsaP384 => 192,
+            ClassicalAlgorithm::Aes128 => 128,
+            ClassicalAlgorithm::Aes256 => 256,
+            ClassicalAlgorithm::Sha1 => 80,
+            ClassicalAlgorithm::Sha256 => 128,
+        }
+    }
+
+    /// Effective security strength against a quantum adversary, in bits.
+    ///
+    /// Asymmetric algorithms drop to zero (Shor); symmetric ciphers and
+    /// hashes are halved (Grover).
+    pub fn quantum_security_bits(&self) -> u16 {
+        if self.is_asymmetric() {
+            0
+        } else {
+            self.classical_security_bits() / 2
+        }
+    }
+
+    /// Whether a quantum adversary breaks this algorithm in practice.
+    ///
+    /// Asymmetric algorithms are always vulnerable. Symmetric ciphers and
+    /// hashes are vulnerable when their halved strength falls below that of
+    /// AES-128 under Grover search (64 bits), the NIST PQC category 1 floor.
+    pub fn is_quantum_vulnerable(&self) -> bool {
+        self.is_asymmetric() || self.quantum_security_bits() < 64
+    }
+
+    /// The post-quantum algorithm that should replace this one, if any.
+    ///
+    /// Symmetric ciphers and hashes have no PQC replacement; they are
+    /// addressed by increasing key or digest sizes instead.
+    pub fn recommended_replacement(&self) -> Option<Algorithm> {
+        match self {
+            ClassicalAlgorithm::Rsa2048 | ClassicalAlgorithm::EcdsaP256 => {
+                Some(Algorithm::MlDsa(MlDsaVariant::MlDsa44))
+            }
+            ClassicalAlgorithm::Rsa3072
+            | ClassicalAlgorithm::Rsa4096
+            | ClassicalAlgorithm::EcdsaP384 => Some(Algorithm::MlDsa(MlDsaVariant::MlDsa65)),
+            ClassicalAlgorithm::Ed25519 => Some(Algorithm::Hybrid(HybridVariant::Ed25519MlDsa65)),
+            ClassicalAlgorithm::X25519 => Some(Algorithm::Hybrid(HybridVariant::X25519MlKem768)),
+            ClassicalAlgorithm::Dh2048 | ClassicalAlgorithm::Dh3072 => {
+                Some(Algorithm::MlKem(MlKemVariant::MlKem768))
+            }
+            ClassicalAlgorithm::Aes128
+            | ClassicalAlgorithm::Aes256
+            | ClassicalAlgorithm::Sha1
+            | ClassicalAlgorithm::Sha256 => None,
+        }
+    }
+
+    /// Canonical display name.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ClassicalAlgorithm::Rsa2048 => "RSA-2048",
+            ClassicalAlgorithm::Rsa3072 => "RSA-3072",
+            ClassicalAlgorithm::Rsa4096 => "RSA-4096",
+            ClassicalAlgorithm::EcdsaP256 => "ECDSA-P256",
+            ClassicalAlgorithm::EcdsaP384 => "ECDSA-P384",
+            ClassicalAlgorithm::Ed25519 => "Ed25519",
+            ClassicalAlgorithm::X25519 => "X25519",
+            ClassicalAlgorithm::Dh2048 => "DH-2048",
+            ClassicalAlgorithm::Dh3072 => "DH-3072",
+            ClassicalAlgorithm::Aes128 => "AES-128",
+            ClassicalAlgorithm::Aes256 => "AES-256",
+            ClassicalAlgorithm::Sha1 => "SHA-1",
+            ClassicalAlgorithm::Sha256 => "SHA-256",
+        }
+    }
+}
+
+impl fmt::Display for ClassicalAlgorithm {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl FromStr for ClassicalAlgorithm {
+    type Err = ParseClassicalAlgorithmError;
+
+    /// Parse a canonical name, case-insensitively.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        ClassicalAlgorithm::ALL
+            .into_iter()
+            .find(|a| a.as_str().eq_ignore_ascii_case(s.trim()))
+            .ok_or_else(|| ParseClassicalAlgorithmError(s.to_string()))
+    }
+}
+
+impl AnyAlgorithm {
+    /// Whether this algorithm is vulnerable to a quantum adversary.
+    pub fn is_quantum_vulnerable(&self) -> bool {
+        match self {
+            AnyAlgorithm::Pqc(_) => false,
+            AnyAlgorithm::Classical(c) => c.is_quantum_vulnerable(),
+        }
+    }
+
+    /// Whether this is a post-quantum algorithm.
+    pub fn is_pqc(&self) -> bool {
+        matches!(self, AnyAlgorithm::Pqc(_))
+    }
+
+    /// Whether new keys may be generated for this algorithm. Classical
+    /// algorithms are never approved; existing keys should be migrated.
+    pub fn is_approved_for_new_keys(&self) -> bool {
+        match self {
+            AnyAlgorithm::Pqc(a) => a.is_approved_for_new_keys(),
+            AnyAlgorithm::Classical(_) => false,
+        }
+    }
+}
+
+impl From<Algorithm> for AnyAlgorithm {
+    fn from(alg: Algorithm) -> Self {
+        AnyAlgorithm::Pqc(alg)
+    }
+}
+
+impl From<ClassicalAlgorithm> for AnyAlgorithm {
+    fn from(alg: ClassicalAlgorithm) -> Self {
+        AnyAlgorithm::Classical(alg)
+    }
+}
+
+impl fmt::Display for AnyAlgorithm {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AnyAlgorithm::Pqc(a) => write!(f, "{a}"),
+            AnyAlgorithm::Classical(a) => write!(f, "{a}"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::algorithm::SecurityLevel;
+
+    #[test]
+    fn asymmetric_algorithms_are_quantum_vulnerable() {
+        for alg in ClassicalAlgorithm::ALL.into_iter().filter(|a| a.is_asymmetric()) {
+            assert!(alg.is_quantum_vulnerable(), "{alg} should be vulnerable");
+            assert_eq!(alg.quantum_security_bits(), 0);
+        }
+    }
+
+    #[test]
+    fn symmetric_strength_is_halved() {
+        assert_eq!(ClassicalAlgorithm::Aes128.quantum_security_bits(), 64);
+        assert_eq!(ClassicalAlgorithm::Aes256.quantum_security_bits(), 128);
+        assert!(!ClassicalAlgorithm::Aes128.is_quantum_vulnerable());
+        assert!(!ClassicalAlgorithm::Aes256.is_quantum_vulnerable());
+        assert!(!ClassicalAlgorithm::Sha256.is_quantum_vulnerable());
+        assert!(ClassicalAlgorithm::Sha1.is_quantum_vulnerable());
+    }
+
+    #[test]
+    fn replacement_mapping() {
+        assert_eq!(
+            ClassicalAlgorithm::X25519.recommended_replacement(),
+            Some(Algorithm::Hybrid(HybridVariant::X25519MlKem768))
+        );
+        assert_eq!(
+            ClassicalAlgorithm::Ed25519.recommended_replacement(),
+            Some(Algorithm::Hybrid(HybridVariant::Ed25519MlDsa65))
+        );
+        assert_eq!(
+            ClassicalAlgorithm::Rsa2048.recommended_replacement(),
+            Some(Algorithm::MlDsa(MlDsaVariant::MlDsa44))
+        );
+        assert_eq!(
+            ClassicalAlgorithm::EcdsaP384.recommended_replacement(),
+            Some(Algorithm::MlDsa(MlDsaVariant::MlDsa65))
+        );
+        assert_eq!(
+            ClassicalAlgorithm::Dh2048.recommended_replacement(),
+            Some(Algorithm::MlKem(MlKemVariant::MlKem768))
+        );
+        assert_eq!(ClassicalAlgorithm::Aes256.recommended_replacement(), None);
+        assert_eq!(ClassicalAlgorithm::Sha1.recommended_replacement(), None);
+    }
+
+    #[test]
+    fn every_vulnerable_asymmetric_algorithm_has_a_replacement() {
+        for alg in ClassicalAlgorithm::ALL.into_iter().filter(|a| a.is_asymmetric()) {
+            let replacement = alg.recommended_replacement().unwrap();
+            assert!(
+                replacement.security_level().meets(SecurityLevel::LEVEL_2),
+                "{alg} replacement {replacement} is too weak"
+            );
+        }
+    }
+
+    #[test]
+    fn display_from_str_round_trip() {
+        for alg in ClassicalAlgorithm::ALL {
+            assert_eq!(alg.to_string().parse::<ClassicalAlgorithm>().unwrap(), alg);
+        }
+        assert_eq!(
+            "rsa-2048".parse::<ClassicalAlgorithm>().unwrap(),
+            ClassicalAlgorithm::Rsa2048
+        );
+        assert!("RSA-1024".parse::<ClassicalAlgorithm>().is_err());
+    }
+
+    #[test]
+    fn any_algorithm_classification() {
+        let pqc: AnyAlgorithm = Algorithm::MlKem(MlKemVariant::MlKem768).into();
+        let classical: AnyAlgorithm = ClassicalAlgorithm::EcdsaP256.into();
+        assert!(pqc.is_pqc());
+        assert!(!pqc.is_quantum_vulnerable());
+        assert!(!classical.is_pqc());
+        assert!(classical.is_quantum_vulnerable());
+        assert_eq!(classical.to_string(), "ECDSA-P256");
+        assert!(pqc.is_approved_for_new_keys());
+        for alg in ClassicalAlgorithm::ALL {
+            assert!(!AnyAlgorithm::from(alg).is_approved_for_new_keys(), "{alg}");
+        }
+    }
+}
@@ -11,6 +11,8 @@ pub enum ErrorCode {
     AlreadyExists,
     PermissionDenied,
     Unauthenticated,
+    /// A backing service could not be reached; safe to retry.
+    ServiceUnavailable,
 
     // Crypto
     UnsupportedAlgorithm,
@@ -22,6 +24,9 @@ pub enum ErrorCode {
     InvalidKeyMaterial,
     KeyExpired,
     KeyRevoked,
+    /// The key has reached a usage limit, such as its nonce space, and must
+    /// be replaced.
+    KeyExhausted,
 
     // TLS
     TlsHandshakeFailed,
@@ -48,6 +53,7 @@ impl ErrorCode {
             ErrorCode::AlreadyExists => "ALREADY_EXISTS",
             ErrorCode::PermissionDenied => "PERMISSION_DENIED",
             ErrorCode::Unauthenticated => "UNAUTHENTICATED",
+            ErrorCode::ServiceUnavailable => "SERVICE_UNAVAILABLE",
             ErrorCode::UnsupportedAlgorithm => "UNSUPPORTED_ALGORITHM",
             ErrorCode::KeyGenerationFailed => "KEY_GENERATION_FAILED",
             ErrorCode::EncapsulationFailed => "ENCAPSULATION_FAILED",
@@ -57,6 +63,7 @@ impl ErrorCode {
             ErrorCode::InvalidKeyMaterial => "INVALID_KEY_MATERIAL",
             ErrorCode::KeyExpired => "KEY_EXPIRED",
             ErrorCode::KeyRevoked => "KEY_REVOKED",
+            ErrorCode::KeyExhausted => "KEY_EXHAUSTED",
             ErrorCode::TlsHandshakeFailed => "TLS_HANDSHAKE_FAILED",
             ErrorCode::CertificateInvalid => "CERTIFICATE_INVALID",
             ErrorCode::CertificateExpired => "CERTIFICATE_EXPIRED",
@@ -69,6 +76,83 @@ impl ErrorCode {
     }
 }
 
+impl ErrorCode {
+    /// Numeric gRPC status code (per `google.rpc.Code`) for this error.
+    ///
+    /// Kept transport-agnostic so this crate does not depend on tonic; see
+    /// [`ErrorCode::to_tonic_code`] behind the `tonic` feature.
+    pub fn grpc_code(&self) -> u32 {
+        match self {
+            ErrorCode::Internal => 13,
+            ErrorCode::InvalidArgument => 3,
+            ErrorCode::NotFound => 5,
+            ErrorCode::AlreadyExists => 6,
+            ErrorCode::PermissionDenied => 7,
+            ErrorCode::Unauthenticated => 16,
+            ErrorCode::ServiceUnavailable => 14,
+            ErrorCode::UnsupportedAlgorithm => 12,
+            ErrorCode::KeyGenerationFailed => 13,
+            ErrorCode::EncapsulationFailed => 13,
+            ErrorCode::DecapsulationFailed => 13,
+            ErrorCode::SigningFailed => 13,
+            ErrorCode::VerificationFailed => 16,
+            ErrorCode::InvalidKeyMaterial => 3,
+            ErrorCode::KeyExpired => 16,
+            ErrorCode::KeyRevoked => 16,
+            ErrorCode::KeyExhausted => 8,
+            ErrorCode::TlsHandshakeFailed => 14,
+            ErrorCode::CertificateInvalid => 16,
+            ErrorCode::CertificateExpired => 16,
+            ErrorCode::DeviceNotProvisioned => 9,
+            ErrorCode::DeviceOffline => 14,
+            ErrorCode::FirmwareIncompatible => 9,
+            ErrorCode::AssessmentFailed => 13,
+            ErrorCode::ScanTimeout => 4,
+        }
+    }
+
+    /// Map a numeric gRPC status code back to the generic error code.
+    ///
+    /// Lossy: domain-specific codes cannot be recovered, and gRPC codes
+    /// without a generic equivalent (including `OK`) become `Internal`.
+    pub fn from_grpc_code(code: u32) -> ErrorCode {
+        match code {
+            3 => ErrorCode::InvalidArgument,
+            5 => ErrorCode::NotFound,
+            6 => ErrorCode::AlreadyExists,
+            7 => ErrorCode::PermissionDenied,
+            12 => ErrorCode::UnsupportedAlgorithm,
+            14 => ErrorCode::ServiceUnavailable,
+            16 => ErrorCode::Unauthenticated,
+            _ => ErrorCode::Internal,
+        }
+    }
+
+    /// The tonic status code for this error.
+    ///
+    /// For example, rejecting calls from a tonic interceptor:
+    ///
+    /// ```
+    /// use quantun_types::ErrorCode;
+    /// use tonic::{Request, Status};
+    ///
+    /// fn require_token(req: Request<()>) -> Result<Request<()>, Status> {
+    ///     if req.metadata().get("authorization").is_none() {
+    ///         let code = ErrorCode::Unauthenticated;
+    ///         return Err(Status::new(code.to_tonic_code(), code.as_str()));
+    ///     }
+    ///     Ok(req)
+    /// }
+    ///
+    /// let err = require_token(Request::new(())).unwrap_err();
+    /// assert_eq!(err.code(), tonic::Code::Unauthenticated);
+    /// ```
+    #[cfg(feature = "tonic")]
+    pub fn to_tonic_code(&self) -> tonic::Code {
+        tonic::Code::from_i32(self.grpc_code() as i32)
+    }
+}
+
 impl fmt::Display for ErrorCode {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(f, "{}", self.as_str())
@@ -92,4 +176,59 @@ mod tests {
     fn error_code_as_str() {
         assert_eq!(ErrorCode::KeyExpired.as_str(), "KEY_EXPIRED");
     }
+
+    #[test]
+    fn grpc_code_table() {
+        let table = [
+            (ErrorCode::Internal, 13),
+            (ErrorCode::InvalidArgument, 3),
+            (ErrorCode::NotFound, 5),
+            (ErrorCode::AlreadyExists, 6),
+            (ErrorCode::PermissionDenied, 7),
+            (ErrorCode::Unauthenticated, 16),
+            (ErrorCode::ServiceUnavailable, 14),
+            (ErrorCode::UnsupportedAlgorithm, 12),
+            (ErrorCode::VerificationFailed, 16),
+            (ErrorCode::InvalidKeyMaterial, 3),
+            (ErrorCode::KeyExpired, 16),
+            (ErrorCode::KeyExhausted, 8),
+            (ErrorCode::TlsHandshakeFailed, 14),
+            (ErrorCode::DeviceNotProvisioned, 9),
+            (ErrorCode::ScanTimeout, 4),
+        ];
+        for (code, grpc) in table {
+            assert_eq!(code.grpc_code(), grpc, "{code}");
+        }
+    }
+
+    #[test]
+    fn from_grpc_code_round_trips_generic_codes() {
+        for code in [
+            ErrorCode::InvalidArgument,
+            ErrorCode::NotFound,
+            ErrorCode::AlreadyExists,
+            ErrorCode::PermissionDenied,
+            ErrorCode::Unauthenticated,
+            ErrorCode::ServiceUnavailable,
+            ErrorCode::UnsupportedAlgorithm,
+            ErrorCode::Internal,
+        ] {
+            assert_eq!(ErrorCode::from_grpc_code(code.grpc_code()), code);
+        }
+    }
+
+    #[test]
+    fn from_grpc_code_is_lossy() {
+        assert_eq!(
+            ErrorCode::from_grpc_code(ErrorCode::KeyExpired.grpc_code()),
+            ErrorCode::Unauthenticated
+        );
+        assert_eq!(ErrorCode::from_grpc_code(0), ErrorCode::Internal);
+        assert_eq!(
+            ErrorCode::from_grpc_code(ErrorCode::DeviceOffline.grpc_code()),
+            ErrorCode::ServiceUnavailable
+        );
+        assert_eq!(ErrorCode::from_grpc_code(8), ErrorCode::Internal);
+        assert_eq!(ErrorCode::from_grpc_code(99), ErrorCode::Internal);
+    }
 }
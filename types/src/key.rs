@@ -0,0 +1,337 @@
+use crate::algorithm::{Algorithm, KeyType, KeyUsage};
+use crate::error_code::ErrorCode;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fmt;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Lifecycle state of a key (after NIST SP 800-57 Part 1, section 7).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum KeyState {
+    PendingActivation,
+    Active,
+    Deactivated,
+    Compromised,
+    Destroyed,
+}
+
+impl KeyState {
+    /// Whether moving from this state to `next` is a legal transition.
+    ///
+    /// `Destroyed` is terminal, and neither `Deactivated` nor `Compromised`
+    /// keys can return to `Active`.
+    pub fn can_transition_to(&self, next: KeyState) -> bool {
+        use KeyState::*;
+        matches!(
+            (self, next),
+            (PendingActivation, Active)
+                | (PendingActivation, Compromised)
+                | (PendingActivation, Destroyed)
+                | (Active, Deactivated)
+                | (Active, Compromised)
+                | (Deactivated, Compromised)
+                | (Deactivated, Destroyed)
+                | (Compromised, Destroyed)
+        )
+    }
+
+    /// Whether the state is terminal.
+    pub fn is_terminal(&self) -> bool {
+        *self == KeyState::Destroyed
+    }
+}
+
+impl fmt::Display for KeyState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            KeyState::PendingActivation => write!(f, "pending_activation"),
+            KeyState::Active => write!(f, "active"),
+            KeyState::Deactivated => write!(f, "deactivated"),
+            KeyState::Compromised => write!(f, "compromised"),
+            KeyState::Destroyed => write!(f, "destroyed"),
+        }
+    }
+}
+
+/// Days past its recommended maximum age that a key may stay in use before
+/// rotation becomes mandatory.
+pub const ROTATION_GRACE_DAYS: i64 = 30;
+
+const SECONDS_PER_DAY: u64 = 86_400;
+
+/// Whether a key is due for rotation under
+/// [`Algorithm::recommended_max_key_age_days`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RotationStatus {
+    /// Within its recommended lifetime.
+    Current,
+    /// Past its recommended lifetime, but within [`ROTATION_GRACE_DAYS`].
+    RotationAdvisory { days_overdue: i64 },
+    /// Past the grace period: rotate before further use.
+    RotationRequired,
+}
+
+/// Metadata describing a managed key, independent of its key material.
+///
+/// Timestamps are seconds since the Unix epoch.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KeyMetadata {
+    pub key_id: String,
+    pub algorithm: Algorithm,
+    pub key_type: KeyType,
+    pub usages: HashSet<KeyUsage>,
+    pub state: KeyState,
+    pub created_at: u64,
+    pub activated_at: Option<u64>,
+    pub expires_at: Option<u64>,
+    pub revoked_at: Option<u64>,
+}
+
+impl KeyMetadata {
+    /// Create metadata for a new key in the `PendingActivation` state.
+    pub fn new(
+        key_id: impl Into<String>,
+        algorithm: Algorithm,
+        usages: impl IntoIterator<Item = KeyUsage>,
+        created_at: u64,
+    ) -> Self {
+        Self {
+            key_id: key_id.into(),
+            algorithm,
+            key_type: algorithm.key_type(),
+            usages: usages.into_iter().collect(),
+            state: KeyState::PendingActivation,
+            created_at,
+            activated_at: None,
+            expires_at: None,
+            revoked_at: None,
+        }
+    }
+
+    /// Set the expiry timestamp.
+    pub fn with_expiry(mut self, expires_at: u64) -> Self {
+        self.expires_at = Some(expires_at);
+        self
+    }
+
+    /// Move the key to `next`, recording the transition time.
+    ///
+    /// Returns `InvalidArgument` for an illegal transition.
+    pub fn transition(&mut self, next: KeyState, at: u64) -> Result<(), ErrorCode> {
+        if !self.state.can_transition_to(next) {
+            return Err(ErrorCode::InvalidArgument);
+        }
+
+        match next {
+            KeyState::Active => self.activated_at = Some(at),
+            KeyState::Deactivated | KeyState::Compromised => {
+                self.revoked_at.get_or_insert(at);
+            }
+            KeyState::PendingActivation | KeyState::Destroyed => {}
+        }
+        self.state = next;
+        Ok(())
+    }
+
+    /// Whether the key has expired as of `now`.
+    pub fn is_expired_at(&self, now: u64) -> bool {
+        self.expires_at.is_some_and(|exp| now >= exp)
+    }
+
+    /// Check whether the key may currently be used for `usage`.
+    pub fn can_perform(&self, usage: KeyUsage) -> Result<(), ErrorCode> {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+        self.can_perform_at(usage, now)
+    }
+
+    /// Check whether the key may be used for `usage` at time `now`.
+    ///
+    /// Revoked states take precedence over expiry, which takes precedence
+    /// over usage restrictions.
+    pub fn can_perform_at(&self, usage: KeyUsage, now: u64) -> Result<(), ErrorCode> {
+        match self.state {
+            KeyState::Active => {}
+            KeyState::Deactivated | KeyState::Compromised | KeyState::Destroyed => {
+                return Err(ErrorCode::KeyRevoked)
+            }
+            KeyState::PendingActivation => return Err(ErrorCode::PermissionDenied),
+        }
+
+        if self.is_expired_at(now) {
+            return Err(ErrorCode::KeyExpired);
+        }
+
+        if !self.usages.contains(&usage) {
+            return Err(ErrorCode::PermissionDenied);
+        }
+
+        Ok(())
+    }
+
+    /// Whether the key is due for rotation, measured from activation (or
+    /// creation, if never activated).
+    pub fn check_rotation_due(&self) -> RotationStatus {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+        self.check_rotation_due_at(now)
+    }
+
+    /// Whether the key is due for rotation at time `now`.
+    pub fn check_rotation_due_at(&self, now: u64) -> RotationStatus {
+        let since = self.activated_at.unwrap_or(self.created_at);
+        let age_days = (now.saturating_sub(since) / SECONDS_PER_DAY) as i64;
+        let days_overdue = age_days - i64::from(self.algorithm.recommended_max_key_age_days());
+        match days_overdue {
+            d if d <= 0 => RotationStatus::Current,
+            d if d <= ROTATION_GRACE_DAYS => RotationStatus::RotationAdvisory { days_overdue: d },
+            _ => RotationStatus::RotationRequired,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::algorithm::MlDsaVariant;
+
+    const ALL_STATES: [KeyState; 5] = [
+        KeyState::PendingActivation,
+        KeyState::Active,
+        KeyState::Deactivated,
+        KeyState::Compromised,
+        KeyState::Destroyed,
+    ];
+
+    fn signing_key() -> KeyMetadata {
+        KeyMetadata::new(
+            "key-1",
+            Algorithm::MlDsa(MlDsaVariant::MlDsa65),
+            [KeyUsage::Sign],
+            1_000,
+        )
+    }
+
+    #[test]
+    fn legal_transitions() {
+        let legal = [
+            (KeyState::PendingActivation, KeyState::Active),
+            (KeyState::PendingActivation, KeyState::Compromised),
+            (KeyState::PendingActivation, KeyState::Destroyed),
+            (KeyState::Active, KeyState::Deactivated),
+            (KeyState::Active, KeyState::Compromised),
+            (KeyState::Deactivated, KeyState::Compromised),
+            (KeyState::Deactivated, KeyState::Destroyed),
+            (KeyState::Compromised, KeyState::Destroyed),
+        ];
+
+        for from in ALL_STATES {
+            for to in ALL_STATES {
+                assert_eq!(
+                    from.can_transition_to(to),
+                    legal.contains(&(from, to)),
+                    "{from} -> {to}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn illegal_transitions() {
+        assert!(!KeyState::Compromised.can_transition_to(KeyState::Active));
+        assert!(!KeyState::Deactivated.can_transition_to(KeyState::Active));
+        assert!(!KeyState::Active.can_transition_to(KeyState::PendingActivation));
+        assert!(!KeyState::Active.can_transition_to(KeyState::Active));
+        for to in ALL_STATES {
+            assert!(!KeyState::Destroyed.can_transition_to(to));
+        }
+        assert!(KeyState::Destroyed.is_terminal());
+    }
+
+    #[test]
+    fn transition_records_timestamps() {
+        let mut key = signing_key();
+        assert_eq!(key.key_type, KeyType::Signature);
+
+        key.transition(KeyState::Active, 2_000).unwrap();
+        assert_eq!(key.activated_at, Some(2_000));
+
+        key.transition(KeyState::Deactivated, 3_000).unwrap();
+        key.transition(KeyState::Compromised, 4_000).unwrap();
+        assert_eq!(key.revoked_at, Some(3_000));
+
+        assert_eq!(
+            key.transition(KeyState::Active, 5_000),
+            Err(ErrorCode::InvalidArgument)
+        );
+        assert_eq!(key.state, KeyState::Compromised);
+    }
+
+    #[test]
+    fn can_perform_checks_state_expiry_and_usage() {
+        let mut key = signing_key().with_expiry(10_000);
+        assert_eq!(
+            key.can_perform_at(KeyUsage::Sign, 1_500),
+            Err(ErrorCode::PermissionDenied)
+        );
+
+        key.transition(KeyState::Active, 2_000).unwrap();
+        assert_eq!(key.can_perform_at(KeyUsage::Sign, 5_000), Ok(()));
+        assert_eq!(
+            key.can_perform_at(KeyUsage::Encrypt, 5_000),
+            Err(ErrorCode::PermissionDenied)
+        );
+        assert_eq!(
+            key.can_perform_at(KeyUsage::Sign, 10_000),
+            Err(ErrorCode::KeyExpired)
+        );
+
+        key.transition(KeyState::Compromised, 6_000).unwrap();
+        assert_eq!(
+            key.can_perform_at(KeyUsage::Sign, 7_000),
+            Err(ErrorCode::KeyRevoked)
+        );
+    }
+
+    #[test]
+    fn rotation_due_by_key_age() {
+        const DAY: u64 = SECONDS_PER_DAY;
+        let mut key = signing_key();
+        key.transition(KeyState::Active, 10 * DAY).unwrap();
+
+        // ML-DSA-65: 730 days, measured from activation.
+        assert_eq!(
+            key.check_rotation_due_at(740 * DAY),
+            RotationStatus::Current
+        );
+        assert_eq!(
+            key.check_rotation_due_at(745 * DAY),
+            RotationStatus::RotationAdvisory { days_overdue: 5 }
+        );
+        assert_eq!(
+            key.check_rotation_due_at(800 * DAY),
+            RotationStatus::RotationRequired
+        );
+
+        let old = KeyMetadata::new(
+            "key-old",
+            Algorithm::MlDsa(MlDsaVariant::MlDsa65),
+            [KeyUsage::Sign],
+            0,
+        );
+        assert_eq!(old.check_rotation_due(), RotationStatus::RotationRequired);
+    }
+
+    #[test]
+    fn serde_round_trip() {
+        let mut key = signing_key();
+        key.transition(KeyState::Active, 2_000).unwrap();
+        let json = serde_json::to_string(&key).unwrap();
+        let back: KeyMetadata = serde_json::from_str(&json).unwrap();
+        assert_eq!(back, key);
+    }
+}
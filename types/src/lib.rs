@@ -1,5 +1,11 @@
 pub mod algorithm;
+pub mod classical;
 pub mod error_code;
+pub mod key;
+pub mod validation;
 
 pub use algorithm::*;
+pub use classical::{AnyAlgorithm, ClassicalAlgorithm, ParseClassicalAlgorithmError};
 pub use error_code::ErrorCode;
+pub use key::{KeyMetadata, KeyState, RotationStatus};
+pub use validation::{InvalidLength, Material};
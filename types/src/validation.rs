@@ -0,0 +1,223 @@
+use crate::algorithm::{Algorithm, MlDsaVariant, MlKemVariant, SlhDsaVariant};
+use std::fmt;
+use thiserror::Error;
+
+/// Kind of raw cryptographic material whose length is being checked.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Material {
+    PublicKey,
+    SecretKey,
+    Seed,
+    Ciphertext,
+    Signature,
+}
+
+impl fmt::Display for Material {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Material::PublicKey => write!(f, "public key"),
+            Material::SecretKey => write!(f, "secret key"),
+            Material::Seed => write!(f, "seed"),
+            Material::Ciphertext => write!(f, "ciphertext"),
+            Material::Signature => write!(f, "signature"),
+        }
+    }
+}
+
+/// Raw material had the wrong length for its algorithm.
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+#[error("invalid {algorithm} {material}: expected {expected} bytes, got {actual}")]
+pub struct InvalidLength {
+    pub algorithm: Algorithm,
+    pub material: Material,
+    pub expected: usize,
+    pub actual: usize,
+}
+
+fn check(
+    algorithm: Algorithm,
+    material: Material,
+    expected: usize,
+    bytes: &[u8],
+) -> Result<(), InvalidLength> {
+    if bytes.len() == expected {
+        Ok(())
+    } else {
+        Err(InvalidLength {
+            algorithm,
+            material,
+            expected,
+            actual: bytes.len(),
+        })
+    }
+}
+
+impl MlKemVariant {
+    /// Size in bytes of the `d || z` seed from which a key pair is derived.
+    pub fn seed_size(&self) -> usize {
+        64
+    }
+
+    /// Check that `bytes` is an encapsulation key of the right size.
+    pub fn validate_public_key(&self, bytes: &[u8]) -> Result<(), InvalidLength> {
+        check(Algorithm::MlKem(*self), Material::PublicKey, self.key_sizes().0, bytes)
+    }
+
+    /// Check that `bytes` is a secret key as this workspace stores it: the
+    /// 64-byte `d || z` seed, not the expanded decapsulation key.
+    pub fn validate_secret_key(&self, bytes: &[u8]) -> Result<(), InvalidLength> {
+        check(Algorithm::MlKem(*self), Material::SecretKey, self.seed_size(), bytes)
+    }
+
+    /// Check that `bytes` is an expanded FIPS 203 decapsulation key of the
+    /// right size.
+    pub fn validate_expanded_secret_key(&self, bytes: &[u8]) -> Result<(), InvalidLength> {
+        check(Algorithm::MlKem(*self), Material::SecretKey, self.key_sizes().1, bytes)
+    }
+
+    /// Check that `bytes` is a key-generation seed of the right size.
+    pub fn validate_seed(&self, bytes: &[u8]) -> Result<(), InvalidLength> {
+        check(Algorithm::MlKem(*self), Material::Seed, self.seed_size(), bytes)
+    }
+
+    /// Check that `bytes` is a ciphertext of the right size.
+    pub fn validate_ciphertext(&self, bytes: &[u8]) -> Result<(), InvalidLength> {
+        check(Algorithm::MlKem(*self), Material::Ciphertext, self.ciphertext_size(), bytes)
+    }
+}
+
+impl MlDsaVariant {
+    /// Size in bytes of the seed from which a key pair is derived.
+    pub fn seed_size(&self) -> usize {
+        32
+    }
+
+    /// Check that `bytes` is a verifying key of the right size.
+    pub fn validate_public_key(&self, bytes: &[u8]) -> Result<(), InvalidLength> {
+        check(Algorithm::MlDsa(*self), Material::PublicKey, self.key_sizes().0, bytes)
+    }
+
+    /// Check that `bytes` is a secret key as this workspace stores it: the
+    /// 32-byte seed, not the expanded signing key.
+    pub fn validate_secret_key(&self, bytes: &[u8]) -> Result<(), InvalidLength> {
+        check(Algorithm::MlDsa(*self), Material::SecretKey, self.seed_size(), bytes)
+    }
+
+    /// Check that `bytes` is an expanded FIPS 204 signing key of the right
+    /// size.
+    pub fn validate_expanded_secret_key(&self, bytes: &[u8]) -> Result<(), InvalidLength> {
+        check(Algorithm::MlDsa(*self), Material::SecretKey, self.key_sizes().1, bytes)
+    }
+
+    /// Check that `bytes` is a key-generation seed of the right size.
+    pub fn validate_seed(&self, bytes: &[u8]) -> Result<(), InvalidLength> {
+        check(Algorithm::MlDsa(*self), Material::Seed, self.seed_size(), bytes)
+    }
+
+    /// Check that `bytes` is a signature of the right size.
+    pub fn validate_signature(&self, bytes: &[u8]) -> Result<(), InvalidLength> {
+        check(Algorithm::MlDsa(*self), Material::Signature, self.signature_size(), bytes)
+    }
+}
+
+impl SlhDsaVariant {
+    /// Check that `bytes` is a verifying key of the right size.
+    pub fn validate_public_key(&self, bytes: &[u8]) -> Result<(), InvalidLength> {
+        check(Algorithm::SlhDsa(*self), Material::PublicKey, self.key_sizes().0, bytes)
+    }
+
+    /// Check that `bytes` is a signing key of the right size.
+    pub fn validate_secret_key(&self, bytes: &[u8]) -> Result<(), InvalidLength> {
+        check(Algorithm::SlhDsa(*self), Material::SecretKey, self.key_sizes().1, bytes)
+    }
+
+    /// Check that `bytes` is a signature of the right size.
+    pub fn validate_signature(&self, bytes: &[u8]) -> Result<(), InvalidLength> {
+        check(Algorithm::SlhDsa(*self), Material::Signature, self.signature_size(), bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mlkem_public_key() {
+        let v = MlKemVariant::MlKem768;
+        assert!(v.validate_public_key(&[0; 1184]).is_ok());
+        let err = v.validate_public_key(&[0; 800]).unwrap_err();
+        assert_eq!(err.material, Material::PublicKey);
+        assert_eq!((err.expected, err.actual), (1184, 800));
+    }
+
+    #[test]
+    fn mlkem_secret_key() {
+        let v = MlKemVariant::MlKem512;
+        assert!(v.validate_secret_key(&[0; 64]).is_ok());
+        let err = v.validate_secret_key(&[0; 1632]).unwrap_err();
+        assert_eq!(err.material, Material::SecretKey);
+        assert_eq!((err.expected, err.actual), (64, 1632));
+
+        assert!(v.validate_expanded_secret_key(&[0; 1632]).is_ok());
+        let err = v.validate_expanded_secret_key(&[0; 2400]).unwrap_err();
+        assert_eq!((err.expected, err.actual), (1632, 2400));
+    }
+
+    #[test]
+    fn mlkem_seed() {
+        let v = MlKemVariant::MlKem768;
+        assert_eq!(v.seed_size(), 64);
+        assert!(v.validate_seed(&[0; 64]).is_ok());
+        assert_eq!(v.validate_seed(&[0; 2400]).unwrap_err().material, Material::Seed);
+    }
+
+    #[test]
+    fn mlkem_ciphertext() {
+        let v = MlKemVariant::MlKem1024;
+        assert!(v.validate_ciphertext(&[0; 1568]).is_ok());
+        let err = v.validate_ciphertext(&[]).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "invalid ML-KEM-1024 ciphertext: expected 1568 bytes, got 0"
+        );
+    }
+
+    #[test]
+    fn mldsa_public_key_and_seed() {
+        let v = MlDsaVariant::MlDsa65;
+        assert_eq!(v.seed_size(), 32);
+        assert!(v.validate_public_key(&[0; 1952]).is_ok());
+        assert!(v.validate_public_key(&[0; 1312]).is_err());
+        assert!(v.validate_seed(&[0; 32]).is_ok());
+        assert_eq!(v.validate_seed(&[0; 31]).unwrap_err().material, Material::Seed);
+    }
+
+    #[test]
+    fn mldsa_secret_key() {
+        let v = MlDsaVariant::MlDsa87;
+        assert!(v.validate_secret_key(&[0; 32]).is_ok());
+        assert_eq!(v.validate_secret_key(&[0; 4896]).unwrap_err().expected, 32);
+        assert!(v.validate_expanded_secret_key(&[0; 4896]).is_ok());
+        assert_eq!(v.validate_expanded_secret_key(&[0; 32]).unwrap_err().expected, 4896);
+    }
+
+    #[test]
+    fn mldsa_signature() {
+        let v = MlDsaVariant::MlDsa44;
+        assert!(v.validate_signature(&[0; 2420]).is_ok());
+        let err = v.validate_signature(&[0; 3309]).unwrap_err();
+        assert_eq!(err.algorithm, Algorithm::MlDsa(MlDsaVariant::MlDsa44));
+        assert_eq!((err.expected, err.actual), (2420, 3309));
+    }
+
+    #[test]
+    fn slhdsa_keys_and_signature() {
+        let v = SlhDsaVariant::Sha2_192f;
+        assert!(v.validate_public_key(&[0; 48]).is_ok());
+        assert!(v.validate_secret_key(&[0; 96]).is_ok());
+        assert!(v.validate_signature(&vec![0; 35664]).is_ok());
+        assert_eq!(v.validate_public_key(&[0; 32]).unwrap_err().expected, 48);
+        assert_eq!(v.validate_secret_key(&[0; 64]).unwrap_err().expected, 96);
+        assert_eq!(v.validate_signature(&[0; 16]).unwrap_err().actual, 16);
+    }
+}